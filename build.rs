@@ -0,0 +1,97 @@
+use naga::{back::spv, front::wgsl, valid::{Capabilities, ValidationFlags, Validator}};
+
+use std::{env, fs, path::Path};
+
+// the part of a directory of shaders every one of them shares
+const COMMON: &str = "common.wgsl";
+
+// compiles every wgsl file in shaders/ into a spir-v module of the same name in OUT_DIR
+fn main() {
+  let out_dir = match env::var("OUT_DIR") {
+    Ok(out_dir) => out_dir,
+    Err(error)  => panic!("failed to get build output directory with error: {error}")
+  };
+
+  println!("cargo:rerun-if-changed=shaders");
+
+  let entries = match fs::read_dir("shaders") {
+    Ok(entries) => entries,
+    Err(error)  => panic!("failed to read shader directory with error: {error}")
+  };
+
+  // shaders are written for vulkan directly so naga should not flip the clip space y axis for us
+  let options = spv::Options {
+    flags: spv::WriterFlags::LABEL_VARYINGS,
+    ..spv::Options::default()
+  };
+
+  for entry in entries.flatten() {
+    let path = entry.path();
+
+    // a directory holds shaders sharing its common.wgsl, which each of the other files is appended to. they are named
+    // after the directory and the file, like path_trace_bvh
+    if path.is_dir() {
+      let common = read_shader(&path.join(COMMON));
+      let variants = match fs::read_dir(&path) {
+        Ok(variants) => variants,
+        Err(error)   => panic!("failed to read shader directory {} with error: {error}", path.display())
+      };
+
+      for variant in variants.flatten().map(|variant| variant.path()) {
+        if !is_wgsl(&variant) || variant.file_name() == Some(COMMON.as_ref()) { continue; }
+
+        let source = format!("{common}\n{}", read_shader(&variant));
+        let name = format!("{}_{}", shader_name(&path), shader_name(&variant));
+        compile(&variant, &name, &source, &options, &out_dir);
+      }
+
+      continue;
+    }
+
+    if !is_wgsl(&path) { continue; }
+
+    let source = read_shader(&path);
+    compile(&path, shader_name(&path), &source, &options, &out_dir);
+  }
+}
+
+fn is_wgsl(path: &Path) -> bool {
+  path.extension().and_then(|extension| extension.to_str()) == Some("wgsl")
+}
+
+fn read_shader(path: &Path) -> String {
+  match fs::read_to_string(path) {
+    Ok(source) => source,
+    Err(error) => panic!("failed to read shader {} with error: {error}", path.display())
+  }
+}
+
+fn shader_name(path: &Path) -> &str {
+  match path.file_stem().and_then(|stem| stem.to_str()) {
+    Some(name) => name,
+    None       => panic!("failed to get shader name for {}", path.display())
+  }
+}
+
+fn compile(path: &Path, name: &str, source: &str, options: &spv::Options, out_dir: &str) {
+  let module = match wgsl::parse_str(source) {
+    Ok(module) => module,
+    Err(error) => panic!("failed to parse shader {}:\n{}", path.display(), error.emit_to_string(source))
+  };
+
+  let info = match Validator::new(ValidationFlags::all(), Capabilities::all()).validate(&module) {
+    Ok(info)   => info,
+    Err(error) => panic!("failed to validate shader {}:\n{}", path.display(), error.emit_to_string(source))
+  };
+
+  let words = match spv::write_vec(&module, &info, options, None) {
+    Ok(words)  => words,
+    Err(error) => panic!("failed to write spir-v for shader {} with error: {error}", path.display())
+  };
+
+  let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+  if let Err(error) = fs::write(Path::new(out_dir).join(format!("{name}.spv")), bytes) {
+    panic!("failed to write shader {name} with error: {error}")
+  }
+}
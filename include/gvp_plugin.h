@@ -0,0 +1,86 @@
+/* app plugins for groot vision pro. a plugin is a dynamic library exporting gvp_plugin_entry, which returns a
+ * descriptor that lives as long as the library. mirrors src/gvp_engine/plugin/abi.rs */
+
+#ifndef GVP_PLUGIN_H
+#define GVP_PLUGIN_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define GVP_PLUGIN_API_VERSION 1
+
+/* what a plugin may ask the host for. calls outside its capabilities do nothing */
+#define GVP_CAPABILITY_DRAW     (1u << 0)
+#define GVP_CAPABILITY_INPUT    (1u << 1)
+#define GVP_CAPABILITY_KEYBOARD (1u << 2)
+#define GVP_CAPABILITY_STATE    (1u << 3)
+#define GVP_CAPABILITY_SCENE    (1u << 4)
+
+#define GVP_PRIMITIVE_CUBE       0u
+#define GVP_PRIMITIVE_SPHERE     1u
+#define GVP_PRIMITIVE_CYLINDER   2u
+#define GVP_PRIMITIVE_CONE       3u
+#define GVP_PRIMITIVE_PLANE      4u
+#define GVP_PRIMITIVE_TORUS      5u
+#define GVP_PRIMITIVE_ICO_SPHERE 6u
+#define GVP_PRIMITIVE_CAPSULE    7u
+
+/* text and keys are empty without the keyboard capability or while the panel is not focused */
+typedef struct GvpInput {
+  bool has_pointer;
+  float pointer[2];
+  bool pressed;
+  bool held;
+  bool released;
+  bool back;
+  float scroll[2];
+  const int32_t *keys;
+  size_t key_count;
+  const uint8_t *text;
+  size_t text_length;
+} GvpInput;
+
+/* only valid during the callback it was given to. colors are straight alpha srgb, rects are x, y, width, height in
+ * pixels of the panel */
+typedef struct GvpHost {
+  uint32_t api_version;
+  void *context;
+  void (*panel_size)(void *context, float (*size)[2]);
+  bool (*focused)(void *context);
+  void (*clear)(void *context, const float (*color)[4]);
+  void (*draw_rect)(void *context, const float (*rect)[4], const float (*color)[4], float radius);
+  void (*draw_line)(void *context, const float (*from)[2], const float (*to)[2], float width, const float (*color)[4]);
+  void (*draw_text)(void *context, const float (*position)[2], const uint8_t *text, size_t length, float size, const float (*color)[4]);
+  /* utf-8 json, anything else is kept as a string */
+  void (*save_state)(void *context, const uint8_t *state, size_t length);
+  /* returns a handle for despawn, 0 if nothing was spawned */
+  uint64_t (*spawn_primitive)(void *context, uint32_t kind, const float (*position)[3], const float (*scale)[3], const float (*color)[4]);
+  void (*despawn)(void *context, uint64_t handle);
+  void (*close)(void *context);
+} GvpHost;
+
+typedef void (*GvpAppCallback)(void *app, const GvpHost *host);
+
+/* create and destroy are required, other callbacks may be null. drawing only works during render */
+typedef struct GvpPluginDescriptor {
+  uint32_t api_version;
+  const char *name;
+  const char *version;
+  uint32_t capabilities;
+  void *(*create)(void);
+  void (*destroy)(void *app);
+  GvpAppCallback on_open;
+  void (*on_input)(void *app, const GvpHost *host, const GvpInput *input);
+  void (*update)(void *app, const GvpHost *host, float delta);
+  GvpAppCallback render;
+  GvpAppCallback on_suspend;
+  GvpAppCallback on_resume;
+  GvpAppCallback on_close;
+  GvpAppCallback save_state;
+  void (*restore_state)(void *app, const uint8_t *state, size_t length);
+} GvpPluginDescriptor;
+
+const GvpPluginDescriptor *gvp_plugin_entry(void);
+
+#endif
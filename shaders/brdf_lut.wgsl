@@ -0,0 +1,59 @@
+const PI: f32 = 3.14159265359;
+const SAMPLE_COUNT: u32 = 1024u;
+
+// x is n dot v and y is roughness. red holds the scale and green the bias applied to f0
+@group(0) @binding(2) var output: texture_storage_2d<rgba16float, write>;
+
+fn hammersley(index: u32, count: u32) -> vec2<f32> {
+  return vec2<f32>(f32(index) / f32(count), f32(reverseBits(index)) * 2.3283064365386963e-10);
+}
+
+fn importance_sample_ggx(xi: vec2<f32>, roughness: f32) -> vec3<f32> {
+  let a = roughness * roughness;
+
+  let phi = 2.0 * PI * xi.x;
+  let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+  let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+
+  return vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+}
+
+fn geometry_schlick_ggx(n_dot: f32, roughness: f32) -> f32 {
+  let k = roughness * roughness / 2.0;
+  return n_dot / (n_dot * (1.0 - k) + k);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn compute_main(@builtin(global_invocation_id) id: vec3<u32>) {
+  let size = textureDimensions(output);
+  if id.x >= size.x || id.y >= size.y { return; }
+
+  let n_dot_v = (f32(id.x) + 0.5) / f32(size.x);
+  let roughness = (f32(id.y) + 0.5) / f32(size.y);
+
+  // the normal is +z so the view vector only needs the angle to it
+  let view = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+
+  var scale = 0.0;
+  var bias = 0.0;
+
+  for (var index = 0u; index < SAMPLE_COUNT; index += 1u) {
+    let halfway = importance_sample_ggx(hammersley(index, SAMPLE_COUNT), roughness);
+    let light = normalize(2.0 * dot(view, halfway) * halfway - view);
+
+    let n_dot_l = max(light.z, 0.0);
+    if n_dot_l <= 0.0 { continue; }
+
+    let n_dot_h = max(halfway.z, 0.0);
+    let v_dot_h = max(dot(view, halfway), 0.0);
+
+    let geometry = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+    let visibility = geometry * v_dot_h / (n_dot_h * n_dot_v);
+    let fresnel = pow(1.0 - v_dot_h, 5.0);
+
+    scale += (1.0 - fresnel) * visibility;
+    bias += fresnel * visibility;
+  }
+
+  textureStore(output, id.xy, vec4<f32>(scale, bias, 0.0, 1.0) / vec4<f32>(f32(SAMPLE_COUNT), f32(SAMPLE_COUNT), 1.0, 1.0));
+}
@@ -0,0 +1,74 @@
+struct Push {
+  // pixels of the target being drawn into
+  target_size: vec2<f32>
+}
+
+struct QuadInput {
+  @location(0) center: vec2<f32>,
+  @location(1) half_size: vec2<f32>,
+  @location(2) color: vec4<f32>,
+  @location(3) border_color: vec4<f32>,
+  // corner radius, border width, rotation, unused
+  @location(4) shape: vec4<f32>
+}
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  // pixels from the center of the quad, before rotating
+  @location(0) local: vec2<f32>,
+  @location(1) half_size: vec2<f32>,
+  @location(2) color: vec4<f32>,
+  @location(3) border_color: vec4<f32>,
+  @location(4) shape: vec4<f32>
+}
+
+var<push_constant> push: Push;
+
+@vertex
+fn vertex_main(@builtin(vertex_index) vertex_index: u32, quad: QuadInput) -> VertexOutput {
+  var corners = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0)
+  );
+
+  // a pixel bigger on every side so the antialiased edge is not cut off
+  let local = corners[vertex_index] * (quad.half_size + vec2<f32>(1.0));
+  let rotation = vec2<f32>(cos(quad.shape.z), sin(quad.shape.z));
+  let pixel = quad.center + vec2<f32>(local.x * rotation.x - local.y * rotation.y, local.x * rotation.y + local.y * rotation.x);
+
+  var output: VertexOutput;
+  output.clip_position = vec4<f32>(pixel / push.target_size * 2.0 - 1.0, 0.0, 1.0);
+  output.local = local;
+  output.half_size = quad.half_size;
+  output.color = quad.color;
+  output.border_color = quad.border_color;
+  output.shape = quad.shape;
+
+  return output;
+}
+
+// signed distance to the edge of a rounded rectangle, negative inside
+fn rounded_rect(point: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+  let corner = abs(point) - half_size + radius;
+  return length(max(corner, vec2<f32>(0.0))) + min(max(corner.x, corner.y), 0.0) - radius;
+}
+
+@fragment
+fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
+  let radius = min(input.shape.x, min(input.half_size.x, input.half_size.y));
+  let distance = rounded_rect(input.local, input.half_size, radius);
+
+  let coverage = clamp(0.5 - distance, 0.0, 1.0);
+  let fill = clamp(0.5 - (distance + input.shape.y), 0.0, 1.0);
+
+  var color = input.color;
+  if (input.shape.y > 0.0) {
+    color = mix(input.border_color, input.color, fill);
+  }
+
+  return vec4<f32>(color.rgb, color.a * coverage);
+}
@@ -0,0 +1,34 @@
+const PI: f32 = 3.14159265359;
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+@group(0) @binding(2) var output: texture_storage_2d_array<rgba16float, write>;
+
+// direction through the center of a texel on a face. faces are in the +x, -x, +y, -y, +z, -z order vulkan uses
+fn cube_direction(face: u32, texel: vec2<u32>, size: u32) -> vec3<f32> {
+  let uv = (vec2<f32>(texel) + 0.5) / f32(size) * 2.0 - 1.0;
+
+  switch face {
+    case 0u: { return normalize(vec3<f32>(1.0, -uv.y, -uv.x)); }
+    case 1u: { return normalize(vec3<f32>(-1.0, -uv.y, uv.x)); }
+    case 2u: { return normalize(vec3<f32>(uv.x, 1.0, uv.y)); }
+    case 3u: { return normalize(vec3<f32>(uv.x, -1.0, -uv.y)); }
+    case 4u: { return normalize(vec3<f32>(uv.x, -uv.y, 1.0)); }
+    default: { return normalize(vec3<f32>(-uv.x, -uv.y, -1.0)); }
+  }
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn compute_main(@builtin(global_invocation_id) id: vec3<u32>) {
+  let size = textureDimensions(output).x;
+  if id.x >= size || id.y >= size { return; }
+
+  let direction = cube_direction(id.z, id.xy, size);
+  let uv = vec2<f32>(atan2(direction.z, direction.x) / (2.0 * PI) + 0.5, acos(clamp(direction.y, -1.0, 1.0)) / PI);
+
+  // a face covers a quarter of the panorama's width, so larger panoramas are read from a smaller mip to avoid aliasing
+  let lod = max(log2(f32(textureDimensions(source).x) / (4.0 * f32(size))), 0.0);
+  let color = textureSampleLevel(source, source_sampler, uv, lod);
+
+  textureStore(output, id.xy, id.z, vec4<f32>(color.rgb, 1.0));
+}
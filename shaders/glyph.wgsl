@@ -0,0 +1,62 @@
+struct Push {
+  // pixels of the target being drawn into
+  target_size: vec2<f32>
+}
+
+struct GlyphInput {
+  // top left
+  @location(0) position: vec2<f32>,
+  @location(1) size: vec2<f32>,
+  @location(2) uv_min: vec2<f32>,
+  @location(3) uv_max: vec2<f32>,
+  @location(4) color: vec4<f32>,
+  // outline offset for bold, slant for italic, the baseline the slant leans from, unused
+  @location(5) shape: vec4<f32>
+}
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+  @location(1) color: vec4<f32>,
+  @location(2) weight: f32
+}
+
+var<push_constant> push: Push;
+
+@group(0) @binding(0) var atlas: texture_2d<f32>;
+@group(0) @binding(1) var atlas_sampler: sampler;
+
+@vertex
+fn vertex_main(@builtin(vertex_index) vertex_index: u32, glyph: GlyphInput) -> VertexOutput {
+  var corners = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 1.0)
+  );
+
+  let corner = corners[vertex_index];
+  var pixel = glyph.position + corner * glyph.size;
+  pixel.x += (glyph.shape.z - pixel.y) * glyph.shape.y;
+
+  var output: VertexOutput;
+  output.clip_position = vec4<f32>(pixel / push.target_size * 2.0 - 1.0, 0.0, 1.0);
+  output.uv = mix(glyph.uv_min, glyph.uv_max, corner);
+  output.color = glyph.color;
+  output.weight = glyph.shape.x;
+
+  return output;
+}
+
+// the atlas holds distance fields with the outline at 0.5. how quickly they change across a pixel on screen decides
+// how wide the antialiased edge is, so text stays crisp at any size. bold moves the edge outwards
+@fragment
+fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
+  let distance = textureSample(atlas, atlas_sampler, input.uv).r + input.weight;
+  let width = max(fwidth(distance), 0.0001);
+  let coverage = clamp((distance - 0.5) / width + 0.5, 0.0, 1.0);
+
+  return vec4<f32>(input.color.rgb, input.color.a * coverage);
+}
@@ -0,0 +1,52 @@
+const PI: f32 = 3.14159265359;
+const SAMPLE_DELTA: f32 = 0.05;
+
+@group(0) @binding(0) var source: texture_cube<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+@group(0) @binding(2) var output: texture_storage_2d_array<rgba16float, write>;
+
+fn cube_direction(face: u32, texel: vec2<u32>, size: u32) -> vec3<f32> {
+  let uv = (vec2<f32>(texel) + 0.5) / f32(size) * 2.0 - 1.0;
+
+  switch face {
+    case 0u: { return normalize(vec3<f32>(1.0, -uv.y, -uv.x)); }
+    case 1u: { return normalize(vec3<f32>(-1.0, -uv.y, uv.x)); }
+    case 2u: { return normalize(vec3<f32>(uv.x, 1.0, uv.y)); }
+    case 3u: { return normalize(vec3<f32>(uv.x, -1.0, -uv.y)); }
+    case 4u: { return normalize(vec3<f32>(uv.x, -uv.y, 1.0)); }
+    default: { return normalize(vec3<f32>(-uv.x, -uv.y, -1.0)); }
+  }
+}
+
+// cosine weighted integral of the environment over the hemisphere around each normal
+@compute @workgroup_size(8, 8, 1)
+fn compute_main(@builtin(global_invocation_id) id: vec3<u32>) {
+  let size = textureDimensions(output).x;
+  if id.x >= size || id.y >= size { return; }
+
+  let normal = cube_direction(id.z, id.xy, size);
+
+  var up = vec3<f32>(0.0, 1.0, 0.0);
+  if abs(normal.y) > 0.999 { up = vec3<f32>(0.0, 0.0, 1.0); }
+
+  let right = normalize(cross(up, normal));
+  up = cross(normal, right);
+
+  // the integral is smooth so a low resolution mip is plenty and keeps the sampling noise down
+  let lod = max(log2(f32(textureDimensions(source).x) / 32.0), 0.0);
+
+  var irradiance = vec3<f32>(0.0);
+  var count = 0.0;
+
+  for (var phi = 0.0; phi < 2.0 * PI; phi += SAMPLE_DELTA) {
+    for (var theta = 0.0; theta < 0.5 * PI; theta += SAMPLE_DELTA) {
+      let local = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+      let direction = local.x * right + local.y * up + local.z * normal;
+
+      irradiance += textureSampleLevel(source, source_sampler, direction, lod).rgb * cos(theta) * sin(theta);
+      count += 1.0;
+    }
+  }
+
+  textureStore(output, id.xy, id.z, vec4<f32>(PI * irradiance / count, 1.0));
+}
@@ -0,0 +1,187 @@
+const PI: f32 = 3.14159265359;
+
+struct View {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  // inverse of the projection and the view without its translation
+  sky: mat4x4<f32>,
+  camera_position: vec4<f32>
+}
+
+struct Frame {
+  // one per layer of a multiview target, single layer targets only use the first
+  views: array<View, 2>,
+  // xyz is the direction the light travels, w is its intensity
+  light: vec4<f32>,
+  light_color: vec4<f32>,
+  // intensity of the image based lighting, highest mip of the prefiltered map, how far the current environment has
+  // faded in over the previous one, unused
+  environment: vec4<f32>
+}
+
+struct Material {
+  base_color: vec4<f32>,
+  // emissive factor already scaled by its strength
+  emissive: vec4<f32>,
+  // metallic, roughness, normal scale, occlusion strength
+  surface: vec4<f32>,
+  // alpha cutoff, 1 when alpha is masked, unused, unused
+  alpha: vec4<f32>
+}
+
+struct Push {
+  model: mat4x4<f32>
+}
+
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+  @location(1) normal: vec3<f32>,
+  @location(2) tangent: vec4<f32>,
+  @location(3) uv: vec2<f32>
+}
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) world_position: vec3<f32>,
+  @location(1) normal: vec3<f32>,
+  @location(2) tangent: vec4<f32>,
+  @location(3) uv: vec2<f32>
+}
+
+@group(0) @binding(0) var<uniform> frame: Frame;
+
+@group(1) @binding(0) var<uniform> material: Material;
+@group(1) @binding(1) var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2) var metallic_roughness_texture: texture_2d<f32>;
+@group(1) @binding(3) var normal_texture: texture_2d<f32>;
+@group(1) @binding(4) var occlusion_texture: texture_2d<f32>;
+@group(1) @binding(5) var emissive_texture: texture_2d<f32>;
+@group(1) @binding(6) var base_color_sampler: sampler;
+@group(1) @binding(7) var metallic_roughness_sampler: sampler;
+@group(1) @binding(8) var normal_sampler: sampler;
+@group(1) @binding(9) var occlusion_sampler: sampler;
+@group(1) @binding(10) var emissive_sampler: sampler;
+
+@group(2) @binding(0) var irradiance_map: texture_cube<f32>;
+@group(2) @binding(1) var prefiltered_map: texture_cube<f32>;
+@group(2) @binding(2) var brdf_lut: texture_2d<f32>;
+@group(2) @binding(3) var environment_sampler: sampler;
+@group(2) @binding(5) var previous_irradiance_map: texture_cube<f32>;
+@group(2) @binding(6) var previous_prefiltered_map: texture_cube<f32>;
+
+var<push_constant> push: Push;
+
+@vertex
+fn vertex_main(input: VertexInput, @builtin(view_index) view_index: i32) -> VertexOutput {
+  let world_position = push.model * vec4<f32>(input.position, 1.0);
+  let view = frame.views[view_index];
+
+  var output: VertexOutput;
+  output.clip_position = view.projection * view.view * world_position;
+  output.world_position = world_position.xyz;
+  output.normal = (push.model * vec4<f32>(input.normal, 0.0)).xyz;
+  output.tangent = vec4<f32>((push.model * vec4<f32>(input.tangent.xyz, 0.0)).xyz, input.tangent.w);
+  output.uv = input.uv;
+
+  return output;
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+  let a2 = roughness * roughness * roughness * roughness;
+  let denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+
+  return a2 / (PI * denominator * denominator);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+  let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+  return (n_dot_v / (n_dot_v * (1.0 - k) + k)) * (n_dot_l / (n_dot_l * (1.0 - k) + k));
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+  return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// rough surfaces reflect less of the environment at grazing angles
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+  return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// both environments are always sampled and mixed, while nothing is fading the previous maps are the current ones
+fn sample_irradiance(direction: vec3<f32>) -> vec3<f32> {
+  let current = textureSampleLevel(irradiance_map, environment_sampler, direction, 0.0).rgb;
+  let previous = textureSampleLevel(previous_irradiance_map, environment_sampler, direction, 0.0).rgb;
+
+  return mix(previous, current, frame.environment.z);
+}
+
+fn sample_prefiltered(direction: vec3<f32>, lod: f32) -> vec3<f32> {
+  let current = textureSampleLevel(prefiltered_map, environment_sampler, direction, lod).rgb;
+  let previous = textureSampleLevel(previous_prefiltered_map, environment_sampler, direction, lod).rgb;
+
+  return mix(previous, current, frame.environment.z);
+}
+
+// narkowicz's fit of the aces filmic curve
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+  return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fragment_main(
+  input: VertexOutput,
+  @builtin(front_facing) front_facing: bool,
+  @builtin(view_index) view_index: i32
+) -> @location(0) vec4<f32> {
+  // every map is sampled up front since implicit derivatives are undefined after a discard
+  let base_color = material.base_color * textureSample(base_color_texture, base_color_sampler, input.uv);
+  let metallic_roughness = textureSample(metallic_roughness_texture, metallic_roughness_sampler, input.uv);
+  let tangent_normal = textureSample(normal_texture, normal_sampler, input.uv).xyz * 2.0 - 1.0;
+  let occlusion_sample = textureSample(occlusion_texture, occlusion_sampler, input.uv).r;
+  let emissive_sample = textureSample(emissive_texture, emissive_sampler, input.uv).rgb;
+
+  if material.alpha.y > 0.5 && base_color.a < material.alpha.x { discard; }
+
+  let metallic = clamp(material.surface.x * metallic_roughness.b, 0.0, 1.0);
+  let roughness = clamp(material.surface.y * metallic_roughness.g, 0.04, 1.0);
+  let occlusion = mix(1.0, occlusion_sample, material.surface.w);
+  let emissive = material.emissive.rgb * emissive_sample;
+
+  let geometric_normal = normalize(input.normal);
+  let tangent = normalize(input.tangent.xyz - geometric_normal * dot(geometric_normal, input.tangent.xyz));
+  let bitangent = cross(geometric_normal, tangent) * input.tangent.w;
+  let scaled = tangent_normal * vec3<f32>(material.surface.z, material.surface.z, 1.0);
+
+  var normal = normalize(tangent * scaled.x + bitangent * scaled.y + geometric_normal * scaled.z);
+  if !front_facing { normal = -normal; }
+
+  let to_camera = normalize(frame.views[view_index].camera_position.xyz - input.world_position);
+  let n_dot_v = max(dot(normal, to_camera), 0.0001);
+  let f0 = mix(vec3<f32>(0.04), base_color.rgb, metallic);
+
+  // direct light from the sun
+  let to_light = -normalize(frame.light.xyz);
+  let halfway = normalize(to_light + to_camera);
+  let n_dot_l = max(dot(normal, to_light), 0.0);
+
+  let fresnel = fresnel_schlick(max(dot(halfway, to_camera), 0.0), f0);
+  let specular = distribution_ggx(max(dot(normal, halfway), 0.0), roughness) * geometry_smith(n_dot_v, n_dot_l, roughness)
+    * fresnel / (4.0 * n_dot_v * n_dot_l + 0.0001);
+  let diffuse = (1.0 - fresnel) * (1.0 - metallic) * base_color.rgb / PI;
+
+  var color = (diffuse + specular) * frame.light_color.rgb * frame.light.w * n_dot_l;
+
+  // image based lighting with the split sum approximation
+  let ambient_fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+  let irradiance = sample_irradiance(normal);
+  let reflection = reflect(-to_camera, normal);
+  let prefiltered = sample_prefiltered(reflection, roughness * frame.environment.y);
+  let brdf = textureSampleLevel(brdf_lut, environment_sampler, vec2<f32>(n_dot_v, roughness), 0.0).rg;
+
+  let ambient_diffuse = (1.0 - ambient_fresnel) * (1.0 - metallic) * irradiance * base_color.rgb;
+  let ambient_specular = prefiltered * (ambient_fresnel * brdf.x + brdf.y);
+
+  color += (ambient_diffuse + ambient_specular) * occlusion * frame.environment.x + emissive;
+
+  return vec4<f32>(tonemap(color), base_color.a);
+}
@@ -0,0 +1,58 @@
+struct View {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  // inverse of the projection and the view without its translation
+  sky: mat4x4<f32>,
+  camera_position: vec4<f32>
+}
+
+struct Frame {
+  // one per layer of a multiview target, single layer targets only use the first
+  views: array<View, 2>,
+  light: vec4<f32>,
+  light_color: vec4<f32>,
+  environment: vec4<f32>
+}
+
+struct Push {
+  model: mat4x4<f32>,
+  // opacity, unused, unused, unused
+  panel: vec4<f32>
+}
+
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+  @location(1) normal: vec3<f32>,
+  @location(2) tangent: vec4<f32>,
+  @location(3) uv: vec2<f32>
+}
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>
+}
+
+@group(0) @binding(0) var<uniform> frame: Frame;
+
+@group(1) @binding(0) var panel_texture: texture_2d<f32>;
+@group(1) @binding(1) var panel_sampler: sampler;
+
+var<push_constant> push: Push;
+
+@vertex
+fn vertex_main(input: VertexInput, @builtin(view_index) view_index: i32) -> VertexOutput {
+  let view = frame.views[view_index];
+
+  var output: VertexOutput;
+  output.clip_position = view.projection * view.view * push.model * vec4<f32>(input.position, 1.0);
+  output.uv = input.uv;
+
+  return output;
+}
+
+// app content is shown as it was drawn, without lighting, faded by the panel's opacity
+@fragment
+fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
+  let color = textureSample(panel_texture, panel_sampler, input.uv);
+  return vec4<f32>(color.rgb, color.a * push.panel.x);
+}
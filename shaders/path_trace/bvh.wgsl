@@ -0,0 +1,124 @@
+// the software fallback, tracing through the hierarchies built on the cpu. appended to common.wgsl
+
+const STACK_SIZE: u32 = 48u;
+
+// leaves hold count items from first, inner nodes have their children at first and first + 1
+struct Node {
+  min_corner: vec3<f32>,
+  first: u32,
+  max_corner: vec3<f32>,
+  count: u32
+}
+
+// every mesh's hierarchy
+@group(0) @binding(1) var<storage, read> nodes: array<Node>;
+// the hierarchy over the instances
+@group(0) @binding(3) var<storage, read> scene_nodes: array<Node>;
+
+fn hits_box(node: Node, origin: vec3<f32>, inverse_direction: vec3<f32>, max_distance: f32) -> bool {
+  let first = (node.min_corner - origin) * inverse_direction;
+  let second = (node.max_corner - origin) * inverse_direction;
+  let near = min(first, second);
+  let far = max(first, second);
+
+  let enter = max(max(near.x, near.y), near.z);
+  let exit = min(min(far.x, far.y), far.z);
+
+  return exit >= max(enter, 0.0) && enter < max_distance;
+}
+
+// distance and barycentric coordinates, the distance is negative on a miss. both sides are hit
+fn hits_triangle(origin: vec3<f32>, direction: vec3<f32>, triangle: Triangle) -> vec3<f32> {
+  let edge1 = triangle.edge1.xyz;
+  let edge2 = triangle.edge2.xyz;
+
+  let p = cross(direction, edge2);
+  let determinant = dot(edge1, p);
+  if determinant == 0.0 { return vec3<f32>(-1.0); }
+
+  let inverse = 1.0 / determinant;
+  let offset = origin - triangle.position.xyz;
+  let u = dot(offset, p) * inverse;
+  if u < 0.0 || u > 1.0 { return vec3<f32>(-1.0); }
+
+  let q = cross(offset, edge1);
+  let v = dot(direction, q) * inverse;
+  if v < 0.0 || u + v > 1.0 { return vec3<f32>(-1.0); }
+
+  return vec3<f32>(dot(edge2, q) * inverse, u, v);
+}
+
+// axes the ray runs along get a huge inverse instead of an infinite one
+fn inverse_of(direction: vec3<f32>) -> vec3<f32> {
+  let tiny = select(vec3<f32>(1e-12), vec3<f32>(-1e-12), direction < vec3<f32>(0.0));
+  return 1.0 / select(direction, tiny, abs(direction) < vec3<f32>(1e-12));
+}
+
+// the ray is in the mesh's own space, left unnormalized so distances match the world's
+fn intersect_mesh(origin: vec3<f32>, direction: vec3<f32>, root: u32, instance: u32, hit: ptr<function, Hit>) {
+  let inverse_direction = inverse_of(direction);
+
+  var stack: array<u32, STACK_SIZE>;
+  var size = 1u;
+  stack[0] = root;
+
+  while size > 0u {
+    size -= 1u;
+    let node = nodes[stack[size]];
+    if !hits_box(node, origin, inverse_direction, (*hit).distance) { continue; }
+
+    if node.count > 0u {
+      for (var index = node.first; index < node.first + node.count; index++) {
+        let result = hits_triangle(origin, direction, triangles[index]);
+
+        if result.x > 0.0 && result.x < (*hit).distance {
+          *hit = Hit(result.x, instance, index, result.yz);
+        }
+      }
+    }
+    else if size + 2u <= STACK_SIZE {
+      stack[size] = node.first;
+      stack[size + 1u] = node.first + 1u;
+      size += 2u;
+    }
+  }
+}
+
+// the nearest hit closer than the max distance, whose instance is NONE when there is none
+fn intersect(origin: vec3<f32>, direction: vec3<f32>, max_distance: f32) -> Hit {
+  var hit = Hit(max_distance, NONE, 0u, vec2<f32>(0.0));
+  if trace.settings.w == 0u { return hit; }
+
+  let inverse_direction = inverse_of(direction);
+
+  var stack: array<u32, STACK_SIZE>;
+  var size = 1u;
+  stack[0] = 0u;
+
+  while size > 0u {
+    size -= 1u;
+    let node = scene_nodes[stack[size]];
+    if !hits_box(node, origin, inverse_direction, hit.distance) { continue; }
+
+    if node.count > 0u {
+      for (var index = node.first; index < node.first + node.count; index++) {
+        let instance = instances[index];
+        let local_origin = (instance.world_to_object * vec4<f32>(origin, 1.0)).xyz;
+        let local_direction = (instance.world_to_object * vec4<f32>(direction, 0.0)).xyz;
+
+        intersect_mesh(local_origin, local_direction, instance.mesh.x, index, &hit);
+      }
+    }
+    else if size + 2u <= STACK_SIZE {
+      stack[size] = node.first;
+      stack[size + 1u] = node.first + 1u;
+      size += 2u;
+    }
+  }
+
+  return hit;
+}
+
+fn occluded(origin: vec3<f32>, direction: vec3<f32>) -> bool {
+  return intersect(origin, direction, 1e30).instance != NONE;
+}
@@ -0,0 +1,319 @@
+// shared by every path tracer, each file next to this one is appended to it and adds intersect and occluded
+
+const PI: f32 = 3.14159265359;
+const NONE: u32 = 0xffffffffu;
+// how far rays start off the surface they leave
+const EPSILON: f32 = 1e-4;
+// hits through blended surfaces do not count as bounces, this stops a pile of them from running forever
+const MAX_STEPS: u32 = 64u;
+// samples brighter than this are clamped, trading a little energy for far fewer fireflies
+const MAX_RADIANCE: f32 = 64.0;
+
+struct Trace {
+  inverse_view: mat4x4<f32>,
+  inverse_projection: mat4x4<f32>,
+  light: vec4<f32>,
+  light_color: vec4<f32>,
+  // intensity, how far the current environment has faded in over the previous one, unused, unused
+  environment: vec4<f32>,
+  // width, height, samples accumulated before this frame, a seed that changes every frame
+  frame: vec4<u32>,
+  // bounces, samples this frame, output shown (color, albedo, normal), instance count
+  settings: vec4<u32>
+}
+
+struct Triangle {
+  position: vec4<f32>,
+  edge1: vec4<f32>,
+  edge2: vec4<f32>,
+  normal0: vec4<f32>,
+  normal1: vec4<f32>,
+  normal2: vec4<f32>
+}
+
+struct Instance {
+  world_to_object: mat4x4<f32>,
+  base_color: vec4<f32>,
+  emissive: vec4<f32>,
+  // metallic, roughness, unused, unused
+  surface: vec4<f32>,
+  // root node of the mesh, 1 when the material blends, first triangle of the mesh, unused
+  mesh: vec4<u32>
+}
+
+struct Hit {
+  distance: f32,
+  instance: u32,
+  triangle: u32,
+  barycentric: vec2<f32>
+}
+
+struct Sample {
+  color: vec3<f32>,
+  albedo: vec3<f32>,
+  normal: vec3<f32>
+}
+
+@group(0) @binding(0) var<uniform> trace: Trace;
+// every mesh's triangles, in the order their hierarchies refer to them
+@group(0) @binding(2) var<storage, read> triangles: array<Triangle>;
+@group(0) @binding(4) var<storage, read> instances: array<Instance>;
+// averages of every sample since the last reset, one per pixel
+@group(0) @binding(5) var<storage, read_write> color_buffer: array<vec4<f32>>;
+@group(0) @binding(6) var<storage, read_write> albedo_buffer: array<vec4<f32>>;
+@group(0) @binding(7) var<storage, read_write> normal_buffer: array<vec4<f32>>;
+@group(0) @binding(8) var output: texture_storage_2d<rgba16float, write>;
+
+@group(1) @binding(3) var environment_sampler: sampler;
+@group(1) @binding(4) var environment_map: texture_cube<f32>;
+@group(1) @binding(7) var previous_environment_map: texture_cube<f32>;
+
+var<private> rng_state: u32;
+
+fn hash(value: u32) -> u32 {
+  let state = value * 747796405u + 2891336453u;
+  let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+  return (word >> 22u) ^ word;
+}
+
+// uniform in [0, 1)
+fn random() -> f32 {
+  rng_state = hash(rng_state);
+  return f32(rng_state >> 8u) / 16777216.0;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+  return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// an orthonormal basis with the normal as its z axis
+fn basis(normal: vec3<f32>) -> mat3x3<f32> {
+  var up = vec3<f32>(0.0, 1.0, 0.0);
+  if abs(normal.y) > 0.999 { up = vec3<f32>(1.0, 0.0, 0.0); }
+
+  let tangent = normalize(cross(up, normal));
+  return mat3x3<f32>(tangent, cross(normal, tangent), normal);
+}
+
+// both environments are always sampled and mixed, while nothing is fading the previous map is the current one
+fn environment(direction: vec3<f32>) -> vec3<f32> {
+  let current = textureSampleLevel(environment_map, environment_sampler, direction, 0.0).rgb;
+  let previous = textureSampleLevel(previous_environment_map, environment_sampler, direction, 0.0).rgb;
+
+  return mix(previous, current, trace.environment.y) * trace.environment.x;
+}
+
+// same model the meshes are shaded with when rasterized, with alpha being roughness squared
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+  let a2 = roughness * roughness * roughness * roughness;
+  let denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+
+  return a2 / (PI * denominator * denominator);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+  let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+  return (n_dot_v / (n_dot_v * (1.0 - k) + k)) * (n_dot_l / (n_dot_l * (1.0 - k) + k));
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+  return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// diffuse and specular reflection from the light direction towards the view
+fn brdf(normal: vec3<f32>, view: vec3<f32>, light: vec3<f32>, base_color: vec3<f32>, metallic: f32, roughness: f32) -> vec3<f32> {
+  let half_vector = normalize(view + light);
+  let n_dot_v = max(dot(normal, view), 1e-4);
+  let n_dot_l = max(dot(normal, light), 1e-4);
+  let n_dot_h = max(dot(normal, half_vector), 0.0);
+
+  let fresnel = fresnel_schlick(max(dot(half_vector, view), 0.0), mix(vec3<f32>(0.04), base_color, metallic));
+  let specular = distribution_ggx(n_dot_h, roughness) * geometry_smith(n_dot_v, n_dot_l, roughness) * fresnel / (4.0 * n_dot_v * n_dot_l);
+  let diffuse = (1.0 - fresnel) * (1.0 - metallic) * base_color / PI;
+
+  return diffuse + specular;
+}
+
+// of picking the light direction when sampling either lobe, weighted by how often each is picked
+fn direction_pdf(normal: vec3<f32>, view: vec3<f32>, light: vec3<f32>, roughness: f32, specular_chance: f32) -> f32 {
+  let half_vector = normalize(view + light);
+  let n_dot_h = max(dot(normal, half_vector), 0.0);
+  let v_dot_h = max(dot(view, half_vector), 1e-4);
+
+  let specular = distribution_ggx(n_dot_h, roughness) * n_dot_h / (4.0 * v_dot_h);
+  let diffuse = max(dot(normal, light), 0.0) / PI;
+
+  return mix(diffuse, specular, specular_chance);
+}
+
+// a half vector from the ggx distribution reflected about, or a cosine weighted direction
+fn sample_direction(normal: vec3<f32>, view: vec3<f32>, roughness: f32, specular_chance: f32) -> vec3<f32> {
+  let frame = basis(normal);
+  let u = random();
+  let v = random();
+  let phi = 2.0 * PI * random();
+
+  if u < specular_chance {
+    let a2 = roughness * roughness * roughness * roughness;
+    let cos_theta = sqrt((1.0 - v) / (1.0 + (a2 - 1.0) * v));
+    let sin_theta = sqrt(max(1.0 - cos_theta * cos_theta, 0.0));
+    let half_vector = frame * vec3<f32>(sin_theta * cos(phi), sin_theta * sin(phi), cos_theta);
+
+    return reflect(-view, half_vector);
+  }
+
+  let radius = sqrt(v);
+  return frame * vec3<f32>(radius * cos(phi), radius * sin(phi), sqrt(max(1.0 - v, 0.0)));
+}
+
+// one path from the camera. the first surface hit also gives the albedo and normal a denoiser is guided by
+fn trace_path(camera_origin: vec3<f32>, camera_direction: vec3<f32>) -> Sample {
+  var sample = Sample(vec3<f32>(0.0), vec3<f32>(0.0), vec3<f32>(0.0));
+  var origin = camera_origin;
+  var direction = camera_direction;
+  var throughput = vec3<f32>(1.0);
+  var bounces = 0u;
+  var first = true;
+
+  let to_light = -normalize(trace.light.xyz);
+  let light = trace.light_color.rgb * trace.light.w;
+
+  for (var step = 0u; step < MAX_STEPS; step++) {
+    let hit = intersect(origin, direction, 1e30);
+
+    if hit.instance == NONE {
+      let sky = environment(direction);
+      sample.color += throughput * sky;
+
+      if first { sample.albedo = min(sky, vec3<f32>(1.0)); }
+      break;
+    }
+
+    let instance = instances[hit.instance];
+    let triangle = triangles[hit.triangle];
+    let position = origin + direction * hit.distance;
+
+    // normals go back to the world through the inverse transpose, which multiplying from the left gives
+    let weights = vec3<f32>(1.0 - hit.barycentric.x - hit.barycentric.y, hit.barycentric);
+    let local_normal = triangle.normal0.xyz * weights.x + triangle.normal1.xyz * weights.y + triangle.normal2.xyz * weights.z;
+
+    var geometric = normalize((vec4<f32>(cross(triangle.edge1.xyz, triangle.edge2.xyz), 0.0) * instance.world_to_object).xyz);
+    if dot(geometric, direction) > 0.0 { geometric = -geometric; }
+
+    var normal = geometric;
+    if dot(local_normal, local_normal) > 1e-12 {
+      normal = normalize((vec4<f32>(local_normal, 0.0) * instance.world_to_object).xyz);
+      if dot(normal, geometric) < 0.0 { normal = -normal; }
+    }
+
+    // blended surfaces are seen through as often as they are transparent
+    if instance.mesh.y == 1u && random() >= instance.base_color.a {
+      origin = position - geometric * EPSILON;
+      continue;
+    }
+
+    let base_color = instance.base_color.rgb;
+    let metallic = clamp(instance.surface.x, 0.0, 1.0);
+    let roughness = clamp(instance.surface.y, 0.03, 1.0);
+    let view = -direction;
+
+    if first {
+      sample.albedo = base_color;
+      sample.normal = normal;
+      first = false;
+    }
+
+    sample.color += throughput * instance.emissive.rgb;
+    origin = position + geometric * EPSILON;
+
+    // the sun is too small to be hit by chance so it is sampled directly
+    if dot(normal, to_light) > 0.0 && dot(geometric, to_light) > 0.0 && !occluded(origin, to_light) {
+      sample.color += throughput * brdf(normal, view, to_light, base_color, metallic, roughness) * dot(normal, to_light) * light;
+    }
+
+    if bounces >= trace.settings.x { break; }
+    bounces += 1u;
+
+    // the lobe reflecting more of the light towards the view is picked more often
+    let fresnel = fresnel_schlick(max(dot(normal, view), 0.0), mix(vec3<f32>(0.04), base_color, metallic));
+    let specular_weight = luminance(fresnel);
+    let diffuse_weight = luminance((1.0 - fresnel) * (1.0 - metallic) * base_color);
+    let specular_chance = clamp(specular_weight / max(specular_weight + diffuse_weight, 1e-6), 0.1, 0.9);
+
+    let next = sample_direction(normal, view, roughness, specular_chance);
+    let n_dot_l = dot(normal, next);
+    if n_dot_l <= 0.0 || dot(geometric, next) <= 0.0 { break; }
+
+    let pdf = direction_pdf(normal, view, next, roughness, specular_chance);
+    if pdf <= 0.0 { break; }
+
+    throughput *= brdf(normal, view, next, base_color, metallic, roughness) * n_dot_l / pdf;
+
+    // paths carrying little light are cut short, the ones that survive make up for them
+    if bounces >= 3u {
+      let survival = clamp(max(throughput.x, max(throughput.y, throughput.z)), 0.05, 0.95);
+      if random() >= survival { break; }
+      throughput /= survival;
+    }
+
+    direction = next;
+  }
+
+  return sample;
+}
+
+// narkowicz's fit of the aces filmic curve, the same the rasterized meshes and sky are tonemapped with
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+  return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn compute_main(@builtin(global_invocation_id) id: vec3<u32>) {
+  let size = trace.frame.xy;
+  if id.x >= size.x || id.y >= size.y { return; }
+
+  let index = id.y * size.x + id.x;
+  rng_state = hash(index ^ hash(trace.frame.w));
+
+  let origin = trace.inverse_view[3].xyz;
+  let count = max(trace.settings.y, 1u);
+  var total = Sample(vec3<f32>(0.0), vec3<f32>(0.0), vec3<f32>(0.0));
+
+  for (var sample_index = 0u; sample_index < count; sample_index++) {
+    // jittered within the pixel, which also antialiases edges as samples accumulate
+    let ndc = (vec2<f32>(id.xy) + vec2<f32>(random(), random())) / vec2<f32>(size) * 2.0 - 1.0;
+    let point = trace.inverse_projection * vec4<f32>(ndc, 0.5, 1.0);
+    let direction = normalize((trace.inverse_view * vec4<f32>(normalize(point.xyz / point.w), 0.0)).xyz);
+
+    let sample = trace_path(origin, direction);
+
+    // a sample gone wrong is dropped rather than spoiling the pixel for good
+    if all(sample.color == sample.color) {
+      total.color += min(sample.color, vec3<f32>(MAX_RADIANCE));
+    }
+
+    total.albedo += sample.albedo;
+    total.normal += sample.normal;
+  }
+
+  // running averages, restarted when nothing was accumulated yet
+  let weight = f32(count) / f32(trace.frame.z + count);
+  let restart = trace.frame.z == 0u;
+
+  let color = mix(select(color_buffer[index].rgb, vec3<f32>(0.0), restart), total.color / f32(count), weight);
+  let albedo = mix(select(albedo_buffer[index].rgb, vec3<f32>(0.0), restart), total.albedo / f32(count), weight);
+  let normal = mix(select(normal_buffer[index].xyz, vec3<f32>(0.0), restart), total.normal / f32(count), weight);
+
+  color_buffer[index] = vec4<f32>(color, 1.0);
+  albedo_buffer[index] = vec4<f32>(albedo, 1.0);
+  normal_buffer[index] = vec4<f32>(normal, 0.0);
+
+  var shown = tonemap(color);
+  switch trace.settings.z {
+    case 1u:  { shown = albedo; }
+    case 2u:  { shown = normal * 0.5 + 0.5; }
+    default:  {}
+  }
+
+  textureStore(output, vec2<i32>(id.xy), vec4<f32>(shown, 1.0));
+}
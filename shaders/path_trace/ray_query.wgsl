@@ -0,0 +1,34 @@
+// the fast path, tracing through acceleration structures built by the driver. appended to common.wgsl
+
+// one instance per entry in instances with its index as custom data, over bottom levels built from the triangles in the
+// same order they are stored in
+@group(0) @binding(9) var scene: acceleration_structure;
+
+// the nearest hit closer than the max distance, whose instance is NONE when there is none
+fn intersect(origin: vec3<f32>, direction: vec3<f32>, max_distance: f32) -> Hit {
+  var hit = Hit(max_distance, NONE, 0u, vec2<f32>(0.0));
+  if trace.settings.w == 0u { return hit; }
+
+  var query: ray_query;
+  rayQueryInitialize(&query, scene, RayDesc(RAY_FLAG_FORCE_OPAQUE, 0xffu, 0.0, max_distance, origin, direction));
+  while rayQueryProceed(&query) {}
+
+  let intersection = rayQueryGetCommittedIntersection(&query);
+  if intersection.kind != RAY_QUERY_INTERSECTION_TRIANGLE { return hit; }
+
+  let instance = intersection.instance_custom_data;
+  let triangle = instances[instance].mesh.z + intersection.primitive_index;
+
+  return Hit(intersection.t, instance, triangle, intersection.barycentrics);
+}
+
+fn occluded(origin: vec3<f32>, direction: vec3<f32>) -> bool {
+  if trace.settings.w == 0u { return false; }
+
+  var query: ray_query;
+  let flags = RAY_FLAG_FORCE_OPAQUE | RAY_FLAG_TERMINATE_ON_FIRST_HIT;
+  rayQueryInitialize(&query, scene, RayDesc(flags, 0xffu, 0.0, 1e30, origin, direction));
+  while rayQueryProceed(&query) {}
+
+  return rayQueryGetCommittedIntersection(&query).kind != RAY_QUERY_INTERSECTION_NONE;
+}
@@ -0,0 +1,92 @@
+const PI: f32 = 3.14159265359;
+const SAMPLE_COUNT: u32 = 256u;
+
+struct Push {
+  roughness: f32,
+  // width of the base level of the source cubemap
+  source_size: f32
+}
+
+@group(0) @binding(0) var source: texture_cube<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+@group(0) @binding(2) var output: texture_storage_2d_array<rgba16float, write>;
+
+var<push_constant> push: Push;
+
+fn cube_direction(face: u32, texel: vec2<u32>, size: u32) -> vec3<f32> {
+  let uv = (vec2<f32>(texel) + 0.5) / f32(size) * 2.0 - 1.0;
+
+  switch face {
+    case 0u: { return normalize(vec3<f32>(1.0, -uv.y, -uv.x)); }
+    case 1u: { return normalize(vec3<f32>(-1.0, -uv.y, uv.x)); }
+    case 2u: { return normalize(vec3<f32>(uv.x, 1.0, uv.y)); }
+    case 3u: { return normalize(vec3<f32>(uv.x, -1.0, -uv.y)); }
+    case 4u: { return normalize(vec3<f32>(uv.x, -uv.y, 1.0)); }
+    default: { return normalize(vec3<f32>(-uv.x, -uv.y, -1.0)); }
+  }
+}
+
+fn hammersley(index: u32, count: u32) -> vec2<f32> {
+  return vec2<f32>(f32(index) / f32(count), f32(reverseBits(index)) * 2.3283064365386963e-10);
+}
+
+fn importance_sample_ggx(xi: vec2<f32>, normal: vec3<f32>, roughness: f32) -> vec3<f32> {
+  let a = roughness * roughness;
+
+  let phi = 2.0 * PI * xi.x;
+  let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+  let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+  let halfway = vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+  var up = vec3<f32>(0.0, 0.0, 1.0);
+  if abs(normal.z) > 0.999 { up = vec3<f32>(1.0, 0.0, 0.0); }
+
+  let tangent = normalize(cross(up, normal));
+  let bitangent = cross(normal, tangent);
+
+  return normalize(tangent * halfway.x + bitangent * halfway.y + normal * halfway.z);
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+  let a2 = roughness * roughness * roughness * roughness;
+  let denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+
+  return a2 / (PI * denominator * denominator);
+}
+
+// split sum prefiltering that assumes the view direction equals the normal
+@compute @workgroup_size(8, 8, 1)
+fn compute_main(@builtin(global_invocation_id) id: vec3<u32>) {
+  let size = textureDimensions(output).x;
+  if id.x >= size || id.y >= size { return; }
+
+  let normal = cube_direction(id.z, id.xy, size);
+  let view = normal;
+
+  let texel_angle = 4.0 * PI / (6.0 * push.source_size * push.source_size);
+
+  var color = vec3<f32>(0.0);
+  var weight = 0.0;
+
+  for (var index = 0u; index < SAMPLE_COUNT; index += 1u) {
+    let halfway = importance_sample_ggx(hammersley(index, SAMPLE_COUNT), normal, push.roughness);
+    let light = normalize(2.0 * dot(view, halfway) * halfway - view);
+
+    let n_dot_l = dot(normal, light);
+    if n_dot_l <= 0.0 { continue; }
+
+    // samples that cover a large solid angle read from a blurrier mip, which removes most of the fireflies
+    let n_dot_h = max(dot(normal, halfway), 0.0);
+    let h_dot_v = max(dot(halfway, view), 0.0);
+    let pdf = distribution_ggx(n_dot_h, push.roughness) * n_dot_h / (4.0 * h_dot_v) + 0.0001;
+    let sample_angle = 1.0 / (f32(SAMPLE_COUNT) * pdf + 0.0001);
+
+    var lod = 0.0;
+    if push.roughness > 0.0 { lod = max(0.5 * log2(sample_angle / texel_angle), 0.0); }
+
+    color += textureSampleLevel(source, source_sampler, light, lod).rgb * n_dot_l;
+    weight += n_dot_l;
+  }
+
+  textureStore(output, id.xy, id.z, vec4<f32>(color / max(weight, 0.0001), 1.0));
+}
@@ -0,0 +1,56 @@
+struct View {
+  view: mat4x4<f32>,
+  projection: mat4x4<f32>,
+  // inverse of the projection and the view without its translation
+  sky: mat4x4<f32>,
+  camera_position: vec4<f32>
+}
+
+struct Frame {
+  // one per layer of a multiview target, single layer targets only use the first
+  views: array<View, 2>,
+  light: vec4<f32>,
+  light_color: vec4<f32>,
+  // intensity of the image based lighting, highest mip of the prefiltered map, how far the current environment has
+  // faded in over the previous one, unused
+  environment: vec4<f32>
+}
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) direction: vec3<f32>
+}
+
+@group(0) @binding(0) var<uniform> frame: Frame;
+
+@group(2) @binding(3) var environment_sampler: sampler;
+@group(2) @binding(4) var environment_map: texture_cube<f32>;
+@group(2) @binding(7) var previous_environment_map: texture_cube<f32>;
+
+// a single triangle covering the screen, placed on the far plane so it only shows where nothing else was drawn
+@vertex
+fn vertex_main(@builtin(vertex_index) index: u32, @builtin(view_index) view_index: i32) -> VertexOutput {
+  let position = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u)) * 2.0 - 1.0;
+  let far = frame.views[view_index].sky * vec4<f32>(position, 1.0, 1.0);
+
+  var output: VertexOutput;
+  output.clip_position = vec4<f32>(position, 1.0, 1.0);
+  output.direction = far.xyz / far.w;
+
+  return output;
+}
+
+// same curve the meshes are tonemapped with so the sky and the lighting it casts agree
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+  return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
+  let direction = normalize(input.direction);
+
+  let current = textureSampleLevel(environment_map, environment_sampler, direction, 0.0).rgb;
+  let previous = textureSampleLevel(previous_environment_map, environment_sampler, direction, 0.0).rgb;
+
+  return vec4<f32>(tonemap(mix(previous, current, frame.environment.z) * frame.environment.x), 1.0);
+}
@@ -0,0 +1,5 @@
+mod documents;
+mod music;
+
+pub use documents::*;
+pub use music::*;
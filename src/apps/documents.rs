@@ -0,0 +1,625 @@
+mod document;
+mod editor;
+mod formats;
+mod history;
+mod storage;
+mod view;
+
+pub use document::*;
+pub use editor::*;
+pub use formats::*;
+pub use history::*;
+pub use storage::*;
+pub use view::*;
+
+use crate::gvp_engine::{
+  app::{AppContext, GVPApp},
+  compositor::{Panel, PanelShape},
+  gui::{Align, Dimension, Gui, GuiEvent, GuiInput, Justify, Rect, Style, Theme, Widget, WidgetId},
+  transform::Transform
+};
+
+use glam::{Quat, Vec2, Vec3};
+use sdl2::keyboard::Keycode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::{mem, path::{Path, PathBuf}};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentSettings {
+  // relative paths are taken from the working directory
+  pub directory: PathBuf,
+  // seconds between writes of the recovery copy while there are unsaved changes
+  pub autosave_interval: f32,
+  pub light_theme: bool
+}
+
+impl Default for DocumentSettings {
+  fn default() -> Self {
+    DocumentSettings { directory: PathBuf::from("documents"), autosave_interval: 5.0, light_theme: false }
+  }
+}
+
+// what the app keeps across sessions
+#[derive(Serialize, Deserialize)]
+struct DocumentsState {
+  settings: DocumentSettings,
+  document: Option<String>
+}
+
+struct Controls {
+  new: WidgetId,
+  save: WidgetId,
+  bold: WidgetId,
+  italic: WidgetId,
+  headings: [WidgetId; 3],
+  bullet: WidgetId,
+  numbered: WidgetId,
+  undo: WidgetId,
+  redo: WidgetId,
+  find: WidgetId,
+  exports: Vec<(WidgetId, Format)>,
+  settings: WidgetId,
+  find_bar: WidgetId,
+  query: WidgetId,
+  replacement: WidgetId,
+  case_sensitive: WidgetId,
+  next: WidgetId,
+  replace: WidgetId,
+  replace_all: WidgetId,
+  files: WidgetId,
+  import_path: WidgetId,
+  import: WidgetId,
+  page: WidgetId,
+  status: WidgetId,
+  settings_modal: WidgetId,
+  light_theme: WidgetId,
+  autosave_label: WidgetId,
+  autosave: WidgetId,
+  close_settings: WidgetId
+}
+
+// a word processor over native documents in a directory. the page takes the keyboard once clicked, the toolbar and
+// fields get it back when one of them is
+pub struct DocumentsApp {
+  settings: DocumentSettings,
+  store: DocumentStore,
+  gui: Gui,
+  controls: Controls,
+  editor: Editor,
+  view: DocumentView,
+  name: String,
+  // the editor revisions last written to the document and to its recovery copy
+  saved: u64,
+  autosaved: u64,
+  since_autosave: f32,
+  // kept from on_input until render, which is where the gui is updated
+  input: Option<GuiInput>,
+  editing: bool,
+  dragging: bool,
+  files: Vec<String>,
+  message: String
+}
+
+impl DocumentsApp {
+  pub fn new(settings: DocumentSettings) -> Self {
+    let (gui, controls) = DocumentsApp::build();
+
+    let mut app = DocumentsApp {
+      store: DocumentStore::new(&settings.directory),
+      settings,
+      gui,
+      controls,
+      editor: Editor::default(),
+      view: DocumentView::default(),
+      name: String::new(),
+      saved: 0,
+      autosaved: 0,
+      since_autosave: 0.0,
+      input: None,
+      editing: false,
+      dragging: false,
+      files: Vec::new(),
+      message: String::new()
+    };
+
+    app.show_settings();
+    app
+  }
+
+  fn build() -> (Gui, Controls) {
+    let mut gui = Gui::new(Theme::dark());
+    let root = Gui::ROOT;
+    let gap = gui.theme.spacing;
+
+    let toolbar = gui.add(root, Widget::container(), Style { gap, ..Style::row() });
+    let button = |gui: &mut Gui, text: &str| gui.add(toolbar, Widget::button(text), Style::default());
+
+    let new = button(&mut gui, "New");
+    let save = button(&mut gui, "Save");
+    let bold = button(&mut gui, "B");
+    let italic = button(&mut gui, "I");
+    let headings = [button(&mut gui, "H1"), button(&mut gui, "H2"), button(&mut gui, "H3")];
+    let bullet = button(&mut gui, "• List");
+    let numbered = button(&mut gui, "1. List");
+    let undo = button(&mut gui, "Undo");
+    let redo = button(&mut gui, "Redo");
+    let find = button(&mut gui, "Find");
+    let exports = [("Markdown", Format::Markdown), ("Text", Format::PlainText), ("HTML", Format::Html)].into_iter()
+      .map(|(text, format)| (button(&mut gui, &format!("Export {text}")), format))
+      .collect();
+    let settings = button(&mut gui, "Settings");
+
+    let find_bar = gui.add(root, Widget::container(), Style { gap, ..Style::row() });
+    let query = gui.add(find_bar, Widget::text_field("Find"), Style::fill());
+    let replacement = gui.add(find_bar, Widget::text_field("Replace with"), Style::fill());
+    let case_sensitive = gui.add(find_bar, Widget::checkbox("Match case", false), Style::default());
+    let next = gui.add(find_bar, Widget::button("Next"), Style::default());
+    let replace = gui.add(find_bar, Widget::button("Replace"), Style::default());
+    let replace_all = gui.add(find_bar, Widget::button("Replace all"), Style::default());
+    gui.set_visible(find_bar, false);
+
+    let body = gui.add(root, Widget::container(), Style { gap, grow: 1.0, align_items: Align::Stretch, ..Style::row() });
+    let sidebar = gui.add(body, Widget::container(), Style { width: Dimension::Pixels(360.0), gap, ..Style::column() });
+    gui.add(sidebar, Widget::label("Documents"), Style::default());
+    let files = gui.add(sidebar, Widget::list(Vec::new()), Style::fill());
+    let import_path = gui.add(sidebar, Widget::text_field("Path to import"), Style::default());
+    let import = gui.add(sidebar, Widget::button("Import"), Style { align_self: Some(Align::End), ..Style::default() });
+    let page = gui.add(body, Widget::container(), Style::fill());
+
+    let status = gui.add(root, Widget::label(""), Style::default());
+
+    let settings_modal = gui.add(root, Widget::modal(), Style::centered());
+    let dialog_style = Style { width: Dimension::Percent(40.0), gap, ..Style::column() };
+    let dialog = gui.add(settings_modal, Widget::container(), dialog_style);
+    gui.add(dialog, Widget::label("Settings"), Style::default());
+    let tabs = gui.add(dialog, Widget::tabs(&["Appearance", "Saving"]), Style::default());
+
+    // the pages scroll on panels too short for them
+    let tab_page = Style {
+      gap, align_items: Align::Start, max_size: Vec2::new(f32::INFINITY, 240.0), ..Style::column()
+    };
+    let appearance = gui.add(tabs, Widget::scroll_view(), tab_page);
+    let light_theme = gui.add(appearance, Widget::checkbox("Light theme", false), Style::default());
+    let saving = gui.add(tabs, Widget::scroll_view(), tab_page);
+    let autosave_label = gui.add(saving, Widget::label(""), Style::default());
+    let autosave = gui.add(saving, Widget::Slider { value: 5.0, min: 1.0, max: 60.0, step: 1.0 }, Style::default());
+
+    let footer = gui.add(dialog, Widget::container(), Style { justify: Justify::End, ..Style::row() });
+    let close_settings = gui.add(footer, Widget::button("Close"), Style::default());
+
+    let controls = Controls {
+      new, save, bold, italic, headings, bullet, numbered, undo, redo, find, exports, settings, find_bar, query,
+      replacement, case_sensitive, next, replace, replace_all, files, import_path, import, page, status, settings_modal,
+      light_theme, autosave_label, autosave, close_settings
+    };
+
+    (gui, controls)
+  }
+
+  // puts the settings into the gui, which is drawn with the theme they pick
+  fn show_settings(&mut self) {
+    let settings = &self.settings;
+
+    self.gui.theme = match settings.light_theme {
+      true  => Theme::light(),
+      false => Theme::dark()
+    };
+
+    if let Widget::Checkbox { checked, .. } = self.gui.widget_mut(self.controls.light_theme) {
+      *checked = settings.light_theme;
+    }
+
+    if let Widget::Slider { value, .. } = self.gui.widget_mut(self.controls.autosave) {
+      *value = settings.autosave_interval;
+    }
+
+    if let Widget::Label { text } = self.gui.widget_mut(self.controls.autosave_label) {
+      *text = format!("Autosave every {} s", settings.autosave_interval);
+    }
+  }
+
+  fn settings_open(&self) -> bool {
+    matches!(self.gui.widget(self.controls.settings_modal), Widget::Modal { open: true })
+  }
+
+  fn dirty(&self) -> bool {
+    self.editor.revision() != self.saved
+  }
+
+  fn refresh_files(&mut self) {
+    self.files = self.store.list();
+
+    let selected = self.files.iter().position(|name| *name == self.name);
+    if let Widget::List { items, selected: current, .. } = self.gui.widget_mut(self.controls.files) {
+      *items = self.files.clone();
+      *current = selected;
+    }
+  }
+
+  // unsaved changes to the document being left stay in its recovery copy
+  fn leave(&mut self) {
+    if self.dirty() && self.editor.revision() != self.autosaved {
+      self.autosave();
+    }
+  }
+
+  fn new_document(&mut self) {
+    self.leave();
+
+    self.name = self.store.unused_name("Untitled");
+    self.editor.load(Document::new());
+    self.saved = self.editor.revision();
+    self.autosaved = self.saved;
+    self.view = DocumentView::default();
+    self.editing = true;
+    self.message = String::new();
+    self.refresh_files();
+  }
+
+  fn open(&mut self, name: &str) {
+    self.leave();
+
+    match self.store.open(name) {
+      Ok((document, recovered)) => {
+        self.name = name.to_string();
+        self.editor.load(document);
+        self.view = DocumentView::default();
+        self.editing = true;
+
+        // a recovered copy is unsaved until saved over the document
+        self.saved = if recovered { self.editor.revision().wrapping_sub(1) } else { self.editor.revision() };
+        self.autosaved = self.editor.revision();
+        self.message = if recovered { "recovered unsaved changes".to_string() } else { String::new() };
+      }
+      Err(error) => self.message = format!("failed to open {name}: {error}")
+    }
+
+    self.refresh_files();
+  }
+
+  fn save(&mut self) {
+    match self.store.save(&self.name, self.editor.document()) {
+      Ok(())     => {
+        self.saved = self.editor.revision();
+        self.autosaved = self.saved;
+        self.message = "saved".to_string();
+      }
+      Err(error) => self.message = format!("failed to save: {error}")
+    }
+
+    self.refresh_files();
+  }
+
+  fn autosave(&mut self) {
+    if let Err(error) = self.store.autosave(&self.name, self.editor.document()) {
+      self.message = format!("failed to autosave: {error}");
+    }
+
+    self.autosaved = self.editor.revision();
+    self.since_autosave = 0.0;
+  }
+
+  fn import(&mut self) {
+    let Widget::TextField { text, .. } = self.gui.widget(self.controls.import_path) else { return; };
+    let path = PathBuf::from(text.trim());
+
+    match self.store.import(&path) {
+      Ok((name, document)) => {
+        self.leave();
+
+        self.name = self.store.unused_name(&name);
+        self.editor.load(document);
+        self.view = DocumentView::default();
+        self.saved = self.editor.revision().wrapping_sub(1);
+        self.autosaved = self.saved;
+        self.editing = true;
+        self.message = format!("imported {}", path.display());
+      }
+      Err(error) => self.message = format!("failed to import {}: {error}", path.display())
+    }
+  }
+
+  fn export(&mut self, format: Format) {
+    match self.store.export(&self.name, self.editor.document(), format) {
+      Ok(path)   => self.message = format!("exported to {}", path.display()),
+      Err(error) => self.message = format!("failed to export: {error}")
+    }
+  }
+
+  fn field(&self, id: WidgetId) -> String {
+    match self.gui.widget(id) {
+      Widget::TextField { text, .. } => text.clone(),
+      _                              => String::new()
+    }
+  }
+
+  fn case_sensitive(&self) -> bool {
+    matches!(self.gui.widget(self.controls.case_sensitive), Widget::Checkbox { checked: true, .. })
+  }
+
+  fn handle(&mut self, event: &GuiEvent) {
+    let controls = &self.controls;
+
+    match *event {
+      GuiEvent::Clicked(id) if id == controls.new      => self.new_document(),
+      GuiEvent::Clicked(id) if id == controls.save     => self.save(),
+      GuiEvent::Clicked(id) if id == controls.undo     => self.editor.undo(),
+      GuiEvent::Clicked(id) if id == controls.redo     => self.editor.redo(),
+      GuiEvent::Clicked(id) if id == controls.bold     => self.editor.toggle_bold(),
+      GuiEvent::Clicked(id) if id == controls.italic   => self.editor.toggle_italic(),
+      GuiEvent::Clicked(id) if id == controls.bullet   => self.editor.toggle_block(BlockKind::Bullet),
+      GuiEvent::Clicked(id) if id == controls.numbered => self.editor.toggle_block(BlockKind::Numbered),
+      GuiEvent::Clicked(id) if id == controls.find     => self.show_find(),
+      GuiEvent::Clicked(id) if id == controls.import   => self.import(),
+      GuiEvent::Submitted(id) if id == controls.import_path => self.import(),
+      GuiEvent::Clicked(id) | GuiEvent::Submitted(id) if id == controls.next || id == controls.query => {
+        let found = self.editor.find_next(&self.field(self.controls.query), self.case_sensitive());
+        self.message = if found { String::new() } else { "not found".to_string() };
+      }
+      GuiEvent::Clicked(id) if id == controls.replace  => {
+        let found = self.editor.replace(&self.field(self.controls.query), &self.field(self.controls.replacement), self.case_sensitive());
+        self.message = if found { String::new() } else { "not found".to_string() };
+      }
+      GuiEvent::Clicked(id) if id == controls.replace_all => {
+        let count = self.editor.replace_all(&self.field(self.controls.query), &self.field(self.controls.replacement), self.case_sensitive());
+        self.message = format!("replaced {count}");
+      }
+      GuiEvent::Clicked(id) if controls.headings.contains(&id) => {
+        let level = controls.headings.iter().position(|heading| *heading == id).unwrap_or(0) as u8 + 1;
+        self.editor.toggle_block(BlockKind::Heading(level));
+      }
+      GuiEvent::Clicked(id) if controls.exports.iter().any(|(export, _)| *export == id) => {
+        if let Some((_, format)) = controls.exports.iter().find(|(export, _)| *export == id) {
+          self.export(*format);
+        }
+      }
+      GuiEvent::Clicked(id) if id == controls.settings => {
+        self.gui.set_open(controls.settings_modal, true);
+        self.editing = false;
+      }
+      GuiEvent::Clicked(id) if id == controls.close_settings => self.gui.set_open(controls.settings_modal, false),
+      GuiEvent::Toggled(id, light) if id == controls.light_theme => {
+        self.settings.light_theme = light;
+        self.show_settings();
+      }
+      GuiEvent::Changed(id, seconds) if id == controls.autosave => {
+        self.settings.autosave_interval = seconds;
+        self.show_settings();
+      }
+      GuiEvent::Selected(id, index) if id == controls.files => {
+        if let Some(name) = self.files.get(index).filter(|name| **name != self.name).cloned() {
+          self.open(&name);
+        }
+      }
+      // the page gives up the keyboard to whatever took it
+      GuiEvent::Focused(Some(_))                       => self.editing = false,
+      _                                                => {}
+    }
+
+    // formatting goes back to the page it was for
+    if let GuiEvent::Clicked(id) = *event {
+      let controls = &self.controls;
+      let formatting = [controls.bold, controls.italic, controls.bullet, controls.numbered, controls.undo, controls.redo];
+
+      if formatting.contains(&id) || controls.headings.contains(&id) {
+        self.gui.focus(None);
+        self.editing = true;
+      }
+    }
+  }
+
+  fn show_find(&mut self) {
+    self.gui.set_visible(self.controls.find_bar, true);
+    self.gui.focus(Some(self.controls.query));
+    self.editing = false;
+  }
+
+  // keys for the page while it has the keyboard. ctrl makes arrows and deletion go by words
+  fn keyboard(&mut self, input: &GuiInput, ctrl: bool, view_height: f32) {
+    let shift = input.shift;
+    let mut moved = false;
+
+    for key in &input.keys {
+      let caret = self.editor.selection().caret;
+      let line = self.editor.document().line_of(caret);
+
+      match (*key, ctrl) {
+        (Keycode::Z, true) if shift   => self.editor.redo(),
+        (Keycode::Z, true)            => self.editor.undo(),
+        (Keycode::Y, true)            => self.editor.redo(),
+        (Keycode::B, true)            => self.editor.toggle_bold(),
+        (Keycode::I, true)            => self.editor.toggle_italic(),
+        (Keycode::S, true)            => self.save(),
+        (Keycode::F, true)            => self.show_find(),
+        (Keycode::A, true)            => self.editor.select_all(),
+        (Keycode::C, true)            => self.editor.copy(),
+        (Keycode::X, true)            => self.editor.cut(),
+        (Keycode::V, true)            => self.editor.paste(),
+        (Keycode::Left, _)            => self.editor.step(false, ctrl, shift),
+        (Keycode::Right, _)           => self.editor.step(true, ctrl, shift),
+        (Keycode::Up, _)              => self.editor.move_to(self.view.step_line(caret, false), shift),
+        (Keycode::Down, _)            => self.editor.move_to(self.view.step_line(caret, true), shift),
+        (Keycode::Home, _)            => self.editor.move_to(self.editor.document().line_range(line).start, shift),
+        (Keycode::End, _)             => self.editor.move_to(self.editor.document().line_range(line).end, shift),
+        (Keycode::Backspace, _)       => self.editor.delete_back(ctrl),
+        (Keycode::Delete, _)          => self.editor.delete_forward(ctrl),
+        (Keycode::Return | Keycode::KpEnter, _) => self.editor.newline(),
+        (Keycode::Escape, _)          => self.editing = false,
+        _                             => continue
+      }
+
+      moved = true;
+    }
+
+    let typed: String = input.text.chars().filter(|character| !character.is_control()).collect();
+    if !ctrl && !typed.is_empty() {
+      self.editor.type_text(&typed);
+      moved = true;
+    }
+
+    if moved {
+      self.view.reveal(self.editor.selection().caret, view_height);
+    }
+  }
+
+  // clicks and drags on the page place the caret and select, the wheel scrolls it
+  fn pointer(&mut self, input: &GuiInput, page: Rect) {
+    let Some(point) = input.pointer else { return; };
+    let over = page.contains(point);
+
+    if input.pressed && over {
+      self.gui.focus(None);
+      self.editing = true;
+      self.dragging = true;
+      self.editor.move_to(self.view.index_at(point - page.position), input.shift);
+    }
+    else if input.pressed {
+      self.editing = false;
+    }
+
+    if input.held && self.dragging {
+      self.editor.move_to(self.view.index_at(point - page.position), true);
+    }
+
+    if input.released {
+      self.dragging = false;
+    }
+
+    if over && input.scroll.y != 0.0 {
+      self.view.scroll_by(-input.scroll.y * self.gui.theme.scroll_speed, page.size.y);
+    }
+  }
+
+  fn status(&self) -> String {
+    let mut status = self.name.clone();
+
+    if self.dirty() {
+      status.push_str(" (unsaved)");
+    }
+
+    if !self.message.is_empty() {
+      status.push_str(" - ");
+      status.push_str(&self.message);
+    }
+
+    status
+  }
+}
+
+impl Default for DocumentsApp {
+  fn default() -> Self {
+    DocumentsApp::new(DocumentSettings::default())
+  }
+}
+
+impl GVPApp for DocumentsApp {
+  fn name(&self) -> &str {
+    "Documents"
+  }
+
+  // wide enough to read across, so it curves around the spot it is opened in front of
+  fn panel(&self) -> Panel {
+    let panel = Panel::new(Transform::new(Vec3::new(0.0, 1.5, -1.4), Quat::IDENTITY, Vec3::ONE), Vec2::new(1.2, 0.8));
+    Panel { shape: PanelShape::Curved { radius: 1.4 }, ..panel }
+  }
+
+  // the document from last time, or a new one
+  fn on_open(&mut self, context: &mut AppContext) {
+    if !context.text().fonts().is_empty() {
+      self.gui.set_text_measure(Box::new(context.text().fonts().clone()));
+    }
+
+    let name = mem::take(&mut self.name);
+
+    match !name.is_empty() && self.store.exists(&name) {
+      true  => self.open(&name),
+      false => self.new_document()
+    }
+  }
+
+  fn on_input(&mut self, _context: &mut AppContext, input: &GuiInput) {
+    self.input = Some(input.clone());
+  }
+
+  fn update(&mut self, _context: &mut AppContext, delta: f32) {
+    self.since_autosave += delta;
+
+    if self.since_autosave >= self.settings.autosave_interval && self.dirty() && self.editor.revision() != self.autosaved {
+      self.autosave();
+    }
+  }
+
+  fn render(&mut self, context: &mut AppContext) {
+    let input = self.input.take().unwrap_or_default();
+    let ctrl = context.input().desktop().key_held(Keycode::LCtrl) || context.input().desktop().key_held(Keycode::RCtrl);
+
+    // the gui only sees the keyboard while the page does not have it
+    let gui_input = match self.editing {
+      true  => GuiInput { keys: Vec::new(), text: String::new(), back: false, ..input.clone() },
+      false => input.clone()
+    };
+
+    // the page is behind the settings while they are open, so the click closing them does not reach it either
+    let covered = self.settings_open();
+    self.gui.update(context.size(), &gui_input);
+
+    for event in self.gui.events().to_vec() {
+      self.handle(&event);
+    }
+
+    let page = self.gui.rect(self.controls.page);
+    self.view.update(&self.editor, context.text(), page.size.x);
+
+    if !covered {
+      self.pointer(&input, page);
+    }
+
+    if self.editing {
+      self.keyboard(&input, ctrl, page.size.y);
+    }
+
+    // what the keys changed is laid out before it is drawn
+    self.view.update(&self.editor, context.text(), page.size.x);
+
+    let status = self.status();
+    if let Widget::Label { text } = self.gui.widget_mut(self.controls.status) {
+      *text = status;
+    }
+
+    let mut canvas = self.gui.paint();
+
+    if !self.settings_open() {
+      self.view.paint(&mut canvas, page, &self.editor, &self.gui.theme, self.editing && context.focused());
+    }
+
+    context.draw(canvas);
+  }
+
+  fn on_suspend(&mut self, _context: &mut AppContext) {
+    self.leave();
+  }
+
+  // closing saves, the way leaving a document for another keeps its changes
+  fn on_close(&mut self, _context: &mut AppContext) {
+    if self.dirty() {
+      self.save();
+    }
+  }
+
+  fn save_state(&self) -> Option<Value> {
+    let state = DocumentsState { settings: self.settings.clone(), document: Some(self.name.clone()) };
+    serde_json::to_value(state).ok()
+  }
+
+  fn restore_state(&mut self, state: &Value) {
+    let Ok(state) = serde_json::from_value::<DocumentsState>(state.clone()) else { return; };
+
+    self.store = DocumentStore::new(Path::new(&state.settings.directory));
+    self.settings = state.settings;
+    self.name = state.document.unwrap_or_default();
+    self.show_settings();
+  }
+}
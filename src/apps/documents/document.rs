@@ -0,0 +1,449 @@
+use crate::gvp_engine::gui::FontStyle;
+
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+
+use std::{iter, ops::Range};
+
+// how a paragraph is laid out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlockKind {
+  #[default]
+  Paragraph,
+  // levels 1 to 3
+  Heading(u8),
+  Bullet,
+  Numbered
+}
+
+impl BlockKind {
+  pub fn is_list(&self) -> bool {
+    matches!(self, BlockKind::Bullet | BlockKind::Numbered)
+  }
+}
+
+// styled text within a paragraph, never holding a line break
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+  pub text: String,
+  #[serde(default)]
+  pub style: FontStyle
+}
+
+// a paragraph taken out of a document, the way formats and the clipboard exchange text
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Paragraph {
+  #[serde(default)]
+  pub kind: BlockKind,
+  #[serde(default)]
+  pub spans: Vec<Span>
+}
+
+impl Paragraph {
+  pub fn new(kind: BlockKind) -> Self {
+    Paragraph { kind, spans: Vec::new() }
+  }
+
+  // joins the last span when the style is the same. line breaks become spaces
+  pub fn push(&mut self, text: &str, style: FontStyle) {
+    if text.is_empty() { return; }
+    let text = text.replace(['\r', '\n'], " ");
+
+    match self.spans.last_mut() {
+      Some(last) if last.style == style => last.text.push_str(&text),
+      _                                 => self.spans.push(Span { text, style })
+    }
+  }
+
+  pub fn text(&self) -> String {
+    self.spans.iter().map(|span| span.text.as_str()).collect()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.spans.iter().all(|span| span.text.is_empty())
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct StyleRun {
+  length: usize,
+  style: FontStyle
+}
+
+// rich text as a rope of characters, the inline style of every character as runs and a block kind for every line.
+// every line is a paragraph and positions are character indices
+#[derive(Clone, Debug)]
+pub struct Document {
+  text: Rope,
+  // cover the text exactly, with no empty runs and no two neighbours of the same style
+  runs: Vec<StyleRun>,
+  // one per line, including the empty one after a trailing line break
+  blocks: Vec<BlockKind>
+}
+
+impl Default for Document {
+  fn default() -> Self {
+    Document::new()
+  }
+}
+
+impl Document {
+  pub fn new() -> Self {
+    Document { text: Rope::new(), runs: Vec::new(), blocks: vec![BlockKind::Paragraph] }
+  }
+
+  // no paragraphs makes one empty paragraph
+  pub fn from_paragraphs(paragraphs: &[Paragraph]) -> Self {
+    let mut document = Document::new();
+    document.insert_paragraphs(0, paragraphs);
+
+    document
+  }
+
+  pub fn len_chars(&self) -> usize {
+    self.text.len_chars()
+  }
+
+  pub fn len_lines(&self) -> usize {
+    self.blocks.len()
+  }
+
+  pub fn char(&self, index: usize) -> Option<char> {
+    (index < self.text.len_chars()).then(|| self.text.char(index))
+  }
+
+  pub fn slice(&self, range: Range<usize>) -> String {
+    self.text.slice(range).to_string()
+  }
+
+  pub fn line_of(&self, index: usize) -> usize {
+    self.text.char_to_line(index.min(self.text.len_chars()))
+  }
+
+  // the characters of a line without its line break
+  pub fn line_range(&self, line: usize) -> Range<usize> {
+    let start = self.text.line_to_char(line);
+    let end = match line + 1 < self.len_lines() {
+      true  => self.text.line_to_char(line + 1) - 1,
+      false => self.text.len_chars()
+    };
+
+    start..end
+  }
+
+  pub fn block(&self, line: usize) -> BlockKind {
+    self.blocks[line]
+  }
+
+  pub fn set_block(&mut self, lines: Range<usize>, kind: BlockKind) {
+    for block in &mut self.blocks[lines] {
+      *block = kind;
+    }
+  }
+
+  // the number a numbered line shows, counting the numbered lines right before it
+  pub fn list_number(&self, line: usize) -> usize {
+    self.blocks[..=line].iter().rev().take_while(|kind| **kind == BlockKind::Numbered).count()
+  }
+
+  // of the character at the index, or the last one past the end
+  pub fn style_at(&self, index: usize) -> FontStyle {
+    let mut start = 0;
+
+    for run in &self.runs {
+      if index < start + run.length {
+        return run.style;
+      }
+
+      start += run.length;
+    }
+
+    self.runs.last().map_or(FontStyle::REGULAR, |run| run.style)
+  }
+
+  // the range cut into pieces of one style
+  pub fn styles(&self, range: Range<usize>) -> Vec<(Range<usize>, FontStyle)> {
+    let mut styles = Vec::new();
+    let mut start = 0;
+
+    for run in &self.runs {
+      let piece = start.max(range.start)..(start + run.length).min(range.end);
+      if !piece.is_empty() {
+        styles.push((piece, run.style));
+      }
+
+      start += run.length;
+      if start >= range.end { break; }
+    }
+
+    styles
+  }
+
+  pub fn set_style(&mut self, range: Range<usize>, style: impl Fn(FontStyle) -> FontStyle) {
+    if range.is_empty() { return; }
+
+    let start = self.split_runs(range.start);
+    let end = self.split_runs(range.end);
+
+    for run in &mut self.runs[start..end] {
+      run.style = style(run.style);
+    }
+
+    self.merge_runs();
+  }
+
+  // line breaks in the text start new paragraphs. they carry on lists and leave headings behind, and one typed at the
+  // start of a line pushes the whole line down with its kind
+  pub fn insert(&mut self, at: usize, text: &str, style: FontStyle) {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    if text.is_empty() { return; }
+
+    let line = self.line_of(at);
+    let range = self.line_range(line);
+    let breaks = text.matches('\n').count();
+
+    let kind = match self.blocks[line] {
+      kind if kind.is_list() => kind,
+      _                      => BlockKind::Paragraph
+    };
+    let index = if at == range.start && !range.is_empty() { line } else { line + 1 };
+    self.blocks.splice(index..index, iter::repeat_n(kind, breaks));
+
+    let run = self.split_runs(at);
+    self.runs.insert(run, StyleRun { length: text.chars().count(), style });
+    self.text.insert(at, &text);
+
+    self.merge_runs();
+  }
+
+  // paragraphs after the first start new lines with their own kinds. returns where the inserted text ends
+  pub fn insert_paragraphs(&mut self, at: usize, paragraphs: &[Paragraph]) -> usize {
+    let mut position = at;
+
+    let line = self.line_of(at);
+    if let (true, Some(first)) = (self.line_range(line).is_empty(), paragraphs.first()) {
+      self.blocks[line] = first.kind;
+    }
+
+    for (index, paragraph) in paragraphs.iter().enumerate() {
+      if index > 0 {
+        self.insert(position, "\n", FontStyle::REGULAR);
+        position += 1;
+
+        let line = self.line_of(position);
+        self.blocks[line] = paragraph.kind;
+      }
+
+      for span in &paragraph.spans {
+        self.insert(position, &span.text, span.style);
+        position += span.text.chars().count();
+      }
+    }
+
+    position
+  }
+
+  // joined lines keep the kind of the first, unless whole lines went away
+  pub fn remove(&mut self, range: Range<usize>) {
+    let range = range.start.min(self.len_chars())..range.end.min(self.len_chars());
+    if range.is_empty() { return; }
+
+    let line = self.line_of(range.start);
+    let breaks = self.text.slice(range.clone()).chars().filter(|character| *character == '\n').count();
+    let whole = range.start == self.line_range(line).start && (range.end == self.len_chars() || self.text.char(range.end - 1) == '\n');
+
+    let first = if whole && range.end != self.len_chars() { line } else { line + 1 };
+    self.blocks.drain(first..first + breaks);
+
+    let start = self.split_runs(range.start);
+    let end = self.split_runs(range.end);
+    self.runs.drain(start..end);
+    self.text.remove(range);
+
+    self.merge_runs();
+  }
+
+  // the new text takes the style of the first character it replaces
+  pub fn replace(&mut self, range: Range<usize>, text: &str) -> Range<usize> {
+    let style = self.style_at(range.start);
+
+    self.remove(range.clone());
+    self.insert(range.start, text, style);
+
+    range.start..range.start + text.chars().count()
+  }
+
+  // the lines touching the range, cut to it
+  pub fn paragraphs(&self, range: Range<usize>) -> Vec<Paragraph> {
+    let (first, last) = (self.line_of(range.start), self.line_of(range.end));
+
+    (first..=last).map(|line| {
+      let line_range = self.line_range(line);
+      let mut paragraph = Paragraph::new(self.blocks[line]);
+
+      for (piece, style) in self.styles(line_range.start.max(range.start)..line_range.end.min(range.end)) {
+        paragraph.push(&self.slice(piece), style);
+      }
+
+      paragraph
+    }).collect()
+  }
+
+  pub fn all_paragraphs(&self) -> Vec<Paragraph> {
+    self.paragraphs(0..self.len_chars())
+  }
+
+  // matches that do not overlap, in order. case is folded a character at a time so positions line up
+  pub fn find(&self, query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+    let fold = |character: char| match case_sensitive {
+      true  => character,
+      false => character.to_lowercase().next().unwrap_or(character)
+    };
+
+    let query: Vec<char> = query.chars().map(fold).collect();
+    if query.is_empty() { return Vec::new(); }
+
+    let text: Vec<char> = self.text.chars().map(fold).collect();
+    let mut matches = Vec::new();
+    let mut index = 0;
+
+    while index + query.len() <= text.len() {
+      match text[index..index + query.len()] == query[..] {
+        true  => {
+          matches.push(index..index + query.len());
+          index += query.len();
+        }
+        false => index += 1
+      }
+    }
+
+    matches
+  }
+
+  // returns how many were replaced
+  pub fn replace_all(&mut self, query: &str, replacement: &str, case_sensitive: bool) -> usize {
+    let matches = self.find(query, case_sensitive);
+
+    for range in matches.iter().rev() {
+      self.replace(range.clone(), replacement);
+    }
+
+    matches.len()
+  }
+
+  // makes a run start at the index, returning that run's index
+  fn split_runs(&mut self, at: usize) -> usize {
+    let mut start = 0;
+
+    for index in 0..self.runs.len() {
+      if start == at { return index; }
+
+      let run = self.runs[index];
+      if at < start + run.length {
+        self.runs[index].length = at - start;
+        self.runs.insert(index + 1, StyleRun { length: start + run.length - at, style: run.style });
+        return index + 1;
+      }
+
+      start += run.length;
+    }
+
+    self.runs.len()
+  }
+
+  fn merge_runs(&mut self) {
+    self.runs.retain(|run| run.length > 0);
+    self.runs.dedup_by(|run, previous| {
+      let same = run.style == previous.style;
+      if same {
+        previous.length += run.length;
+      }
+
+      same
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const BOLD: FontStyle = FontStyle { bold: true, italic: false };
+
+  fn text(document: &Document) -> String {
+    document.slice(0..document.len_chars())
+  }
+
+  fn blocks(document: &Document) -> Vec<BlockKind> {
+    (0..document.len_lines()).map(|line| document.block(line)).collect()
+  }
+
+  #[test]
+  fn keeps_a_block_per_line_through_inserts_and_removes() {
+    let mut document = Document::new();
+    document.insert(0, "one\ntwo", FontStyle::REGULAR);
+    document.set_block(1..2, BlockKind::Bullet);
+
+    // a break in a list item carries the list on
+    document.insert(7, "\nthree", FontStyle::REGULAR);
+    assert_eq!(text(&document), "one\ntwo\nthree");
+    assert_eq!(blocks(&document), [BlockKind::Paragraph, BlockKind::Bullet, BlockKind::Bullet]);
+
+    // joined lines keep the kind of the first
+    document.remove(3..4);
+    assert_eq!(text(&document), "onetwo\nthree");
+    assert_eq!(blocks(&document), [BlockKind::Paragraph, BlockKind::Bullet]);
+
+    // whole lines take their kinds with them
+    document.remove(0..7);
+    assert_eq!(blocks(&document), [BlockKind::Bullet]);
+    assert_eq!(document.line_range(0), 0..5);
+
+    // a break at the start of a line pushes the line down with its kind
+    document.set_block(0..1, BlockKind::Heading(1));
+    document.insert(0, "\n", FontStyle::REGULAR);
+    assert_eq!(blocks(&document), [BlockKind::Paragraph, BlockKind::Heading(1)]);
+  }
+
+  #[test]
+  fn merges_style_runs_around_edits() {
+    let mut document = Document::new();
+    document.insert(0, "plain bold", FontStyle::REGULAR);
+    document.set_style(6..10, |style| FontStyle { bold: true, ..style });
+
+    document.insert(10, "er", BOLD);
+    assert_eq!(document.styles(0..12), [(0..6, FontStyle::REGULAR), (6..12, BOLD)]);
+
+    document.remove(4..8);
+    assert_eq!(text(&document), "plailder");
+    assert_eq!(document.styles(0..8), [(0..4, FontStyle::REGULAR), (4..8, BOLD)]);
+    assert_eq!(document.style_at(4), BOLD);
+  }
+
+  #[test]
+  fn finds_matches_without_overlaps_and_replaces_them_all() {
+    let mut document = Document::new();
+    document.insert(0, "Ana and anna aaaaa", FontStyle::REGULAR);
+
+    assert_eq!(document.find("an", false), [0..2, 4..6, 8..10]);
+    assert_eq!(document.find("an", true), [4..6, 8..10]);
+    assert_eq!(document.find("aa", true), [13..15, 15..17]);
+
+    assert_eq!(document.replace_all("an", "AN", false), 3);
+    assert_eq!(text(&document), "ANa ANd ANna aaaaa");
+  }
+
+  #[test]
+  fn cuts_paragraphs_to_the_range() {
+    let mut document = Document::new();
+    document.insert(0, "first\nsecond", FontStyle::REGULAR);
+    document.set_block(1..2, BlockKind::Numbered);
+
+    let mut first = Paragraph::new(BlockKind::Paragraph);
+    first.push("st", FontStyle::REGULAR);
+    let mut second = Paragraph::new(BlockKind::Numbered);
+    second.push("sec", FontStyle::REGULAR);
+
+    assert_eq!(document.paragraphs(3..9), [first, second]);
+  }
+}
@@ -0,0 +1,403 @@
+use crate::gvp_engine::gui::FontStyle;
+
+use super::{BlockKind, Document, EditKind, History, Paragraph};
+
+use std::ops::Range;
+
+// the caret is where typing goes, the anchor is the other end of the selected text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Selection {
+  pub anchor: usize,
+  pub caret: usize
+}
+
+impl Selection {
+  pub fn caret(index: usize) -> Self {
+    Selection { anchor: index, caret: index }
+  }
+
+  pub fn range(&self) -> Range<usize> {
+    self.anchor.min(self.caret)..self.anchor.max(self.caret)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.anchor == self.caret
+  }
+}
+
+// a document with a selection, editing it through commands that can be undone
+#[derive(Default)]
+pub struct Editor {
+  document: Document,
+  selection: Selection,
+  history: History,
+  // what typed text looks like, taken from the text before the caret whenever it moves
+  typing_style: FontStyle,
+  clipboard: Vec<Paragraph>,
+  // counts changes to the document, for anything kept in step with it
+  revision: u64
+}
+
+impl Editor {
+  pub fn document(&self) -> &Document {
+    &self.document
+  }
+
+  // replaces the document, forgetting its history
+  pub fn load(&mut self, document: Document) {
+    self.document = document;
+    self.selection = Selection::default();
+    self.history.clear();
+    self.typing_style = FontStyle::REGULAR;
+    self.revision += 1;
+  }
+
+  pub fn revision(&self) -> u64 {
+    self.revision
+  }
+
+  pub fn selection(&self) -> Selection {
+    self.selection
+  }
+
+  // extend keeps the anchor where it is
+  pub fn move_to(&mut self, index: usize, extend: bool) {
+    let index = index.min(self.document.len_chars());
+
+    self.selection = match extend {
+      true  => Selection { anchor: self.selection.anchor, caret: index },
+      false => Selection::caret(index)
+    };
+
+    self.typing_style = self.document.style_at(index.saturating_sub(1));
+  }
+
+  pub fn select(&mut self, range: Range<usize>) {
+    self.move_to(range.start, false);
+    self.move_to(range.end, true);
+  }
+
+  pub fn select_all(&mut self) {
+    self.select(0..self.document.len_chars());
+  }
+
+  // a character or word to the left or right, collapsing a selection to its edge instead
+  pub fn step(&mut self, forward: bool, word: bool, extend: bool) {
+    let range = self.selection.range();
+
+    let index = match (self.selection.is_empty() || extend, forward) {
+      (false, false) => range.start,
+      (false, true)  => range.end,
+      (true, _)      => match word {
+        true  => self.word_boundary(self.selection.caret, forward),
+        false => if forward { self.selection.caret + 1 } else { self.selection.caret.saturating_sub(1) }
+      }
+    };
+
+    self.move_to(index, extend);
+  }
+
+  pub fn type_text(&mut self, text: &str) {
+    if text.is_empty() { return; }
+
+    self.edit(EditKind::Typing, |document, range, style| {
+      document.remove(range.clone());
+      document.insert(range.start, text, style);
+
+      range.start + text.chars().count()
+    });
+  }
+
+  // an empty list item stops the list instead of adding another
+  pub fn newline(&mut self) {
+    let line = self.document.line_of(self.selection.caret);
+
+    if self.selection.is_empty() && self.document.block(line).is_list() && self.document.line_range(line).is_empty() {
+      self.edit(EditKind::Other, |document, range, _| {
+        document.set_block(line..line + 1, BlockKind::Paragraph);
+        range.start
+      });
+      return;
+    }
+
+    self.edit(EditKind::Other, |document, range, style| {
+      document.remove(range.clone());
+      document.insert(range.start, "\n", style);
+
+      range.start + 1
+    });
+  }
+
+  // backwards, like backspace. at the start of a list item or heading it turns the line back into a paragraph first
+  pub fn delete_back(&mut self, word: bool) {
+    if self.selection.is_empty() {
+      let caret = self.selection.caret;
+      let line = self.document.line_of(caret);
+
+      if caret == self.document.line_range(line).start && self.document.block(line) != BlockKind::Paragraph {
+        self.edit(EditKind::Other, |document, _, _| {
+          document.set_block(line..line + 1, BlockKind::Paragraph);
+          caret
+        });
+        return;
+      }
+
+      if caret == 0 { return; }
+
+      let start = if word { self.word_boundary(caret, false) } else { caret - 1 };
+      self.selection = Selection { anchor: caret, caret: start };
+    }
+
+    self.edit(EditKind::Deleting, |document, range, _| {
+      document.remove(range.clone());
+      range.start
+    });
+  }
+
+  pub fn delete_forward(&mut self, word: bool) {
+    if self.selection.is_empty() {
+      let caret = self.selection.caret;
+      let end = if word { self.word_boundary(caret, true) } else { caret + 1 };
+      self.selection = Selection { anchor: caret, caret: end.min(self.document.len_chars()) };
+    }
+
+    self.edit(EditKind::Deleting, |document, range, _| {
+      document.remove(range.clone());
+      range.start
+    });
+  }
+
+  // sets bold on the selection unless all of it already is, or on what is typed next when nothing is selected
+  pub fn toggle_bold(&mut self) {
+    let bold = !self.all(|style| style.bold);
+    self.restyle(move |style| FontStyle { bold, ..style });
+  }
+
+  pub fn toggle_italic(&mut self) {
+    let italic = !self.all(|style| style.italic);
+    self.restyle(move |style| FontStyle { italic, ..style });
+  }
+
+  // the kind for every line the selection touches, or back to paragraphs if they all were that kind already
+  pub fn toggle_block(&mut self, kind: BlockKind) {
+    let range = self.selection.range();
+    let lines = self.document.line_of(range.start)..self.document.line_of(range.end) + 1;
+    let kind = match lines.clone().all(|line| self.document.block(line) == kind) {
+      true  => BlockKind::Paragraph,
+      false => kind
+    };
+
+    self.edit(EditKind::Other, |document, _, _| {
+      document.set_block(lines, kind);
+      range.end
+    });
+    self.selection.anchor = range.start;
+  }
+
+  pub fn undo(&mut self) {
+    if let Some((document, selection)) = self.history.undo(&self.document, self.selection) {
+      self.restore(document, selection);
+    }
+  }
+
+  pub fn redo(&mut self) {
+    if let Some((document, selection)) = self.history.redo(&self.document, self.selection) {
+      self.restore(document, selection);
+    }
+  }
+
+  pub fn copy(&mut self) {
+    if self.selection.is_empty() { return; }
+    self.clipboard = self.document.paragraphs(self.selection.range());
+  }
+
+  pub fn cut(&mut self) {
+    if self.selection.is_empty() { return; }
+
+    self.copy();
+    self.delete_back(false);
+  }
+
+  pub fn paste(&mut self) {
+    if self.clipboard.is_empty() { return; }
+    let clipboard = self.clipboard.clone();
+
+    self.edit(EditKind::Other, |document, range, _| {
+      document.remove(range.clone());
+      document.insert_paragraphs(range.start, &clipboard)
+    });
+  }
+
+  pub fn find(&self, query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+    self.document.find(query, case_sensitive)
+  }
+
+  // the first match after the selection, wrapping around to the top
+  pub fn find_next(&mut self, query: &str, case_sensitive: bool) -> bool {
+    let matches = self.find(query, case_sensitive);
+    let after = self.selection.range().end;
+
+    match matches.iter().find(|range| range.start >= after).or(matches.first()) {
+      Some(range) => {
+        self.select(range.clone());
+        true
+      }
+      None        => false
+    }
+  }
+
+  // replaces the selection if it is a match, then selects the next one
+  pub fn replace(&mut self, query: &str, replacement: &str, case_sensitive: bool) -> bool {
+    let range = self.selection.range();
+
+    if self.find(query, case_sensitive).contains(&range) {
+      self.edit(EditKind::Other, |document, range, _| document.replace(range, replacement).end);
+    }
+
+    self.find_next(query, case_sensitive)
+  }
+
+  // as one step of the history
+  pub fn replace_all(&mut self, query: &str, replacement: &str, case_sensitive: bool) -> usize {
+    if self.find(query, case_sensitive).is_empty() { return 0; }
+
+    let mut count = 0;
+    self.edit(EditKind::Other, |document, range, _| {
+      count = document.replace_all(query, replacement, case_sensitive);
+      range.start.min(document.len_chars())
+    });
+
+    count
+  }
+
+  // runs an edit over the selected range in the typing style, recording the state before it. the edit returns where
+  // the caret goes
+  fn edit(&mut self, kind: EditKind, edit: impl FnOnce(&mut Document, Range<usize>, FontStyle) -> usize) {
+    self.history.record(&self.document, self.selection, kind);
+
+    let style = self.typing_style;
+    let caret = edit(&mut self.document, self.selection.range(), style);
+
+    self.selection = Selection::caret(caret.min(self.document.len_chars()));
+    self.typing_style = style;
+    self.revision += 1;
+  }
+
+  fn restyle(&mut self, style: impl Fn(FontStyle) -> FontStyle) {
+    if self.selection.is_empty() {
+      self.typing_style = style(self.typing_style);
+      return;
+    }
+
+    let selection = self.selection;
+    self.history.record(&self.document, selection, EditKind::Other);
+    self.document.set_style(selection.range(), style);
+    self.revision += 1;
+  }
+
+  // whether the whole selection has a style, or the typing style when nothing is selected
+  fn all(&self, check: impl Fn(FontStyle) -> bool) -> bool {
+    match self.selection.is_empty() {
+      true  => check(self.typing_style),
+      false => self.document.styles(self.selection.range()).iter().all(|(_, style)| check(*style))
+    }
+  }
+
+  fn restore(&mut self, document: Document, selection: Selection) {
+    self.document = document;
+    self.selection = selection;
+    self.typing_style = self.document.style_at(selection.caret.saturating_sub(1));
+    self.revision += 1;
+  }
+
+  // the next start of a word going forward, or the start of the word before going back
+  fn word_boundary(&self, from: usize, forward: bool) -> usize {
+    let word = |index: usize| self.document.char(index).is_some_and(char::is_alphanumeric);
+    let mut index = from;
+
+    match forward {
+      true  => {
+        while index < self.document.len_chars() && word(index) { index += 1; }
+        while index < self.document.len_chars() && !word(index) { index += 1; }
+      }
+      false => {
+        while index > 0 && !word(index - 1) { index -= 1; }
+        while index > 0 && word(index - 1) { index -= 1; }
+      }
+    }
+
+    index
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn text(editor: &Editor) -> String {
+    editor.document().slice(0..editor.document().len_chars())
+  }
+
+  #[test]
+  fn backspace_at_the_start_changes_nothing() {
+    let mut editor = Editor::default();
+    editor.type_text("ab");
+    editor.move_to(0, false);
+
+    let revision = editor.revision();
+    editor.delete_back(false);
+    assert_eq!((text(&editor), editor.revision()), ("ab".to_string(), revision));
+
+    // the typing is the only step to undo
+    editor.undo();
+    assert_eq!(text(&editor), "");
+  }
+
+  #[test]
+  fn backspace_at_the_start_of_a_list_item_ends_the_list_first() {
+    let mut editor = Editor::default();
+    editor.type_text("one");
+    editor.newline();
+    editor.toggle_block(BlockKind::Bullet);
+    editor.type_text("two");
+    editor.move_to(4, false);
+
+    editor.delete_back(false);
+    assert_eq!((text(&editor), editor.document().block(1)), ("one\ntwo".to_string(), BlockKind::Paragraph));
+
+    editor.delete_back(false);
+    assert_eq!(text(&editor), "onetwo");
+  }
+
+  #[test]
+  fn finds_the_next_match_wrapping_around_and_replaces_it() {
+    let mut editor = Editor::default();
+    editor.type_text("cat hat cat");
+    editor.move_to(5, false);
+
+    assert!(editor.find_next("cat", true));
+    assert_eq!(editor.selection().range(), 8..11);
+    assert!(editor.find_next("cat", true));
+    assert_eq!(editor.selection().range(), 0..3);
+
+    assert!(editor.replace("cat", "dog", true));
+    assert_eq!(text(&editor), "dog hat cat");
+    assert_eq!(editor.selection().range(), 8..11);
+    assert!(!editor.find_next("cow", true));
+  }
+
+  #[test]
+  fn replaces_every_match_as_one_step() {
+    let mut editor = Editor::default();
+    editor.type_text("a b a b");
+    editor.newline();
+
+    assert_eq!(editor.replace_all("b", "c", true), 2);
+    assert_eq!(text(&editor), "a c a c\n");
+
+    editor.undo();
+    assert_eq!(text(&editor), "a b a b\n");
+    editor.redo();
+    assert_eq!(text(&editor), "a c a c\n");
+  }
+}
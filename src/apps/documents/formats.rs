@@ -0,0 +1,176 @@
+mod markdown;
+mod html;
+
+pub use markdown::*;
+pub use html::*;
+
+use super::{BlockKind, Document, Paragraph};
+
+use serde::{Deserialize, Serialize};
+
+use std::{io, path::Path};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+  // everything the editor keeps, as json
+  Native,
+  Markdown,
+  PlainText,
+  Html
+}
+
+// what a native document file holds
+#[derive(Serialize, Deserialize)]
+struct NativeFile {
+  version: u32,
+  paragraphs: Vec<Paragraph>
+}
+
+impl Format {
+  pub const ALL: [Format; 4] = [Format::Native, Format::Markdown, Format::PlainText, Format::Html];
+  const NATIVE_VERSION: u32 = 1;
+
+  pub fn from_path(path: &Path) -> Option<Format> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Format::ALL.into_iter().find(|format| format.extensions().contains(&extension.as_str()))
+  }
+
+  // the first one is used when writing
+  pub fn extensions(&self) -> &'static [&'static str] {
+    match self {
+      Format::Native    => &["gvpdoc"],
+      Format::Markdown  => &["md", "markdown"],
+      Format::PlainText => &["txt"],
+      Format::Html      => &["html", "htm"]
+    }
+  }
+
+  pub fn extension(&self) -> &'static str {
+    self.extensions()[0]
+  }
+
+  // only native files can fail to read, every other format takes whatever text it is given
+  pub fn import(&self, source: &str) -> io::Result<Document> {
+    let paragraphs = match self {
+      Format::Native    => {
+        let file = serde_json::from_str::<NativeFile>(source)?;
+
+        // only one version so far, anything else is from a newer build
+        if file.version != Format::NATIVE_VERSION {
+          let error = format!("document version {} is not {}", file.version, Format::NATIVE_VERSION);
+          return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+
+        file.paragraphs
+      }
+      Format::Markdown  => markdown_to_paragraphs(source),
+      Format::PlainText => source.lines().map(plain_paragraph).collect(),
+      Format::Html      => html_to_paragraphs(source)
+    };
+
+    Ok(Document::from_paragraphs(&paragraphs))
+  }
+
+  // the title is only used by formats with a place for one
+  pub fn export(&self, document: &Document, title: &str) -> String {
+    let paragraphs = document.all_paragraphs();
+
+    match self {
+      Format::Native    => {
+        let file = NativeFile { version: Format::NATIVE_VERSION, paragraphs };
+        match serde_json::to_string_pretty(&file) {
+          Ok(json)   => json,
+          Err(error) => panic!("failed to serialize document with error: {error}")
+        }
+      }
+      Format::Markdown  => paragraphs_to_markdown(&paragraphs),
+      Format::PlainText => paragraphs_to_plain(&paragraphs),
+      Format::Html      => paragraphs_to_html(&paragraphs, title)
+    }
+  }
+}
+
+fn plain_paragraph(line: &str) -> Paragraph {
+  let mut paragraph = Paragraph::default();
+  paragraph.push(line, Default::default());
+
+  paragraph
+}
+
+// list markers are written out since plain text has no other way to show them
+fn paragraphs_to_plain(paragraphs: &[Paragraph]) -> String {
+  let mut text = String::new();
+  let mut number = 0;
+
+  for (index, paragraph) in paragraphs.iter().enumerate() {
+    if index > 0 {
+      text.push('\n');
+    }
+
+    number = if paragraph.kind == BlockKind::Numbered { number + 1 } else { 0 };
+
+    match paragraph.kind {
+      BlockKind::Bullet   => text.push_str("• "),
+      BlockKind::Numbered => text.push_str(&format!("{number}. ")),
+      _                   => {}
+    }
+
+    text.push_str(&paragraph.text());
+  }
+
+  text
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::gvp_engine::gui::FontStyle;
+
+  const BOLD: FontStyle = FontStyle { bold: true, italic: false };
+
+  fn paragraph(kind: BlockKind, spans: &[(&str, FontStyle)]) -> Paragraph {
+    let mut paragraph = Paragraph::new(kind);
+    for (text, style) in spans {
+      paragraph.push(text, *style);
+    }
+
+    paragraph
+  }
+
+  #[test]
+  fn round_trips_native_documents_whole() {
+    let paragraphs = [
+      paragraph(BlockKind::Heading(2), &[("Title", FontStyle::REGULAR)]),
+      paragraph(BlockKind::Paragraph, &[("plain ", FontStyle::REGULAR), ("bold", BOLD)]),
+      paragraph(BlockKind::Paragraph, &[]),
+      paragraph(BlockKind::Numbered, &[("first", FontStyle::REGULAR)])
+    ];
+
+    let json = Format::Native.export(&Document::from_paragraphs(&paragraphs), "title");
+    assert_eq!(Format::Native.import(&json).unwrap().all_paragraphs(), paragraphs);
+  }
+
+  #[test]
+  fn writes_list_markers_into_plain_text() {
+    let paragraphs = [
+      paragraph(BlockKind::Heading(1), &[("Shopping", FontStyle::REGULAR)]),
+      paragraph(BlockKind::Numbered, &[("eggs", BOLD)]),
+      paragraph(BlockKind::Numbered, &[("milk", FontStyle::REGULAR)]),
+      paragraph(BlockKind::Bullet, &[("later", FontStyle::REGULAR)])
+    ];
+
+    let text = Format::PlainText.export(&Document::from_paragraphs(&paragraphs), "");
+    assert_eq!(text, "Shopping\n1. eggs\n2. milk\n• later");
+
+    let paragraphs = Format::PlainText.import(&text).unwrap().all_paragraphs();
+    assert_eq!(paragraphs.iter().map(Paragraph::text).collect::<Vec<_>>(), ["Shopping", "1. eggs", "2. milk", "• later"]);
+  }
+
+  #[test]
+  fn rejects_native_files_of_other_versions() {
+    let newer = r#"{ "version": 2, "paragraphs": [] }"#;
+
+    assert_eq!(Format::Native.import(newer).err().map(|error| error.kind()), Some(io::ErrorKind::InvalidData));
+    assert!(Format::Native.import(r#"{ "version": 1, "paragraphs": [] }"#).is_ok());
+  }
+}
@@ -0,0 +1,259 @@
+use crate::gvp_engine::gui::FontStyle;
+
+use super::super::{BlockKind, Paragraph};
+
+// a whole page. empty paragraphs keep their line with a break
+pub fn paragraphs_to_html(paragraphs: &[Paragraph], title: &str) -> String {
+  let mut html = format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n", escape(title));
+  let mut list: Option<BlockKind> = None;
+
+  for paragraph in paragraphs {
+    if list.is_some_and(|kind| kind != paragraph.kind) {
+      html.push_str(if list == Some(BlockKind::Numbered) { "</ol>\n" } else { "</ul>\n" });
+      list = None;
+    }
+
+    if paragraph.kind.is_list() && list.is_none() {
+      html.push_str(if paragraph.kind == BlockKind::Numbered { "<ol>\n" } else { "<ul>\n" });
+      list = Some(paragraph.kind);
+    }
+
+    let tag = match paragraph.kind {
+      BlockKind::Heading(level) => format!("h{}", level.clamp(1, 3)),
+      BlockKind::Bullet | BlockKind::Numbered => "li".to_string(),
+      BlockKind::Paragraph      => "p".to_string()
+    };
+
+    let mut content = String::new();
+    for span in &paragraph.spans {
+      let text = escape(&span.text);
+
+      content.push_str(&match (span.style.bold, span.style.italic) {
+        (true, true)   => format!("<strong><em>{text}</em></strong>"),
+        (true, false)  => format!("<strong>{text}</strong>"),
+        (false, true)  => format!("<em>{text}</em>"),
+        (false, false) => text
+      });
+    }
+
+    if content.is_empty() {
+      content.push_str("<br>");
+    }
+
+    html.push_str(&format!("<{tag}>{content}</{tag}>\n"));
+  }
+
+  if let Some(kind) = list {
+    html.push_str(if kind == BlockKind::Numbered { "</ol>\n" } else { "</ul>\n" });
+  }
+
+  html.push_str("</body>\n</html>\n");
+  html
+}
+
+// not a full parser: tags are read for the blocks and inline styles this editor has and everything else is dropped,
+// keeping its text. whitespace collapses the way a browser would show it
+pub fn html_to_paragraphs(source: &str) -> Vec<Paragraph> {
+  let mut paragraphs: Vec<Paragraph> = Vec::new();
+  let mut lists: Vec<BlockKind> = Vec::new();
+  let mut bold = 0u32;
+  let mut italic = 0u32;
+  // inside head, script or style, whose text is not shown
+  let mut hidden = 0u32;
+  // a block ended and the next text starts a new paragraph
+  let mut open = false;
+  let mut rest = source;
+
+  while !rest.is_empty() {
+    let Some(start) = rest.find('<') else {
+      push_text(&mut paragraphs, &mut open, rest, hidden, bold, italic);
+      break;
+    };
+
+    push_text(&mut paragraphs, &mut open, &rest[..start], hidden, bold, italic);
+
+    // comments may hold a > of their own
+    if rest[start..].starts_with("<!--") {
+      rest = rest[start..].find("-->").map_or("", |end| &rest[start + end + 3..]);
+      continue;
+    }
+
+    let Some(end) = rest[start..].find('>') else { break; };
+    let tag = &rest[start + 1..start + end];
+    rest = &rest[start + end + 1..];
+
+    let closing = tag.starts_with('/');
+    let name: String = tag.trim_start_matches('/').chars().take_while(|character| character.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase();
+
+    match (name.as_str(), closing) {
+      ("head" | "script" | "style", false)   => hidden += 1,
+      ("head" | "script" | "style", true)    => hidden = hidden.saturating_sub(1),
+      ("b" | "strong", false)                => bold += 1,
+      ("b" | "strong", true)                 => bold = bold.saturating_sub(1),
+      ("i" | "em", false)                    => italic += 1,
+      ("i" | "em", true)                     => italic = italic.saturating_sub(1),
+      ("ul", false)                          => lists.push(BlockKind::Bullet),
+      ("ol", false)                          => lists.push(BlockKind::Numbered),
+      ("ul" | "ol", true)                    => { lists.pop(); open = false; }
+      ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+        let level = name[1..].parse::<u8>().unwrap_or(1).min(3);
+        start_block(&mut paragraphs, &mut open, BlockKind::Heading(level));
+      }
+      ("li", false)                          => {
+        let kind = lists.last().copied().unwrap_or(BlockKind::Bullet);
+        start_block(&mut paragraphs, &mut open, kind);
+      }
+      ("p" | "div", false)                   => {
+        let kind = lists.last().copied().unwrap_or(BlockKind::Paragraph);
+        // a paragraph right inside a list item belongs to the item
+        if !(open && paragraphs.last().is_some_and(|paragraph| paragraph.kind.is_list() && paragraph.is_empty())) {
+          start_block(&mut paragraphs, &mut open, kind);
+        }
+      }
+      // a break starts a paragraph of the same kind as the one it is in, unless it only holds the line of an empty one
+      ("br", _)                              => {
+        let current = paragraphs.last().filter(|_| open);
+
+        if !current.is_some_and(Paragraph::is_empty) {
+          let kind = current.map_or(BlockKind::Paragraph, |paragraph| paragraph.kind);
+          start_block(&mut paragraphs, &mut open, kind);
+        }
+      }
+      ("p" | "div" | "li" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => open = false,
+      _                                      => {}
+    }
+  }
+
+  paragraphs
+}
+
+fn start_block(paragraphs: &mut Vec<Paragraph>, open: &mut bool, kind: BlockKind) {
+  paragraphs.push(Paragraph::new(kind));
+  *open = true;
+}
+
+fn push_text(paragraphs: &mut Vec<Paragraph>, open: &mut bool, text: &str, hidden: u32, bold: u32, italic: u32) {
+  if hidden > 0 { return; }
+
+  let text = collapse(&decode(text));
+  if text.trim().is_empty() && !*open { return; }
+
+  if !*open {
+    start_block(paragraphs, open, BlockKind::Paragraph);
+  }
+
+  if let Some(paragraph) = paragraphs.last_mut() {
+    // leading whitespace of a block is not shown
+    let text = if paragraph.is_empty() { text.trim_start() } else { &text };
+    paragraph.push(text, FontStyle { bold: bold > 0, italic: italic > 0 });
+  }
+}
+
+fn collapse(text: &str) -> String {
+  let mut collapsed = String::with_capacity(text.len());
+  let mut space = false;
+
+  for character in text.chars() {
+    match character.is_whitespace() && character != '\u{a0}' {
+      true  => space = true,
+      false => {
+        if space {
+          collapsed.push(' ');
+          space = false;
+        }
+        collapsed.push(character);
+      }
+    }
+  }
+
+  if space {
+    collapsed.push(' ');
+  }
+
+  collapsed
+}
+
+fn escape(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// the named entities that come up in ordinary text and every numeric one
+fn decode(text: &str) -> String {
+  let mut decoded = String::with_capacity(text.len());
+  let mut rest = text;
+
+  while let Some(start) = rest.find('&') {
+    decoded.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    let entity = rest.find(';').filter(|end| *end <= 10).map(|end| &rest[1..end]);
+    let character = entity.and_then(|entity| match entity {
+      "amp"  => Some('&'),
+      "lt"   => Some('<'),
+      "gt"   => Some('>'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      "nbsp" => Some('\u{a0}'),
+      _      => match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+        None      => entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()).and_then(char::from_u32)
+      }
+    });
+
+    match (character, entity) {
+      (Some(character), Some(entity)) => {
+        decoded.push(character);
+        rest = &rest[entity.len() + 2..];
+      }
+      _                               => {
+        decoded.push('&');
+        rest = &rest[1..];
+      }
+    }
+  }
+
+  decoded.push_str(rest);
+  decoded
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const BOLD: FontStyle = FontStyle { bold: true, italic: false };
+  const BOLD_ITALIC: FontStyle = FontStyle { bold: true, italic: true };
+
+  fn paragraph(kind: BlockKind, spans: &[(&str, FontStyle)]) -> Paragraph {
+    let mut paragraph = Paragraph::new(kind);
+    for (text, style) in spans {
+      paragraph.push(text, *style);
+    }
+
+    paragraph
+  }
+
+  #[test]
+  fn round_trips_blocks_inline_styles_and_escapes() {
+    let paragraphs = [
+      paragraph(BlockKind::Heading(3), &[("Fish & <chips>", FontStyle::REGULAR)]),
+      paragraph(BlockKind::Paragraph, &[("very ", FontStyle::REGULAR), ("bold", BOLD), (" and both", BOLD_ITALIC)]),
+      paragraph(BlockKind::Paragraph, &[]),
+      paragraph(BlockKind::Bullet, &[("one", FontStyle::REGULAR)]),
+      paragraph(BlockKind::Numbered, &[("first", FontStyle::REGULAR)])
+    ];
+
+    let html = paragraphs_to_html(&paragraphs, "a & b");
+    assert!(html.contains("<title>a &amp; b</title>"));
+    assert!(html.contains("<h3>Fish &amp; &lt;chips&gt;</h3>"));
+    assert!(html.contains("<ul>\n<li>one</li>\n</ul>\n<ol>\n<li>first</li>\n</ol>"));
+
+    assert_eq!(html_to_paragraphs(&html), paragraphs);
+  }
+
+  #[test]
+  fn collapses_whitespace_and_keeps_the_text_of_unknown_tags() {
+    let html = "<body><p>  spread\n   <span>out</span>  <a href=\"#\">link</a></p><script>hidden()</script></body>";
+
+    assert_eq!(html_to_paragraphs(html), [paragraph(BlockKind::Paragraph, &[("spread out link", FontStyle::REGULAR)])]);
+  }
+}
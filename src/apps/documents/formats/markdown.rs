@@ -0,0 +1,186 @@
+use crate::gvp_engine::gui::FontStyle;
+
+use super::super::{BlockKind, Paragraph};
+
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag, TagEnd};
+
+// commonmark through pulldown-cmark. nested lists are flattened, headings past the third level become third level
+// ones, and code, links and images keep only their text
+pub fn markdown_to_paragraphs(source: &str) -> Vec<Paragraph> {
+  let mut paragraphs: Vec<Paragraph> = Vec::new();
+  let mut lists: Vec<BlockKind> = Vec::new();
+  // the paragraph of a list item opens with the item, so the item's own paragraph tag must not open another
+  let mut item_open = false;
+  let mut style = FontStyle::REGULAR;
+
+  for event in Parser::new(source) {
+    match event {
+      Event::Start(Tag::Heading { level, .. }) => {
+        let level = match level {
+          HeadingLevel::H1 => 1,
+          HeadingLevel::H2 => 2,
+          _                => 3
+        };
+        paragraphs.push(Paragraph::new(BlockKind::Heading(level)));
+      }
+      Event::Start(Tag::Paragraph)             => {
+        match item_open {
+          true  => item_open = false,
+          false => paragraphs.push(Paragraph::new(BlockKind::Paragraph))
+        }
+      }
+      Event::Start(Tag::List(start))           => lists.push(if start.is_some() { BlockKind::Numbered } else { BlockKind::Bullet }),
+      Event::End(TagEnd::List(_))              => { lists.pop(); }
+      Event::Start(Tag::Item)                  => {
+        paragraphs.push(Paragraph::new(lists.last().copied().unwrap_or(BlockKind::Bullet)));
+        item_open = true;
+      }
+      Event::End(TagEnd::Item)                 => item_open = false,
+      Event::Start(Tag::Strong)                => style.bold = true,
+      Event::End(TagEnd::Strong)               => style.bold = false,
+      Event::Start(Tag::Emphasis)              => style.italic = true,
+      Event::End(TagEnd::Emphasis)             => style.italic = false,
+      Event::Text(text) | Event::Code(text)    => {
+        if paragraphs.is_empty() {
+          paragraphs.push(Paragraph::default());
+        }
+
+        if let Some(paragraph) = paragraphs.last_mut() {
+          paragraph.push(&text, style);
+        }
+      }
+      Event::SoftBreak                         => {
+        if let Some(paragraph) = paragraphs.last_mut() {
+          paragraph.push(" ", style);
+        }
+      }
+      // a break inside a paragraph starts another one of the same kind
+      Event::HardBreak                         => {
+        let kind = paragraphs.last().map_or(BlockKind::Paragraph, |paragraph| paragraph.kind);
+        paragraphs.push(Paragraph::new(kind));
+      }
+      _                                        => {}
+    }
+  }
+
+  paragraphs
+}
+
+// blank lines between blocks except between items of the same list. empty paragraphs have no markdown of their own
+// and are left out
+pub fn paragraphs_to_markdown(paragraphs: &[Paragraph]) -> String {
+  let mut markdown = String::new();
+  let mut previous: Option<BlockKind> = None;
+  let mut number = 0;
+
+  for paragraph in paragraphs.iter().filter(|paragraph| !paragraph.is_empty()) {
+    if let Some(previous) = previous {
+      let same_list = previous == paragraph.kind && paragraph.kind.is_list();
+      markdown.push_str(if same_list { "\n" } else { "\n\n" });
+    }
+
+    number = match (paragraph.kind, previous) {
+      (BlockKind::Numbered, Some(BlockKind::Numbered)) => number + 1,
+      _                                                => 1
+    };
+
+    match paragraph.kind {
+      BlockKind::Heading(level) => markdown.push_str(&format!("{} ", "#".repeat(level.clamp(1, 3) as usize))),
+      BlockKind::Bullet         => markdown.push_str("- "),
+      BlockKind::Numbered       => markdown.push_str(&format!("{number}. ")),
+      BlockKind::Paragraph      => {}
+    }
+
+    for span in &paragraph.spans {
+      markdown.push_str(&styled(&escape(&span.text), span.style));
+    }
+
+    previous = Some(paragraph.kind);
+  }
+
+  markdown.push('\n');
+  markdown
+}
+
+// emphasis markers go inside surrounding whitespace, which commonmark would not accept next to them
+fn styled(text: &str, style: FontStyle) -> String {
+  let marker = match (style.bold, style.italic) {
+    (true, true)   => "***",
+    (true, false)  => "**",
+    (false, true)  => "*",
+    (false, false) => return text.to_string()
+  };
+
+  let trimmed = text.trim();
+  if trimmed.is_empty() { return text.to_string(); }
+
+  let start = text.len() - text.trim_start().len();
+  let end = start + trimmed.len();
+
+  format!("{}{marker}{trimmed}{marker}{}", &text[..start], &text[end..])
+}
+
+// characters that would otherwise be read as markdown
+fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+
+  for (index, character) in text.char_indices() {
+    let at_start = index == 0;
+
+    match character {
+      '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>'   => escaped.push('\\'),
+      '#' | '-' | '+' if at_start                       => escaped.push('\\'),
+      '.' | ')' if text[..index].chars().all(|digit| digit.is_ascii_digit()) && !at_start => escaped.push('\\'),
+      _                                                 => {}
+    }
+
+    escaped.push(character);
+  }
+
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const BOLD: FontStyle = FontStyle { bold: true, italic: false };
+  const ITALIC: FontStyle = FontStyle { bold: false, italic: true };
+
+  fn paragraph(kind: BlockKind, spans: &[(&str, FontStyle)]) -> Paragraph {
+    let mut paragraph = Paragraph::new(kind);
+    for (text, style) in spans {
+      paragraph.push(text, *style);
+    }
+
+    paragraph
+  }
+
+  #[test]
+  fn round_trips_blocks_and_inline_styles() {
+    let paragraphs = [
+      paragraph(BlockKind::Heading(1), &[("Notes", FontStyle::REGULAR)]),
+      paragraph(BlockKind::Paragraph, &[
+        ("some ", FontStyle::REGULAR), ("bold", BOLD), (" and ", FontStyle::REGULAR), ("leaning", ITALIC)
+      ]),
+      paragraph(BlockKind::Bullet, &[("one", FontStyle::REGULAR)]),
+      paragraph(BlockKind::Bullet, &[("two", FontStyle::REGULAR)]),
+      paragraph(BlockKind::Numbered, &[("first", FontStyle::REGULAR)]),
+      paragraph(BlockKind::Numbered, &[("second", FontStyle::REGULAR)])
+    ];
+
+    let markdown = paragraphs_to_markdown(&paragraphs);
+    assert_eq!(markdown, "# Notes\n\nsome **bold** and *leaning*\n\n- one\n- two\n\n1. first\n2. second\n");
+    assert_eq!(markdown_to_paragraphs(&markdown), paragraphs);
+  }
+
+  #[test]
+  fn escapes_text_that_would_read_as_markdown() {
+    let paragraphs = [
+      paragraph(BlockKind::Paragraph, &[("# not a heading", FontStyle::REGULAR)]),
+      paragraph(BlockKind::Paragraph, &[("1. not a list, *not* emphasis [or a link]", FontStyle::REGULAR)])
+    ];
+
+    assert_eq!(markdown_to_paragraphs(&paragraphs_to_markdown(&paragraphs)), paragraphs);
+  }
+}
@@ -0,0 +1,124 @@
+use super::{Document, Selection};
+
+use std::time::{Duration, Instant};
+
+// what an edit did, so that runs of typing or deleting undo together
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditKind {
+  Typing,
+  Deleting,
+  Other
+}
+
+// the rope shares its unchanged parts between clones, so keeping whole documents is cheap
+struct Snapshot {
+  document: Document,
+  selection: Selection
+}
+
+#[derive(Default)]
+pub struct History {
+  undo: Vec<Snapshot>,
+  redo: Vec<Snapshot>,
+  last: Option<(EditKind, Instant)>
+}
+
+impl History {
+  const LIMIT: usize = 500;
+  // typing or deleting within this long of the last of the same kind joins its step
+  const MERGE_WINDOW: Duration = Duration::from_secs(1);
+
+  // takes the state from before an edit
+  pub fn record(&mut self, document: &Document, selection: Selection, kind: EditKind) {
+    let now = Instant::now();
+    let merge = kind != EditKind::Other && self.last.is_some_and(|(last, time)| last == kind && now - time < History::MERGE_WINDOW);
+
+    self.last = Some((kind, now));
+    self.redo.clear();
+
+    if merge { return; }
+
+    self.undo.push(Snapshot { document: document.clone(), selection });
+    if self.undo.len() > History::LIMIT {
+      self.undo.remove(0);
+    }
+  }
+
+  // the state to go back to, given the current one to redo to
+  pub fn undo(&mut self, document: &Document, selection: Selection) -> Option<(Document, Selection)> {
+    let snapshot = self.undo.pop()?;
+
+    self.redo.push(Snapshot { document: document.clone(), selection });
+    self.last = None;
+
+    Some((snapshot.document, snapshot.selection))
+  }
+
+  pub fn redo(&mut self, document: &Document, selection: Selection) -> Option<(Document, Selection)> {
+    let snapshot = self.redo.pop()?;
+
+    self.undo.push(Snapshot { document: document.clone(), selection });
+    self.last = None;
+
+    Some((snapshot.document, snapshot.selection))
+  }
+
+  pub fn clear(&mut self) {
+    self.undo.clear();
+    self.redo.clear();
+    self.last = None;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::gvp_engine::gui::FontStyle;
+
+  fn document(text: &str) -> Document {
+    let mut document = Document::new();
+    document.insert(0, text, FontStyle::REGULAR);
+
+    document
+  }
+
+  fn text(document: &Document) -> String {
+    document.slice(0..document.len_chars())
+  }
+
+  #[test]
+  fn merges_runs_of_typing_or_deleting_into_one_step() {
+    let mut history = History::default();
+    let selection = Selection::default();
+
+    history.record(&document(""), selection, EditKind::Typing);
+    history.record(&document("a"), selection, EditKind::Typing);
+    history.record(&document("ab"), selection, EditKind::Deleting);
+    history.record(&document("a"), selection, EditKind::Deleting);
+
+    let (back, _) = history.undo(&document(""), selection).unwrap();
+    assert_eq!(text(&back), "ab");
+
+    let (back, _) = history.undo(&back, selection).unwrap();
+    assert_eq!(text(&back), "");
+    assert!(history.undo(&back, selection).is_none());
+
+    let (forward, _) = history.redo(&back, selection).unwrap();
+    assert_eq!(text(&forward), "ab");
+  }
+
+  #[test]
+  fn keeps_other_edits_apart_and_forgets_redo_on_a_new_edit() {
+    let mut history = History::default();
+    let selection = Selection::caret(1);
+
+    history.record(&document(""), Selection::default(), EditKind::Other);
+    history.record(&document("x"), selection, EditKind::Other);
+
+    let (back, restored) = history.undo(&document("xy"), Selection::caret(2)).unwrap();
+    assert_eq!((text(&back), restored), ("x".to_string(), selection));
+
+    history.record(&back, selection, EditKind::Typing);
+    assert!(history.redo(&document("xz"), Selection::caret(2)).is_none());
+  }
+}
@@ -0,0 +1,202 @@
+use super::{Document, Format};
+
+use std::{
+  fs, io,
+  path::{Path, PathBuf}
+};
+
+// native documents in a directory, by name without the extension. autosaves go to a recovery directory beside them
+// and are only read back when they are newer than the document they belong to
+pub struct DocumentStore {
+  directory: PathBuf
+}
+
+impl DocumentStore {
+  const RECOVERY: &str = ".recovery";
+
+  pub fn new(directory: &Path) -> Self {
+    DocumentStore { directory: directory.to_path_buf() }
+  }
+
+  // sorted by name, empty when the directory does not exist yet
+  pub fn list(&self) -> Vec<String> {
+    let mut names: Vec<String> = match fs::read_dir(&self.directory) {
+      Ok(entries) => entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| Format::from_path(path) == Some(Format::Native))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect(),
+      Err(_)      => Vec::new()
+    };
+
+    names.sort_by_key(|name| name.to_lowercase());
+    names
+  }
+
+  // saved, or only autosaved so far
+  pub fn exists(&self, name: &str) -> bool {
+    self.path(name).exists() || self.recovery_path(name).exists()
+  }
+
+  // a name not taken yet, counting up from the base
+  pub fn unused_name(&self, base: &str) -> String {
+    (1..).map(|number| if number == 1 { base.to_string() } else { format!("{base} {number}") })
+      .find(|name| !self.exists(name))
+      .unwrap_or_default()
+  }
+
+  // the recovered copy when a newer one was left behind, which is reported so it can be saved over the document
+  pub fn open(&self, name: &str) -> io::Result<(Document, bool)> {
+    let path = self.path(name);
+    let recovery = self.recovery_path(name);
+
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let recovered = match (modified(&recovery), modified(&path)) {
+      (Some(recovery), Some(saved)) => recovery > saved,
+      (Some(_), None)               => true,
+      _                             => false
+    };
+
+    let source = fs::read_to_string(if recovered { &recovery } else { &path })?;
+    Ok((Format::Native.import(&source)?, recovered))
+  }
+
+  // written beside the document and moved over it, so a crash leaves either the old file or the new one. the
+  // recovery copy is no longer needed after
+  pub fn save(&self, name: &str, document: &Document) -> io::Result<()> {
+    DocumentStore::write(&self.path(name), &Format::Native.export(document, name))?;
+
+    match fs::remove_file(self.recovery_path(name)) {
+      Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+      _                                                      => Ok(())
+    }
+  }
+
+  pub fn autosave(&self, name: &str, document: &Document) -> io::Result<()> {
+    DocumentStore::write(&self.recovery_path(name), &Format::Native.export(document, name))
+  }
+
+  // any format, picked by extension. the name is the file's own
+  pub fn import(&self, path: &Path) -> io::Result<(String, Document)> {
+    let format = Format::from_path(path).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unknown document format"))?;
+    let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("Imported").to_string();
+
+    Ok((name, format.import(&fs::read_to_string(path)?)?))
+  }
+
+  // next to the native documents, returning where it went
+  pub fn export(&self, name: &str, document: &Document, format: Format) -> io::Result<PathBuf> {
+    let path = self.directory.join(DocumentStore::file_name(name, format));
+    DocumentStore::write(&path, &format.export(document, name))?;
+
+    Ok(path)
+  }
+
+  fn path(&self, name: &str) -> PathBuf {
+    self.directory.join(DocumentStore::file_name(name, Format::Native))
+  }
+
+  fn recovery_path(&self, name: &str) -> PathBuf {
+    self.directory.join(DocumentStore::RECOVERY).join(DocumentStore::file_name(name, Format::Native))
+  }
+
+  // names are typed or taken from imported files, so separators are replaced to keep them inside the directory
+  fn file_name(name: &str, format: Format) -> String {
+    let name: String = name.chars()
+      .map(|character| if matches!(character, '/' | '\\' | ':') || character.is_control() { '_' } else { character })
+      .collect();
+
+    format!("{name}.{}", format.extension())
+  }
+
+  fn write(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::gvp_engine::gui::FontStyle;
+
+  use std::{process, time::{Duration, SystemTime}};
+
+  fn document(text: &str) -> Document {
+    let mut document = Document::new();
+    document.insert(0, text, FontStyle::REGULAR);
+
+    document
+  }
+
+  fn text(document: &Document) -> String {
+    document.slice(0..document.len_chars())
+  }
+
+  fn store(name: &str) -> (DocumentStore, PathBuf) {
+    let directory = std::env::temp_dir().join(format!("{name}_{}", process::id()));
+    let _ = fs::remove_dir_all(&directory);
+
+    (DocumentStore::new(&directory), directory)
+  }
+
+  fn opened(store: &DocumentStore, name: &str) -> (String, bool) {
+    let (document, recovered) = store.open(name).unwrap();
+    (text(&document), recovered)
+  }
+
+  // file times are set outright so the test does not depend on how finely the file system keeps them
+  fn touch(path: &Path, seconds: u64) {
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+
+    if let Err(error) = fs::File::options().write(true).open(path).and_then(|file| file.set_modified(time)) {
+      panic!("failed to set the modification time of {} with error: {error}", path.display())
+    }
+  }
+
+  #[test]
+  fn recovers_autosaves_newer_than_the_saved_document() {
+    let (store, directory) = store("documents_recovery");
+
+    store.autosave("note", &document("only autosaved")).unwrap();
+    assert!(store.exists("note"));
+    assert_eq!(opened(&store, "note"), ("only autosaved".to_string(), true));
+
+    store.save("note", &document("saved")).unwrap();
+    store.autosave("note", &document("autosaved")).unwrap();
+    touch(&store.path("note"), 1000);
+
+    touch(&store.recovery_path("note"), 900);
+    assert_eq!(opened(&store, "note"), ("saved".to_string(), false));
+
+    touch(&store.recovery_path("note"), 1100);
+    assert_eq!(opened(&store, "note"), ("autosaved".to_string(), true));
+
+    // saving takes the recovered changes in and lets go of the copy
+    store.save("note", &document("autosaved")).unwrap();
+    assert!(!store.recovery_path("note").exists());
+    assert_eq!(store.list(), ["note"]);
+
+    let _ = fs::remove_dir_all(directory);
+  }
+
+  #[test]
+  fn keeps_names_inside_the_directory() {
+    let (store, directory) = store("documents_names");
+
+    store.save("../escaped", &document("text")).unwrap();
+    store.export("a/b", &document("text"), Format::PlainText).unwrap();
+
+    assert!(!directory.with_file_name("escaped.gvpdoc").exists());
+    assert!(directory.join("a_b.txt").exists());
+    assert_eq!(store.list(), [".._escaped"]);
+    assert_eq!(store.unused_name("../escaped"), "../escaped 2");
+
+    let _ = fs::remove_dir_all(directory);
+  }
+}
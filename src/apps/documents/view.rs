@@ -0,0 +1,223 @@
+use crate::gvp_engine::{
+  gui::{Canvas, Rect, Theme},
+  text::{Text, TextLayout, TextStyle}
+};
+
+use super::{BlockKind, Editor};
+
+use glam::Vec2;
+
+use std::ops::Range;
+
+// a paragraph laid out on the page. its layout indexes bytes of its text, the editor indexes characters of the
+// document
+struct ViewLine {
+  text: String,
+  kind: BlockKind,
+  layout: TextLayout,
+  // document characters the paragraph covers, without its line break
+  range: Range<usize>,
+  // from the top of the page
+  top: f32,
+  indent: f32,
+  size: f32
+}
+
+// the editor's document laid out in a column and drawn with its selection. paragraphs are only laid out again when
+// their text, kind or the page width changed
+#[derive(Default)]
+pub struct DocumentView {
+  lines: Vec<ViewLine>,
+  revision: Option<u64>,
+  width: f32,
+  height: f32,
+  // pixels from the top of the page to the top of the view
+  scroll: f32
+}
+
+impl DocumentView {
+  pub const FONT_SIZE: f32 = 26.0;
+  const MARGIN: f32 = 32.0;
+  const CARET_WIDTH: f32 = 2.0;
+
+  pub fn font_size(kind: BlockKind) -> f32 {
+    match kind {
+      BlockKind::Heading(1) => DocumentView::FONT_SIZE * 1.85,
+      BlockKind::Heading(2) => DocumentView::FONT_SIZE * 1.45,
+      BlockKind::Heading(_) => DocumentView::FONT_SIZE * 1.2,
+      _                     => DocumentView::FONT_SIZE
+    }
+  }
+
+  // lays the document out for a view of this width if it changed since the last time
+  pub fn update(&mut self, editor: &Editor, text: &Text, width: f32) {
+    if self.revision == Some(editor.revision()) && self.width == width { return; }
+
+    let document = editor.document();
+    let mut previous = match self.width == width {
+      true  => std::mem::take(&mut self.lines),
+      false => Vec::new()
+    };
+
+    let page = (width - DocumentView::MARGIN * 2.0).max(DocumentView::FONT_SIZE);
+    let mut top = DocumentView::MARGIN;
+    let mut lines = Vec::with_capacity(document.len_lines());
+
+    for line in 0..document.len_lines() {
+      let range = document.line_range(line);
+      let kind = document.block(line);
+      let line_text = document.slice(range.clone());
+
+      let size = DocumentView::font_size(kind);
+      let indent = if kind.is_list() { size * 1.6 } else { 0.0 };
+
+      // paragraphs usually stay in order, so the one to reuse is found near the front
+      let layout = match previous.iter().position(|old| old.kind == kind && old.text == line_text) {
+        Some(index) => previous.remove(index).layout,
+        None        => text.layout(&line_text, &TextStyle { size, max_width: Some(page - indent), ..TextStyle::default() })
+      };
+
+      let height = layout.size.y.max(text.fonts().line_metrics(size).height);
+      let before = match kind {
+        BlockKind::Heading(_) if line > 0 => size * 0.5,
+        _                                 => 0.0
+      };
+
+      lines.push(ViewLine { text: line_text, kind, layout, range, top: top + before, indent, size });
+      top += before + height + DocumentView::FONT_SIZE * 0.4;
+    }
+
+    self.lines = lines;
+    self.height = top + DocumentView::MARGIN;
+    self.revision = Some(editor.revision());
+    self.width = width;
+  }
+
+  pub fn scroll_by(&mut self, amount: f32, view_height: f32) {
+    self.scroll = (self.scroll + amount).clamp(0.0, (self.height - view_height).max(0.0));
+  }
+
+  // brings the caret into a view of this height
+  pub fn reveal(&mut self, index: usize, view_height: f32) {
+    let Some(caret) = self.caret(index) else { return; };
+
+    if caret.position.y < self.scroll {
+      self.scroll = caret.position.y - DocumentView::MARGIN * 0.5;
+    }
+    else if caret.max().y > self.scroll + view_height {
+      self.scroll = caret.max().y - view_height + DocumentView::MARGIN * 0.5;
+    }
+
+    self.scroll_by(0.0, view_height);
+  }
+
+  // the document character nearest to a point in the view
+  pub fn index_at(&self, point: Vec2) -> usize {
+    self.page_index(point + Vec2::new(0.0, self.scroll))
+  }
+
+  // the character a line above or below, keeping to the same x
+  pub fn step_line(&self, index: usize, down: bool) -> usize {
+    let Some(caret) = self.caret(index) else { return index; };
+    let y = if down { caret.max().y + 1.0 } else { caret.position.y - 1.0 };
+
+    match y {
+      y if y < DocumentView::MARGIN => 0,
+      y if y > self.height - DocumentView::MARGIN => self.lines.last().map_or(index, |line| line.range.end),
+      y => self.page_index(Vec2::new(caret.position.x, y))
+    }
+  }
+
+  fn page_index(&self, point: Vec2) -> usize {
+    let Some(line) = self.lines.iter().rev().find(|line| line.top <= point.y).or(self.lines.first()) else { return 0; };
+
+    let local = point - Vec2::new(DocumentView::MARGIN + line.indent, line.top);
+    let byte = line.layout.index_at(local);
+
+    line.range.start + line.text[..byte].chars().count()
+  }
+
+  // from the top of the page
+  fn caret(&self, index: usize) -> Option<Rect> {
+    let line = self.lines.iter().find(|line| line.range.contains(&index) || line.range.end == index)?;
+    let caret = line.layout.caret(DocumentView::byte(&line.text, index - line.range.start));
+    let position = caret.position + Vec2::new(DocumentView::MARGIN + line.indent, line.top);
+
+    Some(Rect::new(position, Vec2::new(DocumentView::CARET_WIDTH, caret.height.max(line.size))))
+  }
+
+  pub fn paint(&self, canvas: &mut Canvas, rect: Rect, editor: &Editor, theme: &Theme, focused: bool) {
+    canvas.bordered_rect(rect, theme.surface, if focused { theme.focus } else { theme.border }, theme.radius, theme.border_width);
+    canvas.push_clip(rect);
+
+    let document = editor.document();
+    let selection = editor.selection().range();
+    let origin = rect.position + Vec2::new(DocumentView::MARGIN, -self.scroll);
+    let highlight = theme.accent.with_alpha(0.35);
+
+    for line in &self.lines {
+      let top = origin.y + line.top;
+      if top > rect.max().y { break; }
+      if top + line.layout.size.y.max(line.size) < rect.position.y { continue; }
+
+      let position = Vec2::new(origin.x + line.indent, top);
+
+      // the selection, including a sliver for the line break when it reaches past the end of the paragraph
+      let start = selection.start.max(line.range.start);
+      let end = selection.end.min(line.range.end);
+
+      if start < end {
+        let bytes = DocumentView::byte(&line.text, start - line.range.start)..DocumentView::byte(&line.text, end - line.range.start);
+        for selected in line.layout.selection(bytes) {
+          canvas.rect(Rect::new(position + selected.position, selected.size), highlight, 0.0);
+        }
+      }
+
+      if let (true, Some(last)) = (selection.start <= line.range.end && selection.end > line.range.end, line.layout.lines.last()) {
+        canvas.rect(Rect::new(position + Vec2::new(last.end, last.top), Vec2::new(line.size * 0.3, last.height)), highlight, 0.0);
+      }
+
+      match line.kind {
+        BlockKind::Bullet   => {
+          let center = Vec2::new(origin.x + line.indent * 0.45, top + line.layout.lines.first().map_or(line.size, |first| first.height) * 0.5);
+          canvas.circle(center, line.size * 0.12, theme.text);
+        }
+        BlockKind::Numbered => {
+          let marker = format!("{}.", document.list_number(document.line_of(line.range.start)));
+          canvas.text(Vec2::new(origin.x, top), &marker, line.size, theme.text_muted);
+        }
+        _                   => {}
+      }
+
+      // a run for every piece of a wrapped line in one style, placed where the layout put its first character
+      for visual in &line.layout.lines {
+        let first = line.range.start + line.text[..visual.range.start].chars().count();
+        let pieces = document.styles(first..first + line.text[visual.range.clone()].chars().count());
+
+        for (piece, style) in pieces {
+          let bytes = DocumentView::byte(&line.text, piece.start - line.range.start)..DocumentView::byte(&line.text, piece.end - line.range.start);
+          let left = visual.chars.iter()
+            .filter(|char_box| char_box.range.start >= bytes.start && char_box.range.end <= bytes.end)
+            .map(|char_box| char_box.left)
+            .fold(f32::INFINITY, f32::min);
+
+          if left.is_finite() {
+            canvas.styled_text(position + Vec2::new(left, visual.top), &line.text[bytes], line.size, theme.text, style);
+          }
+        }
+      }
+    }
+
+    if focused && selection.is_empty() {
+      if let Some(caret) = self.caret(editor.selection().caret) {
+        canvas.rect(Rect::new(rect.position + caret.position - Vec2::new(0.0, self.scroll), caret.size), theme.focus, 0.0);
+      }
+    }
+
+    canvas.pop_clip();
+  }
+
+  fn byte(text: &str, chars: usize) -> usize {
+    text.char_indices().nth(chars).map_or(text.len(), |(byte, _)| byte)
+  }
+}
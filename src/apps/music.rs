@@ -0,0 +1,568 @@
+mod artwork;
+mod local;
+mod provider;
+mod queue;
+
+pub use artwork::*;
+pub use local::*;
+pub use provider::*;
+pub use queue::*;
+
+use crate::gvp_engine::{
+  app::{AppContext, GVPApp},
+  audio::{Clip, ClipError},
+  compositor::Panel,
+  gui::{Align, Dimension, Gui, GuiEvent, GuiInput, Justify, Style, Theme, Widget, WidgetId},
+  material::Material,
+  primitives::PrimitiveKind,
+  scene::{AudioSource, Collider, Entity, RigidBody},
+  transform::Transform
+};
+
+use glam::{Quat, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::{
+  path::PathBuf,
+  thread::{self, JoinHandle}
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MusicOutput {
+  // out of the speaker box, placed in the room like any other sound
+  #[default]
+  Speaker,
+  // straight into both ears, wherever the speaker is
+  Headphones
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MusicSettings {
+  pub volume: f32,
+  pub output: MusicOutput
+}
+
+impl Default for MusicSettings {
+  fn default() -> Self {
+    MusicSettings { volume: 0.8, output: MusicOutput::Speaker }
+  }
+}
+
+// what the app keeps across sessions. the queue comes back stopped on its track
+#[derive(Serialize, Deserialize)]
+struct MusicState {
+  settings: MusicSettings,
+  queue: Queue
+}
+
+// a track decoding off the main thread
+struct Loading {
+  track: TrackId,
+  decoding: JoinHandle<Result<Clip, ClipError>>
+}
+
+struct Controls {
+  now_playing: WidgetId,
+  elapsed: WidgetId,
+  previous: WidgetId,
+  play: WidgetId,
+  next: WidgetId,
+  shuffle: WidgetId,
+  repeat: WidgetId,
+  output: WidgetId,
+  volume: WidgetId,
+  search: WidgetId,
+  rescan: WidgetId,
+  albums: WidgetId,
+  tracks: WidgetId,
+  queue: WidgetId,
+  status: WidgetId
+}
+
+// plays a library through a speaker box in the room, or through headphones. everything about the music goes through
+// the provider, so the app is the same whether the library is a local folder or a streaming service
+pub struct MusicApp {
+  settings: MusicSettings,
+  provider: Box<dyn MusicProvider>,
+  queue: Queue,
+  gui: Gui,
+  controls: Controls,
+  albums: Vec<Album>,
+  // what the tracks list shows, the album picked and the search narrowing the library down
+  shown: Vec<Track>,
+  album: Option<usize>,
+  speaker: Option<Entity>,
+  artwork: ArtworkHandle,
+  loading: Option<Loading>,
+  // the track the speaker has, and the clip it has it under
+  playing: Option<(TrackId, PathBuf)>,
+  paused: bool,
+  // seconds into the track playing
+  elapsed: f32,
+  // tracks started, which keeps every clip under its own key so starting one over plays it from the top
+  loads: u64,
+  // kept from on_input until render, which is where the gui is updated
+  input: Option<GuiInput>,
+  message: String
+}
+
+impl MusicApp {
+  const SPEAKER: &str = "Music Speaker";
+
+  pub fn new(provider: Box<dyn MusicProvider>) -> Self {
+    let (gui, controls) = MusicApp::build();
+
+    MusicApp {
+      settings: MusicSettings::default(),
+      provider,
+      queue: Queue::new(),
+      gui,
+      controls,
+      albums: Vec::new(),
+      shown: Vec::new(),
+      album: None,
+      speaker: None,
+      artwork: ArtworkHandle::default(),
+      loading: None,
+      playing: None,
+      paused: false,
+      elapsed: 0.0,
+      loads: 0,
+      input: None,
+      message: String::new()
+    }
+  }
+
+  fn build() -> (Gui, Controls) {
+    let mut gui = Gui::new(Theme::dark());
+    let root = Gui::ROOT;
+    let gap = gui.theme.spacing;
+
+    let header = gui.add(root, Widget::container(), Style { gap, justify: Justify::SpaceBetween, ..Style::row() });
+    let now_playing = gui.add(header, Widget::label("Nothing playing"), Style::default());
+    let elapsed = gui.add(header, Widget::label(""), Style::default());
+
+    let transport = gui.add(root, Widget::container(), Style { gap, ..Style::row() });
+    let previous = gui.add(transport, Widget::button("Prev"), Style::default());
+    let play = gui.add(transport, Widget::button("Play"), Style::default());
+    let next = gui.add(transport, Widget::button("Next"), Style::default());
+    let shuffle = gui.add(transport, Widget::checkbox("Shuffle", false), Style::default());
+    let repeat = gui.add(transport, Widget::button(MusicApp::repeat_text(Repeat::Off)), Style::default());
+    let output = gui.add(transport, Widget::button(MusicApp::output_text(MusicOutput::Speaker)), Style::default());
+    let volume = gui.add(transport, Widget::slider(0.8, 0.0, 1.0), Style::fill());
+
+    let find = gui.add(root, Widget::container(), Style { gap, ..Style::row() });
+    let search = gui.add(find, Widget::text_field("Search"), Style::fill());
+    let rescan = gui.add(find, Widget::button("Rescan"), Style::default());
+
+    let body = gui.add(root, Widget::container(), Style { gap, grow: 1.0, align_items: Align::Stretch, ..Style::row() });
+    let column = |gui: &mut Gui, title: &str, style: Style| {
+      let column = gui.add(body, Widget::container(), Style { gap, ..style });
+      gui.add(column, Widget::label(title), Style::default());
+      gui.add(column, Widget::list(Vec::new()), Style::fill())
+    };
+
+    let albums = column(&mut gui, "Albums", Style { width: Dimension::Pixels(300.0), ..Style::column() });
+    let tracks = column(&mut gui, "Tracks", Style { grow: 1.0, ..Style::column() });
+    let queue = column(&mut gui, "Queue", Style { width: Dimension::Pixels(300.0), ..Style::column() });
+
+    let status = gui.add(root, Widget::label(""), Style::default());
+
+    let controls = Controls {
+      now_playing, elapsed, previous, play, next, shuffle, repeat, output, volume, search, rescan, albums, tracks, queue,
+      status
+    };
+
+    (gui, controls)
+  }
+
+  fn repeat_text(repeat: Repeat) -> &'static str {
+    match repeat {
+      Repeat::Off => "Repeat: Off",
+      Repeat::All => "Repeat: All",
+      Repeat::One => "Repeat: One"
+    }
+  }
+
+  fn output_text(output: MusicOutput) -> &'static str {
+    match output {
+      MusicOutput::Speaker    => "Speaker",
+      MusicOutput::Headphones => "Headphones"
+    }
+  }
+
+  fn refresh(&mut self) {
+    self.message = match self.provider.refresh() {
+      Ok(())     => String::new(),
+      Err(error) => error.to_string()
+    };
+
+    self.albums = self.provider.albums();
+    self.album = self.album.filter(|album| *album < self.albums.len());
+
+    let mut items = vec!["All tracks".to_string()];
+    items.extend(self.albums.iter().map(|album| format!("{} - {}", album.title, album.artist)));
+
+    let selected = Some(self.album.map_or(0, |album| album + 1));
+    if let Widget::List { items: current, selected: picked, .. } = self.gui.widget_mut(self.controls.albums) {
+      *current = items;
+      *picked = selected;
+    }
+
+    self.filter();
+  }
+
+  // the picked album's tracks, or the whole library, narrowed down to what the search finds
+  fn filter(&mut self) {
+    let query = match self.gui.widget(self.controls.search) {
+      Widget::TextField { text, .. } => text.clone(),
+      _                              => String::new()
+    };
+
+    let album = self.album.and_then(|album| self.albums.get(album));
+    self.shown = self.provider.search(&query).into_iter()
+      .filter(|track| album.is_none_or(|album| track.album == album.title && track.album_artist == album.artist))
+      .collect();
+
+    let items = self.shown.iter().map(|track| match track.number {
+      Some(number) => format!("{number}. {} - {}", track.title, track.artist),
+      None         => format!("{} - {}", track.title, track.artist)
+    }).collect();
+
+    if let Widget::List { items: current, selected, .. } = self.gui.widget_mut(self.controls.tracks) {
+      *current = items;
+      *selected = None;
+    }
+  }
+
+  fn refresh_queue(&mut self) {
+    let items = self.queue.tracks().iter().map(|track| format!("{} - {}", track.title, track.artist)).collect();
+    let position = self.queue.position();
+
+    if let Widget::List { items: current, selected, .. } = self.gui.widget_mut(self.controls.queue) {
+      *current = items;
+      *selected = position;
+    }
+  }
+
+  // the speaker left from last session if the scene still has it, or a new one by the panel
+  fn find_speaker(&mut self, context: &mut AppContext) -> Entity {
+    if let Some(speaker) = self.speaker.filter(|speaker| context.engine().scene().get::<AudioSource>(*speaker).is_some()) {
+      return speaker;
+    }
+
+    let found = context.engine().scene().find(MusicApp::SPEAKER).filter(|speaker| context.engine().scene().get::<AudioSource>(*speaker).is_some());
+    let speaker = match found {
+      Some(speaker) => speaker,
+      None          => {
+        let transform = Transform::new(Vec3::new(1.9, 1.0, -0.6), Quat::IDENTITY, Vec3::new(0.3, 0.5, 0.3));
+        let speaker = context.spawn_primitive(PrimitiveKind::CUBE, transform, Material::color([0.1, 0.1, 0.12, 1.0], 0.0, 0.6));
+        let scene = context.engine().scene_mut();
+
+        scene.insert(speaker, Collider::for_primitive(PrimitiveKind::CUBE));
+        scene.insert(speaker, RigidBody::default());
+        scene.set_name(speaker, MusicApp::SPEAKER);
+
+        speaker
+      }
+    };
+
+    // whatever it was playing belonged to the last session
+    context.engine().scene_mut().insert(speaker, AudioSource { clip: None, looping: false, paused: true, ..AudioSource::default() });
+    self.speaker = Some(speaker);
+    self.playing = None;
+
+    speaker
+  }
+
+  fn source<'a>(&mut self, context: &'a mut AppContext) -> &'a mut AudioSource {
+    let speaker = self.find_speaker(context);
+
+    match context.engine().scene_mut().get_mut::<AudioSource>(speaker) {
+      Some(source) => source,
+      None         => panic!("failed to find music speaker audio source with error: it was just inserted")
+    }
+  }
+
+  // stops what is playing and decodes the queue's current track, which starts once it is ready
+  fn start(&mut self, context: &mut AppContext) {
+    let source = self.source(context);
+    (source.clip, source.paused) = (None, true);
+
+    self.playing = None;
+    self.loading = None;
+    self.elapsed = 0.0;
+    self.refresh_queue();
+
+    let Some(track) = self.queue.current().cloned() else {
+      self.artwork.set(None);
+      return;
+    };
+
+    match self.provider.stream(&track.id) {
+      Ok(stream) => {
+        let decoding = thread::spawn(move || Clip::decode(stream.source, stream.extension.as_deref()));
+        self.loading = Some(Loading { track: track.id.clone(), decoding });
+        self.paused = false;
+        self.message = String::new();
+      }
+      Err(error) => self.message = error.to_string()
+    }
+
+    self.artwork.set(self.provider.artwork(&track.id));
+  }
+
+  // hands a decoded track to the speaker
+  fn loaded(&mut self, context: &mut AppContext) {
+    if !self.loading.as_ref().is_some_and(|loading| loading.decoding.is_finished()) { return; }
+    let Some(loading) = self.loading.take() else { return; };
+
+    let clip = match loading.decoding.join() {
+      Ok(Ok(clip))   => clip,
+      Ok(Err(error)) => return self.message = format!("failed to decode {}: {error}", loading.track.0),
+      Err(_)         => return self.message = format!("failed to decode {}: the decoder panicked", loading.track.0)
+    };
+
+    self.loads += 1;
+    let key = PathBuf::from(format!("{}#{}", loading.track.0, self.loads));
+    context.engine().audio_mut().insert_clip(&key, clip);
+
+    let paused = self.paused;
+    let source = self.source(context);
+    (source.clip, source.paused) = (Some(key.clone()), paused);
+
+    self.playing = Some((loading.track, key));
+  }
+
+  fn toggle(&mut self, context: &mut AppContext) {
+    if self.playing.is_none() && self.loading.is_none() {
+      if self.queue.current().is_none() {
+        self.queue.play(self.shown.clone(), 0);
+      }
+
+      return self.start(context);
+    }
+
+    self.paused = !self.paused;
+    let paused = self.paused;
+    self.source(context).paused = paused;
+  }
+
+  // keeps the speaker playing the way the settings say, and moves the queue on once a track plays to its end
+  fn sync(&mut self, context: &mut AppContext, delta: f32) {
+    let settings = self.settings.clone();
+    let paused = self.paused;
+    let playing = self.playing.as_ref().map(|(_, key)| key.clone());
+    let source = self.source(context);
+
+    source.volume = settings.volume;
+    source.spatial = settings.output == MusicOutput::Speaker;
+
+    let Some(key) = playing else { return; };
+
+    // the audio pauses a source whose clip ended, which the app did not ask for
+    if !paused && source.paused && source.clip.as_ref() == Some(&key) {
+      self.queue.advance();
+      return self.start(context);
+    }
+
+    if !paused {
+      self.elapsed += delta;
+    }
+  }
+
+  fn handle(&mut self, context: &mut AppContext, event: &GuiEvent) {
+    let controls = &self.controls;
+
+    match *event {
+      GuiEvent::Clicked(id) if id == controls.play     => self.toggle(context),
+      GuiEvent::Clicked(id) if id == controls.next     => {
+        self.queue.next();
+        self.start(context);
+      }
+      GuiEvent::Clicked(id) if id == controls.previous => {
+        // a few seconds in, going back starts the track over
+        if self.elapsed < 3.0 {
+          self.queue.previous();
+        }
+
+        self.start(context);
+      }
+      GuiEvent::Toggled(id, shuffle) if id == controls.shuffle => {
+        self.queue.set_shuffle(shuffle);
+        self.refresh_queue();
+      }
+      GuiEvent::Clicked(id) if id == controls.repeat   => {
+        self.queue.repeat = self.queue.repeat.next();
+        let text = MusicApp::repeat_text(self.queue.repeat);
+
+        if let Widget::Button { text: current } = self.gui.widget_mut(self.controls.repeat) {
+          *current = text.to_string();
+        }
+      }
+      GuiEvent::Clicked(id) if id == controls.output   => {
+        self.settings.output = match self.settings.output {
+          MusicOutput::Speaker    => MusicOutput::Headphones,
+          MusicOutput::Headphones => MusicOutput::Speaker
+        };
+        let text = MusicApp::output_text(self.settings.output);
+
+        if let Widget::Button { text: current } = self.gui.widget_mut(self.controls.output) {
+          *current = text.to_string();
+        }
+      }
+      GuiEvent::Changed(id, volume) if id == controls.volume => self.settings.volume = volume,
+      GuiEvent::Edited(id) if id == controls.search    => self.filter(),
+      GuiEvent::Clicked(id) if id == controls.rescan   => self.refresh(),
+      GuiEvent::Selected(id, index) if id == controls.albums => {
+        self.album = index.checked_sub(1);
+        self.filter();
+      }
+      // the list being looked at becomes the queue
+      GuiEvent::Selected(id, index) if id == controls.tracks => {
+        self.queue.play(self.shown.clone(), index);
+        self.start(context);
+      }
+      GuiEvent::Selected(id, index) if id == controls.queue  => {
+        self.queue.select(index);
+        self.start(context);
+      }
+      _                                                => {}
+    }
+  }
+
+  fn now_playing(&self) -> String {
+    match self.queue.current() {
+      Some(track) if self.loading.is_some() => format!("Loading {}", track.title),
+      Some(track)                           => format!("{} - {} - {}", track.title, track.artist, track.album),
+      None                                  => "Nothing playing".to_string()
+    }
+  }
+
+  fn elapsed(&self) -> String {
+    let time = |seconds: f32| format!("{}:{:02}", seconds as u32 / 60, seconds as u32 % 60);
+
+    match (&self.playing, self.queue.current().and_then(|track| track.duration)) {
+      (Some(_), Some(duration)) => format!("{} / {}", time(self.elapsed.min(duration)), time(duration)),
+      (Some(_), None)           => time(self.elapsed),
+      (None, _)                 => String::new()
+    }
+  }
+
+  fn status(&self) -> String {
+    match self.message.is_empty() {
+      true  => format!("{} library, {} tracks", self.provider.name(), self.provider.tracks().len()),
+      false => self.message.clone()
+    }
+  }
+}
+
+impl GVPApp for MusicApp {
+  fn name(&self) -> &str {
+    "Music"
+  }
+
+  fn panel(&self) -> Panel {
+    Panel::new(Transform::new(Vec3::new(1.25, 1.4, -1.0), Quat::from_rotation_y(-0.7), Vec3::ONE), Vec2::new(0.9, 0.6))
+  }
+
+  fn on_open(&mut self, context: &mut AppContext) {
+    if !context.text().fonts().is_empty() {
+      self.gui.set_text_measure(Box::new(context.text().fonts().clone()));
+    }
+
+    // the cover floats above the player
+    let panel = self.panel();
+    let above = panel.transform.rotation * Vec3::new(0.0, panel.size.y * 0.5 + 0.17, 0.0);
+    let cover = Panel::new(Transform::new(panel.transform.translation + above, panel.transform.rotation, Vec3::ONE), Vec2::new(0.3, 0.3));
+    let cover = context.create_panel(cover);
+    context.attach_surface(cover, Box::new(ArtworkSurface::new(self.artwork.clone())));
+
+    self.find_speaker(context);
+    self.refresh();
+    self.refresh_queue();
+
+    let (shuffle, volume) = (self.queue.shuffle(), self.settings.volume);
+    let (repeat, output) = (MusicApp::repeat_text(self.queue.repeat), MusicApp::output_text(self.settings.output));
+
+    if let Widget::Checkbox { checked, .. } = self.gui.widget_mut(self.controls.shuffle) { *checked = shuffle; }
+    if let Widget::Slider { value, .. } = self.gui.widget_mut(self.controls.volume) { *value = volume; }
+    if let Widget::Button { text } = self.gui.widget_mut(self.controls.repeat) { *text = repeat.to_string(); }
+    if let Widget::Button { text } = self.gui.widget_mut(self.controls.output) { *text = output.to_string(); }
+
+    if let Some(track) = self.queue.current() {
+      self.artwork.set(self.provider.artwork(&track.id));
+    }
+  }
+
+  fn on_input(&mut self, _context: &mut AppContext, input: &GuiInput) {
+    self.input = Some(input.clone());
+  }
+
+  fn update(&mut self, context: &mut AppContext, delta: f32) {
+    self.loaded(context);
+    self.sync(context, delta);
+  }
+
+  fn render(&mut self, context: &mut AppContext) {
+    let input = self.input.take().unwrap_or_default();
+    self.gui.update(context.size(), &input);
+
+    for event in self.gui.events().to_vec() {
+      self.handle(context, &event);
+    }
+
+    let playing = self.playing.is_some() && !self.paused || self.loading.is_some();
+    let texts = [
+      (self.controls.now_playing, self.now_playing()),
+      (self.controls.elapsed, self.elapsed()),
+      (self.controls.status, self.status())
+    ];
+
+    for (id, value) in texts {
+      if let Widget::Label { text } = self.gui.widget_mut(id) {
+        *text = value;
+      }
+    }
+
+    if let Widget::Button { text } = self.gui.widget_mut(self.controls.play) {
+      *text = if playing { "Pause" } else { "Play" }.to_string();
+    }
+
+    context.draw(self.gui.paint());
+  }
+
+  // music does not play on from a hidden player
+  fn on_suspend(&mut self, context: &mut AppContext) {
+    if self.playing.is_some() {
+      self.paused = true;
+      self.source(context).paused = true;
+    }
+  }
+
+  // the speaker goes with the player
+  fn on_close(&mut self, context: &mut AppContext) {
+    self.loading = None;
+
+    if let Some(speaker) = self.speaker.take() {
+      context.despawn(speaker);
+    }
+  }
+
+  fn save_state(&self) -> Option<Value> {
+    let state = MusicState { settings: self.settings.clone(), queue: self.queue.clone() };
+    serde_json::to_value(state).ok()
+  }
+
+  fn restore_state(&mut self, state: &Value) {
+    let Ok(state) = serde_json::from_value::<MusicState>(state.clone()) else { return; };
+
+    self.settings = state.settings;
+
+    if state.queue.is_valid() {
+      self.queue = state.queue;
+    }
+  }
+}
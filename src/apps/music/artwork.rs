@@ -0,0 +1,111 @@
+use super::Artwork;
+
+use crate::gvp_engine::surface::{Damage, ExternalSurface, PixelFormat, SurfaceEvent, SurfaceFrame};
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+// the app's end of the cover being shown, which it can swap while the surface belongs to the engine. the count goes up
+// with every change so the surface knows to redraw
+#[derive(Clone, Default)]
+pub struct ArtworkHandle(Arc<Mutex<(Option<Artwork>, u64)>>);
+
+impl ArtworkHandle {
+  pub fn set(&self, artwork: Option<Artwork>) {
+    let mut shared = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+    shared.0 = artwork;
+    shared.1 += 1;
+  }
+}
+
+// the current cover scaled to fill the panel, or a dark square when there is none
+pub struct ArtworkSurface {
+  handle: ArtworkHandle,
+  width: u32,
+  height: u32,
+  pixels: Vec<u8>,
+  // of the cover last drawn, none until the first frame
+  drawn: Option<u64>
+}
+
+impl ArtworkSurface {
+  const EMPTY: [u8; 4] = [28, 28, 32, 255];
+
+  pub fn new(handle: ArtworkHandle) -> Self {
+    ArtworkSurface { handle, width: 0, height: 0, pixels: Vec::new(), drawn: None }
+  }
+
+  // averages the cover's pixels under each of the surface's, which covers shrinking and growing both
+  fn draw(&mut self, artwork: Option<&Artwork>) {
+    let Some(artwork) = artwork.filter(|artwork| artwork.width > 0 && artwork.height > 0) else {
+      for pixel in self.pixels.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&ArtworkSurface::EMPTY);
+      }
+
+      return;
+    };
+
+    let scale_x = artwork.width as f32 / self.width as f32;
+    let scale_y = artwork.height as f32 / self.height as f32;
+
+    for y in 0..self.height {
+      let top = (y as f32 * scale_y) as u32;
+      let bottom = (((y + 1) as f32 * scale_y) as u32).clamp(top + 1, artwork.height);
+
+      for x in 0..self.width {
+        let left = (x as f32 * scale_x) as u32;
+        let right = (((x + 1) as f32 * scale_x) as u32).clamp(left + 1, artwork.width);
+        let mut sum = [0u32; 4];
+
+        for source_y in top..bottom {
+          for source_x in left..right {
+            let index = ((source_y * artwork.width + source_x) * 4) as usize;
+
+            for (total, channel) in sum.iter_mut().zip(&artwork.pixels[index..index + 4]) {
+              *total += *channel as u32;
+            }
+          }
+        }
+
+        let count = (bottom - top) * (right - left);
+        let index = ((y * self.width + x) * 4) as usize;
+
+        for (pixel, total) in self.pixels[index..index + 4].iter_mut().zip(sum) {
+          *pixel = (total / count) as u8;
+        }
+      }
+    }
+  }
+}
+
+impl ExternalSurface for ArtworkSurface {
+  fn resize(&mut self, width: u32, height: u32) {
+    self.width = width;
+    self.height = height;
+    self.pixels = vec![0; (width * height * 4) as usize];
+    self.drawn = None;
+  }
+
+  fn frame(&mut self, _delta: f32) -> Option<SurfaceFrame<'_>> {
+    if self.width == 0 || self.height == 0 { return None; }
+
+    let handle = self.handle.clone();
+    let shared = handle.0.lock().unwrap_or_else(PoisonError::into_inner);
+    if self.drawn == Some(shared.1) { return None; }
+
+    // covers with too few pixels for their size are left out rather than read past
+    let artwork = shared.0.as_ref().filter(|artwork| artwork.pixels.len() >= (artwork.width * artwork.height * 4) as usize);
+    self.draw(artwork);
+    self.drawn = Some(shared.1);
+
+    Some(SurfaceFrame {
+      pixels: &self.pixels,
+      width: self.width,
+      height: self.height,
+      stride: self.width * 4,
+      format: PixelFormat::Rgba8,
+      damage: Damage::Full
+    })
+  }
+
+  fn input(&mut self, _event: &SurfaceEvent) {}
+}
@@ -0,0 +1,170 @@
+use super::{Artwork, MusicError, MusicProvider, Track, TrackId, TrackStream};
+
+use symphonia::core::{
+  formats::FormatOptions,
+  io::MediaSourceStream,
+  meta::{MetadataOptions, StandardTagKey, StandardVisualKey, Tag, Visual},
+  probe::Hint
+};
+
+use std::{
+  fs::{self, File},
+  io,
+  path::{Path, PathBuf}
+};
+
+// what the start of a music file says about it
+struct Metadata {
+  tags: Vec<Tag>,
+  visuals: Vec<Visual>,
+  duration: Option<f32>
+}
+
+// a folder of music files, and every folder below it, indexed from the tags in them. files without tags are named
+// after themselves and the folder they are in
+pub struct LocalProvider {
+  directory: PathBuf,
+  tracks: Vec<Track>
+}
+
+impl LocalProvider {
+  const EXTENSIONS: [&str; 3] = ["wav", "ogg", "flac"];
+  // looked for beside tracks without a picture in their tags, as jpg or png
+  const COVERS: [&str; 3] = ["cover", "folder", "front"];
+
+  pub fn new(directory: &Path) -> Self {
+    LocalProvider { directory: directory.to_path_buf(), tracks: Vec::new() }
+  }
+
+  fn files(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+      let path = entry?.path();
+
+      if path.is_dir() {
+        LocalProvider::files(&path, files)?;
+      }
+      else if path.extension().and_then(|extension| extension.to_str()).is_some_and(|extension| LocalProvider::EXTENSIONS.contains(&extension.to_lowercase().as_str())) {
+        files.push(path);
+      }
+    }
+
+    Ok(())
+  }
+
+  fn index(path: &Path) -> Option<Track> {
+    let metadata = LocalProvider::metadata(path)?;
+    let tag = |key: StandardTagKey| metadata.tags.iter().rev()
+      .find(|tag| tag.std_key == Some(key))
+      .map(|tag| tag.value.to_string().trim().to_string())
+      .filter(|value| !value.is_empty());
+
+    let name = |path: Option<&Path>| path.and_then(Path::file_stem).and_then(|name| name.to_str()).unwrap_or_default().to_string();
+    let artist = tag(StandardTagKey::Artist).unwrap_or_else(|| "Unknown Artist".to_string());
+
+    Some(Track {
+      id: TrackId(path.to_string_lossy().into_owned()),
+      title: tag(StandardTagKey::TrackTitle).unwrap_or_else(|| name(Some(path))),
+      album: tag(StandardTagKey::Album).unwrap_or_else(|| name(path.parent())),
+      album_artist: tag(StandardTagKey::AlbumArtist).unwrap_or_else(|| artist.clone()),
+      artist,
+      // often written as the number out of how many there are
+      number: tag(StandardTagKey::TrackNumber).and_then(|number| number.split('/').next()?.trim().parse().ok()),
+      duration: metadata.duration
+    })
+  }
+
+  // only the headers are read, from the container and the stream both, with the stream's coming last
+  fn metadata(path: &Path) -> Option<Metadata> {
+    let file = File::open(path).ok()?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+      hint.with_extension(extension);
+    }
+
+    let mut probed = symphonia::default::get_probe().format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default()).ok()?;
+    let mut metadata = Metadata { tags: Vec::new(), visuals: Vec::new(), duration: None };
+
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|log| log.current()) {
+      metadata.tags.extend_from_slice(revision.tags());
+      metadata.visuals.extend_from_slice(revision.visuals());
+    }
+
+    if let Some(revision) = probed.format.metadata().current() {
+      metadata.tags.extend_from_slice(revision.tags());
+      metadata.visuals.extend_from_slice(revision.visuals());
+    }
+
+    let parameters = &probed.format.default_track()?.codec_params;
+    metadata.duration = parameters.n_frames.zip(parameters.sample_rate).map(|(frames, rate)| frames as f32 / rate as f32);
+
+    Some(metadata)
+  }
+
+  fn decode(data: &[u8]) -> Option<Artwork> {
+    let image = image::load_from_memory(data).ok()?.to_rgba8();
+    Some(Artwork { width: image.width(), height: image.height(), pixels: image.into_raw() })
+  }
+}
+
+impl MusicProvider for LocalProvider {
+  fn name(&self) -> &str {
+    "Local"
+  }
+
+  // a directory that does not exist yet is an empty library
+  fn refresh(&mut self) -> Result<(), MusicError> {
+    let mut files = Vec::new();
+
+    match LocalProvider::files(&self.directory, &mut files) {
+      Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(MusicError::Library(error.to_string())),
+      _                                                      => {}
+    }
+
+    self.tracks = files.iter().filter_map(|path| LocalProvider::index(path)).collect();
+    self.tracks.sort_by_cached_key(|track| {
+      (track.album_artist.to_lowercase(), track.album.to_lowercase(), track.number.unwrap_or(u32::MAX), track.title.to_lowercase())
+    });
+
+    Ok(())
+  }
+
+  fn tracks(&self) -> Vec<Track> {
+    self.tracks.clone()
+  }
+
+  fn stream(&self, track: &TrackId) -> Result<TrackStream, MusicError> {
+    if !self.tracks.iter().any(|indexed| indexed.id == *track) {
+      return Err(MusicError::NotFound(track.clone()));
+    }
+
+    let path = Path::new(&track.0);
+    let file = File::open(path).map_err(|error| MusicError::Stream(error.to_string()))?;
+
+    Ok(TrackStream {
+      source: Box::new(file),
+      extension: path.extension().and_then(|extension| extension.to_str()).map(String::from)
+    })
+  }
+
+  // the front cover in the tags, any picture in them otherwise, or a cover image in the track's folder
+  fn artwork(&self, track: &TrackId) -> Option<Artwork> {
+    let path = Path::new(&track.0);
+
+    if let Some(metadata) = LocalProvider::metadata(path) {
+      let visual = metadata.visuals.iter().find(|visual| visual.usage == Some(StandardVisualKey::FrontCover)).or(metadata.visuals.first());
+
+      if let Some(artwork) = visual.and_then(|visual| LocalProvider::decode(&visual.data)) {
+        return Some(artwork);
+      }
+    }
+
+    let folder = path.parent()?;
+
+    LocalProvider::COVERS.iter()
+      .flat_map(|name| ["jpg", "jpeg", "png"].map(|extension| folder.join(format!("{name}.{extension}"))))
+      .filter(|cover| cover.exists())
+      .find_map(|cover| LocalProvider::decode(&fs::read(cover).ok()?))
+  }
+}
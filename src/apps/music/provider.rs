@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use symphonia::core::io::MediaSource;
+
+use std::fmt;
+
+// means something to the provider it came from only: a path for the local folder, a catalog id for a service
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TrackId(pub String);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+  pub id: TrackId,
+  pub title: String,
+  pub artist: String,
+  pub album: String,
+  // who the album is by, which is the artist unless it is a compilation
+  pub album_artist: String,
+  pub number: Option<u32>,
+  // in seconds, when it is known without decoding the track
+  pub duration: Option<f32>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Album {
+  pub title: String,
+  pub artist: String,
+  pub tracks: Vec<Track>
+}
+
+// rgba, 8 bits per channel, rows packed tightly
+#[derive(Clone, Debug, PartialEq)]
+pub struct Artwork {
+  pub width: u32,
+  pub height: u32,
+  pub pixels: Vec<u8>
+}
+
+// the encoded audio of a track, whole or as it arrives. the player decodes it off the main thread
+pub struct TrackStream {
+  pub source: Box<dyn MediaSource>,
+  // the one a file of it would have, which helps tell what it is encoded as
+  pub extension: Option<String>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MusicError {
+  // the library could not be read or reached
+  Library(String),
+  NotFound(TrackId),
+  Stream(String)
+}
+
+impl fmt::Display for MusicError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      MusicError::Library(error) => write!(formatter, "could not read the library: {error}"),
+      MusicError::NotFound(id)   => write!(formatter, "no track {}", id.0),
+      MusicError::Stream(error)  => write!(formatter, "could not stream the track: {error}")
+    }
+  }
+}
+
+// where the player's music comes from. the player only goes through this, so a streaming service can stand in for the
+// local folder without the player changing
+pub trait MusicProvider {
+  fn name(&self) -> &str;
+
+  // brings the library up to date, by scanning it again or fetching it
+  fn refresh(&mut self) -> Result<(), MusicError>;
+
+  // every track in the library, sorted the way it should be browsed
+  fn tracks(&self) -> Vec<Track>;
+
+  // the tracks grouped by the album and album artist they are tagged with, in the order they first come up
+  fn albums(&self) -> Vec<Album> {
+    let mut albums = Vec::<Album>::new();
+
+    for track in self.tracks() {
+      match albums.iter_mut().find(|album| album.title == track.album && album.artist == track.album_artist) {
+        Some(album) => album.tracks.push(track),
+        None        => albums.push(Album { title: track.album.clone(), artist: track.album_artist.clone(), tracks: vec![track] })
+      }
+    }
+
+    albums
+  }
+
+  // tracks with every word of the query somewhere in their title, artist or album, whatever the case. a service
+  // would rather ask its own search
+  fn search(&self, query: &str) -> Vec<Track> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+
+    self.tracks().into_iter().filter(|track| {
+      let text = format!("{} {} {}", track.title, track.artist, track.album).to_lowercase();
+      words.iter().all(|word| text.contains(word.as_str()))
+    }).collect()
+  }
+
+  fn stream(&self, track: &TrackId) -> Result<TrackStream, MusicError>;
+
+  // the cover of the track's album, if it has one
+  fn artwork(&self, track: &TrackId) -> Option<Artwork>;
+}
@@ -0,0 +1,317 @@
+use super::Track;
+
+use serde::{Deserialize, Serialize};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Repeat {
+  #[default]
+  Off,
+  // the whole queue, starting over once it ends
+  All,
+  // the current track, until skipped
+  One
+}
+
+impl Repeat {
+  // the one after it, the order a repeat button goes through them in
+  pub fn next(self) -> Repeat {
+    match self {
+      Repeat::Off => Repeat::All,
+      Repeat::All => Repeat::One,
+      Repeat::One => Repeat::Off
+    }
+  }
+}
+
+// the tracks lined up to play, in the order they were added and in the order they play, which is another when
+// shuffling
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Queue {
+  tracks: Vec<Track>,
+  // indices into the tracks
+  order: Vec<usize>,
+  // into the order. none before playing and once the end is played past
+  position: Option<usize>,
+  shuffle: bool,
+  pub repeat: Repeat,
+  #[serde(skip, default = "Queue::seed")]
+  seed: u64
+}
+
+impl Queue {
+  pub fn new() -> Self {
+    Queue { tracks: Vec::new(), order: Vec::new(), position: None, shuffle: false, repeat: Repeat::Off, seed: Queue::seed() }
+  }
+
+  // replaces the queue, starting at the track at the index. shuffling puts it first and the rest after it
+  pub fn play(&mut self, tracks: Vec<Track>, start: usize) {
+    self.tracks = tracks;
+    self.order = (0..self.tracks.len()).collect();
+    self.position = (start < self.tracks.len()).then_some(start);
+
+    if self.shuffle {
+      self.reshuffle();
+    }
+  }
+
+  pub fn current(&self) -> Option<&Track> {
+    self.position.map(|position| &self.tracks[self.order[position]])
+  }
+
+  // in the order they play
+  pub fn tracks(&self) -> Vec<&Track> {
+    self.order.iter().map(|index| &self.tracks[*index]).collect()
+  }
+
+  pub fn position(&self) -> Option<usize> {
+    self.position
+  }
+
+  pub fn shuffle(&self) -> bool {
+    self.shuffle
+  }
+
+  // the order plays every track once and the position is in it, which a queue read back from saved state may not do
+  pub fn is_valid(&self) -> bool {
+    let mut seen = vec![false; self.tracks.len()];
+    let every_track = self.order.len() == self.tracks.len()
+      && self.order.iter().all(|index| *index < seen.len() && !std::mem::replace(&mut seen[*index], true));
+
+    every_track && self.position.is_none_or(|position| position < self.order.len())
+  }
+
+  // moves on once the current track has played to its end. repeating one plays it again
+  pub fn advance(&mut self) -> Option<&Track> {
+    match self.repeat {
+      Repeat::One => self.current(),
+      _           => self.next()
+    }
+  }
+
+  // skips to the next track. past the last one the queue starts over when repeating, and stops otherwise
+  pub fn next(&mut self) -> Option<&Track> {
+    let next = self.position.map_or(0, |position| position + 1);
+
+    if next < self.order.len() {
+      self.position = Some(next);
+    }
+    else if self.repeat != Repeat::Off && !self.order.is_empty() {
+      // another lap, in another order
+      self.position = None;
+
+      if self.shuffle {
+        self.reshuffle();
+      }
+
+      self.position = Some(0);
+    }
+    else {
+      self.position = None;
+    }
+
+    self.current()
+  }
+
+  // goes back a track, to the last one when repeating the whole queue and staying at the first otherwise
+  pub fn previous(&mut self) -> Option<&Track> {
+    self.position = match self.position {
+      Some(0) if self.repeat == Repeat::All => self.order.len().checked_sub(1),
+      Some(position)                        => Some(position.saturating_sub(1)),
+      None                                  => self.order.len().checked_sub(1)
+    };
+
+    self.current()
+  }
+
+  // jumps to the track at the index in the play order
+  pub fn select(&mut self, index: usize) -> Option<&Track> {
+    if index < self.order.len() {
+      self.position = Some(index);
+    }
+
+    self.current()
+  }
+
+  // the current track keeps playing either way. shuffling puts the rest after it in a random order, and stopping
+  // puts them back in the order they were added
+  pub fn set_shuffle(&mut self, shuffle: bool) {
+    if shuffle == self.shuffle { return; }
+    self.shuffle = shuffle;
+
+    match shuffle {
+      true  => self.reshuffle(),
+      false => {
+        self.position = self.position.map(|position| self.order[position]);
+        self.order = (0..self.tracks.len()).collect();
+      }
+    }
+  }
+
+  // fisher-yates over everything but the current track, which goes first
+  fn reshuffle(&mut self) {
+    let current = self.position.map(|position| self.order[position]);
+    let mut order: Vec<usize> = (0..self.tracks.len()).filter(|index| Some(*index) != current).collect();
+
+    for index in (1..order.len()).rev() {
+      let other = (self.random() % (index as u64 + 1)) as usize;
+      order.swap(index, other);
+    }
+
+    if let Some(current) = current {
+      order.insert(0, current);
+      self.position = Some(0);
+    }
+
+    self.order = order;
+  }
+
+  // xorshift, which is plenty for picking what plays next
+  fn random(&mut self) -> u64 {
+    self.seed ^= self.seed << 13;
+    self.seed ^= self.seed >> 7;
+    self.seed ^= self.seed << 17;
+    self.seed
+  }
+
+  // never zero, which xorshift would stay at
+  fn seed() -> u64 {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+    time | 1
+  }
+}
+
+impl Default for Queue {
+  fn default() -> Self {
+    Queue::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::apps::music::TrackId;
+
+  fn tracks(count: usize) -> Vec<Track> {
+    (0..count).map(|index| Track {
+      id: TrackId(index.to_string()),
+      title: format!("track {index}"),
+      artist: String::new(),
+      album: String::new(),
+      album_artist: String::new(),
+      number: Some(index as u32 + 1),
+      duration: None
+    }).collect()
+  }
+
+  fn seeded(seed: u64) -> Queue {
+    Queue { seed, ..Queue::new() }
+  }
+
+  fn titles(queue: &Queue) -> Vec<String> {
+    queue.tracks().iter().map(|track| track.title.clone()).collect()
+  }
+
+  fn current(queue: &Queue) -> Option<String> {
+    queue.current().map(|track| track.title.clone())
+  }
+
+  #[test]
+  fn shuffles_the_rest_after_the_current_track_and_puts_them_back() {
+    let mut queue = seeded(42);
+    queue.play(tracks(8), 3);
+    queue.set_shuffle(true);
+
+    assert_eq!(queue.position(), Some(0));
+    assert_eq!(current(&queue).as_deref(), Some("track 3"));
+    assert!(queue.is_valid());
+
+    // the same seed shuffles the same way
+    let mut again = seeded(42);
+    again.play(tracks(8), 3);
+    again.set_shuffle(true);
+    assert_eq!(titles(&queue), titles(&again));
+
+    let added: Vec<String> = (0..8).map(|index| format!("track {index}")).collect();
+    assert_ne!(titles(&queue), added);
+
+    queue.set_shuffle(false);
+    assert_eq!(titles(&queue), added);
+    assert_eq!(queue.position(), Some(3));
+  }
+
+  #[test]
+  fn stops_past_the_end_unless_repeating() {
+    let mut queue = seeded(1);
+    queue.play(tracks(2), 1);
+    assert_eq!(current(&queue).as_deref(), Some("track 1"));
+
+    queue.repeat = Repeat::One;
+    assert_eq!(queue.advance().map(|track| track.title.as_str()), Some("track 1"));
+
+    queue.repeat = Repeat::All;
+    assert_eq!(queue.advance().map(|track| track.title.as_str()), Some("track 0"));
+
+    queue.select(1);
+    queue.repeat = Repeat::Off;
+    assert_eq!(queue.advance(), None);
+    assert_eq!(queue.position(), None);
+  }
+
+  #[test]
+  fn starts_another_shuffled_lap_when_repeating() {
+    let mut queue = seeded(7);
+    queue.repeat = Repeat::All;
+    queue.set_shuffle(true);
+    queue.play(tracks(5), 0);
+
+    for _ in 0..4 {
+      queue.next();
+    }
+
+    assert!(queue.next().is_some());
+    assert_eq!(queue.position(), Some(0));
+    assert!(queue.is_valid());
+  }
+
+  #[test]
+  fn goes_back_to_the_last_track_only_when_repeating_all() {
+    let mut queue = seeded(1);
+    queue.play(tracks(3), 0);
+
+    assert_eq!(queue.previous().map(|track| track.title.as_str()), Some("track 0"));
+
+    queue.repeat = Repeat::All;
+    assert_eq!(queue.previous().map(|track| track.title.as_str()), Some("track 2"));
+    assert_eq!(queue.previous().map(|track| track.title.as_str()), Some("track 1"));
+
+    queue.repeat = Repeat::Off;
+    queue.select(2);
+    queue.next();
+    assert_eq!(queue.previous().map(|track| track.title.as_str()), Some("track 2"));
+  }
+
+  #[test]
+  fn rejects_saved_queues_pointing_past_their_tracks() {
+    let mut queue = seeded(1);
+    queue.play(tracks(3), 2);
+
+    let mut state = serde_json::to_value(&queue).unwrap();
+    assert!(serde_json::from_value::<Queue>(state.clone()).unwrap().is_valid());
+
+    state["position"] = 3.into();
+    assert!(!serde_json::from_value::<Queue>(state.clone()).unwrap().is_valid());
+
+    state["position"] = 0.into();
+    state["order"] = serde_json::json!([0, 1, 3]);
+    assert!(!serde_json::from_value::<Queue>(state.clone()).unwrap().is_valid());
+
+    state["order"] = serde_json::json!([0, 1, 1]);
+    assert!(!serde_json::from_value::<Queue>(state.clone()).unwrap().is_valid());
+
+    state["order"] = serde_json::json!([0, 1]);
+    assert!(!serde_json::from_value::<Queue>(state).unwrap().is_valid());
+  }
+}
@@ -1,5 +1,32 @@
 pub mod engine;
+pub mod primitives;
+pub mod transform;
+pub mod material;
+pub mod scene;
+pub mod texture;
+pub mod input;
+pub mod gesture;
+pub mod gaze;
+pub mod compositor;
+pub mod gui;
+pub mod text;
+pub mod app;
+pub mod plugin;
+pub mod surface;
+pub mod physics;
+pub mod audio;
+pub mod renderer;
+pub mod performance;
 
 mod window;
 mod gpu;
-mod renderer;
\ No newline at end of file
+mod allocator;
+mod mesh;
+mod bvh;
+mod acceleration;
+mod model;
+mod shader;
+mod camera;
+mod ibl;
+mod environment;
+mod xr;
\ No newline at end of file
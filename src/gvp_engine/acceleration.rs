@@ -0,0 +1,486 @@
+use crate::gvp_engine::{
+  allocator::{as_bytes, Allocator, Buffer},
+  bvh::MeshBvh,
+  gpu::GPU
+};
+
+use ash::{khr::acceleration_structure, vk};
+use glam::{Mat4, Vec3};
+
+use std::{collections::HashMap, ffi::c_void, mem::size_of};
+
+// a mesh's triangles in the order its hierarchy stores them, compacted once built
+struct BottomLevel {
+  structure: vk::AccelerationStructureKHR,
+  buffer: Buffer,
+  address: vk::DeviceAddress
+}
+
+// the instances of one frame in flight, built from scratch when the meshes they use change and refit otherwise
+struct TopLevel {
+  structure: vk::AccelerationStructureKHR,
+  buffer: Buffer,
+  instances: Buffer,
+  instances_address: vk::DeviceAddress,
+  pointer: *mut c_void,
+  scratch: Buffer,
+  scratch_address: vk::DeviceAddress,
+  // instances the buffers have room for
+  capacity: u32,
+  count: u32,
+  // the bottom levels the structure was last built over, which a refit has to keep to. none until it is first built
+  built: Option<Vec<vk::DeviceAddress>>,
+  mode: vk::BuildAccelerationStructureModeKHR
+}
+
+// hardware acceleration structures for ray queries: a bottom level per mesh, built once and compacted, and a top level
+// over the instances every frame. only made when the gpu supports ray queries, everything traced falls back to the
+// hierarchies built on the cpu otherwise
+pub struct AccelerationStructures {
+  loader: acceleration_structure::Device,
+  scratch_alignment: vk::DeviceSize,
+  // by the id of the mesh hierarchy they were built from
+  bottom_levels: HashMap<u64, BottomLevel>,
+  frames: Vec<Option<TopLevel>>
+}
+
+impl AccelerationStructures {
+  // top levels start with room for this many instances and double from there
+  const MIN_INSTANCES: u32 = 64;
+
+  pub fn new(instance: &ash::Instance, device: &ash::Device, gpu: &GPU, frame_count: usize) -> Option<Self> {
+    if !gpu.ray_query { return None; }
+
+    let mut acceleration_properties = vk::PhysicalDeviceAccelerationStructurePropertiesKHR::default();
+    let mut properties = vk::PhysicalDeviceProperties2::default().push_next(&mut acceleration_properties);
+
+    unsafe { instance.get_physical_device_properties2(gpu.device, &mut properties) };
+
+    Some(AccelerationStructures {
+      loader: acceleration_structure::Device::new(instance, device),
+      scratch_alignment: (acceleration_properties.min_acceleration_structure_scratch_offset_alignment as vk::DeviceSize).max(1),
+      bottom_levels: HashMap::new(),
+      frames: (0..frame_count).map(|_| None).collect()
+    })
+  }
+
+  // frees every structure, which are built again the next time they are needed. the device must be idle
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    for (_, bottom_level) in self.bottom_levels.drain() {
+      unsafe { self.loader.destroy_acceleration_structure(bottom_level.structure, None) };
+      allocator.destroy_buffer(device, &bottom_level.buffer);
+    }
+
+    for top_level in self.frames.iter_mut().filter_map(Option::take) {
+      AccelerationStructures::destroy_top_level(&self.loader, device, allocator, &top_level);
+    }
+  }
+
+  // builds bottom levels for the meshes without one and drops those of meshes not given. the device must be idle
+  // whenever a mesh is no longer given, since frames in flight may still be tracing it
+  pub fn prepare_meshes(&mut self, device: &ash::Device, allocator: &Allocator, meshes: &[&MeshBvh]) {
+    let stale: Vec<u64> = {
+      self.bottom_levels.keys().filter(|id| !meshes.iter().any(|mesh| mesh.id == **id)).copied().collect()
+    };
+
+    for id in stale {
+      if let Some(bottom_level) = self.bottom_levels.remove(&id) {
+        unsafe { self.loader.destroy_acceleration_structure(bottom_level.structure, None) };
+        allocator.destroy_buffer(device, &bottom_level.buffer);
+      }
+    }
+
+    // meshes without triangles have nothing to build, their instances are left inactive
+    let missing: Vec<&MeshBvh> = meshes.iter()
+      .filter(|mesh| !mesh.triangles.is_empty() && !self.bottom_levels.contains_key(&mesh.id))
+      .copied()
+      .collect();
+
+    if !missing.is_empty() {
+      self.build_bottom_levels(device, allocator, &missing);
+    }
+  }
+
+  // makes sure the frame's top level has room for the instances. returns true when it was made again, which waits
+  // for the device first
+  pub fn prepare_frame(&mut self, device: &ash::Device, allocator: &Allocator, frame_index: usize, count: usize) -> bool {
+    let count = count as u32;
+    if self.frames[frame_index].as_ref().is_some_and(|top_level| top_level.capacity >= count) { return false; }
+
+    if let Err(error) = unsafe { device.device_wait_idle() } {
+      panic!("failed to wait for device idle with error: {error}")
+    }
+
+    if let Some(top_level) = self.frames[frame_index].take() {
+      AccelerationStructures::destroy_top_level(&self.loader, device, allocator, &top_level);
+    }
+
+    let capacity = count.next_power_of_two().max(AccelerationStructures::MIN_INSTANCES);
+    self.frames[frame_index] = Some(self.create_top_level(device, allocator, capacity));
+
+    true
+  }
+
+  pub fn top_level(&self, frame_index: usize) -> Option<vk::AccelerationStructureKHR> {
+    self.frames[frame_index].as_ref().map(|top_level| top_level.structure)
+  }
+
+  // writes the frame's instances once its fence has been waited on, each with its index as custom data for the shader
+  // to look it up by. the next record builds over them, or refits when they use the same meshes as last time
+  pub fn write_instances(&mut self, frame_index: usize, instances: &[(u64, Mat4)]) {
+    let Some(top_level) = &mut self.frames[frame_index] else { return; };
+    let count = instances.len().min(top_level.capacity as usize);
+
+    let flags = vk::GeometryInstanceFlagsKHR::TRIANGLE_FACING_CULL_DISABLE | vk::GeometryInstanceFlagsKHR::FORCE_OPAQUE;
+
+    let references: Vec<vk::DeviceAddress> = instances[..count].iter().map(|(mesh, _)| {
+      self.bottom_levels.get(mesh).map_or(0, |bottom_level| bottom_level.address)
+    }).collect();
+
+    let written: Vec<vk::AccelerationStructureInstanceKHR> = instances[..count].iter().zip(&references).enumerate().map(|(index, ((_, transform), reference))| {
+      // the top three rows of the transform one after another
+      let rows = transform.transpose().to_cols_array();
+      let mut matrix = [0.0; 12];
+      matrix.copy_from_slice(&rows[..12]);
+
+      vk::AccelerationStructureInstanceKHR {
+        transform: vk::TransformMatrixKHR { matrix },
+        instance_custom_index_and_mask: vk::Packed24_8::new(index as u32, 0xff),
+        instance_shader_binding_table_record_offset_and_flags: vk::Packed24_8::new(0, flags.as_raw() as u8),
+        // a null reference leaves the instance inactive
+        acceleration_structure_reference: vk::AccelerationStructureReferenceKHR { device_handle: *reference }
+      }
+    }).collect();
+
+    let bytes = as_bytes(&written);
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), top_level.pointer as *mut u8, bytes.len()) };
+
+    top_level.mode = match top_level.built.as_ref() == Some(&references) {
+      true  => vk::BuildAccelerationStructureModeKHR::UPDATE,
+      false => vk::BuildAccelerationStructureModeKHR::BUILD
+    };
+
+    top_level.count = count as u32;
+    top_level.built = Some(references);
+  }
+
+  // builds or refits the frame's top level from the instances last written, finished before any compute shader reads it
+  pub fn record(&self, device: &ash::Device, command_buffer: vk::CommandBuffer, frame_index: usize) {
+    let Some(top_level) = &self.frames[frame_index] else { return; };
+    let geometries = [AccelerationStructures::instance_geometry(top_level.instances_address)];
+
+    let mut build_info = {
+      vk::AccelerationStructureBuildGeometryInfoKHR::default()
+        .ty(vk::AccelerationStructureTypeKHR::TOP_LEVEL)
+        .flags(AccelerationStructures::top_level_flags())
+        .mode(top_level.mode)
+        .dst_acceleration_structure(top_level.structure)
+        .geometries(&geometries)
+        .scratch_data(vk::DeviceOrHostAddressKHR { device_address: top_level.scratch_address })
+    };
+
+    if top_level.mode == vk::BuildAccelerationStructureModeKHR::UPDATE {
+      build_info = build_info.src_acceleration_structure(top_level.structure);
+    }
+
+    let ranges = [vk::AccelerationStructureBuildRangeInfoKHR::default().primitive_count(top_level.count)];
+
+    let barrier = {
+      vk::MemoryBarrier::default()
+        .src_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_WRITE_KHR)
+        .dst_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_READ_KHR)
+    };
+
+    unsafe {
+      self.loader.cmd_build_acceleration_structures(command_buffer, &[build_info], &[&ranges]);
+      device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_KHR,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::DependencyFlags::empty(),
+        &[barrier],
+        &[],
+        &[]
+      )
+    };
+  }
+
+  // every mesh's triangles are uploaded as plain vertices and built together, then compacted into structures of the
+  // size the build turned out to need
+  fn build_bottom_levels(&mut self, device: &ash::Device, allocator: &Allocator, meshes: &[&MeshBvh]) {
+    let usage = vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR;
+
+    let vertex_buffers: Vec<Buffer> = meshes.iter().map(|mesh| {
+      let vertices: Vec<[f32; 3]> = mesh.triangles.iter().flat_map(|triangle| {
+        let corner = Vec3::from_slice(&triangle.position[..3]);
+        let [first, second] = triangle.edges.map(|edge| corner + Vec3::from_slice(&edge[..3]));
+
+        [corner, first, second].map(|vertex| vertex.to_array())
+      }).collect();
+
+      allocator.upload_buffer(device, as_bytes(&vertices), usage)
+    }).collect();
+
+    let geometries: Vec<[vk::AccelerationStructureGeometryKHR; 1]> = meshes.iter().zip(&vertex_buffers).map(|(mesh, buffer)| {
+      let triangles = {
+        vk::AccelerationStructureGeometryTrianglesDataKHR::default()
+          .vertex_format(vk::Format::R32G32B32_SFLOAT)
+          .vertex_data(vk::DeviceOrHostAddressConstKHR { device_address: allocator.buffer_address(device, buffer) })
+          .vertex_stride(size_of::<[f32; 3]>() as vk::DeviceSize)
+          .max_vertex(mesh.triangles.len() as u32 * 3 - 1)
+          .index_type(vk::IndexType::NONE_KHR)
+      };
+
+      [
+        vk::AccelerationStructureGeometryKHR::default()
+          .geometry_type(vk::GeometryTypeKHR::TRIANGLES)
+          .geometry(vk::AccelerationStructureGeometryDataKHR { triangles })
+          .flags(vk::GeometryFlagsKHR::OPAQUE)
+      ]
+    }).collect();
+
+    let flags = vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE | vk::BuildAccelerationStructureFlagsKHR::ALLOW_COMPACTION;
+    let mut built = Vec::<(vk::AccelerationStructureKHR, Buffer, Buffer)>::new();
+    let mut build_infos = Vec::<vk::AccelerationStructureBuildGeometryInfoKHR>::new();
+
+    for (mesh, geometry) in meshes.iter().zip(&geometries) {
+      let build_info = {
+        vk::AccelerationStructureBuildGeometryInfoKHR::default()
+          .ty(vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL)
+          .flags(flags)
+          .mode(vk::BuildAccelerationStructureModeKHR::BUILD)
+          .geometries(geometry)
+      };
+
+      let sizes = self.build_sizes(&build_info, mesh.triangles.len() as u32);
+      let (structure, buffer) = self.create_structure(device, allocator, vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL, sizes.acceleration_structure_size);
+      let (scratch, scratch_address) = self.create_scratch(device, allocator, sizes.build_scratch_size);
+
+      build_infos.push(
+        build_info
+          .dst_acceleration_structure(structure)
+          .scratch_data(vk::DeviceOrHostAddressKHR { device_address: scratch_address })
+      );
+
+      built.push((structure, buffer, scratch));
+    }
+
+    let ranges: Vec<[vk::AccelerationStructureBuildRangeInfoKHR; 1]> = {
+      meshes.iter().map(|mesh| [vk::AccelerationStructureBuildRangeInfoKHR::default().primitive_count(mesh.triangles.len() as u32)]).collect()
+    };
+
+    let range_slices: Vec<&[vk::AccelerationStructureBuildRangeInfoKHR]> = ranges.iter().map(|range| &range[..]).collect();
+    let structures: Vec<vk::AccelerationStructureKHR> = built.iter().map(|(structure, _, _)| *structure).collect();
+
+    let query_info = {
+      vk::QueryPoolCreateInfo::default()
+        .query_type(vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR)
+        .query_count(structures.len() as u32)
+    };
+
+    let query_pool = match unsafe { device.create_query_pool(&query_info, None) } {
+      Ok(query_pool) => query_pool,
+      Err(error)     => panic!("failed to create acceleration structure query pool with error: {error}")
+    };
+
+    let loader = &self.loader;
+
+    allocator.submit(device, |command_buffer| {
+      // the sizes are only written once every build is done
+      let barrier = {
+        vk::MemoryBarrier::default()
+          .src_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_WRITE_KHR)
+          .dst_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_READ_KHR)
+      };
+
+      unsafe {
+        device.cmd_reset_query_pool(command_buffer, query_pool, 0, structures.len() as u32);
+        loader.cmd_build_acceleration_structures(command_buffer, &build_infos, &range_slices);
+        device.cmd_pipeline_barrier(
+          command_buffer,
+          vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_KHR,
+          vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_KHR,
+          vk::DependencyFlags::empty(),
+          &[barrier],
+          &[],
+          &[]
+        );
+        loader.cmd_write_acceleration_structures_properties(
+          command_buffer,
+          &structures,
+          vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR,
+          query_pool,
+          0
+        );
+      };
+    });
+
+    let mut compacted_sizes = vec![0u64; structures.len()];
+
+    if let Err(error) = unsafe {
+      device.get_query_pool_results(query_pool, 0, &mut compacted_sizes, vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT)
+    } {
+      panic!("failed to get compacted acceleration structure sizes with error: {error}")
+    }
+
+    let compacted: Vec<(vk::AccelerationStructureKHR, Buffer)> = compacted_sizes.iter().map(|size| {
+      self.create_structure(device, allocator, vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL, *size)
+    }).collect();
+
+    allocator.submit(device, |command_buffer| {
+      for (source, (destination, _)) in structures.iter().zip(&compacted) {
+        let copy_info = {
+          vk::CopyAccelerationStructureInfoKHR::default()
+            .src(*source)
+            .dst(*destination)
+            .mode(vk::CopyAccelerationStructureModeKHR::COMPACT)
+        };
+
+        unsafe { loader.cmd_copy_acceleration_structure(command_buffer, &copy_info) };
+      }
+    });
+
+    unsafe { device.destroy_query_pool(query_pool, None) };
+
+    for ((structure, buffer, scratch), vertex_buffer) in built.iter().zip(&vertex_buffers) {
+      unsafe { self.loader.destroy_acceleration_structure(*structure, None) };
+
+      for buffer in [buffer, scratch, vertex_buffer] {
+        allocator.destroy_buffer(device, buffer);
+      }
+    }
+
+    for (mesh, (structure, buffer)) in meshes.iter().zip(compacted) {
+      let address = self.structure_address(structure);
+      self.bottom_levels.insert(mesh.id, BottomLevel { structure, buffer, address });
+    }
+  }
+
+  fn create_top_level(&self, device: &ash::Device, allocator: &Allocator, capacity: u32) -> TopLevel {
+    let instances = allocator.create_buffer(
+      device,
+      capacity as vk::DeviceSize * size_of::<vk::AccelerationStructureInstanceKHR>() as vk::DeviceSize,
+      vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR,
+      vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+    );
+
+    // instances stay mapped for the lifetime of the top level
+    let pointer = allocator.map(device, &instances);
+    let instances_address = allocator.buffer_address(device, &instances);
+
+    let geometries = [AccelerationStructures::instance_geometry(instances_address)];
+    let build_info = {
+      vk::AccelerationStructureBuildGeometryInfoKHR::default()
+        .ty(vk::AccelerationStructureTypeKHR::TOP_LEVEL)
+        .flags(AccelerationStructures::top_level_flags())
+        .mode(vk::BuildAccelerationStructureModeKHR::BUILD)
+        .geometries(&geometries)
+    };
+
+    let sizes = self.build_sizes(&build_info, capacity);
+    let (structure, buffer) = self.create_structure(device, allocator, vk::AccelerationStructureTypeKHR::TOP_LEVEL, sizes.acceleration_structure_size);
+    let (scratch, scratch_address) = self.create_scratch(device, allocator, sizes.build_scratch_size.max(sizes.update_scratch_size));
+
+    TopLevel {
+      structure,
+      buffer,
+      instances,
+      instances_address,
+      pointer,
+      scratch,
+      scratch_address,
+      capacity,
+      count: 0,
+      built: None,
+      mode: vk::BuildAccelerationStructureModeKHR::BUILD
+    }
+  }
+
+  fn destroy_top_level(loader: &acceleration_structure::Device, device: &ash::Device, allocator: &Allocator, top_level: &TopLevel) {
+    unsafe {
+      loader.destroy_acceleration_structure(top_level.structure, None);
+      device.unmap_memory(top_level.instances.memory);
+    };
+
+    for buffer in [&top_level.buffer, &top_level.instances, &top_level.scratch] {
+      allocator.destroy_buffer(device, buffer);
+    }
+  }
+
+  // top levels are refit every frame the meshes they use stay the same
+  fn top_level_flags() -> vk::BuildAccelerationStructureFlagsKHR {
+    vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE | vk::BuildAccelerationStructureFlagsKHR::ALLOW_UPDATE
+  }
+
+  fn instance_geometry<'a>(address: vk::DeviceAddress) -> vk::AccelerationStructureGeometryKHR<'a> {
+    let instances = {
+      vk::AccelerationStructureGeometryInstancesDataKHR::default()
+        .data(vk::DeviceOrHostAddressConstKHR { device_address: address })
+    };
+
+    vk::AccelerationStructureGeometryKHR::default()
+      .geometry_type(vk::GeometryTypeKHR::INSTANCES)
+      .geometry(vk::AccelerationStructureGeometryDataKHR { instances })
+      .flags(vk::GeometryFlagsKHR::OPAQUE)
+  }
+
+  fn build_sizes(&self, build_info: &vk::AccelerationStructureBuildGeometryInfoKHR, count: u32) -> vk::AccelerationStructureBuildSizesInfoKHR<'static> {
+    let mut sizes = vk::AccelerationStructureBuildSizesInfoKHR::default();
+
+    unsafe {
+      self.loader.get_acceleration_structure_build_sizes(
+        vk::AccelerationStructureBuildTypeKHR::DEVICE,
+        build_info,
+        &[count],
+        &mut sizes
+      )
+    };
+
+    sizes
+  }
+
+  fn create_structure(
+    &self,
+    device: &ash::Device,
+    allocator: &Allocator,
+    ty: vk::AccelerationStructureTypeKHR,
+    size: vk::DeviceSize
+  ) -> (vk::AccelerationStructureKHR, Buffer) {
+    let buffer = allocator.create_buffer(
+      device,
+      size,
+      vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+      vk::MemoryPropertyFlags::DEVICE_LOCAL
+    );
+
+    let create_info = {
+      vk::AccelerationStructureCreateInfoKHR::default()
+        .buffer(buffer.buffer)
+        .size(size)
+        .ty(ty)
+    };
+
+    match unsafe { self.loader.create_acceleration_structure(&create_info, None) } {
+      Ok(structure) => (structure, buffer),
+      Err(error)    => panic!("failed to create acceleration structure with error: {error}")
+    }
+  }
+
+  // scratch addresses have to be aligned, so the buffer is made large enough to start at the next aligned address
+  fn create_scratch(&self, device: &ash::Device, allocator: &Allocator, size: vk::DeviceSize) -> (Buffer, vk::DeviceAddress) {
+    let buffer = allocator.create_buffer(
+      device,
+      size + self.scratch_alignment,
+      vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+      vk::MemoryPropertyFlags::DEVICE_LOCAL
+    );
+
+    let address = allocator.buffer_address(device, &buffer).next_multiple_of(self.scratch_alignment);
+    (buffer, address)
+  }
+
+  fn structure_address(&self, structure: vk::AccelerationStructureKHR) -> vk::DeviceAddress {
+    let info = vk::AccelerationStructureDeviceAddressInfoKHR::default().acceleration_structure(structure);
+    unsafe { self.loader.get_acceleration_structure_device_address(&info) }
+  }
+}
@@ -0,0 +1,436 @@
+use crate::gvp_engine::gpu::GPU;
+
+use ash::vk;
+
+use std::{collections::HashMap, ffi::c_void, mem::size_of_val, slice, sync::{Mutex, MutexGuard}};
+
+pub struct Buffer {
+  pub buffer: vk::Buffer,
+  pub memory: vk::DeviceMemory,
+  pub size: vk::DeviceSize
+}
+
+pub struct Image {
+  pub image: vk::Image,
+  pub memory: vk::DeviceMemory,
+  pub view: vk::ImageView,
+  pub format: vk::Format,
+  pub extent: vk::Extent3D,
+  pub mip_levels: u32,
+  pub array_layers: u32
+}
+
+// what the allocator has taken from one of the gpu's memory heaps
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HeapUsage {
+  pub used: vk::DeviceSize,
+  pub size: vk::DeviceSize,
+  // the gpu's own memory, as opposed to memory it reaches over the bus
+  pub device_local: bool
+}
+
+pub struct Allocator {
+  memory_properties: vk::PhysicalDeviceMemoryProperties,
+  queue: vk::Queue,
+  // the pool is shared by every upload so it has to be locked while a command buffer is recorded and submitted
+  command_pool: Mutex<vk::CommandPool>,
+  // the heap and size of every allocation still alive, for reporting usage
+  allocations: Mutex<HashMap<vk::DeviceMemory, (usize, vk::DeviceSize)>>
+}
+
+// reinterprets plain vertex / index / pixel data as bytes for uploading
+pub fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+  unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, size_of_val(data)) }
+}
+
+impl Allocator {
+  pub fn new(instance: &ash::Instance, device: &ash::Device, gpu: &GPU) -> Self {
+    let memory_properties = unsafe { instance.get_physical_device_memory_properties(gpu.device) };
+    let main_queue = gpu.main_queue();
+
+    let create_info = {
+      vk::CommandPoolCreateInfo::default()
+        .flags(vk::CommandPoolCreateFlags::TRANSIENT | vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+        .queue_family_index(main_queue.index as u32)
+    };
+
+    let command_pool = match unsafe { device.create_command_pool(&create_info, None) } {
+      Ok(command_pool)  => command_pool,
+      Err(error)        => panic!("failed to create allocator command pool with error: {error}")
+    };
+
+    Allocator {
+      memory_properties,
+      queue: main_queue.queue,
+      command_pool: Mutex::new(command_pool),
+      allocations: Mutex::new(HashMap::new())
+    }
+  }
+
+  pub fn clean(&mut self, device: &ash::Device) {
+    let command_pool = match self.command_pool.lock() {
+      Ok(command_pool)  => command_pool,
+      Err(error)        => panic!("failed to lock allocator command pool with error: {error}")
+    };
+
+    unsafe { device.destroy_command_pool(*command_pool, None) };
+  }
+
+  pub fn create_buffer(
+    &self,
+    device: &ash::Device,
+    size: vk::DeviceSize,
+    usage: vk::BufferUsageFlags,
+    properties: vk::MemoryPropertyFlags
+  ) -> Buffer {
+    let create_info = {
+      vk::BufferCreateInfo::default()
+        .size(size)
+        .usage(usage)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+    };
+
+    let buffer = match unsafe { device.create_buffer(&create_info, None) } {
+      Ok(buffer) => buffer,
+      Err(error) => panic!("failed to create buffer with error: {error}")
+    };
+
+    // buffers read through their address, like acceleration structure inputs, need memory that hands one out
+    let flags = match usage.contains(vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS) {
+      true  => vk::MemoryAllocateFlags::DEVICE_ADDRESS,
+      false => vk::MemoryAllocateFlags::empty()
+    };
+
+    let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+    let memory = self.allocate(device, &requirements, properties, flags);
+
+    if let Err(error) = unsafe { device.bind_buffer_memory(buffer, memory, 0) } {
+      panic!("failed to bind buffer memory with error: {error}")
+    }
+
+    Buffer {
+      buffer,
+      memory,
+      size
+    }
+  }
+
+  // creates a device local buffer and fills it through a temporary staging buffer. empty data still gets a buffer of a
+  // byte since vulkan has no empty buffers, with nothing copied into it
+  pub fn upload_buffer(&self, device: &ash::Device, data: &[u8], usage: vk::BufferUsageFlags) -> Buffer {
+    let size = data.len() as vk::DeviceSize;
+
+    let buffer = self.create_buffer(
+      device,
+      size.max(1),
+      usage | vk::BufferUsageFlags::TRANSFER_DST,
+      vk::MemoryPropertyFlags::DEVICE_LOCAL
+    );
+
+    if data.is_empty() { return buffer; }
+
+    let staging = self.create_staging_buffer(device, data);
+
+    self.submit(device, |command_buffer| {
+      let region = vk::BufferCopy::default().size(size);
+      unsafe { device.cmd_copy_buffer(command_buffer, staging.buffer, buffer.buffer, &[region]) };
+    });
+
+    self.destroy_buffer(device, &staging);
+
+    buffer
+  }
+
+  // only valid for buffers created with HOST_VISIBLE | HOST_COHERENT memory
+  pub fn write_buffer(&self, device: &ash::Device, buffer: &Buffer, offset: vk::DeviceSize, data: &[u8]) {
+    if offset + data.len() as vk::DeviceSize > buffer.size {
+      panic!("failed to write buffer with error: write of {} bytes at {offset} overflows buffer of size {}", data.len(), buffer.size)
+    }
+
+    let pointer = self.map(device, buffer);
+    unsafe {
+      std::ptr::copy_nonoverlapping(data.as_ptr(), (pointer as *mut u8).add(offset as usize), data.len());
+      device.unmap_memory(buffer.memory);
+    }
+  }
+
+  // only valid for buffers created with SHADER_DEVICE_ADDRESS usage
+  pub fn buffer_address(&self, device: &ash::Device, buffer: &Buffer) -> vk::DeviceAddress {
+    let info = vk::BufferDeviceAddressInfo::default().buffer(buffer.buffer);
+    unsafe { device.get_buffer_device_address(&info) }
+  }
+
+  pub fn map(&self, device: &ash::Device, buffer: &Buffer) -> *mut c_void {
+    match unsafe { device.map_memory(buffer.memory, 0, buffer.size, vk::MemoryMapFlags::empty()) } {
+      Ok(pointer) => pointer,
+      Err(error)  => panic!("failed to map buffer memory with error: {error}")
+    }
+  }
+
+  pub fn create_staging_buffer(&self, device: &ash::Device, data: &[u8]) -> Buffer {
+    let staging = self.create_buffer(
+      device,
+      data.len().max(1) as vk::DeviceSize,
+      vk::BufferUsageFlags::TRANSFER_SRC,
+      vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+    );
+
+    self.write_buffer(device, &staging, 0, data);
+
+    staging
+  }
+
+  pub fn create_image(
+    &self,
+    device: &ash::Device,
+    create_info: &vk::ImageCreateInfo,
+    aspect_mask: vk::ImageAspectFlags,
+    view_type: vk::ImageViewType
+  ) -> Image {
+    let image = match unsafe { device.create_image(create_info, None) } {
+      Ok(image)  => image,
+      Err(error) => panic!("failed to create image with error: {error}")
+    };
+
+    let requirements = unsafe { device.get_image_memory_requirements(image) };
+    let memory = self.allocate(device, &requirements, vk::MemoryPropertyFlags::DEVICE_LOCAL, vk::MemoryAllocateFlags::empty());
+
+    if let Err(error) = unsafe { device.bind_image_memory(image, memory, 0) } {
+      panic!("failed to bind image memory with error: {error}")
+    }
+
+    let subresource_range = {
+      vk::ImageSubresourceRange::default()
+        .aspect_mask(aspect_mask)
+        .base_mip_level(0)
+        .level_count(create_info.mip_levels)
+        .base_array_layer(0)
+        .layer_count(create_info.array_layers)
+    };
+
+    let view_info = {
+      vk::ImageViewCreateInfo::default()
+        .image(image)
+        .view_type(view_type)
+        .format(create_info.format)
+        .subresource_range(subresource_range)
+    };
+
+    let view = match unsafe { device.create_image_view(&view_info, None) } {
+      Ok(view)   => view,
+      Err(error) => panic!("failed to create image view with error: {error}")
+    };
+
+    Image {
+      image,
+      memory,
+      view,
+      format: create_info.format,
+      extent: create_info.extent,
+      mip_levels: create_info.mip_levels,
+      array_layers: create_info.array_layers
+    }
+  }
+
+  // extra views onto an existing image, like a single mip or a cubemap seen as an array of faces. the caller destroys
+  // these since destroy_image only knows about the view made with the image
+  pub fn create_view(
+    &self,
+    device: &ash::Device,
+    image: &Image,
+    view_type: vk::ImageViewType,
+    range: vk::ImageSubresourceRange
+  ) -> vk::ImageView {
+    let view_info = {
+      vk::ImageViewCreateInfo::default()
+        .image(image.image)
+        .view_type(view_type)
+        .format(image.format)
+        .subresource_range(range)
+    };
+
+    match unsafe { device.create_image_view(&view_info, None) } {
+      Ok(view)   => view,
+      Err(error) => panic!("failed to create image view with error: {error}")
+    }
+  }
+
+  pub fn color_range(base_mip: u32, mip_count: u32, base_layer: u32, layer_count: u32) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange::default()
+      .aspect_mask(vk::ImageAspectFlags::COLOR)
+      .base_mip_level(base_mip)
+      .level_count(mip_count)
+      .base_array_layer(base_layer)
+      .layer_count(layer_count)
+  }
+
+  pub fn transition_image(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    range: vk::ImageSubresourceRange,
+    layouts: (vk::ImageLayout, vk::ImageLayout),
+    access: (vk::AccessFlags, vk::AccessFlags),
+    stages: (vk::PipelineStageFlags, vk::PipelineStageFlags)
+  ) {
+    let barrier = {
+      vk::ImageMemoryBarrier::default()
+        .old_layout(layouts.0)
+        .new_layout(layouts.1)
+        .src_access_mask(access.0)
+        .dst_access_mask(access.1)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(range)
+    };
+
+    unsafe {
+      device.cmd_pipeline_barrier(
+        command_buffer,
+        stages.0,
+        stages.1,
+        vk::DependencyFlags::empty(),
+        &[],
+        &[],
+        &[barrier]
+      )
+    };
+  }
+
+  // records and submits a one time command buffer and blocks until the main queue has finished it
+  pub fn submit<F: FnOnce(vk::CommandBuffer)>(&self, device: &ash::Device, record: F) {
+    let command_pool = match self.command_pool.lock() {
+      Ok(command_pool)  => command_pool,
+      Err(error)        => panic!("failed to lock allocator command pool with error: {error}")
+    };
+
+    let allocate_info = {
+      vk::CommandBufferAllocateInfo::default()
+        .command_pool(*command_pool)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(1)
+    };
+
+    let command_buffer = match unsafe { device.allocate_command_buffers(&allocate_info) } {
+      Ok(command_buffers) => command_buffers[0],
+      Err(error)          => panic!("failed to allocate upload command buffer with error: {error}")
+    };
+
+    let begin_info = {
+      vk::CommandBufferBeginInfo::default()
+        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+    };
+
+    if let Err(error) = unsafe { device.begin_command_buffer(command_buffer, &begin_info) } {
+      panic!("failed to begin upload command buffer with error: {error}")
+    }
+
+    record(command_buffer);
+
+    if let Err(error) = unsafe { device.end_command_buffer(command_buffer) } {
+      panic!("failed to end upload command buffer with error: {error}")
+    }
+
+    let command_buffers = [command_buffer];
+    let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+
+    unsafe {
+      if let Err(error) = device.queue_submit(self.queue, &[submit_info], vk::Fence::null()) {
+        panic!("failed to submit upload command buffer with error: {error}")
+      }
+
+      if let Err(error) = device.queue_wait_idle(self.queue) {
+        panic!("failed to wait for upload to finish with error: {error}")
+      }
+
+      device.free_command_buffers(*command_pool, &command_buffers);
+    }
+  }
+
+  pub fn destroy_buffer(&self, device: &ash::Device, buffer: &Buffer) {
+    self.allocations().remove(&buffer.memory);
+
+    unsafe {
+      device.destroy_buffer(buffer.buffer, None);
+      device.free_memory(buffer.memory, None);
+    }
+  }
+
+  pub fn destroy_image(&self, device: &ash::Device, image: &Image) {
+    self.allocations().remove(&image.memory);
+
+    unsafe {
+      device.destroy_image_view(image.view, None);
+      device.destroy_image(image.image, None);
+      device.free_memory(image.memory, None);
+    }
+  }
+
+  // one entry per heap, counting only what was allocated through the allocator
+  pub fn usage(&self) -> Vec<HeapUsage> {
+    let count = self.memory_properties.memory_heap_count as usize;
+    let mut heaps: Vec<HeapUsage> = self.memory_properties.memory_heaps[..count].iter().map(|heap| HeapUsage {
+      used: 0,
+      size: heap.size,
+      device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL)
+    }).collect();
+
+    for (heap, size) in self.allocations().values() {
+      if let Some(usage) = heaps.get_mut(*heap) {
+        usage.used += size;
+      }
+    }
+
+    heaps
+  }
+
+  fn allocate(
+    &self,
+    device: &ash::Device,
+    requirements: &vk::MemoryRequirements,
+    properties: vk::MemoryPropertyFlags,
+    flags: vk::MemoryAllocateFlags
+  ) -> vk::DeviceMemory {
+    let mut flags_info = vk::MemoryAllocateFlagsInfo::default().flags(flags);
+
+    let memory_type = self.memory_type(requirements.memory_type_bits, properties);
+
+    let allocate_info = {
+      vk::MemoryAllocateInfo::default()
+        .allocation_size(requirements.size)
+        .memory_type_index(memory_type)
+        .push_next(&mut flags_info)
+    };
+
+    let memory = match unsafe { device.allocate_memory(&allocate_info, None) } {
+      Ok(memory) => memory,
+      Err(error) => panic!("failed to allocate device memory with error: {error}")
+    };
+
+    let heap = self.memory_properties.memory_types[memory_type as usize].heap_index as usize;
+    self.allocations().insert(memory, (heap, requirements.size));
+
+    memory
+  }
+
+  fn allocations(&self) -> MutexGuard<'_, HashMap<vk::DeviceMemory, (usize, vk::DeviceSize)>> {
+    match self.allocations.lock() {
+      Ok(allocations) => allocations,
+      Err(error)      => panic!("failed to lock allocator allocations with error: {error}")
+    }
+  }
+
+  fn memory_type(&self, type_bits: u32, properties: vk::MemoryPropertyFlags) -> u32 {
+    let count = self.memory_properties.memory_type_count as usize;
+
+    for (index, memory_type) in self.memory_properties.memory_types[..count].iter().enumerate() {
+      if type_bits & (1 << index) == 0 { continue; }
+      if memory_type.property_flags & properties != properties { continue; }
+
+      return index as u32;
+    }
+
+    panic!("failed to find memory type with properties {properties:?}")
+  }
+}
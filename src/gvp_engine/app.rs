@@ -0,0 +1,165 @@
+mod manager;
+
+pub use manager::*;
+
+use crate::gvp_engine::{
+  compositor::{Panel, PanelId},
+  engine::GVPEngine,
+  gui::{Canvas, GuiInput},
+  input::Input,
+  material::Material,
+  primitives::PrimitiveKind,
+  scene::Entity,
+  surface::{ExternalSurface, SurfaceId},
+  text::Text,
+  transform::Transform
+};
+
+use glam::{Quat, Vec2, Vec3};
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AppId(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppState {
+  Running,
+  // hidden, and skipped by input, update and render until resumed
+  Suspended
+}
+
+// an application living in a panel. every call gets a context for reaching the engine, and whatever the app creates
+// through the context is freed when it closes. only the name is required
+pub trait GVPApp {
+  // also the key its saved state is kept under
+  fn name(&self) -> &str;
+
+  // where the app's panel starts out
+  fn panel(&self) -> Panel {
+    Panel::new(Transform::new(Vec3::new(0.0, 1.5, -1.5), Quat::IDENTITY, Vec3::ONE), Vec2::new(1.0, 0.625))
+  }
+
+  fn on_open(&mut self, _context: &mut AppContext) {}
+
+  // this frame's input, given while the app's panel is focused or pointed at
+  fn on_input(&mut self, _context: &mut AppContext, _input: &GuiInput) {}
+
+  // seconds since the last update
+  fn update(&mut self, _context: &mut AppContext, _delta: f32) {}
+
+  // draws into the app's panel target through AppContext::draw
+  fn render(&mut self, _context: &mut AppContext) {}
+
+  fn on_suspend(&mut self, _context: &mut AppContext) {}
+
+  fn on_resume(&mut self, _context: &mut AppContext) {}
+
+  fn on_close(&mut self, _context: &mut AppContext) {}
+
+  // kept when the app closes and handed to the next app of the same name, across sessions once saved to a file
+  fn save_state(&self) -> Option<Value> {
+    None
+  }
+
+  fn restore_state(&mut self, _state: &Value) {}
+}
+
+// what an app created, freed when it closes
+#[derive(Clone, Debug, Default)]
+pub struct AppScope {
+  pub panels: Vec<PanelId>,
+  pub entities: Vec<Entity>,
+  // panels the app had showing when it was suspended
+  hidden: Vec<PanelId>
+}
+
+impl AppScope {
+  fn free(&mut self, engine: &mut GVPEngine) {
+    for entity in self.entities.drain(..) {
+      engine.scene_mut().despawn(entity);
+    }
+
+    for panel in self.panels.drain(..) {
+      engine.destroy_panel(panel);
+    }
+  }
+}
+
+pub struct AppContext<'a> {
+  engine: &'a mut GVPEngine,
+  panel: PanelId,
+  scope: &'a mut AppScope,
+  close: bool
+}
+
+impl<'a> AppContext<'a> {
+  fn new(engine: &'a mut GVPEngine, panel: PanelId, scope: &'a mut AppScope) -> Self {
+    AppContext { engine, panel, scope, close: false }
+  }
+
+  // the panel the app was opened with
+  pub fn panel(&self) -> PanelId {
+    self.panel
+  }
+
+  // in pixels of the panel's target
+  pub fn size(&self) -> Vec2 {
+    let extent = self.engine.panel(self.panel).extent();
+    Vec2::new(extent.width as f32, extent.height as f32)
+  }
+
+  pub fn focused(&self) -> bool {
+    self.engine.focused_panel().is_some_and(|panel| self.scope.panels.contains(&panel))
+  }
+
+  // the whole engine, for anything the context does not cover. what is created through it directly is not freed with
+  // the app
+  pub fn engine(&mut self) -> &mut GVPEngine {
+    self.engine
+  }
+
+  pub fn input(&self) -> &Input {
+    self.engine.input()
+  }
+
+  pub fn text(&self) -> &Text {
+    self.engine.text()
+  }
+
+  pub fn draw(&mut self, canvas: Canvas) {
+    self.engine.draw_canvas(self.panel, canvas);
+  }
+
+  pub fn create_panel(&mut self, panel: Panel) -> PanelId {
+    let id = self.engine.create_panel(panel);
+    self.scope.panels.push(id);
+
+    id
+  }
+
+  pub fn update_panel(&mut self, id: PanelId, panel: Panel) {
+    self.engine.update_panel(id, panel);
+  }
+
+  // lives as long as the panel it is shown on
+  pub fn attach_surface(&mut self, panel: PanelId, source: Box<dyn ExternalSurface>) -> SurfaceId {
+    self.engine.attach_surface(panel, source)
+  }
+
+  pub fn spawn_primitive(&mut self, kind: PrimitiveKind, transform: Transform, material: Material) -> Entity {
+    let entity = self.engine.spawn_primitive(kind, transform, material);
+    self.scope.entities.push(entity);
+
+    entity
+  }
+
+  pub fn despawn(&mut self, entity: Entity) {
+    self.scope.entities.retain(|spawned| *spawned != entity);
+    self.engine.scene_mut().despawn(entity);
+  }
+
+  // the app closes after the call it asked from
+  pub fn close(&mut self) {
+    self.close = true;
+  }
+}
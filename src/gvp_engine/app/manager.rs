@@ -0,0 +1,249 @@
+use crate::gvp_engine::{
+  compositor::{Panel, PanelId},
+  engine::GVPEngine,
+  gui::GuiInput,
+  scene::PanelRecord
+};
+
+use super::{AppContext, AppId, AppScope, AppState, GVPApp};
+
+use serde_json::Value;
+
+use std::{collections::{BTreeMap, HashMap}, fmt, fs, path::Path, time::Instant};
+
+// why saved app states were not read
+#[derive(Clone, Debug, PartialEq)]
+pub enum AppStatesError {
+  Read(String),
+  Parse(String)
+}
+
+impl fmt::Display for AppStatesError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      AppStatesError::Read(error)  => write!(formatter, "could not read file: {error}"),
+      AppStatesError::Parse(error) => write!(formatter, "could not parse: {error}")
+    }
+  }
+}
+
+struct Entry {
+  app: Box<dyn GVPApp>,
+  state: AppState,
+  // the panel the app was opened with, its scope holds every other one
+  panel: PanelId,
+  scope: AppScope
+}
+
+// opens apps into panels and runs the open ones every frame. the engine owns it and hands itself to the apps while
+// they run, so apps reach the engine but not the manager
+#[derive(Default)]
+pub struct AppManager {
+  // ids index into this and are not reused once an app is closed
+  entries: Vec<Option<Entry>>,
+  // states of closed apps by name, handed back when an app of that name is launched
+  saved: HashMap<String, Value>,
+  // where the panels of apps that are not open were left by app name, an app of that name launches into it
+  placements: HashMap<String, PanelRecord>,
+  last_update: Option<Instant>
+}
+
+impl AppManager {
+  pub fn launch(&mut self, engine: &mut GVPEngine, mut app: Box<dyn GVPApp>) -> AppId {
+    let id = AppId(self.entries.len());
+
+    let panel = match self.placements.remove(app.name()) {
+      Some(placement) => Panel { transform: placement.transform, size: placement.size, ..app.panel() },
+      None            => app.panel()
+    };
+
+    let panel = engine.create_panel(panel);
+    let mut scope = AppScope { panels: vec![panel], ..AppScope::default() };
+
+    if let Some(state) = self.saved.remove(app.name()) {
+      app.restore_state(&state);
+    }
+
+    let mut context = AppContext::new(engine, panel, &mut scope);
+    app.on_open(&mut context);
+    let close = context.close;
+
+    self.entries.push(Some(Entry { app, state: AppState::Running, panel, scope }));
+    engine.focus_panel(Some(panel));
+
+    if close {
+      self.close(engine, id);
+    }
+
+    id
+  }
+
+  pub fn suspend(&mut self, engine: &mut GVPEngine, id: AppId) {
+    let close = {
+      let Some(entry) = self.entries.get_mut(id.0).and_then(Option::as_mut) else { return; };
+      if entry.state == AppState::Suspended { return; }
+
+      let mut context = AppContext::new(engine, entry.panel, &mut entry.scope);
+      entry.app.on_suspend(&mut context);
+      let close = context.close;
+
+      entry.state = AppState::Suspended;
+      entry.scope.hidden = entry.scope.panels.iter().copied().filter(|panel| engine.panel(*panel).visible).collect();
+
+      for panel in &entry.scope.hidden {
+        engine.update_panel(*panel, Panel { visible: false, ..*engine.panel(*panel) });
+      }
+
+      if engine.focused_panel().is_some_and(|panel| entry.scope.panels.contains(&panel)) {
+        engine.focus_panel(None);
+      }
+
+      close
+    };
+
+    if close {
+      self.close(engine, id);
+    }
+  }
+
+  pub fn resume(&mut self, engine: &mut GVPEngine, id: AppId) {
+    let close = {
+      let Some(entry) = self.entries.get_mut(id.0).and_then(Option::as_mut) else { return; };
+      if entry.state == AppState::Running { return; }
+
+      for panel in entry.scope.hidden.drain(..) {
+        engine.update_panel(panel, Panel { visible: true, ..*engine.panel(panel) });
+      }
+
+      entry.state = AppState::Running;
+
+      let mut context = AppContext::new(engine, entry.panel, &mut entry.scope);
+      entry.app.on_resume(&mut context);
+      context.close
+    };
+
+    if close {
+      self.close(engine, id);
+    }
+  }
+
+  // keeps the app's state for the next launch and frees everything it created
+  pub fn close(&mut self, engine: &mut GVPEngine, id: AppId) {
+    let Some(mut entry) = self.entries.get_mut(id.0).and_then(Option::take) else { return; };
+
+    if let Some(state) = entry.app.save_state() {
+      self.saved.insert(entry.app.name().to_string(), state);
+    }
+
+    let panel = engine.panel(entry.panel);
+    self.placements.insert(entry.app.name().to_string(), PanelRecord { transform: panel.transform, size: panel.size });
+
+    let mut context = AppContext::new(engine, entry.panel, &mut entry.scope);
+    entry.app.on_close(&mut context);
+
+    entry.scope.free(engine);
+  }
+
+  // closes every app, like when the engine shuts down
+  pub fn close_all(&mut self, engine: &mut GVPEngine) {
+    for index in 0..self.entries.len() {
+      self.close(engine, AppId(index));
+    }
+  }
+
+  // runs every app that is not suspended: input if its panel is focused or pointed at, then update, then render
+  pub fn update(&mut self, engine: &mut GVPEngine) {
+    let now = Instant::now();
+    let delta = self.last_update.map_or(0.0, |last| (now - last).as_secs_f32());
+    self.last_update = Some(now);
+
+    let mut closing = Vec::new();
+
+    for (index, slot) in self.entries.iter_mut().enumerate() {
+      let Some(entry) = slot.as_mut().filter(|entry| entry.state == AppState::Running) else { continue; };
+
+      let focused = engine.focused_panel().is_some_and(|panel| entry.scope.panels.contains(&panel));
+      let hit = engine.panel_hit().filter(|hit| entry.scope.panels.contains(&hit.panel));
+      let input = (focused || hit.is_some()).then(|| GuiInput::from_input(engine.input(), hit.map(|hit| hit.position), focused));
+
+      let mut context = AppContext::new(engine, entry.panel, &mut entry.scope);
+
+      if let Some(input) = &input {
+        entry.app.on_input(&mut context, input);
+      }
+
+      entry.app.update(&mut context, delta);
+      entry.app.render(&mut context);
+
+      if context.close {
+        closing.push(AppId(index));
+      }
+    }
+
+    for id in closing {
+      self.close(engine, id);
+    }
+  }
+
+  pub fn state(&self, id: AppId) -> Option<AppState> {
+    self.entry(id).map(|entry| entry.state)
+  }
+
+  // the states of open apps along with the ones kept from closed apps, as one json object keyed by app name
+  pub fn save(&self, path: &Path) {
+    let mut states = self.saved.clone();
+
+    for entry in self.entries.iter().flatten() {
+      if let Some(state) = entry.app.save_state() {
+        states.insert(entry.app.name().to_string(), state);
+      }
+    }
+
+    let json = match serde_json::to_string_pretty(&states) {
+      Ok(json)   => json,
+      Err(error) => panic!("failed to serialize app states with error: {error}")
+    };
+
+    if let Err(error) = fs::write(path, json) {
+      panic!("failed to write app states {} with error: {error}", path.display())
+    }
+  }
+
+  // states are handed to apps as they launch. a file that cannot be read leaves the kept states as they are
+  pub fn load(&mut self, path: &Path) -> Result<(), AppStatesError> {
+    let json = fs::read_to_string(path).map_err(|error| AppStatesError::Read(error.to_string()))?;
+    let states: HashMap<String, Value> = serde_json::from_str(&json).map_err(|error| AppStatesError::Parse(error.to_string()))?;
+    self.saved.extend(states);
+
+    Ok(())
+  }
+
+  // where the panels of open apps are and where those of closed ones were left
+  pub fn placements(&self, engine: &GVPEngine) -> BTreeMap<String, PanelRecord> {
+    let mut placements: BTreeMap<String, PanelRecord> = self.placements.clone().into_iter().collect();
+
+    for entry in self.entries.iter().flatten() {
+      let panel = engine.panel(entry.panel);
+      placements.insert(entry.app.name().to_string(), PanelRecord { transform: panel.transform, size: panel.size });
+    }
+
+    placements
+  }
+
+  // moves the panels of open apps, the rest are used when an app of that name launches
+  pub fn place(&mut self, engine: &mut GVPEngine, placements: &BTreeMap<String, PanelRecord>) {
+    for (name, placement) in placements {
+      match self.entries.iter().flatten().find(|entry| entry.app.name() == name) {
+        Some(entry) => {
+          let panel = Panel { transform: placement.transform, size: placement.size, ..*engine.panel(entry.panel) };
+          engine.update_panel(entry.panel, panel);
+        },
+        None        => { self.placements.insert(name.clone(), *placement); }
+      }
+    }
+  }
+
+  fn entry(&self, id: AppId) -> Option<&Entry> {
+    self.entries.get(id.0).and_then(Option::as_ref)
+  }
+}
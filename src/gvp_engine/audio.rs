@@ -0,0 +1,261 @@
+mod clip;
+mod mixer;
+mod output;
+mod spatial;
+
+pub use clip::*;
+pub use output::*;
+
+use mixer::*;
+use spatial::*;
+
+use crate::gvp_engine::scene::{AudioSource, Entity, Scene};
+
+use glam::{Mat4, Vec3};
+
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+  sync::{mpsc::{self, Receiver, Sender}, Arc},
+  thread::{self, JoinHandle},
+  time::Instant
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AudioMode {
+  // spatial sources are placed around the listener's head
+  #[default]
+  Spatial,
+  // everything plays straight into both ears at its own volume, the way headphones play music
+  Headphones
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioSettings {
+  pub mode: AudioMode,
+  pub volume: f32,
+  // spatial sources closer than this play at their full volume
+  pub reference_distance: f32,
+  // and further than this stop getting quieter
+  pub max_distance: f32,
+  // how quickly they fade in between. at 1 the volume halves every time the distance doubles
+  pub rolloff: f32,
+  // in meters per second, for the doppler shift and how much later the far ear hears a sound
+  pub speed_of_sound: f32,
+  // scales the doppler shift, 0 turns it off
+  pub doppler: f32
+}
+
+impl Default for AudioSettings {
+  fn default() -> Self {
+    AudioSettings {
+      mode: AudioMode::Spatial,
+      volume: 1.0,
+      reference_distance: 1.0,
+      max_distance: 50.0,
+      rolloff: 1.0,
+      speed_of_sound: 343.0,
+      doppler: 1.0
+    }
+  }
+}
+
+// what updating the audio did
+#[derive(Clone, Debug, PartialEq)]
+pub enum AudioEvent {
+  // a clip that does not loop played to its end, pausing its source
+  Finished(Entity),
+  // the source stays silent until its clip changes
+  Failed { entity: Entity, path: PathBuf, error: ClipError }
+}
+
+// a source as the mixer last heard of it
+struct Playing {
+  source: AudioSource,
+  position: Vec3
+}
+
+// plays the clips of the scene's audio sources on a mixer thread, placed around the listener's head. the scene stays
+// the source of truth: sources added, changed or removed there are picked up on the next update, and the entities they
+// are on are followed as they move
+pub struct Audio {
+  pub settings: AudioSettings,
+  commands: Sender<Command>,
+  // kept across outputs so clips ending while switching are not missed
+  finished: (Sender<Entity>, Receiver<Entity>),
+  mixer: Option<JoinHandle<Mixer>>,
+  // shared by every source playing the same file, and let go once none are
+  clips: HashMap<PathBuf, Arc<Clip>>,
+  playing: HashMap<Entity, Playing>,
+  // the settings the mixer has
+  sent: AudioSettings,
+  listener: Option<Vec3>,
+  last_update: Option<Instant>
+}
+
+impl Audio {
+  // asked of sound cards, which may pick another
+  pub const SAMPLE_RATE: u32 = 48_000;
+  // in meters per second. anything moving faster between updates is taken as having jumped there, shifting nothing
+  const MAX_SPEED: f32 = 50.0;
+
+  pub fn new(output: Box<dyn AudioOutput>) -> Self {
+    let settings = AudioSettings::default();
+    let finished = mpsc::channel();
+    let (commands, mixer) = Audio::start(Mixer::new(output.sample_rate(), settings), output, finished.0.clone());
+
+    Audio {
+      settings,
+      commands,
+      finished,
+      mixer: Some(mixer),
+      clips: HashMap::new(),
+      playing: HashMap::new(),
+      sent: settings,
+      listener: None,
+      last_update: None
+    }
+  }
+
+  // moves the mix over to another output, everything carrying on from where it was
+  pub fn set_output(&mut self, output: Box<dyn AudioOutput>) {
+    let mut mixer = self.stop();
+    mixer.sample_rate = output.sample_rate();
+
+    let (commands, mixer) = Audio::start(mixer, output, self.finished.0.clone());
+    (self.commands, self.mixer) = (commands, Some(mixer));
+  }
+
+  // for a clip that was not loaded from the path, like one decoded elsewhere. sources naming the path play it from the
+  // next update on, and it is let go once none do
+  pub fn insert_clip(&mut self, path: &Path, clip: Clip) {
+    self.clips.insert(path.to_path_buf(), Arc::new(clip));
+  }
+
+  // brings the mixer up to date with the scene's sources and the head. sources whose clips ended are written back as
+  // paused so they stay quiet until unpaused
+  pub fn update(&mut self, scene: &mut Scene, head: Mat4) -> Vec<AudioEvent> {
+    let now = Instant::now();
+    let delta = self.last_update.map_or(0.0, |last| (now - last).as_secs_f32());
+    self.last_update = Some(now);
+
+    if self.settings != self.sent {
+      Audio::send(&self.commands, Command::Settings(self.settings));
+      self.sent = self.settings;
+    }
+
+    let position = head.w_axis.truncate();
+    Audio::send(&self.commands, Command::Listener { transform: head, velocity: Audio::velocity(self.listener, position, delta) });
+    self.listener = Some(position);
+
+    let mut events = Vec::new();
+
+    for entity in self.finished.1.try_iter() {
+      let (Some(playing), Some(source)) = (self.playing.get_mut(&entity), scene.get_mut::<AudioSource>(entity)) else { continue; };
+
+      (playing.source.paused, source.paused) = (true, true);
+      events.push(AudioEvent::Finished(entity));
+    }
+
+    let removed: Vec<Entity> = self.playing.keys().filter(|entity| scene.get::<AudioSource>(**entity).is_none()).copied().collect();
+
+    for entity in removed {
+      self.playing.remove(&entity);
+      Audio::send(&self.commands, Command::Stop(entity));
+    }
+
+    for (entity, source) in scene.query::<AudioSource>() {
+      let position = scene.world_matrix(entity).w_axis.truncate();
+      let settings = VoiceSettings { volume: source.volume, looping: source.looping, spatial: source.spatial, paused: source.paused };
+
+      if let Some(playing) = self.playing.get_mut(&entity).filter(|playing| playing.source.clip == source.clip) {
+        if playing.source != *source {
+          Audio::send(&self.commands, Command::Update { entity, settings });
+          playing.source = source.clone();
+        }
+
+        let velocity = Audio::velocity(Some(playing.position), position, delta);
+        playing.position = position;
+
+        Audio::send(&self.commands, Command::Place { entity, position, velocity });
+        continue;
+      }
+
+      // added, or playing another clip now. failures are kept too so they are not tried again every update
+      if self.playing.insert(entity, Playing { source: source.clone(), position }).is_some() {
+        Audio::send(&self.commands, Command::Stop(entity));
+      }
+
+      let Some(path) = &source.clip else { continue; };
+
+      match self.clip(path) {
+        Ok(clip)   => Audio::send(&self.commands, Command::Play { entity, clip, settings, position }),
+        Err(error) => events.push(AudioEvent::Failed { entity, path: path.clone(), error })
+      }
+    }
+
+    self.clips.retain(|path, _| self.playing.values().any(|playing| playing.source.clip.as_ref() == Some(path)));
+
+    events
+  }
+
+  // stops every source, for when the scene is replaced
+  pub fn clear(&mut self) {
+    for (entity, _) in self.playing.drain() {
+      Audio::send(&self.commands, Command::Stop(entity));
+    }
+  }
+
+  fn clip(&mut self, path: &Path) -> Result<Arc<Clip>, ClipError> {
+    if let Some(clip) = self.clips.get(path) { return Ok(clip.clone()); }
+
+    let clip = Arc::new(Clip::load(path)?);
+    self.clips.insert(path.to_path_buf(), clip.clone());
+
+    Ok(clip)
+  }
+
+  fn start(mixer: Mixer, output: Box<dyn AudioOutput>, finished: Sender<Entity>) -> (Sender<Command>, JoinHandle<Mixer>) {
+    let (commands, received) = mpsc::channel();
+
+    match thread::Builder::new().name("audio mixer".to_string()).spawn(move || mixer.run(output, received, finished)) {
+      Ok(mixer)  => (commands, mixer),
+      Err(error) => panic!("failed to start audio mixer thread with error: {error}")
+    }
+  }
+
+  fn stop(&mut self) -> Mixer {
+    // a mixer that is gone already is found out joining it
+    self.commands.send(Command::Quit).ok();
+
+    match self.mixer.take().map(JoinHandle::join) {
+      Some(Ok(mixer)) => mixer,
+      _               => panic!("failed to stop audio mixer thread with error: it panicked")
+    }
+  }
+
+  fn send(commands: &Sender<Command>, command: Command) {
+    if commands.send(command).is_err() {
+      panic!("failed to send to audio mixer thread with error: it stopped");
+    }
+  }
+
+  fn velocity(last: Option<Vec3>, position: Vec3, delta: f32) -> Vec3 {
+    let velocity = match last {
+      Some(last) if delta > 0.0 => (position - last) / delta,
+      _                         => Vec3::ZERO
+    };
+
+    if velocity.length() > Audio::MAX_SPEED { Vec3::ZERO } else { velocity }
+  }
+}
+
+impl Drop for Audio {
+  fn drop(&mut self) {
+    self.commands.send(Command::Quit).ok();
+
+    if let Some(mixer) = self.mixer.take() {
+      mixer.join().ok();
+    }
+  }
+}
@@ -0,0 +1,137 @@
+use symphonia::core::{
+  audio::SampleBuffer,
+  codecs::DecoderOptions,
+  errors::Error,
+  formats::FormatOptions,
+  io::{MediaSource, MediaSourceStream},
+  meta::MetadataOptions,
+  probe::Hint
+};
+
+use std::{fmt, fs::File, io, path::Path};
+
+// why a clip was not loaded
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClipError {
+  Open(String),
+  // not wav, ogg vorbis or flac
+  Unsupported,
+  NoTrack,
+  Decode(String),
+  Empty
+}
+
+impl fmt::Display for ClipError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ClipError::Open(error)   => write!(formatter, "could not open file: {error}"),
+      ClipError::Unsupported   => write!(formatter, "not a wav, ogg vorbis or flac file"),
+      ClipError::NoTrack       => write!(formatter, "no audio track"),
+      ClipError::Decode(error) => write!(formatter, "could not decode: {error}"),
+      ClipError::Empty         => write!(formatter, "no samples")
+    }
+  }
+}
+
+// a sound decoded whole into left and right samples at the rate it was recorded at. mono sounds are kept as the same
+// sample on both sides, and anything with more channels is folded down
+#[derive(Clone, Debug)]
+pub struct Clip {
+  pub sample_rate: u32,
+  pub frames: Vec<[f32; 2]>
+}
+
+impl Clip {
+  pub fn load(path: &Path) -> Result<Clip, ClipError> {
+    let file = File::open(path).map_err(|error| ClipError::Open(error.to_string()))?;
+    Clip::decode(Box::new(file), path.extension().and_then(|extension| extension.to_str()))
+  }
+
+  // from anything holding an encoded sound, like a file or a download. the extension it would have helps tell what
+  // it is encoded as
+  pub fn decode(source: Box<dyn MediaSource>, extension: Option<&str>) -> Result<Clip, ClipError> {
+    let stream = MediaSourceStream::new(source, Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = extension {
+      hint.with_extension(extension);
+    }
+
+    let probed = match symphonia::default::get_probe().format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default()) {
+      Ok(probed) => probed,
+      Err(_)     => return Err(ClipError::Unsupported)
+    };
+
+    let mut format = probed.format;
+    let track = format.default_track().ok_or(ClipError::NoTrack)?;
+    let track_id = track.id;
+
+    let mut decoder = match symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default()) {
+      Ok(decoder) => decoder,
+      Err(_)      => return Err(ClipError::Unsupported)
+    };
+
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut frames = Vec::new();
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+
+    loop {
+      let packet = match format.next_packet() {
+        Ok(packet) => packet,
+        // which is how the end of the stream comes
+        Err(Error::IoError(error)) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+        Err(error) => return Err(ClipError::Decode(error.to_string()))
+      };
+
+      if packet.track_id() != track_id { continue; }
+
+      let decoded = match decoder.decode(&packet) {
+        Ok(decoded) => decoded,
+        // a damaged packet is skipped over
+        Err(Error::DecodeError(_)) => continue,
+        Err(error) => return Err(ClipError::Decode(error.to_string()))
+      };
+
+      let spec = *decoded.spec();
+      let channels = spec.channels.count();
+      sample_rate = spec.rate;
+
+      if buffer.as_ref().is_none_or(|buffer| buffer.capacity() < decoded.capacity() * channels) {
+        buffer = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+      }
+
+      let Some(buffer) = &mut buffer else { continue; };
+      buffer.copy_interleaved_ref(decoded);
+
+      frames.extend(buffer.samples().chunks_exact(channels.max(1)).map(Clip::fold));
+    }
+
+    if frames.is_empty() || sample_rate == 0 { return Err(ClipError::Empty); }
+
+    Ok(Clip { sample_rate, frames })
+  }
+
+  // the frame at a position between two frames, wrapping around to the start when looping
+  pub fn sample(&self, position: f64, looping: bool) -> [f32; 2] {
+    let index = position as usize;
+    let blend = (position - index as f64) as f32;
+
+    let next = if index + 1 < self.frames.len() { self.frames[index + 1] } else if looping { self.frames[0] } else { [0.0; 2] };
+
+    let [left, right] = self.frames[index];
+    [left + (next[0] - left) * blend, right + (next[1] - right) * blend]
+  }
+
+  // the first two channels are taken as left and right and the rest are spread over both
+  fn fold(channels: &[f32]) -> [f32; 2] {
+    match channels {
+      [mono]                   => [*mono; 2],
+      [left, right]            => [*left, *right],
+      [left, right, rest @ ..] => {
+        let rest = rest.iter().sum::<f32>() * 0.5 / rest.len() as f32;
+        [left + rest, right + rest]
+      },
+      []                       => [0.0; 2]
+    }
+  }
+}
@@ -0,0 +1,278 @@
+use crate::gvp_engine::scene::Entity;
+
+use super::{AudioMode, AudioOutput, AudioSettings, Binaural, Clip};
+
+use glam::{Mat4, Vec3};
+
+use std::sync::{mpsc::{Receiver, Sender, TryRecvError}, Arc};
+
+// how a source plays, which can change without starting it over
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoiceSettings {
+  pub volume: f32,
+  pub looping: bool,
+  pub spatial: bool,
+  pub paused: bool
+}
+
+// what the engine tells the mixer thread
+pub enum Command {
+  Play { entity: Entity, clip: Arc<Clip>, settings: VoiceSettings, position: Vec3 },
+  Update { entity: Entity, settings: VoiceSettings },
+  Place { entity: Entity, position: Vec3, velocity: Vec3 },
+  Stop(Entity),
+  // the head's world transform, looking down -z, and how fast it moves
+  Listener { transform: Mat4, velocity: Vec3 },
+  Settings(AudioSettings),
+  // ends the thread, handing the mixer back
+  Quit
+}
+
+struct Voice {
+  entity: Entity,
+  clip: Arc<Clip>,
+  settings: VoiceSettings,
+  position: Vec3,
+  velocity: Vec3,
+  // in frames of the clip
+  cursor: f64,
+  // as of the end of the last block, the next one glides from it
+  gain: f32,
+  binaural: Binaural
+}
+
+pub struct Mixer {
+  pub sample_rate: u32,
+  settings: AudioSettings,
+  listener: Mat4,
+  listener_velocity: Vec3,
+  voices: Vec<Voice>,
+  // of the voice being mixed, folded to mono for the binaural stage
+  mono: Vec<f32>
+}
+
+impl Mixer {
+  // frames mixed at a time, around 5ms at 48khz
+  pub const BLOCK: usize = 256;
+  // the fastest a source or the listener counts as closing in at, as a share of the speed of sound, keeping the shift
+  // within an octave either way
+  const MAX_DOPPLER_SPEED: f32 = 0.5;
+
+  pub fn new(sample_rate: u32, settings: AudioSettings) -> Self {
+    Mixer {
+      sample_rate,
+      settings,
+      listener: Mat4::IDENTITY,
+      listener_velocity: Vec3::ZERO,
+      voices: Vec::new(),
+      mono: Vec::with_capacity(Mixer::BLOCK)
+    }
+  }
+
+  // the mixer thread. applies what the engine sent, mixes a block and hands it to the output, which holds the thread
+  // until the device is ready for more. gives the mixer back when told to quit or when the engine goes away
+  pub fn run(mut self, mut output: Box<dyn AudioOutput>, commands: Receiver<Command>, finished: Sender<Entity>) -> Mixer {
+    let mut block = vec![[0.0; 2]; Mixer::BLOCK];
+    let mut ended = Vec::new();
+
+    loop {
+      loop {
+        match commands.try_recv() {
+          Ok(Command::Quit) | Err(TryRecvError::Disconnected) => return self,
+          Ok(command)                                         => self.apply(command),
+          Err(TryRecvError::Empty)                            => break
+        }
+      }
+
+      self.render(&mut block, &mut ended);
+
+      // nobody listening for them means the engine is shutting down, which the next command will say
+      for entity in ended.drain(..) {
+        finished.send(entity).ok();
+      }
+
+      output.write(&block);
+    }
+  }
+
+  pub fn apply(&mut self, command: Command) {
+    match command {
+      Command::Play { entity, clip, settings, position } => {
+        self.voices.retain(|voice| voice.entity != entity);
+        self.voices.push(Voice {
+          entity,
+          clip,
+          settings,
+          position,
+          velocity: Vec3::ZERO,
+          cursor: 0.0,
+          gain: 0.0,
+          binaural: Binaural::default()
+        });
+      },
+      Command::Update { entity, settings } => if let Some(voice) = self.voices.iter_mut().find(|voice| voice.entity == entity) {
+        // resuming glides in from silence
+        if voice.settings.paused { voice.gain = 0.0; }
+        voice.settings = settings;
+      },
+      Command::Place { entity, position, velocity } => if let Some(voice) = self.voices.iter_mut().find(|voice| voice.entity == entity) {
+        (voice.position, voice.velocity) = (position, velocity);
+      },
+      Command::Stop(entity)                    => self.voices.retain(|voice| voice.entity != entity),
+      Command::Listener { transform, velocity } => (self.listener, self.listener_velocity) = (transform, velocity),
+      Command::Settings(settings)              => self.settings = settings,
+      Command::Quit                            => {}
+    }
+  }
+
+  // mixes the next frames of every voice that is not paused, listing the entities whose clips played to the end.
+  // those are paused back at the start
+  pub fn render(&mut self, frames: &mut [[f32; 2]], finished: &mut Vec<Entity>) {
+    frames.fill([0.0; 2]);
+
+    let Mixer { sample_rate, settings, listener, listener_velocity, voices, mono } = self;
+    let sample_rate = *sample_rate as f32;
+    let listener_position = listener.w_axis.truncate();
+    let into_listener = listener.inverse();
+
+    for voice in voices.iter_mut().filter(|voice| !voice.settings.paused) {
+      let spatial = voice.settings.spatial && settings.mode == AudioMode::Spatial;
+      let mut step = voice.clip.sample_rate as f64 / sample_rate as f64;
+      let mut gain = voice.settings.volume * settings.volume;
+      let mut direction = Vec3::NEG_Z;
+
+      if spatial {
+        let offset = voice.position - listener_position;
+        direction = into_listener.transform_vector3(offset).normalize_or_zero();
+
+        // inverse distance, clamped so nearby sources do not blow up and far ones stop fading
+        let reference = settings.reference_distance.max(0.01);
+        let distance = offset.length().clamp(reference, settings.max_distance.max(reference));
+        gain *= reference / (reference + settings.rolloff * (distance - reference));
+
+        // speeds along the line from the source to the listener
+        let toward = -offset.normalize_or_zero();
+        let speed = settings.speed_of_sound;
+        let limit = speed * Mixer::MAX_DOPPLER_SPEED;
+        let source = (voice.velocity.dot(toward) * settings.doppler).clamp(-limit, limit);
+        let away = (listener_velocity.dot(toward) * settings.doppler).clamp(-limit, limit);
+
+        step *= ((speed - away) / (speed - source)) as f64;
+      }
+
+      let length = voice.clip.frames.len() as f64;
+      let count = frames.len() as f32;
+      let mut ended = false;
+      mono.clear();
+
+      for (frame, mixed) in frames.iter_mut().enumerate() {
+        let level = voice.gain + (gain - voice.gain) * frame as f32 / count;
+        let [left, right] = if ended { [0.0; 2] } else { voice.clip.sample(voice.cursor, voice.settings.looping) };
+
+        voice.cursor += step;
+
+        if voice.cursor >= length {
+          if voice.settings.looping { voice.cursor %= length; } else { ended = true; }
+        }
+
+        if spatial {
+          mono.push((left + right) * 0.5 * level);
+        }
+        else {
+          mixed[0] += left * level;
+          mixed[1] += right * level;
+        }
+      }
+
+      if spatial {
+        voice.binaural.process(mono, direction, sample_rate, settings.speed_of_sound, frames);
+      }
+
+      voice.gain = gain;
+
+      if ended {
+        (voice.cursor, voice.gain, voice.settings.paused) = (0.0, 0.0, true);
+        finished.push(voice.entity);
+      }
+    }
+
+    for frame in frames {
+      *frame = frame.map(|sample| sample.clamp(-1.0, 1.0));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::gvp_engine::{audio::NullOutput, scene::Scene, transform::Transform};
+
+  use std::{sync::mpsc, thread, time::Duration};
+
+  const SAMPLE_RATE: u32 = 48000;
+
+  fn entity() -> Entity {
+    Scene::new().spawn(Transform::IDENTITY)
+  }
+
+  // a second of a steady level on both sides
+  fn tone() -> Arc<Clip> {
+    Arc::new(Clip { sample_rate: SAMPLE_RATE, frames: vec![[0.5; 2]; SAMPLE_RATE as usize] })
+  }
+
+  fn voice() -> VoiceSettings {
+    VoiceSettings { volume: 1.0, looping: true, spatial: true, paused: false }
+  }
+
+  // the second block, once the gain has glided up from silence
+  fn mix(settings: AudioSettings, position: Vec3) -> Vec<[f32; 2]> {
+    let mut mixer = Mixer::new(SAMPLE_RATE, settings);
+    let mut block = vec![[0.0; 2]; Mixer::BLOCK];
+
+    mixer.apply(Command::Play { entity: entity(), clip: tone(), settings: voice(), position });
+    mixer.render(&mut block, &mut Vec::new());
+    mixer.render(&mut block, &mut Vec::new());
+
+    block
+  }
+
+  fn level(block: &[[f32; 2]]) -> f32 {
+    block.iter().flatten().map(|sample| sample.abs()).sum()
+  }
+
+  #[test]
+  fn attenuates_with_distance() {
+    let near = mix(AudioSettings::default(), Vec3::new(0.0, 0.0, -1.0));
+    let far = mix(AudioSettings::default(), Vec3::new(0.0, 0.0, -8.0));
+
+    assert!(level(&far) > 0.0);
+    assert!(level(&far) < level(&near) * 0.5);
+  }
+
+  #[test]
+  fn plays_the_same_into_both_ears_on_headphones() {
+    let headphones = AudioSettings { mode: AudioMode::Headphones, ..AudioSettings::default() };
+    let block = mix(headphones, Vec3::new(5.0, 0.0, 0.0));
+
+    assert!(level(&block) > 0.0);
+    assert!(block.iter().all(|[left, right]| left == right));
+  }
+
+  #[test]
+  fn reports_clips_that_end_while_running() {
+    let (commands, receiver) = mpsc::channel();
+    let (finished, ended) = mpsc::channel();
+    let source = entity();
+
+    let mixer = thread::spawn(move || Mixer::new(SAMPLE_RATE, AudioSettings::default()).run(Box::new(NullOutput::new(SAMPLE_RATE)), receiver, finished));
+    let clip = Arc::new(Clip { sample_rate: SAMPLE_RATE, frames: vec![[0.5; 2]; 100] });
+
+    commands.send(Command::Play { entity: source, clip, settings: VoiceSettings { looping: false, ..voice() }, position: Vec3::ZERO }).unwrap();
+    assert_eq!(ended.recv_timeout(Duration::from_secs(1)), Ok(source));
+
+    commands.send(Command::Quit).unwrap();
+    let mixer = mixer.join().unwrap();
+    assert_eq!(mixer.sample_rate, SAMPLE_RATE);
+  }
+}
@@ -0,0 +1,242 @@
+use sdl2::audio::AudioCallback;
+
+use std::{
+  collections::VecDeque,
+  fs::File,
+  io::{self, BufWriter, Seek, SeekFrom, Write},
+  path::Path,
+  sync::{Arc, Condvar, Mutex, PoisonError},
+  thread,
+  time::{Duration, Instant}
+};
+
+// where the mixer thread sends what it mixed, as pairs of left and right samples. writing is what paces the mixer, so
+// an output holds on to the thread until it is ready for more
+pub trait AudioOutput: Send {
+  fn sample_rate(&self) -> u32;
+  fn write(&mut self, frames: &[[f32; 2]]);
+}
+
+// frames mixed ahead of the sound card, shared between the mixer's end and the one sdl pulls from
+#[derive(Clone, Default)]
+pub struct SpeakerQueue(Arc<(Mutex<VecDeque<[f32; 2]>>, Condvar)>);
+
+impl SpeakerQueue {
+  pub fn playback(&self, channels: u8) -> Playback {
+    Playback { queue: self.clone(), channels: channels.max(1) as usize }
+  }
+
+  pub fn speakers(&self, sample_rate: u32) -> Speakers {
+    Speakers { queue: self.clone(), sample_rate }
+  }
+}
+
+// sdl's end of the queue, called from its audio thread whenever the device wants more. runs dry into silence
+pub struct Playback {
+  queue: SpeakerQueue,
+  channels: usize
+}
+
+impl AudioCallback for Playback {
+  type Channel = f32;
+
+  fn callback(&mut self, output: &mut [f32]) {
+    let (queued, taken) = &*self.queue.0;
+    let mut queued = queued.lock().unwrap_or_else(PoisonError::into_inner);
+
+    for frame in output.chunks_mut(self.channels) {
+      let [left, right] = queued.pop_front().unwrap_or([0.0; 2]);
+
+      match frame {
+        [mono]                     => *mono = (left + right) * 0.5,
+        [first, second, rest @ ..] => {
+          (*first, *second) = (left, right);
+          rest.fill(0.0);
+        },
+        []                         => {}
+      }
+    }
+
+    taken.notify_one();
+  }
+}
+
+// the sound card, through a device the window opened
+pub struct Speakers {
+  queue: SpeakerQueue,
+  sample_rate: u32
+}
+
+impl Speakers {
+  // how far ahead of the device the mixer gets, in seconds
+  const LATENCY: f32 = 0.04;
+  // waited for the device to take frames before giving up on the block, when it has stopped pulling
+  const STALL: Duration = Duration::from_millis(100);
+}
+
+impl AudioOutput for Speakers {
+  fn sample_rate(&self) -> u32 {
+    self.sample_rate
+  }
+
+  fn write(&mut self, frames: &[[f32; 2]]) {
+    let (queued, taken) = &*self.queue.0;
+    let ahead = (self.sample_rate as f32 * Speakers::LATENCY) as usize;
+    let mut queued = queued.lock().unwrap_or_else(PoisonError::into_inner);
+
+    while queued.len() + frames.len() > ahead.max(frames.len()) {
+      let (guard, wait) = taken.wait_timeout(queued, Speakers::STALL).unwrap_or_else(PoisonError::into_inner);
+      queued = guard;
+
+      if wait.timed_out() { return; }
+    }
+
+    queued.extend(frames);
+  }
+}
+
+// keeps an output with no device behind it taking frames at the rate a device would
+struct Pacer {
+  sample_rate: u32,
+  started: Option<Instant>,
+  frames: u64
+}
+
+impl Pacer {
+  fn new(sample_rate: u32) -> Self {
+    Pacer { sample_rate, started: None, frames: 0 }
+  }
+
+  // waits until the frames are due to start playing
+  fn wait(&mut self, frames: usize) {
+    let started = *self.started.get_or_insert_with(Instant::now);
+    let due = started + Duration::from_secs_f64(self.frames as f64 / self.sample_rate as f64);
+    self.frames += frames as u64;
+
+    if let Some(ahead) = due.checked_duration_since(Instant::now()) {
+      thread::sleep(ahead);
+    }
+  }
+}
+
+// throws everything away, for running without sound hardware
+pub struct NullOutput {
+  pacer: Pacer
+}
+
+impl NullOutput {
+  pub fn new(sample_rate: u32) -> Self {
+    NullOutput { pacer: Pacer::new(sample_rate) }
+  }
+}
+
+impl AudioOutput for NullOutput {
+  fn sample_rate(&self) -> u32 {
+    self.pacer.sample_rate
+  }
+
+  fn write(&mut self, frames: &[[f32; 2]]) {
+    self.pacer.wait(frames.len());
+  }
+}
+
+// records everything to a 32 bit float stereo wav file, in real time like a device would play it. the sizes in the
+// header are filled in when it is dropped
+pub struct WavOutput {
+  file: BufWriter<File>,
+  pacer: Pacer
+}
+
+impl WavOutput {
+  pub fn create(path: &Path, sample_rate: u32) -> io::Result<WavOutput> {
+    let mut output = WavOutput { file: BufWriter::new(File::create(path)?), pacer: Pacer::new(sample_rate) };
+    output.header()?;
+
+    Ok(output)
+  }
+
+  fn header(&mut self) -> io::Result<()> {
+    let rate = self.pacer.sample_rate;
+    let data = (self.pacer.frames * 8).min(u32::MAX as u64 - 36) as u32;
+
+    self.file.write_all(b"RIFF")?;
+    self.file.write_all(&(36 + data).to_le_bytes())?;
+    self.file.write_all(b"WAVEfmt ")?;
+    self.file.write_all(&16u32.to_le_bytes())?;
+    // ieee float, 2 channels, the rate, bytes per second, bytes per frame and bits per sample
+    self.file.write_all(&3u16.to_le_bytes())?;
+    self.file.write_all(&2u16.to_le_bytes())?;
+    self.file.write_all(&rate.to_le_bytes())?;
+    self.file.write_all(&(rate * 8).to_le_bytes())?;
+    self.file.write_all(&8u16.to_le_bytes())?;
+    self.file.write_all(&32u16.to_le_bytes())?;
+    self.file.write_all(b"data")?;
+    self.file.write_all(&data.to_le_bytes())
+  }
+
+  fn finish(&mut self) -> io::Result<()> {
+    self.file.seek(SeekFrom::Start(0))?;
+    self.header()?;
+    self.file.flush()
+  }
+}
+
+impl AudioOutput for WavOutput {
+  fn sample_rate(&self) -> u32 {
+    self.pacer.sample_rate
+  }
+
+  fn write(&mut self, frames: &[[f32; 2]]) {
+    for sample in frames.iter().flatten() {
+      if let Err(error) = self.file.write_all(&sample.to_le_bytes()) {
+        panic!("failed to write audio capture with error: {error}");
+      }
+    }
+
+    self.pacer.wait(frames.len());
+  }
+}
+
+impl Drop for WavOutput {
+  fn drop(&mut self) {
+    // there is nothing left to report a failure to, the file just keeps the sizes it had
+    self.finish().ok();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::fs;
+
+  #[test]
+  fn writes_a_wav_header_matching_the_frames() {
+    let path = std::env::temp_dir().join(format!("wav_output_{}.wav", std::process::id()));
+    let frames: Vec<[f32; 2]> = (0..300).map(|frame| [frame as f32 / 300.0, -(frame as f32) / 300.0]).collect();
+
+    let mut output = match WavOutput::create(&path, 48000) {
+      Ok(output) => output,
+      Err(error) => panic!("failed to create {} with error: {error}", path.display())
+    };
+
+    output.write(&frames[..100]);
+    output.write(&frames[100..]);
+    drop(output);
+
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).ok();
+
+    let word = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let half = |offset: usize| u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap());
+
+    assert_eq!(bytes.len(), 44 + frames.len() * 8);
+    assert_eq!((&bytes[0..4], &bytes[8..16], &bytes[36..40]), (&b"RIFF"[..], &b"WAVEfmt "[..], &b"data"[..]));
+    assert_eq!(word(4) as usize, bytes.len() - 8);
+    assert_eq!((half(20), half(22), word(24), word(28), half(32), half(34)), (3, 2, 48000, 48000 * 8, 8, 32));
+    assert_eq!(word(40) as usize, frames.len() * 8);
+
+    let samples: Vec<f32> = bytes[44..].chunks_exact(4).map(|sample| f32::from_le_bytes(sample.try_into().unwrap())).collect();
+    assert_eq!(samples, frames.concat());
+  }
+}
@@ -0,0 +1,138 @@
+use glam::Vec3;
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+// one side of the head: how late and through what filter a sound reaches it
+#[derive(Clone, Copy, Debug)]
+struct Ear {
+  // in samples, behind the sound arriving at the center of the head
+  delay: f32,
+  // how much of the highs the head lets through, 2 for an ear facing the sound and 0.1 for one behind the head
+  shadow: f32,
+  // of the shadow filter, the last sample in and out
+  input: f32,
+  output: f32
+}
+
+impl Ear {
+  const LEFT: Vec3 = Vec3::NEG_X;
+  const RIGHT: Vec3 = Vec3::X;
+
+  fn new() -> Self {
+    Ear { delay: 0.0, shadow: 1.0, input: 0.0, output: 0.0 }
+  }
+}
+
+// places a mono sound around the listener with a spherical head model (brown and duda's). the ear further away hears
+// it later, by the extra path around the head, and duller, through a shelf taking off more of the highs the more the
+// head is in the way. that places sounds around the head without a measured dataset, though with no ears modelled
+// sounds ahead and behind are heard alike
+pub struct Binaural {
+  // the last of the input, long enough for a block and the largest delay at any sample rate
+  history: Vec<f32>,
+  written: usize,
+  ears: [Ear; 2],
+  // whether the ears were placed yet, until then they jump straight to where they should be
+  placed: bool
+}
+
+impl Default for Binaural {
+  fn default() -> Self {
+    Binaural {
+      history: vec![0.0; Binaural::HISTORY],
+      written: 0,
+      ears: [Ear::new(); 2],
+      placed: false
+    }
+  }
+}
+
+impl Binaural {
+  // meters, an average head
+  const HEAD_RADIUS: f32 = 0.0875;
+  const MIN_SHADOW: f32 = 0.1;
+  // from the ear, where the head shadows the most. past it sound bends around the head and brightens again
+  const MIN_SHADOW_ANGLE: f32 = 150.0 * PI / 180.0;
+  const HISTORY: usize = 1024;
+
+  // adds the input to both sides of the output coming from a normalized direction in the listener's space (x to the
+  // right, y up and -z ahead). the delays and shadows glide over the block from where the last one left them so moving
+  // sounds do not click
+  pub fn process(&mut self, input: &[f32], direction: Vec3, sample_rate: f32, speed_of_sound: f32, output: &mut [[f32; 2]]) {
+    let head = Binaural::HEAD_RADIUS / speed_of_sound;
+    let targets = [Ear::LEFT, Ear::RIGHT].map(|axis| {
+      let angle = direction.dot(axis).clamp(-1.0, 1.0).acos();
+
+      // woodworth's path around the head, with the near ear hearing it as far ahead as the far one is behind
+      let delay = if angle < FRAC_PI_2 { head * (1.0 - angle.cos()) } else { head * (1.0 + angle - FRAC_PI_2) };
+
+      let shadow = (1.0 + Binaural::MIN_SHADOW / 2.0) + (1.0 - Binaural::MIN_SHADOW / 2.0) * (angle / Binaural::MIN_SHADOW_ANGLE * PI).cos();
+      (delay * sample_rate, shadow)
+    });
+
+    if !self.placed {
+      for (ear, (delay, shadow)) in self.ears.iter_mut().zip(targets) {
+        (ear.delay, ear.shadow) = (delay, shadow);
+      }
+
+      self.placed = true;
+    }
+
+    // the shelf's corner is where the wavelength is around the head's size, discretized with the bilinear transform
+    let corner = 2.0 * speed_of_sound / Binaural::HEAD_RADIUS;
+    let warp = 2.0 * sample_rate;
+    let scale = 1.0 / (corner + warp);
+    let pole = (corner - warp) * scale;
+
+    // the block goes in first so every ear reads back from the frame it is on
+    let mask = self.history.len() - 1;
+    let frames = input.len().min(output.len());
+    let first = self.written;
+
+    for sample in &input[..frames] {
+      self.history[self.written & mask] = *sample;
+      self.written = self.written.wrapping_add(1);
+    }
+
+    for (side, (delay, shadow)) in targets.into_iter().enumerate() {
+      let ear = &mut self.ears[side];
+      let steps = frames.max(1) as f32;
+      let (delay_step, shadow_step) = ((delay - ear.delay) / steps, (shadow - ear.shadow) / steps);
+
+      for (frame, mixed) in output[..frames].iter_mut().enumerate() {
+        let back = (ear.delay + delay_step * frame as f32).max(0.0);
+        let shadow = ear.shadow + shadow_step * frame as f32;
+
+        let whole = back as usize;
+        let blend = back - whole as f32;
+        let newer = self.history[first.wrapping_add(frame).wrapping_sub(whole) & mask];
+        let older = self.history[first.wrapping_add(frame).wrapping_sub(whole + 1) & mask];
+        let delayed = newer + (older - newer) * blend;
+
+        let sample = (corner + shadow * warp) * scale * delayed + (corner - shadow * warp) * scale * ear.input - pole * ear.output;
+        (ear.input, ear.output) = (delayed, sample);
+
+        mixed[side] += sample;
+      }
+
+      (ear.delay, ear.shadow) = (delay, shadow);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn favors_the_ear_facing_the_sound() {
+    let mut binaural = Binaural::default();
+    let input: Vec<f32> = (0..256).map(|frame| (frame as f32 * 0.3).sin()).collect();
+    let mut output = vec![[0.0; 2]; input.len()];
+
+    binaural.process(&input, Vec3::X, 48000.0, 343.0, &mut output);
+
+    let [left, right] = output.iter().fold([0.0; 2], |[left, right], frame| [left + frame[0].abs(), right + frame[1].abs()]);
+    assert!(right > left);
+  }
+}
@@ -0,0 +1,196 @@
+use crate::gvp_engine::mesh::MeshData;
+
+use glam::{Mat4, Vec3};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// a node of a bounding volume hierarchy laid out the way the path tracer reads it. leaves hold count items starting at
+// first, inner nodes have a count of 0 and their two children at first and first + 1
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BvhNode {
+  pub min: Vec3,
+  pub first: u32,
+  pub max: Vec3,
+  pub count: u32
+}
+
+// built top down over the bounds of whatever it holds, splitting where the surface area heuristic says is cheapest to
+// trace through. leaves refer to ranges of order, which lists the items the way they should be stored
+pub struct Bvh {
+  pub nodes: Vec<BvhNode>,
+  pub order: Vec<u32>
+}
+
+impl Bvh {
+  // candidate splits per axis
+  const BINS: usize = 12;
+  // nodes this small are never split
+  const LEAF_SIZE: usize = 2;
+
+  pub fn build(bounds: &[(Vec3, Vec3)]) -> Self {
+    let mut order: Vec<u32> = (0..bounds.len() as u32).collect();
+    if bounds.is_empty() { return Bvh { nodes: Vec::new(), order }; }
+
+    let centers: Vec<Vec3> = bounds.iter().map(|(min, max)| (*min + *max) * 0.5).collect();
+    let mut nodes = vec![Bvh::node(bounds, &order, 0)];
+    let mut stack = vec![0];
+
+    while let Some(index) = stack.pop() {
+      let (first, count) = (nodes[index].first as usize, nodes[index].count as usize);
+      if count <= Bvh::LEAF_SIZE { continue; }
+
+      let items = &mut order[first..first + count];
+      let Some((axis, split, low, extent)) = Bvh::split(&nodes[index], bounds, &centers, items) else { continue; };
+
+      // items whose centers land in the bins up to the split go left
+      let bin = |item: u32| (((centers[item as usize][axis] - low) / extent * Bvh::BINS as f32) as usize).min(Bvh::BINS - 1);
+      let mut left = 0;
+
+      for position in 0..count {
+        if bin(items[position]) <= split {
+          items.swap(position, left);
+          left += 1;
+        }
+      }
+
+      if left == 0 || left == count { continue; }
+
+      let child = nodes.len();
+      nodes.push(Bvh::node(bounds, &order[first..first + left], first));
+      nodes.push(Bvh::node(bounds, &order[first + left..first + count], first + left));
+
+      nodes[index].first = child as u32;
+      nodes[index].count = 0;
+      stack.extend([child, child + 1]);
+    }
+
+    Bvh { nodes, order }
+  }
+
+  // a leaf over the items, which start at first in the order
+  fn node(bounds: &[(Vec3, Vec3)], items: &[u32], first: usize) -> BvhNode {
+    let (min, max) = items.iter().fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), item| {
+      let (item_min, item_max) = bounds[*item as usize];
+      (min.min(item_min), max.max(item_max))
+    });
+
+    BvhNode { min, first: first as u32, max, count: items.len() as u32 }
+  }
+
+  // the axis and the last bin on the left of the cheapest split, along with the range the bins cover. none when keeping
+  // the node as a leaf is cheaper
+  fn split(node: &BvhNode, bounds: &[(Vec3, Vec3)], centers: &[Vec3], items: &[u32]) -> Option<(usize, usize, f32, f32)> {
+    let (low, high) = items.iter().fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(low, high), item| {
+      (low.min(centers[*item as usize]), high.max(centers[*item as usize]))
+    });
+
+    let mut best: Option<(f32, usize, usize)> = None;
+
+    for axis in 0..3 {
+      let extent = high[axis] - low[axis];
+      if extent <= 1e-6 { continue; }
+
+      let mut counts = [0usize; Bvh::BINS];
+      let mut boxes = [(Vec3::INFINITY, Vec3::NEG_INFINITY); Bvh::BINS];
+
+      for item in items {
+        let bin = (((centers[*item as usize][axis] - low[axis]) / extent * Bvh::BINS as f32) as usize).min(Bvh::BINS - 1);
+        let (min, max) = bounds[*item as usize];
+
+        counts[bin] += 1;
+        boxes[bin] = (boxes[bin].0.min(min), boxes[bin].1.max(max));
+      }
+
+      // costs of everything left of each split, then added to everything right of it sweeping back
+      let mut left_costs = [0.0; Bvh::BINS];
+      let (mut count, mut area) = (0, (Vec3::INFINITY, Vec3::NEG_INFINITY));
+
+      for bin in 0..Bvh::BINS - 1 {
+        count += counts[bin];
+        area = (area.0.min(boxes[bin].0), area.1.max(boxes[bin].1));
+        left_costs[bin] = count as f32 * Bvh::area(area.0, area.1);
+      }
+
+      let (mut count, mut area) = (0, (Vec3::INFINITY, Vec3::NEG_INFINITY));
+
+      for bin in (1..Bvh::BINS).rev() {
+        count += counts[bin];
+        area = (area.0.min(boxes[bin].0), area.1.max(boxes[bin].1));
+        let cost = left_costs[bin - 1] + count as f32 * Bvh::area(area.0, area.1);
+
+        if best.is_none_or(|(best, _, _)| cost < best) {
+          best = Some((cost, axis, bin - 1));
+        }
+      }
+    }
+
+    let (cost, axis, split) = best?;
+    let leaf = items.len() as f32 * Bvh::area(node.min, node.max);
+
+    (cost < leaf).then_some((axis, split, low[axis], high[axis] - low[axis]))
+  }
+
+  // half the surface area of a box, which is all the heuristic compares
+  fn area(min: Vec3, max: Vec3) -> f32 {
+    let extent = (max - min).max(Vec3::ZERO);
+    extent.x * extent.y + extent.y * extent.z + extent.z * extent.x
+  }
+}
+
+// a triangle as the path tracer reads it, as one corner and the edges away from it with a normal at every corner
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BvhTriangle {
+  pub position: [f32; 4],
+  pub edges: [[f32; 4]; 2],
+  pub normals: [[f32; 4]; 3]
+}
+
+static NEXT_MESH_BVH: AtomicU64 = AtomicU64::new(0);
+
+// a mesh's triangles in its own space, stored in the order its hierarchy refers to them. every one built gets its own
+// id so the path tracer can tell meshes apart even when one is freed and another is made in its place
+pub struct MeshBvh {
+  pub id: u64,
+  pub nodes: Vec<BvhNode>,
+  pub triangles: Vec<BvhTriangle>
+}
+
+impl MeshBvh {
+  pub fn build(data: &MeshData) -> Self {
+    let vertices: Vec<[usize; 3]> = data.indices.chunks_exact(3).map(|triangle| [0, 1, 2].map(|corner| triangle[corner] as usize)).collect();
+    let corners: Vec<[Vec3; 3]> = vertices.iter().map(|triangle| triangle.map(|vertex| Vec3::from(data.vertices[vertex].position))).collect();
+
+    let bounds: Vec<(Vec3, Vec3)> = corners.iter().map(|[a, b, c]| (a.min(*b).min(*c), a.max(*b).max(*c))).collect();
+    let bvh = Bvh::build(&bounds);
+
+    let triangles = bvh.order.iter().map(|triangle| {
+      let [a, b, c] = corners[*triangle as usize];
+      let normal = |corner: usize| Vec3::from(data.vertices[vertices[*triangle as usize][corner]].normal).extend(0.0).to_array();
+
+      BvhTriangle {
+        position: a.extend(1.0).to_array(),
+        edges: [(b - a).extend(0.0).to_array(), (c - a).extend(0.0).to_array()],
+        normals: [normal(0), normal(1), normal(2)]
+      }
+    }).collect();
+
+    MeshBvh { id: NEXT_MESH_BVH.fetch_add(1, Ordering::Relaxed), nodes: bvh.nodes, triangles }
+  }
+
+  // the box around the mesh once transformed, from the corners of the box around it untransformed
+  pub fn bounds(&self, transform: &Mat4) -> (Vec3, Vec3) {
+    let Some(root) = self.nodes.first() else { return (Vec3::ZERO, Vec3::ZERO); };
+
+    (0..8).map(|corner| {
+      let point = Vec3::new(
+        if corner & 1 == 0 { root.min.x } else { root.max.x },
+        if corner & 2 == 0 { root.min.y } else { root.max.y },
+        if corner & 4 == 0 { root.min.z } else { root.max.z }
+      );
+
+      transform.transform_point3(point)
+    }).fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), point| (min.min(point), max.max(point)))
+  }
+}
@@ -0,0 +1,53 @@
+use crate::gvp_engine::transform::Transform;
+
+use glam::{Mat4, Vec3, Vec4};
+
+pub struct Camera {
+  pub transform: Transform,
+  pub fov_y: f32,
+  pub near: f32,
+  pub far: f32
+}
+
+impl Default for Camera {
+  fn default() -> Self {
+    Camera {
+      transform: Transform::from_translation(Vec3::new(0.0, 1.6, 3.0)),
+      fov_y: 70f32.to_radians(),
+      near: 0.05,
+      far: 500.0
+    }
+  }
+}
+
+impl Camera {
+  pub fn view(&self) -> Mat4 {
+    self.transform.matrix().inverse()
+  }
+
+  // right handed with a [0, 1] depth range. y is flipped since vulkan clip space points y down
+  pub fn projection(&self, aspect_ratio: f32) -> Mat4 {
+    let mut projection = Mat4::perspective_rh(self.fov_y, aspect_ratio, self.near, self.far);
+    projection.y_axis.y *= -1.0;
+
+    projection
+  }
+
+  // off axis projection for a headset eye. the angles are measured from the view direction, with left and down being
+  // negative like openxr reports them
+  pub fn fov_projection(&self, left: f32, right: f32, up: f32, down: f32) -> Mat4 {
+    let (left, right, up, down) = (left.tan(), right.tan(), up.tan(), down.tan());
+    let (width, height, depth) = (right - left, up - down, self.far - self.near);
+
+    Mat4::from_cols(
+      Vec4::new(2.0 / width, 0.0, 0.0, 0.0),
+      Vec4::new(0.0, -2.0 / height, 0.0, 0.0),
+      Vec4::new((right + left) / width, -(up + down) / height, -self.far / depth, -1.0),
+      Vec4::new(0.0, 0.0, -self.far * self.near / depth, 0.0)
+    )
+  }
+
+  pub fn position(&self) -> Vec3 {
+    self.transform.translation
+  }
+}
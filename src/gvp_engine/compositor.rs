@@ -0,0 +1,366 @@
+mod panel;
+
+pub use panel::*;
+
+use crate::gvp_engine::{
+  allocator::{Allocator, Image},
+  gaze::{GazeFade, GazeFadeSettings, GazeTarget},
+  gpu::GPU,
+  mesh::Mesh,
+  texture::{SamplerInfo, Texture}
+};
+
+use ash::vk;
+use glam::{Mat4, Vec2, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PanelId(usize);
+
+// everything the renderer needs to draw a panel. the descriptor set is bound as set 1 of the panel pipeline
+pub struct PanelDraw<'a> {
+  pub mesh: &'a Mesh,
+  pub transform: Mat4,
+  pub descriptor_set: vk::DescriptorSet,
+  pub opacity: f32
+}
+
+// where a ray met a panel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PanelHit {
+  pub panel: PanelId,
+  // in pixels of the panel's target from its top left, which is what the app sees
+  pub position: Vec2,
+  pub uv: Vec2,
+  pub point: Vec3,
+  pub distance: f32
+}
+
+struct Entry {
+  panel: Panel,
+  target: Image,
+  mesh: Mesh,
+  descriptor_set: vk::DescriptorSet
+}
+
+// gives every app panel an offscreen target and hands the renderer the quads showing them, faded by where the user
+// looks. the renderer sorts them in with the rest of the transparent geometry
+pub struct Compositor {
+  descriptor_set_layout: vk::DescriptorSetLayout,
+  descriptor_pool: vk::DescriptorPool,
+  sampler: vk::Sampler,
+  // ids index into this and are not reused once a panel is destroyed
+  entries: Vec<Option<Entry>>,
+  gaze: GazeFade<PanelId>,
+  // seconds since the engine started at the last update, for smoothing the fades
+  last_update: Option<f32>
+}
+
+impl Compositor {
+  pub const FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+  const MAX_PANELS: u32 = 64;
+  // what a target shows until its app draws into it
+  const CLEAR_COLOR: [f32; 4] = [0.08, 0.08, 0.1, 0.85];
+
+  pub fn new(device: &ash::Device, gpu: &GPU) -> Self {
+    Compositor {
+      descriptor_set_layout: Compositor::create_descriptor_set_layout(device),
+      descriptor_pool: Compositor::create_descriptor_pool(device),
+      sampler: Texture::create_sampler(device, gpu, &SamplerInfo::CLAMP, 1),
+      entries: Vec::new(),
+      gaze: GazeFade::new(GazeFadeSettings::default()),
+      last_update: None
+    }
+  }
+
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    for entry in self.entries.iter_mut().flatten() {
+      allocator.destroy_image(device, &entry.target);
+      entry.mesh.clean(device, allocator);
+    }
+
+    unsafe {
+      device.destroy_sampler(self.sampler, None);
+      device.destroy_descriptor_pool(self.descriptor_pool, None);
+      device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+    };
+  }
+
+  pub fn layout(&self) -> vk::DescriptorSetLayout {
+    self.descriptor_set_layout
+  }
+
+  pub fn create_panel(&mut self, device: &ash::Device, allocator: &Allocator, panel: Panel) -> PanelId {
+    let target = Compositor::create_target(device, allocator, panel.extent());
+    let mesh = Mesh::upload(device, allocator, &panel.mesh_data());
+    let descriptor_set = self.allocate_descriptor_set(device);
+
+    self.write_descriptor_set(device, descriptor_set, &target);
+    self.entries.push(Some(Entry { panel, target, mesh, descriptor_set }));
+
+    PanelId(self.entries.len() - 1)
+  }
+
+  pub fn destroy_panel(&mut self, device: &ash::Device, allocator: &Allocator, id: PanelId) {
+    let Some(mut entry) = self.entries.get_mut(id.0).and_then(Option::take) else { return; };
+
+    // the target may still be read by a frame in flight
+    if let Err(error) = unsafe { device.device_wait_idle() } {
+      panic!("failed to wait for device idle with error: {error}")
+    }
+
+    allocator.destroy_image(device, &entry.target);
+    entry.mesh.clean(device, allocator);
+
+    if let Err(error) = unsafe { device.free_descriptor_sets(self.descriptor_pool, &[entry.descriptor_set]) } {
+      panic!("failed to free panel descriptor set with error: {error}")
+    }
+  }
+
+  pub fn panel(&self, id: PanelId) -> &Panel {
+    &self.entry(id).panel
+  }
+
+  pub fn panels(&self) -> impl Iterator<Item = (PanelId, &Panel)> {
+    self.entries.iter().enumerate().filter_map(|(index, entry)| entry.as_ref().map(|entry| (PanelId(index), &entry.panel)))
+  }
+
+  // the target is rebuilt when the panel's pixel size changes, which loses what the app drew, and the mesh whenever
+  // its shape or size does
+  pub fn update_panel(&mut self, device: &ash::Device, allocator: &Allocator, id: PanelId, panel: Panel) {
+    let entry = self.entry(id);
+    let resized = entry.panel.extent() != panel.extent();
+    let reshaped = entry.panel.shape != panel.shape || entry.panel.size != panel.size;
+
+    if resized || reshaped {
+      if let Err(error) = unsafe { device.device_wait_idle() } {
+        panic!("failed to wait for device idle with error: {error}")
+      }
+    }
+
+    if resized {
+      let target = Compositor::create_target(device, allocator, panel.extent());
+      let descriptor_set = self.entry(id).descriptor_set;
+      self.write_descriptor_set(device, descriptor_set, &target);
+
+      let old = std::mem::replace(&mut self.entry_mut(id).target, target);
+      allocator.destroy_image(device, &old);
+    }
+
+    if reshaped {
+      let mut old = std::mem::replace(&mut self.entry_mut(id).mesh, Mesh::upload(device, allocator, &panel.mesh_data()));
+      old.clean(device, allocator);
+    }
+
+    self.entry_mut(id).panel = panel;
+  }
+
+  // the image the panel's app draws into, left in SHADER_READ_ONLY_OPTIMAL between frames
+  pub fn target(&self, id: PanelId) -> &Image {
+    &self.entry(id).target
+  }
+
+  pub fn gaze_settings_mut(&mut self) -> &mut GazeFadeSettings {
+    &mut self.gaze.settings
+  }
+
+  // what the panel is drawn with, its own opacity faded by the gaze
+  pub fn opacity(&self, id: PanelId) -> f32 {
+    self.entry(id).panel.opacity * self.gaze.opacity(id)
+  }
+
+  // moves the gaze fades along, time being seconds since the engine started
+  pub fn update(&mut self, head: Mat4, time: f32) {
+    let delta = time - self.last_update.unwrap_or(time);
+    self.last_update = Some(time);
+
+    let targets = self.panels()
+      .filter(|(_, panel)| panel.visible && panel.gaze_fade)
+      .map(|(id, panel)| (id, GazeTarget { position: panel.transform.translation, radius: panel.radius() }))
+      .collect::<Vec<_>>();
+
+    self.gaze.update(head, targets, delta);
+  }
+
+  pub fn draws(&self) -> Vec<PanelDraw<'_>> {
+    self.entries.iter().enumerate()
+      .filter_map(|(index, entry)| entry.as_ref().map(|entry| (PanelId(index), entry)))
+      .filter(|(_, entry)| entry.panel.visible)
+      .map(|(id, entry)| PanelDraw {
+        mesh: &entry.mesh,
+        transform: entry.panel.transform.matrix(),
+        descriptor_set: entry.descriptor_set,
+        opacity: self.opacity(id)
+      })
+      .filter(|draw| draw.opacity > 0.0)
+      .collect()
+  }
+
+  // the nearest visible panel along a pose's -z. the scene does not block the ray
+  pub fn hit_test(&self, ray: Mat4) -> Option<PanelHit> {
+    let origin = ray.w_axis.truncate();
+    let direction = ray.transform_vector3(Vec3::NEG_Z).normalize_or_zero();
+
+    self.panels().filter(|(_, panel)| panel.visible).filter_map(|(id, panel)| {
+      let to_panel = panel.transform.matrix().inverse();
+
+      // the ray stays parameterized the same way in the panel's space, so distances along it carry over
+      let (distance, uv) = panel.intersect(to_panel.transform_point3(origin), to_panel.transform_vector3(direction))?;
+      let extent = panel.extent();
+
+      Some(PanelHit {
+        panel: id,
+        position: uv * Vec2::new(extent.width as f32, extent.height as f32),
+        uv,
+        point: origin + direction * distance,
+        distance
+      })
+    }).min_by(|a, b| a.distance.total_cmp(&b.distance))
+  }
+
+  fn entry(&self, id: PanelId) -> &Entry {
+    match self.entries.get(id.0).and_then(Option::as_ref) {
+      Some(entry) => entry,
+      None        => panic!("failed to find panel {} with error: it was destroyed", id.0)
+    }
+  }
+
+  fn entry_mut(&mut self, id: PanelId) -> &mut Entry {
+    match self.entries.get_mut(id.0).and_then(Option::as_mut) {
+      Some(entry) => entry,
+      None        => panic!("failed to find panel {} with error: it was destroyed", id.0)
+    }
+  }
+
+  // cleared to the panel background and left ready to be sampled
+  fn create_target(device: &ash::Device, allocator: &Allocator, extent: vk::Extent2D) -> Image {
+    let create_info = {
+      vk::ImageCreateInfo::default()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(Compositor::FORMAT)
+        .extent(extent.into())
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+    };
+
+    let target = allocator.create_image(device, &create_info, vk::ImageAspectFlags::COLOR, vk::ImageViewType::TYPE_2D);
+    let range = Allocator::color_range(0, 1, 0, 1);
+
+    allocator.submit(device, |command_buffer| {
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        target.image,
+        range,
+        (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+        (vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE),
+        (vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::TRANSFER)
+      );
+
+      let clear = vk::ClearColorValue { float32: Compositor::CLEAR_COLOR };
+      unsafe { device.cmd_clear_color_image(command_buffer, target.image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &clear, &[range]) };
+
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        target.image,
+        range,
+        (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+        (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ),
+        (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::FRAGMENT_SHADER)
+      );
+    });
+
+    target
+  }
+
+  fn allocate_descriptor_set(&self, device: &ash::Device) -> vk::DescriptorSet {
+    let layouts = [self.descriptor_set_layout];
+
+    let allocate_info = {
+      vk::DescriptorSetAllocateInfo::default()
+        .descriptor_pool(self.descriptor_pool)
+        .set_layouts(&layouts)
+    };
+
+    match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+      Ok(sets)   => sets[0],
+      Err(error) => panic!("failed to allocate panel descriptor set (at most {} panels) with error: {error}", Compositor::MAX_PANELS)
+    }
+  }
+
+  fn write_descriptor_set(&self, device: &ash::Device, descriptor_set: vk::DescriptorSet, target: &Image) {
+    let image_info = [
+      vk::DescriptorImageInfo::default()
+        .image_view(target.view)
+        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+    ];
+
+    let sampler_info = [vk::DescriptorImageInfo::default().sampler(self.sampler)];
+
+    let writes = [
+      vk::WriteDescriptorSet::default()
+        .dst_set(descriptor_set)
+        .dst_binding(0)
+        .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+        .image_info(&image_info),
+      vk::WriteDescriptorSet::default()
+        .dst_set(descriptor_set)
+        .dst_binding(1)
+        .descriptor_type(vk::DescriptorType::SAMPLER)
+        .image_info(&sampler_info)
+    ];
+
+    unsafe { device.update_descriptor_sets(&writes, &[]) };
+  }
+
+  fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+    let bindings = [
+      vk::DescriptorSetLayoutBinding::default()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+      vk::DescriptorSetLayoutBinding::default()
+        .binding(1)
+        .descriptor_type(vk::DescriptorType::SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+    ];
+
+    let create_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+    match unsafe { device.create_descriptor_set_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create panel descriptor set layout with error: {error}")
+    }
+  }
+
+  // sets are freed along with their panels
+  fn create_descriptor_pool(device: &ash::Device) -> vk::DescriptorPool {
+    let pool_sizes = [
+      vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::SAMPLED_IMAGE)
+        .descriptor_count(Compositor::MAX_PANELS),
+      vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::SAMPLER)
+        .descriptor_count(Compositor::MAX_PANELS)
+    ];
+
+    let create_info = {
+      vk::DescriptorPoolCreateInfo::default()
+        .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+        .max_sets(Compositor::MAX_PANELS)
+        .pool_sizes(&pool_sizes)
+    };
+
+    match unsafe { device.create_descriptor_pool(&create_info, None) } {
+      Ok(pool)   => pool,
+      Err(error) => panic!("failed to create panel descriptor pool with error: {error}")
+    }
+  }
+}
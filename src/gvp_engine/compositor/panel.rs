@@ -0,0 +1,223 @@
+use crate::gvp_engine::{mesh::{MeshData, Vertex}, transform::Transform};
+
+use ash::vk;
+use glam::{Vec2, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PanelShape {
+  Flat,
+  // bent around a vertical axis radius meters in front of the panel, so it wraps around someone standing there
+  Curved { radius: f32 }
+}
+
+// a window in the world that an app draws into
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Panel {
+  // the center of the panel, which faces down +z towards whoever looks at it
+  pub transform: Transform,
+  // in meters, along the surface for curved panels
+  pub size: Vec2,
+  // pixels per inch of the app's target at the panel's size
+  pub dpi: f32,
+  pub shape: PanelShape,
+  pub visible: bool,
+  // multiplied with the gaze fade
+  pub opacity: f32,
+  // whether the panel dims while the user looks away from it
+  pub gaze_fade: bool
+}
+
+impl Panel {
+  pub const DEFAULT_DPI: f32 = 48.0;
+  // targets are kept within what every gpu can sample
+  const MAX_PIXELS: u32 = 4096;
+  const METERS_PER_INCH: f32 = 0.0254;
+  // columns a curved panel is built from
+  const CURVE_SEGMENTS: u32 = 32;
+
+  pub fn new(transform: Transform, size: Vec2) -> Self {
+    Panel {
+      transform,
+      size,
+      dpi: Panel::DEFAULT_DPI,
+      shape: PanelShape::Flat,
+      visible: true,
+      opacity: 1.0,
+      gaze_fade: true
+    }
+  }
+
+  pub fn extent(&self) -> vk::Extent2D {
+    let pixels = (self.size / Panel::METERS_PER_INCH * self.dpi).round();
+
+    vk::Extent2D {
+      width: (pixels.x as u32).clamp(1, Panel::MAX_PIXELS),
+      height: (pixels.y as u32).clamp(1, Panel::MAX_PIXELS)
+    }
+  }
+
+  // how far the panel reaches from its center, for the gaze fade
+  pub fn radius(&self) -> f32 {
+    (self.size * self.transform.scale.truncate()).length() * 0.5
+  }
+
+  // in the panel's own space, with uv 0, 0 at the top left like the app's target
+  pub fn mesh_data(&self) -> MeshData {
+    let columns = match self.shape {
+      PanelShape::Flat          => 1,
+      PanelShape::Curved { .. } => Panel::CURVE_SEGMENTS
+    };
+
+    let mut vertices = Vec::with_capacity((columns as usize + 1) * 2);
+
+    for column in 0..=columns {
+      let u = column as f32 / columns as f32;
+      let (position, normal, tangent) = self.surface(u);
+
+      for v in [0.0, 1.0] {
+        vertices.push(Vertex {
+          position: (position + Vec3::Y * (0.5 - v) * self.size.y).to_array(),
+          normal: normal.to_array(),
+          tangent: tangent.extend(1.0).to_array(),
+          uv: [u, v]
+        });
+      }
+    }
+
+    // counter clockwise seen from the front
+    let indices = (0..columns).flat_map(|column| {
+      let (top, bottom) = (column * 2, column * 2 + 1);
+      [top, bottom, bottom + 2, top, bottom + 2, top + 2]
+    }).collect();
+
+    MeshData { vertices, indices, ..MeshData::default() }
+  }
+
+  // where a ray in the panel's own space first hits its front, as the distance along the ray and the uv there
+  pub fn intersect(&self, origin: Vec3, direction: Vec3) -> Option<(f32, Vec2)> {
+    let uv = |point: Vec3, u: f32| {
+      let v = 0.5 - point.y / self.size.y;
+      ((0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v)).then_some(Vec2::new(u, v))
+    };
+
+    match self.shape {
+      PanelShape::Flat => {
+        if direction.z >= 0.0 { return None; }
+
+        let distance = -origin.z / direction.z;
+        if distance < 0.0 { return None; }
+
+        let point = origin + direction * distance;
+        uv(point, point.x / self.size.x + 0.5).map(|uv| (distance, uv))
+      },
+      PanelShape::Curved { radius } => {
+        // the cylinder around the vertical axis through the center of curvature, in the xz plane
+        let center = Vec3::new(0.0, 0.0, radius);
+        let offset = Vec2::new(origin.x - center.x, origin.z - center.z);
+        let heading = Vec2::new(direction.x, direction.z);
+
+        let a = heading.length_squared();
+        let b = 2.0 * offset.dot(heading);
+        let c = offset.length_squared() - radius * radius;
+        let discriminant = b * b - 4.0 * a * c;
+
+        if a <= f32::EPSILON || discriminant < 0.0 { return None; }
+
+        let root = discriminant.sqrt();
+
+        [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)].into_iter().filter(|distance| *distance >= 0.0).find_map(|distance| {
+          let point = origin + direction * distance;
+          // only the inside faces the viewer
+          let inward = Vec3::new(center.x - point.x, 0.0, center.z - point.z);
+          if direction.dot(inward) >= 0.0 { return None; }
+
+          let angle = point.x.atan2(radius - point.z);
+          uv(point, angle * radius / self.size.x + 0.5).map(|uv| (distance, uv))
+        })
+      }
+    }
+  }
+
+  // position, normal and tangent across the panel at u, halfway up
+  fn surface(&self, u: f32) -> (Vec3, Vec3, Vec3) {
+    let offset = (u - 0.5) * self.size.x;
+
+    match self.shape {
+      PanelShape::Flat              => (Vec3::new(offset, 0.0, 0.0), Vec3::Z, Vec3::X),
+      PanelShape::Curved { radius } => {
+        let angle = offset / radius;
+        let (sin, cos) = angle.sin_cos();
+
+        (Vec3::new(radius * sin, 0.0, radius * (1.0 - cos)), Vec3::new(-sin, 0.0, cos), Vec3::new(cos, 0.0, sin))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn panel(shape: PanelShape) -> Panel {
+    Panel { shape, ..Panel::new(Transform::IDENTITY, Vec2::new(1.2, 0.8)) }
+  }
+
+  fn assert_near(found: Vec2, expected: Vec2) {
+    assert!(found.abs_diff_eq(expected, 1e-4), "expected {expected}, found {found}");
+  }
+
+  #[test]
+  fn hits_flat_panels_from_the_front() {
+    let flat = panel(PanelShape::Flat);
+    let origin = Vec3::new(0.0, 0.0, 1.0);
+
+    let (distance, uv) = flat.intersect(origin, Vec3::NEG_Z).unwrap();
+    assert!((distance - 1.0).abs() < 1e-5);
+    assert_near(uv, Vec2::splat(0.5));
+
+    // the top left corner is uv 0, 0
+    let (_, uv) = flat.intersect(Vec3::new(-0.3, 0.2, 1.0), Vec3::NEG_Z).unwrap();
+    assert_near(uv, Vec2::new(0.25, 0.25));
+
+    assert_eq!(flat.intersect(Vec3::new(0.7, 0.0, 1.0), Vec3::NEG_Z), None);
+    assert_eq!(flat.intersect(Vec3::new(0.0, 0.0, -1.0), Vec3::Z), None);
+  }
+
+  #[test]
+  fn hits_curved_panels_along_their_surface() {
+    let radius = 1.4;
+    let curved = panel(PanelShape::Curved { radius });
+    let center = Vec3::new(0.0, 0.0, radius);
+
+    // from the center of curvature every point is the radius away
+    let (distance, uv) = curved.intersect(center, Vec3::NEG_Z).unwrap();
+    assert!((distance - radius).abs() < 1e-5);
+    assert_near(uv, Vec2::splat(0.5));
+
+    // u follows the arc, so a quarter of the width along it is a quarter of the way from the middle
+    let angle = 0.3 / radius;
+    let (distance, uv) = curved.intersect(center, Vec3::new(angle.sin(), 0.0, -angle.cos())).unwrap();
+    assert!((distance - radius).abs() < 1e-5);
+    assert_near(uv, Vec2::new(0.75, 0.5));
+
+    // past the ends of the arc and from behind
+    assert_eq!(curved.intersect(center, Vec3::new(1.0, 0.0, -0.2).normalize()), None);
+    assert_eq!(curved.intersect(Vec3::new(0.0, 0.0, -1.0), Vec3::Z), None);
+  }
+
+  #[test]
+  fn curves_the_mesh_on_the_same_surface_it_hits() {
+    let curved = panel(PanelShape::Curved { radius: 1.4 });
+    let mesh = curved.mesh_data();
+
+    assert_eq!(mesh.vertices.len(), (Panel::CURVE_SEGMENTS as usize + 1) * 2);
+
+    for vertex in &mesh.vertices {
+      let position = Vec3::from_array(vertex.position);
+      let origin = Vec3::new(0.0, position.y, 1.4);
+      let (_, uv) = curved.intersect(origin, (position - origin).normalize()).unwrap();
+
+      assert_near(uv, Vec2::from_array(vertex.uv));
+    }
+  }
+}
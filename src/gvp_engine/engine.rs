@@ -1,8 +1,39 @@
-use crate::gvp_engine::{window::Window, gpu::GPU, renderer::Renderer};
+use crate::gvp_engine::{
+  app::{AppId, AppManager, AppState, AppStatesError, GVPApp},
+  audio::{Audio, AudioEvent, NullOutput},
+  window::Window,
+  gpu::GPU,
+  renderer::Renderer,
+  allocator::Allocator,
+  camera::Camera,
+  compositor::{Compositor, Panel, PanelHit, PanelId},
+  environment::Environment,
+  gaze::GazeFadeSettings,
+  gesture::{GestureEvent, GestureRecognizer, GestureThresholds},
+  gui::Canvas,
+  input::Input,
+  material::{Material, MaterialHandle, MaterialRegistry},
+  mesh::Mesh,
+  model::{Model, ModelData, ModelError, Projection},
+  performance::{Performance, PerformanceOverlay},
+  physics::Physics,
+  plugin::{PluginEvent, PluginId, PluginManager},
+  primitives::PrimitiveKind,
+  renderer::{AtlasUpload, CanvasDraw, DrawCall, DrawList, MirrorMode, PathTraceSettings, RenderMode, SetLayouts, Sun, SurfaceDraw},
+  scene::{AssetRecord, AudioSource, CameraComponent, Collider, Entity, LightKind, Light, LoadedModel, MeshSource, RigidBody, Scene, SceneError, SceneFile},
+  surface::{ExternalSurface, SurfaceId, Surfaces},
+  text::{CanvasGlyphs, Text},
+  texture::{TextureData, TextureError},
+  transform::Transform,
+  xr::Xr
+};
 
 use ash::{vk, khr::surface};
+use glam::Vec3;
+use sdl2::keyboard::Keycode;
+use uuid::Uuid;
 
-use std::ffi::CStr;
+use std::{collections::HashMap, ffi::CStr, mem, path::{Path, PathBuf}, time::{Duration, Instant}};
 
 macro_rules! c_str {
   ($s:expr) => {
@@ -16,12 +47,49 @@ macro_rules! gvp_version {
 
 pub struct GVPEngine {
   window: Window,
+  // present when an openxr runtime with a headset was found, the scene is then drawn to both eyes as well
+  xr: Option<Xr>,
   gpu: GPU,
   instance: ash::Instance,
   surface_loader: surface::Instance,
   surface: vk::SurfaceKHR,
   device: ash::Device,
-  renderer: Renderer
+  allocator: Allocator,
+  materials: MaterialRegistry,
+  environment: Environment,
+  // what the environment was last loaded from, none while it is the built in one
+  environment_path: Option<PathBuf>,
+  compositor: Compositor,
+  // drawn into their panels' targets on the next render
+  canvases: Vec<(PanelId, Canvas)>,
+  // the panel last selected, whose gui gets the keyboard
+  focused_panel: Option<PanelId>,
+  // pixels drawn outside the engine, uploaded into their panels' targets on render
+  surfaces: Surfaces,
+  text: Text,
+  apps: AppManager,
+  plugins: PluginManager,
+  renderer: Renderer,
+  camera: Camera,
+  input: Input,
+  gestures: GestureRecognizer,
+  started: Instant,
+  scene: Scene,
+  physics: Physics,
+  // plays the scene's audio sources, on the sound card when there is one
+  audio: Audio,
+  // frame times, gpu timings and memory usage, recorded after every render
+  performance: Performance,
+  // the app drawing them, kept suspended while hidden so it keeps its panel
+  performance_overlay: Option<AppId>,
+  // scene revision the gpu resources were last brought up to date with
+  synced_revision: u64,
+  // unloaded models leave their slot empty so MeshSource indices keep pointing at the same models
+  models: Vec<Option<Model>>,
+  // generated meshes are shared between every entity using a primitive of the same kind
+  primitive_meshes: Vec<(PrimitiveKind, Mesh)>,
+  // registered material of every entity with a Material component
+  entity_materials: HashMap<Entity, MaterialHandle>
 }
 
 impl GVPEngine {
@@ -31,8 +99,9 @@ impl GVPEngine {
       Err(error) => panic!("failed to load vulkan with error: {error}")
     };
 
-    let window = Window::new();
-    let instance = GVPEngine::create_instance(&window, &entry);
+    let mut window = Window::new();
+    let mut xr = Xr::new();
+    let instance = GVPEngine::create_instance(&window, &entry, xr.as_ref());
     let surface_loader = surface::Instance::new(&entry, &instance);
     let surface = window.surface(&instance);
 
@@ -41,31 +110,643 @@ impl GVPEngine {
       vk::KHR_DYNAMIC_RENDERING_NAME.as_ptr()
     ];
 
-    let mut gpu = GPU::get(&instance, &surface_loader, &surface, &required_extensions);
-    let device = GVPEngine::create_device(&instance, &gpu, &mut required_extensions);
+    let headset_gpu = xr.as_ref().map(|xr| xr.physical_device(&instance));
+    let mut gpu = GPU::get(&instance, &surface_loader, &surface, &required_extensions, headset_gpu);
+    let device = GVPEngine::create_device(&entry, &instance, &gpu, &mut required_extensions, xr.as_ref());
 
     gpu.get_queues(&device);
 
-    let renderer = Renderer::new(&instance, &device, &surface_loader, &surface, &gpu);
+    let allocator = Allocator::new(&instance, &device, &gpu);
+    let materials = MaterialRegistry::new(&instance, &device, &allocator, &gpu);
+    let environment = Environment::new(&instance, &device, &allocator, &gpu);
+    let compositor = Compositor::new(&device, &gpu);
+    let renderer = Renderer::new(
+      &instance,
+      &device,
+      &allocator,
+      &surface_loader,
+      &surface,
+      &gpu,
+      SetLayouts { material: materials.layout(), environment: environment.layout(), panel: compositor.layout() }
+    );
+
+    if let Some(xr) = &mut xr {
+      xr.start_session(&instance, &device, &allocator, &gpu, renderer.format());
+    }
+
+    let audio = match window.speakers() {
+      Some(speakers) => Audio::new(Box::new(speakers)),
+      None           => Audio::new(Box::new(NullOutput::new(Audio::SAMPLE_RATE)))
+    };
 
     GVPEngine {
       window,
+      xr,
       instance,
       surface_loader,
       surface,
       gpu,
       device,
-      renderer
+      allocator,
+      materials,
+      environment,
+      environment_path: None,
+      compositor,
+      canvases: Vec::new(),
+      focused_panel: None,
+      surfaces: Surfaces::default(),
+      apps: AppManager::default(),
+      plugins: PluginManager::default(),
+      text: Text::new(),
+      renderer,
+      camera: Camera::default(),
+      input: Input::new(),
+      gestures: GestureRecognizer::new(GestureThresholds::default()),
+      started: Instant::now(),
+      scene: Scene::new(),
+      physics: Physics::default(),
+      audio,
+      performance: Performance::default(),
+      performance_overlay: None,
+      synced_revision: 0,
+      models: Vec::new(),
+      primitive_meshes: Vec::new(),
+      entity_materials: HashMap::new()
+    }
+  }
+
+  // also brings the input actions up to date for the frame
+  pub fn poll_events(&mut self) -> bool {
+    self.input.begin_frame();
+
+    let quit = self.window.poll_events(self.input.desktop_mut());
+    let xr_exit = self.xr.as_mut().is_some_and(|xr| xr.poll_events());
+
+    if let Some(xr) = &mut self.xr {
+      xr.sync_input(&mut self.input, &self.camera);
+    }
+
+    self.input.resolve(&self.camera);
+
+    self.gestures.begin_frame();
+    self.gestures.observe(&self.input, self.started.elapsed().as_secs_f32());
+    self.input.set_gestures(self.gestures.events().iter().map(|event| event.gesture));
+
+    if self.input.pressed(Input::SELECT) {
+      self.focused_panel = self.panel_hit().map(|hit| hit.panel);
+    }
+
+    if self.input.desktop().key_presses().contains(&Keycode::F3) {
+      self.set_performance_overlay(!self.performance_overlay());
+    }
+
+    if self.input.desktop().key_presses().contains(&Keycode::F4) {
+      self.set_mirror_mode(self.mirror_mode().next());
+    }
+
+    if self.input.desktop().key_presses().contains(&Keycode::F5) {
+      let settings = self.path_tracing_mut();
+      settings.view = settings.view.next();
+    }
+
+    quit || xr_exit
+  }
+
+  // recognized while polling this frame's events
+  pub fn gestures(&self) -> &[GestureEvent] {
+    self.gestures.events()
+  }
+
+  // for tuning thresholds and recording pose streams
+  pub fn gesture_recognizer_mut(&mut self) -> &mut GestureRecognizer {
+    &mut self.gestures
+  }
+
+  pub fn input(&self) -> &Input {
+    &self.input
+  }
+
+  pub fn input_mut(&mut self) -> &mut Input {
+    &mut self.input
+  }
+
+  // what the window shows while a headset is being rendered to, which F4 steps through as well
+  pub fn set_mirror_mode(&mut self, mirror: MirrorMode) {
+    self.renderer.set_mirror_mode(mirror);
+  }
+
+  pub fn mirror_mode(&self) -> MirrorMode {
+    self.renderer.mirror_mode()
+  }
+
+  // path traces the window instead of rasterizing it
+  pub fn set_render_mode(&mut self, mode: RenderMode) {
+    self.renderer.set_render_mode(&self.device, &self.allocator, mode);
+  }
+
+  // the bounces, samples and what a traced window shows, which F5 steps through as well
+  pub fn path_tracing_mut(&mut self) -> &mut PathTraceSettings {
+    self.renderer.path_tracing_mut()
+  }
+
+  pub fn performance(&self) -> &Performance {
+    &self.performance
+  }
+
+  // shows or hides the performance overlay, which F3 toggles as well
+  pub fn set_performance_overlay(&mut self, shown: bool) {
+    let state = self.performance_overlay.and_then(|id| self.apps.state(id));
+
+    match (self.performance_overlay.filter(|_| state.is_some()), shown) {
+      (None, true)      => {
+        // launching focuses, which the overlay should not take from whatever had it
+        let focused = self.focused_panel;
+        self.performance_overlay = Some(self.launch_app(Box::new(PerformanceOverlay::new())));
+        self.focused_panel = focused;
+      },
+      (Some(id), true)  => self.resume_app(id),
+      (Some(id), false) => self.suspend_app(id),
+      (None, false)     => ()
+    }
+  }
+
+  pub fn performance_overlay(&self) -> bool {
+    self.performance_overlay.and_then(|id| self.apps.state(id)) == Some(AppState::Running)
+  }
+
+  // an app window in the world. its target starts out cleared to the panel background
+  pub fn create_panel(&mut self, panel: Panel) -> PanelId {
+    self.compositor.create_panel(&self.device, &self.allocator, panel)
+  }
+
+  pub fn panel(&self, id: PanelId) -> &Panel {
+    self.compositor.panel(id)
+  }
+
+  // moving a panel is cheap, resizing it or changing its dpi rebuilds its target
+  pub fn update_panel(&mut self, id: PanelId, panel: Panel) {
+    self.compositor.update_panel(&self.device, &self.allocator, id, panel);
+  }
+
+  pub fn destroy_panel(&mut self, id: PanelId) {
+    self.canvases.retain(|(panel, _)| *panel != id);
+    self.surfaces.detach_panel(id);
+    self.focused_panel = self.focused_panel.filter(|panel| *panel != id);
+    self.compositor.destroy_panel(&self.device, &self.allocator, id);
+  }
+
+  // the panel under the pointer action, with the spot it points at in the panel's pixels
+  pub fn panel_hit(&self) -> Option<PanelHit> {
+    self.input.pose(Input::POINTER).and_then(|ray| self.compositor.hit_test(ray))
+  }
+
+  pub fn gaze_fade_mut(&mut self) -> &mut GazeFadeSettings {
+    self.compositor.gaze_settings_mut()
+  }
+
+  pub fn focused_panel(&self) -> Option<PanelId> {
+    self.focused_panel
+  }
+
+  // the panel the keyboard goes to, none to take it from every panel
+  pub fn focus_panel(&mut self, panel: Option<PanelId>) {
+    self.focused_panel = panel;
+  }
+
+  // opens the app into a panel of its own and focuses it
+  pub fn launch_app(&mut self, app: Box<dyn GVPApp>) -> AppId {
+    self.with_apps(|apps, engine| apps.launch(engine, app))
+  }
+
+  pub fn suspend_app(&mut self, id: AppId) {
+    self.with_apps(|apps, engine| apps.suspend(engine, id));
+  }
+
+  pub fn resume_app(&mut self, id: AppId) {
+    self.with_apps(|apps, engine| apps.resume(engine, id));
+  }
+
+  // frees every panel, entity and model the app created
+  pub fn close_app(&mut self, id: AppId) {
+    self.with_apps(|apps, engine| apps.close(engine, id));
+  }
+
+  // once a frame between polling events and rendering
+  pub fn update_apps(&mut self) {
+    self.with_apps(|apps, engine| apps.update(engine));
+  }
+
+  pub fn apps(&self) -> &AppManager {
+    &self.apps
+  }
+
+  pub fn save_app_states(&self, path: &Path) {
+    self.apps.save(path);
+  }
+
+  pub fn load_app_states(&mut self, path: &Path) -> Result<(), AppStatesError> {
+    self.apps.load(path)
+  }
+
+  // where poll_plugins looks for plugin libraries
+  pub fn set_plugin_directory(&mut self, directory: &Path) {
+    self.plugins.set_directory(directory);
+  }
+
+  // picks up plugins added to, changed in or removed from the plugin directory
+  pub fn poll_plugins(&mut self) -> Vec<PluginEvent> {
+    self.with_plugins(|plugins, engine| plugins.poll(engine))
+  }
+
+  pub fn launch_plugin(&mut self, id: PluginId) -> Option<AppId> {
+    self.with_plugins(|plugins, engine| plugins.launch(engine, id))
+  }
+
+  // the panel keeps showing the last canvas drawn into it. a surface on the panel is drawn over it whole again
+  pub fn draw_canvas(&mut self, panel: PanelId, canvas: Canvas) {
+    self.canvases.retain(|(id, _)| *id != panel);
+    self.canvases.push((panel, canvas));
+    self.surfaces.invalidate(panel);
+  }
+
+  // shows pixels from outside the engine on the panel, sized to its target and given the input over it. a panel shows
+  // one surface at a time, and it is detached when the panel is destroyed
+  pub fn attach_surface(&mut self, panel: PanelId, source: Box<dyn ExternalSurface>) -> SurfaceId {
+    self.surfaces.attach(panel, source)
+  }
+
+  // fonts for guis and anything else drawing text. guis measure with them once given
+  // `Box::new(engine.text().fonts().clone())` as their text measure
+  pub fn text(&self) -> &Text {
+    &self.text
+  }
+
+  pub fn text_mut(&mut self) -> &mut Text {
+    &mut self.text
+  }
+
+  pub fn scene(&self) -> &Scene {
+    &self.scene
+  }
+
+  pub fn scene_mut(&mut self) -> &mut Scene {
+    &mut self.scene
+  }
+
+  // uploads the model and spawns its node hierarchy under a new root entity named after the file
+  pub fn load_model(&mut self, path: &Path) -> Result<Entity, ModelError> {
+    let index = self.load_model_asset(path, Uuid::new_v4())?;
+    Ok(self.spawn_model(index))
+  }
+
+  // spawns another copy of a loaded model's node hierarchy
+  pub fn spawn_model(&mut self, index: usize) -> Entity {
+    let model = GVPEngine::loaded(&self.models, index);
+
+    let root = self.scene.spawn(Transform::IDENTITY);
+    if let Some(name) = model.path.file_stem().and_then(|stem| stem.to_str()) {
+      self.scene.set_name(root, name);
+    }
+
+    let mut stack: Vec<(usize, Entity)> = model.roots.iter().map(|node| (*node, root)).collect();
+    while let Some((node_index, parent)) = stack.pop() {
+      let node = &model.nodes[node_index];
+      let entity = self.scene.spawn_child(parent, node.transform);
+
+      if let Some(name) = &node.name {
+        self.scene.set_name(entity, name);
+      }
+
+      if let Some(mesh) = node.mesh {
+        // a node can hold several primitives but an entity only one mesh, so extra primitives become children
+        for (primitive_index, primitive) in model.meshes[mesh].iter().enumerate() {
+          let target = match primitive_index {
+            0 => entity,
+            _ => self.scene.spawn_child(entity, Transform::IDENTITY)
+          };
+
+          self.scene.insert(target, MeshSource::Model { model: index, mesh, primitive: primitive_index });
+
+          if let Some(material) = primitive.material {
+            self.scene.insert(target, model.materials[material].clone());
+          }
+        }
+      }
+
+      if let Some(camera) = node.camera {
+        // unnamed nodes go by their camera's name
+        if let (None, Some(name)) = (&node.name, &model.cameras[camera].name) {
+          self.scene.set_name(entity, name);
+        }
+
+        if let Projection::Perspective { yfov, znear, zfar, .. } = model.cameras[camera].projection {
+          self.scene.insert(entity, CameraComponent {
+            fov_y: yfov,
+            near: znear,
+            far: zfar.unwrap_or(CameraComponent::default().far),
+            active: false
+          });
+        }
+      }
+
+      stack.extend(node.children.iter().map(|child| (*child, entity)));
+    }
+
+    root
+  }
+
+  // uploads a model without placing it in the scene. returns its index for MeshSource::Model
+  pub fn load_model_asset(&mut self, path: &Path, id: Uuid) -> Result<usize, ModelError> {
+    let data = ModelData::load(path)?;
+    self.models.push(Some(Model::upload(&self.instance, &self.device, &self.allocator, &self.gpu, data, id)));
+
+    Ok(self.models.len() - 1)
+  }
+
+  // the scene along with where the app panels are
+  pub fn save_scene(&self, path: &Path) -> Result<(), SceneError> {
+    // nothing in the scene refers to unloaded models, their records only keep the indices lined up
+    let assets: Vec<AssetRecord> = self.models.iter().map(|model| match model {
+      Some(model) => AssetRecord { id: model.id, path: model.path.clone() },
+      None        => AssetRecord { id: Uuid::nil(), path: PathBuf::new() }
+    }).collect();
+
+    SceneFile::capture(&self.scene, &assets, self.environment_path.as_deref(), self.apps.placements(self)).save(path)
+  }
+
+  // replaces the current scene with the saved one, loading any models and the environment it needs that are not loaded
+  // yet, and moves app panels back to where they were. a file that cannot be read leaves the current scene as it is
+  pub fn load_scene(&mut self, path: &Path) -> Result<(), SceneError> {
+    let file = SceneFile::load(path)?;
+    let mut models = HashMap::<Uuid, LoadedModel>::new();
+
+    // entities drawing a model that failed to load come back without their mesh
+    for asset in &file.assets {
+      let index = match self.models.iter().position(|model| model.as_ref().is_some_and(|model| model.id == asset.id || model.path == asset.path)) {
+        Some(index) => index,
+        None        => match self.load_model_asset(&asset.path, asset.id) {
+          Ok(index)  => index,
+          Err(error) => {
+            eprintln!("failed to load model {} with error: {error}", asset.path.display());
+            continue;
+          }
+        }
+      };
+
+      let primitives = GVPEngine::loaded(&self.models, index).meshes.iter().map(Vec::len).collect();
+      models.insert(asset.id, LoadedModel { index, primitives });
+    }
+
+    self.scene = Scene::new();
+    self.physics.clear();
+    self.audio.clear();
+    self.synced_revision = 0;
+
+    let frame = self.renderer.frames();
+    for (_, handle) in self.entity_materials.drain() {
+      self.materials.release(handle, frame);
+    }
+
+    file.restore(&mut self.scene, &models)?;
+    self.with_apps(|apps, engine| apps.place(engine, &file.panels));
+
+    if let Some(environment) = &file.environment {
+      if let Err(error) = self.set_environment(environment, Duration::ZERO) {
+        eprintln!("failed to load environment {} with error: {error}", environment.display());
+      }
+    }
+
+    Ok(())
+  }
+
+  // crossfades the sky and the lighting over to another environment. takes an equirectangular hdr panorama or a ktx2
+  // cubemap, a zero fade switches immediately
+  pub fn set_environment(&mut self, path: &Path, fade: Duration) -> Result<(), TextureError> {
+    let data = TextureData::load(path, false)?;
+    self.environment.load(&self.instance, &self.device, &self.allocator, &self.gpu, &data, fade);
+    self.environment_path = Some(path.to_path_buf());
+
+    Ok(())
+  }
+
+  // a primitive with a collider fitted to it, falling, thrown around or staying put depending on the body's kind
+  pub fn spawn_body(&mut self, kind: PrimitiveKind, transform: Transform, material: Material, body: RigidBody) -> Entity {
+    let entity = self.spawn_primitive(kind, transform, material);
+
+    self.scene.insert(entity, Collider::for_primitive(kind));
+    self.scene.insert(entity, body);
+
+    entity
+  }
+
+  // steps the bodies for the time since the last call. the grab action picks them up at the pointer unless a panel is
+  // in the way
+  pub fn update_physics(&mut self) {
+    let blocked = self.panel_hit().map(|hit| hit.distance);
+    self.physics.update(&mut self.scene, &self.input, blocked);
+  }
+
+  // keeps the audio sources playing from where their entities are, heard from the head. clips that failed to load and
+  // ones that played to their end are reported
+  pub fn update_audio(&mut self) -> Vec<AudioEvent> {
+    self.scene.update_transforms();
+    self.audio.update(&mut self.scene, self.input.head())
+  }
+
+  pub fn audio_mut(&mut self) -> &mut Audio {
+    &mut self.audio
+  }
+
+  // a box playing a clip over and over from where it is, which can be picked up and carried around like any other body
+  pub fn spawn_speaker(&mut self, clip: &Path, transform: Transform) -> Entity {
+    let entity = self.spawn_body(PrimitiveKind::CUBE, transform, Material::color([0.1, 0.1, 0.12, 1.0], 0.0, 0.6), RigidBody::default());
+    self.scene.insert(entity, AudioSource { clip: Some(clip.to_path_buf()), looping: true, ..AudioSource::default() });
+
+    entity
+  }
+
+  pub fn spawn_primitive(&mut self, kind: PrimitiveKind, transform: Transform, material: Material) -> Entity {
+    let entity = self.scene.spawn(transform);
+
+    self.scene.insert(entity, MeshSource::Primitive(kind));
+    self.scene.insert(entity, material);
+
+    entity
+  }
+
+  pub fn render(&mut self) {
+    self.scene.update_transforms();
+    self.sync_scene();
+    self.environment.update(&self.device, &self.allocator);
+
+    let mut meshes = Vec::<DrawCall>::new();
+
+    for (entity, source) in self.scene.query::<MeshSource>() {
+      let mesh = match *source {
+        MeshSource::Primitive(kind) => match self.primitive_meshes.iter().find(|(existing, _)| *existing == kind) {
+          Some((_, mesh)) => mesh,
+          None            => continue
+        },
+        MeshSource::Model { model, mesh, primitive } => &GVPEngine::loaded(&self.models, model).meshes[mesh][primitive].mesh
+      };
+
+      meshes.push(DrawCall {
+        mesh,
+        transform: self.scene.world_matrix(entity),
+        material: self.materials.get(self.material_handle(entity))
+      });
+    }
+
+    if let Some((entity, camera)) = self.scene.active_camera() {
+      self.camera = Camera {
+        transform: Transform::from_matrix(&self.scene.world_matrix(entity)),
+        fov_y: camera.fov_y,
+        near: camera.near,
+        far: camera.far
+      };
+    }
+
+    let sun = self.scene.query::<Light>()
+      .find(|(_, light)| light.kind == LightKind::Directional)
+      .map(|(entity, light)| Sun {
+        direction: self.scene.world_matrix(entity).transform_vector3(Vec3::NEG_Z).normalize(),
+        color: light.color,
+        intensity: light.intensity
+      })
+      .unwrap_or(Sun { direction: Vec3::new(-0.4, -1.0, -0.3).normalize(), color: Vec3::ONE, intensity: 1.0 });
+
+    self.compositor.update(self.input.head(), self.started.elapsed().as_secs_f32());
+    // glyphs are made before the atlas is handed over so the rows they were written to go up this frame
+    let glyphs: Vec<CanvasGlyphs> = self.canvases.iter().map(|(_, canvas)| self.text.canvas_glyphs(canvas)).collect();
+    let atlas_rows = self.text.atlas_mut().take_dirty();
+
+    let canvases = self.canvases.iter().zip(&glyphs).map(|((panel, canvas), glyphs)| CanvasDraw {
+      target: self.compositor.target(*panel),
+      canvas,
+      glyphs
+    }).collect();
+
+    let atlas = atlas_rows.map(|rows| AtlasUpload { pixels: self.text.atlas().pixels(), rows });
+
+    let uploads = self.surfaces.update(&self.compositor, &self.input, self.panel_hit(), self.focused_panel);
+    let surfaces = uploads.iter().map(|upload| SurfaceDraw { target: self.compositor.target(upload.panel), upload }).collect();
+
+    let draws = DrawList { meshes, panels: self.compositor.draws(), canvases, atlas, surfaces };
+    self.renderer.prepare_surfaces(&self.device, &self.allocator, &draws.surfaces);
+    self.renderer.prepare_trace(&self.device, &self.allocator, &draws.meshes);
+
+    // the headset frame is waited on before drawing so the eye poses are as close to display time as possible
+    let xr_frame = self.xr.as_mut().and_then(|xr| xr.begin_frame());
+
+    let targets = match (&self.xr, &xr_frame) {
+      (Some(xr), Some(frame)) => xr.targets(frame, &self.camera),
+      _                       => Vec::new()
+    };
+
+    let out_of_date = self.renderer.render(&self.device, &self.camera, &sun, &self.environment, &draws, &targets);
+    drop(targets);
+    self.canvases.clear();
+    self.performance.record(self.renderer.waited(), self.renderer.timings(), self.allocator.usage());
+
+    if let (Some(xr), Some(frame)) = (&mut self.xr, xr_frame) {
+      xr.end_frame(frame);
+    }
+
+    if out_of_date {
+      self.renderer.recreate_swapchain(&self.device, &self.allocator, &self.surface_loader, &self.surface, &self.gpu);
+    }
+  }
+
+  // the apps run with the engine while the manager is out of it
+  fn with_apps<T>(&mut self, run: impl FnOnce(&mut AppManager, &mut GVPEngine) -> T) -> T {
+    let mut apps = mem::take(&mut self.apps);
+    let result = run(&mut apps, self);
+    self.apps = apps;
+
+    result
+  }
+
+  fn with_plugins<T>(&mut self, run: impl FnOnce(&mut PluginManager, &mut GVPEngine) -> T) -> T {
+    let mut plugins = mem::take(&mut self.plugins);
+    let result = run(&mut plugins, self);
+    self.plugins = plugins;
+
+    result
+  }
+
+  fn loaded(models: &[Option<Model>], index: usize) -> &Model {
+    match models.get(index).and_then(Option::as_ref) {
+      Some(model) => model,
+      None        => panic!("failed to find model {index} with error: it was unloaded")
     }
   }
 
-  pub fn poll_events(&self) -> bool {
-    self.window.poll_events()
+  // entities without a material, or whose material was removed, draw with the default one
+  fn material_handle(&self, entity: Entity) -> MaterialHandle {
+    match self.scene.get::<Material>(entity) {
+      Some(_) => self.entity_materials.get(&entity).copied().unwrap_or(MaterialRegistry::DEFAULT),
+      None    => MaterialRegistry::DEFAULT
+    }
   }
 
-  fn create_instance(window: &Window, entry: &ash::Entry) -> ash::Instance {
+  // uploads meshes for any primitive kinds that entities started using since the last sync and registers materials
+  // that were added or edited
+  fn sync_scene(&mut self) {
+    let frame = self.renderer.frames();
+    self.materials.collect(&self.device, &self.allocator, self.renderer.finished_frames());
+
+    let mut missing = Vec::<PrimitiveKind>::new();
+
+    for (_, source) in self.scene.changed_since::<MeshSource>(self.synced_revision) {
+      let MeshSource::Primitive(kind) = *source else { continue; };
+
+      if self.primitive_meshes.iter().any(|(existing, _)| *existing == kind) || missing.contains(&kind) { continue; }
+      missing.push(kind);
+    }
+
+    for kind in missing {
+      let mesh = Mesh::upload(&self.device, &self.allocator, &kind.generate());
+      self.primitive_meshes.push((kind, mesh));
+    }
+
+    // model materials index into their model's textures, so moving an entity to another model re-registers it too
+    let mut stale: Vec<Entity> = self.scene.changed_since::<Material>(self.synced_revision).map(|(entity, _)| entity).collect();
+    stale.extend(self.scene.changed_since::<MeshSource>(self.synced_revision).map(|(entity, _)| entity));
+
+    for entity in stale {
+      let Some(material) = self.scene.get::<Material>(entity) else { continue; };
+
+      let owner = match self.scene.get::<MeshSource>(entity) {
+        Some(MeshSource::Model { model, .. }) => Some(*model),
+        _                                     => None
+      };
+
+      let textures = match owner {
+        Some(model) => GVPEngine::loaded(&self.models, model).textures.as_slice(),
+        None        => &[]
+      };
+
+      // registered before the old one is released so an unchanged material keeps its entry
+      let handle = self.materials.register(&self.device, &self.allocator, material, owner, textures);
+
+      if let Some(old) = self.entity_materials.insert(entity, handle) {
+        self.materials.release(old, frame);
+      }
+    }
+
+    // despawned entities and removed materials give their handles back
+    let (scene, materials) = (&self.scene, &mut self.materials);
+    self.entity_materials.retain(|entity, handle| {
+      let kept = scene.get::<Material>(*entity).is_some();
+      if !kept { materials.release(*handle, frame); }
 
+      kept
+    });
 
+    self.synced_revision = self.scene.revision();
+  }
+
+  fn create_instance(window: &Window, entry: &ash::Entry, xr: Option<&Xr>) -> ash::Instance {
     let application_info = {
       vk::ApplicationInfo::default()
         .application_name(c_str!("Groot Vision Pro"))
@@ -109,6 +790,11 @@ impl GVPEngine {
         .enabled_extension_names(&extensions)
     };
 
+    // the runtime adds the instance extensions it needs on top of ours
+    if let Some(xr) = xr {
+      return xr.create_vulkan_instance(entry, &create_info);
+    }
+
     match unsafe { entry.create_instance(&create_info, None) } {
       Ok(instance)  => instance,
       Err(error)    => panic!("failed to create instance with error: {error}")
@@ -116,9 +802,11 @@ impl GVPEngine {
   }
 
   fn create_device(
+    entry: &ash::Entry,
     instance: &ash::Instance,
     gpu: &GPU,
-    required_extensions: &mut Vec<*const i8>
+    required_extensions: &mut Vec<*const i8>,
+    xr: Option<&Xr>
   ) -> ash::Device {
     let device_extensions = match unsafe { instance.enumerate_device_extension_properties(gpu.device) } {
       Ok(extensions)  => extensions,
@@ -138,20 +826,54 @@ impl GVPEngine {
     }
 
     let queue_create_infos = gpu.queue_create_infos();
-    let features = vk::PhysicalDeviceFeatures::default();
+    let features = gpu.features;
     let mut dynamic_rendering = {
       vk::PhysicalDeviceDynamicRenderingFeatures::default()
         .dynamic_rendering(true)
     };
 
-    let create_info = {
+    // both eyes of a headset are drawn in one pass. support is required since vulkan 1.1
+    let mut vulkan_11 = {
+      vk::PhysicalDeviceVulkan11Features::default()
+        .multiview(true)
+    };
+
+    // the path tracer's fast path, enabled whenever the gpu has it
+    if gpu.ray_query {
+      required_extensions.extend(GPU::ray_query_extensions().map(|extension| extension.as_ptr()));
+    }
+
+    let mut vulkan_12 = {
+      vk::PhysicalDeviceVulkan12Features::default()
+        .buffer_device_address(gpu.ray_query)
+    };
+
+    let mut acceleration_structure = {
+      vk::PhysicalDeviceAccelerationStructureFeaturesKHR::default()
+        .acceleration_structure(gpu.ray_query)
+    };
+
+    let mut ray_query = vk::PhysicalDeviceRayQueryFeaturesKHR::default().ray_query(gpu.ray_query);
+
+    let mut create_info = {
       vk::DeviceCreateInfo::default()
         .enabled_extension_names(&required_extensions)
         .enabled_features(&features)
         .queue_create_infos(&queue_create_infos)
         .push_next(&mut dynamic_rendering)
+        .push_next(&mut vulkan_11)
+        .push_next(&mut vulkan_12)
     };
 
+    // the feature structures of extensions that are not enabled cannot be chained
+    if gpu.ray_query {
+      create_info = create_info.push_next(&mut acceleration_structure).push_next(&mut ray_query);
+    }
+
+    if let Some(xr) = xr {
+      return xr.create_vulkan_device(entry, instance, gpu, &create_info);
+    }
+
     match unsafe { instance.create_device(gpu.device, &create_info, None) } {
       Ok(device) => device,
       Err(error) => panic!("failed to create device with error: {error}")
@@ -161,8 +883,29 @@ impl GVPEngine {
 
 impl Drop for GVPEngine {
   fn drop(&mut self) {
+    // apps free what they created while the engine is still whole
+    self.with_apps(|apps, engine| apps.close_all(engine));
+
     unsafe{
-      self.renderer.clean(&self.device);
+      self.renderer.clean(&self.device, &self.allocator);
+
+      if let Some(xr) = &mut self.xr {
+        xr.clean(&self.device, &self.allocator);
+      }
+
+      for model in self.models.iter_mut().flatten() {
+        model.clean(&self.device, &self.allocator);
+      }
+
+      for (_, mesh) in &mut self.primitive_meshes {
+        mesh.clean(&self.device, &self.allocator);
+      }
+
+      self.compositor.clean(&self.device, &self.allocator);
+      self.environment.clean(&self.device, &self.allocator);
+      self.materials.clean(&self.device, &self.allocator);
+
+      self.allocator.clean(&self.device);
       self.device.destroy_device(None);
       self.surface_loader.destroy_surface(self.surface, None);
       self.instance.destroy_instance(None);
@@ -0,0 +1,228 @@
+use crate::gvp_engine::{
+  allocator::Allocator,
+  gpu::GPU,
+  ibl::{Ibl, IblMaps},
+  texture::{SamplerInfo, Texture, TextureData}
+};
+
+use ash::vk;
+
+use std::time::{Duration, Instant};
+
+struct Fade {
+  // maps being faded out
+  from: IblMaps,
+  start: Instant,
+  duration: Duration
+}
+
+// the surroundings the user is placed in. draws as the skybox and lights every pbr surface through the same maps so
+// objects and panels always match the sky behind them. the descriptor set is bound as set 2
+pub struct Environment {
+  ibl: Ibl,
+  descriptor_set_layout: vk::DescriptorSetLayout,
+  descriptor_pool: vk::DescriptorPool,
+  pub descriptor_set: vk::DescriptorSet,
+  sampler: vk::Sampler,
+  current: IblMaps,
+  fade: Option<Fade>
+}
+
+impl Environment {
+  const SAMPLER_BINDING: u32 = 3;
+  const BINDING_COUNT: u32 = 8;
+
+  pub fn new(instance: &ash::Instance, device: &ash::Device, allocator: &Allocator, gpu: &GPU) -> Self {
+    let ibl = Ibl::new(device, allocator);
+    let current = ibl.bake(instance, device, allocator, gpu, &Ibl::default_sky());
+
+    let descriptor_set_layout = Environment::create_descriptor_set_layout(device);
+    let (descriptor_pool, descriptor_set) = Environment::create_descriptor_set(device, &descriptor_set_layout);
+
+    let environment = Environment {
+      ibl,
+      descriptor_set_layout,
+      descriptor_pool,
+      descriptor_set,
+      sampler: Texture::create_sampler(device, gpu, &SamplerInfo::CLAMP, vk::LOD_CLAMP_NONE as u32),
+      current,
+      fade: None
+    };
+
+    environment.write_descriptor_set(device);
+
+    environment
+  }
+
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    self.current.clean(device, allocator);
+
+    if let Some(fade) = &self.fade {
+      fade.from.clean(device, allocator);
+    }
+
+    self.ibl.clean(device, allocator);
+
+    unsafe {
+      device.destroy_descriptor_pool(self.descriptor_pool, None);
+      device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+      device.destroy_sampler(self.sampler, None);
+    };
+  }
+
+  pub fn layout(&self) -> vk::DescriptorSetLayout {
+    self.descriptor_set_layout
+  }
+
+  // mip the roughest reflections are read from
+  pub fn max_lod(&self) -> f32 {
+    (Ibl::PREFILTERED_MIPS - 1) as f32
+  }
+
+  // how far the current environment has faded in, 1 once nothing is fading
+  pub fn blend(&self) -> f32 {
+    match &self.fade {
+      Some(fade) => (fade.start.elapsed().as_secs_f32() / fade.duration.as_secs_f32().max(f32::EPSILON)).min(1.0),
+      None       => 1.0
+    }
+  }
+
+  // bakes the environment and fades over to it. a zero duration switches immediately. takes an equirectangular
+  // panorama or a cubemap
+  pub fn load(
+    &mut self,
+    instance: &ash::Instance,
+    device: &ash::Device,
+    allocator: &Allocator,
+    gpu: &GPU,
+    data: &TextureData,
+    duration: Duration
+  ) {
+    let maps = self.ibl.bake(instance, device, allocator, gpu, data);
+
+    // frames in flight may still be sampling whatever gets released below
+    if let Err(error) = unsafe { device.device_wait_idle() } {
+      panic!("failed to wait for device idle with error: {error}")
+    }
+
+    // starting another fade part way through drops the environment that was fading out
+    if let Some(fade) = self.fade.take() {
+      fade.from.clean(device, allocator);
+    }
+
+    let from = std::mem::replace(&mut self.current, maps);
+
+    match duration.is_zero() {
+      true  => from.clean(device, allocator),
+      false => self.fade = Some(Fade { from, start: Instant::now(), duration })
+    }
+
+    self.write_descriptor_set(device);
+  }
+
+  // releases the faded out environment once the fade has finished. called once per frame before rendering
+  pub fn update(&mut self, device: &ash::Device, allocator: &Allocator) {
+    if self.blend() < 1.0 { return; }
+    let Some(fade) = self.fade.take() else { return; };
+
+    if let Err(error) = unsafe { device.device_wait_idle() } {
+      panic!("failed to wait for device idle with error: {error}")
+    }
+
+    fade.from.clean(device, allocator);
+    self.write_descriptor_set(device);
+  }
+
+  // bindings 5 to 7 hold the environment being faded out. they repeat the current one when nothing is fading
+  fn write_descriptor_set(&self, device: &ash::Device) {
+    let from = self.fade.as_ref().map(|fade| &fade.from).unwrap_or(&self.current);
+
+    let views = [
+      self.current.irradiance.view,
+      self.current.prefiltered.view,
+      self.ibl.brdf_lut.view,
+      vk::ImageView::null(),
+      self.current.environment.image.view,
+      from.irradiance.view,
+      from.prefiltered.view,
+      from.environment.image.view
+    ];
+
+    let image_infos = views.map(|view| {
+      [vk::DescriptorImageInfo::default().image_view(view).image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)]
+    });
+
+    let sampler_info = [vk::DescriptorImageInfo::default().sampler(self.sampler)];
+
+    let writes: Vec<vk::WriteDescriptorSet> = image_infos.iter().enumerate().map(|(binding, image_info)| {
+      let write = vk::WriteDescriptorSet::default().dst_set(self.descriptor_set).dst_binding(binding as u32);
+
+      match binding as u32 {
+        Environment::SAMPLER_BINDING => write.descriptor_type(vk::DescriptorType::SAMPLER).image_info(&sampler_info),
+        _                            => write.descriptor_type(vk::DescriptorType::SAMPLED_IMAGE).image_info(image_info)
+      }
+    }).collect();
+
+    unsafe { device.update_descriptor_sets(&writes, &[]) };
+  }
+
+  fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+    let bindings: Vec<vk::DescriptorSetLayoutBinding> = (0..Environment::BINDING_COUNT).map(|binding| {
+      let descriptor_type = match binding {
+        Environment::SAMPLER_BINDING => vk::DescriptorType::SAMPLER,
+        _                            => vk::DescriptorType::SAMPLED_IMAGE
+      };
+
+      vk::DescriptorSetLayoutBinding::default()
+        .binding(binding)
+        .descriptor_type(descriptor_type)
+        .descriptor_count(1)
+        // the path tracer lights with the same set
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT | vk::ShaderStageFlags::COMPUTE)
+    }).collect();
+
+    let create_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+    match unsafe { device.create_descriptor_set_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create environment descriptor set layout with error: {error}")
+    }
+  }
+
+  fn create_descriptor_set(
+    device: &ash::Device,
+    layout: &vk::DescriptorSetLayout
+  ) -> (vk::DescriptorPool, vk::DescriptorSet) {
+    let pool_sizes = [
+      vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::SAMPLED_IMAGE)
+        .descriptor_count(Environment::BINDING_COUNT - 1),
+      vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::SAMPLER)
+        .descriptor_count(1)
+    ];
+
+    let create_info = {
+      vk::DescriptorPoolCreateInfo::default()
+        .max_sets(1)
+        .pool_sizes(&pool_sizes)
+    };
+
+    let pool = match unsafe { device.create_descriptor_pool(&create_info, None) } {
+      Ok(pool)   => pool,
+      Err(error) => panic!("failed to create environment descriptor pool with error: {error}")
+    };
+
+    let layouts = [*layout];
+    let allocate_info = {
+      vk::DescriptorSetAllocateInfo::default()
+        .descriptor_pool(pool)
+        .set_layouts(&layouts)
+    };
+
+    match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+      Ok(sets)   => (pool, sets[0]),
+      Err(error) => panic!("failed to allocate environment descriptor set with error: {error}")
+    }
+  }
+}
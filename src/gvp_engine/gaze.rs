@@ -0,0 +1,146 @@
+use glam::{Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+
+use std::{collections::HashMap, hash::Hash};
+
+// opacity by how far a panel is from where the head faces, as (angle in radians, opacity) points joined by straight
+// lines. angles before the first point and past the last one keep the opacity of that point
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OpacityCurve {
+  points: Vec<(f32, f32)>
+}
+
+impl Default for OpacityCurve {
+  fn default() -> Self {
+    OpacityCurve::falloff(20f32.to_radians(), 50f32.to_radians(), 0.25)
+  }
+}
+
+impl OpacityCurve {
+  // segments a smoothstep is approximated with
+  const FALLOFF_STEPS: usize = 8;
+
+  pub fn new(mut points: Vec<(f32, f32)>) -> Self {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    OpacityCurve { points }
+  }
+
+  // fully opaque up to inner, easing down to floor at outer
+  pub fn falloff(inner: f32, outer: f32, floor: f32) -> Self {
+    let points = (0..=OpacityCurve::FALLOFF_STEPS).map(|step| {
+      let t = step as f32 / OpacityCurve::FALLOFF_STEPS as f32;
+      let eased = t * t * (3.0 - 2.0 * t);
+
+      (inner + (outer - inner) * t, 1.0 + (floor - 1.0) * eased)
+    }).collect();
+
+    OpacityCurve::new(points)
+  }
+
+  pub fn sample(&self, angle: f32) -> f32 {
+    let Some(&(first_angle, first)) = self.points.first() else { return 1.0; };
+    if angle <= first_angle { return first; }
+
+    for pair in self.points.windows(2) {
+      let ((from_angle, from), (to_angle, to)) = (pair[0], pair[1]);
+      if angle > to_angle { continue; }
+
+      let t = (angle - from_angle) / (to_angle - from_angle).max(f32::EPSILON);
+      return from + (to - from) * t;
+    }
+
+    self.points[self.points.len() - 1].1
+  }
+}
+
+// what the gaze is measured against. radius is how far the panel reaches from its center, so looking at its edge
+// counts as looking at it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GazeTarget {
+  pub position: Vec3,
+  pub radius: f32
+}
+
+impl GazeTarget {
+  // between the head's forward and the nearest edge of the target, zero when looking at it
+  pub fn angle(&self, head: Mat4) -> f32 {
+    let forward = head.transform_vector3(Vec3::NEG_Z).normalize_or_zero();
+    let offset = self.position - head.w_axis.truncate();
+    let distance = offset.length();
+
+    if distance <= self.radius { return 0.0; }
+
+    let center = forward.angle_between(offset / distance);
+    (center - (self.radius / distance).asin()).max(0.0)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GazeFadeSettings {
+  pub enabled: bool,
+  pub curve: OpacityCurve,
+  // seconds to cover most of the way to a new opacity, brightening faster than dimming so glancing over is quick but
+  // glancing away does not flicker
+  pub fade_in: f32,
+  pub fade_out: f32
+}
+
+impl Default for GazeFadeSettings {
+  fn default() -> Self {
+    GazeFadeSettings {
+      enabled: true,
+      curve: OpacityCurve::default(),
+      fade_in: 0.15,
+      fade_out: 0.5
+    }
+  }
+}
+
+// fades panels the user is not looking at. there is no eye tracking so where the head faces stands in for the gaze
+pub struct GazeFade<K> {
+  pub settings: GazeFadeSettings,
+  opacities: HashMap<K, f32>
+}
+
+impl<K: Copy + Eq + Hash> GazeFade<K> {
+  pub fn new(settings: GazeFadeSettings) -> Self {
+    GazeFade {
+      settings,
+      opacities: HashMap::new()
+    }
+  }
+
+  // moves every panel's opacity towards what the curve gives for it, delta being the seconds since the last update.
+  // panels left out are forgotten and new ones start at their target
+  pub fn update(&mut self, head: Mat4, targets: impl IntoIterator<Item = (K, GazeTarget)>, delta: f32) {
+    let mut opacities = HashMap::with_capacity(self.opacities.len());
+
+    for (key, target) in targets {
+      let goal = match self.settings.enabled {
+        true  => self.settings.curve.sample(target.angle(head)),
+        false => 1.0
+      };
+
+      let opacity = match self.opacities.get(&key) {
+        Some(current) => {
+          let rate = match goal > *current {
+            true  => self.settings.fade_in,
+            false => self.settings.fade_out
+          };
+
+          current + (goal - current) * (1.0 - (-delta / rate.max(f32::EPSILON) * 3.0).exp())
+        },
+        None => goal
+      };
+
+      opacities.insert(key, opacity);
+    }
+
+    self.opacities = opacities;
+  }
+
+  // panels that were never updated are fully opaque
+  pub fn opacity(&self, key: K) -> f32 {
+    self.opacities.get(&key).copied().unwrap_or(1.0)
+  }
+}
@@ -0,0 +1,357 @@
+mod recording;
+
+pub use recording::*;
+
+use crate::gvp_engine::input::{Hand, HandPose, Input};
+
+use glam::{Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+use std::{collections::{HashMap, VecDeque}, f32::consts::{PI, TAU}};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwipeDirection {
+  Up,
+  Down,
+  Left,
+  Right
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gesture {
+  // fingers is how many were held out for the whole stroke
+  Swipe { direction: SwipeDirection, fingers: u8 },
+  // pinched and held without moving
+  Pinch,
+  // a quick pinch and release
+  AirTap,
+  Circle { clockwise: bool }
+}
+
+impl Gesture {
+  // the sword art online menu swipe
+  pub const MENU_SWIPE: Gesture = Gesture::Swipe { direction: SwipeDirection::Down, fingers: 2 };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GestureSource {
+  Hand(Hand),
+  // the pointer action, so a controller's aim or the mouse cursor. select drags with one finger and grab with two
+  Pointer
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GestureEvent {
+  pub gesture: Gesture,
+  pub source: GestureSource,
+  // seconds since the engine started
+  pub time: f32
+}
+
+// one reading of a pose stream. positions are in meters relative to the head, x to the right and y up
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PoseSample {
+  pub time: f32,
+  pub position: Vec3,
+  // strokes only count while at least one finger is held out
+  pub fingers: u8,
+  pub pinching: bool
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GestureThresholds {
+  // how far a swipe has to travel along its direction, and how quickly in seconds
+  pub swipe_distance: f32,
+  pub swipe_duration: f32,
+  // how far a swipe can stray sideways, relative to the distance it travelled
+  pub swipe_drift: f32,
+  // the straight line distance of a swipe over the length of its path, so arcs are left to circles
+  pub swipe_straightness: f32,
+  // longest pinch that is still a tap, and how long one has to be held to count as a pinch
+  pub tap_duration: f32,
+  pub pinch_hold: f32,
+  // how far a tap or held pinch can move before it is neither
+  pub pinch_movement: f32,
+  pub circle_min_radius: f32,
+  pub circle_max_radius: f32,
+  pub circle_duration: f32,
+  // part of a full turn the loop has to cover
+  pub circle_coverage: f32,
+  // how much the radius can vary around the loop, relative to its mean
+  pub circle_roundness: f32,
+  // after a swipe or circle the source is ignored this long so one motion does not fire twice
+  pub cooldown: f32
+}
+
+impl Default for GestureThresholds {
+  fn default() -> Self {
+    GestureThresholds {
+      swipe_distance: 0.15,
+      swipe_duration: 0.5,
+      swipe_drift: 0.5,
+      swipe_straightness: 0.9,
+      tap_duration: 0.3,
+      pinch_hold: 0.5,
+      pinch_movement: 0.03,
+      circle_min_radius: 0.04,
+      circle_max_radius: 0.4,
+      circle_duration: 1.5,
+      circle_coverage: 0.9,
+      circle_roundness: 0.35,
+      cooldown: 0.3
+    }
+  }
+}
+
+struct PinchState {
+  start: f32,
+  origin: Vec3,
+  moved: bool,
+  held: bool
+}
+
+#[derive(Default)]
+struct Track {
+  // oldest first, no older than the longest gesture
+  samples: VecDeque<PoseSample>,
+  pinch: Option<PinchState>,
+  quiet_until: f32
+}
+
+// turns the pose streams of the hands and the pointer into gestures. streams are fed once a frame and the gestures
+// found in that frame are kept until the next one
+pub struct GestureRecognizer {
+  pub thresholds: GestureThresholds,
+  tracks: HashMap<GestureSource, Track>,
+  events: Vec<GestureEvent>,
+  recording: Option<PoseRecording>
+}
+
+impl GestureRecognizer {
+  // the mouse and controllers are read at a point this far along the pointer, so turning them moves it like a hand
+  const POINTER_REACH: f32 = 1.0;
+  // fewer samples than this do not describe a loop
+  const CIRCLE_SAMPLES: usize = 8;
+
+  pub fn new(thresholds: GestureThresholds) -> Self {
+    GestureRecognizer {
+      thresholds,
+      tracks: HashMap::new(),
+      events: Vec::new(),
+      recording: None
+    }
+  }
+
+  pub fn events(&self) -> &[GestureEvent] {
+    &self.events
+  }
+
+  pub fn begin_frame(&mut self) {
+    self.events.clear();
+  }
+
+  // feeds the tracked hands and the pointer of the resolved input. directions are taken relative to the head
+  pub fn observe(&mut self, input: &Input, time: f32) {
+    let to_head = input.head().inverse();
+
+    for hand in Hand::BOTH {
+      let sample = input.hand(hand).map(|pose| GestureRecognizer::hand_sample(pose, to_head, time));
+      self.push(GestureSource::Hand(hand), sample);
+    }
+
+    let pointer = input.pose(Input::POINTER).map(|pose| {
+      let fingers = match (input.held(Input::GRAB), input.held(Input::SELECT)) {
+        (true, _)      => 2,
+        (false, true)  => 1,
+        (false, false) => 0
+      };
+
+      PoseSample {
+        time,
+        position: to_head.transform_point3(pose.transform_point3(Vec3::NEG_Z * GestureRecognizer::POINTER_REACH)),
+        fingers,
+        pinching: input.held(Input::SELECT)
+      }
+    });
+
+    self.push(GestureSource::Pointer, pointer);
+  }
+
+  // feeds the next sample of a stream, None while the source is not tracked
+  pub fn push(&mut self, source: GestureSource, sample: Option<PoseSample>) {
+    let Some(sample) = sample else {
+      self.tracks.remove(&source);
+      return;
+    };
+
+    if let Some(recording) = self.recording.as_mut().filter(|recording| recording.source == source) {
+      recording.samples.push(sample);
+    }
+
+    let thresholds = self.thresholds;
+    let track = self.tracks.entry(source).or_default();
+    let previous = track.samples.back().copied();
+
+    track.samples.push_back(sample);
+
+    let window = thresholds.swipe_duration.max(thresholds.circle_duration);
+    while track.samples.front().is_some_and(|oldest| sample.time - oldest.time > window) {
+      track.samples.pop_front();
+    }
+
+    let mut found = track.pinch(sample, previous, &thresholds);
+
+    if sample.time >= track.quiet_until {
+      if let Some(gesture) = track.swipe(&thresholds).or_else(|| track.circle(&thresholds)) {
+        found.push(gesture);
+
+        track.quiet_until = sample.time + thresholds.cooldown;
+        track.samples.clear();
+        track.samples.push_back(sample);
+      }
+    }
+
+    self.events.extend(found.into_iter().map(|gesture| GestureEvent { gesture, source, time: sample.time }));
+  }
+
+  // every sample the source is fed from now on is kept until the recording is stopped
+  pub fn start_recording(&mut self, source: GestureSource) {
+    self.recording = Some(PoseRecording::new(source));
+  }
+
+  // times are moved to start at zero
+  pub fn stop_recording(&mut self) -> Option<PoseRecording> {
+    let mut recording = self.recording.take()?;
+    let start = recording.samples.first().map(|sample| sample.time).unwrap_or(0.0);
+
+    for sample in &mut recording.samples {
+      sample.time -= start;
+    }
+
+    Some(recording)
+  }
+
+  // the midpoint of the fingers held out, or the index tip when the hand is closed
+  fn hand_sample(pose: &HandPose, to_head: Mat4, time: f32) -> PoseSample {
+    let extended: Vec<usize> = (1..=4).filter(|finger| pose.extended(*finger)).collect();
+
+    let position = match extended.is_empty() {
+      true  => pose.joint(HandPose::INDEX_TIP).position,
+      false => {
+        let sum: Vec3 = extended.iter().map(|finger| pose.joint(HandPose::INDEX_TIP + (finger - 1) * 5).position).sum();
+        sum / extended.len() as f32
+      }
+    };
+
+    PoseSample {
+      time,
+      position: to_head.transform_point3(position),
+      fingers: extended.len() as u8,
+      pinching: pose.pinching()
+    }
+  }
+}
+
+impl Track {
+  // the newest samples that were all made with the same fingers held out as the last one, oldest first
+  fn stroke(&self, duration: f32) -> Vec<PoseSample> {
+    let Some(last) = self.samples.back() else { return Vec::new(); };
+    if last.fingers == 0 { return Vec::new(); }
+
+    let mut stroke: Vec<PoseSample> = self.samples.iter().rev()
+      .take_while(|sample| sample.fingers == last.fingers && last.time - sample.time <= duration)
+      .copied()
+      .collect();
+
+    stroke.reverse();
+    stroke
+  }
+
+  fn pinch(&mut self, sample: PoseSample, previous: Option<PoseSample>, thresholds: &GestureThresholds) -> Vec<Gesture> {
+    let was_pinching = previous.is_some_and(|previous| previous.pinching);
+    let mut found = Vec::new();
+
+    match (was_pinching, sample.pinching) {
+      (false, true) => {
+        self.pinch = Some(PinchState { start: sample.time, origin: sample.position, moved: false, held: false });
+      },
+      (true, true) => {
+        let Some(pinch) = &mut self.pinch else { return found; };
+        pinch.moved |= pinch.origin.distance(sample.position) > thresholds.pinch_movement;
+
+        if !pinch.moved && !pinch.held && sample.time - pinch.start >= thresholds.pinch_hold {
+          pinch.held = true;
+          found.push(Gesture::Pinch);
+        }
+      },
+      (true, false) => {
+        let Some(pinch) = self.pinch.take() else { return found; };
+
+        if !pinch.moved && sample.time - pinch.start <= thresholds.tap_duration {
+          found.push(Gesture::AirTap);
+        }
+      },
+      (false, false) => ()
+    }
+
+    found
+  }
+
+  // tries every start of the stroke from the oldest, since it may have wandered before the swipe began
+  fn swipe(&self, thresholds: &GestureThresholds) -> Option<Gesture> {
+    let stroke = self.stroke(thresholds.swipe_duration);
+    let last = *stroke.last()?;
+    let mut path = 0.0;
+    let mut lengths = vec![0.0; stroke.len()];
+
+    // path length from each sample to the end of the stroke
+    for index in (0..stroke.len().saturating_sub(1)).rev() {
+      path += stroke[index].position.distance(stroke[index + 1].position);
+      lengths[index] = path;
+    }
+
+    stroke.iter().zip(lengths).find_map(|(start, length)| {
+      let delta = (last.position - start.position).truncate();
+
+      let (along, across, direction) = match delta.x.abs() > delta.y.abs() {
+        true if delta.x > 0.0 => (delta.x, delta.y, SwipeDirection::Right),
+        true                  => (-delta.x, delta.y, SwipeDirection::Left),
+        false if delta.y > 0.0 => (delta.y, delta.x, SwipeDirection::Up),
+        false                  => (-delta.y, delta.x, SwipeDirection::Down)
+      };
+
+      let matched = along >= thresholds.swipe_distance
+        && across.abs() <= along * thresholds.swipe_drift
+        && delta.length() >= length * thresholds.swipe_straightness;
+
+      matched.then_some(Gesture::Swipe { direction, fingers: last.fingers })
+    })
+  }
+
+  fn circle(&self, thresholds: &GestureThresholds) -> Option<Gesture> {
+    let stroke = self.stroke(thresholds.circle_duration);
+
+    (0..stroke.len()).find_map(|start| {
+      let points: Vec<Vec2> = stroke[start..].iter().map(|sample| sample.position.truncate()).collect();
+      if points.len() < GestureRecognizer::CIRCLE_SAMPLES { return None; }
+
+      let center = points.iter().sum::<Vec2>() / points.len() as f32;
+      let radii: Vec<f32> = points.iter().map(|point| point.distance(center)).collect();
+      let radius = radii.iter().sum::<f32>() / radii.len() as f32;
+
+      if radius < thresholds.circle_min_radius || radius > thresholds.circle_max_radius { return None; }
+      if radii.iter().any(|other| (other - radius).abs() > radius * thresholds.circle_roundness) { return None; }
+
+      // counter clockwise is positive with y up
+      let turned: f32 = points.windows(2).map(|pair| {
+        let angle = (pair[1] - center).to_angle() - (pair[0] - center).to_angle();
+        (angle + PI).rem_euclid(TAU) - PI
+      }).sum();
+
+      (turned.abs() >= thresholds.circle_coverage * TAU).then_some(Gesture::Circle { clockwise: turned < 0.0 })
+    })
+  }
+}
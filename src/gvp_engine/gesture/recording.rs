@@ -0,0 +1,85 @@
+use crate::gvp_engine::gesture::{Gesture, GestureRecognizer, GestureSource, GestureThresholds, PoseSample};
+
+use serde::{Deserialize, Serialize};
+
+use std::{fs, path::Path};
+
+// a captured pose stream of one source. the ones under fixtures/gestures list what they should be recognized as with
+// the default thresholds, so changes to the recognizer can be checked by replaying them
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PoseRecording {
+  pub source: GestureSource,
+  pub samples: Vec<PoseSample>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub expected: Vec<Gesture>
+}
+
+impl PoseRecording {
+  pub fn new(source: GestureSource) -> Self {
+    PoseRecording {
+      source,
+      samples: Vec::new(),
+      expected: Vec::new()
+    }
+  }
+
+  // feeds every sample through a fresh recognizer and returns the gestures in the order they were found
+  pub fn replay(&self, thresholds: GestureThresholds) -> Vec<Gesture> {
+    let mut recognizer = GestureRecognizer::new(thresholds);
+
+    for sample in &self.samples {
+      recognizer.push(self.source, Some(*sample));
+    }
+
+    recognizer.events().iter().map(|event| event.gesture).collect()
+  }
+
+  pub fn save(&self, path: &Path) {
+    let json = match serde_json::to_string_pretty(self) {
+      Ok(json)   => json,
+      Err(error) => panic!("failed to serialize pose recording with error: {error}")
+    };
+
+    if let Err(error) = fs::write(path, json) {
+      panic!("failed to write pose recording {} with error: {error}", path.display())
+    }
+  }
+
+  pub fn load(path: &Path) -> Self {
+    let json = match fs::read_to_string(path) {
+      Ok(json)   => json,
+      Err(error) => panic!("failed to read pose recording {} with error: {error}", path.display())
+    };
+
+    match serde_json::from_str(&json) {
+      Ok(recording) => recording,
+      Err(error)    => panic!("failed to parse pose recording {} with error: {error}", path.display())
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::path::PathBuf;
+
+  #[test]
+  fn replays_every_fixture_as_expected() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/gestures");
+
+    let mut paths: Vec<PathBuf> = match fs::read_dir(&directory) {
+      Ok(entries) => entries.filter_map(Result::ok).map(|entry| entry.path()).collect(),
+      Err(error)  => panic!("failed to read {} with error: {error}", directory.display())
+    };
+
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "json"));
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+      let recording = PoseRecording::load(&path);
+      assert_eq!(recording.replay(GestureThresholds::default()), recording.expected, "{}", path.display());
+    }
+  }
+}
@@ -1,5 +1,7 @@
 mod queuefamilies;
 
+pub use queuefamilies::{QueueFamily, QueueFamilyType};
+
 use queuefamilies::*;
 
 use ash::{vk, khr::surface};
@@ -8,6 +10,13 @@ use std::{ffi::CStr, vec::Vec};
 
 pub struct GPU {
   pub device: vk::PhysicalDevice,
+  pub properties: vk::PhysicalDeviceProperties,
+  // the optional features the device gets created with, limited to what the gpu supports
+  pub features: vk::PhysicalDeviceFeatures,
+  // acceleration structures and ray queries, which the path tracer uses in place of its own hierarchies when supported
+  pub ray_query: bool,
+  // bits of the main queue's timestamps that count, 0 when it cannot write them
+  pub timestamp_bits: u32,
   queue_families: QueueFamilyMap
 }
 
@@ -17,11 +26,13 @@ impl GPU {
   // 2. methods for accessing queue families
   // 3. query whether the gpu contains a specific queue
 
+  // required limits the choice to one gpu, like the one an xr runtime says its headset is connected to
   pub fn get(
     instance: &ash::Instance,
     surface_loader: &surface::Instance,
     surface: &vk::SurfaceKHR,
-    extensions: &Vec<*const i8>
+    extensions: &Vec<*const i8>,
+    required: Option<vk::PhysicalDevice>
   ) -> Self {
     // 1. get a list of all gpus
     // 2. loop through all gpus and check if they are suitable
@@ -32,6 +43,7 @@ impl GPU {
     //      - Physical GPU
     //      - Integrated
     //      - Virtual
+    //      - CPU, like lavapipe, when there is nothing else
     // 4. Store gpu in a variable called best option. Move most suitable gpu into it based on priority
     // 5. Store the queue families
 
@@ -45,13 +57,11 @@ impl GPU {
     let mut queue_families : Option<QueueFamilyMap> = None;
 
     'gpu_loop: for gpu in gpus {
+      if required.is_some_and(|required| required != gpu) { continue; }
+
       let properties = unsafe { instance.get_physical_device_properties(gpu) };
 
-      match properties.device_type {
-        vk::PhysicalDeviceType::CPU   => continue,
-        vk::PhysicalDeviceType::OTHER => continue,
-        _ => ()
-      }
+      if properties.device_type == vk::PhysicalDeviceType::OTHER { continue; }
 
       if !GPU::has_priority(device_type, properties.device_type) { continue; }
 
@@ -95,12 +105,78 @@ impl GPU {
       panic!("failed to find suitable physical device")
     };
 
+    let device = device.unwrap();
+    let properties = unsafe { instance.get_physical_device_properties(device) };
+    let supported = unsafe { instance.get_physical_device_features(device) };
+
+    let features = {
+      vk::PhysicalDeviceFeatures::default()
+        .sampler_anisotropy(supported.sampler_anisotropy == vk::TRUE)
+        .texture_compression_bc(supported.texture_compression_bc == vk::TRUE)
+        .texture_compression_etc2(supported.texture_compression_etc2 == vk::TRUE)
+        .texture_compression_astc_ldr(supported.texture_compression_astc_ldr == vk::TRUE)
+        .pipeline_statistics_query(supported.pipeline_statistics_query == vk::TRUE)
+    };
+
+    let queue_families = queue_families.unwrap();
+    let main_index = queue_families.map[&QueueFamilyType::Main].index;
+    let families = unsafe { instance.get_physical_device_queue_family_properties(device) };
+
     GPU {
-      device: device.unwrap(),
-      queue_families: queue_families.unwrap()
+      device,
+      properties,
+      features,
+      ray_query: GPU::supports_ray_query(instance, device),
+      timestamp_bits: families.get(main_index).map_or(0, |family| family.timestamp_valid_bits),
+      queue_families
     }
   }
 
+  // the extensions the device is created with when ray queries are supported
+  pub fn ray_query_extensions() -> [&'static CStr; 3] {
+    [
+      vk::KHR_ACCELERATION_STRUCTURE_NAME,
+      vk::KHR_RAY_QUERY_NAME,
+      vk::KHR_DEFERRED_HOST_OPERATIONS_NAME
+    ]
+  }
+
+  fn supports_ray_query(instance: &ash::Instance, device: vk::PhysicalDevice) -> bool {
+    let extensions = match unsafe { instance.enumerate_device_extension_properties(device) } {
+      Ok(extensions)  => extensions,
+      Err(error)      => panic!("failed to get gpu extensions to check for ray queries with error: {error}")
+    };
+
+    let supported = GPU::ray_query_extensions().iter().all(|required| {
+      extensions.iter().any(|extension| extension.extension_name_as_c_str() == Ok(*required))
+    });
+
+    if !supported { return false; }
+
+    let mut acceleration_structure = vk::PhysicalDeviceAccelerationStructureFeaturesKHR::default();
+    let mut ray_query = vk::PhysicalDeviceRayQueryFeaturesKHR::default();
+    let mut vulkan_12 = vk::PhysicalDeviceVulkan12Features::default();
+
+    let mut features = {
+      vk::PhysicalDeviceFeatures2::default()
+        .push_next(&mut acceleration_structure)
+        .push_next(&mut ray_query)
+        .push_next(&mut vulkan_12)
+    };
+
+    unsafe { instance.get_physical_device_features2(device, &mut features) };
+
+    acceleration_structure.acceleration_structure == vk::TRUE
+      && ray_query.ray_query == vk::TRUE
+      && vulkan_12.buffer_device_address == vk::TRUE
+  }
+
+  // whether optimally tiled images of the format support every one of the given features
+  pub fn supports_format(&self, instance: &ash::Instance, format: vk::Format, features: vk::FormatFeatureFlags) -> bool {
+    let properties = unsafe { instance.get_physical_device_format_properties(self.device, format) };
+    properties.optimal_tiling_features.contains(features)
+  }
+
   pub fn queue_create_infos(&self) -> Vec<vk::DeviceQueueCreateInfo> {
     let mut create_infos = Vec::<vk::DeviceQueueCreateInfo>::new();
 
@@ -123,10 +199,23 @@ impl GPU {
     }
   }
 
+  pub fn queue_family(&self, family_type: &QueueFamilyType) -> Option<&QueueFamily> {
+    self.queue_families.map.get(family_type)
+  }
+
+  // the main queue is guaranteed to exist since gpus without one are never selected
+  pub fn main_queue(&self) -> &QueueFamily {
+    match self.queue_family(&QueueFamilyType::Main) {
+      Some(queue_family)  => queue_family,
+      None                => panic!("failed to find main queue family on selected gpu")
+    }
+  }
+
   // checks to see if gpu2 has priority over gpu1.
   fn has_priority(gpu1: vk::PhysicalDeviceType, gpu2: vk::PhysicalDeviceType) -> bool {
     match ( gpu1, gpu2 ) {
-      ( vk::PhysicalDeviceType::OTHER, _ )                                              => return true,
+      // nothing picked yet, or a cpu implementation like lavapipe which any real gpu beats
+      ( vk::PhysicalDeviceType::OTHER | vk::PhysicalDeviceType::CPU, _ )                => return true,
       ( vk::PhysicalDeviceType::INTEGRATED_GPU, vk::PhysicalDeviceType::DISCRETE_GPU )  => return true,
       ( vk::PhysicalDeviceType::VIRTUAL_GPU, vk::PhysicalDeviceType::DISCRETE_GPU )     => return true,
       ( vk::PhysicalDeviceType::VIRTUAL_GPU, vk::PhysicalDeviceType::INTEGRATED_GPU )   => return true,
@@ -0,0 +1,646 @@
+mod layout;
+mod painter;
+mod theme;
+mod widget;
+
+pub use layout::*;
+pub use painter::*;
+pub use theme::*;
+pub use widget::*;
+
+use crate::gvp_engine::input::Input;
+
+use glam::Vec2;
+use sdl2::keyboard::Keycode;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WidgetId(usize);
+
+// what the input did to widgets during an update, in the order it happened
+#[derive(Clone, Debug, PartialEq)]
+pub enum GuiEvent {
+  Clicked(WidgetId),
+  Toggled(WidgetId, bool),
+  // a slider moved to a new value
+  Changed(WidgetId, f32),
+  // the text of a text field was edited
+  Edited(WidgetId),
+  // enter was pressed in a text field
+  Submitted(WidgetId),
+  // an item of a list or a tab was picked
+  Selected(WidgetId, usize),
+  // a modal closed itself from a click outside its card or going back
+  Dismissed(WidgetId),
+  Focused(Option<WidgetId>)
+}
+
+// the part of the engine's input a gui reads in a frame
+#[derive(Clone, Debug, Default)]
+pub struct GuiInput {
+  // over the gui, in its pixels
+  pub pointer: Option<Vec2>,
+  // the select action
+  pub pressed: bool,
+  pub held: bool,
+  pub released: bool,
+  // the scroll action in wheel clicks, up and right being positive
+  pub scroll: Vec2,
+  // went down this frame, repeating while held
+  pub keys: Vec<Keycode>,
+  pub text: String,
+  pub shift: bool,
+  // the back action, unless it came from the backspace key which text fields use
+  pub back: bool
+}
+
+impl GuiInput {
+  // the pointer comes from wherever the gui is shown, like a panel hit. only the focused gui gets the keyboard
+  pub fn from_input(input: &Input, pointer: Option<Vec2>, focused: bool) -> Self {
+    let desktop = input.desktop();
+    let backspace = desktop.key_presses().contains(&Keycode::Backspace);
+
+    let (keys, text) = match focused {
+      true  => (desktop.key_presses().to_vec(), desktop.typed().to_string()),
+      false => (Vec::new(), String::new())
+    };
+
+    GuiInput {
+      pointer,
+      pressed: input.pressed(Input::SELECT),
+      held: input.held(Input::SELECT),
+      released: input.released(Input::SELECT),
+      scroll: pointer.map_or(Vec2::ZERO, |_| input.axis_2d(Input::SCROLL)),
+      keys,
+      text,
+      shift: desktop.key_held(Keycode::LShift) || desktop.key_held(Keycode::RShift),
+      back: focused && input.pressed(Input::BACK) && !backspace
+    }
+  }
+}
+
+struct Node {
+  widget: Widget,
+  style: Style,
+  parent: Option<WidgetId>,
+  children: Vec<WidgetId>,
+  visible: bool
+}
+
+// a retained tree of widgets, laid out like flexbox and drawn into a canvas. input is routed to the widget under the
+// pointer or the focused one, and what it did comes back as events
+pub struct Gui {
+  pub theme: Theme,
+  // ids index into this and are not reused once a widget is removed
+  nodes: Vec<Option<Node>>,
+  placements: Vec<Placement>,
+  size: Vec2,
+  measure: Box<dyn TextMeasure>,
+  pointer: Option<Vec2>,
+  hovered: Option<WidgetId>,
+  // took the pointer when it went down and keeps it until it goes up
+  pressed: Option<WidgetId>,
+  focused: Option<WidgetId>,
+  events: Vec<GuiEvent>
+}
+
+impl Gui {
+  pub const ROOT: WidgetId = WidgetId(0);
+
+  // the root is a column filling the whole gui
+  pub fn new(theme: Theme) -> Self {
+    let root = Node {
+      widget: Widget::container(),
+      style: Style { padding: Edges::all(theme.padding), gap: theme.spacing, ..Style::default() },
+      parent: None,
+      children: Vec::new(),
+      visible: true
+    };
+
+    Gui {
+      theme,
+      nodes: vec![Some(root)],
+      placements: Vec::new(),
+      size: Vec2::ZERO,
+      measure: Box::new(ApproximateText),
+      pointer: None,
+      hovered: None,
+      pressed: None,
+      focused: None,
+      events: Vec::new()
+    }
+  }
+
+  // text is measured approximately until a font is set
+  pub fn set_text_measure(&mut self, measure: Box<dyn TextMeasure>) {
+    self.measure = measure;
+  }
+
+  pub fn add(&mut self, parent: WidgetId, widget: Widget, style: Style) -> WidgetId {
+    let id = WidgetId(self.nodes.len());
+
+    self.nodes.push(Some(Node { widget, style, parent: Some(parent), children: Vec::new(), visible: true }));
+    self.node_mut(parent).children.push(id);
+
+    id
+  }
+
+  pub fn widget(&self, id: WidgetId) -> &Widget {
+    &self.node(id).widget
+  }
+
+  pub fn widget_mut(&mut self, id: WidgetId) -> &mut Widget {
+    &mut self.node_mut(id).widget
+  }
+
+  // hidden widgets take no space and get no input
+  pub fn set_visible(&mut self, id: WidgetId, visible: bool) {
+    self.node_mut(id).visible = visible;
+  }
+
+  pub fn set_open(&mut self, modal: WidgetId, open: bool) {
+    if let Widget::Modal { open: current } = self.widget_mut(modal) {
+      *current = open;
+    }
+  }
+
+  // where the widget was placed by the last update
+  pub fn rect(&self, id: WidgetId) -> Rect {
+    self.placements.get(id.0).map_or(Rect::EMPTY, |placement| placement.rect)
+  }
+
+  pub fn focus(&mut self, id: Option<WidgetId>) {
+    if self.focused == id { return; }
+
+    self.focused = id;
+    self.events.push(GuiEvent::Focused(id));
+  }
+
+  // from the last update
+  pub fn events(&self) -> &[GuiEvent] {
+    &self.events
+  }
+
+  // lays the gui out at a size in pixels and routes a frame of input through it, replacing the events
+  pub fn update(&mut self, size: Vec2, input: &GuiInput) {
+    self.events.clear();
+    self.size = size;
+    self.layout();
+
+    let scope = self.scope();
+    let hit = input.pointer.and_then(|point| self.hit_test(scope, point, Rect::new(Vec2::ZERO, size)));
+
+    self.pointer = input.pointer;
+    self.hovered = hit.and_then(|id| self.target(id));
+
+    if let (true, Some(point)) = (input.pressed, input.pointer) {
+      self.press(scope, point);
+    }
+
+    if let (true, Some(id), Some(point)) = (input.held, self.pressed, input.pointer) {
+      self.drag(id, point);
+    }
+
+    if input.released {
+      if let Some(id) = self.pressed.take().filter(|id| Some(*id) == self.hovered) {
+        self.activate(id);
+      }
+    }
+
+    if input.scroll != Vec2::ZERO {
+      if let Some(id) = hit {
+        self.scroll(id, input.scroll);
+      }
+    }
+
+    self.keyboard(scope, input);
+
+    // what the input changed shows up this frame
+    self.layout();
+  }
+
+  pub fn paint(&self) -> Canvas {
+    let mut canvas = Canvas::new(self.size, self.theme.background);
+
+    self.paint_node(&mut canvas, Gui::ROOT);
+
+    for modal in self.open_modals() {
+      self.paint_node(&mut canvas, modal);
+    }
+
+    canvas
+  }
+
+  fn node(&self, id: WidgetId) -> &Node {
+    match self.nodes.get(id.0).and_then(Option::as_ref) {
+      Some(node) => node,
+      None       => panic!("failed to find widget {} with error: it was removed", id.0)
+    }
+  }
+
+  fn node_mut(&mut self, id: WidgetId) -> &mut Node {
+    match self.nodes.get_mut(id.0).and_then(Option::as_mut) {
+      Some(node) => node,
+      None       => panic!("failed to find widget {} with error: it was removed", id.0)
+    }
+  }
+
+  // children taking part in the layout. modals are laid out over the whole gui instead and tabs only show one page
+  fn flow(&self, id: WidgetId) -> Vec<WidgetId> {
+    let node = self.node(id);
+    let shown = node.children.iter().copied().filter(|child| {
+      let child = self.node(*child);
+      child.visible && !matches!(child.widget, Widget::Modal { .. })
+    });
+
+    match node.widget {
+      Widget::Tabs { selected, .. } => node.children.get(selected).copied().filter(|page| self.node(*page).visible).into_iter().collect(),
+      _                             => shown.collect()
+    }
+  }
+
+  // every open modal in drawing order, the last one being on top
+  fn open_modals(&self) -> Vec<WidgetId> {
+    let mut modals = Vec::new();
+    let mut stack = vec![Gui::ROOT];
+
+    while let Some(id) = stack.pop() {
+      let node = self.node(id);
+      if !node.visible { continue; }
+
+      if let Widget::Modal { open } = node.widget {
+        if !open { continue; }
+        modals.push(id);
+      }
+
+      let children = match node.widget {
+        Widget::Tabs { selected, .. } => node.children.get(selected).copied().into_iter().collect(),
+        _                             => node.children.clone()
+      };
+
+      stack.extend(children.into_iter().rev());
+    }
+
+    modals
+  }
+
+  // the top open modal takes all input, otherwise the whole gui does
+  fn scope(&self) -> WidgetId {
+    self.open_modals().last().copied().unwrap_or(Gui::ROOT)
+  }
+
+  fn layout(&mut self) {
+    let empty = Style::default();
+    let bounds = Rect::new(Vec2::ZERO, self.size);
+
+    // offsets are kept within what there is to scroll through as the content changes
+    for (index, placement) in self.placements.iter().enumerate() {
+      let Some(node) = self.nodes[index].as_mut() else { continue; };
+      let theme = &self.theme;
+
+      match &mut node.widget {
+        Widget::ScrollView { offset } => *offset = offset.clamp(Vec2::ZERO, (placement.content - placement.rect.size).max(Vec2::ZERO)),
+        Widget::List { items, scroll, .. } => {
+          *scroll = scroll.clamp(0.0, (items.len() as f32 * theme.control_height - placement.rect.size.y).max(0.0));
+        },
+        _ => ()
+      }
+    }
+
+    let nodes: Vec<LayoutNode> = self.nodes.iter().enumerate().map(|(index, node)| match node {
+      Some(node) => LayoutNode {
+        style: &node.style,
+        children: self.flow(WidgetId(index)).into_iter().map(|child| child.0).collect(),
+        content: node.widget.content_size(&self.theme, self.measure.as_ref()),
+        chrome: node.widget.chrome(&self.theme),
+        scroll: match node.widget {
+          Widget::ScrollView { offset } => Some(offset),
+          _                             => None
+        }
+      },
+      None => LayoutNode { style: &empty, children: Vec::new(), content: Vec2::ZERO, chrome: Edges::ZERO, scroll: None }
+    }).collect();
+
+    let mut placements = vec![Placement::default(); self.nodes.len()];
+    layout(&nodes, Gui::ROOT.0, bounds, &mut placements);
+
+    for modal in self.open_modals() {
+      layout(&nodes, modal.0, bounds, &mut placements);
+    }
+
+    self.placements = placements;
+  }
+
+  // the deepest shown widget under the point, scroll views cutting off what is outside them
+  fn hit_test(&self, id: WidgetId, point: Vec2, clip: Rect) -> Option<WidgetId> {
+    let rect = self.rect(id);
+
+    let inner_clip = match self.node(id).widget {
+      Widget::ScrollView { .. } => clip.intersect(rect),
+      _                         => clip
+    };
+
+    self.flow(id).into_iter().rev()
+      .find_map(|child| self.hit_test(child, point, inner_clip))
+      .or((rect.contains(point) && clip.contains(point)).then_some(id))
+  }
+
+  // what a hit on the widget acts on, itself or the nearest focusable widget above it
+  fn target(&self, mut id: WidgetId) -> Option<WidgetId> {
+    loop {
+      let node = self.node(id);
+      if node.widget.focusable() { return Some(id); }
+
+      id = node.parent?;
+    }
+  }
+
+  fn press(&mut self, scope: WidgetId, point: Vec2) {
+    if scope != Gui::ROOT && !self.card(scope).contains(point) {
+      self.dismiss(scope);
+      return;
+    }
+
+    self.pressed = self.hovered;
+    self.focus(self.hovered);
+
+    let Some(id) = self.hovered else { return; };
+    let rect = self.rect(id);
+    let (theme, measure) = (&self.theme, self.measure.as_ref());
+
+    let widget = self.nodes[id.0].as_mut().map(|node| &mut node.widget);
+
+    let selected = match widget {
+      Some(Widget::TextField { text, caret, .. }) => {
+        *caret = Widget::field_index(text, *caret, rect, point.x, theme, measure);
+        None
+      },
+      Some(Widget::List { items, selected, scroll }) => {
+        let row = Widget::list_row(rect, *scroll, items.len(), point, theme);
+        if row.is_some() { *selected = row; }
+        row
+      },
+      Some(Widget::Tabs { titles, selected }) => {
+        let tab = Widget::tab_rects(titles, rect, theme, measure).iter().position(|tab| tab.contains(point));
+        if let Some(tab) = tab { *selected = tab; }
+        tab
+      },
+      _ => None
+    };
+
+    if let Some(index) = selected {
+      self.events.push(GuiEvent::Selected(id, index));
+    }
+
+    self.drag(id, point);
+  }
+
+  // sliders follow the pointer while it is held
+  fn drag(&mut self, id: WidgetId, point: Vec2) {
+    let rect = self.rect(id);
+    let theme = &self.theme;
+
+    let Some(Widget::Slider { value, min, max, step }) = self.nodes[id.0].as_mut().map(|node| &mut node.widget) else { return; };
+
+    let dragged = Widget::slider_value(rect, point.x, (*min, *max, *step), theme);
+    if dragged == *value { return; }
+
+    *value = dragged;
+    self.events.push(GuiEvent::Changed(id, dragged));
+  }
+
+  // a click, or enter and space on the focused widget
+  fn activate(&mut self, id: WidgetId) {
+    match self.widget_mut(id) {
+      Widget::Button { .. } => self.events.push(GuiEvent::Clicked(id)),
+      Widget::Checkbox { checked, .. } => {
+        *checked = !*checked;
+        let checked = *checked;
+        self.events.push(GuiEvent::Toggled(id, checked));
+      },
+      _ => ()
+    }
+  }
+
+  // by the nearest list or scroll view at or above the widget
+  fn scroll(&mut self, mut id: WidgetId, wheel: Vec2) {
+    let amount = wheel * self.theme.scroll_speed;
+
+    loop {
+      let node = self.node_mut(id);
+
+      match &mut node.widget {
+        Widget::List { scroll, .. } => {
+          *scroll -= amount.y;
+          return;
+        },
+        Widget::ScrollView { offset } => {
+          *offset += Vec2::new(amount.x, -amount.y);
+          return;
+        },
+        _ => ()
+      }
+
+      let Some(parent) = node.parent else { return; };
+      id = parent;
+    }
+  }
+
+  fn keyboard(&mut self, scope: WidgetId, input: &GuiInput) {
+    for key in &input.keys {
+      match (*key, self.focused) {
+        (Keycode::Tab, _) => self.cycle_focus(scope, input.shift),
+        (key, Some(id))   => self.key(id, key),
+        (_, None)         => ()
+      }
+    }
+
+    let typed: String = input.text.chars().filter(|character| !character.is_control()).collect();
+
+    if let (false, Some(id)) = (typed.is_empty(), self.focused) {
+      if let Widget::TextField { text, caret, .. } = self.widget_mut(id) {
+        text.insert_str(Gui::byte_index(text, *caret), &typed);
+        *caret += typed.chars().count();
+        self.events.push(GuiEvent::Edited(id));
+      }
+    }
+
+    if input.back {
+      match scope == Gui::ROOT {
+        true  => self.focus(None),
+        false => self.dismiss(scope)
+      }
+    }
+  }
+
+  fn key(&mut self, id: WidgetId, key: Keycode) {
+    let enter = matches!(key, Keycode::Return | Keycode::KpEnter);
+    let space = key == Keycode::Space;
+    let step = |direction: f32| match key {
+      Keycode::Left | Keycode::Down => -direction,
+      Keycode::Right | Keycode::Up  => direction,
+      _                             => 0.0
+    };
+
+    if (enter || space) && matches!(self.widget(id), Widget::Button { .. } | Widget::Checkbox { .. }) {
+      self.activate(id);
+      return;
+    }
+
+    let control_height = self.theme.control_height;
+    let list_height = self.rect(id).size.y;
+
+    let event = match self.widget_mut(id) {
+      Widget::TextField { .. } if enter => Some(GuiEvent::Submitted(id)),
+      Widget::TextField { text, caret, .. } => {
+        let count = text.chars().count();
+
+        match key {
+          Keycode::Backspace if *caret > 0 => {
+            *caret -= 1;
+            text.remove(Gui::byte_index(text, *caret));
+            Some(GuiEvent::Edited(id))
+          },
+          Keycode::Delete if *caret < count => {
+            text.remove(Gui::byte_index(text, *caret));
+            Some(GuiEvent::Edited(id))
+          },
+          Keycode::Left  => { *caret = caret.saturating_sub(1); None },
+          Keycode::Right => { *caret = (*caret + 1).min(count); None },
+          Keycode::Home  => { *caret = 0; None },
+          Keycode::End   => { *caret = count; None },
+          _              => None
+        }
+      },
+      Widget::Slider { value, min, max, step: increment } => {
+        let amount = match *increment > 0.0 {
+          true  => *increment,
+          false => (*max - *min) / 20.0
+        };
+
+        let moved = Widget::snap(*value + step(amount), (*min, *max, *increment));
+
+        match moved != *value {
+          true  => { *value = moved; Some(GuiEvent::Changed(id, moved)) },
+          false => None
+        }
+      },
+      Widget::List { items, selected, scroll } if !items.is_empty() => {
+        let last = items.len() - 1;
+
+        let next = match (key, *selected) {
+          (Keycode::Down, Some(index))        => Some((index + 1).min(last)),
+          (Keycode::Up, Some(index))          => Some(index.saturating_sub(1)),
+          (Keycode::Down | Keycode::Up, None) => Some(0),
+          _                                   => None
+        };
+
+        next.filter(|next| Some(*next) != *selected).map(|next| {
+          *selected = Some(next);
+
+          // keeps the row in view
+          let top = next as f32 * control_height;
+          *scroll = scroll.clamp(top + control_height - list_height, top);
+
+          GuiEvent::Selected(id, next)
+        })
+      },
+      Widget::Tabs { titles, selected } => {
+        let next = (*selected as f32 + step(1.0)).clamp(0.0, titles.len().saturating_sub(1) as f32) as usize;
+
+        match next != *selected {
+          true  => { *selected = next; Some(GuiEvent::Selected(id, next)) },
+          false => None
+        }
+      },
+      _ => None
+    };
+
+    self.events.extend(event);
+  }
+
+  // to the next focusable widget in tree order, or the previous one, wrapping around
+  fn cycle_focus(&mut self, scope: WidgetId, backwards: bool) {
+    let mut focusable = Vec::new();
+    let mut stack = vec![scope];
+
+    while let Some(id) = stack.pop() {
+      if self.node(id).widget.focusable() {
+        focusable.push(id);
+      }
+
+      stack.extend(self.flow(id).into_iter().rev());
+    }
+
+    if focusable.is_empty() { return; }
+
+    let current = self.focused.and_then(|focused| focusable.iter().position(|id| *id == focused));
+    let count = focusable.len();
+
+    let next = match (current, backwards) {
+      (Some(index), false) => (index + 1) % count,
+      (Some(index), true)  => (index + count - 1) % count,
+      (None, false)        => 0,
+      (None, true)         => count - 1
+    };
+
+    self.focus(Some(focusable[next]));
+  }
+
+  fn dismiss(&mut self, modal: WidgetId) {
+    self.set_open(modal, false);
+    self.focus(None);
+    self.events.push(GuiEvent::Dismissed(modal));
+  }
+
+  // the dialog a modal's children sit on
+  fn card(&self, modal: WidgetId) -> Rect {
+    let children = self.flow(modal).into_iter().map(|child| self.rect(child));
+    children.reduce(|card, rect| card.union(rect)).unwrap_or(Rect::EMPTY).expand(self.theme.padding)
+  }
+
+  fn paint_node(&self, canvas: &mut Canvas, id: WidgetId) {
+    let node = self.node(id);
+    let placement = self.placements.get(id.0).copied().unwrap_or_default();
+    let rect = placement.rect;
+
+    let state = WidgetState {
+      hovered: self.hovered == Some(id),
+      pressed: self.pressed == Some(id),
+      focused: self.focused == Some(id),
+      pointer: self.pointer
+    };
+
+    node.widget.paint(canvas, rect, state, &self.theme, self.measure.as_ref());
+
+    if let Widget::Modal { .. } = node.widget {
+      canvas.rect(self.card(id), self.theme.dialog, self.theme.radius);
+    }
+
+    let scroll = match node.widget {
+      Widget::ScrollView { offset } => Some(offset),
+      _                             => None
+    };
+
+    if scroll.is_some() {
+      canvas.push_clip(rect);
+    }
+
+    for child in self.flow(id) {
+      self.paint_node(canvas, child);
+    }
+
+    if let Some(offset) = scroll {
+      canvas.pop_clip();
+      Widget::paint_scrollbars(canvas, rect, placement.content, offset, &self.theme);
+    }
+
+    if state.focused {
+      let width = self.theme.focus_width;
+      canvas.outline(rect.expand(width + 1.0), self.theme.focus, self.theme.radius + width + 1.0, width);
+    }
+  }
+
+  fn byte_index(text: &str, index: usize) -> usize {
+    text.char_indices().nth(index).map_or(text.len(), |(byte, _)| byte)
+  }
+}
@@ -0,0 +1,490 @@
+use crate::gvp_engine::gui::Rect;
+
+use glam::Vec2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Dimension {
+  // sized by the content, or stretched by the parent
+  #[default]
+  Auto,
+  Pixels(f32),
+  // of the parent's content box. acts like auto when the parent is sized by its own content
+  Percent(f32)
+}
+
+impl Dimension {
+  fn resolve(&self, available: f32) -> Option<f32> {
+    match self {
+      Dimension::Auto             => None,
+      Dimension::Pixels(pixels)   => Some(*pixels),
+      Dimension::Percent(percent) => available.is_finite().then_some(available * percent / 100.0)
+    }
+  }
+}
+
+// the main axis children are laid out along
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Direction {
+  Row,
+  #[default]
+  Column
+}
+
+impl Direction {
+  fn main(&self, size: Vec2) -> f32 {
+    match self {
+      Direction::Row    => size.x,
+      Direction::Column => size.y
+    }
+  }
+
+  fn cross(&self, size: Vec2) -> f32 {
+    match self {
+      Direction::Row    => size.y,
+      Direction::Column => size.x
+    }
+  }
+
+  fn compose(&self, main: f32, cross: f32) -> Vec2 {
+    match self {
+      Direction::Row    => Vec2::new(main, cross),
+      Direction::Column => Vec2::new(cross, main)
+    }
+  }
+}
+
+// where children go along the main axis when they do not fill it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Justify {
+  #[default]
+  Start,
+  Center,
+  End,
+  SpaceBetween
+}
+
+// where children go across the main axis
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+  Start,
+  Center,
+  End,
+  #[default]
+  Stretch
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Edges {
+  pub top: f32,
+  pub right: f32,
+  pub bottom: f32,
+  pub left: f32
+}
+
+impl Edges {
+  pub const ZERO: Edges = Edges::all(0.0);
+
+  pub const fn all(value: f32) -> Self {
+    Edges { top: value, right: value, bottom: value, left: value }
+  }
+
+  pub const fn symmetric(horizontal: f32, vertical: f32) -> Self {
+    Edges { top: vertical, right: horizontal, bottom: vertical, left: horizontal }
+  }
+
+  pub fn top(value: f32) -> Self {
+    Edges { top: value, ..Edges::ZERO }
+  }
+
+  // both sides along each axis added up
+  pub fn size(&self) -> Vec2 {
+    Vec2::new(self.left + self.right, self.top + self.bottom)
+  }
+
+  pub fn start(&self) -> Vec2 {
+    Vec2::new(self.left, self.top)
+  }
+
+  pub fn end(&self) -> Vec2 {
+    Vec2::new(self.right, self.bottom)
+  }
+
+  pub fn add(&self, other: Edges) -> Edges {
+    Edges {
+      top: self.top + other.top,
+      right: self.right + other.right,
+      bottom: self.bottom + other.bottom,
+      left: self.left + other.left
+    }
+  }
+
+  pub fn shrink(&self, rect: Rect) -> Rect {
+    Rect::new(rect.position + self.start(), (rect.size - self.size()).max(Vec2::ZERO))
+  }
+}
+
+// flexbox without wrapping. children are measured by their content, then grown or shrunk along the main axis to fill
+// their parent, and stretched or aligned across it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+  pub direction: Direction,
+  pub justify: Justify,
+  pub align_items: Align,
+  // overrides the parent's align_items for this widget
+  pub align_self: Option<Align>,
+  pub width: Dimension,
+  pub height: Dimension,
+  pub min_size: Vec2,
+  pub max_size: Vec2,
+  pub padding: Edges,
+  pub margin: Edges,
+  // between children
+  pub gap: f32,
+  // share of the free space along the parent's main axis
+  pub grow: f32,
+  // share of the overflow taken away, weighted by size
+  pub shrink: f32
+}
+
+impl Default for Style {
+  fn default() -> Self {
+    Style {
+      direction: Direction::Column,
+      justify: Justify::Start,
+      align_items: Align::Stretch,
+      align_self: None,
+      width: Dimension::Auto,
+      height: Dimension::Auto,
+      min_size: Vec2::ZERO,
+      max_size: Vec2::INFINITY,
+      padding: Edges::ZERO,
+      margin: Edges::ZERO,
+      gap: 0.0,
+      grow: 0.0,
+      shrink: 1.0
+    }
+  }
+}
+
+impl Style {
+  pub fn row() -> Self {
+    Style { direction: Direction::Row, align_items: Align::Center, ..Style::default() }
+  }
+
+  pub fn column() -> Self {
+    Style::default()
+  }
+
+  // takes all the free space of its parent
+  pub fn fill() -> Self {
+    Style { grow: 1.0, ..Style::default() }
+  }
+
+  // children kept in the middle, like the dialog of a modal
+  pub fn centered() -> Self {
+    Style { justify: Justify::Center, align_items: Align::Center, ..Style::default() }
+  }
+
+  fn clamp(&self, size: Vec2) -> Vec2 {
+    size.min(self.max_size).max(self.min_size)
+  }
+}
+
+// what the layout needs to know about a widget
+pub struct LayoutNode<'a> {
+  pub style: &'a Style,
+  // the ones taking part in the layout, in order
+  pub children: Vec<usize>,
+  // what a widget without children draws, like the text of a label
+  pub content: Vec2,
+  // room the widget keeps around its children for itself, like the bar of tabs
+  pub chrome: Edges,
+  // scroll views lay their children out at their natural size, moved back by the offset
+  pub scroll: Option<Vec2>
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Placement {
+  pub rect: Rect,
+  // how far the children reach including padding, which is what a scroll view can scroll through
+  pub content: Vec2
+}
+
+// places root in rect and everything under it. placements is indexed like nodes and only entries under root change
+pub fn layout(nodes: &[LayoutNode], root: usize, rect: Rect, placements: &mut [Placement]) {
+  let mut sizes = vec![Vec2::ZERO; nodes.len()];
+  measure(nodes, root, rect.size, &mut sizes);
+  arrange(nodes, root, rect, &sizes, placements);
+}
+
+// the size a node wants including its padding but not its margin, bottom up. available is the parent's content box,
+// infinite when that is sized by its content
+fn measure(nodes: &[LayoutNode], index: usize, available: Vec2, sizes: &mut [Vec2]) -> Vec2 {
+  let node = &nodes[index];
+  let style = node.style;
+  let inset = style.padding.add(node.chrome);
+
+  let width = style.width.resolve(available.x);
+  let height = style.height.resolve(available.y);
+
+  let mut inner_available = Vec2::new(width.unwrap_or(available.x), height.unwrap_or(available.y)) - inset.size();
+  if node.scroll.is_some() {
+    inner_available = Vec2::INFINITY;
+  }
+
+  let axis = style.direction;
+  let (mut main, mut cross) = (0.0f32, 0.0f32);
+
+  for child in &node.children {
+    let size = measure(nodes, *child, inner_available, sizes) + nodes[*child].style.margin.size();
+    main += axis.main(size);
+    cross = cross.max(axis.cross(size));
+  }
+
+  main += style.gap * node.children.len().saturating_sub(1) as f32;
+
+  let content = match node.children.is_empty() {
+    true  => node.content,
+    false => axis.compose(main, cross)
+  } + inset.size();
+
+  let size = style.clamp(Vec2::new(width.unwrap_or(content.x), height.unwrap_or(content.y)));
+  sizes[index] = size;
+
+  size
+}
+
+fn arrange(nodes: &[LayoutNode], index: usize, rect: Rect, sizes: &[Vec2], placements: &mut [Placement]) {
+  let node = &nodes[index];
+  let style = node.style;
+  let inset = style.padding.add(node.chrome);
+  let inner = inset.shrink(rect);
+  let axis = style.direction;
+
+  placements[index] = Placement { rect, content: rect.size };
+  if node.children.is_empty() { return; }
+
+  let count = node.children.len();
+  let gaps = style.gap * (count - 1) as f32;
+  let margins: Vec<Vec2> = node.children.iter().map(|child| nodes[*child].style.margin.size()).collect();
+
+  // main axis sizes including margins
+  let mut mains: Vec<f32> = node.children.iter().zip(&margins).map(|(child, margin)| axis.main(sizes[*child] + *margin)).collect();
+  let free = axis.main(inner.size) - mains.iter().sum::<f32>() - gaps;
+
+  if node.scroll.is_none() {
+    let children = || node.children.iter().map(|child| nodes[*child].style);
+
+    if free > 0.0 {
+      let total = children().map(|style| style.grow).sum::<f32>();
+
+      if total > 0.0 {
+        for ((main, child), margin) in mains.iter_mut().zip(children()).zip(&margins) {
+          let grown = *main + free * child.grow / total;
+          *main = grown.min(axis.main(child.max_size + *margin));
+        }
+      }
+    }
+    else if free < 0.0 {
+      let total = children().zip(&mains).map(|(style, main)| style.shrink * main).sum::<f32>();
+
+      if total > 0.0 {
+        for ((main, child), margin) in mains.iter_mut().zip(children()).zip(&margins) {
+          let shrunk = *main + free * child.shrink * *main / total;
+          *main = shrunk.max(axis.main(child.min_size + *margin));
+        }
+      }
+    }
+  }
+
+  let remaining = (axis.main(inner.size) - mains.iter().sum::<f32>() - gaps).max(0.0);
+
+  let (mut cursor, spacing) = match style.justify {
+    Justify::Start                     => (0.0, style.gap),
+    Justify::Center                    => (remaining * 0.5, style.gap),
+    Justify::End                       => (remaining, style.gap),
+    Justify::SpaceBetween if count > 1 => (0.0, style.gap + remaining / (count - 1) as f32),
+    Justify::SpaceBetween              => (0.0, style.gap)
+  };
+
+  let offset = node.scroll.unwrap_or(Vec2::ZERO);
+  let mut reach = Vec2::ZERO;
+
+  for ((child, main), margin) in node.children.iter().zip(&mains).zip(&margins) {
+    let child_style = nodes[*child].style;
+    let available_cross = axis.cross(inner.size) - axis.cross(*margin);
+
+    let cross_dimension = match axis {
+      Direction::Row    => child_style.height,
+      Direction::Column => child_style.width
+    };
+
+    let align = child_style.align_self.unwrap_or(style.align_items);
+    let cross = match (align, cross_dimension) {
+      (Align::Stretch, Dimension::Auto) => axis.cross(child_style.clamp(axis.compose(0.0, available_cross.max(0.0)))),
+      _                                 => axis.cross(sizes[*child])
+    };
+
+    let cross_position = match align {
+      Align::Start | Align::Stretch => 0.0,
+      Align::Center                 => (available_cross - cross) * 0.5,
+      Align::End                    => available_cross - cross
+    };
+
+    let size = axis.compose(main - axis.main(*margin), cross);
+    let position = inner.position + axis.compose(cursor, cross_position) + child_style.margin.start() - offset;
+
+    arrange(nodes, *child, Rect::new(position, size.max(Vec2::ZERO)), sizes, placements);
+
+    reach = reach.max(position + offset + size + child_style.margin.end() - rect.position);
+    cursor += main + spacing;
+  }
+
+  placements[index].content = reach + style.padding.end();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // node 0 is the root, laid out in a rect at the origin
+  fn place(
+    styles: &[Style], children: &[&[usize]], contents: &[Vec2], scroll: Option<Vec2>, size: Vec2
+  ) -> Vec<Placement> {
+    let nodes: Vec<LayoutNode> = styles.iter().enumerate().map(|(index, style)| LayoutNode {
+      style,
+      children: children.get(index).map_or(Vec::new(), |children| children.to_vec()),
+      content: contents[index],
+      chrome: Edges::ZERO,
+      scroll: scroll.filter(|_| index == 0)
+    }).collect();
+
+    let mut placements = vec![Placement::default(); nodes.len()];
+    layout(&nodes, 0, Rect::new(Vec2::ZERO, size), &mut placements);
+
+    placements
+  }
+
+  fn rects(placements: &[Placement]) -> Vec<(Vec2, Vec2)> {
+    placements.iter().map(|placement| (placement.rect.position, placement.rect.size)).collect()
+  }
+
+  #[test]
+  fn measures_rows_by_their_children_gaps_and_padding() {
+    let root = Style { align_items: Align::Start, ..Style::column() };
+    let row = Style { padding: Edges::all(4.0), gap: 2.0, ..Style::row() };
+    let leaf = Style::default();
+
+    let placements = place(
+      &[root, row, leaf, leaf], &[&[1], &[2, 3]], &[Vec2::ZERO, Vec2::ZERO, Vec2::new(10.0, 5.0), Vec2::new(20.0, 8.0)],
+      None, Vec2::new(100.0, 100.0)
+    );
+
+    assert_eq!(rects(&placements)[1..], [
+      (Vec2::ZERO, Vec2::new(40.0, 16.0)),
+      (Vec2::new(4.0, 5.5), Vec2::new(10.0, 5.0)),
+      (Vec2::new(16.0, 4.0), Vec2::new(20.0, 8.0))
+    ]);
+  }
+
+  #[test]
+  fn grows_children_by_their_share_of_the_free_space() {
+    let contents = [Vec2::ZERO; 3];
+    let styles = [Style::row(), Style { grow: 1.0, ..Style::default() }, Style { grow: 3.0, ..Style::default() }];
+
+    let placements = place(&styles, &[&[1, 2]], &contents, None, Vec2::new(100.0, 10.0));
+
+    assert_eq!(placements[1].rect.size.x, 25.0);
+    assert_eq!(placements[2].rect, Rect::new(Vec2::new(25.0, 5.0), Vec2::new(75.0, 0.0)));
+  }
+
+  #[test]
+  fn shrinks_overflowing_children_by_their_size_down_to_their_minimum() {
+    let contents = [Vec2::ZERO, Vec2::new(40.0, 0.0), Vec2::new(80.0, 0.0)];
+    let styles = [Style::row(), Style::default(), Style::default()];
+
+    let placements = place(&styles, &[&[1, 2]], &contents, None, Vec2::new(60.0, 10.0));
+    assert_eq!([placements[1].rect.size.x, placements[2].rect.size.x], [20.0, 40.0]);
+
+    let styles = [Style::row(), Style { min_size: Vec2::new(30.0, 0.0), ..Style::default() }, Style::default()];
+
+    let placements = place(&styles, &[&[1, 2]], &contents, None, Vec2::new(60.0, 10.0));
+    assert_eq!(placements[1].rect.size.x, 30.0);
+  }
+
+  #[test]
+  fn justifies_the_space_left_along_the_main_axis() {
+    let contents = [Vec2::ZERO, Vec2::new(10.0, 0.0), Vec2::new(10.0, 0.0)];
+
+    for (justify, expected) in [
+      (Justify::Start,        [0.0, 10.0]),
+      (Justify::Center,       [40.0, 50.0]),
+      (Justify::End,          [80.0, 90.0]),
+      (Justify::SpaceBetween, [0.0, 90.0])
+    ] {
+      let styles = [Style { justify, ..Style::row() }, Style::default(), Style::default()];
+      let placements = place(&styles, &[&[1, 2]], &contents, None, Vec2::new(100.0, 10.0));
+
+      assert_eq!([placements[1].rect.position.x, placements[2].rect.position.x], expected, "{justify:?}");
+    }
+  }
+
+  #[test]
+  fn aligns_children_across_the_main_axis() {
+    let aligned = |align| Style { align_self: Some(align), ..Style::default() };
+    let styles = [
+      Style::row(), aligned(Align::Start), aligned(Align::Center), aligned(Align::End), aligned(Align::Stretch)
+    ];
+    let mut contents = [Vec2::new(10.0, 10.0); 5];
+    contents[0] = Vec2::ZERO;
+
+    let placements = place(&styles, &[&[1, 2, 3, 4]], &contents, None, Vec2::new(100.0, 50.0));
+
+    assert_eq!(rects(&placements)[1..], [
+      (Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0)),
+      (Vec2::new(10.0, 20.0), Vec2::new(10.0, 10.0)),
+      (Vec2::new(20.0, 40.0), Vec2::new(10.0, 10.0)),
+      (Vec2::new(30.0, 0.0), Vec2::new(10.0, 50.0))
+    ]);
+  }
+
+  #[test]
+  fn sizes_percentages_by_the_parent() {
+    let styles = [
+      Style { padding: Edges::all(10.0), ..Style::column() },
+      Style { width: Dimension::Percent(25.0), height: Dimension::Pixels(5.0), ..Style::default() }
+    ];
+
+    let placements = place(&styles, &[&[1]], &[Vec2::ZERO; 2], None, Vec2::new(220.0, 100.0));
+
+    assert_eq!(placements[1].rect, Rect::new(Vec2::new(10.0, 10.0), Vec2::new(50.0, 5.0)));
+  }
+
+  #[test]
+  fn keeps_margins_around_children() {
+    let styles = [Style::column(), Style { margin: Edges::all(5.0), ..Style::default() }, Style::default()];
+    let contents = [Vec2::ZERO, Vec2::new(0.0, 10.0), Vec2::new(0.0, 10.0)];
+
+    let placements = place(&styles, &[&[1, 2]], &contents, None, Vec2::new(100.0, 100.0));
+
+    assert_eq!(rects(&placements)[1..], [
+      (Vec2::new(5.0, 5.0), Vec2::new(90.0, 10.0)),
+      (Vec2::new(0.0, 20.0), Vec2::new(100.0, 10.0))
+    ]);
+  }
+
+  #[test]
+  fn scrolls_children_at_their_natural_size_and_reports_how_far_they_reach() {
+    let styles = [Style::column(), Style::fill(), Style::fill(), Style::fill()];
+    let contents = [Vec2::ZERO, Vec2::new(0.0, 40.0), Vec2::new(0.0, 40.0), Vec2::new(0.0, 40.0)];
+
+    let placements = place(&styles, &[&[1, 2, 3]], &contents, Some(Vec2::new(0.0, 30.0)), Vec2::new(100.0, 50.0));
+
+    assert_eq!(rects(&placements)[1..], [
+      (Vec2::new(0.0, -30.0), Vec2::new(100.0, 40.0)),
+      (Vec2::new(0.0, 10.0), Vec2::new(100.0, 40.0)),
+      (Vec2::new(0.0, 50.0), Vec2::new(100.0, 40.0))
+    ]);
+    assert_eq!(placements[0].content, Vec2::new(100.0, 120.0));
+  }
+}
@@ -0,0 +1,297 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use std::ops::Range;
+
+// in pixels from the top left of the gui
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+  pub position: Vec2,
+  pub size: Vec2
+}
+
+impl Rect {
+  pub const EMPTY: Rect = Rect { position: Vec2::ZERO, size: Vec2::ZERO };
+
+  pub fn new(position: Vec2, size: Vec2) -> Self {
+    Rect { position, size }
+  }
+
+  pub fn from_corners(min: Vec2, max: Vec2) -> Self {
+    Rect { position: min, size: (max - min).max(Vec2::ZERO) }
+  }
+
+  pub fn max(&self) -> Vec2 {
+    self.position + self.size
+  }
+
+  pub fn center(&self) -> Vec2 {
+    self.position + self.size * 0.5
+  }
+
+  pub fn contains(&self, point: Vec2) -> bool {
+    point.cmpge(self.position).all() && point.cmplt(self.max()).all()
+  }
+
+  pub fn intersect(&self, other: Rect) -> Rect {
+    Rect::from_corners(self.position.max(other.position), self.max().min(other.max()))
+  }
+
+  pub fn union(&self, other: Rect) -> Rect {
+    Rect::from_corners(self.position.min(other.position), self.max().max(other.max()))
+  }
+
+  // grows the rectangle on every side, shrinking it for negative amounts
+  pub fn expand(&self, amount: f32) -> Rect {
+    Rect::from_corners(self.position - amount, self.max() + amount)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.size.x <= 0.0 || self.size.y <= 0.0
+  }
+}
+
+// srgb with straight alpha, the way colors are picked in image editors
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+  pub r: f32,
+  pub g: f32,
+  pub b: f32,
+  pub a: f32
+}
+
+impl Color {
+  pub const TRANSPARENT: Color = Color::rgba(0.0, 0.0, 0.0, 0.0);
+  pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+  pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+
+  pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+    Color { r, g, b, a: 1.0 }
+  }
+
+  pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+    Color { r, g, b, a }
+  }
+
+  // like 0x3478f6
+  pub const fn hex(rgb: u32) -> Self {
+    Color::rgb(((rgb >> 16) & 0xff) as f32 / 255.0, ((rgb >> 8) & 0xff) as f32 / 255.0, (rgb & 0xff) as f32 / 255.0)
+  }
+
+  pub fn with_alpha(self, a: f32) -> Self {
+    Color { a, ..self }
+  }
+
+  pub fn mix(self, other: Color, t: f32) -> Self {
+    Color {
+      r: self.r + (other.r - self.r) * t,
+      g: self.g + (other.g - self.g) * t,
+      b: self.b + (other.b - self.b) * t,
+      a: self.a + (other.a - self.a) * t
+    }
+  }
+
+  // panel targets are srgb, so colors are blended in linear space and encoded again when written
+  pub fn linear(&self) -> [f32; 4] {
+    let decode = |channel: f32| match channel <= 0.04045 {
+      true  => channel / 12.92,
+      false => ((channel + 0.055) / 1.055).powf(2.4)
+    };
+
+    [decode(self.r), decode(self.g), decode(self.b), self.a]
+  }
+}
+
+// one instance of the canvas pipeline, a rounded rectangle that can be rotated. lines and dots are rectangles with
+// fully rounded corners
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Quad {
+  pub center: [f32; 2],
+  pub half_size: [f32; 2],
+  // linear, straight alpha
+  pub color: [f32; 4],
+  pub border_color: [f32; 4],
+  // corner radius, border width, rotation in radians around the center, unused
+  pub shape: [f32; 4]
+}
+
+// bold and italic are drawn from the regular glyphs, thickened and slanted by the text renderer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FontStyle {
+  pub bold: bool,
+  pub italic: bool
+}
+
+impl FontStyle {
+  pub const REGULAR: FontStyle = FontStyle { bold: false, italic: false };
+}
+
+// laid out and drawn by the text renderer
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextRun {
+  // top left of the line
+  pub position: Vec2,
+  pub text: String,
+  // font size in pixels
+  pub size: f32,
+  pub color: Color,
+  pub style: FontStyle,
+  pub clip: Rect
+}
+
+// quads sharing a clip rectangle, followed by the text drawn over them
+#[derive(Clone, Debug, PartialEq)]
+pub struct Batch {
+  pub clip: Rect,
+  pub quads: Range<u32>,
+  pub texts: Range<u32>
+}
+
+// what a gui drew in a frame, in the order it is composited. the renderer draws each batch with one instanced call
+#[derive(Clone, Debug, Default)]
+pub struct Canvas {
+  pub size: Vec2,
+  // the target is cleared to this before drawing
+  pub clear: Color,
+  pub quads: Vec<Quad>,
+  pub texts: Vec<TextRun>,
+  pub batches: Vec<Batch>,
+  clips: Vec<Rect>
+}
+
+impl Canvas {
+  pub fn new(size: Vec2, clear: Color) -> Self {
+    Canvas { size, clear, ..Canvas::default() }
+  }
+
+  pub fn rect(&mut self, rect: Rect, color: Color, radius: f32) {
+    self.bordered_rect(rect, color, Color::TRANSPARENT, radius, 0.0);
+  }
+
+  // the border is drawn inside the rectangle
+  pub fn bordered_rect(&mut self, rect: Rect, fill: Color, border: Color, radius: f32, width: f32) {
+    self.push(Quad {
+      center: rect.center().to_array(),
+      half_size: (rect.size * 0.5).to_array(),
+      color: fill.linear(),
+      border_color: border.linear(),
+      shape: [radius, width, 0.0, 0.0]
+    }, rect);
+  }
+
+  pub fn outline(&mut self, rect: Rect, color: Color, radius: f32, width: f32) {
+    self.bordered_rect(rect, Color::TRANSPARENT, color, radius, width);
+  }
+
+  pub fn circle(&mut self, center: Vec2, radius: f32, color: Color) {
+    self.rect(Rect::new(center - radius, Vec2::splat(radius * 2.0)), color, radius);
+  }
+
+  // with round caps
+  pub fn line(&mut self, from: Vec2, to: Vec2, width: f32, color: Color) {
+    let direction = to - from;
+    let half_width = width * 0.5;
+    let half_length = direction.length() * 0.5 + half_width;
+    let bounds = Rect::from_corners(from.min(to) - half_width, from.max(to) + half_width);
+
+    self.push(Quad {
+      center: ((from + to) * 0.5).to_array(),
+      half_size: [half_length, half_width],
+      color: color.linear(),
+      border_color: Color::TRANSPARENT.linear(),
+      shape: [half_width, 0.0, direction.y.atan2(direction.x), 0.0]
+    }, bounds);
+  }
+
+  // a stroked polyline, joined by the round caps of its segments
+  pub fn path(&mut self, points: &[Vec2], width: f32, color: Color, closed: bool) {
+    for pair in points.windows(2) {
+      self.line(pair[0], pair[1], width, color);
+    }
+
+    if let (true, Some(first), Some(last)) = (closed && points.len() > 2, points.first(), points.last()) {
+      self.line(*last, *first, width, color);
+    }
+  }
+
+  pub fn text(&mut self, position: Vec2, text: &str, size: f32, color: Color) {
+    self.styled_text(position, text, size, color, FontStyle::REGULAR);
+  }
+
+  pub fn styled_text(&mut self, position: Vec2, text: &str, size: f32, color: Color, style: FontStyle) {
+    if text.is_empty() || color.a <= 0.0 { return; }
+
+    let clip = self.clip();
+    self.batch(clip).texts.end += 1;
+
+    self.texts.push(TextRun { position, text: text.to_string(), size, color, style, clip });
+  }
+
+  // later drawing is cut to the rectangle, within whatever clip was already active
+  pub fn push_clip(&mut self, rect: Rect) {
+    let clip = self.clip().intersect(rect);
+    self.clips.push(clip);
+  }
+
+  pub fn pop_clip(&mut self) {
+    self.clips.pop();
+  }
+
+  pub fn clip(&self) -> Rect {
+    self.clips.last().copied().unwrap_or(Rect::new(Vec2::ZERO, self.size))
+  }
+
+  // quads outside the clip are dropped here rather than left to the scissor
+  fn push(&mut self, quad: Quad, bounds: Rect) {
+    let clip = self.clip();
+    if clip.intersect(bounds.expand(1.0)).is_empty() || (quad.color[3] <= 0.0 && quad.border_color[3] <= 0.0) { return; }
+
+    // quads drawn after text need a batch of their own to end up over it
+    if self.batches.last().is_some_and(|batch| batch.clip == clip && !batch.texts.is_empty()) {
+      let (quads, texts) = (self.quads.len() as u32, self.texts.len() as u32);
+      self.batches.push(Batch { clip, quads: quads..quads, texts: texts..texts });
+    }
+
+    self.batch(clip).quads.end += 1;
+    self.quads.push(quad);
+  }
+
+  // the last batch when it has the same clip, otherwise a new empty one
+  fn batch(&mut self, clip: Rect) -> &mut Batch {
+    if self.batches.last().is_none_or(|batch| batch.clip != clip) {
+      let (quads, texts) = (self.quads.len() as u32, self.texts.len() as u32);
+      self.batches.push(Batch { clip, quads: quads..quads, texts: texts..texts });
+    }
+
+    let last = self.batches.len() - 1;
+    &mut self.batches[last]
+  }
+}
+
+// how big text comes out, for layout and for placing the caret. whatever draws the text runs measures them the same way
+pub trait TextMeasure {
+  // of a single line at a font size in pixels
+  fn measure(&self, text: &str, size: f32) -> Vec2;
+
+  // from the start of the line to the caret before the character at index
+  fn caret(&self, text: &str, size: f32, index: usize) -> f32 {
+    let end = text.char_indices().nth(index).map_or(text.len(), |(byte, _)| byte);
+    self.measure(&text[..end], size).x
+  }
+
+  // the caret position nearest to x
+  fn index_at(&self, text: &str, size: f32, x: f32) -> usize {
+    let distance = |index: usize| (self.caret(text, size, index) - x).abs();
+    (0..=text.chars().count()).min_by(|a, b| distance(*a).total_cmp(&distance(*b))).unwrap_or(0)
+  }
+}
+
+// every character half as wide as the font size, for when no fonts are loaded
+pub struct ApproximateText;
+
+impl TextMeasure for ApproximateText {
+  fn measure(&self, text: &str, size: f32) -> Vec2 {
+    Vec2::new(text.chars().count() as f32 * size * 0.5, size * 1.25)
+  }
+}
@@ -0,0 +1,115 @@
+use crate::gvp_engine::gui::Color;
+
+use serde::{Deserialize, Serialize};
+
+// colors and measurements every widget is drawn with. sizes are in pixels of the panel's target
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+  // what the gui's root is filled with
+  pub background: Color,
+  // controls like buttons and text fields
+  pub surface: Color,
+  pub surface_hovered: Color,
+  pub surface_pressed: Color,
+  // selected items, checked boxes, filled part of sliders
+  pub accent: Color,
+  // text drawn over the accent
+  pub on_accent: Color,
+  pub text: Color,
+  pub text_muted: Color,
+  pub border: Color,
+  pub focus: Color,
+  // dims everything behind an open modal
+  pub backdrop: Color,
+  // the card behind a modal's content
+  pub dialog: Color,
+  pub radius: f32,
+  pub border_width: f32,
+  pub focus_width: f32,
+  pub font_size: f32,
+  // between a control's edge and its content
+  pub padding: f32,
+  pub spacing: f32,
+  // height of buttons, text fields, sliders, checkboxes, list rows and tab bars
+  pub control_height: f32,
+  pub check_size: f32,
+  pub slider_track: f32,
+  pub slider_thumb: f32,
+  pub scrollbar_width: f32,
+  // pixels scrolled per wheel click
+  pub scroll_speed: f32
+}
+
+impl Default for Theme {
+  fn default() -> Self {
+    Theme::dark()
+  }
+}
+
+impl Theme {
+  pub fn dark() -> Self {
+    Theme {
+      background: Color::hex(0x1c1c20).with_alpha(0.85),
+      surface: Color::hex(0x2c2c32),
+      surface_hovered: Color::hex(0x38383f),
+      surface_pressed: Color::hex(0x24242a),
+      accent: Color::hex(0x3478f6),
+      on_accent: Color::WHITE,
+      text: Color::hex(0xf2f2f7),
+      text_muted: Color::hex(0x8e8e93),
+      border: Color::hex(0x48484f),
+      focus: Color::hex(0x64a0ff),
+      backdrop: Color::BLACK.with_alpha(0.5),
+      dialog: Color::hex(0x242428),
+      ..Theme::metrics()
+    }
+  }
+
+  pub fn light() -> Self {
+    Theme {
+      background: Color::hex(0xf2f2f7).with_alpha(0.9),
+      surface: Color::hex(0xffffff),
+      surface_hovered: Color::hex(0xe9e9ee),
+      surface_pressed: Color::hex(0xd8d8de),
+      accent: Color::hex(0x007aff),
+      on_accent: Color::WHITE,
+      text: Color::hex(0x1c1c1e),
+      text_muted: Color::hex(0x6c6c70),
+      border: Color::hex(0xc6c6cc),
+      focus: Color::hex(0x0a84ff),
+      backdrop: Color::BLACK.with_alpha(0.3),
+      dialog: Color::hex(0xfafafc),
+      ..Theme::metrics()
+    }
+  }
+
+  // shared by both themes, the colors are replaced
+  fn metrics() -> Self {
+    Theme {
+      background: Color::TRANSPARENT,
+      surface: Color::TRANSPARENT,
+      surface_hovered: Color::TRANSPARENT,
+      surface_pressed: Color::TRANSPARENT,
+      accent: Color::TRANSPARENT,
+      on_accent: Color::TRANSPARENT,
+      text: Color::TRANSPARENT,
+      text_muted: Color::TRANSPARENT,
+      border: Color::TRANSPARENT,
+      focus: Color::TRANSPARENT,
+      backdrop: Color::TRANSPARENT,
+      dialog: Color::TRANSPARENT,
+      radius: 8.0,
+      border_width: 1.0,
+      focus_width: 2.0,
+      font_size: 16.0,
+      padding: 12.0,
+      spacing: 8.0,
+      control_height: 36.0,
+      check_size: 20.0,
+      slider_track: 4.0,
+      slider_thumb: 9.0,
+      scrollbar_width: 4.0,
+      scroll_speed: 40.0
+    }
+  }
+}
@@ -0,0 +1,351 @@
+use crate::gvp_engine::gui::{Canvas, Color, Edges, Rect, TextMeasure, Theme};
+
+use glam::Vec2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Widget {
+  // groups its children, filled with the background when there is one
+  Container { background: Option<Color> },
+  Label { text: String },
+  Button { text: String },
+  // caret is a character index into the text
+  TextField { text: String, placeholder: String, caret: usize },
+  // moves in steps when step is above zero
+  Slider { value: f32, min: f32, max: f32, step: f32 },
+  Checkbox { text: String, checked: bool },
+  // one row per item, scroll is in pixels from the top
+  List { items: Vec<String>, selected: Option<usize>, scroll: f32 },
+  // its children can be larger than it is and are scrolled through
+  ScrollView { offset: Vec2 },
+  // one child per title, only the selected one is shown
+  Tabs { titles: Vec<String>, selected: usize },
+  // covers the whole gui while open with its children on a card, and takes all input from everything behind it.
+  // usually added with Style::centered
+  Modal { open: bool }
+}
+
+// how a widget is being interacted with, for drawing it
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WidgetState {
+  pub hovered: bool,
+  pub pressed: bool,
+  pub focused: bool,
+  pub pointer: Option<Vec2>
+}
+
+impl Widget {
+  pub fn container() -> Self {
+    Widget::Container { background: None }
+  }
+
+  pub fn label(text: &str) -> Self {
+    Widget::Label { text: text.to_string() }
+  }
+
+  pub fn button(text: &str) -> Self {
+    Widget::Button { text: text.to_string() }
+  }
+
+  pub fn text_field(placeholder: &str) -> Self {
+    Widget::TextField { text: String::new(), placeholder: placeholder.to_string(), caret: 0 }
+  }
+
+  pub fn slider(value: f32, min: f32, max: f32) -> Self {
+    Widget::Slider { value, min, max, step: 0.0 }
+  }
+
+  pub fn checkbox(text: &str, checked: bool) -> Self {
+    Widget::Checkbox { text: text.to_string(), checked }
+  }
+
+  pub fn list(items: Vec<String>) -> Self {
+    Widget::List { items, selected: None, scroll: 0.0 }
+  }
+
+  pub fn scroll_view() -> Self {
+    Widget::ScrollView { offset: Vec2::ZERO }
+  }
+
+  pub fn tabs(titles: &[&str]) -> Self {
+    Widget::Tabs { titles: titles.iter().map(|title| title.to_string()).collect(), selected: 0 }
+  }
+
+  pub fn modal() -> Self {
+    Widget::Modal { open: false }
+  }
+
+  // can take keyboard focus, and is what a click lands on when it hits something inside it
+  pub fn focusable(&self) -> bool {
+    matches!(
+      self,
+      Widget::Button { .. } | Widget::TextField { .. } | Widget::Slider { .. } | Widget::Checkbox { .. } | Widget::List { .. } | Widget::Tabs { .. }
+    )
+  }
+
+  // what the widget draws when it has no children
+  pub fn content_size(&self, theme: &Theme, measure: &dyn TextMeasure) -> Vec2 {
+    let text = |text: &str| measure.measure(text, theme.font_size);
+
+    match self {
+      Widget::Label { text: label } => text(label),
+      Widget::Button { text: label } => {
+        Vec2::new(text(label).x + theme.padding * 2.0, theme.control_height)
+      },
+      Widget::TextField { .. } | Widget::Slider { .. } => Vec2::new(theme.control_height * 5.0, theme.control_height),
+      Widget::Checkbox { text: label, .. } => {
+        Vec2::new(theme.check_size + theme.spacing + text(label).x, theme.control_height)
+      },
+      // lists ask for a few rows and scroll through the rest
+      Widget::List { items, .. } => {
+        let width = items.iter().map(|item| text(item).x).fold(0.0, f32::max);
+        Vec2::new(width + theme.padding * 2.0, items.len().min(3) as f32 * theme.control_height)
+      },
+      Widget::Container { .. } | Widget::ScrollView { .. } | Widget::Tabs { .. } | Widget::Modal { .. } => Vec2::ZERO
+    }
+  }
+
+  // room kept around the children
+  pub fn chrome(&self, theme: &Theme) -> Edges {
+    match self {
+      Widget::Tabs { .. } => Edges::top(theme.control_height),
+      _                   => Edges::ZERO
+    }
+  }
+
+  pub fn paint(&self, canvas: &mut Canvas, rect: Rect, state: WidgetState, theme: &Theme, measure: &dyn TextMeasure) {
+    let surface = match (state.pressed, state.hovered) {
+      (true, _)      => theme.surface_pressed,
+      (false, true)  => theme.surface_hovered,
+      (false, false) => theme.surface
+    };
+
+    match self {
+      Widget::Container { background } => {
+        if let Some(background) = background {
+          canvas.rect(rect, *background, theme.radius);
+        }
+      },
+      Widget::Label { text } => {
+        Widget::paint_text(canvas, rect, text, theme.text, 0.0, theme, measure);
+      },
+      Widget::Button { text } => {
+        canvas.bordered_rect(rect, surface, theme.border, theme.radius, theme.border_width);
+        Widget::paint_text(canvas, rect, text, theme.text, 0.5, theme, measure);
+      },
+      Widget::TextField { text, placeholder, caret } => {
+        let border = match state.focused {
+          true  => theme.accent,
+          false => theme.border
+        };
+
+        canvas.bordered_rect(rect, theme.surface, border, theme.radius, theme.border_width);
+
+        let inner = Edges::symmetric(theme.padding, 0.0).shrink(rect);
+        let shift = Widget::field_shift(text, *caret, inner, theme, measure);
+        let moved = Rect::new(inner.position - Vec2::new(shift, 0.0), inner.size);
+
+        canvas.push_clip(inner);
+
+        match text.is_empty() {
+          true  => Widget::paint_text(canvas, inner, placeholder, theme.text_muted, 0.0, theme, measure),
+          false => Widget::paint_text(canvas, moved, text, theme.text, 0.0, theme, measure)
+        }
+
+        if state.focused {
+          let x = moved.position.x + measure.caret(text, theme.font_size, *caret);
+          let half_height = theme.font_size * 0.6;
+          let y = rect.center().y;
+
+          canvas.line(Vec2::new(x, y - half_height), Vec2::new(x, y + half_height), 2.0, theme.text);
+        }
+
+        canvas.pop_clip();
+      },
+      Widget::Slider { value, min, max, .. } => {
+        let (start, end) = Widget::slider_track(rect, theme);
+        let fraction = ((value - min) / (max - min)).clamp(0.0, 1.0);
+        let thumb = start.lerp(end, if fraction.is_finite() { fraction } else { 0.0 });
+
+        canvas.line(start, end, theme.slider_track, theme.border);
+        canvas.line(start, thumb, theme.slider_track, theme.accent);
+
+        let thumb_color = match state.pressed || state.hovered {
+          true  => theme.text,
+          false => theme.text.mix(theme.surface, 0.1)
+        };
+
+        canvas.circle(thumb, theme.slider_thumb, thumb_color);
+      },
+      Widget::Checkbox { text, checked } => {
+        let check = Widget::check_rect(rect, theme);
+
+        match checked {
+          true  => canvas.rect(check, theme.accent, theme.radius * 0.5),
+          false => canvas.bordered_rect(check, surface, theme.border, theme.radius * 0.5, theme.border_width)
+        }
+
+        if *checked {
+          let mark = [Vec2::new(0.25, 0.52), Vec2::new(0.43, 0.7), Vec2::new(0.76, 0.32)].map(|point| check.position + point * check.size);
+          canvas.path(&mark, check.size.x * 0.12, theme.on_accent, false);
+        }
+
+        let label = Rect::from_corners(Vec2::new(check.max().x + theme.spacing, rect.position.y), rect.max());
+        Widget::paint_text(canvas, label, text, theme.text, 0.0, theme, measure);
+      },
+      Widget::List { items, selected, scroll } => {
+        canvas.push_clip(rect);
+
+        let hovered_row = state.pointer.filter(|_| state.hovered).and_then(|point| Widget::list_row(rect, *scroll, items.len(), point, theme));
+        let first = (scroll / theme.control_height).floor().max(0.0) as usize;
+        let visible = (rect.size.y / theme.control_height).ceil() as usize + 1;
+
+        for (index, item) in items.iter().enumerate().skip(first).take(visible) {
+          let row = Rect::new(
+            rect.position + Vec2::new(0.0, index as f32 * theme.control_height - scroll),
+            Vec2::new(rect.size.x, theme.control_height)
+          );
+
+          let color = match (*selected == Some(index), hovered_row == Some(index)) {
+            (true, _) => {
+              canvas.rect(row, theme.accent, theme.radius);
+              theme.on_accent
+            },
+            (false, true) => {
+              canvas.rect(row, theme.surface_hovered, theme.radius);
+              theme.text
+            },
+            (false, false) => theme.text
+          };
+
+          Widget::paint_text(canvas, Edges::symmetric(theme.padding, 0.0).shrink(row), item, color, 0.0, theme, measure);
+        }
+
+        canvas.pop_clip();
+
+        let content = Vec2::new(rect.size.x, items.len() as f32 * theme.control_height);
+        Widget::paint_scrollbars(canvas, rect, content, Vec2::new(0.0, *scroll), theme);
+      },
+      Widget::Tabs { titles, selected } => {
+        let bar = Rect::new(rect.position, Vec2::new(rect.size.x, theme.control_height));
+        let hovered_tab = state.pointer.filter(|_| state.hovered).and_then(|point| {
+          Widget::tab_rects(titles, rect, theme, measure).iter().position(|tab| tab.contains(point))
+        });
+
+        canvas.rect(
+          Rect::new(Vec2::new(bar.position.x, bar.max().y - theme.border_width), Vec2::new(bar.size.x, theme.border_width)),
+          theme.border,
+          0.0
+        );
+
+        for (index, (title, tab)) in titles.iter().zip(Widget::tab_rects(titles, rect, theme, measure)).enumerate() {
+          if hovered_tab == Some(index) && index != *selected {
+            canvas.rect(tab, theme.surface_hovered, theme.radius);
+          }
+
+          let color = match index == *selected {
+            true  => {
+              let underline = Rect::new(Vec2::new(tab.position.x, tab.max().y - 3.0), Vec2::new(tab.size.x, 3.0));
+              canvas.rect(underline, theme.accent, 1.5);
+              theme.text
+            },
+            false => theme.text_muted
+          };
+
+          Widget::paint_text(canvas, tab, title, color, 0.5, theme, measure);
+        }
+      },
+      Widget::Modal { .. } => canvas.rect(rect, theme.backdrop, 0.0),
+      Widget::ScrollView { .. } => ()
+    }
+  }
+
+  // vertically centered, align being 0 for the left of the rectangle, 1 for its right
+  pub fn paint_text(canvas: &mut Canvas, rect: Rect, text: &str, color: Color, align: f32, theme: &Theme, measure: &dyn TextMeasure) {
+    let size = measure.measure(text, theme.font_size);
+    let position = rect.position + (rect.size - size) * Vec2::new(align, 0.5);
+
+    canvas.text(position, text, theme.font_size, color);
+  }
+
+  // thin bars along the right and bottom edges when the content is larger than the view
+  pub fn paint_scrollbars(canvas: &mut Canvas, rect: Rect, content: Vec2, offset: Vec2, theme: &Theme) {
+    let color = theme.text_muted.with_alpha(0.6);
+    let width = theme.scrollbar_width;
+
+    if content.y > rect.size.y {
+      let length = rect.size.y * rect.size.y / content.y;
+      let start = offset.y / (content.y - rect.size.y) * (rect.size.y - length);
+      let bar = Rect::new(rect.position + Vec2::new(rect.size.x - width - 2.0, start), Vec2::new(width, length));
+
+      canvas.rect(bar, color, width * 0.5);
+    }
+
+    if content.x > rect.size.x {
+      let length = rect.size.x * rect.size.x / content.x;
+      let start = offset.x / (content.x - rect.size.x) * (rect.size.x - length);
+      let bar = Rect::new(rect.position + Vec2::new(start, rect.size.y - width - 2.0), Vec2::new(length, width));
+
+      canvas.rect(bar, color, width * 0.5);
+    }
+  }
+
+  // ends of the track the thumb's center moves along
+  pub fn slider_track(rect: Rect, theme: &Theme) -> (Vec2, Vec2) {
+    let y = rect.center().y;
+    (Vec2::new(rect.position.x + theme.slider_thumb, y), Vec2::new(rect.max().x - theme.slider_thumb, y))
+  }
+
+  // the value under x, snapped to the step
+  pub fn slider_value(rect: Rect, x: f32, (min, max, step): (f32, f32, f32), theme: &Theme) -> f32 {
+    let (start, end) = Widget::slider_track(rect, theme);
+    let fraction = ((x - start.x) / (end.x - start.x).max(f32::EPSILON)).clamp(0.0, 1.0);
+
+    Widget::snap(min + (max - min) * fraction, (min, max, step))
+  }
+
+  pub fn snap(value: f32, (min, max, step): (f32, f32, f32)) -> f32 {
+    let snapped = match step > 0.0 {
+      true  => min + ((value - min) / step).round() * step,
+      false => value
+    };
+
+    snapped.clamp(min.min(max), max.max(min))
+  }
+
+  pub fn check_rect(rect: Rect, theme: &Theme) -> Rect {
+    let position = Vec2::new(rect.position.x, rect.center().y - theme.check_size * 0.5);
+    Rect::new(position, Vec2::splat(theme.check_size))
+  }
+
+  pub fn list_row(rect: Rect, scroll: f32, count: usize, point: Vec2, theme: &Theme) -> Option<usize> {
+    if !rect.contains(point) { return None; }
+
+    let row = ((point.y - rect.position.y + scroll) / theme.control_height).floor();
+    (row >= 0.0 && (row as usize) < count).then_some(row as usize)
+  }
+
+  // the tabs in the bar across the top, each as wide as its title
+  pub fn tab_rects(titles: &[String], rect: Rect, theme: &Theme, measure: &dyn TextMeasure) -> Vec<Rect> {
+    let mut x = rect.position.x;
+
+    titles.iter().map(|title| {
+      let width = measure.measure(title, theme.font_size).x + theme.padding * 2.0;
+      let tab = Rect::new(Vec2::new(x, rect.position.y), Vec2::new(width, theme.control_height));
+      x += width;
+      tab
+    }).collect()
+  }
+
+  // how far a text field's text is moved left to keep the caret in view
+  pub fn field_shift(text: &str, caret: usize, inner: Rect, theme: &Theme, measure: &dyn TextMeasure) -> f32 {
+    (measure.caret(text, theme.font_size, caret) - inner.size.x + 2.0).max(0.0)
+  }
+
+  // the caret position in a text field under x
+  pub fn field_index(text: &str, caret: usize, rect: Rect, x: f32, theme: &Theme, measure: &dyn TextMeasure) -> usize {
+    let inner = Edges::symmetric(theme.padding, 0.0).shrink(rect);
+    let shift = Widget::field_shift(text, caret, inner, theme, measure);
+
+    measure.index_at(text, theme.font_size, x - inner.position.x + shift)
+  }
+}
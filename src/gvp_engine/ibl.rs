@@ -0,0 +1,552 @@
+use crate::gvp_engine::{
+  allocator::{as_bytes, Allocator, Image},
+  gpu::GPU,
+  shader,
+  texture::{SamplerInfo, Texture, TextureData}
+};
+
+use ash::vk;
+use glam::Vec3;
+
+use std::{mem::size_of, slice};
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct BakeConstants {
+  roughness: f32,
+  source_size: f32
+}
+
+// the maps baked from a single environment. the environment cubemap itself is kept for drawing the sky
+pub struct IblMaps {
+  pub environment: Texture,
+  pub irradiance: Image,
+  pub prefiltered: Image
+}
+
+// bakes environments into the maps the pbr shader lights with. the brdf lookup table does not depend on the
+// environment so it is generated once up front
+pub struct Ibl {
+  bake_set_layout: vk::DescriptorSetLayout,
+  bake_pipeline_layout: vk::PipelineLayout,
+  equirect_pipeline: vk::Pipeline,
+  irradiance_pipeline: vk::Pipeline,
+  prefilter_pipeline: vk::Pipeline,
+  pub brdf_lut: Image
+}
+
+impl IblMaps {
+  pub fn clean(&self, device: &ash::Device, allocator: &Allocator) {
+    self.environment.clean(device, allocator);
+    allocator.destroy_image(device, &self.irradiance);
+    allocator.destroy_image(device, &self.prefiltered);
+  }
+}
+
+impl Ibl {
+  pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+  const ENVIRONMENT_SIZE: u32 = 512;
+  const IRRADIANCE_SIZE: u32 = 32;
+  const PREFILTERED_SIZE: u32 = 128;
+  pub const PREFILTERED_MIPS: u32 = 5;
+  const BRDF_LUT_SIZE: u32 = 256;
+  const WORKGROUP_SIZE: u32 = 8;
+
+  pub fn new(device: &ash::Device, allocator: &Allocator) -> Self {
+    let bake_set_layout = Ibl::create_bake_set_layout(device);
+    let bake_pipeline_layout = Ibl::create_bake_pipeline_layout(device, &bake_set_layout);
+
+    let brdf_lut = Ibl::bake_brdf_lut(device, allocator, &bake_set_layout, &bake_pipeline_layout);
+
+    let equirect_pipeline = Ibl::create_pipeline(device, &bake_pipeline_layout, shader::EQUIRECT_TO_CUBE);
+    let irradiance_pipeline = Ibl::create_pipeline(device, &bake_pipeline_layout, shader::IRRADIANCE);
+    let prefilter_pipeline = Ibl::create_pipeline(device, &bake_pipeline_layout, shader::PREFILTER);
+
+    Ibl {
+      bake_set_layout,
+      bake_pipeline_layout,
+      equirect_pipeline,
+      irradiance_pipeline,
+      prefilter_pipeline,
+      brdf_lut
+    }
+  }
+
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    allocator.destroy_image(device, &self.brdf_lut);
+
+    unsafe {
+      device.destroy_pipeline(self.equirect_pipeline, None);
+      device.destroy_pipeline(self.irradiance_pipeline, None);
+      device.destroy_pipeline(self.prefilter_pipeline, None);
+      device.destroy_pipeline_layout(self.bake_pipeline_layout, None);
+      device.destroy_descriptor_set_layout(self.bake_set_layout, None);
+    };
+  }
+
+  // accepts either an equirectangular panorama or a cubemap. panoramas are projected onto a cubemap first
+  pub fn bake(
+    &self,
+    instance: &ash::Instance,
+    device: &ash::Device,
+    allocator: &Allocator,
+    gpu: &GPU,
+    data: &TextureData
+  ) -> IblMaps {
+    let panorama = match data.cube {
+      true  => None,
+      false => {
+        // wraps horizontally around the seam but not over the poles
+        let sampler = SamplerInfo {
+          address_mode: [vk::SamplerAddressMode::REPEAT, vk::SamplerAddressMode::CLAMP_TO_EDGE, vk::SamplerAddressMode::CLAMP_TO_EDGE],
+          ..SamplerInfo::CLAMP
+        };
+
+        Some(Texture::upload(instance, device, allocator, gpu, data, &sampler))
+      }
+    };
+
+    let environment = match &panorama {
+      Some(_) => {
+        let image = Ibl::create_cube(device, allocator, Ibl::ENVIRONMENT_SIZE, 32 - Ibl::ENVIRONMENT_SIZE.leading_zeros(), true);
+        let sampler = Texture::create_sampler(device, gpu, &SamplerInfo::CLAMP, image.mip_levels);
+
+        Texture { image, sampler }
+      },
+      None    => Texture::upload(instance, device, allocator, gpu, data, &SamplerInfo::CLAMP)
+    };
+
+    let irradiance = Ibl::create_cube(device, allocator, Ibl::IRRADIANCE_SIZE, 1, false);
+    let prefiltered = Ibl::create_cube(device, allocator, Ibl::PREFILTERED_SIZE, Ibl::PREFILTERED_MIPS, false);
+
+    // one storage view per level written, plus the panorama pass when there is one
+    let mut views = Vec::<vk::ImageView>::new();
+    let mut view = |image: &Image, mip: u32| {
+      let view = allocator.create_view(device, image, vk::ImageViewType::TYPE_2D_ARRAY, Allocator::color_range(mip, 1, 0, 6));
+      views.push(view);
+
+      view
+    };
+
+    let environment_view = view(&environment.image, 0);
+    let irradiance_view = view(&irradiance, 0);
+    let prefiltered_views: Vec<vk::ImageView> = (0..Ibl::PREFILTERED_MIPS).map(|mip| view(&prefiltered, mip)).collect();
+
+    let set_count = 2 + Ibl::PREFILTERED_MIPS;
+    let (pool, sets) = Ibl::allocate_bake_sets(device, &self.bake_set_layout, set_count);
+
+    let panorama_set = sets[0];
+    let irradiance_set = sets[1];
+    let prefilter_sets = &sets[2..];
+
+    if let Some(panorama) = &panorama {
+      Ibl::write_bake_set(device, panorama_set, Some((panorama.image.view, panorama.sampler)), environment_view);
+    }
+
+    let source = Some((environment.image.view, environment.sampler));
+    Ibl::write_bake_set(device, irradiance_set, source, irradiance_view);
+
+    for (set, view) in prefilter_sets.iter().zip(&prefiltered_views) {
+      Ibl::write_bake_set(device, *set, source, *view);
+    }
+
+    allocator.submit(device, |command_buffer| {
+      let to_general = |image: &Image| {
+        Allocator::transition_image(
+          device,
+          command_buffer,
+          image.image,
+          Allocator::color_range(0, image.mip_levels, 0, 6),
+          (vk::ImageLayout::UNDEFINED, vk::ImageLayout::GENERAL),
+          (vk::AccessFlags::empty(), vk::AccessFlags::SHADER_WRITE),
+          (vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::COMPUTE_SHADER)
+        );
+      };
+
+      if panorama.is_some() {
+        let image = &environment.image;
+
+        Allocator::transition_image(
+          device,
+          command_buffer,
+          image.image,
+          Allocator::color_range(0, 1, 0, 6),
+          (vk::ImageLayout::UNDEFINED, vk::ImageLayout::GENERAL),
+          (vk::AccessFlags::empty(), vk::AccessFlags::SHADER_WRITE),
+          (vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::COMPUTE_SHADER)
+        );
+
+        self.dispatch(device, command_buffer, self.equirect_pipeline, panorama_set, Ibl::ENVIRONMENT_SIZE, None);
+
+        // the rest of the chain is blitted down from the projected base level
+        Allocator::transition_image(
+          device,
+          command_buffer,
+          image.image,
+          Allocator::color_range(0, 1, 0, 6),
+          (vk::ImageLayout::GENERAL, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+          (vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::TRANSFER_WRITE),
+          (vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::TRANSFER)
+        );
+
+        Allocator::transition_image(
+          device,
+          command_buffer,
+          image.image,
+          Allocator::color_range(1, image.mip_levels - 1, 0, 6),
+          (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+          (vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE),
+          (vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::TRANSFER)
+        );
+
+        Texture::generate_mips(device, command_buffer, image);
+      }
+
+      // make the finished environment visible to the compute passes reading it below
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        environment.image.image,
+        Allocator::color_range(0, environment.image.mip_levels, 0, 6),
+        (vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+        (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ),
+        (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::COMPUTE_SHADER)
+      );
+
+      to_general(&irradiance);
+      to_general(&prefiltered);
+
+      self.dispatch(device, command_buffer, self.irradiance_pipeline, irradiance_set, Ibl::IRRADIANCE_SIZE, None);
+
+      for (mip, set) in prefilter_sets.iter().enumerate() {
+        let constants = BakeConstants {
+          roughness: mip as f32 / (Ibl::PREFILTERED_MIPS - 1) as f32,
+          source_size: environment.image.extent.width as f32
+        };
+
+        let size = (Ibl::PREFILTERED_SIZE >> mip).max(1);
+        self.dispatch(device, command_buffer, self.prefilter_pipeline, *set, size, Some(constants));
+      }
+
+      for image in [&irradiance, &prefiltered] {
+        Allocator::transition_image(
+          device,
+          command_buffer,
+          image.image,
+          Allocator::color_range(0, image.mip_levels, 0, 6),
+          (vk::ImageLayout::GENERAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+          (vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ),
+          (vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::FRAGMENT_SHADER)
+        );
+      }
+    });
+
+    unsafe {
+      for view in views {
+        device.destroy_image_view(view, None);
+      }
+
+      device.destroy_descriptor_pool(pool, None);
+    };
+
+    if let Some(panorama) = panorama {
+      panorama.clean(device, allocator);
+    }
+
+    IblMaps {
+      environment,
+      irradiance,
+      prefiltered
+    }
+  }
+
+  fn dispatch(
+    &self,
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    pipeline: vk::Pipeline,
+    set: vk::DescriptorSet,
+    size: u32,
+    constants: Option<BakeConstants>
+  ) {
+    let groups = size.div_ceil(Ibl::WORKGROUP_SIZE);
+
+    unsafe {
+      device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline);
+      device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::COMPUTE, self.bake_pipeline_layout, 0, &[set], &[]);
+
+      if let Some(constants) = constants {
+        device.cmd_push_constants(
+          command_buffer,
+          self.bake_pipeline_layout,
+          vk::ShaderStageFlags::COMPUTE,
+          0,
+          as_bytes(slice::from_ref(&constants))
+        );
+      }
+
+      device.cmd_dispatch(command_buffer, groups, groups, 6);
+    };
+  }
+
+  // a calm gradient sky used until an environment is loaded
+  pub fn default_sky() -> TextureData {
+    let (width, height) = (256, 128);
+
+    let zenith = Vec3::new(0.18, 0.36, 0.75);
+    let horizon = Vec3::new(0.75, 0.82, 0.9);
+    let ground = Vec3::new(0.25, 0.23, 0.2);
+
+    let mut pixels = Vec::<f32>::with_capacity(width * height * 4);
+
+    for y in 0..height {
+      // 1 straight up, 0 at the horizon and -1 straight down
+      let elevation = 1.0 - 2.0 * (y as f32 + 0.5) / height as f32;
+
+      let color = match elevation >= 0.0 {
+        true  => horizon.lerp(zenith, elevation.sqrt()),
+        false => horizon.lerp(ground, (-elevation).sqrt().min(1.0))
+      };
+
+      for _ in 0..width {
+        pixels.extend_from_slice(&[color.x, color.y, color.z, 1.0]);
+      }
+    }
+
+    TextureData::rgba32f(width as u32, height as u32, &pixels)
+  }
+
+  fn bake_brdf_lut(
+    device: &ash::Device,
+    allocator: &Allocator,
+    set_layout: &vk::DescriptorSetLayout,
+    pipeline_layout: &vk::PipelineLayout
+  ) -> Image {
+    let create_info = {
+      vk::ImageCreateInfo::default()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(Ibl::FORMAT)
+        .extent(vk::Extent3D { width: Ibl::BRDF_LUT_SIZE, height: Ibl::BRDF_LUT_SIZE, depth: 1 })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+    };
+
+    let image = allocator.create_image(device, &create_info, vk::ImageAspectFlags::COLOR, vk::ImageViewType::TYPE_2D);
+    let pipeline = Ibl::create_pipeline(device, pipeline_layout, shader::BRDF_LUT);
+    let (pool, sets) = Ibl::allocate_bake_sets(device, set_layout, 1);
+
+    Ibl::write_bake_set(device, sets[0], None, image.view);
+
+    allocator.submit(device, |command_buffer| {
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        image.image,
+        Allocator::color_range(0, 1, 0, 1),
+        (vk::ImageLayout::UNDEFINED, vk::ImageLayout::GENERAL),
+        (vk::AccessFlags::empty(), vk::AccessFlags::SHADER_WRITE),
+        (vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::COMPUTE_SHADER)
+      );
+
+      let groups = Ibl::BRDF_LUT_SIZE.div_ceil(Ibl::WORKGROUP_SIZE);
+
+      unsafe {
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline);
+        device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::COMPUTE, *pipeline_layout, 0, &sets, &[]);
+        device.cmd_dispatch(command_buffer, groups, groups, 1);
+      };
+
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        image.image,
+        Allocator::color_range(0, 1, 0, 1),
+        (vk::ImageLayout::GENERAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+        (vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ),
+        (vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::FRAGMENT_SHADER)
+      );
+    });
+
+    unsafe {
+      device.destroy_descriptor_pool(pool, None);
+      device.destroy_pipeline(pipeline, None);
+    };
+
+    image
+  }
+
+  // mips is the number of levels. environments also get blitted into which needs the transfer usages
+  fn create_cube(device: &ash::Device, allocator: &Allocator, size: u32, mips: u32, blit: bool) -> Image {
+    let mut usage = vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED;
+    if blit { usage |= vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST; }
+
+    let create_info = {
+      vk::ImageCreateInfo::default()
+        .flags(vk::ImageCreateFlags::CUBE_COMPATIBLE)
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(Ibl::FORMAT)
+        .extent(vk::Extent3D { width: size, height: size, depth: 1 })
+        .mip_levels(mips)
+        .array_layers(6)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(usage)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+    };
+
+    allocator.create_image(device, &create_info, vk::ImageAspectFlags::COLOR, vk::ImageViewType::CUBE)
+  }
+
+  fn allocate_bake_sets(
+    device: &ash::Device,
+    layout: &vk::DescriptorSetLayout,
+    count: u32
+  ) -> (vk::DescriptorPool, Vec<vk::DescriptorSet>) {
+    let pool_sizes = [
+      vk::DescriptorPoolSize::default().ty(vk::DescriptorType::SAMPLED_IMAGE).descriptor_count(count),
+      vk::DescriptorPoolSize::default().ty(vk::DescriptorType::SAMPLER).descriptor_count(count),
+      vk::DescriptorPoolSize::default().ty(vk::DescriptorType::STORAGE_IMAGE).descriptor_count(count)
+    ];
+
+    let create_info = {
+      vk::DescriptorPoolCreateInfo::default()
+        .max_sets(count)
+        .pool_sizes(&pool_sizes)
+    };
+
+    let pool = match unsafe { device.create_descriptor_pool(&create_info, None) } {
+      Ok(pool)   => pool,
+      Err(error) => panic!("failed to create ibl bake descriptor pool with error: {error}")
+    };
+
+    let layouts = vec![*layout; count as usize];
+    let allocate_info = {
+      vk::DescriptorSetAllocateInfo::default()
+        .descriptor_pool(pool)
+        .set_layouts(&layouts)
+    };
+
+    match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+      Ok(sets)   => (pool, sets),
+      Err(error) => panic!("failed to allocate ibl bake descriptor sets with error: {error}")
+    }
+  }
+
+  // source is left out for the brdf lookup table which reads nothing
+  fn write_bake_set(
+    device: &ash::Device,
+    set: vk::DescriptorSet,
+    source: Option<(vk::ImageView, vk::Sampler)>,
+    output: vk::ImageView
+  ) {
+    let output_info = [vk::DescriptorImageInfo::default().image_view(output).image_layout(vk::ImageLayout::GENERAL)];
+
+    let mut writes = vec![
+      vk::WriteDescriptorSet::default()
+        .dst_set(set)
+        .dst_binding(2)
+        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+        .image_info(&output_info)
+    ];
+
+    let source_info = source.map(|(view, _)| {
+      [vk::DescriptorImageInfo::default().image_view(view).image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)]
+    });
+
+    let sampler_info = source.map(|(_, sampler)| [vk::DescriptorImageInfo::default().sampler(sampler)]);
+
+    if let (Some(source_info), Some(sampler_info)) = (&source_info, &sampler_info) {
+      writes.push(
+        vk::WriteDescriptorSet::default()
+          .dst_set(set)
+          .dst_binding(0)
+          .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+          .image_info(source_info)
+      );
+
+      writes.push(
+        vk::WriteDescriptorSet::default()
+          .dst_set(set)
+          .dst_binding(1)
+          .descriptor_type(vk::DescriptorType::SAMPLER)
+          .image_info(sampler_info)
+      );
+    }
+
+    unsafe { device.update_descriptor_sets(&writes, &[]) };
+  }
+
+  fn create_bake_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+    let bindings = [
+      (0, vk::DescriptorType::SAMPLED_IMAGE),
+      (1, vk::DescriptorType::SAMPLER),
+      (2, vk::DescriptorType::STORAGE_IMAGE)
+    ].map(|(binding, descriptor_type)| {
+      vk::DescriptorSetLayoutBinding::default()
+        .binding(binding)
+        .descriptor_type(descriptor_type)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::COMPUTE)
+    });
+
+    let create_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+    match unsafe { device.create_descriptor_set_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create ibl bake descriptor set layout with error: {error}")
+    }
+  }
+
+  fn create_bake_pipeline_layout(device: &ash::Device, set_layout: &vk::DescriptorSetLayout) -> vk::PipelineLayout {
+    let set_layouts = [*set_layout];
+
+    let push_constant_ranges = [
+      vk::PushConstantRange::default()
+        .stage_flags(vk::ShaderStageFlags::COMPUTE)
+        .size(size_of::<BakeConstants>() as u32)
+    ];
+
+    let create_info = {
+      vk::PipelineLayoutCreateInfo::default()
+        .set_layouts(&set_layouts)
+        .push_constant_ranges(&push_constant_ranges)
+    };
+
+    match unsafe { device.create_pipeline_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create ibl bake pipeline layout with error: {error}")
+    }
+  }
+
+  fn create_pipeline(device: &ash::Device, layout: &vk::PipelineLayout, spirv: &[u8]) -> vk::Pipeline {
+    let module = shader::create_shader_module(device, spirv);
+
+    let stage = {
+      vk::PipelineShaderStageCreateInfo::default()
+        .stage(vk::ShaderStageFlags::COMPUTE)
+        .module(module)
+        .name(c"compute_main")
+    };
+
+    let create_info = {
+      vk::ComputePipelineCreateInfo::default()
+        .stage(stage)
+        .layout(*layout)
+    };
+
+    let pipeline = match unsafe { device.create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None) } {
+      Ok(pipelines)   => pipelines[0],
+      Err((_, error)) => panic!("failed to create ibl bake pipeline with error: {error}")
+    };
+
+    unsafe { device.destroy_shader_module(module, None) };
+
+    pipeline
+  }
+}
@@ -0,0 +1,367 @@
+mod bindings;
+mod desktop;
+mod hands;
+
+pub use bindings::*;
+pub use desktop::*;
+pub use hands::*;
+
+use crate::gvp_engine::{camera::Camera, gesture::Gesture};
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use sdl2::keyboard::Keycode;
+
+// a group of actions that are switched on and off together, like the controls of one app
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ActionSet(usize);
+
+// something the user can do, like select or grab, independent of the device doing it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Action(usize);
+
+impl ActionSet {
+  pub fn index(&self) -> usize {
+    self.0
+  }
+}
+
+impl Action {
+  pub fn index(&self) -> usize {
+    self.0
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionKind {
+  Button,
+  Axis2d,
+  Pose
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ActionValue {
+  Button(bool),
+  Axis2d(Vec2),
+  // world space, looking down -z. None while nothing bound to it is tracked
+  Pose(Option<Mat4>)
+}
+
+impl ActionValue {
+  fn rest(kind: ActionKind) -> Self {
+    match kind {
+      ActionKind::Button => ActionValue::Button(false),
+      ActionKind::Axis2d => ActionValue::Axis2d(Vec2::ZERO),
+      ActionKind::Pose   => ActionValue::Pose(None)
+    }
+  }
+
+  // how far from rest the value is, used to pick between sources
+  fn magnitude(&self) -> f32 {
+    match self {
+      ActionValue::Button(held)  => *held as u8 as f32,
+      ActionValue::Axis2d(value) => value.length(),
+      ActionValue::Pose(pose)    => pose.is_some() as u8 as f32
+    }
+  }
+
+  // reads a source as another kind of action, like a pushed thumbstick as a button
+  fn convert(self, kind: ActionKind) -> Option<Self> {
+    const PRESS_THRESHOLD: f32 = 0.5;
+
+    match (self, kind) {
+      (ActionValue::Button(held), ActionKind::Button)  => Some(ActionValue::Button(held)),
+      (ActionValue::Axis2d(value), ActionKind::Button) => Some(ActionValue::Button(value.length() > PRESS_THRESHOLD)),
+      (ActionValue::Axis2d(value), ActionKind::Axis2d) => Some(ActionValue::Axis2d(value)),
+      (ActionValue::Pose(pose), ActionKind::Pose)      => Some(ActionValue::Pose(pose)),
+      _                                                => None
+    }
+  }
+}
+
+pub struct ActionSetInfo {
+  // lowercase letters, digits, dashes, underscores and periods, as openxr requires
+  pub name: String,
+  pub localized_name: String,
+  pub active: bool
+}
+
+pub struct ActionInfo {
+  pub name: String,
+  pub localized_name: String,
+  pub set: ActionSet,
+  pub kind: ActionKind,
+  pub bindings: Vec<Binding>
+}
+
+// maps the keyboard and mouse, xr controllers and tracked hands onto the same actions so apps never deal with
+// devices. values are resolved once per frame when the engine polls events
+pub struct Input {
+  sets: Vec<ActionSetInfo>,
+  actions: Vec<ActionInfo>,
+  values: Vec<ActionValue>,
+  previous: Vec<ActionValue>,
+  // what the xr runtime last reported for each action, which it already combined over the action's xr bindings
+  xr_values: Vec<Option<ActionValue>>,
+  desktop: DesktopInput,
+  hands: [Option<HandPose>; 2],
+  // where the headset is, when one is tracked, and the head used this frame which falls back to the camera
+  xr_head: Option<Mat4>,
+  head: Mat4,
+  // recognized during the last frame
+  gestures: Vec<Gesture>,
+  cursor_ray: Option<Mat4>
+}
+
+impl Default for Input {
+  fn default() -> Self {
+    Input::new()
+  }
+}
+
+impl Input {
+  pub const ENGINE: ActionSet = ActionSet(0);
+
+  pub const SELECT: Action = Action(0);
+  pub const GRAB: Action = Action(1);
+  pub const OPEN_MENU: Action = Action(2);
+  pub const BACK: Action = Action(3);
+  pub const MOVE: Action = Action(4);
+  pub const POINTER: Action = Action(5);
+  pub const SCROLL: Action = Action(6);
+
+  pub fn new() -> Self {
+    let mut input = Input {
+      sets: Vec::new(),
+      actions: Vec::new(),
+      values: Vec::new(),
+      previous: Vec::new(),
+      xr_values: Vec::new(),
+      desktop: DesktopInput::default(),
+      hands: [None, None],
+      xr_head: None,
+      head: Mat4::IDENTITY,
+      gestures: Vec::new(),
+      cursor_ray: None
+    };
+
+    // created in the order of the constants above
+    let set = input.create_set("engine", "Engine");
+
+    for (name, localized_name, kind) in [
+      ("select", "Select", ActionKind::Button),
+      ("grab", "Grab", ActionKind::Button),
+      ("open_menu", "Open Menu", ActionKind::Button),
+      ("back", "Back", ActionKind::Button),
+      ("move", "Move", ActionKind::Axis2d),
+      ("pointer", "Pointer", ActionKind::Pose),
+      ("scroll", "Scroll", ActionKind::Axis2d)
+    ] {
+      let action = input.create_action(set, name, localized_name, kind);
+
+      for binding in Binding::defaults(action) {
+        input.bind(action, binding);
+      }
+    }
+
+    input
+  }
+
+  // sets start active. with xr, sets and actions have to exist before the first frame since the runtime only takes
+  // them once per session. ones made later still work with every other binding
+  pub fn create_set(&mut self, name: &str, localized_name: &str) -> ActionSet {
+    self.sets.push(ActionSetInfo {
+      name: name.to_string(),
+      localized_name: localized_name.to_string(),
+      active: true
+    });
+
+    ActionSet(self.sets.len() - 1)
+  }
+
+  pub fn create_action(&mut self, set: ActionSet, name: &str, localized_name: &str, kind: ActionKind) -> Action {
+    self.actions.push(ActionInfo {
+      name: name.to_string(),
+      localized_name: localized_name.to_string(),
+      set,
+      kind,
+      bindings: Vec::new()
+    });
+
+    self.values.push(ActionValue::rest(kind));
+    self.previous.push(ActionValue::rest(kind));
+    self.xr_values.push(None);
+
+    Action(self.actions.len() - 1)
+  }
+
+  pub fn bind(&mut self, action: Action, binding: Binding) {
+    self.actions[action.0].bindings.push(binding);
+  }
+
+  pub fn clear_bindings(&mut self, action: Action) {
+    self.actions[action.0].bindings.clear();
+  }
+
+  // actions of inactive sets stay at rest
+  pub fn set_active(&mut self, set: ActionSet, active: bool) {
+    self.sets[set.0].active = active;
+  }
+
+  pub fn sets(&self) -> impl Iterator<Item = (ActionSet, &ActionSetInfo)> {
+    self.sets.iter().enumerate().map(|(index, set)| (ActionSet(index), set))
+  }
+
+  pub fn actions(&self) -> impl Iterator<Item = (Action, &ActionInfo)> {
+    self.actions.iter().enumerate().map(|(index, action)| (Action(index), action))
+  }
+
+  pub fn action(&self, action: Action) -> &ActionInfo {
+    &self.actions[action.0]
+  }
+
+  pub fn value(&self, action: Action) -> ActionValue {
+    self.values[action.0]
+  }
+
+  pub fn held(&self, action: Action) -> bool {
+    self.values[action.0] == ActionValue::Button(true)
+  }
+
+  // went down this frame
+  pub fn pressed(&self, action: Action) -> bool {
+    self.held(action) && self.previous[action.0] == ActionValue::Button(false)
+  }
+
+  // went up this frame
+  pub fn released(&self, action: Action) -> bool {
+    !self.held(action) && self.previous[action.0] == ActionValue::Button(true)
+  }
+
+  pub fn axis_2d(&self, action: Action) -> Vec2 {
+    match self.values[action.0] {
+      ActionValue::Axis2d(value) => value,
+      _                          => Vec2::ZERO
+    }
+  }
+
+  pub fn pose(&self, action: Action) -> Option<Mat4> {
+    match self.values[action.0] {
+      ActionValue::Pose(pose) => pose,
+      _                       => None
+    }
+  }
+
+  pub fn hand(&self, hand: Hand) -> Option<&HandPose> {
+    self.hands[hand.index()].as_ref()
+  }
+
+  // world transform of the user's head, looking down -z
+  pub fn head(&self) -> Mat4 {
+    self.head
+  }
+
+  pub fn desktop(&self) -> &DesktopInput {
+    &self.desktop
+  }
+
+  pub fn desktop_mut(&mut self) -> &mut DesktopInput {
+    &mut self.desktop
+  }
+
+  pub fn set_xr_value(&mut self, action: Action, value: Option<ActionValue>) {
+    self.xr_values[action.0] = value;
+  }
+
+  pub fn set_hand(&mut self, hand: Hand, pose: Option<HandPose>) {
+    self.hands[hand.index()] = pose;
+  }
+
+  pub fn set_xr_head(&mut self, head: Option<Mat4>) {
+    self.xr_head = head;
+  }
+
+  // gestures are recognized from the resolved actions, so they reach gesture bindings on the next resolve
+  pub fn set_gestures(&mut self, gestures: impl IntoIterator<Item = Gesture>) {
+    self.gestures.clear();
+    self.gestures.extend(gestures);
+  }
+
+  // called before the devices report anything for the new frame
+  pub fn begin_frame(&mut self) {
+    self.desktop.begin_frame();
+    self.previous.clone_from(&self.values);
+  }
+
+  // combines every source of every active action into its value for this frame. the camera is needed to turn the
+  // cursor into a ray
+  pub fn resolve(&mut self, camera: &Camera) {
+    self.cursor_ray = self.cursor_ray(camera);
+    self.head = self.xr_head.unwrap_or(camera.transform.matrix());
+
+    for index in 0..self.actions.len() {
+      let action = &self.actions[index];
+      let rest = ActionValue::rest(action.kind);
+
+      if !self.sets[action.set.0].active {
+        self.values[index] = rest;
+        continue;
+      }
+
+      let sources = action.bindings.iter()
+        .filter(|binding| !matches!(binding, Binding::Xr { .. }))
+        .filter_map(|binding| self.read(binding))
+        .chain(self.xr_values[index])
+        .filter_map(|value| value.convert(action.kind));
+
+      // the strongest source wins, so a half pulled trigger does not hide a held mouse button
+      self.values[index] = sources.fold(rest, |best, value| match value.magnitude() > best.magnitude() {
+        true  => value,
+        false => best
+      });
+    }
+  }
+
+  fn read(&self, binding: &Binding) -> Option<ActionValue> {
+    let desktop = &self.desktop;
+
+    match binding {
+      Binding::Key(key)            => Some(ActionValue::Button(desktop.key_held(*key))),
+      Binding::MouseButton(button) => Some(ActionValue::Button(desktop.button_held(*button))),
+      Binding::MouseWheel          => Some(ActionValue::Axis2d(desktop.wheel)),
+      Binding::Cursor              => Some(ActionValue::Pose(self.cursor_ray)),
+      Binding::Xr { .. }           => None,
+      Binding::HandPinch(hand)     => self.hand(*hand).map(|pose| ActionValue::Button(pose.pinching())),
+      Binding::HandGrab(hand)      => self.hand(*hand).map(|pose| ActionValue::Button(pose.grabbing())),
+      Binding::HandAim(hand)       => self.hand(*hand).map(|pose| ActionValue::Pose(Some(pose.aim()))),
+      Binding::Gesture(gesture)    => Some(ActionValue::Button(self.gestures.contains(gesture))),
+      Binding::KeyAxis { up, down, left, right } => {
+        let axis = |positive: Keycode, negative: Keycode| {
+          desktop.key_held(positive) as u8 as f32 - desktop.key_held(negative) as u8 as f32
+        };
+
+        Some(ActionValue::Axis2d(Vec2::new(axis(*right, *left), axis(*up, *down)).normalize_or_zero()))
+      }
+    }
+  }
+
+  // from the camera through the cursor, looking down -z like the other poses
+  fn cursor_ray(&self, camera: &Camera) -> Option<Mat4> {
+    let size = self.desktop.window_size;
+    if size.x <= 0.0 || size.y <= 0.0 { return None; }
+
+    // the projection already flips y so window coordinates map onto clip space directly
+    let clip = self.desktop.cursor / size * 2.0 - 1.0;
+    let inverse = (camera.projection(size.x / size.y) * camera.view()).inverse();
+
+    let near = inverse * Vec4::new(clip.x, clip.y, 0.0, 1.0);
+    let far = inverse * Vec4::new(clip.x, clip.y, 1.0, 1.0);
+    let direction = (far.truncate() / far.w - near.truncate() / near.w).normalize();
+
+    let up = match direction.abs().dot(Vec3::Y) > 0.999 {
+      true  => Vec3::Z,
+      false => Vec3::Y
+    };
+
+    Some(Mat4::look_to_rh(camera.position(), direction, up).inverse())
+  }
+}
@@ -0,0 +1,112 @@
+use crate::gvp_engine::{gesture::Gesture, input::{Action, Hand, Input}};
+
+use sdl2::{keyboard::Keycode, mouse::MouseButton};
+
+// a device input an action reads from. an action can have any number of them and takes the strongest
+#[derive(Clone, Debug, PartialEq)]
+pub enum Binding {
+  Key(Keycode),
+  MouseButton(MouseButton),
+  // wheel clicks since the last frame
+  MouseWheel,
+  // four keys making up a 2d axis, like wasd
+  KeyAxis { up: Keycode, down: Keycode, left: Keycode, right: Keycode },
+  // a ray from the camera through the cursor
+  Cursor,
+  // an input path of an openxr interaction profile. bindings are suggested for every profile and the runtime uses
+  // those of the profile closest to the connected controllers
+  Xr { profile: String, path: String },
+  HandPinch(Hand),
+  HandGrab(Hand),
+  HandAim(Hand),
+  // held for the frame after the gesture is recognized
+  Gesture(Gesture)
+}
+
+impl Binding {
+  pub const SIMPLE_CONTROLLER: &'static str = "/interaction_profiles/khr/simple_controller";
+  pub const TOUCH_CONTROLLER: &'static str = "/interaction_profiles/oculus/touch_controller";
+  pub const INDEX_CONTROLLER: &'static str = "/interaction_profiles/valve/index_controller";
+  pub const VIVE_CONTROLLER: &'static str = "/interaction_profiles/htc/vive_controller";
+
+  pub fn xr(profile: &str, path: &str) -> Self {
+    Binding::Xr { profile: profile.to_string(), path: path.to_string() }
+  }
+
+  // what the engine actions are bound to out of the box
+  pub fn defaults(action: Action) -> Vec<Binding> {
+    let (mut bindings, xr): (Vec<Binding>, &[(&str, &str)]) = match action {
+      Input::SELECT => (
+        vec![Binding::MouseButton(MouseButton::Left), Binding::HandPinch(Hand::Left), Binding::HandPinch(Hand::Right)],
+        &[
+          (Binding::SIMPLE_CONTROLLER, "/user/hand/left/input/select/click"),
+          (Binding::SIMPLE_CONTROLLER, "/user/hand/right/input/select/click"),
+          (Binding::TOUCH_CONTROLLER, "/user/hand/left/input/trigger/value"),
+          (Binding::TOUCH_CONTROLLER, "/user/hand/right/input/trigger/value"),
+          (Binding::INDEX_CONTROLLER, "/user/hand/left/input/trigger/click"),
+          (Binding::INDEX_CONTROLLER, "/user/hand/right/input/trigger/click"),
+          (Binding::VIVE_CONTROLLER, "/user/hand/left/input/trigger/click"),
+          (Binding::VIVE_CONTROLLER, "/user/hand/right/input/trigger/click")
+        ]
+      ),
+      Input::GRAB => (
+        vec![Binding::MouseButton(MouseButton::Right), Binding::HandGrab(Hand::Left), Binding::HandGrab(Hand::Right)],
+        &[
+          (Binding::TOUCH_CONTROLLER, "/user/hand/left/input/squeeze/value"),
+          (Binding::TOUCH_CONTROLLER, "/user/hand/right/input/squeeze/value"),
+          (Binding::INDEX_CONTROLLER, "/user/hand/left/input/squeeze/value"),
+          (Binding::INDEX_CONTROLLER, "/user/hand/right/input/squeeze/value"),
+          (Binding::VIVE_CONTROLLER, "/user/hand/left/input/squeeze/click"),
+          (Binding::VIVE_CONTROLLER, "/user/hand/right/input/squeeze/click")
+        ]
+      ),
+      Input::OPEN_MENU => (
+        vec![Binding::Key(Keycode::Tab), Binding::Gesture(Gesture::MENU_SWIPE)],
+        &[
+          (Binding::SIMPLE_CONTROLLER, "/user/hand/left/input/menu/click"),
+          (Binding::TOUCH_CONTROLLER, "/user/hand/left/input/menu/click"),
+          (Binding::INDEX_CONTROLLER, "/user/hand/left/input/b/click"),
+          (Binding::VIVE_CONTROLLER, "/user/hand/left/input/menu/click")
+        ]
+      ),
+      Input::BACK => (
+        vec![Binding::Key(Keycode::Backspace)],
+        &[
+          (Binding::SIMPLE_CONTROLLER, "/user/hand/right/input/menu/click"),
+          (Binding::TOUCH_CONTROLLER, "/user/hand/right/input/b/click"),
+          (Binding::INDEX_CONTROLLER, "/user/hand/right/input/b/click"),
+          (Binding::VIVE_CONTROLLER, "/user/hand/right/input/menu/click")
+        ]
+      ),
+      Input::MOVE => (
+        vec![Binding::KeyAxis { up: Keycode::W, down: Keycode::S, left: Keycode::A, right: Keycode::D }],
+        &[
+          (Binding::TOUCH_CONTROLLER, "/user/hand/left/input/thumbstick"),
+          (Binding::INDEX_CONTROLLER, "/user/hand/left/input/thumbstick"),
+          (Binding::VIVE_CONTROLLER, "/user/hand/left/input/trackpad")
+        ]
+      ),
+      Input::POINTER => (
+        vec![Binding::Cursor, Binding::HandAim(Hand::Right)],
+        &[
+          (Binding::SIMPLE_CONTROLLER, "/user/hand/right/input/aim/pose"),
+          (Binding::TOUCH_CONTROLLER, "/user/hand/right/input/aim/pose"),
+          (Binding::INDEX_CONTROLLER, "/user/hand/right/input/aim/pose"),
+          (Binding::VIVE_CONTROLLER, "/user/hand/right/input/aim/pose")
+        ]
+      ),
+      Input::SCROLL => (
+        vec![Binding::MouseWheel],
+        &[
+          (Binding::TOUCH_CONTROLLER, "/user/hand/right/input/thumbstick"),
+          (Binding::INDEX_CONTROLLER, "/user/hand/right/input/thumbstick"),
+          (Binding::VIVE_CONTROLLER, "/user/hand/right/input/trackpad")
+        ]
+      ),
+      _ => (Vec::new(), &[])
+    };
+
+    bindings.extend(xr.iter().map(|(profile, path)| Binding::xr(profile, path)));
+    bindings
+  }
+}
@@ -0,0 +1,73 @@
+use glam::Vec2;
+use sdl2::{keyboard::Keycode, mouse::MouseButton};
+
+use std::collections::HashSet;
+
+// keyboard and mouse state, kept up to date by the window as it pumps events
+#[derive(Default)]
+pub struct DesktopInput {
+  keys: HashSet<Keycode>,
+  buttons: HashSet<MouseButton>,
+  // went down since the last frame, with the repeats of held keys, and the text typed
+  presses: Vec<Keycode>,
+  typed: String,
+  // in pixels from the top left of the window
+  pub cursor: Vec2,
+  pub window_size: Vec2,
+  // wheel clicks scrolled since the last frame
+  pub wheel: Vec2
+}
+
+impl DesktopInput {
+  pub fn key_held(&self, key: Keycode) -> bool {
+    self.keys.contains(&key)
+  }
+
+  pub fn button_held(&self, button: MouseButton) -> bool {
+    self.buttons.contains(&button)
+  }
+
+  pub fn key_presses(&self) -> &[Keycode] {
+    &self.presses
+  }
+
+  pub fn typed(&self) -> &str {
+    &self.typed
+  }
+
+  pub fn press_key(&mut self, key: Keycode) {
+    self.keys.insert(key);
+    self.presses.push(key);
+  }
+
+  pub fn release_key(&mut self, key: Keycode) {
+    self.keys.remove(&key);
+  }
+
+  pub fn press_button(&mut self, button: MouseButton) {
+    self.buttons.insert(button);
+  }
+
+  pub fn release_button(&mut self, button: MouseButton) {
+    self.buttons.remove(&button);
+  }
+
+  pub fn move_cursor(&mut self, position: Vec2) {
+    self.cursor = position;
+  }
+
+  pub fn scroll(&mut self, wheel: Vec2) {
+    self.wheel += wheel;
+  }
+
+  pub fn type_text(&mut self, text: &str) {
+    self.typed.push_str(text);
+  }
+
+  // clears what only lasts a frame. held keys and buttons carry over until released
+  pub fn begin_frame(&mut self) {
+    self.wheel = Vec2::ZERO;
+    self.presses.clear();
+    self.typed.clear();
+  }
+}
@@ -0,0 +1,90 @@
+use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hand {
+  Left,
+  Right
+}
+
+impl Hand {
+  pub const BOTH: [Hand; 2] = [Hand::Left, Hand::Right];
+
+  pub fn index(&self) -> usize {
+    match self {
+      Hand::Left  => 0,
+      Hand::Right => 1
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HandJoint {
+  pub position: Vec3,
+  pub orientation: Quat,
+  pub radius: f32
+}
+
+// every joint of a tracked hand in world space, in the order XR_EXT_hand_tracking lists them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HandPose {
+  pub joints: [HandJoint; HandPose::JOINT_COUNT]
+}
+
+impl HandPose {
+  pub const JOINT_COUNT: usize = 26;
+
+  pub const PALM: usize = 0;
+  pub const WRIST: usize = 1;
+  pub const THUMB_TIP: usize = 5;
+  pub const INDEX_PROXIMAL: usize = 7;
+  pub const INDEX_TIP: usize = 10;
+  pub const MIDDLE_TIP: usize = 15;
+  pub const RING_TIP: usize = 20;
+  pub const LITTLE_TIP: usize = 25;
+
+  // in meters, between the surfaces of the joints rather than their centers
+  const PINCH_DISTANCE: f32 = 0.015;
+  const GRAB_DISTANCE: f32 = 0.05;
+  // how close to straight the bones of a finger have to be for it to count as held out
+  const EXTENDED_RATIO: f32 = 0.85;
+
+  pub fn joint(&self, index: usize) -> &HandJoint {
+    &self.joints[index]
+  }
+
+  // thumb and index finger tips touching
+  pub fn pinching(&self) -> bool {
+    let (thumb, index) = (self.joint(HandPose::THUMB_TIP), self.joint(HandPose::INDEX_TIP));
+    thumb.position.distance(index.position) - thumb.radius - index.radius < HandPose::PINCH_DISTANCE
+  }
+
+  // the three outer fingers curled into the palm. the index finger is left out so a pinch does not count
+  pub fn grabbing(&self) -> bool {
+    let palm = self.joint(HandPose::PALM).position;
+
+    [HandPose::MIDDLE_TIP, HandPose::RING_TIP, HandPose::LITTLE_TIP].iter()
+      .all(|tip| self.joint(*tip).position.distance(palm) < HandPose::GRAB_DISTANCE)
+  }
+
+  // fingers 1 to 4, index to little. a finger is held out while its tip is nearly as far from the wrist as the bones
+  // leading there are long
+  pub fn extended(&self, finger: usize) -> bool {
+    let tip = HandPose::INDEX_TIP + (finger - 1) * 5;
+    let wrist = self.joint(HandPose::WRIST).position;
+
+    let (_, length) = (tip - 3..=tip).fold((wrist, 0.0), |(previous, length), joint| {
+      let position = self.joint(joint).position;
+      (position, length + previous.distance(position))
+    });
+
+    wrist.distance(self.joint(tip).position) > length * HandPose::EXTENDED_RATIO
+  }
+
+  // a ray out of the palm along the fingers, pointing down -z like controller aim poses
+  pub fn aim(&self) -> Mat4 {
+    let palm = self.joint(HandPose::PALM);
+    Mat4::from_rotation_translation(palm.orientation, palm.position)
+  }
+}
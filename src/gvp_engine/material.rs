@@ -0,0 +1,69 @@
+mod registry;
+
+pub use registry::*;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum AlphaMode {
+  #[default]
+  Opaque,
+  Mask,
+  Blend
+}
+
+// metallic-roughness parameters as described by glTF. texture fields index into the textures of whatever owns the
+// material (a model for now)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Material {
+  pub name: Option<String>,
+  pub base_color_factor: [f32; 4],
+  pub base_color_texture: Option<usize>,
+  pub metallic_factor: f32,
+  pub roughness_factor: f32,
+  pub metallic_roughness_texture: Option<usize>,
+  pub normal_texture: Option<usize>,
+  pub normal_scale: f32,
+  pub occlusion_texture: Option<usize>,
+  pub occlusion_strength: f32,
+  pub emissive_factor: [f32; 3],
+  pub emissive_texture: Option<usize>,
+  pub emissive_strength: f32,
+  pub alpha_mode: AlphaMode,
+  pub alpha_cutoff: f32,
+  pub double_sided: bool
+}
+
+impl Default for Material {
+  fn default() -> Self {
+    Material {
+      name: None,
+      base_color_factor: [1.0; 4],
+      base_color_texture: None,
+      metallic_factor: 1.0,
+      roughness_factor: 1.0,
+      metallic_roughness_texture: None,
+      normal_texture: None,
+      normal_scale: 1.0,
+      occlusion_texture: None,
+      occlusion_strength: 1.0,
+      emissive_factor: [0.0; 3],
+      emissive_texture: None,
+      emissive_strength: 1.0,
+      alpha_mode: AlphaMode::Opaque,
+      alpha_cutoff: 0.5,
+      double_sided: false
+    }
+  }
+}
+
+impl Material {
+  pub fn color(base_color: [f32; 4], metallic: f32, roughness: f32) -> Self {
+    Material {
+      base_color_factor: base_color,
+      metallic_factor: metallic,
+      roughness_factor: roughness,
+      ..Material::default()
+    }
+  }
+}
@@ -0,0 +1,366 @@
+use crate::gvp_engine::{
+  allocator::{as_bytes, Allocator, Buffer},
+  gpu::GPU,
+  material::{AlphaMode, Material},
+  texture::{SamplerInfo, Texture, TextureData}
+};
+
+use ash::vk;
+use glam::Vec3;
+
+use std::{mem::size_of, slice};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaterialUniforms {
+  pub base_color: [f32; 4],
+  pub emissive: [f32; 4],
+  // metallic, roughness, normal scale, occlusion strength
+  pub surface: [f32; 4],
+  // alpha cutoff, 1 when masked, unused, unused
+  pub alpha: [f32; 4]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialHandle(usize);
+
+// everything the renderer needs to draw with a material. the descriptor set is bound as set 1
+pub struct MaterialInstance {
+  pub descriptor_set: vk::DescriptorSet,
+  pub blend: bool,
+  pub double_sided: bool,
+  // what the uniform buffer holds, for the path tracer which reads every material at once
+  pub uniforms: MaterialUniforms,
+  uniform_buffer: Buffer
+}
+
+struct Entry {
+  // index of the model whose textures the material refers to
+  owner: Option<usize>,
+  material: Material,
+  instance: MaterialInstance,
+  // handles given out and not yet released. an entry at zero is kept until the frames that drew with it finish, and is
+  // handed out again if the same material comes back before then
+  references: usize
+}
+
+// hands out one descriptor set per distinct material. materials are immutable once registered, so editing a material
+// component registers (or finds) another entry and releases the old one
+pub struct MaterialRegistry {
+  descriptor_set_layout: vk::DescriptorSetLayout,
+  descriptor_pools: Vec<vk::DescriptorPool>,
+  // 1x1 stand ins bound in place of any map a material does not use
+  white: Texture,
+  flat_normal: Texture,
+  // freed entries leave a hole that the next new material fills, so handles stay put
+  entries: Vec<Option<Entry>>,
+  // entries no longer referenced and the frame they were released before. freed once the gpu finishes that frame
+  retired: Vec<(usize, u64)>,
+  // descriptor sets of freed entries, rewritten for new ones since pools are never freed individually
+  free_sets: Vec<vk::DescriptorSet>
+}
+
+impl MaterialRegistry {
+  pub const DEFAULT: MaterialHandle = MaterialHandle(0);
+
+  const SETS_PER_POOL: u32 = 64;
+  const TEXTURE_COUNT: u32 = 5;
+
+  pub fn new(instance: &ash::Instance, device: &ash::Device, allocator: &Allocator, gpu: &GPU) -> Self {
+    let white = TextureData::rgba8(1, 1, vec![255; 4], false);
+    let flat_normal = TextureData::rgba8(1, 1, vec![128, 128, 255, 255], false);
+
+    let mut registry = MaterialRegistry {
+      descriptor_set_layout: MaterialRegistry::create_descriptor_set_layout(device),
+      descriptor_pools: vec![MaterialRegistry::create_descriptor_pool(device)],
+      white: Texture::upload(instance, device, allocator, gpu, &white, &SamplerInfo::default()),
+      flat_normal: Texture::upload(instance, device, allocator, gpu, &flat_normal, &SamplerInfo::default()),
+      entries: Vec::new(),
+      retired: Vec::new(),
+      free_sets: Vec::new()
+    };
+
+    // never released, so it is never freed
+    registry.register(device, allocator, &Material::default(), None, &[]);
+
+    registry
+  }
+
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    for entry in self.entries.iter().flatten() {
+      allocator.destroy_buffer(device, &entry.instance.uniform_buffer);
+    }
+
+    self.white.clean(device, allocator);
+    self.flat_normal.clean(device, allocator);
+
+    unsafe {
+      for pool in &self.descriptor_pools {
+        device.destroy_descriptor_pool(*pool, None);
+      }
+
+      device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+    };
+  }
+
+  pub fn layout(&self) -> vk::DescriptorSetLayout {
+    self.descriptor_set_layout
+  }
+
+  // owner is the model the material came from and textures are that model's textures. materials without an owner
+  // ignore their texture indices. every handle returned has to be released once it is no longer drawn with
+  pub fn register(
+    &mut self,
+    device: &ash::Device,
+    allocator: &Allocator,
+    material: &Material,
+    owner: Option<usize>,
+    textures: &[Texture]
+  ) -> MaterialHandle {
+    let existing = self.entries.iter_mut().enumerate().find_map(|(index, entry)| {
+      entry.as_mut().filter(|entry| entry.owner == owner && entry.material == *material).map(|entry| (index, entry))
+    });
+
+    if let Some((index, entry)) = existing {
+      // back from retirement before it was freed
+      if entry.references == 0 {
+        self.retired.retain(|(retired, _)| *retired != index);
+      }
+
+      entry.references += 1;
+      return MaterialHandle(index);
+    }
+
+    let uniforms = MaterialUniforms {
+      base_color: material.base_color_factor,
+      emissive: (Vec3::from(material.emissive_factor) * material.emissive_strength).extend(0.0).to_array(),
+      surface: [material.metallic_factor, material.roughness_factor, material.normal_scale, material.occlusion_strength],
+      alpha: [material.alpha_cutoff, if material.alpha_mode == AlphaMode::Mask { 1.0 } else { 0.0 }, 0.0, 0.0]
+    };
+
+    let uniform_buffer = allocator.create_buffer(
+      device,
+      size_of::<MaterialUniforms>() as vk::DeviceSize,
+      vk::BufferUsageFlags::UNIFORM_BUFFER,
+      vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+    );
+
+    allocator.write_buffer(device, &uniform_buffer, 0, as_bytes(slice::from_ref(&uniforms)));
+
+    let lookup = |index: Option<usize>, fallback: &'_ Texture| -> (vk::ImageView, vk::Sampler) {
+      let texture = match (owner, index) {
+        (Some(_), Some(index)) => textures.get(index).unwrap_or(fallback),
+        _                      => fallback
+      };
+
+      (texture.image.view, texture.sampler)
+    };
+
+    let maps = [
+      lookup(material.base_color_texture, &self.white),
+      lookup(material.metallic_roughness_texture, &self.white),
+      lookup(material.normal_texture, &self.flat_normal),
+      lookup(material.occlusion_texture, &self.white),
+      lookup(material.emissive_texture, &self.white)
+    ];
+
+    let descriptor_set = match self.free_sets.pop() {
+      Some(descriptor_set) => descriptor_set,
+      None                 => self.allocate_descriptor_set(device)
+    };
+
+    let buffer_info = [
+      vk::DescriptorBufferInfo::default()
+        .buffer(uniform_buffer.buffer)
+        .range(vk::WHOLE_SIZE)
+    ];
+
+    let image_infos = maps.map(|(view, _)| {
+      [vk::DescriptorImageInfo::default().image_view(view).image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)]
+    });
+
+    let sampler_infos = maps.map(|(_, sampler)| [vk::DescriptorImageInfo::default().sampler(sampler)]);
+
+    let mut writes = vec![
+      vk::WriteDescriptorSet::default()
+        .dst_set(descriptor_set)
+        .dst_binding(0)
+        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+        .buffer_info(&buffer_info)
+    ];
+
+    for (index, image_info) in image_infos.iter().enumerate() {
+      writes.push(
+        vk::WriteDescriptorSet::default()
+          .dst_set(descriptor_set)
+          .dst_binding(1 + index as u32)
+          .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+          .image_info(image_info)
+      );
+    }
+
+    for (index, sampler_info) in sampler_infos.iter().enumerate() {
+      writes.push(
+        vk::WriteDescriptorSet::default()
+          .dst_set(descriptor_set)
+          .dst_binding(1 + MaterialRegistry::TEXTURE_COUNT + index as u32)
+          .descriptor_type(vk::DescriptorType::SAMPLER)
+          .image_info(sampler_info)
+      );
+    }
+
+    unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+    let entry = Entry {
+      owner,
+      material: material.clone(),
+      instance: MaterialInstance {
+        descriptor_set,
+        blend: material.alpha_mode == AlphaMode::Blend,
+        double_sided: material.double_sided,
+        uniforms,
+        uniform_buffer
+      },
+      references: 1
+    };
+
+    match self.entries.iter().position(Option::is_none) {
+      Some(index) => {
+        self.entries[index] = Some(entry);
+        MaterialHandle(index)
+      },
+      None        => {
+        self.entries.push(Some(entry));
+        MaterialHandle(self.entries.len() - 1)
+      }
+    }
+  }
+
+  pub fn get(&self, handle: MaterialHandle) -> &MaterialInstance {
+    match &self.entries[handle.0] {
+      Some(entry) => &entry.instance,
+      None        => panic!("failed to find material {} with error: it was freed", handle.0)
+    }
+  }
+
+  // frame is the next one to be submitted, the last that can have drawn with the handle is the one before it
+  pub fn release(&mut self, handle: MaterialHandle, frame: u64) {
+    if handle == MaterialRegistry::DEFAULT { return; }
+
+    let Some(entry) = &mut self.entries[handle.0] else { return; };
+    entry.references -= 1;
+
+    if entry.references == 0 {
+      self.retired.push((handle.0, frame));
+    }
+  }
+
+  // finished counts the frames the gpu is done with. entries released before any frame after those was submitted
+  // are freed
+  pub fn collect(&mut self, device: &ash::Device, allocator: &Allocator, finished: u64) {
+    let (done, waiting): (Vec<_>, Vec<_>) = self.retired.drain(..).partition(|(_, frame)| *frame <= finished);
+    self.retired = waiting;
+
+    for (index, _) in done {
+      self.free(device, allocator, index);
+    }
+  }
+
+  fn free(&mut self, device: &ash::Device, allocator: &Allocator, index: usize) {
+    let Some(entry) = self.entries[index].take() else { return; };
+
+    allocator.destroy_buffer(device, &entry.instance.uniform_buffer);
+    self.free_sets.push(entry.instance.descriptor_set);
+  }
+
+  // pools are never freed individually, a new one is started whenever the last fills up
+  fn allocate_descriptor_set(&mut self, device: &ash::Device) -> vk::DescriptorSet {
+    let layouts = [self.descriptor_set_layout];
+
+    let allocate = |pool: vk::DescriptorPool| {
+      let allocate_info = {
+        vk::DescriptorSetAllocateInfo::default()
+          .descriptor_pool(pool)
+          .set_layouts(&layouts)
+      };
+
+      unsafe { device.allocate_descriptor_sets(&allocate_info) }
+    };
+
+    match allocate(self.descriptor_pools[self.descriptor_pools.len() - 1]) {
+      Ok(sets)                                                                    => return sets[0],
+      Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY | vk::Result::ERROR_FRAGMENTED_POOL) => (),
+      Err(error)                                                                  => {
+        panic!("failed to allocate material descriptor set with error: {error}")
+      }
+    }
+
+    let pool = MaterialRegistry::create_descriptor_pool(device);
+    self.descriptor_pools.push(pool);
+
+    match allocate(pool) {
+      Ok(sets)   => sets[0],
+      Err(error) => panic!("failed to allocate material descriptor set with error: {error}")
+    }
+  }
+
+  fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+    let mut bindings = vec![
+      vk::DescriptorSetLayoutBinding::default()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+    ];
+
+    for index in 0..MaterialRegistry::TEXTURE_COUNT {
+      bindings.push(
+        vk::DescriptorSetLayoutBinding::default()
+          .binding(1 + index)
+          .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+          .descriptor_count(1)
+          .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+      );
+
+      bindings.push(
+        vk::DescriptorSetLayoutBinding::default()
+          .binding(1 + MaterialRegistry::TEXTURE_COUNT + index)
+          .descriptor_type(vk::DescriptorType::SAMPLER)
+          .descriptor_count(1)
+          .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+      );
+    }
+
+    let create_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+    match unsafe { device.create_descriptor_set_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create material descriptor set layout with error: {error}")
+    }
+  }
+
+  fn create_descriptor_pool(device: &ash::Device) -> vk::DescriptorPool {
+    let pool_sizes = [
+      vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::UNIFORM_BUFFER)
+        .descriptor_count(MaterialRegistry::SETS_PER_POOL),
+      vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::SAMPLED_IMAGE)
+        .descriptor_count(MaterialRegistry::SETS_PER_POOL * MaterialRegistry::TEXTURE_COUNT),
+      vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::SAMPLER)
+        .descriptor_count(MaterialRegistry::SETS_PER_POOL * MaterialRegistry::TEXTURE_COUNT)
+    ];
+
+    let create_info = {
+      vk::DescriptorPoolCreateInfo::default()
+        .max_sets(MaterialRegistry::SETS_PER_POOL)
+        .pool_sizes(&pool_sizes)
+    };
+
+    match unsafe { device.create_descriptor_pool(&create_info, None) } {
+      Ok(pool)   => pool,
+      Err(error) => panic!("failed to create material descriptor pool with error: {error}")
+    }
+  }
+}
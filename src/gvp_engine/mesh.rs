@@ -0,0 +1,176 @@
+use crate::gvp_engine::{
+  allocator::{as_bytes, Allocator, Buffer},
+  bvh::MeshBvh
+};
+
+use ash::vk;
+use glam::{Vec2, Vec3};
+
+use std::mem::{offset_of, size_of};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vertex {
+  pub position: [f32; 3],
+  pub normal: [f32; 3],
+  pub tangent: [f32; 4],
+  pub uv: [f32; 2]
+}
+
+impl Vertex {
+  pub fn binding_description() -> vk::VertexInputBindingDescription {
+    vk::VertexInputBindingDescription::default()
+      .binding(0)
+      .stride(size_of::<Vertex>() as u32)
+      .input_rate(vk::VertexInputRate::VERTEX)
+  }
+
+  pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 4] {
+    [
+      vk::VertexInputAttributeDescription::default()
+        .location(0)
+        .binding(0)
+        .format(vk::Format::R32G32B32_SFLOAT)
+        .offset(offset_of!(Vertex, position) as u32),
+      vk::VertexInputAttributeDescription::default()
+        .location(1)
+        .binding(0)
+        .format(vk::Format::R32G32B32_SFLOAT)
+        .offset(offset_of!(Vertex, normal) as u32),
+      vk::VertexInputAttributeDescription::default()
+        .location(2)
+        .binding(0)
+        .format(vk::Format::R32G32B32A32_SFLOAT)
+        .offset(offset_of!(Vertex, tangent) as u32),
+      vk::VertexInputAttributeDescription::default()
+        .location(3)
+        .binding(0)
+        .format(vk::Format::R32G32_SFLOAT)
+        .offset(offset_of!(Vertex, uv) as u32)
+    ]
+  }
+}
+
+// cpu side geometry. joints and weights are either empty or have one entry per vertex
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+  pub vertices: Vec<Vertex>,
+  pub indices: Vec<u32>,
+  pub joints: Vec<[u16; 4]>,
+  pub weights: Vec<[f32; 4]>
+}
+
+impl MeshData {
+  pub fn generate_normals(&mut self) {
+    let mut normals = vec![Vec3::ZERO; self.vertices.len()];
+
+    for triangle in self.indices.chunks_exact(3) {
+      let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+      let (p0, p1, p2) = (self.position(a), self.position(b), self.position(c));
+
+      // area weighted face normal
+      let normal = (p1 - p0).cross(p2 - p0);
+      normals[a] += normal;
+      normals[b] += normal;
+      normals[c] += normal;
+    }
+
+    for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+      vertex.normal = normal.normalize_or(Vec3::Y).to_array();
+    }
+  }
+
+  // per triangle tangents accumulated and orthogonalized against the vertex normal
+  pub fn generate_tangents(&mut self) {
+    let mut tangents = vec![Vec3::ZERO; self.vertices.len()];
+    let mut bitangents = vec![Vec3::ZERO; self.vertices.len()];
+
+    for triangle in self.indices.chunks_exact(3) {
+      let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+
+      let edge1 = self.position(b) - self.position(a);
+      let edge2 = self.position(c) - self.position(a);
+      let delta1 = Vec2::from(self.vertices[b].uv) - Vec2::from(self.vertices[a].uv);
+      let delta2 = Vec2::from(self.vertices[c].uv) - Vec2::from(self.vertices[a].uv);
+
+      let determinant = delta1.x * delta2.y - delta2.x * delta1.y;
+      if determinant.abs() < f32::EPSILON { continue; }
+
+      let r = 1.0 / determinant;
+      let tangent = (edge1 * delta2.y - edge2 * delta1.y) * r;
+      let bitangent = (edge2 * delta1.x - edge1 * delta2.x) * r;
+
+      for index in [a, b, c] {
+        tangents[index] += tangent;
+        bitangents[index] += bitangent;
+      }
+    }
+
+    for (index, vertex) in self.vertices.iter_mut().enumerate() {
+      let normal = Vec3::from(vertex.normal);
+      let tangent = (tangents[index] - normal * normal.dot(tangents[index])).normalize_or(normal.any_orthonormal_vector());
+      let handedness = if normal.cross(tangent).dot(bitangents[index]) < 0.0 { -1.0 } else { 1.0 };
+
+      vertex.tangent = tangent.extend(handedness).to_array();
+    }
+  }
+
+  pub fn is_skinned(&self) -> bool {
+    !self.joints.is_empty() && self.joints.len() == self.vertices.len()
+  }
+
+  fn position(&self, index: usize) -> Vec3 {
+    Vec3::from(self.vertices[index].position)
+  }
+}
+
+pub struct Mesh {
+  pub vertex_buffer: Buffer,
+  pub index_buffer: Buffer,
+  pub index_count: u32,
+  // joints (4 x u16) followed by weights (4 x f32) per vertex when the mesh is skinned
+  pub skin_buffer: Option<Buffer>,
+  // kept on the cpu for the path tracer, in the rest pose when the mesh is skinned
+  pub bvh: MeshBvh
+}
+
+impl Mesh {
+  pub fn upload(device: &ash::Device, allocator: &Allocator, data: &MeshData) -> Self {
+    if data.vertices.is_empty() || data.indices.is_empty() {
+      panic!("failed to upload mesh with error: mesh has no geometry")
+    }
+
+    let vertex_buffer = allocator.upload_buffer(device, as_bytes(&data.vertices), vk::BufferUsageFlags::VERTEX_BUFFER);
+    let index_buffer = allocator.upload_buffer(device, as_bytes(&data.indices), vk::BufferUsageFlags::INDEX_BUFFER);
+
+    let skin_buffer = match data.is_skinned() {
+      true  => {
+        let mut bytes = Vec::<u8>::with_capacity(data.joints.len() * 24);
+        for (joints, weights) in data.joints.iter().zip(&data.weights) {
+          bytes.extend_from_slice(as_bytes(joints));
+          bytes.extend_from_slice(as_bytes(weights));
+        }
+
+        Some(allocator.upload_buffer(device, &bytes, vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::STORAGE_BUFFER))
+      },
+      false => None
+    };
+
+    Mesh {
+      vertex_buffer,
+      index_buffer,
+      index_count: data.indices.len() as u32,
+      skin_buffer,
+      bvh: MeshBvh::build(data)
+    }
+  }
+
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    allocator.destroy_buffer(device, &self.vertex_buffer);
+    allocator.destroy_buffer(device, &self.index_buffer);
+
+    if let Some(skin_buffer) = &self.skin_buffer {
+      allocator.destroy_buffer(device, skin_buffer);
+    }
+  }
+}
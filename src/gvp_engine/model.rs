@@ -0,0 +1,486 @@
+use crate::gvp_engine::{
+  allocator::Allocator,
+  gpu::GPU,
+  material::{AlphaMode, Material},
+  mesh::{Mesh, MeshData, Vertex},
+  texture::{SamplerInfo, Texture, TextureData},
+  transform::Transform
+};
+
+use ash::vk;
+use glam::{Quat, Vec3};
+use gltf::{camera::Projection as GltfProjection, image::Format, texture::{MagFilter, MinFilter, WrappingMode}, Gltf};
+
+use uuid::Uuid;
+
+use std::{fmt, fs, path::{Path, PathBuf}};
+
+// required extensions outside of this list cause the load to fail instead of silently rendering the asset wrong
+const SUPPORTED_EXTENSIONS: [&str; 1] = [
+  "KHR_materials_emissive_strength"
+];
+
+// why a model was not loaded
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModelError {
+  Read(String),
+  Parse(String),
+  // required by the file but not in SUPPORTED_EXTENSIONS
+  UnsupportedExtension(String),
+  Invalid(String),
+  Buffers(String),
+  Images(String),
+  // only triangle lists are drawn
+  UnsupportedMode(String),
+  MissingPositions,
+  // an index past the end of its primitive's vertices
+  IndexOutOfRange { index: u32, vertices: usize }
+}
+
+impl fmt::Display for ModelError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ModelError::Read(error)                        => write!(formatter, "could not read file: {error}"),
+      ModelError::Parse(error)                       => write!(formatter, "could not parse: {error}"),
+      ModelError::UnsupportedExtension(extension)    => write!(formatter, "required extension {extension} is not supported"),
+      ModelError::Invalid(error)                     => write!(formatter, "invalid gltf: {error}"),
+      ModelError::Buffers(error)                     => write!(formatter, "could not load buffers: {error}"),
+      ModelError::Images(error)                      => write!(formatter, "could not load images: {error}"),
+      ModelError::UnsupportedMode(mode)              => write!(formatter, "primitive mode {mode} is not supported"),
+      ModelError::MissingPositions                   => write!(formatter, "primitive has no positions"),
+      ModelError::IndexOutOfRange { index, vertices } => write!(formatter, "index {index} is past the {vertices} vertices of its primitive")
+    }
+  }
+}
+
+// rgba8 pixels for a single glTF texture along with the sampler state it asked for
+pub struct ModelTexture {
+  pub data: TextureData,
+  pub sampler: SamplerInfo
+}
+
+pub struct PrimitiveData {
+  pub mesh: MeshData,
+  pub material: Option<usize>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+  Perspective { yfov: f32, aspect_ratio: Option<f32>, znear: f32, zfar: Option<f32> },
+  Orthographic { xmag: f32, ymag: f32, znear: f32, zfar: f32 }
+}
+
+#[derive(Clone, Debug)]
+pub struct CameraData {
+  pub name: Option<String>,
+  pub projection: Projection
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Node {
+  pub name: Option<String>,
+  pub parent: Option<usize>,
+  pub children: Vec<usize>,
+  pub transform: Transform,
+  pub mesh: Option<usize>,
+  pub camera: Option<usize>
+}
+
+// everything a glTF file describes but skins, decoded on the cpu. skinned meshes load in their bind pose. loading this
+// never touches vulkan so it works headlessly
+pub struct ModelData {
+  pub path: PathBuf,
+  pub meshes: Vec<Vec<PrimitiveData>>,
+  pub materials: Vec<Material>,
+  pub textures: Vec<ModelTexture>,
+  pub nodes: Vec<Node>,
+  pub roots: Vec<usize>,
+  pub cameras: Vec<CameraData>
+}
+
+pub struct ModelPrimitive {
+  pub mesh: Mesh,
+  pub material: Option<usize>
+}
+
+// the id is what saved scenes use to refer back to the model, so it is kept stable across sessions
+pub struct Model {
+  pub id: Uuid,
+  pub path: PathBuf,
+  pub meshes: Vec<Vec<ModelPrimitive>>,
+  pub materials: Vec<Material>,
+  pub textures: Vec<Texture>,
+  pub nodes: Vec<Node>,
+  pub roots: Vec<usize>,
+  pub cameras: Vec<CameraData>
+}
+
+impl ModelData {
+  // loads both .gltf (with external or embedded buffers) and .glb files
+  pub fn load(path: &Path) -> Result<Self, ModelError> {
+    let bytes = fs::read(path).map_err(|error| ModelError::Read(error.to_string()))?;
+    let gltf = Gltf::from_slice_without_validation(&bytes).map_err(|error| ModelError::Parse(error.to_string()))?;
+
+    if let Some(extension) = gltf.extensions_required().find(|extension| !SUPPORTED_EXTENSIONS.contains(extension)) {
+      return Err(ModelError::UnsupportedExtension(extension.to_string()));
+    }
+
+    let Gltf { document, blob } = gltf;
+    let document = gltf::Document::from_json(document.into_json()).map_err(|error| ModelError::Invalid(error.to_string()))?;
+
+    let base = path.parent();
+    let buffers = gltf::import_buffers(&document, base, blob).map_err(|error| ModelError::Buffers(error.to_string()))?;
+    let images = gltf::import_images(&document, base, &buffers).map_err(|error| ModelError::Images(error.to_string()))?;
+
+    let materials: Vec<Material> = document.materials().map(|material| ModelData::material(&material)).collect();
+
+    let mut textures = Vec::<ModelTexture>::new();
+    for texture in document.textures() {
+      textures.push(ModelData::texture(&texture, &images[texture.source().index()], &materials));
+    }
+
+    let mut meshes = Vec::<Vec<PrimitiveData>>::new();
+    for mesh in document.meshes() {
+      let mut primitives = Vec::<PrimitiveData>::new();
+
+      for primitive in mesh.primitives() {
+        primitives.push(ModelData::primitive(&primitive, &buffers)?);
+      }
+
+      meshes.push(primitives);
+    }
+
+    let mut nodes = Vec::<Node>::new();
+    for node in document.nodes() {
+      let (translation, rotation, scale) = node.transform().decomposed();
+
+      nodes.push(Node {
+        name: node.name().map(String::from),
+        parent: None,
+        children: node.children().map(|child| child.index()).collect(),
+        transform: Transform::new(Vec3::from(translation), Quat::from_array(rotation), Vec3::from(scale)),
+        mesh: node.mesh().map(|mesh| mesh.index()),
+        camera: node.camera().map(|camera| camera.index())
+      });
+    }
+
+    for index in 0..nodes.len() {
+      for child in nodes[index].children.clone() {
+        nodes[child].parent = Some(index);
+      }
+    }
+
+    // prefer the scene the file asks for, otherwise fall back to every node without a parent
+    let roots = match document.default_scene().or(document.scenes().next()) {
+      Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+      None        => (0..nodes.len()).filter(|&index| nodes[index].parent.is_none()).collect()
+    };
+
+    let cameras = document.cameras().map(|camera| {
+      let projection = match camera.projection() {
+        GltfProjection::Perspective(perspective) => Projection::Perspective {
+          yfov: perspective.yfov(),
+          aspect_ratio: perspective.aspect_ratio(),
+          znear: perspective.znear(),
+          zfar: perspective.zfar()
+        },
+        GltfProjection::Orthographic(orthographic) => Projection::Orthographic {
+          xmag: orthographic.xmag(),
+          ymag: orthographic.ymag(),
+          znear: orthographic.znear(),
+          zfar: orthographic.zfar()
+        }
+      };
+
+      CameraData {
+        name: camera.name().map(String::from),
+        projection
+      }
+    }).collect();
+
+    Ok(ModelData {
+      path: path.to_path_buf(),
+      meshes,
+      materials,
+      textures,
+      nodes,
+      roots,
+      cameras
+    })
+  }
+
+  fn primitive(primitive: &gltf::Primitive, buffers: &[gltf::buffer::Data]) -> Result<PrimitiveData, ModelError> {
+    if primitive.mode() != gltf::mesh::Mode::Triangles {
+      return Err(ModelError::UnsupportedMode(format!("{:?}", primitive.mode())));
+    }
+
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+    let positions: Vec<[f32; 3]> = reader.read_positions().ok_or(ModelError::MissingPositions)?.collect();
+
+    let mut mesh = MeshData {
+      vertices: positions.iter().map(|&position| Vertex { position, ..Vertex::default() }).collect(),
+      ..MeshData::default()
+    };
+
+    mesh.indices = match reader.read_indices() {
+      Some(indices) => indices.into_u32().collect(),
+      None          => (0..positions.len() as u32).collect()
+    };
+
+    // generating normals and tangents indexes the vertices with these
+    if let Some(&index) = mesh.indices.iter().find(|&&index| index as usize >= positions.len()) {
+      return Err(ModelError::IndexOutOfRange { index, vertices: positions.len() });
+    }
+
+    if let Some(uvs) = reader.read_tex_coords(0) {
+      for (vertex, uv) in mesh.vertices.iter_mut().zip(uvs.into_f32()) {
+        vertex.uv = uv;
+      }
+    }
+
+    match reader.read_normals() {
+      Some(normals) => {
+        for (vertex, normal) in mesh.vertices.iter_mut().zip(normals) {
+          vertex.normal = normal;
+        }
+      },
+      None          => mesh.generate_normals()
+    }
+
+    match reader.read_tangents() {
+      Some(tangents) => {
+        for (vertex, tangent) in mesh.vertices.iter_mut().zip(tangents) {
+          vertex.tangent = tangent;
+        }
+      },
+      None           => mesh.generate_tangents()
+    }
+
+    if let (Some(joints), Some(weights)) = (reader.read_joints(0), reader.read_weights(0)) {
+      mesh.joints = joints.into_u16().collect();
+      mesh.weights = weights.into_f32().collect();
+    }
+
+    Ok(PrimitiveData {
+      mesh,
+      material: primitive.material().index()
+    })
+  }
+
+  fn material(material: &gltf::Material) -> Material {
+    let pbr = material.pbr_metallic_roughness();
+
+    let alpha_mode = match material.alpha_mode() {
+      gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+      gltf::material::AlphaMode::Mask   => AlphaMode::Mask,
+      gltf::material::AlphaMode::Blend  => AlphaMode::Blend
+    };
+
+    Material {
+      name: material.name().map(String::from),
+      base_color_factor: pbr.base_color_factor(),
+      base_color_texture: pbr.base_color_texture().map(|info| info.texture().index()),
+      metallic_factor: pbr.metallic_factor(),
+      roughness_factor: pbr.roughness_factor(),
+      metallic_roughness_texture: pbr.metallic_roughness_texture().map(|info| info.texture().index()),
+      normal_texture: material.normal_texture().map(|normal| normal.texture().index()),
+      normal_scale: material.normal_texture().map_or(1.0, |normal| normal.scale()),
+      occlusion_texture: material.occlusion_texture().map(|occlusion| occlusion.texture().index()),
+      occlusion_strength: material.occlusion_texture().map_or(1.0, |occlusion| occlusion.strength()),
+      emissive_factor: material.emissive_factor(),
+      emissive_texture: material.emissive_texture().map(|info| info.texture().index()),
+      emissive_strength: material.emissive_strength().unwrap_or(1.0),
+      alpha_mode,
+      alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+      double_sided: material.double_sided()
+    }
+  }
+
+  fn texture(texture: &gltf::Texture, image: &gltf::image::Data, materials: &[Material]) -> ModelTexture {
+    let index = Some(texture.index());
+
+    // color textures are stored in srgb, everything else (normals, metallic/roughness, occlusion) is linear data
+    let srgb = materials.iter().any(|material| {
+      material.base_color_texture == index || material.emissive_texture == index
+    });
+
+    let sampler = texture.sampler();
+
+    let mag_filter = match sampler.mag_filter() {
+      Some(MagFilter::Nearest) => vk::Filter::NEAREST,
+      _                        => vk::Filter::LINEAR
+    };
+
+    let min_filter = match sampler.min_filter() {
+      Some(MinFilter::Nearest)              => vk::Filter::NEAREST,
+      Some(MinFilter::NearestMipmapNearest) => vk::Filter::NEAREST,
+      Some(MinFilter::NearestMipmapLinear)  => vk::Filter::NEAREST,
+      _                                     => vk::Filter::LINEAR
+    };
+
+    // filters without a mipmap mode still get a mip chain, blending between levels looks better than aliasing
+    let mipmap_mode = match sampler.min_filter() {
+      Some(MinFilter::NearestMipmapNearest) => vk::SamplerMipmapMode::NEAREST,
+      Some(MinFilter::LinearMipmapNearest)  => vk::SamplerMipmapMode::NEAREST,
+      _                                     => vk::SamplerMipmapMode::LINEAR
+    };
+
+    let [u, v] = [sampler.wrap_s(), sampler.wrap_t()].map(|mode| match mode {
+      WrappingMode::ClampToEdge     => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+      WrappingMode::MirroredRepeat  => vk::SamplerAddressMode::MIRRORED_REPEAT,
+      WrappingMode::Repeat          => vk::SamplerAddressMode::REPEAT
+    });
+
+    ModelTexture {
+      data: TextureData::rgba8(image.width, image.height, ModelData::rgba8(image), srgb),
+      sampler: SamplerInfo {
+        mag_filter,
+        min_filter,
+        mipmap_mode,
+        address_mode: [u, v, vk::SamplerAddressMode::REPEAT],
+        anisotropy: true
+      }
+    }
+  }
+
+  // expands every image format glTF can produce into tightly packed rgba8
+  fn rgba8(image: &gltf::image::Data) -> Vec<u8> {
+    let pixels = &image.pixels;
+
+    match image.format {
+      Format::R8                 => pixels.iter().flat_map(|&r| [r, r, r, 255]).collect(),
+      Format::R8G8               => pixels.chunks_exact(2).flat_map(|p| [p[0], p[1], 0, 255]).collect(),
+      Format::R8G8B8             => pixels.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+      Format::R8G8B8A8           => pixels.clone(),
+      Format::R16                => pixels.chunks_exact(2).flat_map(|p| [p[1], p[1], p[1], 255]).collect(),
+      Format::R16G16             => pixels.chunks_exact(4).flat_map(|p| [p[1], p[3], 0, 255]).collect(),
+      Format::R16G16B16          => pixels.chunks_exact(6).flat_map(|p| [p[1], p[3], p[5], 255]).collect(),
+      Format::R16G16B16A16       => pixels.chunks_exact(8).flat_map(|p| [p[1], p[3], p[5], p[7]]).collect(),
+      Format::R32G32B32FLOAT     => ModelData::float_rgba8(pixels, 3),
+      Format::R32G32B32A32FLOAT  => ModelData::float_rgba8(pixels, 4)
+    }
+  }
+
+  fn float_rgba8(pixels: &[u8], channels: usize) -> Vec<u8> {
+    let mut rgba = Vec::<u8>::with_capacity(pixels.len() / channels);
+
+    for pixel in pixels.chunks_exact(channels * 4) {
+      for channel in 0..4 {
+        let value = match channel < channels {
+          true  => f32::from_le_bytes([pixel[channel * 4], pixel[channel * 4 + 1], pixel[channel * 4 + 2], pixel[channel * 4 + 3]]),
+          false => 1.0
+        };
+
+        rgba.push((value.clamp(0.0, 1.0) * 255.0).round() as u8);
+      }
+    }
+
+    rgba
+  }
+}
+
+impl Model {
+  pub fn upload(
+    instance: &ash::Instance,
+    device: &ash::Device,
+    allocator: &Allocator,
+    gpu: &GPU,
+    data: ModelData,
+    id: Uuid
+  ) -> Self {
+    let meshes = data.meshes.iter().map(|primitives| {
+      primitives.iter().map(|primitive| ModelPrimitive {
+        mesh: Mesh::upload(device, allocator, &primitive.mesh),
+        material: primitive.material
+      }).collect()
+    }).collect();
+
+    let textures = data.textures.iter().map(|texture| {
+      Texture::upload(instance, device, allocator, gpu, &texture.data, &texture.sampler)
+    }).collect();
+
+    Model {
+      id,
+      path: data.path,
+      meshes,
+      materials: data.materials,
+      textures,
+      nodes: data.nodes,
+      roots: data.roots,
+      cameras: data.cameras
+    }
+  }
+
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    for primitives in &mut self.meshes {
+      for primitive in primitives {
+        primitive.mesh.clean(device, allocator);
+      }
+    }
+
+    for texture in &self.textures {
+      texture.clean(device, allocator);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/models").join(name)
+  }
+
+  // both fixtures hold the same red triangle under a root node
+  fn assert_triangle(model: &ModelData) {
+    assert_eq!(model.meshes.len(), 1);
+    assert_eq!(model.meshes[0].len(), 1);
+
+    let primitive = &model.meshes[0][0];
+    assert_eq!(primitive.mesh.indices, vec![0, 1, 2]);
+    assert_eq!(primitive.mesh.vertices[1].position, [1.0, 0.0, 0.0]);
+    assert_eq!(primitive.material, Some(0));
+
+    // the file has no normals, so they are generated from the winding
+    for vertex in &primitive.mesh.vertices {
+      assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+    }
+
+    assert_eq!(model.materials[0].name.as_deref(), Some("red"));
+    assert_eq!(model.materials[0].base_color_factor, [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(model.roots, vec![0]);
+    assert_eq!(model.nodes[1].parent, Some(0));
+    assert_eq!(model.nodes[1].mesh, Some(0));
+    assert_eq!(model.nodes[0].transform.translation, Vec3::Y);
+  }
+
+  #[test]
+  fn loads_gltf_with_embedded_buffers() {
+    let model = ModelData::load(&fixture("triangle.gltf")).unwrap();
+    assert_triangle(&model);
+  }
+
+  #[test]
+  fn loads_glb() {
+    let model = ModelData::load(&fixture("triangle.glb")).unwrap();
+    assert_triangle(&model);
+  }
+
+  #[test]
+  fn rejects_unsupported_required_extensions() {
+    let error = ModelData::load(&fixture("unsupported_extension.gltf")).err();
+    assert_eq!(error, Some(ModelError::UnsupportedExtension("KHR_draco_mesh_compression".to_string())));
+  }
+
+  #[test]
+  fn rejects_indices_past_the_vertices() {
+    let error = ModelData::load(&fixture("bad_indices.gltf")).err();
+    assert_eq!(error, Some(ModelError::IndexOutOfRange { index: 7, vertices: 3 }));
+  }
+
+  #[test]
+  fn reports_missing_files() {
+    assert!(matches!(ModelData::load(&fixture("missing.gltf")), Err(ModelError::Read(_))));
+  }
+}
@@ -0,0 +1,82 @@
+mod overlay;
+
+pub use overlay::*;
+
+pub use crate::gvp_engine::{
+  allocator::HeapUsage,
+  renderer::{GpuTimings, PipelineStatistics}
+};
+
+use std::{
+  collections::VecDeque,
+  time::{Duration, Instant}
+};
+
+// one frame as the cpu and the gpu saw it, in milliseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameSample {
+  // since the frame before
+  pub frame: f32,
+  // the part of the frame spent working rather than waiting on the gpu or the display
+  pub cpu: f32,
+  // from the gpu's timestamps, none when the gpu cannot write them
+  pub gpu: Option<f32>
+}
+
+// how the last few seconds of frames went, recorded once a frame after rendering
+#[derive(Default)]
+pub struct Performance {
+  history: VecDeque<FrameSample>,
+  gpu: GpuTimings,
+  memory: Vec<HeapUsage>,
+  last_frame: Option<Instant>
+}
+
+impl Performance {
+  // frames kept, 4 seconds at 60 per second
+  pub const HISTORY: usize = 240;
+
+  // the gpu timings are of the last frame the gpu finished, so they trail the cpu by a frame or two
+  pub fn record(&mut self, waited: Duration, gpu: &GpuTimings, memory: Vec<HeapUsage>) {
+    let now = Instant::now();
+    let Some(last) = self.last_frame.replace(now) else { return; };
+
+    let frame = (now - last).as_secs_f32() * 1000.0;
+    let cpu = (frame - waited.as_secs_f32() * 1000.0).max(0.0);
+
+    if self.history.len() == Performance::HISTORY {
+      self.history.pop_front();
+    }
+
+    self.history.push_back(FrameSample { frame, cpu, gpu: gpu.frame });
+    self.gpu = gpu.clone();
+    self.memory = memory;
+  }
+
+  // oldest first
+  pub fn history(&self) -> &VecDeque<FrameSample> {
+    &self.history
+  }
+
+  // over the last count frames, the gpu time over those that had one
+  pub fn average(&self, count: usize) -> FrameSample {
+    let recent: Vec<&FrameSample> = self.history.iter().rev().take(count.max(1)).collect();
+    let timed: Vec<f32> = recent.iter().filter_map(|sample| sample.gpu).collect();
+    let mean = |values: &mut dyn Iterator<Item = f32>, count: usize| values.sum::<f32>() / count.max(1) as f32;
+
+    FrameSample {
+      frame: mean(&mut recent.iter().map(|sample| sample.frame), recent.len()),
+      cpu: mean(&mut recent.iter().map(|sample| sample.cpu), recent.len()),
+      gpu: (!timed.is_empty()).then(|| mean(&mut timed.iter().copied(), timed.len()))
+    }
+  }
+
+  pub fn gpu(&self) -> &GpuTimings {
+    &self.gpu
+  }
+
+  // per heap of the gpu's memory
+  pub fn memory(&self) -> &[HeapUsage] {
+    &self.memory
+  }
+}
@@ -0,0 +1,197 @@
+use crate::gvp_engine::{
+  app::{AppContext, GVPApp},
+  compositor::Panel,
+  gui::{Canvas, Color, Rect, Theme},
+  transform::Transform
+};
+
+use super::{Performance, PipelineStatistics};
+
+use glam::{Quat, Vec2, Vec3};
+
+// frame times, what each pass cost the gpu and how much of its memory is in use, on a panel that stays in the upper
+// left of the view
+pub struct PerformanceOverlay {
+  theme: Theme
+}
+
+impl PerformanceOverlay {
+  // from the head, in meters
+  const OFFSET: Vec3 = Vec3::new(-0.35, 0.2, -1.0);
+  const SIZE: Vec2 = Vec2::new(0.45, 0.4);
+  const GRAPH_HEIGHT: f32 = 110.0;
+  // frames averaged over for the numbers, so they can be read
+  const AVERAGED: usize = 30;
+  // the frame times of 60 and 30 frames per second, marked on the graph
+  const BUDGETS: [f32; 2] = [1000.0 / 60.0, 1000.0 / 30.0];
+  const FRAME_COLOR: Color = Color::rgba(0.55, 0.55, 0.6, 0.8);
+  const CPU_COLOR: Color = Color::rgb(0.95, 0.65, 0.2);
+  const GPU_COLOR: Color = Color::rgb(0.3, 0.8, 0.45);
+
+  pub fn new() -> Self {
+    PerformanceOverlay { theme: Theme::dark() }
+  }
+
+  fn placement(head: glam::Mat4) -> Panel {
+    let (_, rotation, position) = head.to_scale_rotation_translation();
+    let transform = Transform::new(position + rotation * PerformanceOverlay::OFFSET, rotation, Vec3::ONE);
+
+    // never dims, it is looked past rather than at
+    Panel { gaze_fade: false, ..Panel::new(transform, PerformanceOverlay::SIZE) }
+  }
+
+  fn paint(&self, canvas: &mut Canvas, performance: &Performance) {
+    let theme = &self.theme;
+    let (padding, line) = (theme.padding, theme.font_size * 1.5);
+    let width = canvas.size.x - padding * 2.0;
+    let mut y = padding;
+
+    let average = performance.average(PerformanceOverlay::AVERAGED);
+    let gpu = average.gpu.map_or("-".to_string(), |gpu| format!("{gpu:.2}"));
+    let fps = if average.frame > 0.0 { 1000.0 / average.frame } else { 0.0 };
+    let summary = format!("{fps:.0} fps   frame {:.2} ms   cpu {:.2} ms   gpu {gpu} ms", average.frame, average.cpu);
+
+    canvas.text(Vec2::new(padding, y), &summary, theme.font_size, theme.text);
+    y += line;
+
+    let graph = Rect::new(Vec2::new(padding, y), Vec2::new(width, PerformanceOverlay::GRAPH_HEIGHT));
+    self.graph(canvas, graph, performance);
+    y += graph.size.y + theme.spacing;
+
+    let legend = [("frame", PerformanceOverlay::FRAME_COLOR), ("cpu", PerformanceOverlay::CPU_COLOR), ("gpu", PerformanceOverlay::GPU_COLOR)];
+    for (index, (name, color)) in legend.into_iter().enumerate() {
+      let x = padding + index as f32 * 100.0;
+      canvas.rect(Rect::new(Vec2::new(x, y + theme.font_size * 0.25), Vec2::splat(theme.font_size * 0.5)), color, 2.0);
+      canvas.text(Vec2::new(x + theme.font_size, y), name, theme.font_size, theme.text_muted);
+    }
+    y += line * 1.5;
+
+    // passes against the longest, which gets the whole bar
+    let timings = performance.gpu();
+    let longest = timings.passes.iter().map(|pass| pass.milliseconds).fold(0.001, f32::max);
+
+    canvas.text(Vec2::new(padding, y), "GPU passes", theme.font_size, theme.text);
+    y += line;
+
+    if timings.passes.is_empty() {
+      canvas.text(Vec2::new(padding, y), "timestamps are not supported", theme.font_size, theme.text_muted);
+      y += line;
+    }
+
+    for pass in &timings.passes {
+      canvas.text(Vec2::new(padding, y), pass.name, theme.font_size, theme.text_muted);
+      canvas.text(Vec2::new(padding + 120.0, y), &format!("{:.3} ms", pass.milliseconds), theme.font_size, theme.text);
+      self.bar(canvas, Rect::new(Vec2::new(padding + 240.0, y + 4.0), Vec2::new(width - 240.0, line - 12.0)), pass.milliseconds / longest, PerformanceOverlay::GPU_COLOR);
+      y += line;
+    }
+
+    if let Some(statistics) = timings.statistics {
+      y += theme.spacing;
+      canvas.text(Vec2::new(padding, y), &PerformanceOverlay::statistics(&statistics), theme.font_size, theme.text_muted);
+      y += line;
+    }
+
+    y += theme.spacing;
+    canvas.text(Vec2::new(padding, y), "Memory", theme.font_size, theme.text);
+    y += line;
+
+    for (index, heap) in performance.memory().iter().enumerate() {
+      let kind = if heap.device_local { "device" } else { "host" };
+      let megabytes = |bytes: u64| bytes as f32 / (1024.0 * 1024.0);
+      let usage = format!("{:.0} / {:.0} MB", megabytes(heap.used), megabytes(heap.size));
+
+      canvas.text(Vec2::new(padding, y), &format!("heap {index} {kind}"), theme.font_size, theme.text_muted);
+      canvas.text(Vec2::new(padding + 120.0, y), &usage, theme.font_size, theme.text);
+      self.bar(canvas, Rect::new(Vec2::new(padding + 240.0, y + 4.0), Vec2::new(width - 240.0, line - 12.0)), heap.used as f32 / heap.size.max(1) as f32, theme.accent);
+      y += line;
+    }
+  }
+
+  // every frame kept as a bar of its whole time, with the cpu and gpu parts traced over them. the scale grows to fit
+  // the longest frame but never shows less than 30 frames per second's worth
+  fn graph(&self, canvas: &mut Canvas, rect: Rect, performance: &Performance) {
+    let theme = &self.theme;
+    let history = performance.history();
+    let longest = history.iter().map(|sample| sample.frame).fold(PerformanceOverlay::BUDGETS[1], f32::max) * 1.1;
+    let step = rect.size.x / Performance::HISTORY as f32;
+    let height = |milliseconds: f32| rect.max().y - rect.size.y * (milliseconds / longest).min(1.0);
+
+    canvas.rect(rect, theme.surface, 4.0);
+    canvas.push_clip(rect);
+
+    // the newest frame is at the right edge
+    let start = rect.position.x + (Performance::HISTORY - history.len()) as f32 * step;
+
+    for (index, sample) in history.iter().enumerate() {
+      let top = height(sample.frame);
+      canvas.rect(Rect::new(Vec2::new(start + index as f32 * step, top), Vec2::new(step, rect.max().y - top)), PerformanceOverlay::FRAME_COLOR, 0.0);
+    }
+
+    let trace = |value: fn(&super::FrameSample) -> Option<f32>| -> Vec<Vec2> {
+      history.iter().enumerate()
+        .filter_map(|(index, sample)| value(sample).map(|milliseconds| Vec2::new(start + (index as f32 + 0.5) * step, height(milliseconds))))
+        .collect()
+    };
+
+    canvas.path(&trace(|sample| Some(sample.cpu)), 1.5, PerformanceOverlay::CPU_COLOR, false);
+    canvas.path(&trace(|sample| sample.gpu), 1.5, PerformanceOverlay::GPU_COLOR, false);
+
+    for budget in PerformanceOverlay::BUDGETS {
+      let y = height(budget);
+      canvas.line(Vec2::new(rect.position.x, y), Vec2::new(rect.max().x, y), 1.0, theme.text_muted.with_alpha(0.5));
+      canvas.text(Vec2::new(rect.position.x + 4.0, y - theme.font_size * 1.1), &format!("{budget:.1} ms"), theme.font_size * 0.75, theme.text_muted);
+    }
+
+    canvas.pop_clip();
+  }
+
+  fn bar(&self, canvas: &mut Canvas, rect: Rect, fraction: f32, color: Color) {
+    canvas.rect(rect, self.theme.surface, 3.0);
+    canvas.rect(Rect::new(rect.position, Vec2::new(rect.size.x * fraction.clamp(0.0, 1.0), rect.size.y)), color, 3.0);
+  }
+
+  fn statistics(statistics: &PipelineStatistics) -> String {
+    let count = |count: u64| match count {
+      0..1_000             => count.to_string(),
+      1_000..1_000_000     => format!("{:.1}k", count as f32 / 1e3),
+      _                    => format!("{:.1}M", count as f32 / 1e6)
+    };
+
+    format!(
+      "primitives {} ({} clipped)   vertices {}   fragments {}   compute {}",
+      count(statistics.input_primitives),
+      count(statistics.clipped_primitives),
+      count(statistics.vertex_invocations),
+      count(statistics.fragment_invocations),
+      count(statistics.compute_invocations)
+    )
+  }
+}
+
+impl Default for PerformanceOverlay {
+  fn default() -> Self {
+    PerformanceOverlay::new()
+  }
+}
+
+impl GVPApp for PerformanceOverlay {
+  fn name(&self) -> &str {
+    "Performance"
+  }
+
+  fn panel(&self) -> Panel {
+    Panel { gaze_fade: false, ..Panel::new(Transform::new(PerformanceOverlay::OFFSET + Vec3::Y * 1.5, Quat::IDENTITY, Vec3::ONE), PerformanceOverlay::SIZE) }
+  }
+
+  // follows the head so it stays in view
+  fn update(&mut self, context: &mut AppContext, _delta: f32) {
+    let (panel, head) = (context.panel(), context.input().head());
+    context.update_panel(panel, PerformanceOverlay::placement(head));
+  }
+
+  fn render(&mut self, context: &mut AppContext) {
+    let mut canvas = Canvas::new(context.size(), self.theme.background);
+    self.paint(&mut canvas, context.engine().performance());
+    context.draw(canvas);
+  }
+}
@@ -0,0 +1,355 @@
+mod body;
+mod collision;
+mod grab;
+mod solver;
+
+use body::*;
+use collision::*;
+use grab::*;
+use solver::*;
+
+use crate::gvp_engine::{
+  input::Input,
+  scene::{BodyKind, Collider, Entity, RigidBody, Scene}
+};
+
+use glam::{Mat4, Quat, Vec3};
+
+use std::{collections::HashMap, time::Instant};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhysicsSettings {
+  pub enabled: bool,
+  pub gravity: Vec3,
+  // seconds of simulation per step. steps run at this rate whatever the frame rate is
+  pub timestep: f32,
+  // in one update at most, so a long frame slows the simulation down instead of making the next frame longer too
+  pub max_steps: u32,
+  // passes over the contacts per step, more keeps stacks steadier
+  pub iterations: u32
+}
+
+impl Default for PhysicsSettings {
+  fn default() -> Self {
+    PhysicsSettings {
+      enabled: true,
+      gravity: Vec3::new(0.0, -9.81, 0.0),
+      timestep: 1.0 / 60.0,
+      max_steps: 4,
+      iterations: 20
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+  pub entity: Entity,
+  pub point: Vec3,
+  pub normal: Vec3,
+  pub distance: f32
+}
+
+// rigid bodies of the scene's entities with a collider, stepped at a fixed rate. the scene stays the source of truth:
+// transforms and velocities changed there are picked up, and the simulated poses are written back blended between the
+// last two steps so motion stays smooth at any frame rate
+#[derive(Default)]
+pub struct Physics {
+  pub settings: PhysicsSettings,
+  bodies: Vec<Body>,
+  grabber: Grabber,
+  // of the last step's contacts
+  impulses: Vec<Impulse>,
+  // simulation time not stepped yet
+  accumulator: f32,
+  last_update: Option<Instant>
+}
+
+impl Physics {
+  // bodies slower than this for long enough stop being simulated until something touches them
+  const SLEEP_SPEED: f32 = 0.08;
+  const SLEEP_TIME: f32 = 0.6;
+  // per second, slowing everything a little as air would
+  const LINEAR_DAMPING: f32 = 0.05;
+  const ANGULAR_DAMPING: f32 = 0.1;
+  const MAX_ANGULAR_SPEED: f32 = 50.0;
+
+  pub fn update(&mut self, scene: &mut Scene, input: &Input, blocked: Option<f32>) {
+    let now = Instant::now();
+    let delta = self.last_update.map_or(0.0, |last| (now - last).as_secs_f32());
+    self.last_update = Some(now);
+
+    self.sync(scene);
+
+    if !self.settings.enabled {
+      self.grabber.release();
+      return;
+    }
+
+    let ray = input.pose(Input::POINTER).map(|pose| (pose.w_axis.truncate(), pose.transform_vector3(Vec3::NEG_Z).normalize_or_zero()));
+    self.grabber.update(&mut self.bodies, input, ray, blocked);
+
+    let timestep = self.settings.timestep.max(0.001);
+
+    for _ in 0..self.advance(delta) {
+      self.step(timestep);
+    }
+
+    self.write(scene, self.accumulator / timestep);
+  }
+
+  // forgets every body, for when the scene is replaced
+  pub fn clear(&mut self) {
+    self.bodies.clear();
+    self.impulses.clear();
+    self.grabber.release();
+    self.accumulator = 0.0;
+  }
+
+  // the steps a frame of delta seconds catches up on, leaving the rest of it for the next frame
+  fn advance(&mut self, delta: f32) -> u32 {
+    let timestep = self.settings.timestep.max(0.001);
+    self.accumulator += delta;
+
+    let mut steps = 0;
+    while self.accumulator >= timestep && steps < self.settings.max_steps {
+      self.accumulator -= timestep;
+      steps += 1;
+    }
+
+    // whatever could not be caught up on is dropped
+    self.accumulator = self.accumulator.min(timestep);
+    steps
+  }
+
+  fn cast(bodies: &[Body], origin: Vec3, direction: Vec3, max_distance: f32) -> Option<(usize, RayHit)> {
+    bodies.iter().enumerate().filter_map(|(index, body)| {
+      let (distance, normal) = body.posed().raycast(origin, direction)?;
+      (distance <= max_distance).then_some((index, RayHit { entity: body.entity, point: origin + direction * distance, normal, distance }))
+    }).min_by(|(_, first), (_, second)| first.distance.total_cmp(&second.distance))
+  }
+
+  // brings the bodies up to date with the scene's colliders, adding and dropping them as needed
+  fn sync(&mut self, scene: &Scene) {
+    let count = self.bodies.len();
+    self.bodies.retain(|body| scene.get::<Collider>(body.entity).is_some());
+
+    // whatever rested on a body that went away has to fall again
+    if self.bodies.len() != count {
+      self.bodies.iter_mut().for_each(Body::wake);
+    }
+
+    let indices: HashMap<Entity, usize> = self.bodies.iter().enumerate().map(|(index, body)| (body.entity, index)).collect();
+    let mut added = Vec::new();
+
+    for (entity, collider) in scene.query::<Collider>() {
+      let body = scene.get::<RigidBody>(entity).copied().unwrap_or(RigidBody { kind: BodyKind::Static, ..RigidBody::default() });
+      let local = *scene.transform(entity);
+      let parent = scene.parent(entity).map_or(Mat4::IDENTITY, |parent| scene.world_matrix(parent));
+
+      match indices.get(&entity) {
+        Some(index) => self.bodies[*index].sync(&body, collider, local, parent),
+        None        => added.push(Body::new(entity, &body, collider, local, parent))
+      }
+    }
+
+    self.bodies.append(&mut added);
+  }
+
+  fn write(&mut self, scene: &mut Scene, blend: f32) {
+    for body in &mut self.bodies {
+      let parent = scene.parent(body.entity).map(|parent| scene.world_matrix(parent));
+
+      if let Some(transform) = body.pose(parent, blend) {
+        scene.set_transform(body.entity, transform);
+      }
+
+      // only looked up when changed, as getting it mutably counts as changing the scene
+      if let Some((linear, angular)) = body.velocity() {
+        if let Some(component) = scene.get_mut::<RigidBody>(body.entity) {
+          component.linear_velocity = linear;
+          component.angular_velocity = angular;
+        }
+      }
+    }
+  }
+
+  fn step(&mut self, delta: f32) {
+    let gravity = self.settings.gravity;
+
+    for body in &mut self.bodies {
+      body.previous_position = body.position;
+      body.previous_rotation = body.rotation;
+
+      if body.is_moving() {
+        body.linear = (body.linear + gravity * delta) / (1.0 + delta * Physics::LINEAR_DAMPING);
+        body.angular = (body.angular / (1.0 + delta * Physics::ANGULAR_DAMPING)).clamp_length_max(Physics::MAX_ANGULAR_SPEED);
+      }
+    }
+
+    self.grabber.drive(&mut self.bodies, delta);
+
+    let contacts = self.contacts();
+
+    // a sleeping body is woken by anything moving into it
+    for (a, b, _) in &contacts {
+      for (mover, other) in [(*a, *b), (*b, *a)] {
+        if self.bodies[mover].is_moving() && self.bodies[other].sleeping && self.bodies[mover].linear.length() > Physics::SLEEP_SPEED {
+          self.bodies[other].wake();
+        }
+      }
+    }
+
+    let mut solver = Solver::new(&self.bodies, &contacts, &self.impulses, delta);
+    solver.warm_start(&mut self.bodies);
+
+    for _ in 0..self.settings.iterations {
+      solver.solve(&mut self.bodies);
+    }
+
+    self.impulses = solver.impulses(&self.bodies);
+
+    for body in &mut self.bodies {
+      if body.kind == BodyKind::Static || body.sleeping { continue; }
+
+      body.position += body.linear * delta;
+      body.rotation = (body.rotation + Quat::from_xyzw(body.angular.x, body.angular.y, body.angular.z, 0.0) * body.rotation * (0.5 * delta)).normalize();
+
+      if body.kind != BodyKind::Dynamic { continue; }
+
+      let still = body.linear.length() < Physics::SLEEP_SPEED && body.angular.length() < Physics::SLEEP_SPEED;
+      body.resting = if still { body.resting + delta } else { 0.0 };
+
+      if body.resting > Physics::SLEEP_TIME && self.grabber.held() != Some(body.entity) {
+        body.sleeping = true;
+        body.linear = Vec3::ZERO;
+        body.angular = Vec3::ZERO;
+      }
+    }
+  }
+
+  // pairs whose bounds overlap along x are found by sweeping over the bodies sorted by where they start, then tested
+  // shape against shape. pairs with nothing moving in them are skipped
+  fn contacts(&self) -> Vec<(usize, usize, ContactPoint)> {
+    let posed: Vec<Posed> = self.bodies.iter().map(Body::posed).collect();
+    let bounds: Vec<(Vec3, Vec3)> = posed.iter().map(Posed::bounds).collect();
+
+    let mut order: Vec<usize> = (0..self.bodies.len()).collect();
+    order.sort_by(|first, second| bounds[*first].0.x.total_cmp(&bounds[*second].0.x));
+
+    let mut contacts = Vec::new();
+
+    for (position, &a) in order.iter().enumerate() {
+      for &b in &order[position + 1..] {
+        if bounds[b].0.x > bounds[a].1.x { break; }
+        if !self.bodies[a].is_moving() && !self.bodies[b].is_moving() { continue; }
+
+        let overlapping = bounds[a].0.cmple(bounds[b].1).all() && bounds[b].0.cmple(bounds[a].1).all();
+        if !overlapping { continue; }
+
+        contacts.extend(collide(&posed[a], &posed[b]).into_iter().map(|contact| (a, b, contact)));
+      }
+    }
+
+    contacts
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::gvp_engine::{scene::ColliderShape, transform::Transform};
+
+  fn spawn(scene: &mut Scene, position: Vec3, shape: ColliderShape, body: Option<RigidBody>) -> Entity {
+    let entity = scene.spawn(Transform::from_translation(position));
+    scene.insert(entity, Collider::new(shape));
+
+    if let Some(body) = body {
+      scene.insert(entity, body);
+    }
+
+    entity
+  }
+
+  // a static slab with its top at y = 0 and a ball dropped onto it
+  fn drop_ball(scene: &mut Scene) -> Entity {
+    spawn(scene, Vec3::new(0.0, -0.5, 0.0), ColliderShape::Box { half_extents: Vec3::new(5.0, 0.5, 5.0) }, None);
+    spawn(scene, Vec3::new(0.0, 2.0, 0.0), ColliderShape::Sphere { radius: 0.5 }, Some(RigidBody::default()))
+  }
+
+  #[test]
+  fn steps_at_a_fixed_rate_whatever_the_frame_delta() {
+    let mut physics = Physics { settings: PhysicsSettings { timestep: 0.25, ..PhysicsSettings::default() }, ..Physics::default() };
+
+    assert_eq!(physics.advance(0.625), 2);
+    assert_eq!(physics.accumulator, 0.125);
+    assert_eq!(physics.advance(0.125), 1);
+    assert_eq!(physics.advance(0.125), 0);
+    assert_eq!(physics.advance(0.125), 1);
+    assert_eq!(physics.accumulator, 0.0);
+
+    // a long frame is only caught up on as far as max_steps
+    assert_eq!(physics.advance(10.0), 4);
+    assert_eq!(physics.accumulator, 0.25);
+    assert_eq!(physics.advance(0.0), 1);
+  }
+
+  #[test]
+  fn casts_to_the_nearest_collider_in_reach() {
+    let mut scene = Scene::new();
+    let near = spawn(&mut scene, Vec3::ZERO, ColliderShape::Box { half_extents: Vec3::ONE }, None);
+    spawn(&mut scene, Vec3::new(0.0, 0.0, 5.0), ColliderShape::Sphere { radius: 1.0 }, None);
+
+    let mut physics = Physics::default();
+    physics.sync(&scene);
+
+    let (_, hit) = Physics::cast(&physics.bodies, Vec3::new(0.0, 0.0, -10.0), Vec3::Z, 100.0).unwrap();
+    assert_eq!(hit.entity, near);
+    assert_eq!(hit.distance, 9.0);
+    assert_eq!(hit.point, Vec3::new(0.0, 0.0, -1.0));
+    assert_eq!(hit.normal, Vec3::NEG_Z);
+
+    assert!(Physics::cast(&physics.bodies, Vec3::new(0.0, 0.0, -10.0), Vec3::Z, 8.0).is_none());
+  }
+
+  #[test]
+  fn rests_a_dropped_ball_on_the_ground_and_puts_it_to_sleep() {
+    let mut scene = Scene::new();
+    let ball = drop_ball(&mut scene);
+
+    let mut physics = Physics::default();
+    physics.sync(&scene);
+
+    for _ in 0..300 {
+      physics.step(physics.settings.timestep);
+    }
+
+    let body = physics.bodies.iter().find(|body| body.entity == ball).unwrap();
+    assert!((body.position.y - 0.5).abs() < 0.02, "ball rests at {}", body.position.y);
+    assert!(body.position.x.abs() < 1e-4 && body.position.z.abs() < 1e-4);
+    assert!(body.sleeping);
+
+    physics.write(&mut scene, 1.0);
+    assert!((scene.transform(ball).translation.y - 0.5).abs() < 0.02);
+  }
+
+  #[test]
+  fn leaves_the_scene_unchanged_for_bodies_at_rest() {
+    let mut scene = Scene::new();
+    drop_ball(&mut scene);
+
+    let mut physics = Physics::default();
+    physics.sync(&scene);
+    physics.step(physics.settings.timestep);
+    physics.write(&mut scene, 1.0);
+
+    // the ball's velocity is written once it changes, and only then
+    let revision = scene.revision();
+    physics.write(&mut scene, 1.0);
+    assert_eq!(scene.revision(), revision);
+
+    physics.step(physics.settings.timestep);
+    physics.write(&mut scene, 1.0);
+    assert!(scene.revision() > revision);
+  }
+}
@@ -0,0 +1,350 @@
+use crate::gvp_engine::{
+  scene::{BodyKind, Collider, ColliderShape, Entity, RigidBody},
+  transform::Transform
+};
+
+use glam::{Mat3, Mat4, Quat, Vec3};
+
+// a collider's shape in world units where it is in the world
+#[derive(Clone, Copy, Debug)]
+pub struct Posed {
+  pub shape: ColliderShape,
+  pub center: Vec3,
+  pub rotation: Quat
+}
+
+impl Posed {
+  pub fn axes(&self) -> [Vec3; 3] {
+    let rotation = Mat3::from_quat(self.rotation);
+    [rotation.x_axis, rotation.y_axis, rotation.z_axis]
+  }
+
+  // between the centers of a capsule's caps
+  pub fn segment(&self, half_height: f32) -> (Vec3, Vec3) {
+    let axis = self.rotation * Vec3::Y * half_height;
+    (self.center - axis, self.center + axis)
+  }
+
+  // the world aligned box around the shape, as its min and max corners
+  pub fn bounds(&self) -> (Vec3, Vec3) {
+    let reach = match self.shape {
+      ColliderShape::Sphere { radius }              => Vec3::splat(radius),
+      ColliderShape::Box { half_extents }           => {
+        let rotation = Mat3::from_quat(self.rotation);
+        rotation.x_axis.abs() * half_extents.x + rotation.y_axis.abs() * half_extents.y + rotation.z_axis.abs() * half_extents.z
+      }
+      ColliderShape::Capsule { radius, half_height } => (self.rotation * Vec3::Y * half_height).abs() + radius
+    };
+
+    (self.center - reach, self.center + reach)
+  }
+
+  // the distance along a normalized direction to where the ray enters the shape, and the surface normal there. rays
+  // starting inside miss
+  pub fn raycast(&self, origin: Vec3, direction: Vec3) -> Option<(f32, Vec3)> {
+    let to_local = self.rotation.inverse();
+    let (origin, direction) = (to_local * (origin - self.center), to_local * direction);
+
+    let (distance, normal) = match self.shape {
+      ColliderShape::Sphere { radius }               => {
+        let distance = ray_sphere(origin, direction, Vec3::ZERO, radius)?;
+        (distance, (origin + direction * distance).normalize_or_zero())
+      }
+      ColliderShape::Box { half_extents }            => ray_box(origin, direction, half_extents)?,
+      ColliderShape::Capsule { radius, half_height } => ray_capsule(origin, direction, radius, half_height)?
+    };
+
+    Some((distance, self.rotation * normal))
+  }
+}
+
+fn ray_sphere(origin: Vec3, direction: Vec3, center: Vec3, radius: f32) -> Option<f32> {
+  let offset = origin - center;
+  let b = offset.dot(direction);
+  let c = offset.length_squared() - radius * radius;
+
+  if c > 0.0 && b > 0.0 { return None; }
+
+  let discriminant = b * b - c;
+  if discriminant < 0.0 { return None; }
+
+  let distance = -b - discriminant.sqrt();
+  (distance >= 0.0).then_some(distance)
+}
+
+// slabs along each axis, the last one entered is the face hit
+fn ray_box(origin: Vec3, direction: Vec3, half_extents: Vec3) -> Option<(f32, Vec3)> {
+  let (mut near, mut far) = (f32::NEG_INFINITY, f32::INFINITY);
+  let mut normal = Vec3::ZERO;
+
+  for axis in 0..3 {
+    if direction[axis].abs() < 1e-8 {
+      if origin[axis].abs() > half_extents[axis] { return None; }
+      continue;
+    }
+
+    let first = (-half_extents[axis] - origin[axis]) / direction[axis];
+    let second = (half_extents[axis] - origin[axis]) / direction[axis];
+    let (enter, exit, sign) = if first < second { (first, second, -1.0) } else { (second, first, 1.0) };
+
+    if enter > near {
+      near = enter;
+      normal = Vec3::ZERO;
+      normal[axis] = sign;
+    }
+
+    far = far.min(exit);
+    if near > far { return None; }
+  }
+
+  (near >= 0.0).then_some((near, normal))
+}
+
+// the side of the cylinder between the caps, then the caps themselves
+fn ray_capsule(origin: Vec3, direction: Vec3, radius: f32, half_height: f32) -> Option<(f32, Vec3)> {
+  let mut hit: Option<(f32, Vec3)> = None;
+  let a = direction.x * direction.x + direction.z * direction.z;
+
+  if a > 1e-8 {
+    let b = origin.x * direction.x + origin.z * direction.z;
+    let c = origin.x * origin.x + origin.z * origin.z - radius * radius;
+    let discriminant = b * b - a * c;
+
+    if discriminant >= 0.0 {
+      let distance = (-b - discriminant.sqrt()) / a;
+      let point = origin + direction * distance;
+
+      if distance >= 0.0 && point.y.abs() <= half_height {
+        hit = Some((distance, Vec3::new(point.x, 0.0, point.z).normalize_or_zero()));
+      }
+    }
+  }
+
+  for cap in [Vec3::new(0.0, half_height, 0.0), Vec3::new(0.0, -half_height, 0.0)] {
+    if let Some(distance) = ray_sphere(origin, direction, cap, radius) {
+      if hit.is_none_or(|(nearest, _)| distance < nearest) {
+        hit = Some((distance, (origin + direction * distance - cap).normalize_or_zero()));
+      }
+    }
+  }
+
+  hit
+}
+
+// a rigid body as the simulation sees it, in world space with its center of mass at the entity's origin
+pub struct Body {
+  pub entity: Entity,
+  pub kind: BodyKind,
+  pub inverse_mass: f32,
+  // about the body's own axes
+  pub inverse_inertia: Vec3,
+  pub position: Vec3,
+  pub rotation: Quat,
+  // where the last step started, the pose written to the scene is blended from it
+  pub previous_position: Vec3,
+  pub previous_rotation: Quat,
+  pub linear: Vec3,
+  pub angular: Vec3,
+  // scaled to the world along with its offset
+  pub collider: Collider,
+  pub scale: Vec3,
+  pub sleeping: bool,
+  // seconds the body has been nearly still
+  pub resting: f32,
+  // what was last read from or written to the scene, anything else found there was changed from outside
+  written: Transform,
+  written_velocity: (Vec3, Vec3)
+}
+
+impl Body {
+  pub fn new(entity: Entity, body: &RigidBody, collider: &Collider, local: Transform, parent: Mat4) -> Self {
+    let mut new = Body {
+      entity,
+      kind: body.kind,
+      inverse_mass: 0.0,
+      inverse_inertia: Vec3::ZERO,
+      position: Vec3::ZERO,
+      rotation: Quat::IDENTITY,
+      previous_position: Vec3::ZERO,
+      previous_rotation: Quat::IDENTITY,
+      linear: body.linear_velocity,
+      angular: body.angular_velocity,
+      collider: *collider,
+      scale: Vec3::ONE,
+      sleeping: false,
+      resting: 0.0,
+      written: local,
+      written_velocity: (body.linear_velocity, body.angular_velocity)
+    };
+
+    new.teleport(local, parent);
+    new.shape(body, collider);
+
+    new
+  }
+
+  // takes on whatever was changed in the scene since the body last wrote to it
+  pub fn sync(&mut self, body: &RigidBody, collider: &Collider, local: Transform, parent: Mat4) {
+    if local != self.written {
+      self.teleport(local, parent);
+      self.written = local;
+      self.wake();
+    }
+
+    if (body.linear_velocity, body.angular_velocity) != self.written_velocity {
+      self.linear = body.linear_velocity;
+      self.angular = body.angular_velocity;
+      self.written_velocity = (body.linear_velocity, body.angular_velocity);
+      self.wake();
+    }
+
+    if body.kind != self.kind {
+      self.kind = body.kind;
+      self.wake();
+    }
+
+    self.shape(body, collider);
+  }
+
+  // the pose part of the way from the start of the last step to its end, when it changed since it was last written
+  pub fn pose(&mut self, parent: Option<Mat4>, blend: f32) -> Option<Transform> {
+    let position = self.previous_position.lerp(self.position, blend);
+    let rotation = self.previous_rotation.slerp(self.rotation, blend);
+
+    let local = match parent {
+      Some(parent) => Transform::from_matrix(&(parent.inverse() * Mat4::from_scale_rotation_translation(self.scale, rotation, position))),
+      None         => Transform::new(position, rotation, self.scale)
+    };
+
+    if local == self.written { return None; }
+
+    self.written = local;
+    Some(local)
+  }
+
+  // when they changed since they were last written
+  pub fn velocity(&mut self) -> Option<(Vec3, Vec3)> {
+    if self.kind != BodyKind::Dynamic || (self.linear, self.angular) == self.written_velocity { return None; }
+
+    self.written_velocity = (self.linear, self.angular);
+    Some(self.written_velocity)
+  }
+
+  pub fn posed(&self) -> Posed {
+    Posed { shape: self.collider.shape, center: self.position + self.rotation * self.collider.offset, rotation: self.rotation }
+  }
+
+  // dynamic and awake, the only bodies the solver pushes around
+  pub fn is_moving(&self) -> bool {
+    self.kind == BodyKind::Dynamic && !self.sleeping
+  }
+
+  pub fn wake(&mut self) {
+    self.sleeping = false;
+    self.resting = 0.0;
+  }
+
+  // zero for everything but moving bodies, which is what keeps the rest in place
+  pub fn moving_inverse_mass(&self) -> f32 {
+    if self.is_moving() { self.inverse_mass } else { 0.0 }
+  }
+
+  pub fn moving_inverse_inertia(&self) -> Mat3 {
+    if !self.is_moving() { return Mat3::ZERO; }
+
+    let rotation = Mat3::from_quat(self.rotation);
+    rotation * Mat3::from_diagonal(self.inverse_inertia) * rotation.transpose()
+  }
+
+  fn teleport(&mut self, local: Transform, parent: Mat4) {
+    let (scale, rotation, position) = (parent * local.matrix()).to_scale_rotation_translation();
+
+    self.position = position;
+    self.rotation = rotation;
+    self.previous_position = position;
+    self.previous_rotation = rotation;
+    self.scale = scale;
+  }
+
+  // the collider scaled to the world and the mass spread over it as a solid
+  fn shape(&mut self, body: &RigidBody, collider: &Collider) {
+    let scale = self.scale.abs();
+
+    let shape = match collider.shape {
+      ColliderShape::Sphere { radius }               => ColliderShape::Sphere { radius: radius * scale.max_element() },
+      ColliderShape::Box { half_extents }            => ColliderShape::Box { half_extents: half_extents * scale },
+      ColliderShape::Capsule { radius, half_height } => {
+        ColliderShape::Capsule { radius: radius * scale.x.max(scale.z), half_height: half_height * scale.y }
+      }
+    };
+
+    self.collider = Collider { shape, offset: collider.offset * self.scale, ..*collider };
+
+    if body.kind != BodyKind::Dynamic {
+      self.inverse_mass = 0.0;
+      self.inverse_inertia = Vec3::ZERO;
+      return;
+    }
+
+    let mass = body.mass.max(0.001);
+
+    // capsules are taken as the box around them
+    let inertia = match shape {
+      ColliderShape::Sphere { radius }               => Vec3::splat(0.4 * mass * radius * radius),
+      ColliderShape::Box { half_extents }            => Body::box_inertia(mass, half_extents),
+      ColliderShape::Capsule { radius, half_height } => Body::box_inertia(mass, Vec3::new(radius, half_height + radius, radius))
+    };
+
+    self.inverse_mass = 1.0 / mass;
+    self.inverse_inertia = inertia.max(Vec3::splat(1e-6)).recip();
+  }
+
+  fn box_inertia(mass: f32, half_extents: Vec3) -> Vec3 {
+    let squared = half_extents * half_extents;
+    Vec3::new(squared.y + squared.z, squared.x + squared.z, squared.x + squared.y) * mass / 3.0
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn hits_a_sphere_where_the_ray_enters_it() {
+    assert_eq!(ray_sphere(Vec3::new(0.0, 0.0, -5.0), Vec3::Z, Vec3::ZERO, 1.0), Some(4.0));
+    assert_eq!(ray_sphere(Vec3::new(2.0, 0.0, -5.0), Vec3::Z, Vec3::ZERO, 1.0), None);
+    assert_eq!(ray_sphere(Vec3::new(0.0, 0.0, 5.0), Vec3::Z, Vec3::ZERO, 1.0), None);
+    assert_eq!(ray_sphere(Vec3::ZERO, Vec3::Z, Vec3::ZERO, 1.0), None);
+  }
+
+  #[test]
+  fn hits_a_box_on_the_face_entered_last() {
+    let half_extents = Vec3::new(1.0, 2.0, 1.0);
+
+    assert_eq!(ray_box(Vec3::new(-5.0, 0.0, 0.0), Vec3::X, half_extents), Some((4.0, Vec3::NEG_X)));
+    assert_eq!(ray_box(Vec3::new(0.0, 5.0, 0.0), Vec3::NEG_Y, half_extents), Some((3.0, Vec3::Y)));
+    assert_eq!(ray_box(Vec3::new(-5.0, 3.0, 0.0), Vec3::X, half_extents), None);
+    assert_eq!(ray_box(Vec3::ZERO, Vec3::X, half_extents), None);
+  }
+
+  #[test]
+  fn hits_a_capsule_on_its_side_or_its_caps() {
+    assert_eq!(ray_capsule(Vec3::new(-5.0, 0.5, 0.0), Vec3::X, 0.5, 1.0), Some((4.5, Vec3::NEG_X)));
+    assert_eq!(ray_capsule(Vec3::new(0.0, 5.0, 0.0), Vec3::NEG_Y, 0.5, 1.0), Some((3.5, Vec3::Y)));
+    assert_eq!(ray_capsule(Vec3::new(0.0, -5.0, 0.0), Vec3::Y, 0.5, 1.0), Some((3.5, Vec3::NEG_Y)));
+    assert_eq!(ray_capsule(Vec3::new(-5.0, 0.0, 1.0), Vec3::X, 0.5, 1.0), None);
+  }
+
+  #[test]
+  fn casts_rays_in_the_shapes_own_space() {
+    let posed = Posed {
+      shape: ColliderShape::Box { half_extents: Vec3::new(2.0, 0.5, 0.5) },
+      center: Vec3::new(0.0, 1.0, 0.0),
+      rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)
+    };
+
+    let (distance, normal) = posed.raycast(Vec3::new(0.0, -5.0, 0.0), Vec3::Y).unwrap();
+    assert!((distance - 4.0).abs() < 1e-5);
+    assert!(normal.abs_diff_eq(Vec3::NEG_Y, 1e-5));
+  }
+}
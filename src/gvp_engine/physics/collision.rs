@@ -0,0 +1,334 @@
+use crate::gvp_engine::scene::ColliderShape;
+
+use super::Posed;
+
+use glam::Vec3;
+
+// where two shapes touch, with the normal pointing from the first shape into the second
+#[derive(Clone, Copy, Debug)]
+pub struct ContactPoint {
+  pub point: Vec3,
+  pub normal: Vec3,
+  pub depth: f32
+}
+
+enum Feature {
+  FaceA(usize),
+  FaceB(usize),
+  Edges(usize, usize)
+}
+
+// every point the shapes overlap at, none when they are apart
+pub fn collide(a: &Posed, b: &Posed) -> Vec<ContactPoint> {
+  match (a.shape, b.shape) {
+    (ColliderShape::Sphere { radius: first }, ColliderShape::Sphere { radius: second }) => {
+      spheres(a.center, first, b.center, second).into_iter().collect()
+    }
+    (ColliderShape::Box { half_extents }, ColliderShape::Sphere { radius })             => {
+      box_sphere(a, half_extents, b.center, radius).into_iter().collect()
+    }
+    (ColliderShape::Box { half_extents: first }, ColliderShape::Box { half_extents: second }) => boxes(a, first, b, second),
+    (ColliderShape::Capsule { radius, half_height }, ColliderShape::Sphere { radius: second }) => {
+      let (start, end) = a.segment(half_height);
+      spheres(closest_on_segment(start, end, b.center), radius, b.center, second).into_iter().collect()
+    }
+    (ColliderShape::Capsule { radius: first, half_height: first_height }, ColliderShape::Capsule { radius: second, half_height: second_height }) => {
+      let (first_start, first_end) = a.segment(first_height);
+      let (second_start, second_end) = b.segment(second_height);
+      let (on_first, on_second) = closest_between_segments(first_start, first_end, second_start, second_end);
+
+      spheres(on_first, first, on_second, second).into_iter().collect()
+    }
+    // the capsule's ends and the point of it nearest the box's center, which covers it lying on a face or an edge
+    (ColliderShape::Box { half_extents }, ColliderShape::Capsule { radius, half_height }) => {
+      let (start, end) = b.segment(half_height);
+
+      [start, end, closest_on_segment(start, end, a.center)].into_iter()
+        .filter_map(|center| box_sphere(a, half_extents, center, radius))
+        .collect()
+    }
+    _                                                                                  => flip(collide(b, a))
+  }
+}
+
+fn flip(contacts: Vec<ContactPoint>) -> Vec<ContactPoint> {
+  contacts.into_iter().map(|contact| ContactPoint { normal: -contact.normal, ..contact }).collect()
+}
+
+fn spheres(first: Vec3, first_radius: f32, second: Vec3, second_radius: f32) -> Option<ContactPoint> {
+  let offset = second - first;
+  let distance = offset.length();
+  let depth = first_radius + second_radius - distance;
+
+  if depth <= 0.0 { return None; }
+
+  // straight up when the centers are on top of each other
+  let normal = if distance > 1e-6 { offset / distance } else { Vec3::Y };
+  Some(ContactPoint { point: first + normal * (first_radius - depth * 0.5), normal, depth })
+}
+
+// the normal points from the box into the sphere
+fn box_sphere(posed: &Posed, half_extents: Vec3, center: Vec3, radius: f32) -> Option<ContactPoint> {
+  let local = posed.rotation.inverse() * (center - posed.center);
+  let closest = local.clamp(-half_extents, half_extents);
+
+  // a center inside the box is pushed out through the nearest face
+  if closest == local {
+    let room = half_extents - local.abs();
+    let axis = if room.x < room.y && room.x < room.z { 0 } else if room.y < room.z { 1 } else { 2 };
+
+    let mut normal = Vec3::ZERO;
+    normal[axis] = if local[axis] < 0.0 { -1.0 } else { 1.0 };
+
+    return Some(ContactPoint { point: center, normal: posed.rotation * normal, depth: radius + room[axis] });
+  }
+
+  let offset = local - closest;
+  let distance = offset.length();
+  if distance >= radius { return None; }
+
+  let normal = posed.rotation * (offset / distance);
+  let depth = radius - distance;
+
+  Some(ContactPoint { point: posed.center + posed.rotation * closest - normal * depth * 0.5, normal, depth })
+}
+
+// separating axes, face against face clipped to the reference face's sides or the nearest points of two edges
+fn boxes(a: &Posed, first: Vec3, b: &Posed, second: Vec3) -> Vec<ContactPoint> {
+  // edges and the second box's faces only win over what was found before when they separate clearly more, so resting
+  // contact does not flicker between features
+  const RELATIVE_TOLERANCE: f32 = 0.95;
+  const ABSOLUTE_TOLERANCE: f32 = 0.001;
+
+  let (axes_a, axes_b) = (a.axes(), b.axes());
+  let offset = b.center - a.center;
+  let reach = |axes: &[Vec3; 3], half_extents: Vec3, axis: Vec3| (0..3).map(|index| half_extents[index] * axes[index].dot(axis).abs()).sum::<f32>();
+
+  let mut best: Option<(f32, Vec3, Feature)> = None;
+  let mut candidates: Vec<(Vec3, Feature)> = Vec::with_capacity(15);
+  candidates.extend((0..3).map(|index| (axes_a[index], Feature::FaceA(index))));
+  candidates.extend((0..3).map(|index| (axes_b[index], Feature::FaceB(index))));
+  candidates.extend((0..9).map(|index| (axes_a[index / 3].cross(axes_b[index % 3]), Feature::Edges(index / 3, index % 3))));
+
+  for (axis, feature) in candidates {
+    // parallel edges have no axis of their own, the faces cover them
+    let length = axis.length();
+    if length < 1e-4 { continue; }
+
+    let axis = axis / length;
+    let separation = offset.dot(axis).abs() - reach(&axes_a, first, axis) - reach(&axes_b, second, axis);
+    if separation > 0.0 { return Vec::new(); }
+
+    let better = match (&best, &feature) {
+      (None, _)                               => true,
+      (Some((best, _, _)), Feature::FaceA(_)) => separation > *best,
+      (Some((best, _, _)), _)                 => separation > *best * RELATIVE_TOLERANCE + ABSOLUTE_TOLERANCE
+    };
+
+    if better {
+      let axis = if offset.dot(axis) < 0.0 { -axis } else { axis };
+      best = Some((separation, axis, feature));
+    }
+  }
+
+  let Some((separation, normal, feature)) = best else { return Vec::new(); };
+
+  match feature {
+    Feature::FaceA(axis) => clip_face(a, first, axis, normal, b, second).into_iter()
+      .map(|(point, depth)| ContactPoint { point: point + normal * depth * 0.5, normal, depth })
+      .collect(),
+    Feature::FaceB(axis) => clip_face(b, second, axis, -normal, a, first).into_iter()
+      .map(|(point, depth)| ContactPoint { point: point - normal * depth * 0.5, normal, depth })
+      .collect(),
+    Feature::Edges(edge_a, edge_b) => {
+      // the edge of each box furthest towards the other
+      let mut on_a = a.center;
+      let mut on_b = b.center;
+
+      for index in (0..3).filter(|index| *index != edge_a) {
+        on_a += axes_a[index] * first[index] * axes_a[index].dot(normal).signum();
+      }
+
+      for index in (0..3).filter(|index| *index != edge_b) {
+        on_b -= axes_b[index] * second[index] * axes_b[index].dot(normal).signum();
+      }
+
+      let (along_a, along_b) = (axes_a[edge_a] * first[edge_a], axes_b[edge_b] * second[edge_b]);
+      let (point_a, point_b) = closest_between_segments(on_a - along_a, on_a + along_a, on_b - along_b, on_b + along_b);
+
+      vec![ContactPoint { point: (point_a + point_b) * 0.5, normal, depth: -separation }]
+    }
+  }
+}
+
+// the incident box's face turned most against the reference face, cut down to the reference face's sides. points
+// are left on the incident face along with how far they reach past the reference face
+fn clip_face(reference: &Posed, reference_extents: Vec3, axis: usize, normal: Vec3, incident: &Posed, incident_extents: Vec3) -> Vec<(Vec3, f32)> {
+  let reference_axes = reference.axes();
+  let incident_axes = incident.axes();
+
+  let face = (0..3).max_by(|first, second| incident_axes[*first].dot(normal).abs().total_cmp(&incident_axes[*second].dot(normal).abs())).unwrap_or(0);
+  let facing = incident_axes[face] * -incident_axes[face].dot(normal).signum();
+  let center = incident.center + facing * incident_extents[face];
+
+  let u = incident_axes[(face + 1) % 3] * incident_extents[(face + 1) % 3];
+  let v = incident_axes[(face + 2) % 3] * incident_extents[(face + 2) % 3];
+  let mut polygon = vec![center + u + v, center - u + v, center - u - v, center + u - v];
+
+  for side in [(axis + 1) % 3, (axis + 2) % 3] {
+    for sign in [1.0, -1.0] {
+      let plane = reference_axes[side] * sign;
+      polygon = clip(&polygon, plane, plane.dot(reference.center) + reference_extents[side]);
+    }
+  }
+
+  let surface = normal.dot(reference.center) + reference_extents[axis];
+
+  polygon.into_iter().filter_map(|point| {
+    let depth = surface - normal.dot(point);
+    (depth >= 0.0).then_some((point, depth))
+  }).collect()
+}
+
+// keeps the part of a polygon behind a plane
+fn clip(polygon: &[Vec3], normal: Vec3, offset: f32) -> Vec<Vec3> {
+  let mut clipped = Vec::with_capacity(polygon.len() + 1);
+
+  for (index, current) in polygon.iter().enumerate() {
+    let next = polygon[(index + 1) % polygon.len()];
+    let (current_side, next_side) = (normal.dot(*current) - offset, normal.dot(next) - offset);
+
+    if current_side <= 0.0 {
+      clipped.push(*current);
+    }
+
+    if (current_side <= 0.0) != (next_side <= 0.0) {
+      clipped.push(current.lerp(next, current_side / (current_side - next_side)));
+    }
+  }
+
+  clipped
+}
+
+fn closest_on_segment(start: Vec3, end: Vec3, point: Vec3) -> Vec3 {
+  let along = end - start;
+  let length = along.length_squared();
+  if length < 1e-12 { return start; }
+
+  start + along * ((point - start).dot(along) / length).clamp(0.0, 1.0)
+}
+
+// the nearest points of two segments to each other
+fn closest_between_segments(first_start: Vec3, first_end: Vec3, second_start: Vec3, second_end: Vec3) -> (Vec3, Vec3) {
+  let (first, second) = (first_end - first_start, second_end - second_start);
+  let between = first_start - second_start;
+  let (a, e, f) = (first.length_squared(), second.length_squared(), second.dot(between));
+
+  if a < 1e-12 && e < 1e-12 { return (first_start, second_start); }
+  if a < 1e-12 { return (first_start, closest_on_segment(second_start, second_end, first_start)); }
+
+  let c = first.dot(between);
+  if e < 1e-12 { return (closest_on_segment(first_start, first_end, second_start), second_start); }
+
+  let b = first.dot(second);
+  let denominator = a * e - b * b;
+
+  // parallel segments take any pair of points, starting from the first segment's start
+  let mut s = if denominator > 1e-12 { ((b * f - c * e) / denominator).clamp(0.0, 1.0) } else { 0.0 };
+  let mut t = (b * s + f) / e;
+
+  if t < 0.0 {
+    t = 0.0;
+    s = (-c / a).clamp(0.0, 1.0);
+  }
+  else if t > 1.0 {
+    t = 1.0;
+    s = ((b - c) / a).clamp(0.0, 1.0);
+  }
+
+  (first_start + first * s, second_start + second * t)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use glam::Quat;
+
+  fn posed(shape: ColliderShape, center: Vec3) -> Posed {
+    Posed { shape, center, rotation: Quat::IDENTITY }
+  }
+
+  #[test]
+  fn touches_overlapping_spheres_halfway_into_the_overlap() {
+    let a = posed(ColliderShape::Sphere { radius: 1.0 }, Vec3::ZERO);
+    let b = posed(ColliderShape::Sphere { radius: 1.0 }, Vec3::new(1.5, 0.0, 0.0));
+
+    let contacts = collide(&a, &b);
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].normal, Vec3::X);
+    assert_eq!(contacts[0].depth, 0.5);
+    assert_eq!(contacts[0].point, Vec3::new(0.75, 0.0, 0.0));
+
+    let apart = posed(ColliderShape::Sphere { radius: 1.0 }, Vec3::new(2.5, 0.0, 0.0));
+    assert!(collide(&a, &apart).is_empty());
+  }
+
+  #[test]
+  fn points_the_normal_from_the_first_shape_into_the_second() {
+    let floor = posed(ColliderShape::Box { half_extents: Vec3::ONE }, Vec3::ZERO);
+    let ball = posed(ColliderShape::Sphere { radius: 0.5 }, Vec3::new(0.0, 1.25, 0.0));
+
+    let contacts = collide(&floor, &ball);
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].normal, Vec3::Y);
+    assert_eq!(contacts[0].depth, 0.25);
+
+    let flipped = collide(&ball, &floor);
+    assert_eq!(flipped.len(), 1);
+    assert_eq!(flipped[0].normal, Vec3::NEG_Y);
+  }
+
+  #[test]
+  fn pushes_a_sphere_inside_a_box_out_of_its_nearest_face() {
+    let floor = posed(ColliderShape::Box { half_extents: Vec3::ONE }, Vec3::ZERO);
+    let ball = posed(ColliderShape::Sphere { radius: 0.5 }, Vec3::new(0.0, 0.0, -0.75));
+
+    let contacts = collide(&floor, &ball);
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].normal, Vec3::NEG_Z);
+    assert_eq!(contacts[0].depth, 0.75);
+  }
+
+  #[test]
+  fn touches_a_box_resting_on_another_at_its_four_corners() {
+    let floor = posed(ColliderShape::Box { half_extents: Vec3::new(2.0, 0.5, 2.0) }, Vec3::ZERO);
+    let block = posed(ColliderShape::Box { half_extents: Vec3::splat(0.5) }, Vec3::new(0.0, 0.9, 0.0));
+
+    let contacts = collide(&floor, &block);
+    assert_eq!(contacts.len(), 4);
+
+    for contact in contacts {
+      assert!(contact.normal.abs_diff_eq(Vec3::Y, 1e-5));
+      assert!((contact.depth - 0.1).abs() < 1e-5);
+      assert!((contact.point.x.abs() - 0.5).abs() < 1e-5 && (contact.point.z.abs() - 0.5).abs() < 1e-5);
+    }
+
+    let lifted = posed(ColliderShape::Box { half_extents: Vec3::splat(0.5) }, Vec3::new(0.0, 1.1, 0.0));
+    assert!(collide(&floor, &lifted).is_empty());
+  }
+
+  #[test]
+  fn touches_capsules_at_the_nearest_points_of_their_segments() {
+    let upright = posed(ColliderShape::Capsule { radius: 0.5, half_height: 1.0 }, Vec3::ZERO);
+    let lying = Posed {
+      rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+      ..posed(ColliderShape::Capsule { radius: 0.5, half_height: 1.0 }, Vec3::new(0.0, 0.5, 0.75))
+    };
+
+    let contacts = collide(&upright, &lying);
+    assert_eq!(contacts.len(), 1);
+    assert!(contacts[0].normal.abs_diff_eq(Vec3::Z, 1e-5));
+    assert!((contacts[0].depth - 0.25).abs() < 1e-5);
+  }
+}
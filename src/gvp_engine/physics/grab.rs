@@ -0,0 +1,85 @@
+use crate::gvp_engine::{
+  input::Input,
+  scene::{BodyKind, Entity}
+};
+
+use super::{Body, Physics};
+
+use glam::Vec3;
+
+struct Held {
+  entity: Entity,
+  // where the body was grabbed, in its own space
+  anchor: Vec3,
+  // along the pointer, kept while the body is held
+  distance: f32,
+  target: Vec3
+}
+
+// picks dynamic bodies up at the pointer while the grab action is held and lets go of them with the velocity they
+// were being moved at, which is what throws them
+#[derive(Default)]
+pub struct Grabber {
+  held: Option<Held>
+}
+
+impl Grabber {
+  // share of the distance to the pointer closed every step
+  const STIFFNESS: f32 = 0.4;
+  // kept every step, so held bodies stop spinning
+  const ANGULAR_DAMPING: f32 = 0.9;
+  const MAX_SPEED: f32 = 20.0;
+  const MAX_DISTANCE: f32 = 20.0;
+
+  pub fn held(&self) -> Option<Entity> {
+    self.held.as_ref().map(|held| held.entity)
+  }
+
+  pub fn release(&mut self) {
+    self.held = None;
+  }
+
+  // the pointer is a ray, blocked by whatever panel it hits first
+  pub fn update(&mut self, bodies: &mut [Body], input: &Input, ray: Option<(Vec3, Vec3)>, blocked: Option<f32>) {
+    let gone = self.held.as_ref().is_some_and(|held| !bodies.iter().any(|body| body.entity == held.entity));
+
+    if input.released(Input::GRAB) || ray.is_none() || gone {
+      self.held = None;
+    }
+
+    let Some((origin, direction)) = ray else { return; };
+
+    if input.pressed(Input::GRAB) {
+      let reach = blocked.unwrap_or(Grabber::MAX_DISTANCE).min(Grabber::MAX_DISTANCE);
+      let hit = Physics::cast(bodies, origin, direction, reach).filter(|(index, _)| bodies[*index].kind == BodyKind::Dynamic);
+
+      if let Some((index, hit)) = hit {
+        let body = &mut bodies[index];
+        body.wake();
+
+        self.held = Some(Held {
+          entity: body.entity,
+          anchor: body.rotation.inverse() * (hit.point - body.position),
+          distance: hit.distance,
+          target: hit.point
+        });
+      }
+    }
+
+    if let Some(held) = &mut self.held {
+      held.target = origin + direction * held.distance;
+    }
+  }
+
+  // steers the held body towards the pointer each step, before contacts so they can still push it back
+  pub fn drive(&self, bodies: &mut [Body], delta: f32) {
+    let Some(held) = &self.held else { return; };
+    let Some(body) = bodies.iter_mut().find(|body| body.entity == held.entity) else { return; };
+
+    let anchor = body.position + body.rotation * held.anchor;
+
+    body.linear = ((held.target - anchor) * Grabber::STIFFNESS / delta).clamp_length_max(Grabber::MAX_SPEED);
+    body.angular *= Grabber::ANGULAR_DAMPING;
+    body.wake();
+  }
+}
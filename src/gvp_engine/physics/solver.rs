@@ -0,0 +1,175 @@
+use crate::gvp_engine::scene::Entity;
+
+use super::{Body, ContactPoint};
+
+use glam::{Mat3, Vec3};
+
+use std::collections::HashMap;
+
+// what a contact pushed with by the end of a step. a contact found again near the same point starts the next step
+// from it, which is what lets stacks come to rest
+#[derive(Clone, Copy, Debug)]
+pub struct Impulse {
+  a: Entity,
+  b: Entity,
+  point: Vec3,
+  normal: f32,
+  friction: Vec3
+}
+
+// a contact point between two bodies as a constraint on their velocities
+struct Constraint {
+  a: usize,
+  b: usize,
+  // from each body's center to the point
+  offset_a: Vec3,
+  offset_b: Vec3,
+  normal: Vec3,
+  tangents: [Vec3; 2],
+  normal_mass: f32,
+  tangent_mass: [f32; 2],
+  // the normal velocity the contact aims for, to bounce back or to push out of the overlap
+  bias: f32,
+  friction: f32,
+  // impulses applied so far this step, clamped as a whole rather than one iteration at a time
+  normal_impulse: f32,
+  tangent_impulse: [f32; 2]
+}
+
+// sequential impulses over every contact of a step
+pub struct Solver {
+  constraints: Vec<Constraint>,
+  inverse_masses: Vec<f32>,
+  inverse_inertias: Vec<Mat3>
+}
+
+impl Solver {
+  // contacts this close to one of the last step are taken as the same one
+  const MATCH_DISTANCE: f32 = 0.02;
+  // fraction of the overlap pushed out per second of a step
+  const BAUMGARTE: f32 = 0.2;
+  // overlap left alone so resting contacts stay touching
+  const SLOP: f32 = 0.005;
+  // slower impacts do not bounce
+  const BOUNCE_SPEED: f32 = 1.0;
+
+  pub fn new(bodies: &[Body], contacts: &[(usize, usize, ContactPoint)], previous: &[Impulse], delta: f32) -> Self {
+    let inverse_masses: Vec<f32> = bodies.iter().map(Body::moving_inverse_mass).collect();
+    let inverse_inertias: Vec<Mat3> = bodies.iter().map(Body::moving_inverse_inertia).collect();
+
+    let mut pairs = HashMap::<(Entity, Entity), Vec<&Impulse>>::new();
+    for impulse in previous {
+      pairs.entry((impulse.a, impulse.b)).or_default().push(impulse);
+    }
+
+    let constraints = contacts.iter().map(|(a, b, contact)| {
+      let (first, second) = (&bodies[*a], &bodies[*b]);
+      let offset_a = contact.point - first.position;
+      let offset_b = contact.point - second.position;
+      let tangents: [Vec3; 2] = contact.normal.any_orthonormal_pair().into();
+
+      let mass = |direction: Vec3| {
+        let angular_a = (inverse_inertias[*a] * offset_a.cross(direction)).cross(offset_a);
+        let angular_b = (inverse_inertias[*b] * offset_b.cross(direction)).cross(offset_b);
+        let inverse = inverse_masses[*a] + inverse_masses[*b] + direction.dot(angular_a + angular_b);
+
+        if inverse > 0.0 { 1.0 / inverse } else { 0.0 }
+      };
+
+      let relative = Solver::relative_velocity(first, second, offset_a, offset_b).dot(contact.normal);
+      let push = Solver::BAUMGARTE / delta * (contact.depth - Solver::SLOP).max(0.0);
+      let restitution = first.collider.restitution.max(second.collider.restitution);
+      let bounce = if relative < -Solver::BOUNCE_SPEED { -restitution * relative } else { 0.0 };
+
+      let last = pairs.get(&(first.entity, second.entity))
+        .and_then(|impulses| impulses.iter().find(|impulse| impulse.point.distance(contact.point) < Solver::MATCH_DISTANCE));
+
+      Constraint {
+        a: *a,
+        b: *b,
+        offset_a,
+        offset_b,
+        normal: contact.normal,
+        tangents,
+        normal_mass: mass(contact.normal),
+        tangent_mass: [mass(tangents[0]), mass(tangents[1])],
+        bias: push.max(bounce),
+        friction: (first.collider.friction * second.collider.friction).sqrt(),
+        normal_impulse: last.map_or(0.0, |last| last.normal),
+        tangent_impulse: last.map_or([0.0; 2], |last| [last.friction.dot(tangents[0]), last.friction.dot(tangents[1])])
+      }
+    }).collect();
+
+    Solver { constraints, inverse_masses, inverse_inertias }
+  }
+
+  // applies what the contacts carried over from the last step before solving starts
+  pub fn warm_start(&self, bodies: &mut [Body]) {
+    for (index, constraint) in self.constraints.iter().enumerate() {
+      let impulse = constraint.normal * constraint.normal_impulse
+        + constraint.tangents[0] * constraint.tangent_impulse[0]
+        + constraint.tangents[1] * constraint.tangent_impulse[1];
+
+      self.apply(bodies, index, impulse);
+    }
+  }
+
+  // to start the next step from
+  pub fn impulses(&self, bodies: &[Body]) -> Vec<Impulse> {
+    self.constraints.iter().map(|constraint| Impulse {
+      a: bodies[constraint.a].entity,
+      b: bodies[constraint.b].entity,
+      point: bodies[constraint.a].position + constraint.offset_a,
+      normal: constraint.normal_impulse,
+      friction: constraint.tangents[0] * constraint.tangent_impulse[0] + constraint.tangents[1] * constraint.tangent_impulse[1]
+    }).collect()
+  }
+
+  pub fn solve(&mut self, bodies: &mut [Body]) {
+    for index in 0..self.constraints.len() {
+      let constraint = &self.constraints[index];
+      let (a, b) = (constraint.a, constraint.b);
+
+      // friction first, bounded by the normal impulse of the last iteration
+      for tangent in 0..2 {
+        let constraint = &self.constraints[index];
+        let direction = constraint.tangents[tangent];
+        let speed = Solver::relative_velocity(&bodies[a], &bodies[b], constraint.offset_a, constraint.offset_b).dot(direction);
+        let limit = constraint.friction * constraint.normal_impulse;
+
+        let total = (constraint.tangent_impulse[tangent] - speed * constraint.tangent_mass[tangent]).clamp(-limit, limit);
+        let impulse = total - constraint.tangent_impulse[tangent];
+        self.constraints[index].tangent_impulse[tangent] = total;
+
+        self.apply(bodies, index, direction * impulse);
+      }
+
+      let constraint = &self.constraints[index];
+      let speed = Solver::relative_velocity(&bodies[a], &bodies[b], constraint.offset_a, constraint.offset_b).dot(constraint.normal);
+
+      // contacts only ever push
+      let total = (constraint.normal_impulse + (constraint.bias - speed) * constraint.normal_mass).max(0.0);
+      let impulse = total - constraint.normal_impulse;
+      let direction = constraint.normal;
+      self.constraints[index].normal_impulse = total;
+
+      self.apply(bodies, index, direction * impulse);
+    }
+  }
+
+  // pushes the second body along the impulse and the first against it
+  fn apply(&self, bodies: &mut [Body], index: usize, impulse: Vec3) {
+    let constraint = &self.constraints[index];
+    let (a, b) = (constraint.a, constraint.b);
+
+    bodies[a].linear -= impulse * self.inverse_masses[a];
+    bodies[a].angular -= self.inverse_inertias[a] * constraint.offset_a.cross(impulse);
+    bodies[b].linear += impulse * self.inverse_masses[b];
+    bodies[b].angular += self.inverse_inertias[b] * constraint.offset_b.cross(impulse);
+  }
+
+  // of the second body's point against the first's
+  fn relative_velocity(first: &Body, second: &Body, offset_a: Vec3, offset_b: Vec3) -> Vec3 {
+    (second.linear + second.angular.cross(offset_b)) - (first.linear + first.angular.cross(offset_a))
+  }
+}
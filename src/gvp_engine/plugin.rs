@@ -0,0 +1,437 @@
+mod abi;
+mod app;
+
+pub use abi::*;
+pub use app::*;
+
+use crate::gvp_engine::{app::AppId, engine::GVPEngine};
+
+use libloading::Library;
+
+use std::{
+  collections::HashMap,
+  env,
+  ffi::{c_char, c_void, CStr},
+  fmt,
+  fs,
+  path::{Path, PathBuf},
+  process,
+  rc::Rc,
+  time::{Duration, Instant, SystemTime}
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PluginId(usize);
+
+type Create = extern "C" fn() -> *mut c_void;
+type Destroy = extern "C" fn(app: *mut c_void);
+
+// why a library was not loaded
+#[derive(Clone, Debug, PartialEq)]
+pub enum PluginError {
+  Open(String),
+  MissingEntry,
+  NullDescriptor,
+  ApiVersion { found: u32, expected: u32 },
+  InvalidName,
+  // create and destroy are required
+  MissingCallbacks,
+  UnknownCapabilities(u32),
+  // asked for capabilities the manager does not allow
+  Denied(u32),
+  // another loaded plugin has the name
+  Duplicate(String)
+}
+
+impl fmt::Display for PluginError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PluginError::Open(error)                    => write!(formatter, "could not open library: {error}"),
+      PluginError::MissingEntry                   => write!(formatter, "no gvp_plugin_entry symbol"),
+      PluginError::NullDescriptor                 => write!(formatter, "gvp_plugin_entry returned null"),
+      PluginError::ApiVersion { found, expected } => write!(formatter, "built for plugin api {found}, the engine has {expected}"),
+      PluginError::InvalidName                    => write!(formatter, "name is missing or not utf-8"),
+      PluginError::MissingCallbacks               => write!(formatter, "create or destroy is null"),
+      PluginError::UnknownCapabilities(bits)      => write!(formatter, "unknown capabilities {bits:#x}"),
+      PluginError::Denied(bits)                   => write!(formatter, "capabilities {bits:#x} are not allowed"),
+      PluginError::Duplicate(name)                => write!(formatter, "a plugin named {name} is already loaded")
+    }
+  }
+}
+
+// what polling the plugin directory did
+#[derive(Clone, Debug, PartialEq)]
+pub enum PluginEvent {
+  Loaded(PluginId),
+  // the file changed. the old plugin's apps were closed and as many were opened from the new one
+  Reloaded { old: PluginId, new: PluginId },
+  // the file was removed, closing its apps
+  Unloaded(PluginId),
+  Rejected(PathBuf, PluginError)
+}
+
+// a validated plugin library. the descriptor's pointers are valid for as long as the library is loaded
+pub struct Plugin {
+  descriptor: PluginDescriptor,
+  // the descriptor's, checked to be there when the library was loaded
+  create: Create,
+  destroy: Destroy,
+  name: String,
+  capabilities: u32,
+  path: PathBuf,
+  // the library is loaded from a copy so the original can be rebuilt while it runs
+  copy: PathBuf,
+  modified: SystemTime,
+  // dropped last, after everything pointing into it
+  _library: Library
+}
+
+impl Drop for Plugin {
+  fn drop(&mut self) {
+    // unlinking is fine while the library is still mapped
+    let _ = fs::remove_file(&self.copy);
+  }
+}
+
+struct Entry {
+  plugin: Rc<Plugin>,
+  apps: Vec<AppId>
+}
+
+// loads app plugins, dynamic libraries exporting gvp_plugin_entry, and launches their apps. a directory can be polled
+// to pick up plugins as they are added, changed or removed while the engine runs
+pub struct PluginManager {
+  directory: Option<PathBuf>,
+  allowed: u32,
+  // ids index into this and are not reused once a plugin is unloaded
+  entries: Vec<Option<Entry>>,
+  // files that failed to load with their modification time, so they are only retried once they change
+  rejected: HashMap<PathBuf, SystemTime>,
+  last_scan: Option<Instant>,
+  loads: usize
+}
+
+impl Default for PluginManager {
+  fn default() -> Self {
+    PluginManager::new(CAPABILITY_ALL)
+  }
+}
+
+impl PluginManager {
+  const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+  // plugins asking for more capabilities than allowed are rejected
+  pub fn new(allowed: u32) -> Self {
+    PluginManager { directory: None, allowed, entries: Vec::new(), rejected: HashMap::new(), last_scan: None, loads: 0 }
+  }
+
+  pub fn set_directory(&mut self, directory: &Path) {
+    self.directory = Some(directory.to_path_buf());
+    self.last_scan = None;
+  }
+
+  pub fn load(&mut self, path: &Path) -> Result<PluginId, PluginError> {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).map_err(|error| PluginError::Open(error.to_string()))?;
+
+    let copy = env::temp_dir().join("gvp-plugins").join(format!(
+      "{}-{}-{}",
+      process::id(),
+      self.loads,
+      path.file_name().and_then(|name| name.to_str()).unwrap_or("plugin")
+    ));
+    self.loads += 1;
+
+    let directory = copy.parent().ok_or(PluginError::Open(format!("{} has no parent directory", copy.display())))?;
+    fs::create_dir_all(directory).and_then(|_| fs::copy(path, &copy)).map_err(|error| PluginError::Open(error.to_string()))?;
+
+    let plugin = PluginManager::open(&copy).and_then(|(library, descriptor)| {
+      let (name, create, destroy) = self.validate(&descriptor)?;
+
+      Ok(Plugin {
+        descriptor,
+        create,
+        destroy,
+        name,
+        capabilities: descriptor.capabilities,
+        path: path.to_path_buf(),
+        copy: copy.clone(),
+        modified,
+        _library: library
+      })
+    });
+
+    match plugin {
+      Ok(plugin) => {
+        self.rejected.remove(path);
+        self.entries.push(Some(Entry { plugin: Rc::new(plugin), apps: Vec::new() }));
+
+        Ok(PluginId(self.entries.len() - 1))
+      }
+      Err(error) => {
+        let _ = fs::remove_file(&copy);
+        self.rejected.insert(path.to_path_buf(), modified);
+
+        Err(error)
+      }
+    }
+  }
+
+  // closes the plugin's apps, their states kept like any other app's, then lets go of the library
+  pub fn unload(&mut self, engine: &mut GVPEngine, id: PluginId) {
+    let Some(entry) = self.entries.get_mut(id.0).and_then(Option::take) else { return; };
+
+    for app in entry.apps {
+      engine.close_app(app);
+    }
+  }
+
+  // opens another instance of the plugin's app
+  pub fn launch(&mut self, engine: &mut GVPEngine, id: PluginId) -> Option<AppId> {
+    let entry = self.entries.get_mut(id.0).and_then(Option::as_mut)?;
+    let app = engine.launch_app(Box::new(PluginApp::new(entry.plugin.clone())));
+
+    entry.apps.push(app);
+    Some(app)
+  }
+
+  // loads plugins that appeared in the directory, reloads changed ones and unloads removed ones. the directory is
+  // read at most once a second
+  pub fn poll(&mut self, engine: &mut GVPEngine) -> Vec<PluginEvent> {
+    let Some(directory) = self.directory.clone() else { return Vec::new(); };
+    if self.last_scan.is_some_and(|last| last.elapsed() < PluginManager::SCAN_INTERVAL) { return Vec::new(); }
+    self.last_scan = Some(Instant::now());
+
+    let files: HashMap<PathBuf, SystemTime> = match fs::read_dir(&directory) {
+      Ok(entries) => entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == env::consts::DLL_EXTENSION))
+        .filter_map(|path| Some((path.clone(), fs::metadata(&path).and_then(|metadata| metadata.modified()).ok()?)))
+        .collect(),
+      Err(_)      => HashMap::new()
+    };
+
+    let mut events = Vec::new();
+
+    for id in self.ids().collect::<Vec<PluginId>>() {
+      let Some(entry) = self.entries[id.0].as_ref() else { continue; };
+      let (path, modified) = (entry.plugin.path.clone(), entry.plugin.modified);
+
+      match files.get(&path) {
+        None                                  => {
+          self.unload(engine, id);
+          events.push(PluginEvent::Unloaded(id));
+        }
+        Some(changed) if *changed != modified => {
+          let open = self.apps(id).filter(|app| engine.apps().state(*app).is_some()).count();
+          self.unload(engine, id);
+
+          match self.load(&path) {
+            Ok(new)    => {
+              for _ in 0..open {
+                self.launch(engine, new);
+              }
+
+              events.push(PluginEvent::Reloaded { old: id, new });
+            }
+            Err(error) => {
+              events.push(PluginEvent::Unloaded(id));
+              events.push(PluginEvent::Rejected(path, error));
+            }
+          }
+        }
+        Some(_)                               => {}
+      }
+    }
+
+    let loaded: Vec<PathBuf> = self.plugins().map(|(_, plugin)| plugin.path.clone()).collect();
+    let mut new: Vec<(&PathBuf, &SystemTime)> = files.iter().filter(|(path, _)| !loaded.contains(path)).collect();
+    new.sort();
+
+    for (path, modified) in new {
+      if self.rejected.get(path) == Some(modified) { continue; }
+
+      match self.load(path) {
+        Ok(id)     => events.push(PluginEvent::Loaded(id)),
+        Err(error) => events.push(PluginEvent::Rejected(path.clone(), error))
+      }
+    }
+
+    events
+  }
+
+  pub fn ids(&self) -> impl Iterator<Item = PluginId> + '_ {
+    self.entries.iter().enumerate().filter(|(_, entry)| entry.is_some()).map(|(index, _)| PluginId(index))
+  }
+
+  pub fn plugins(&self) -> impl Iterator<Item = (PluginId, &Plugin)> {
+    self.entries.iter().enumerate().filter_map(|(index, entry)| entry.as_ref().map(|entry| (PluginId(index), entry.plugin.as_ref())))
+  }
+
+  pub fn find(&self, name: &str) -> Option<PluginId> {
+    self.plugins().find(|(_, plugin)| plugin.name == name).map(|(id, _)| id)
+  }
+
+  // apps launched from the plugin, some of which may have closed since
+  pub fn apps(&self, id: PluginId) -> impl Iterator<Item = AppId> + '_ {
+    self.entries.get(id.0).and_then(Option::as_ref).into_iter().flat_map(|entry| entry.apps.iter().copied())
+  }
+
+  // the library and the descriptor its entry point returned, checked for the api version this engine speaks
+  fn open(path: &Path) -> Result<(Library, PluginDescriptor), PluginError> {
+    let library = match unsafe { Library::new(path) } {
+      Ok(library) => library,
+      Err(error)  => return Err(PluginError::Open(error.to_string()))
+    };
+
+    let descriptor = match unsafe { library.get::<extern "C" fn() -> *const PluginDescriptor>(GVP_PLUGIN_ENTRY) } {
+      Ok(entry) => PluginManager::read(entry())?,
+      Err(_)    => return Err(PluginError::MissingEntry)
+    };
+
+    Ok((library, descriptor))
+  }
+
+  // the version is the first field of every version of the descriptor, so it is read on its own before the rest
+  fn read(descriptor: *const PluginDescriptor) -> Result<PluginDescriptor, PluginError> {
+    if descriptor.is_null() { return Err(PluginError::NullDescriptor); }
+
+    let found = unsafe { descriptor.cast::<u32>().read() };
+    if found != GVP_PLUGIN_API_VERSION {
+      return Err(PluginError::ApiVersion { found, expected: GVP_PLUGIN_API_VERSION });
+    }
+
+    Ok(unsafe { descriptor.read() })
+  }
+
+  // the name and the required callbacks of a plugin this manager takes: named, asking only for known and allowed
+  // capabilities, and not named like one already loaded
+  fn validate(&self, descriptor: &PluginDescriptor) -> Result<(String, Create, Destroy), PluginError> {
+    let name = PluginManager::string(descriptor.name).filter(|name| !name.is_empty()).ok_or(PluginError::InvalidName)?;
+
+    let (Some(create), Some(destroy)) = (descriptor.create, descriptor.destroy) else {
+      return Err(PluginError::MissingCallbacks);
+    };
+
+    let unknown = descriptor.capabilities & !CAPABILITY_ALL;
+    if unknown != 0 { return Err(PluginError::UnknownCapabilities(unknown)); }
+
+    let denied = descriptor.capabilities & !self.allowed;
+    if denied != 0 { return Err(PluginError::Denied(denied)); }
+
+    if self.find(&name).is_some() { return Err(PluginError::Duplicate(name)); }
+
+    Ok((name, create, destroy))
+  }
+
+  fn string(pointer: *const c_char) -> Option<String> {
+    if pointer.is_null() { return None; }
+    unsafe { CStr::from_ptr(pointer) }.to_str().ok().map(str::to_string)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::ptr;
+
+  extern "C" fn create() -> *mut c_void {
+    ptr::null_mut()
+  }
+
+  extern "C" fn destroy(_app: *mut c_void) {}
+
+  fn descriptor(name: &CStr, capabilities: u32) -> PluginDescriptor {
+    PluginDescriptor {
+      api_version: GVP_PLUGIN_API_VERSION,
+      name: name.as_ptr(),
+      version: ptr::null(),
+      capabilities,
+      create: Some(create),
+      destroy: Some(destroy),
+      on_open: None,
+      on_input: None,
+      update: None,
+      render: None,
+      on_suspend: None,
+      on_resume: None,
+      on_close: None,
+      save_state: None,
+      restore_state: None
+    }
+  }
+
+  fn validate(manager: &PluginManager, descriptor: &PluginDescriptor) -> Result<String, PluginError> {
+    manager.validate(descriptor).map(|(name, _, _)| name)
+  }
+
+  #[test]
+  fn takes_a_named_descriptor_asking_for_allowed_capabilities() {
+    let manager = PluginManager::new(CAPABILITY_DRAW | CAPABILITY_INPUT);
+
+    assert_eq!(validate(&manager, &descriptor(c"clock", CAPABILITY_DRAW)), Ok("clock".to_string()));
+  }
+
+  #[test]
+  fn rejects_descriptors_built_for_another_api_version() {
+    let newer = PluginDescriptor { api_version: GVP_PLUGIN_API_VERSION + 1, ..descriptor(c"clock", 0) };
+
+    assert!(PluginManager::read(&descriptor(c"clock", 0)).is_ok());
+    assert_eq!(PluginManager::read(ptr::null()).err(), Some(PluginError::NullDescriptor));
+    assert_eq!(
+      PluginManager::read(&newer).err(),
+      Some(PluginError::ApiVersion { found: GVP_PLUGIN_API_VERSION + 1, expected: GVP_PLUGIN_API_VERSION })
+    );
+  }
+
+  #[test]
+  fn rejects_descriptors_without_a_name_or_callbacks() {
+    let manager = PluginManager::default();
+
+    let headless = PluginDescriptor { destroy: None, ..descriptor(c"clock", 0) };
+
+    assert_eq!(validate(&manager, &descriptor(c"", 0)), Err(PluginError::InvalidName));
+    assert_eq!(validate(&manager, &headless), Err(PluginError::MissingCallbacks));
+  }
+
+  #[test]
+  fn rejects_unknown_and_denied_capabilities() {
+    let manager = PluginManager::new(CAPABILITY_DRAW);
+    let unknown = 1 << 20;
+
+    assert_eq!(
+      validate(&manager, &descriptor(c"clock", CAPABILITY_DRAW | unknown)),
+      Err(PluginError::UnknownCapabilities(unknown))
+    );
+    assert_eq!(
+      validate(&manager, &descriptor(c"clock", CAPABILITY_DRAW | CAPABILITY_SCENE | CAPABILITY_STATE)),
+      Err(PluginError::Denied(CAPABILITY_SCENE | CAPABILITY_STATE))
+    );
+  }
+
+  // the test binary stands in for the library of the plugin already loaded
+  #[cfg(unix)]
+  #[test]
+  fn rejects_a_second_plugin_with_a_loaded_name() {
+    let mut manager = PluginManager::default();
+    let loaded = descriptor(c"clock", 0);
+
+    manager.entries.push(Some(Entry {
+      plugin: Rc::new(Plugin {
+        descriptor: loaded,
+        create,
+        destroy,
+        name: "clock".to_string(),
+        capabilities: 0,
+        path: PathBuf::from("clock"),
+        copy: env::temp_dir().join(format!("plugin_duplicate_{}", process::id())),
+        modified: SystemTime::UNIX_EPOCH,
+        _library: libloading::os::unix::Library::this().into()
+      }),
+      apps: Vec::new()
+    }));
+
+    assert_eq!(validate(&manager, &loaded), Err(PluginError::Duplicate("clock".to_string())));
+    assert!(validate(&manager, &descriptor(c"timer", 0)).is_ok());
+  }
+}
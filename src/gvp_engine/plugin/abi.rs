@@ -0,0 +1,95 @@
+// the c abi between the engine and plugin libraries, mirrored by include/gvp_plugin.h. any change to these structs
+// bumps GVP_PLUGIN_API_VERSION. strings are utf-8, either nul terminated or given with a length
+
+use std::ffi::{c_char, c_void};
+
+pub const GVP_PLUGIN_API_VERSION: u32 = 1;
+
+// the symbol every plugin library exports, an `extern "C" fn() -> *const PluginDescriptor`
+pub const GVP_PLUGIN_ENTRY: &[u8] = b"gvp_plugin_entry\0";
+
+// what a plugin may ask the host for. calls outside its capabilities do nothing
+pub const CAPABILITY_DRAW: u32 = 1 << 0;
+// pointer input over its panel
+pub const CAPABILITY_INPUT: u32 = 1 << 1;
+// keys and typed text while its panel is focused
+pub const CAPABILITY_KEYBOARD: u32 = 1 << 2;
+// state kept across launches and sessions
+pub const CAPABILITY_STATE: u32 = 1 << 3;
+// entities spawned into the scene
+pub const CAPABILITY_SCENE: u32 = 1 << 4;
+pub const CAPABILITY_ALL: u32 = CAPABILITY_DRAW | CAPABILITY_INPUT | CAPABILITY_KEYBOARD | CAPABILITY_STATE | CAPABILITY_SCENE;
+
+// primitive kinds for PluginHost::spawn_primitive
+pub const PRIMITIVE_CUBE: u32 = 0;
+pub const PRIMITIVE_SPHERE: u32 = 1;
+pub const PRIMITIVE_CYLINDER: u32 = 2;
+pub const PRIMITIVE_CONE: u32 = 3;
+pub const PRIMITIVE_PLANE: u32 = 4;
+pub const PRIMITIVE_TORUS: u32 = 5;
+pub const PRIMITIVE_ICO_SPHERE: u32 = 6;
+pub const PRIMITIVE_CAPSULE: u32 = 7;
+
+pub type AppCallback = extern "C" fn(app: *mut c_void, host: *const PluginHost);
+
+// returned by the entry point and read once when the library loads, so it has to live as long as the library does.
+// callbacks left null are skipped
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PluginDescriptor {
+  pub api_version: u32,
+  pub name: *const c_char,
+  pub version: *const c_char,
+  pub capabilities: u32,
+  // an instance of the app, handed back to every other callback
+  pub create: Option<extern "C" fn() -> *mut c_void>,
+  pub destroy: Option<extern "C" fn(app: *mut c_void)>,
+  pub on_open: Option<AppCallback>,
+  pub on_input: Option<extern "C" fn(app: *mut c_void, host: *const PluginHost, input: *const PluginInput)>,
+  pub update: Option<extern "C" fn(app: *mut c_void, host: *const PluginHost, delta: f32)>,
+  // the only callback drawing works in. the panel shows what was drawn until the next render that draws
+  pub render: Option<AppCallback>,
+  pub on_suspend: Option<AppCallback>,
+  pub on_resume: Option<AppCallback>,
+  pub on_close: Option<AppCallback>,
+  // answered through PluginHost::save_state
+  pub save_state: Option<AppCallback>,
+  pub restore_state: Option<extern "C" fn(app: *mut c_void, state: *const u8, length: usize)>
+}
+
+// a frame's input. text and keys are empty without the keyboard capability or while the panel is not focused
+#[repr(C)]
+pub struct PluginInput {
+  pub has_pointer: bool,
+  // in pixels of the panel
+  pub pointer: [f32; 2],
+  pub pressed: bool,
+  pub held: bool,
+  pub released: bool,
+  pub back: bool,
+  pub scroll: [f32; 2],
+  // sdl keycodes
+  pub keys: *const i32,
+  pub key_count: usize,
+  pub text: *const u8,
+  pub text_length: usize
+}
+
+// the engine's side, only valid during the callback it was given to. every function takes `context` first
+#[repr(C)]
+pub struct PluginHost {
+  pub api_version: u32,
+  pub context: *mut c_void,
+  pub panel_size: extern "C" fn(context: *mut c_void, size: *mut [f32; 2]),
+  pub focused: extern "C" fn(context: *mut c_void) -> bool,
+  // colors are straight alpha srgb
+  pub clear: extern "C" fn(context: *mut c_void, color: *const [f32; 4]),
+  pub draw_rect: extern "C" fn(context: *mut c_void, rect: *const [f32; 4], color: *const [f32; 4], radius: f32),
+  pub draw_line: extern "C" fn(context: *mut c_void, from: *const [f32; 2], to: *const [f32; 2], width: f32, color: *const [f32; 4]),
+  pub draw_text: extern "C" fn(context: *mut c_void, position: *const [f32; 2], text: *const u8, length: usize, size: f32, color: *const [f32; 4]),
+  pub save_state: extern "C" fn(context: *mut c_void, state: *const u8, length: usize),
+  // returns a handle for despawn, 0 if nothing was spawned
+  pub spawn_primitive: extern "C" fn(context: *mut c_void, kind: u32, position: *const [f32; 3], scale: *const [f32; 3], color: *const [f32; 4]) -> u64,
+  pub despawn: extern "C" fn(context: *mut c_void, handle: u64),
+  pub close: extern "C" fn(context: *mut c_void)
+}
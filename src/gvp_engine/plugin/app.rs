@@ -0,0 +1,306 @@
+use crate::gvp_engine::{
+  app::{AppContext, GVPApp},
+  gui::{Canvas, Color, GuiInput, Rect, Theme},
+  material::Material,
+  primitives::PrimitiveKind,
+  scene::Entity,
+  transform::Transform
+};
+
+use super::{abi::*, Plugin};
+
+use glam::{Quat, Vec2, Vec3};
+use serde_json::Value;
+
+use std::{ffi::c_void, ptr, rc::Rc, slice};
+
+// what the host functions reach through the context pointer during one callback
+struct Host<'a, 'b> {
+  // none while saving state, which happens outside of any app context
+  context: Option<&'a mut AppContext<'b>>,
+  capabilities: u32,
+  // only during render
+  canvas: Option<Canvas>,
+  drew: bool,
+  // handle - 1 indexes into this
+  entities: &'a mut Vec<Option<Entity>>,
+  state: Option<Value>
+}
+
+impl Host<'_, '_> {
+  // points back at the host, which must not move while the table is in use
+  fn table(&mut self) -> PluginHost {
+    PluginHost {
+      api_version: GVP_PLUGIN_API_VERSION,
+      context: ptr::from_mut(self).cast(),
+      panel_size,
+      focused,
+      clear,
+      draw_rect,
+      draw_line,
+      draw_text,
+      save_state,
+      spawn_primitive,
+      despawn,
+      close
+    }
+  }
+}
+
+// an instance of a plugin's app. it keeps the library loaded for as long as it lives
+pub struct PluginApp {
+  plugin: Rc<Plugin>,
+  instance: *mut c_void,
+  entities: Vec<Option<Entity>>
+}
+
+impl PluginApp {
+  pub fn new(plugin: Rc<Plugin>) -> Self {
+    let instance = (plugin.create)();
+
+    PluginApp { plugin, instance, entities: Vec::new() }
+  }
+
+  // runs a callback with a host over the context, handing back what it drew and saved
+  fn call(&mut self, context: Option<&mut AppContext>, canvas: Option<Canvas>, run: impl FnOnce(*mut c_void, *const PluginHost)) -> (Option<Canvas>, bool, Option<Value>) {
+    let mut host = Host {
+      context,
+      capabilities: self.plugin.capabilities,
+      canvas,
+      drew: false,
+      entities: &mut self.entities,
+      state: None
+    };
+
+    let table = host.table();
+
+    run(self.instance, &table);
+
+    (host.canvas, host.drew, host.state)
+  }
+
+  fn callback(&mut self, context: &mut AppContext, callback: Option<AppCallback>) {
+    if let Some(callback) = callback {
+      self.call(Some(context), None, |app, host| callback(app, host));
+    }
+  }
+}
+
+impl GVPApp for PluginApp {
+  fn name(&self) -> &str {
+    &self.plugin.name
+  }
+
+  fn on_open(&mut self, context: &mut AppContext) {
+    self.callback(context, self.plugin.descriptor.on_open);
+  }
+
+  fn on_input(&mut self, context: &mut AppContext, input: &GuiInput) {
+    let Some(on_input) = self.plugin.descriptor.on_input else { return; };
+    if self.plugin.capabilities & CAPABILITY_INPUT == 0 { return; }
+
+    let keyboard = self.plugin.capabilities & CAPABILITY_KEYBOARD != 0;
+    let keys: Vec<i32> = match keyboard {
+      true  => input.keys.iter().map(|key| key.into_i32()).collect(),
+      false => Vec::new()
+    };
+    let text = if keyboard { input.text.as_str() } else { "" };
+
+    let input = PluginInput {
+      has_pointer: input.pointer.is_some(),
+      pointer: input.pointer.unwrap_or_default().to_array(),
+      pressed: input.pressed,
+      held: input.held,
+      released: input.released,
+      back: input.back,
+      scroll: input.scroll.to_array(),
+      keys: keys.as_ptr(),
+      key_count: keys.len(),
+      text: text.as_ptr(),
+      text_length: text.len()
+    };
+
+    self.call(Some(context), None, |app, host| on_input(app, host, &input));
+  }
+
+  fn update(&mut self, context: &mut AppContext, delta: f32) {
+    if let Some(update) = self.plugin.descriptor.update {
+      self.call(Some(context), None, |app, host| update(app, host, delta));
+    }
+  }
+
+  fn render(&mut self, context: &mut AppContext) {
+    let Some(render) = self.plugin.descriptor.render else { return; };
+
+    let canvas = Canvas::new(context.size(), Theme::dark().background);
+    let (canvas, drew, _) = self.call(Some(context), Some(canvas), |app, host| render(app, host));
+
+    if let (Some(canvas), true) = (canvas, drew) {
+      context.draw(canvas);
+    }
+  }
+
+  fn on_suspend(&mut self, context: &mut AppContext) {
+    self.callback(context, self.plugin.descriptor.on_suspend);
+  }
+
+  fn on_resume(&mut self, context: &mut AppContext) {
+    self.callback(context, self.plugin.descriptor.on_resume);
+  }
+
+  fn on_close(&mut self, context: &mut AppContext) {
+    self.callback(context, self.plugin.descriptor.on_close);
+  }
+
+  // the app manager only asks through a shared reference, so the instance pointer is used as is
+  fn save_state(&self) -> Option<Value> {
+    let save = self.plugin.descriptor.save_state?;
+    if self.plugin.capabilities & CAPABILITY_STATE == 0 { return None; }
+
+    let mut entities = Vec::new();
+    let mut host = Host { context: None, capabilities: CAPABILITY_STATE, canvas: None, drew: false, entities: &mut entities, state: None };
+
+    let table = host.table();
+
+    save(self.instance, &table);
+    host.state
+  }
+
+  fn restore_state(&mut self, state: &Value) {
+    let Some(restore) = self.plugin.descriptor.restore_state else { return; };
+    if self.plugin.capabilities & CAPABILITY_STATE == 0 { return; }
+
+    let state = state.to_string();
+    restore(self.instance, state.as_ptr(), state.len());
+  }
+}
+
+impl Drop for PluginApp {
+  fn drop(&mut self) {
+    (self.plugin.destroy)(self.instance);
+  }
+}
+
+// the host of the callback running right now
+fn host<'a>(context: *mut c_void) -> &'a mut Host<'a, 'a> {
+  unsafe { &mut *context.cast::<Host>() }
+}
+
+fn read<T: Copy>(pointer: *const T) -> Option<T> {
+  unsafe { pointer.as_ref().copied() }
+}
+
+fn color(pointer: *const [f32; 4]) -> Color {
+  read(pointer).map_or(Color::WHITE, |[r, g, b, a]| Color::rgba(r, g, b, a))
+}
+
+// invalid utf-8 is replaced rather than trusted
+fn string(pointer: *const u8, length: usize) -> String {
+  if pointer.is_null() { return String::new(); }
+  String::from_utf8_lossy(unsafe { slice::from_raw_parts(pointer, length) }).into_owned()
+}
+
+// the canvas when drawing is allowed right now
+fn canvas<'a>(host: &'a mut Host) -> Option<&'a mut Canvas> {
+  if host.capabilities & CAPABILITY_DRAW == 0 { return None; }
+
+  host.drew = host.canvas.is_some();
+  host.canvas.as_mut()
+}
+
+extern "C" fn panel_size(context: *mut c_void, size: *mut [f32; 2]) {
+  let host = host(context);
+  let panel = host.context.as_ref().map_or(Vec2::ZERO, |context| context.size());
+
+  if let Some(size) = unsafe { size.as_mut() } {
+    *size = panel.to_array();
+  }
+}
+
+extern "C" fn focused(context: *mut c_void) -> bool {
+  host(context).context.as_ref().is_some_and(|context| context.focused())
+}
+
+extern "C" fn clear(context: *mut c_void, color_pointer: *const [f32; 4]) {
+  if let Some(canvas) = canvas(host(context)) {
+    canvas.clear = color(color_pointer);
+  }
+}
+
+extern "C" fn draw_rect(context: *mut c_void, rect: *const [f32; 4], color_pointer: *const [f32; 4], radius: f32) {
+  let Some([x, y, width, height]) = read(rect) else { return; };
+
+  if let Some(canvas) = canvas(host(context)) {
+    canvas.rect(Rect::new(Vec2::new(x, y), Vec2::new(width, height)), color(color_pointer), radius);
+  }
+}
+
+extern "C" fn draw_line(context: *mut c_void, from: *const [f32; 2], to: *const [f32; 2], width: f32, color_pointer: *const [f32; 4]) {
+  let (Some(from), Some(to)) = (read(from), read(to)) else { return; };
+
+  if let Some(canvas) = canvas(host(context)) {
+    canvas.line(Vec2::from(from), Vec2::from(to), width, color(color_pointer));
+  }
+}
+
+extern "C" fn draw_text(context: *mut c_void, position: *const [f32; 2], text: *const u8, length: usize, size: f32, color_pointer: *const [f32; 4]) {
+  let Some(position) = read(position) else { return; };
+
+  if let Some(canvas) = canvas(host(context)) {
+    canvas.text(Vec2::from(position), &string(text, length), size, color(color_pointer));
+  }
+}
+
+// anything that is not json is kept as a string
+extern "C" fn save_state(context: *mut c_void, state: *const u8, length: usize) {
+  let host = host(context);
+  if host.capabilities & CAPABILITY_STATE == 0 { return; }
+
+  let state = string(state, length);
+  host.state = Some(serde_json::from_str(&state).unwrap_or(Value::String(state)));
+}
+
+extern "C" fn spawn_primitive(context: *mut c_void, kind: u32, position: *const [f32; 3], scale: *const [f32; 3], color_pointer: *const [f32; 4]) -> u64 {
+  let host = host(context);
+  if host.capabilities & CAPABILITY_SCENE == 0 { return 0; }
+  let Some(context) = host.context.as_mut() else { return 0; };
+
+  let kind = match kind {
+    PRIMITIVE_CUBE       => PrimitiveKind::CUBE,
+    PRIMITIVE_SPHERE     => PrimitiveKind::SPHERE,
+    PRIMITIVE_CYLINDER   => PrimitiveKind::CYLINDER,
+    PRIMITIVE_CONE       => PrimitiveKind::CONE,
+    PRIMITIVE_PLANE      => PrimitiveKind::PLANE,
+    PRIMITIVE_TORUS      => PrimitiveKind::TORUS,
+    PRIMITIVE_ICO_SPHERE => PrimitiveKind::ICO_SPHERE,
+    PRIMITIVE_CAPSULE    => PrimitiveKind::CAPSULE,
+    _                    => return 0
+  };
+
+  let transform = Transform::new(
+    read(position).map_or(Vec3::ZERO, Vec3::from),
+    Quat::IDENTITY,
+    read(scale).map_or(Vec3::ONE, Vec3::from)
+  );
+
+  let entity = context.spawn_primitive(kind, transform, Material::color(color(color_pointer).linear(), 0.0, 0.5));
+  host.entities.push(Some(entity));
+
+  host.entities.len() as u64
+}
+
+extern "C" fn despawn(context: *mut c_void, handle: u64) {
+  let host = host(context);
+  let Some(context) = host.context.as_mut() else { return; };
+
+  let slot = (handle as usize).checked_sub(1).and_then(|index| host.entities.get_mut(index));
+  if let Some(entity) = slot.and_then(Option::take) {
+    context.despawn(entity);
+  }
+}
+
+extern "C" fn close(context: *mut c_void) {
+  if let Some(context) = host(context).context.as_mut() {
+    context.close();
+  }
+}
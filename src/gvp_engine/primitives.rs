@@ -0,0 +1,362 @@
+use crate::gvp_engine::mesh::{MeshData, Vertex};
+
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+use std::{collections::HashMap, f32::consts::{PI, TAU}};
+
+// every primitive fits inside a unit cube centered on the origin (the torus and capsule excepted along their long
+// axes) and is sized in the world through its transform
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PrimitiveKind {
+  Cube { subdivisions: u32 },
+  UvSphere { segments: u32, rings: u32 },
+  IcoSphere { subdivisions: u32 },
+  Cylinder { segments: u32 },
+  // radius 0.5 hemispheres on a cylinder of height 1
+  Capsule { segments: u32, rings: u32 },
+  Cone { segments: u32 },
+  // lies on the xz plane facing +y
+  Plane { subdivisions: u32 },
+  // ring radius 0.5 with a tube of the given radius
+  Torus { major_segments: u32, minor_segments: u32, tube_radius: f32 }
+}
+
+impl PrimitiveKind {
+  pub const CUBE: PrimitiveKind = PrimitiveKind::Cube { subdivisions: 1 };
+  pub const SPHERE: PrimitiveKind = PrimitiveKind::UvSphere { segments: 32, rings: 16 };
+  pub const ICO_SPHERE: PrimitiveKind = PrimitiveKind::IcoSphere { subdivisions: 3 };
+  pub const CYLINDER: PrimitiveKind = PrimitiveKind::Cylinder { segments: 32 };
+  pub const CAPSULE: PrimitiveKind = PrimitiveKind::Capsule { segments: 32, rings: 8 };
+  pub const CONE: PrimitiveKind = PrimitiveKind::Cone { segments: 32 };
+  pub const PLANE: PrimitiveKind = PrimitiveKind::Plane { subdivisions: 1 };
+  pub const TORUS: PrimitiveKind = PrimitiveKind::Torus { major_segments: 48, minor_segments: 16, tube_radius: 0.15 };
+
+  pub fn generate(&self) -> MeshData {
+    let mut mesh = match *self {
+      PrimitiveKind::Cube { subdivisions }                                => cube(subdivisions.max(1)),
+      PrimitiveKind::UvSphere { segments, rings }                         => uv_sphere(segments.max(3), rings.max(2)),
+      PrimitiveKind::IcoSphere { subdivisions }                           => ico_sphere(subdivisions.min(6)),
+      PrimitiveKind::Cylinder { segments }                                => cylinder(segments.max(3)),
+      PrimitiveKind::Capsule { segments, rings }                          => capsule(segments.max(3), rings.max(1)),
+      PrimitiveKind::Cone { segments }                                    => cone(segments.max(3)),
+      PrimitiveKind::Plane { subdivisions }                               => plane(subdivisions.max(1)),
+      PrimitiveKind::Torus { major_segments, minor_segments, tube_radius } => {
+        torus(major_segments.max(3), minor_segments.max(3), tube_radius.clamp(0.01, 0.5))
+      }
+    };
+
+    mesh.generate_tangents();
+    mesh
+  }
+}
+
+fn vertex(position: Vec3, normal: Vec3, uv: Vec2) -> Vertex {
+  Vertex {
+    position: position.to_array(),
+    normal: normal.to_array(),
+    uv: uv.to_array(),
+    ..Vertex::default()
+  }
+}
+
+// builds a (columns + 1) x (rows + 1) grid of vertices from a surface parameterized over u, v in [0, 1]. the surface
+// has to be parameterized so that d/du x d/dv points out of the front face
+fn lattice<F: Fn(f32, f32) -> (Vec3, Vec3)>(mesh: &mut MeshData, columns: u32, rows: u32, surface: F) {
+  let base = mesh.vertices.len() as u32;
+
+  for row in 0..=rows {
+    for column in 0..=columns {
+      let (u, v) = (column as f32 / columns as f32, row as f32 / rows as f32);
+      let (position, normal) = surface(u, v);
+
+      mesh.vertices.push(vertex(position, normal, Vec2::new(u, 1.0 - v)));
+    }
+  }
+
+  let stride = columns + 1;
+  for row in 0..rows {
+    for column in 0..columns {
+      let a = base + row * stride + column;
+      let b = a + 1;
+      let c = b + stride;
+      let d = a + stride;
+
+      mesh.indices.extend_from_slice(&[a, b, c, a, c, d]);
+    }
+  }
+}
+
+// a flat disk at height y facing up or down
+fn disk(mesh: &mut MeshData, segments: u32, y: f32, up: bool) {
+  let normal = if up { Vec3::Y } else { Vec3::NEG_Y };
+  let center = mesh.vertices.len() as u32;
+
+  mesh.vertices.push(vertex(Vec3::new(0.0, y, 0.0), normal, Vec2::splat(0.5)));
+
+  for segment in 0..=segments {
+    let theta = TAU * segment as f32 / segments as f32;
+    let (sin, cos) = theta.sin_cos();
+
+    mesh.vertices.push(vertex(
+      Vec3::new(0.5 * cos, y, -0.5 * sin),
+      normal,
+      Vec2::new(0.5 + 0.5 * cos, 0.5 + if up { 0.5 } else { -0.5 } * sin)
+    ));
+  }
+
+  for segment in 0..segments {
+    let (a, b) = (center + 1 + segment, center + 2 + segment);
+
+    match up {
+      true  => mesh.indices.extend_from_slice(&[center, a, b]),
+      false => mesh.indices.extend_from_slice(&[center, b, a])
+    }
+  }
+}
+
+fn plane(subdivisions: u32) -> MeshData {
+  let mut mesh = MeshData::default();
+
+  lattice(&mut mesh, subdivisions, subdivisions, |u, v| {
+    (Vec3::new(u - 0.5, 0.0, 0.5 - v), Vec3::Y)
+  });
+
+  mesh
+}
+
+fn cube(subdivisions: u32) -> MeshData {
+  let mut mesh = MeshData::default();
+
+  // (normal, u axis, v axis) with u x v == normal
+  let faces = [
+    (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+    (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+    (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+    (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+    (Vec3::Z, Vec3::X, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y)
+  ];
+
+  for (normal, u_axis, v_axis) in faces {
+    let origin = (normal - u_axis - v_axis) * 0.5;
+
+    lattice(&mut mesh, subdivisions, subdivisions, |u, v| {
+      (origin + u_axis * u + v_axis * v, normal)
+    });
+  }
+
+  mesh
+}
+
+// point on a sphere of radius 0.5 where theta wraps around y and phi runs from the top pole (0) to the bottom (pi)
+fn sphere_normal(theta: f32, phi: f32) -> Vec3 {
+  Vec3::new(phi.sin() * theta.cos(), phi.cos(), -phi.sin() * theta.sin())
+}
+
+fn uv_sphere(segments: u32, rings: u32) -> MeshData {
+  let mut mesh = MeshData::default();
+
+  lattice(&mut mesh, segments, rings, |u, v| {
+    let normal = sphere_normal(TAU * u, PI * (1.0 - v));
+    (normal * 0.5, normal)
+  });
+
+  mesh
+}
+
+fn ico_sphere(subdivisions: u32) -> MeshData {
+  let t = (1.0 + 5f32.sqrt()) / 2.0;
+
+  let mut positions: Vec<Vec3> = [
+    (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+    (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+    (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0)
+  ].iter().map(|&(x, y, z)| Vec3::new(x, y, z).normalize()).collect();
+
+  let mut triangles: Vec<[u32; 3]> = vec![
+    [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+    [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+    [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+    [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1]
+  ];
+
+  for _ in 0..subdivisions {
+    // shared edges reuse the same midpoint so the sphere stays watertight
+    let mut midpoints = HashMap::<(u32, u32), u32>::new();
+    let mut midpoint = |a: u32, b: u32, positions: &mut Vec<Vec3>| -> u32 {
+      *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+        positions.push(((positions[a as usize] + positions[b as usize]) * 0.5).normalize());
+        positions.len() as u32 - 1
+      })
+    };
+
+    let mut subdivided = Vec::<[u32; 3]>::with_capacity(triangles.len() * 4);
+    for [a, b, c] in triangles {
+      let ab = midpoint(a, b, &mut positions);
+      let bc = midpoint(b, c, &mut positions);
+      let ca = midpoint(c, a, &mut positions);
+
+      subdivided.extend_from_slice(&[[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+    }
+
+    triangles = subdivided;
+  }
+
+  let vertices = positions.iter().map(|&normal| {
+    let u = (-normal.z).atan2(normal.x) / TAU;
+    let uv = Vec2::new(u.rem_euclid(1.0), normal.y.clamp(-1.0, 1.0).acos() / PI);
+
+    vertex(normal * 0.5, normal, uv)
+  }).collect();
+
+  MeshData {
+    vertices,
+    indices: triangles.into_iter().flatten().collect(),
+    ..MeshData::default()
+  }
+}
+
+fn cylinder(segments: u32) -> MeshData {
+  let mut mesh = MeshData::default();
+
+  lattice(&mut mesh, segments, 1, |u, v| {
+    let (sin, cos) = (TAU * u).sin_cos();
+    let normal = Vec3::new(cos, 0.0, -sin);
+
+    (normal * 0.5 + Vec3::new(0.0, v - 0.5, 0.0), normal)
+  });
+
+  disk(&mut mesh, segments, 0.5, true);
+  disk(&mut mesh, segments, -0.5, false);
+
+  mesh
+}
+
+fn cone(segments: u32) -> MeshData {
+  let mut mesh = MeshData::default();
+
+  // slant normal of a cone with radius 0.5 and height 1
+  lattice(&mut mesh, segments, 1, |u, v| {
+    let (sin, cos) = (TAU * u).sin_cos();
+    let normal = Vec3::new(cos, 0.5, -sin).normalize();
+
+    (Vec3::new(0.5 * (1.0 - v) * cos, v - 0.5, -0.5 * (1.0 - v) * sin), normal)
+  });
+
+  disk(&mut mesh, segments, -0.5, false);
+
+  mesh
+}
+
+fn capsule(segments: u32, rings: u32) -> MeshData {
+  let mut mesh = MeshData::default();
+
+  // rows run from the bottom pole to the top pole. the first hemisphere ends and the second begins at the equator
+  // so the middle row of quads forms the cylinder
+  let rows = 2 * rings + 1;
+  let profile = |row: u32| -> (f32, f32) {
+    match row <= rings {
+      true  => (PI - 0.5 * PI * row as f32 / rings as f32, -0.5),
+      false => (0.5 * PI - 0.5 * PI * (row - rings - 1) as f32 / rings as f32, 0.5)
+    }
+  };
+
+  lattice(&mut mesh, segments, rows, |u, v| {
+    let (phi, offset) = profile((v * rows as f32).round() as u32);
+    let normal = sphere_normal(TAU * u, phi);
+
+    (normal * 0.5 + Vec3::new(0.0, offset, 0.0), normal)
+  });
+
+  mesh
+}
+
+fn torus(major_segments: u32, minor_segments: u32, tube_radius: f32) -> MeshData {
+  let mut mesh = MeshData::default();
+
+  lattice(&mut mesh, major_segments, minor_segments, |u, v| {
+    let (sin_theta, cos_theta) = (TAU * u).sin_cos();
+    let (sin_phi, cos_phi) = (TAU * v).sin_cos();
+
+    let normal = Vec3::new(cos_phi * cos_theta, sin_phi, -cos_phi * sin_theta);
+    let center = Vec3::new(0.5 * cos_theta, 0.0, -0.5 * sin_theta);
+
+    (center + normal * tube_radius, normal)
+  });
+
+  mesh
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // every kind at two tessellations with the vertex and index counts they should come out with
+  fn kinds() -> Vec<(PrimitiveKind, usize, usize)> {
+    let mut kinds = Vec::new();
+
+    for n in [1usize, 3] {
+      let m = n as u32;
+
+      kinds.extend([
+        (PrimitiveKind::Cube { subdivisions: m }, 6 * (n + 1) * (n + 1), 36 * n * n),
+        (PrimitiveKind::UvSphere { segments: 4 * m, rings: 2 * m }, (4 * n + 1) * (2 * n + 1), 48 * n * n),
+        (PrimitiveKind::IcoSphere { subdivisions: m }, 10 * 4usize.pow(m) + 2, 60 * 4usize.pow(m)),
+        (PrimitiveKind::Cylinder { segments: 8 * m }, (8 * n + 1) * 2 + 2 * (8 * n + 2), 8 * n * 6 + 2 * 8 * n * 3),
+        (PrimitiveKind::Capsule { segments: 8 * m, rings: m }, (8 * n + 1) * (2 * n + 2), 8 * n * (2 * n + 1) * 6),
+        (PrimitiveKind::Cone { segments: 8 * m }, (8 * n + 1) * 2 + 8 * n + 2, 8 * n * 6 + 8 * n * 3),
+        (PrimitiveKind::Plane { subdivisions: m }, (n + 1) * (n + 1), 6 * n * n),
+        (PrimitiveKind::Torus { major_segments: 8 * m, minor_segments: 4 * m, tube_radius: 0.2 }, (8 * n + 1) * (4 * n + 1), 8 * n * 4 * n * 6)
+      ]);
+    }
+
+    kinds
+  }
+
+  #[test]
+  fn generates_the_expected_counts() {
+    for (kind, vertices, indices) in kinds() {
+      let mesh = kind.generate();
+
+      assert_eq!((mesh.vertices.len(), mesh.indices.len()), (vertices, indices), "{kind:?}");
+      assert!(mesh.indices.iter().all(|&index| (index as usize) < vertices), "{kind:?}");
+    }
+  }
+
+  #[test]
+  fn generates_unit_normals_and_tangents() {
+    for (kind, _, _) in kinds() {
+      for vertex in kind.generate().vertices {
+        let (normal, tangent) = (Vec3::from(vertex.normal), Vec3::from_slice(&vertex.tangent[..3]));
+
+        assert!((normal.length() - 1.0).abs() < 1e-4, "{kind:?} normal {normal}");
+        assert!((tangent.length() - 1.0).abs() < 1e-3, "{kind:?} tangent {tangent}");
+        assert!(normal.dot(tangent).abs() < 1e-3, "{kind:?}");
+      }
+    }
+  }
+
+  // the front faces of the convex kinds point away from their center, the plane's up
+  #[test]
+  fn winds_front_faces_outwards() {
+    for (kind, _, _) in kinds().into_iter().filter(|(kind, _, _)| !matches!(kind, PrimitiveKind::Torus { .. })) {
+      let mesh = kind.generate();
+
+      for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|corner| Vec3::from(mesh.vertices[triangle[corner] as usize].position));
+        let facing = (b - a).cross(c - a);
+
+        // the poles of uv spheres collapse a row of triangles
+        if facing.length() < 1e-6 { continue; }
+
+        let outwards = match kind {
+          PrimitiveKind::Plane { .. } => Vec3::Y,
+          _                           => (a + b + c) / 3.0
+        };
+
+        assert!(facing.dot(outwards) > 0.0, "{kind:?} {triangle:?}");
+      }
+    }
+  }
+}
@@ -1,34 +1,240 @@
-use crate::gvp_engine::gpu::GPU;
+mod canvas;
+mod timing;
+mod trace;
+mod upload;
+
+pub use canvas::*;
+pub use timing::*;
+pub use trace::*;
+pub use upload::*;
+
+use crate::gvp_engine::{
+  allocator::{as_bytes, Allocator, Buffer, Image},
+  camera::Camera,
+  compositor::PanelDraw,
+  environment::Environment,
+  gpu::GPU,
+  material::MaterialInstance,
+  mesh::{Mesh, Vertex},
+  shader
+};
 
 use ash::{vk, khr::{surface, swapchain}};
+use glam::{Mat3, Mat4, Vec3, Vec4};
+
+use std::{ffi::c_void, mem::size_of, slice, time::{Duration, Instant}};
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ViewUniforms {
+  view: Mat4,
+  projection: Mat4,
+  // inverse of the projection and the view without its translation, for the sky
+  sky: Mat4,
+  camera_position: Vec4
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct FrameUniforms {
+  // indexed by the multiview view index, which is always 0 for single layer targets
+  views: [ViewUniforms; Renderer::MAX_LAYERS],
+  light: Vec4,
+  light_color: Vec4,
+  environment: Vec4
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PushConstants {
+  model: Mat4,
+  // opacity, unused, unused, unused. only read when drawing panels
+  panel: Vec4
+}
+
+pub struct DrawCall<'a> {
+  pub mesh: &'a Mesh,
+  pub transform: Mat4,
+  pub material: &'a MaterialInstance
+}
+
+// everything drawn in a frame. canvases and then surfaces are drawn into their panels' targets before the scene
+pub struct DrawList<'a> {
+  pub meshes: Vec<DrawCall<'a>>,
+  pub panels: Vec<PanelDraw<'a>>,
+  pub canvases: Vec<CanvasDraw<'a>>,
+  pub atlas: Option<AtlasUpload<'a>>,
+  pub surfaces: Vec<SurfaceDraw<'a>>
+}
+
+// blended meshes and panels are sorted together so they composite over each other in the right order
+enum Transparent<'a> {
+  Mesh(&'a DrawCall<'a>),
+  Panel(&'a PanelDraw<'a>)
+}
+
+impl Transparent<'_> {
+  fn position(&self) -> Vec3 {
+    match self {
+      Transparent::Mesh(draw)  => draw.transform.w_axis.truncate(),
+      Transparent::Panel(draw) => draw.transform.w_axis.truncate()
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PipelineKind {
+  Mesh { blend: bool, double_sided: bool },
+  Skybox,
+  Panel
+}
+
+// the directional light the scene is lit by
+#[derive(Clone, Copy)]
+pub struct Sun {
+  // the direction the light travels in
+  pub direction: Vec3,
+  pub color: Vec3,
+  pub intensity: f32
+}
+
+// what the desktop window shows while a headset is being rendered to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MirrorMode {
+  // drawn separately from the desktop camera
+  Camera,
+  LeftEye,
+  RightEye,
+  SideBySide
+}
+
+impl MirrorMode {
+  // the order F4 steps through them in
+  pub fn next(self) -> Self {
+    match self {
+      MirrorMode::Camera     => MirrorMode::LeftEye,
+      MirrorMode::LeftEye    => MirrorMode::RightEye,
+      MirrorMode::RightEye   => MirrorMode::SideBySide,
+      MirrorMode::SideBySide => MirrorMode::Camera
+    }
+  }
+}
+
+// how the window draws the scene. headsets are always rasterized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+  Raster,
+  // traced a few samples a frame and accumulated while the view holds still, with panels rasterized over it
+  PathTraced
+}
+
+// where a single layer of a target is seen from
+#[derive(Clone, Copy)]
+pub struct RenderView {
+  pub view: Mat4,
+  pub projection: Mat4,
+  pub position: Vec3
+}
+
+// an image the scene is drawn into alongside the window, like the eyes of a headset
+pub struct RenderTarget<'a> {
+  pub image: vk::Image,
+  // covers every layer of the image, as a 2d array view when there are several
+  pub image_view: vk::ImageView,
+  pub depth: &'a Image,
+  pub format: vk::Format,
+  pub extent: vk::Extent2D,
+  // one per array layer. targets with more than one are drawn in a single pass with multiview
+  pub views: Vec<RenderView>,
+  // layout the image is left in once drawn
+  pub final_layout: vk::ImageLayout
+}
+
+impl RenderTarget<'_> {
+  fn view_mask(&self) -> u32 {
+    match self.views.len() {
+      1 => 0,
+      n => (1 << n) - 1
+    }
+  }
+}
+
+// the descriptor set layouts owned elsewhere that the renderer's pipelines bind. materials and the environment are
+// sets 1 and 2 of meshes, a panel's target is set 1 of panels
+#[derive(Clone, Copy)]
+pub struct SetLayouts {
+  pub material: vk::DescriptorSetLayout,
+  pub environment: vk::DescriptorSetLayout,
+  pub panel: vk::DescriptorSetLayout
+}
+
+// the pipelines for drawing into one color format with one view mask
+struct PipelineSet {
+  format: vk::Format,
+  view_mask: u32,
+  // indexed by Renderer::pipeline_index, one per combination of blending and culling
+  meshes: Vec<vk::Pipeline>,
+  skybox: vk::Pipeline,
+  panel: vk::Pipeline
+}
 
 pub struct Renderer {
   frame_index: usize,
+  // frames submitted since the start
+  frames: u64,
   format: vk::SurfaceFormatKHR,
   present_mode: vk::PresentModeKHR,
   extent: vk::Extent2D,
   swapchain_loader: swapchain::Device,
   swapchain: vk::SwapchainKHR,
   images: Vec<vk::Image>,
-  image_views: Vec<vk::ImageView>
-  // depth_memory: vk::DeviceMemory,
-  // depth_buffer: vk::Buffer,
-  // depth_image: vk::Image,
-  // depth_image_view: vk::ImageView,
-  // flight_fences: Vec<vk::Fence>,
-  // image_semaphores: Vec<vk::Semaphore>,
-  // render_semaphores: Vec<vk::Semaphore>
+  image_views: Vec<vk::ImageView>,
+  depth_image: Image,
+  queue: vk::Queue,
+  command_pool: vk::CommandPool,
+  command_buffers: Vec<vk::CommandBuffer>,
+  flight_fences: Vec<vk::Fence>,
+  image_semaphores: Vec<vk::Semaphore>,
+  // one per swapchain image since presentation may still be reading the semaphore when the frame comes around again
+  render_semaphores: Vec<vk::Semaphore>,
+  // one uniform buffer per target per frame in flight
+  frame_buffers: Vec<Buffer>,
+  frame_pointers: Vec<*mut c_void>,
+  descriptor_set_layout: vk::DescriptorSetLayout,
+  descriptor_pool: vk::DescriptorPool,
+  descriptor_sets: Vec<vk::DescriptorSet>,
+  pipeline_layout: vk::PipelineLayout,
+  // shares set 0 and the push constants with the mesh layout, set 1 is the panel's target
+  panel_pipeline_layout: vk::PipelineLayout,
+  pipeline_sets: Vec<PipelineSet>,
+  canvas: CanvasRenderer,
+  surface: SurfaceUploader,
+  tracer: PathTracer,
+  timer: GpuTimer,
+  // how long the last render was held up by the frame fence, acquiring an image and presenting it
+  waited: Duration,
+  mirror: MirrorMode,
+  mode: RenderMode
 }
 
 impl Renderer {
   const MAX_FRAME_COUNT: usize = 2;
+  // the window and both eyes of a headset when they have separate images
+  const MAX_TARGETS: usize = 3;
+  // layers of a multiview target
+  pub const MAX_LAYERS: usize = 2;
+  pub const DEPTH_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+  const CLEAR_COLOR: [f32; 4] = [0.53, 0.71, 0.86, 1.0];
+  const IBL_INTENSITY: f32 = 1.0;
 
   pub fn new(
     instance: &ash::Instance,
     device: &ash::Device,
+    allocator: &Allocator,
     surface_loader: &surface::Instance,
     surface: &vk::SurfaceKHR,
-    gpu: &GPU
+    gpu: &GPU,
+    layouts: SetLayouts
   ) -> Self {
     let swapchain_loader = swapchain::Device::new(instance, device);
 
@@ -46,20 +252,379 @@ impl Renderer {
     };
 
     let image_views = Renderer::get_image_views(device, &images, &format.format);
+    let depth_image = Renderer::create_depth_image(device, allocator, &extent, 1);
+
+    let main_queue = gpu.main_queue();
+    let command_pool = Renderer::create_command_pool(device, main_queue.index as u32);
+    let command_buffers = Renderer::create_command_buffers(device, &command_pool);
+    let (flight_fences, image_semaphores, render_semaphores) = Renderer::create_sync_objects(device, images.len());
+
+    let frame_buffers: Vec<Buffer> = (0..Renderer::MAX_FRAME_COUNT * Renderer::MAX_TARGETS).map(|_| {
+      allocator.create_buffer(
+        device,
+        size_of::<FrameUniforms>() as vk::DeviceSize,
+        vk::BufferUsageFlags::UNIFORM_BUFFER,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+      )
+    }).collect();
+
+    // uniform buffers stay mapped for the lifetime of the renderer
+    let frame_pointers = frame_buffers.iter().map(|buffer| allocator.map(device, buffer)).collect();
+
+    let descriptor_set_layout = Renderer::create_descriptor_set_layout(device);
+    let descriptor_pool = Renderer::create_descriptor_pool(device);
+    let descriptor_sets = {
+      Renderer::create_descriptor_sets(device, &descriptor_pool, &descriptor_set_layout, &frame_buffers)
+    };
+
+    let set_layouts = [descriptor_set_layout, layouts.material, layouts.environment];
+    let pipeline_layout = Renderer::create_pipeline_layout(device, &set_layouts);
+    let panel_pipeline_layout = Renderer::create_pipeline_layout(device, &[descriptor_set_layout, layouts.panel]);
+
+    let pipeline_sets = {
+      vec![Renderer::create_pipeline_set(device, (&pipeline_layout, &panel_pipeline_layout), format.format, 0)]
+    };
 
     Renderer {
       frame_index: 0,
+      frames: 0,
       format,
       present_mode,
       extent,
       swapchain_loader,
       swapchain,
       images,
-      image_views
+      image_views,
+      depth_image,
+      queue: main_queue.queue,
+      command_pool,
+      command_buffers,
+      flight_fences,
+      image_semaphores,
+      render_semaphores,
+      frame_buffers,
+      frame_pointers,
+      descriptor_set_layout,
+      descriptor_pool,
+      descriptor_sets,
+      pipeline_layout,
+      panel_pipeline_layout,
+      pipeline_sets,
+      canvas: CanvasRenderer::new(device, allocator, gpu, Renderer::MAX_FRAME_COUNT),
+      surface: SurfaceUploader::new(Renderer::MAX_FRAME_COUNT),
+      tracer: PathTracer::new(instance, device, gpu, layouts.environment, Renderer::MAX_FRAME_COUNT),
+      timer: GpuTimer::new(device, gpu, Renderer::MAX_FRAME_COUNT),
+      waited: Duration::ZERO,
+      mirror: MirrorMode::LeftEye,
+      mode: RenderMode::Raster
     }
   }
 
-  pub fn clean(&mut self, device: &ash::Device) {
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    unsafe {
+      if let Err(error) = device.device_wait_idle() {
+        panic!("failed to wait for device idle with error: {error}")
+      }
+
+      for set in &self.pipeline_sets {
+        for pipeline in set.meshes.iter().chain([&set.skybox, &set.panel]) {
+          device.destroy_pipeline(*pipeline, None);
+        }
+      }
+
+      device.destroy_pipeline_layout(self.pipeline_layout, None);
+      device.destroy_pipeline_layout(self.panel_pipeline_layout, None);
+      self.canvas.clean(device, allocator);
+      self.surface.clean(device, allocator);
+      self.tracer.clean(device, allocator);
+      self.timer.clean(device);
+      device.destroy_descriptor_pool(self.descriptor_pool, None);
+      device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+
+      for buffer in &self.frame_buffers {
+        device.unmap_memory(buffer.memory);
+        allocator.destroy_buffer(device, buffer);
+      }
+
+      for fence in &self.flight_fences {
+        device.destroy_fence(*fence, None);
+      }
+
+      for semaphore in self.image_semaphores.iter().chain(&self.render_semaphores) {
+        device.destroy_semaphore(*semaphore, None);
+      }
+
+      device.destroy_command_pool(self.command_pool, None);
+    };
+
+    self.clean_swapchain(device, allocator);
+  }
+
+  pub fn aspect_ratio(&self) -> f32 {
+    self.extent.width as f32 / self.extent.height.max(1) as f32
+  }
+
+  pub fn format(&self) -> vk::Format {
+    self.format.format
+  }
+
+  // makes room for the next frame's surface uploads, before it is rendered
+  pub fn prepare_surfaces(&mut self, device: &ash::Device, allocator: &Allocator, surfaces: &[SurfaceDraw]) {
+    self.surface.prepare(device, allocator, self.frame_index, surfaces);
+  }
+
+  // builds the hierarchy the next frame is traced through, before it is rendered. does nothing while rasterizing
+  pub fn prepare_trace(&mut self, device: &ash::Device, allocator: &Allocator, meshes: &[DrawCall]) {
+    if self.mode != RenderMode::PathTraced { return; }
+    self.tracer.prepare(device, allocator, self.frame_index, self.extent, meshes);
+  }
+
+  // the tracer's buffers are freed when switching back to rasterizing
+  pub fn set_render_mode(&mut self, device: &ash::Device, allocator: &Allocator, mode: RenderMode) {
+    if mode == RenderMode::Raster {
+      self.tracer.release(device, allocator);
+    }
+
+    self.mode = mode;
+  }
+
+  // changing any of these starts the accumulated image over
+  pub fn path_tracing_mut(&mut self) -> &mut PathTraceSettings {
+    &mut self.tracer.settings
+  }
+
+  // what the gpu spent on each pass of the last frame it finished
+  pub fn timings(&self) -> &GpuTimings {
+    self.timer.timings()
+  }
+
+  // the part of the last render spent waiting on the gpu and the display rather than working
+  pub fn waited(&self) -> Duration {
+    self.waited
+  }
+
+  // frames submitted so far. anything last used before this count is free once finished_frames reaches it
+  pub fn frames(&self) -> u64 {
+    self.frames
+  }
+
+  // how many of the submitted frames the gpu is known to be done with. the fence of each frame is waited on before
+  // its slot is submitted again
+  pub fn finished_frames(&self) -> u64 {
+    self.frames.saturating_sub(Renderer::MAX_FRAME_COUNT as u64)
+  }
+
+  // falls back to drawing the camera's view whenever there are no eye images to mirror
+  pub fn set_mirror_mode(&mut self, mirror: MirrorMode) {
+    self.mirror = mirror;
+  }
+
+  pub fn mirror_mode(&self) -> MirrorMode {
+    self.mirror
+  }
+
+  // draws the scene into the window and any extra targets in a single submission. the layers of the extra targets are
+  // taken as the eyes of a headset in order, which the window can mirror instead of drawing the camera's view. returns
+  // true when the swapchain no longer matches the surface and has to be recreated
+  pub fn render(
+    &mut self,
+    device: &ash::Device,
+    camera: &Camera,
+    sun: &Sun,
+    environment: &Environment,
+    draws: &DrawList,
+    targets: &[RenderTarget]
+  ) -> bool {
+    for target in targets {
+      self.ensure_pipelines(device, target.format, target.view_mask());
+    }
+
+    let fence = self.flight_fences[self.frame_index];
+    let waiting = Instant::now();
+
+    if let Err(error) = unsafe { device.wait_for_fences(&[fence], true, u64::MAX) } {
+      panic!("failed to wait for frame fence with error: {error}")
+    }
+
+    self.timer.collect(device, self.frame_index);
+
+    let image_index = match unsafe {
+      self.swapchain_loader.acquire_next_image(
+        self.swapchain,
+        u64::MAX,
+        self.image_semaphores[self.frame_index],
+        vk::Fence::null()
+      )
+    } {
+      Ok((image_index, _))                      => image_index as usize,
+      Err(vk::Result::ERROR_OUT_OF_DATE_KHR)    => return true,
+      Err(error)                                => panic!("failed to acquire swapchain image with error: {error}")
+    };
+
+    self.waited = waiting.elapsed();
+
+    if let Err(error) = unsafe { device.reset_fences(&[fence]) } {
+      panic!("failed to reset frame fence with error: {error}")
+    }
+
+    let window = RenderTarget {
+      image: self.images[image_index],
+      image_view: self.image_views[image_index],
+      depth: &self.depth_image,
+      format: self.format.format,
+      extent: self.extent,
+      views: vec![RenderView {
+        view: camera.view(),
+        projection: camera.projection(self.aspect_ratio()),
+        position: camera.position()
+      }],
+      final_layout: vk::ImageLayout::PRESENT_SRC_KHR
+    };
+
+    // every layer of the extra targets in order, for mirroring
+    let eyes: Vec<(&RenderTarget, u32)> = {
+      targets.iter().flat_map(|target| (0..target.views.len() as u32).map(move |layer| (target, layer))).collect()
+    };
+
+    // the traced image is only ever shown in the window so it is never replaced by an eye
+    let traced = self.mode == RenderMode::PathTraced && self.tracer.ready(self.frame_index, self.extent);
+
+    let mirrored = match self.mirror {
+      _ if traced                               => None,
+      MirrorMode::Camera                        => None,
+      MirrorMode::LeftEye                       => eyes.first().map(|eye| vec![*eye]),
+      MirrorMode::RightEye                      => eyes.get(1).map(|eye| vec![*eye]),
+      MirrorMode::SideBySide if eyes.len() >= 2 => Some(eyes[..2].to_vec()),
+      MirrorMode::SideBySide                    => None
+    };
+
+    let drawn: Vec<&RenderTarget> = match mirrored {
+      Some(_) => targets.iter().take(Renderer::MAX_TARGETS).collect(),
+      None    => [&window].into_iter().chain(targets).take(Renderer::MAX_TARGETS).collect()
+    };
+
+    for (target_index, target) in drawn.iter().enumerate() {
+      let views: [ViewUniforms; Renderer::MAX_LAYERS] = std::array::from_fn(|layer| {
+        let view = target.views[layer.min(target.views.len() - 1)];
+        // the sky is centered on the camera so only the rotation of the view is kept
+        let rotation = Mat4::from_mat3(Mat3::from_mat4(view.view));
+
+        ViewUniforms {
+          view: view.view,
+          projection: view.projection,
+          sky: (view.projection * rotation).inverse(),
+          camera_position: view.position.extend(1.0)
+        }
+      });
+
+      let uniforms = FrameUniforms {
+        views,
+        light: sun.direction.normalize().extend(sun.intensity),
+        light_color: sun.color.extend(1.0),
+        environment: Vec4::new(Renderer::IBL_INTENSITY, environment.max_lod(), environment.blend(), 0.0)
+      };
+
+      let bytes = as_bytes(slice::from_ref(&uniforms));
+      let pointer = self.frame_pointers[self.frame_index * Renderer::MAX_TARGETS + target_index];
+      unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), pointer as *mut u8, bytes.len()) };
+    }
+
+    if traced {
+      self.tracer.update(self.frame_index, &window.views[0], sun, environment.blend());
+    }
+
+    let command_buffer = self.command_buffers[self.frame_index];
+    let mirror_target = mirrored.map(|eyes| (window.image, eyes));
+    let passes = self.record(device, command_buffer, (&drawn, traced), mirror_target, environment, draws);
+    self.timer.submitted(self.frame_index, passes);
+
+    let wait_semaphores = [self.image_semaphores[self.frame_index]];
+    let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+    let signal_semaphores = [self.render_semaphores[image_index]];
+    let command_buffers = [command_buffer];
+
+    let submit_info = {
+      vk::SubmitInfo::default()
+        .wait_semaphores(&wait_semaphores)
+        .wait_dst_stage_mask(&wait_stages)
+        .command_buffers(&command_buffers)
+        .signal_semaphores(&signal_semaphores)
+    };
+
+    if let Err(error) = unsafe { device.queue_submit(self.queue, &[submit_info], fence) } {
+      panic!("failed to submit frame with error: {error}")
+    }
+
+    let swapchains = [self.swapchain];
+    let image_indices = [image_index as u32];
+
+    let present_info = {
+      vk::PresentInfoKHR::default()
+        .wait_semaphores(&signal_semaphores)
+        .swapchains(&swapchains)
+        .image_indices(&image_indices)
+    };
+
+    self.frame_index = (self.frame_index + 1) % Renderer::MAX_FRAME_COUNT;
+    self.frames += 1;
+    let presenting = Instant::now();
+
+    let out_of_date = match unsafe { self.swapchain_loader.queue_present(self.queue, &present_info) } {
+      Ok(suboptimal)                          => suboptimal,
+      Err(vk::Result::ERROR_OUT_OF_DATE_KHR)  => true,
+      Err(error)                              => panic!("failed to present frame with error: {error}")
+    };
+
+    self.waited += presenting.elapsed();
+    out_of_date
+  }
+
+  pub fn recreate_swapchain(
+    &mut self,
+    device: &ash::Device,
+    allocator: &Allocator,
+    surface_loader: &surface::Instance,
+    surface: &vk::SurfaceKHR,
+    gpu: &GPU
+  ) {
+    if let Err(error) = unsafe { device.device_wait_idle() } {
+      panic!("failed to wait for device idle with error: {error}")
+    }
+
+    self.clean_swapchain(device, allocator);
+
+    let (format, present_mode, extent, image_count, transform) = {
+      Renderer::get_swapchain_details(surface_loader, surface, gpu)
+    };
+
+    self.swapchain = {
+      Renderer::create_swapchain(&self.swapchain_loader, surface, &format, &present_mode, &extent, &image_count, transform)
+    };
+
+    self.images = match unsafe { self.swapchain_loader.get_swapchain_images(self.swapchain) } {
+      Ok(images) => images,
+      Err(error) => panic!("failed to get swapchain images with error: {error}")
+    };
+
+    self.format = format;
+    self.present_mode = present_mode;
+    self.extent = extent;
+    self.image_views = Renderer::get_image_views(device, &self.images, &format.format);
+    self.depth_image = Renderer::create_depth_image(device, allocator, &extent, 1);
+
+    // the image count can change with the swapchain so the per image semaphores are rebuilt as well
+    unsafe {
+      for semaphore in &self.render_semaphores {
+        device.destroy_semaphore(*semaphore, None);
+      }
+    }
+
+    self.render_semaphores = (0..self.images.len()).map(|_| Renderer::create_semaphore(device)).collect();
+  }
+
+  fn clean_swapchain(&mut self, device: &ash::Device, allocator: &Allocator) {
+    allocator.destroy_image(device, &self.depth_image);
+
     unsafe {
       for image_view in &self.image_views {
         device.destroy_image_view(*image_view, None);
@@ -68,6 +633,428 @@ impl Renderer {
     };
   }
 
+  fn record(
+    &self,
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    // whether the first target is the window with the scene path traced into it
+    (targets, traced): (&[&RenderTarget], bool),
+    mirror: Option<(vk::Image, Vec<(&RenderTarget, u32)>)>,
+    environment: &Environment,
+    draws: &DrawList
+  ) -> Vec<&'static str> {
+    unsafe {
+      if let Err(error) = device.reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty()) {
+        panic!("failed to reset frame command buffer with error: {error}")
+      }
+
+      let begin_info = vk::CommandBufferBeginInfo::default().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+      if let Err(error) = device.begin_command_buffer(command_buffer, &begin_info) {
+        panic!("failed to begin frame command buffer with error: {error}")
+      }
+    };
+
+    // the passes timed, in the order they were recorded
+    let mut passes = Vec::new();
+    let time = |passes: &mut Vec<&'static str>, name, record: &dyn Fn()| {
+      self.timer.time(device, command_buffer, self.frame_index, (passes, name), record);
+    };
+
+    self.timer.begin_frame(device, command_buffer, self.frame_index);
+
+    if !draws.canvases.is_empty() || draws.atlas.is_some() {
+      time(&mut passes, "panels", &|| self.canvas.record(device, command_buffer, self.frame_index, &draws.canvases, draws.atlas.as_ref()));
+    }
+
+    if !draws.surfaces.is_empty() {
+      time(&mut passes, "surfaces", &|| self.surface.record(device, command_buffer, self.frame_index, &draws.surfaces));
+    }
+
+    for (target_index, target) in targets.iter().enumerate() {
+      let descriptor_set = self.descriptor_sets[self.frame_index * Renderer::MAX_TARGETS + target_index];
+      let traced = traced && target_index == 0;
+
+      if traced {
+        time(&mut passes, "path trace", &|| {
+          self.tracer.record(device, command_buffer, self.frame_index, environment.descriptor_set, target.image);
+        });
+      }
+
+      let name = match target.final_layout {
+        vk::ImageLayout::PRESENT_SRC_KHR => "window",
+        _                                => "headset"
+      };
+
+      time(&mut passes, name, &|| self.record_target(device, command_buffer, (target, traced), descriptor_set, environment, draws));
+    }
+
+    if let Some((window, eyes)) = mirror {
+      time(&mut passes, "mirror", &|| self.record_mirror(device, command_buffer, window, &eyes));
+    }
+
+    self.timer.end_frame(device, command_buffer, self.frame_index);
+
+    if let Err(error) = unsafe { device.end_command_buffer(command_buffer) } {
+      panic!("failed to end frame command buffer with error: {error}")
+    }
+
+    passes
+  }
+
+  fn record_target(
+    &self,
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    // a traced target already holds the scene, so only panels are drawn over it
+    (target, traced): (&RenderTarget, bool),
+    descriptor_set: vk::DescriptorSet,
+    environment: &Environment,
+    draws: &DrawList
+  ) {
+    let layers = target.views.len() as u32;
+
+    if !traced {
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        target.image,
+        Allocator::color_range(0, 1, 0, layers),
+        (vk::ImageLayout::UNDEFINED, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+        (vk::AccessFlags::empty(), vk::AccessFlags::COLOR_ATTACHMENT_WRITE),
+        (vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+      );
+    }
+
+    let load_op = match traced {
+      true  => vk::AttachmentLoadOp::LOAD,
+      false => vk::AttachmentLoadOp::CLEAR
+    };
+
+    let depth_range = {
+      vk::ImageSubresourceRange::default()
+        .aspect_mask(vk::ImageAspectFlags::DEPTH)
+        .level_count(1)
+        .layer_count(layers)
+    };
+
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      target.depth.image,
+      depth_range,
+      (vk::ImageLayout::UNDEFINED, vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL),
+      (vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE, vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE),
+      (vk::PipelineStageFlags::LATE_FRAGMENT_TESTS, vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS)
+    );
+
+    let color_attachments = [
+      vk::RenderingAttachmentInfo::default()
+        .image_view(target.image_view)
+        .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+        .load_op(load_op)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .clear_value(vk::ClearValue { color: vk::ClearColorValue { float32: Renderer::CLEAR_COLOR } })
+    ];
+
+    let depth_attachment = {
+      vk::RenderingAttachmentInfo::default()
+        .image_view(target.depth.view)
+        .image_layout(vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .clear_value(vk::ClearValue { depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 } })
+    };
+
+    let rendering_info = {
+      vk::RenderingInfo::default()
+        .render_area(vk::Rect2D { offset: vk::Offset2D::default(), extent: target.extent })
+        .layer_count(1)
+        .view_mask(target.view_mask())
+        .color_attachments(&color_attachments)
+        .depth_attachment(&depth_attachment)
+    };
+
+    let viewport = {
+      vk::Viewport::default()
+        .width(target.extent.width as f32)
+        .height(target.extent.height as f32)
+        .max_depth(1.0)
+    };
+
+    let scissor = vk::Rect2D { offset: vk::Offset2D::default(), extent: target.extent };
+
+    // opaque geometry goes first, then the sky fills whatever it left uncovered, then blended geometry and panels from
+    // back to front so they composite over what is behind them. with several views the order is taken from between them
+    let position = target.views.iter().map(|view| view.position).sum::<Vec3>() / layers as f32;
+    let distance = |item: &Transparent| item.position().distance_squared(position);
+
+    let meshes = match traced {
+      true  => &[][..],
+      false => &draws.meshes[..]
+    };
+
+    let opaque: Vec<&DrawCall> = meshes.iter().filter(|draw| !draw.material.blend).collect();
+    let mut transparent: Vec<Transparent> = meshes.iter()
+      .filter(|draw| draw.material.blend)
+      .map(Transparent::Mesh)
+      .chain(draws.panels.iter().map(Transparent::Panel))
+      .collect();
+
+    transparent.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+
+    let pipelines = self.pipeline_set(target.format, target.view_mask());
+
+    unsafe {
+      device.cmd_begin_rendering(command_buffer, &rendering_info);
+      device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+      device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+      device.cmd_bind_descriptor_sets(
+        command_buffer,
+        vk::PipelineBindPoint::GRAPHICS,
+        self.pipeline_layout,
+        0,
+        &[descriptor_set],
+        &[]
+      );
+      device.cmd_bind_descriptor_sets(
+        command_buffer,
+        vk::PipelineBindPoint::GRAPHICS,
+        self.pipeline_layout,
+        2,
+        &[environment.descriptor_set],
+        &[]
+      );
+
+      self.record_draws(device, command_buffer, pipelines, &opaque);
+
+      if !traced {
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipelines.skybox);
+        device.cmd_draw(command_buffer, 3, 1, 0, 0);
+      }
+
+      // switching to the panel layout disturbs the environment set, so it is bound again for every run of meshes
+      for run in transparent.chunk_by(|a, b| matches!((a, b), (Transparent::Mesh(_), Transparent::Mesh(_)))) {
+        match run[0] {
+          Transparent::Mesh(_) => {
+            let meshes: Vec<&DrawCall> = run.iter().filter_map(|item| match item {
+              Transparent::Mesh(draw) => Some(*draw),
+              Transparent::Panel(_)   => None
+            }).collect();
+
+            device.cmd_bind_descriptor_sets(
+              command_buffer,
+              vk::PipelineBindPoint::GRAPHICS,
+              self.pipeline_layout,
+              2,
+              &[environment.descriptor_set],
+              &[]
+            );
+
+            self.record_draws(device, command_buffer, pipelines, &meshes);
+          },
+          Transparent::Panel(draw) => self.record_panel(device, command_buffer, pipelines, draw)
+        }
+      }
+
+      device.cmd_end_rendering(command_buffer);
+    };
+
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      target.image,
+      Allocator::color_range(0, 1, 0, layers),
+      (vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL, target.final_layout),
+      (vk::AccessFlags::COLOR_ATTACHMENT_WRITE, vk::AccessFlags::empty()),
+      (vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT, vk::PipelineStageFlags::BOTTOM_OF_PIPE)
+    );
+  }
+
+  // copies eye layers into the window instead of drawing it, side by side when there are two. each keeps its aspect
+  // ratio with black bars filling the rest. the eye images are returned to the layout they were left in
+  fn record_mirror(&self, device: &ash::Device, command_buffer: vk::CommandBuffer, window: vk::Image, eyes: &[(&RenderTarget, u32)]) {
+    let window_range = Allocator::color_range(0, 1, 0, 1);
+
+    for (target, layer) in eyes {
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        target.image,
+        Allocator::color_range(0, 1, *layer, 1),
+        (target.final_layout, vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+        (vk::AccessFlags::COLOR_ATTACHMENT_WRITE, vk::AccessFlags::TRANSFER_READ),
+        (vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT, vk::PipelineStageFlags::TRANSFER)
+      );
+    }
+
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      window,
+      window_range,
+      (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+      (vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE),
+      (vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::TRANSFER)
+    );
+
+    unsafe {
+      let black = vk::ClearColorValue { float32: [0.0, 0.0, 0.0, 1.0] };
+      device.cmd_clear_color_image(command_buffer, window, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &black, &[window_range]);
+    };
+
+    // the blits write over the cleared image
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      window,
+      window_range,
+      (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+      (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::TRANSFER_WRITE),
+      (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::TRANSFER)
+    );
+
+    let slot_width = self.extent.width / eyes.len() as u32;
+
+    let subresource = |layer: u32| {
+      vk::ImageSubresourceLayers::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .mip_level(0)
+        .base_array_layer(layer)
+        .layer_count(1)
+    };
+
+    for (index, (target, layer)) in eyes.iter().enumerate() {
+      // largest rectangle with the eye's aspect ratio that fits its slot, centered in it
+      let scale = (slot_width as f32 / target.extent.width as f32).min(self.extent.height as f32 / target.extent.height as f32);
+      let width = (target.extent.width as f32 * scale) as i32;
+      let height = (target.extent.height as f32 * scale) as i32;
+      let x = (index as u32 * slot_width) as i32 + (slot_width as i32 - width) / 2;
+      let y = (self.extent.height as i32 - height) / 2;
+
+      let region = {
+        vk::ImageBlit::default()
+          .src_subresource(subresource(*layer))
+          .src_offsets([
+            vk::Offset3D::default(),
+            vk::Offset3D { x: target.extent.width as i32, y: target.extent.height as i32, z: 1 }
+          ])
+          .dst_subresource(subresource(0))
+          .dst_offsets([vk::Offset3D { x, y, z: 0 }, vk::Offset3D { x: x + width, y: y + height, z: 1 }])
+      };
+
+      unsafe {
+        device.cmd_blit_image(
+          command_buffer,
+          target.image,
+          vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+          window,
+          vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+          &[region],
+          vk::Filter::LINEAR
+        )
+      };
+    }
+
+    for (target, layer) in eyes {
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        target.image,
+        Allocator::color_range(0, 1, *layer, 1),
+        (vk::ImageLayout::TRANSFER_SRC_OPTIMAL, target.final_layout),
+        (vk::AccessFlags::TRANSFER_READ, vk::AccessFlags::empty()),
+        (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::BOTTOM_OF_PIPE)
+      );
+    }
+
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      window,
+      window_range,
+      (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::PRESENT_SRC_KHR),
+      (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::empty()),
+      (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::BOTTOM_OF_PIPE)
+    );
+  }
+
+  // binds pipelines and material sets only when they change between draws
+  fn record_draws(
+    &self,
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    pipelines: &PipelineSet,
+    draws: &[&DrawCall]
+  ) {
+    let mut bound_pipeline = vk::Pipeline::null();
+    let mut bound_material = vk::DescriptorSet::null();
+
+    unsafe {
+      for draw in draws {
+        let pipeline = pipelines.meshes[Renderer::pipeline_index(draw.material.blend, draw.material.double_sided)];
+
+        if pipeline != bound_pipeline {
+          device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+          bound_pipeline = pipeline;
+        }
+
+        if draw.material.descriptor_set != bound_material {
+          device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout,
+            1,
+            &[draw.material.descriptor_set],
+            &[]
+          );
+          bound_material = draw.material.descriptor_set;
+        }
+
+        let push_constants = PushConstants { model: draw.transform, panel: Vec4::ZERO };
+
+        device.cmd_push_constants(
+          command_buffer,
+          self.pipeline_layout,
+          vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+          0,
+          as_bytes(slice::from_ref(&push_constants))
+        );
+
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[draw.mesh.vertex_buffer.buffer], &[0]);
+        device.cmd_bind_index_buffer(command_buffer, draw.mesh.index_buffer.buffer, 0, vk::IndexType::UINT32);
+        device.cmd_draw_indexed(command_buffer, draw.mesh.index_count, 1, 0, 0, 0);
+      }
+    };
+  }
+
+  // panels are drawn one at a time since they come interleaved with blended meshes
+  fn record_panel(&self, device: &ash::Device, command_buffer: vk::CommandBuffer, pipelines: &PipelineSet, draw: &PanelDraw) {
+    let push_constants = PushConstants { model: draw.transform, panel: Vec4::new(draw.opacity, 0.0, 0.0, 0.0) };
+
+    unsafe {
+      device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipelines.panel);
+      device.cmd_bind_descriptor_sets(
+        command_buffer,
+        vk::PipelineBindPoint::GRAPHICS,
+        self.panel_pipeline_layout,
+        1,
+        &[draw.descriptor_set],
+        &[]
+      );
+      device.cmd_push_constants(
+        command_buffer,
+        self.panel_pipeline_layout,
+        vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+        0,
+        as_bytes(slice::from_ref(&push_constants))
+      );
+
+      device.cmd_bind_vertex_buffers(command_buffer, 0, &[draw.mesh.vertex_buffer.buffer], &[0]);
+      device.cmd_bind_index_buffer(command_buffer, draw.mesh.index_buffer.buffer, 0, vk::IndexType::UINT32);
+      device.cmd_draw_indexed(command_buffer, draw.mesh.index_count, 1, 0, 0, 0);
+    };
+  }
+
   fn get_swapchain_details(
     surface_loader: &surface::Instance,
     surface: &vk::SurfaceKHR,
@@ -161,7 +1148,8 @@ impl Renderer {
         .present_mode(*present_mode)
         .image_extent(*extent)
         .image_array_layers(1)
-        .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+        // transfer so eye images can be mirrored into it
+        .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_DST)
         .pre_transform(transform)
         .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
         .clipped(true)
@@ -214,4 +1202,343 @@ impl Renderer {
 
     image_views
   }
-}
\ No newline at end of file
+
+  // several layers make a 2d array for multiview targets
+  pub fn create_depth_image(device: &ash::Device, allocator: &Allocator, extent: &vk::Extent2D, layers: u32) -> Image {
+    let create_info = {
+      vk::ImageCreateInfo::default()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(Renderer::DEPTH_FORMAT)
+        .extent((*extent).into())
+        .mip_levels(1)
+        .array_layers(layers)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+    };
+
+    let view_type = match layers {
+      1 => vk::ImageViewType::TYPE_2D,
+      _ => vk::ImageViewType::TYPE_2D_ARRAY
+    };
+
+    allocator.create_image(device, &create_info, vk::ImageAspectFlags::DEPTH, view_type)
+  }
+
+  fn create_command_pool(device: &ash::Device, queue_family_index: u32) -> vk::CommandPool {
+    let create_info = {
+      vk::CommandPoolCreateInfo::default()
+        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+        .queue_family_index(queue_family_index)
+    };
+
+    match unsafe { device.create_command_pool(&create_info, None) } {
+      Ok(command_pool)  => command_pool,
+      Err(error)        => panic!("failed to create renderer command pool with error: {error}")
+    }
+  }
+
+  fn create_command_buffers(device: &ash::Device, command_pool: &vk::CommandPool) -> Vec<vk::CommandBuffer> {
+    let allocate_info = {
+      vk::CommandBufferAllocateInfo::default()
+        .command_pool(*command_pool)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(Renderer::MAX_FRAME_COUNT as u32)
+    };
+
+    match unsafe { device.allocate_command_buffers(&allocate_info) } {
+      Ok(command_buffers) => command_buffers,
+      Err(error)          => panic!("failed to allocate frame command buffers with error: {error}")
+    }
+  }
+
+  fn create_sync_objects(
+    device: &ash::Device,
+    image_count: usize
+  ) -> (Vec<vk::Fence>, Vec<vk::Semaphore>, Vec<vk::Semaphore>) {
+    // fences start signaled so the first wait on each frame returns immediately
+    let fence_info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
+
+    let flight_fences = (0..Renderer::MAX_FRAME_COUNT).map(|_| {
+      match unsafe { device.create_fence(&fence_info, None) } {
+        Ok(fence)  => fence,
+        Err(error) => panic!("failed to create frame fence with error: {error}")
+      }
+    }).collect();
+
+    let image_semaphores = (0..Renderer::MAX_FRAME_COUNT).map(|_| Renderer::create_semaphore(device)).collect();
+    let render_semaphores = (0..image_count).map(|_| Renderer::create_semaphore(device)).collect();
+
+    (flight_fences, image_semaphores, render_semaphores)
+  }
+
+  fn create_semaphore(device: &ash::Device) -> vk::Semaphore {
+    match unsafe { device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None) } {
+      Ok(semaphore) => semaphore,
+      Err(error)    => panic!("failed to create semaphore with error: {error}")
+    }
+  }
+
+  fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+    let bindings = [
+      vk::DescriptorSetLayoutBinding::default()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+    ];
+
+    let create_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+    match unsafe { device.create_descriptor_set_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create frame descriptor set layout with error: {error}")
+    }
+  }
+
+  fn create_descriptor_pool(device: &ash::Device) -> vk::DescriptorPool {
+    let pool_sizes = [
+      vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::UNIFORM_BUFFER)
+        .descriptor_count((Renderer::MAX_FRAME_COUNT * Renderer::MAX_TARGETS) as u32)
+    ];
+
+    let create_info = {
+      vk::DescriptorPoolCreateInfo::default()
+        .max_sets((Renderer::MAX_FRAME_COUNT * Renderer::MAX_TARGETS) as u32)
+        .pool_sizes(&pool_sizes)
+    };
+
+    match unsafe { device.create_descriptor_pool(&create_info, None) } {
+      Ok(pool)   => pool,
+      Err(error) => panic!("failed to create frame descriptor pool with error: {error}")
+    }
+  }
+
+  fn create_descriptor_sets(
+    device: &ash::Device,
+    descriptor_pool: &vk::DescriptorPool,
+    descriptor_set_layout: &vk::DescriptorSetLayout,
+    frame_buffers: &[Buffer]
+  ) -> Vec<vk::DescriptorSet> {
+    let layouts = vec![*descriptor_set_layout; Renderer::MAX_FRAME_COUNT * Renderer::MAX_TARGETS];
+
+    let allocate_info = {
+      vk::DescriptorSetAllocateInfo::default()
+        .descriptor_pool(*descriptor_pool)
+        .set_layouts(&layouts)
+    };
+
+    let descriptor_sets = match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+      Ok(sets)   => sets,
+      Err(error) => panic!("failed to allocate frame descriptor sets with error: {error}")
+    };
+
+    for (descriptor_set, buffer) in descriptor_sets.iter().zip(frame_buffers) {
+      let buffer_info = [
+        vk::DescriptorBufferInfo::default()
+          .buffer(buffer.buffer)
+          .range(vk::WHOLE_SIZE)
+      ];
+
+      let write = {
+        vk::WriteDescriptorSet::default()
+          .dst_set(*descriptor_set)
+          .dst_binding(0)
+          .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+          .buffer_info(&buffer_info)
+      };
+
+      unsafe { device.update_descriptor_sets(&[write], &[]) };
+    }
+
+    descriptor_sets
+  }
+
+  // pipelines are baked against a color format and view mask, so targets that differ from the window in either get
+  // their own set
+  fn ensure_pipelines(&mut self, device: &ash::Device, format: vk::Format, view_mask: u32) {
+    if self.pipeline_sets.iter().any(|set| set.format == format && set.view_mask == view_mask) { return; }
+
+    let set = Renderer::create_pipeline_set(device, (&self.pipeline_layout, &self.panel_pipeline_layout), format, view_mask);
+    self.pipeline_sets.push(set);
+  }
+
+  fn pipeline_set(&self, format: vk::Format, view_mask: u32) -> &PipelineSet {
+    match self.pipeline_sets.iter().find(|set| set.format == format && set.view_mask == view_mask) {
+      Some(set) => set,
+      None      => panic!("failed to find pipelines for color format {format:?} and view mask {view_mask:#b}")
+    }
+  }
+
+  // layouts are the mesh layout and the panel layout
+  fn create_pipeline_set(
+    device: &ash::Device,
+    (layout, panel_layout): (&vk::PipelineLayout, &vk::PipelineLayout),
+    format: vk::Format,
+    view_mask: u32
+  ) -> PipelineSet {
+    let meshes = [(false, false), (false, true), (true, false), (true, true)].iter().map(|(blend, double_sided)| {
+      let kind = PipelineKind::Mesh { blend: *blend, double_sided: *double_sided };
+      Renderer::create_pipeline(device, layout, format, view_mask, kind)
+    }).collect();
+
+    PipelineSet {
+      format,
+      view_mask,
+      meshes,
+      skybox: Renderer::create_pipeline(device, layout, format, view_mask, PipelineKind::Skybox),
+      panel: Renderer::create_pipeline(device, panel_layout, format, view_mask, PipelineKind::Panel)
+    }
+  }
+
+  fn pipeline_index(blend: bool, double_sided: bool) -> usize {
+    (blend as usize) * 2 + double_sided as usize
+  }
+
+  // set 0 is the frame, set 1 the material and set 2 the image based lighting, or set 1 the target for panels. both
+  // share the push constant range so set 0 stays bound when switching between them
+  fn create_pipeline_layout(device: &ash::Device, set_layouts: &[vk::DescriptorSetLayout]) -> vk::PipelineLayout {
+    let push_constant_ranges = [
+      vk::PushConstantRange::default()
+        .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+        .size(size_of::<PushConstants>() as u32)
+    ];
+
+    let create_info = {
+      vk::PipelineLayoutCreateInfo::default()
+        .set_layouts(set_layouts)
+        .push_constant_ranges(&push_constant_ranges)
+    };
+
+    match unsafe { device.create_pipeline_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create pipeline layout with error: {error}")
+    }
+  }
+
+  // blended pipelines test against depth without writing it. double sided ones skip back face culling. the skybox
+  // has no vertex input and only passes on the far plane where nothing was drawn. panels blend and are one sided
+  fn create_pipeline(
+    device: &ash::Device,
+    layout: &vk::PipelineLayout,
+    color_format: vk::Format,
+    view_mask: u32,
+    kind: PipelineKind
+  ) -> vk::Pipeline {
+    let (spirv, blend, cull_mode) = match kind {
+      PipelineKind::Mesh { blend, double_sided: true }  => (shader::MESH, blend, vk::CullModeFlags::NONE),
+      PipelineKind::Mesh { blend, double_sided: false } => (shader::MESH, blend, vk::CullModeFlags::BACK),
+      PipelineKind::Skybox                              => (shader::SKYBOX, false, vk::CullModeFlags::NONE),
+      PipelineKind::Panel                               => (shader::PANEL, true, vk::CullModeFlags::BACK)
+    };
+
+    let skybox = matches!(kind, PipelineKind::Skybox);
+    let module = shader::create_shader_module(device, spirv);
+
+    let stages = [
+      vk::PipelineShaderStageCreateInfo::default()
+        .stage(vk::ShaderStageFlags::VERTEX)
+        .module(module)
+        .name(c"vertex_main"),
+      vk::PipelineShaderStageCreateInfo::default()
+        .stage(vk::ShaderStageFlags::FRAGMENT)
+        .module(module)
+        .name(c"fragment_main")
+    ];
+
+    let bindings = [Vertex::binding_description()];
+    let attributes = Vertex::attribute_descriptions();
+
+    let vertex_input = match skybox {
+      true  => vk::PipelineVertexInputStateCreateInfo::default(),
+      false => {
+        vk::PipelineVertexInputStateCreateInfo::default()
+          .vertex_binding_descriptions(&bindings)
+          .vertex_attribute_descriptions(&attributes)
+      }
+    };
+
+    let input_assembly = {
+      vk::PipelineInputAssemblyStateCreateInfo::default()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+    };
+
+    let viewport_state = {
+      vk::PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1)
+    };
+
+    let rasterization = {
+      vk::PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(vk::PolygonMode::FILL)
+        .cull_mode(cull_mode)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .line_width(1.0)
+    };
+
+    let multisample = {
+      vk::PipelineMultisampleStateCreateInfo::default()
+        .rasterization_samples(vk::SampleCountFlags::TYPE_1)
+    };
+
+    let depth_stencil = {
+      vk::PipelineDepthStencilStateCreateInfo::default()
+        .depth_test_enable(true)
+        .depth_write_enable(!blend && !skybox)
+        .depth_compare_op(if skybox { vk::CompareOp::LESS_OR_EQUAL } else { vk::CompareOp::LESS })
+    };
+
+    let blend_attachments = [
+      vk::PipelineColorBlendAttachmentState::default()
+        .blend_enable(blend)
+        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .color_blend_op(vk::BlendOp::ADD)
+        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+        .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .alpha_blend_op(vk::BlendOp::ADD)
+        .color_write_mask(vk::ColorComponentFlags::RGBA)
+    ];
+
+    let color_blend = vk::PipelineColorBlendStateCreateInfo::default().attachments(&blend_attachments);
+
+    let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+    let color_formats = [color_format];
+    let mut rendering = {
+      vk::PipelineRenderingCreateInfo::default()
+        .view_mask(view_mask)
+        .color_attachment_formats(&color_formats)
+        .depth_attachment_format(Renderer::DEPTH_FORMAT)
+    };
+
+    let create_info = {
+      vk::GraphicsPipelineCreateInfo::default()
+        .stages(&stages)
+        .vertex_input_state(&vertex_input)
+        .input_assembly_state(&input_assembly)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization)
+        .multisample_state(&multisample)
+        .depth_stencil_state(&depth_stencil)
+        .color_blend_state(&color_blend)
+        .dynamic_state(&dynamic_state)
+        .layout(*layout)
+        .push_next(&mut rendering)
+    };
+
+    let pipeline = match unsafe { device.create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None) } {
+      Ok(pipelines)   => pipelines[0],
+      Err((_, error)) => panic!("failed to create {kind:?} pipeline with error: {error}")
+    };
+
+    unsafe { device.destroy_shader_module(module, None) };
+
+    pipeline
+  }
+}
@@ -0,0 +1,604 @@
+use crate::gvp_engine::{
+  allocator::{as_bytes, Allocator, Buffer, Image},
+  compositor::Compositor,
+  gpu::GPU,
+  gui::{Canvas, Quad},
+  shader,
+  text::{CanvasGlyphs, GlyphAtlas, GlyphQuad},
+  texture::{SamplerInfo, Texture}
+};
+
+use ash::vk;
+use glam::Vec2;
+
+use std::{ffi::c_void, mem::{offset_of, size_of}, ops::Range, slice};
+
+// a gui's canvas, the glyphs of its text and the panel target it is drawn into
+pub struct CanvasDraw<'a> {
+  pub target: &'a Image,
+  pub canvas: &'a Canvas,
+  pub glyphs: &'a CanvasGlyphs
+}
+
+// rows of the glyph atlas written since the last frame, copied in before any canvas is drawn
+pub struct AtlasUpload<'a> {
+  pub pixels: &'a [u8],
+  pub rows: Range<u32>
+}
+
+// buffers of one frame in flight, mapped for the lifetime of the renderer
+struct FrameBuffers {
+  quads: Buffer,
+  glyphs: Buffer,
+  staging: Buffer,
+  quad_pointer: *mut c_void,
+  glyph_pointer: *mut c_void,
+  staging_pointer: *mut c_void
+}
+
+// draws canvases into panel targets at the start of the frame. every batch is one instanced draw of quads followed by
+// one of glyphs sampled from the atlas. targets are left in SHADER_READ_ONLY_OPTIMAL for the panels sampling them
+// later in the frame
+pub struct CanvasRenderer {
+  pipeline_layout: vk::PipelineLayout,
+  quad_pipeline: vk::Pipeline,
+  glyph_pipeline: vk::Pipeline,
+  descriptor_set_layout: vk::DescriptorSetLayout,
+  descriptor_pool: vk::DescriptorPool,
+  descriptor_set: vk::DescriptorSet,
+  atlas: Image,
+  sampler: vk::Sampler,
+  frames: Vec<FrameBuffers>
+}
+
+impl CanvasRenderer {
+  // quads and glyphs across every canvas in a frame, anything past this is dropped
+  const MAX_QUADS: usize = 32768;
+  const MAX_GLYPHS: usize = 65536;
+
+  pub fn new(device: &ash::Device, allocator: &Allocator, gpu: &GPU, frame_count: usize) -> Self {
+    let mapped = |size: usize, usage: vk::BufferUsageFlags| {
+      let buffer = allocator.create_buffer(
+        device,
+        size as vk::DeviceSize,
+        usage,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+      );
+
+      let pointer = allocator.map(device, &buffer);
+      (buffer, pointer)
+    };
+
+    let frames = (0..frame_count).map(|_| {
+      let (quads, quad_pointer) = mapped(CanvasRenderer::MAX_QUADS * size_of::<Quad>(), vk::BufferUsageFlags::VERTEX_BUFFER);
+      let (glyphs, glyph_pointer) = mapped(CanvasRenderer::MAX_GLYPHS * size_of::<GlyphQuad>(), vk::BufferUsageFlags::VERTEX_BUFFER);
+      let (staging, staging_pointer) = mapped((GlyphAtlas::SIZE * GlyphAtlas::SIZE) as usize, vk::BufferUsageFlags::TRANSFER_SRC);
+
+      FrameBuffers { quads, glyphs, staging, quad_pointer, glyph_pointer, staging_pointer }
+    }).collect();
+
+    let descriptor_set_layout = CanvasRenderer::create_descriptor_set_layout(device);
+    let (descriptor_pool, descriptor_set) = CanvasRenderer::create_descriptor_set(device, &descriptor_set_layout);
+    let pipeline_layout = CanvasRenderer::create_pipeline_layout(device, &descriptor_set_layout);
+
+    let quad_attributes = [
+      CanvasRenderer::attribute(0, vk::Format::R32G32_SFLOAT, offset_of!(Quad, center)),
+      CanvasRenderer::attribute(1, vk::Format::R32G32_SFLOAT, offset_of!(Quad, half_size)),
+      CanvasRenderer::attribute(2, vk::Format::R32G32B32A32_SFLOAT, offset_of!(Quad, color)),
+      CanvasRenderer::attribute(3, vk::Format::R32G32B32A32_SFLOAT, offset_of!(Quad, border_color)),
+      CanvasRenderer::attribute(4, vk::Format::R32G32B32A32_SFLOAT, offset_of!(Quad, shape))
+    ];
+
+    let glyph_attributes = [
+      CanvasRenderer::attribute(0, vk::Format::R32G32_SFLOAT, offset_of!(GlyphQuad, position)),
+      CanvasRenderer::attribute(1, vk::Format::R32G32_SFLOAT, offset_of!(GlyphQuad, size)),
+      CanvasRenderer::attribute(2, vk::Format::R32G32_SFLOAT, offset_of!(GlyphQuad, uv_min)),
+      CanvasRenderer::attribute(3, vk::Format::R32G32_SFLOAT, offset_of!(GlyphQuad, uv_max)),
+      CanvasRenderer::attribute(4, vk::Format::R32G32B32A32_SFLOAT, offset_of!(GlyphQuad, color)),
+      CanvasRenderer::attribute(5, vk::Format::R32G32B32A32_SFLOAT, offset_of!(GlyphQuad, shape))
+    ];
+
+    let renderer = CanvasRenderer {
+      pipeline_layout,
+      quad_pipeline: CanvasRenderer::create_pipeline(device, &pipeline_layout, shader::CANVAS, size_of::<Quad>(), &quad_attributes),
+      glyph_pipeline: CanvasRenderer::create_pipeline(device, &pipeline_layout, shader::GLYPH, size_of::<GlyphQuad>(), &glyph_attributes),
+      descriptor_set_layout,
+      descriptor_pool,
+      descriptor_set,
+      atlas: CanvasRenderer::create_atlas(device, allocator),
+      sampler: Texture::create_sampler(device, gpu, &SamplerInfo::CLAMP, 1),
+      frames
+    };
+
+    renderer.write_descriptor_set(device);
+
+    renderer
+  }
+
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    unsafe {
+      device.destroy_pipeline(self.quad_pipeline, None);
+      device.destroy_pipeline(self.glyph_pipeline, None);
+      device.destroy_pipeline_layout(self.pipeline_layout, None);
+      device.destroy_descriptor_pool(self.descriptor_pool, None);
+      device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+      device.destroy_sampler(self.sampler, None);
+
+      for frame in &self.frames {
+        for buffer in [&frame.quads, &frame.glyphs, &frame.staging] {
+          device.unmap_memory(buffer.memory);
+          allocator.destroy_buffer(device, buffer);
+        }
+      }
+    };
+
+    allocator.destroy_image(device, &self.atlas);
+  }
+
+  pub fn record(
+    &self,
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    frame_index: usize,
+    draws: &[CanvasDraw],
+    atlas: Option<&AtlasUpload>
+  ) {
+    let frame = &self.frames[frame_index];
+
+    if let Some(upload) = atlas {
+      self.upload_atlas(device, command_buffer, frame, upload);
+    }
+
+    let (mut quads_written, mut glyphs_written) = (0, 0);
+
+    for draw in draws {
+      let quad_count = draw.canvas.quads.len().min(CanvasRenderer::MAX_QUADS - quads_written);
+      let glyph_count = draw.glyphs.quads.len().min(CanvasRenderer::MAX_GLYPHS - glyphs_written);
+
+      let quads = as_bytes(&draw.canvas.quads[..quad_count]);
+      let glyphs = as_bytes(&draw.glyphs.quads[..glyph_count]);
+
+      unsafe {
+        std::ptr::copy_nonoverlapping(quads.as_ptr(), (frame.quad_pointer as *mut u8).add(quads_written * size_of::<Quad>()), quads.len());
+        std::ptr::copy_nonoverlapping(glyphs.as_ptr(), (frame.glyph_pointer as *mut u8).add(glyphs_written * size_of::<GlyphQuad>()), glyphs.len());
+      };
+
+      let quads = quads_written as u32..(quads_written + quad_count) as u32;
+      let glyphs = glyphs_written as u32..(glyphs_written + glyph_count) as u32;
+      self.record_canvas(device, command_buffer, frame, draw, quads, glyphs);
+
+      quads_written += quad_count;
+      glyphs_written += glyph_count;
+    }
+  }
+
+  // copies the written rows through this frame's staging buffer, other frames in flight may still be reading theirs
+  fn upload_atlas(&self, device: &ash::Device, command_buffer: vk::CommandBuffer, frame: &FrameBuffers, upload: &AtlasUpload) {
+    let range = Allocator::color_range(0, 1, 0, 1);
+    let bytes = (upload.rows.start * GlyphAtlas::SIZE) as usize..(upload.rows.end * GlyphAtlas::SIZE) as usize;
+
+    unsafe {
+      let pixels = &upload.pixels[bytes.clone()];
+      std::ptr::copy_nonoverlapping(pixels.as_ptr(), (frame.staging_pointer as *mut u8).add(bytes.start), pixels.len());
+    };
+
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      self.atlas.image,
+      range,
+      (vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+      (vk::AccessFlags::SHADER_READ, vk::AccessFlags::TRANSFER_WRITE),
+      (vk::PipelineStageFlags::FRAGMENT_SHADER, vk::PipelineStageFlags::TRANSFER)
+    );
+
+    let region = {
+      vk::BufferImageCopy::default()
+        .buffer_offset(bytes.start as vk::DeviceSize)
+        .image_subresource(vk::ImageSubresourceLayers::default().aspect_mask(vk::ImageAspectFlags::COLOR).layer_count(1))
+        .image_offset(vk::Offset3D { x: 0, y: upload.rows.start as i32, z: 0 })
+        .image_extent(vk::Extent3D { width: GlyphAtlas::SIZE, height: upload.rows.end - upload.rows.start, depth: 1 })
+    };
+
+    unsafe {
+      device.cmd_copy_buffer_to_image(
+        command_buffer,
+        frame.staging.buffer,
+        self.atlas.image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &[region]
+      )
+    };
+
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      self.atlas.image,
+      range,
+      (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+      (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ),
+      (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::FRAGMENT_SHADER)
+    );
+  }
+
+  // quads and glyphs are the canvas' instances in the frame's buffers, cut short if the buffers ran out of room
+  fn record_canvas(
+    &self,
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    frame: &FrameBuffers,
+    draw: &CanvasDraw,
+    quads: Range<u32>,
+    glyphs: Range<u32>
+  ) {
+    let range = Allocator::color_range(0, 1, 0, 1);
+    let extent = vk::Extent2D { width: draw.target.extent.width, height: draw.target.extent.height };
+
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      draw.target.image,
+      range,
+      (vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+      (vk::AccessFlags::SHADER_READ, vk::AccessFlags::COLOR_ATTACHMENT_WRITE),
+      (vk::PipelineStageFlags::FRAGMENT_SHADER, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+    );
+
+    let color_attachments = [
+      vk::RenderingAttachmentInfo::default()
+        .image_view(draw.target.view)
+        .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .clear_value(vk::ClearValue { color: vk::ClearColorValue { float32: draw.canvas.clear.linear() } })
+    ];
+
+    let rendering_info = {
+      vk::RenderingInfo::default()
+        .render_area(vk::Rect2D { offset: vk::Offset2D::default(), extent })
+        .layer_count(1)
+        .color_attachments(&color_attachments)
+    };
+
+    let viewport = {
+      vk::Viewport::default()
+        .width(extent.width as f32)
+        .height(extent.height as f32)
+        .max_depth(1.0)
+    };
+
+    // the canvas may have been drawn at a different size than the target, its pixels are stretched over it
+    let scale = Vec2::new(extent.width as f32, extent.height as f32) / draw.canvas.size.max(Vec2::ONE);
+    let target_size = draw.canvas.size.max(Vec2::ONE);
+
+    // instance ranges of a batch, relative to the canvas and then moved to where the canvas starts in the buffers
+    let clamp = |batch: Range<u32>, written: &Range<u32>| {
+      let count = written.end - written.start;
+      written.start + batch.start.min(count)..written.start + batch.end.min(count)
+    };
+
+    unsafe {
+      device.cmd_begin_rendering(command_buffer, &rendering_info);
+      device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+      device.cmd_bind_descriptor_sets(
+        command_buffer,
+        vk::PipelineBindPoint::GRAPHICS,
+        self.pipeline_layout,
+        0,
+        &[self.descriptor_set],
+        &[]
+      );
+      device.cmd_push_constants(
+        command_buffer,
+        self.pipeline_layout,
+        vk::ShaderStageFlags::VERTEX,
+        0,
+        as_bytes(slice::from_ref(&target_size))
+      );
+
+      for batch in &draw.canvas.batches {
+        let min = (batch.clip.position * scale).floor().max(Vec2::ZERO);
+        let max = (batch.clip.max() * scale).ceil().min(Vec2::new(extent.width as f32, extent.height as f32));
+        if max.x <= min.x || max.y <= min.y { continue; }
+
+        let scissor = vk::Rect2D {
+          offset: vk::Offset2D { x: min.x as i32, y: min.y as i32 },
+          extent: vk::Extent2D { width: (max.x - min.x) as u32, height: (max.y - min.y) as u32 }
+        };
+
+        device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+
+        let batch_quads = clamp(batch.quads.clone(), &quads);
+        if !batch_quads.is_empty() {
+          device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.quad_pipeline);
+          device.cmd_bind_vertex_buffers(command_buffer, 0, &[frame.quads.buffer], &[0]);
+          device.cmd_draw(command_buffer, 6, batch_quads.end - batch_quads.start, 0, batch_quads.start);
+        }
+
+        // the runs of a batch are next to each other so their glyphs are too
+        let runs = &draw.glyphs.runs;
+        let batch_glyphs = match (runs.get(batch.texts.start as usize), runs.get(batch.texts.end as usize - 1)) {
+          (Some(first), Some(last)) if !batch.texts.is_empty() => clamp(first.start..last.end, &glyphs),
+          _                                                    => 0..0
+        };
+
+        if !batch_glyphs.is_empty() {
+          device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.glyph_pipeline);
+          device.cmd_bind_vertex_buffers(command_buffer, 0, &[frame.glyphs.buffer], &[0]);
+          device.cmd_draw(command_buffer, 6, batch_glyphs.end - batch_glyphs.start, 0, batch_glyphs.start);
+        }
+      }
+
+      device.cmd_end_rendering(command_buffer);
+    };
+
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      draw.target.image,
+      range,
+      (vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+      (vk::AccessFlags::COLOR_ATTACHMENT_WRITE, vk::AccessFlags::SHADER_READ),
+      (vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT, vk::PipelineStageFlags::FRAGMENT_SHADER)
+    );
+  }
+
+  // cleared so glyphs never sample garbage, and left ready to be sampled
+  fn create_atlas(device: &ash::Device, allocator: &Allocator) -> Image {
+    let create_info = {
+      vk::ImageCreateInfo::default()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(vk::Format::R8_UNORM)
+        .extent(vk::Extent3D { width: GlyphAtlas::SIZE, height: GlyphAtlas::SIZE, depth: 1 })
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+    };
+
+    let atlas = allocator.create_image(device, &create_info, vk::ImageAspectFlags::COLOR, vk::ImageViewType::TYPE_2D);
+    let range = Allocator::color_range(0, 1, 0, 1);
+
+    allocator.submit(device, |command_buffer| {
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        atlas.image,
+        range,
+        (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+        (vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE),
+        (vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::TRANSFER)
+      );
+
+      let clear = vk::ClearColorValue { float32: [0.0; 4] };
+      unsafe { device.cmd_clear_color_image(command_buffer, atlas.image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &clear, &[range]) };
+
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        atlas.image,
+        range,
+        (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+        (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ),
+        (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::FRAGMENT_SHADER)
+      );
+    });
+
+    atlas
+  }
+
+  fn write_descriptor_set(&self, device: &ash::Device) {
+    let image_info = [
+      vk::DescriptorImageInfo::default()
+        .image_view(self.atlas.view)
+        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+    ];
+
+    let sampler_info = [vk::DescriptorImageInfo::default().sampler(self.sampler)];
+
+    let writes = [
+      vk::WriteDescriptorSet::default()
+        .dst_set(self.descriptor_set)
+        .dst_binding(0)
+        .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+        .image_info(&image_info),
+      vk::WriteDescriptorSet::default()
+        .dst_set(self.descriptor_set)
+        .dst_binding(1)
+        .descriptor_type(vk::DescriptorType::SAMPLER)
+        .image_info(&sampler_info)
+    ];
+
+    unsafe { device.update_descriptor_sets(&writes, &[]) };
+  }
+
+  fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+    let bindings = [
+      vk::DescriptorSetLayoutBinding::default()
+        .binding(0)
+        .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+      vk::DescriptorSetLayoutBinding::default()
+        .binding(1)
+        .descriptor_type(vk::DescriptorType::SAMPLER)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+    ];
+
+    let create_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+    match unsafe { device.create_descriptor_set_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create canvas descriptor set layout with error: {error}")
+    }
+  }
+
+  fn create_descriptor_set(device: &ash::Device, layout: &vk::DescriptorSetLayout) -> (vk::DescriptorPool, vk::DescriptorSet) {
+    let pool_sizes = [
+      vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::SAMPLED_IMAGE)
+        .descriptor_count(1),
+      vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::SAMPLER)
+        .descriptor_count(1)
+    ];
+
+    let create_info = {
+      vk::DescriptorPoolCreateInfo::default()
+        .max_sets(1)
+        .pool_sizes(&pool_sizes)
+    };
+
+    let pool = match unsafe { device.create_descriptor_pool(&create_info, None) } {
+      Ok(pool)   => pool,
+      Err(error) => panic!("failed to create canvas descriptor pool with error: {error}")
+    };
+
+    let layouts = [*layout];
+    let allocate_info = {
+      vk::DescriptorSetAllocateInfo::default()
+        .descriptor_pool(pool)
+        .set_layouts(&layouts)
+    };
+
+    match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+      Ok(sets)   => (pool, sets[0]),
+      Err(error) => panic!("failed to allocate canvas descriptor set with error: {error}")
+    }
+  }
+
+  // shared by both pipelines, quads just leave the atlas alone
+  fn create_pipeline_layout(device: &ash::Device, set_layout: &vk::DescriptorSetLayout) -> vk::PipelineLayout {
+    let set_layouts = [*set_layout];
+    let push_constant_ranges = [
+      vk::PushConstantRange::default()
+        .stage_flags(vk::ShaderStageFlags::VERTEX)
+        .size(size_of::<Vec2>() as u32)
+    ];
+
+    let create_info = {
+      vk::PipelineLayoutCreateInfo::default()
+        .set_layouts(&set_layouts)
+        .push_constant_ranges(&push_constant_ranges)
+    };
+
+    match unsafe { device.create_pipeline_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create canvas pipeline layout with error: {error}")
+    }
+  }
+
+  fn attribute(location: u32, format: vk::Format, offset: usize) -> vk::VertexInputAttributeDescription {
+    vk::VertexInputAttributeDescription::default()
+      .location(location)
+      .binding(0)
+      .format(format)
+      .offset(offset as u32)
+  }
+
+  // quads and glyphs are instances of six generated vertices, blended over each other in order without depth
+  fn create_pipeline(
+    device: &ash::Device,
+    layout: &vk::PipelineLayout,
+    spirv: &[u8],
+    stride: usize,
+    attributes: &[vk::VertexInputAttributeDescription]
+  ) -> vk::Pipeline {
+    let module = shader::create_shader_module(device, spirv);
+
+    let stages = [
+      vk::PipelineShaderStageCreateInfo::default()
+        .stage(vk::ShaderStageFlags::VERTEX)
+        .module(module)
+        .name(c"vertex_main"),
+      vk::PipelineShaderStageCreateInfo::default()
+        .stage(vk::ShaderStageFlags::FRAGMENT)
+        .module(module)
+        .name(c"fragment_main")
+    ];
+
+    let bindings = [
+      vk::VertexInputBindingDescription::default()
+        .binding(0)
+        .stride(stride as u32)
+        .input_rate(vk::VertexInputRate::INSTANCE)
+    ];
+
+    let vertex_input = {
+      vk::PipelineVertexInputStateCreateInfo::default()
+        .vertex_binding_descriptions(&bindings)
+        .vertex_attribute_descriptions(attributes)
+    };
+
+    let input_assembly = {
+      vk::PipelineInputAssemblyStateCreateInfo::default()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+    };
+
+    let viewport_state = {
+      vk::PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1)
+    };
+
+    let rasterization = {
+      vk::PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(vk::PolygonMode::FILL)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .line_width(1.0)
+    };
+
+    let multisample = {
+      vk::PipelineMultisampleStateCreateInfo::default()
+        .rasterization_samples(vk::SampleCountFlags::TYPE_1)
+    };
+
+    let blend_attachments = [
+      vk::PipelineColorBlendAttachmentState::default()
+        .blend_enable(true)
+        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .color_blend_op(vk::BlendOp::ADD)
+        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+        .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .alpha_blend_op(vk::BlendOp::ADD)
+        .color_write_mask(vk::ColorComponentFlags::RGBA)
+    ];
+
+    let color_blend = vk::PipelineColorBlendStateCreateInfo::default().attachments(&blend_attachments);
+
+    let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+    let color_formats = [Compositor::FORMAT];
+    let mut rendering = vk::PipelineRenderingCreateInfo::default().color_attachment_formats(&color_formats);
+
+    let create_info = {
+      vk::GraphicsPipelineCreateInfo::default()
+        .stages(&stages)
+        .vertex_input_state(&vertex_input)
+        .input_assembly_state(&input_assembly)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization)
+        .multisample_state(&multisample)
+        .color_blend_state(&color_blend)
+        .dynamic_state(&dynamic_state)
+        .layout(*layout)
+        .push_next(&mut rendering)
+    };
+
+    let pipeline = match unsafe { device.create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None) } {
+      Ok(pipelines)   => pipelines[0],
+      Err((_, error)) => panic!("failed to create canvas pipeline with error: {error}")
+    };
+
+    unsafe { device.destroy_shader_module(module, None) };
+
+    pipeline
+  }
+}
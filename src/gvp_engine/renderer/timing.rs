@@ -0,0 +1,196 @@
+use crate::gvp_engine::gpu::GPU;
+
+use ash::vk;
+
+use std::mem;
+
+// how long the gpu spent on one pass of a frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PassTiming {
+  pub name: &'static str,
+  pub milliseconds: f32
+}
+
+// counted by the gpu over a whole frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineStatistics {
+  pub input_primitives: u64,
+  pub vertex_invocations: u64,
+  // primitives that made it through clipping
+  pub clipped_primitives: u64,
+  pub fragment_invocations: u64,
+  pub compute_invocations: u64
+}
+
+// the last frame the gpu finished, which is a frame or two behind the one being recorded. empty when the main queue
+// cannot write timestamps
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpuTimings {
+  pub passes: Vec<PassTiming>,
+  // from the start of the first pass to the end of the last
+  pub frame: Option<f32>,
+  // none when the gpu does not count them
+  pub statistics: Option<PipelineStatistics>
+}
+
+// the query pools of one frame in flight, and the passes last recorded into them
+struct FrameQueries {
+  timestamps: vk::QueryPool,
+  statistics: Option<vk::QueryPool>,
+  passes: Vec<&'static str>
+}
+
+// timestamps written before and after each pass of a frame, and the pipeline statistics of the whole frame. a frame's
+// results are read once its fence says the gpu is done with it, so reading never stalls
+pub struct GpuTimer {
+  frames: Vec<FrameQueries>,
+  // nanoseconds per tick
+  period: f32,
+  // the bits of a timestamp that count, the rest are garbage
+  mask: u64,
+  timings: GpuTimings
+}
+
+impl GpuTimer {
+  // passes timed in a frame, past which the rest go untimed
+  pub const MAX_PASSES: usize = 16;
+
+  const STATISTICS: vk::QueryPipelineStatisticFlags = vk::QueryPipelineStatisticFlags::from_raw(
+    vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES.as_raw()
+      | vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS.as_raw()
+      | vk::QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES.as_raw()
+      | vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS.as_raw()
+      | vk::QueryPipelineStatisticFlags::COMPUTE_SHADER_INVOCATIONS.as_raw()
+  );
+
+  pub fn new(device: &ash::Device, gpu: &GPU, frame_count: usize) -> Self {
+    let bits = gpu.timestamp_bits;
+    let statistics = gpu.features.pipeline_statistics_query == vk::TRUE;
+
+    // a queue that cannot write timestamps gets no pools at all
+    let frames = match bits {
+      0 => Vec::new(),
+      _ => (0..frame_count).map(|_| FrameQueries {
+        timestamps: GpuTimer::create_pool(device, vk::QueryType::TIMESTAMP, GpuTimer::MAX_PASSES as u32 * 2, vk::QueryPipelineStatisticFlags::empty()),
+        statistics: statistics.then(|| GpuTimer::create_pool(device, vk::QueryType::PIPELINE_STATISTICS, 1, GpuTimer::STATISTICS)),
+        passes: Vec::new()
+      }).collect()
+    };
+
+    GpuTimer {
+      frames,
+      period: gpu.properties.limits.timestamp_period,
+      mask: if bits >= 64 { u64::MAX } else { (1u64 << bits) - 1 },
+      timings: GpuTimings::default()
+    }
+  }
+
+  pub fn clean(&mut self, device: &ash::Device) {
+    for frame in self.frames.drain(..) {
+      unsafe {
+        device.destroy_query_pool(frame.timestamps, None);
+
+        if let Some(statistics) = frame.statistics {
+          device.destroy_query_pool(statistics, None);
+        }
+      }
+    }
+  }
+
+  pub fn timings(&self) -> &GpuTimings {
+    &self.timings
+  }
+
+  // reads back what the frame's last submission wrote, once its fence was waited on
+  pub fn collect(&mut self, device: &ash::Device, frame_index: usize) {
+    let Some(frame) = self.frames.get_mut(frame_index) else { return; };
+    let passes = mem::take(&mut frame.passes);
+
+    if passes.is_empty() { return; }
+
+    let mut timestamps = vec![0u64; passes.len() * 2];
+    let read = unsafe { device.get_query_pool_results(frame.timestamps, 0, &mut timestamps, vk::QueryResultFlags::TYPE_64) };
+
+    // results that are not there yet only happen when the frame was never finished, like after a lost device
+    if read.is_err() { return; }
+
+    let milliseconds = |begin: u64, end: u64| ((end & self.mask).wrapping_sub(begin & self.mask) & self.mask) as f32 * self.period / 1e6;
+
+    self.timings.passes = passes.iter().zip(timestamps.chunks_exact(2)).map(|(name, pair)| PassTiming {
+      name,
+      milliseconds: milliseconds(pair[0], pair[1])
+    }).collect();
+
+    self.timings.frame = Some(milliseconds(timestamps[0], timestamps[timestamps.len() - 1]));
+
+    self.timings.statistics = frame.statistics.and_then(|pool| {
+      let mut counts = [[0u64; 5]];
+      unsafe { device.get_query_pool_results(pool, 0, &mut counts, vk::QueryResultFlags::TYPE_64) }.ok()?;
+
+      // in the order of the flags' bits
+      let [input_primitives, vertex_invocations, clipped_primitives, fragment_invocations, compute_invocations] = counts[0];
+      Some(PipelineStatistics { input_primitives, vertex_invocations, clipped_primitives, fragment_invocations, compute_invocations })
+    });
+  }
+
+  // resets the frame's queries and starts counting, before anything else in its command buffer
+  pub fn begin_frame(&self, device: &ash::Device, command_buffer: vk::CommandBuffer, frame_index: usize) {
+    let Some(frame) = self.frames.get(frame_index) else { return; };
+
+    unsafe {
+      device.cmd_reset_query_pool(command_buffer, frame.timestamps, 0, GpuTimer::MAX_PASSES as u32 * 2);
+
+      if let Some(statistics) = frame.statistics {
+        device.cmd_reset_query_pool(command_buffer, statistics, 0, 1);
+        device.cmd_begin_query(command_buffer, statistics, 0, vk::QueryControlFlags::empty());
+      }
+    }
+  }
+
+  pub fn end_frame(&self, device: &ash::Device, command_buffer: vk::CommandBuffer, frame_index: usize) {
+    let Some(statistics) = self.frames.get(frame_index).and_then(|frame| frame.statistics) else { return; };
+    unsafe { device.cmd_end_query(command_buffer, statistics, 0) };
+  }
+
+  // records the pass between timestamps, outside of any rendering. passes past the limit are recorded untimed
+  pub fn time(
+    &self,
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    frame_index: usize,
+    (passes, name): (&mut Vec<&'static str>, &'static str),
+    record: impl FnOnce()
+  ) {
+    let Some(frame) = self.frames.get(frame_index).filter(|_| passes.len() < GpuTimer::MAX_PASSES) else {
+      return record();
+    };
+
+    let query = passes.len() as u32 * 2;
+    passes.push(name);
+
+    unsafe { device.cmd_write_timestamp(command_buffer, vk::PipelineStageFlags::TOP_OF_PIPE, frame.timestamps, query) };
+    record();
+    unsafe { device.cmd_write_timestamp(command_buffer, vk::PipelineStageFlags::BOTTOM_OF_PIPE, frame.timestamps, query + 1) };
+  }
+
+  // the passes that were timed in the frame just submitted, read back when it comes around again
+  pub fn submitted(&mut self, frame_index: usize, passes: Vec<&'static str>) {
+    if let Some(frame) = self.frames.get_mut(frame_index) {
+      frame.passes = passes;
+    }
+  }
+
+  fn create_pool(device: &ash::Device, kind: vk::QueryType, count: u32, statistics: vk::QueryPipelineStatisticFlags) -> vk::QueryPool {
+    let create_info = {
+      vk::QueryPoolCreateInfo::default()
+        .query_type(kind)
+        .query_count(count)
+        .pipeline_statistics(statistics)
+    };
+
+    match unsafe { device.create_query_pool(&create_info, None) } {
+      Ok(pool)   => pool,
+      Err(error) => panic!("failed to create query pool with error: {error}")
+    }
+  }
+}
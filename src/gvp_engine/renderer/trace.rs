@@ -0,0 +1,833 @@
+use crate::gvp_engine::{
+  acceleration::AccelerationStructures,
+  allocator::{as_bytes, Allocator, Buffer, Image},
+  bvh::{Bvh, BvhNode, BvhTriangle, MeshBvh},
+  gpu::GPU,
+  ibl::Ibl,
+  shader
+};
+
+use super::{DrawCall, RenderView, Sun};
+
+use ash::vk;
+use glam::{Mat4, Vec4};
+
+use std::{ffi::c_void, mem::size_of, slice};
+
+// what the window shows while it is path traced. albedo and normal are the first surface each pixel sees, averaged
+// the same way the color is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathTraceView {
+  Color,
+  Albedo,
+  Normal
+}
+
+impl PathTraceView {
+  // the order F5 steps through them in
+  pub fn next(self) -> Self {
+    match self {
+      PathTraceView::Color  => PathTraceView::Albedo,
+      PathTraceView::Albedo => PathTraceView::Normal,
+      PathTraceView::Normal => PathTraceView::Color
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathTraceSettings {
+  // reflections followed after the first surface a path hits
+  pub max_bounces: u32,
+  pub samples_per_frame: u32,
+  // a still image stops being traced once it has this many, 0 keeps going forever
+  pub max_samples: u32,
+  pub view: PathTraceView,
+  // traces through hardware acceleration structures when the gpu supports ray queries, through the hierarchies built
+  // on the cpu otherwise
+  pub ray_query: bool
+}
+
+impl Default for PathTraceSettings {
+  fn default() -> Self {
+    PathTraceSettings {
+      max_bounces: 4,
+      samples_per_frame: 1,
+      max_samples: 4096,
+      view: PathTraceView::Color,
+      ray_query: true
+    }
+  }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TraceUniforms {
+  inverse_view: Mat4,
+  inverse_projection: Mat4,
+  light: Vec4,
+  light_color: Vec4,
+  // intensity, how far the current environment has faded in, unused, unused
+  environment: Vec4,
+  // width, height, samples accumulated before this frame, a seed that changes every frame
+  frame: [u32; 4],
+  // bounces, samples this frame, output shown, instance count
+  settings: [u32; 4]
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq)]
+struct TraceInstance {
+  world_to_object: Mat4,
+  base_color: [f32; 4],
+  emissive: [f32; 4],
+  // metallic, roughness, unused, unused
+  surface: [f32; 4],
+  // root node of the mesh, 1 when the material blends, first triangle of the mesh, unused
+  mesh: [u32; 4]
+}
+
+// everything the accumulated samples were traced with besides the scene, any change starts them over
+#[derive(Clone, Copy, PartialEq)]
+struct Traced {
+  view: Mat4,
+  projection: Mat4,
+  light: Vec4,
+  light_color: Vec4,
+  blend: f32,
+  settings: PathTraceSettings
+}
+
+// the hierarchies and triangles of every mesh being traced, in one pair of buffers
+struct Geometry {
+  // the id of each mesh's hierarchy and where its root and first triangle ended up
+  meshes: Vec<(u64, u32, u32)>,
+  nodes: Buffer,
+  triangles: Buffer
+}
+
+struct Mapped {
+  buffer: Buffer,
+  pointer: *mut c_void
+}
+
+// what changes every frame, so one of each per frame in flight
+struct TraceFrame {
+  uniforms: Mapped,
+  nodes: Mapped,
+  instances: Mapped
+}
+
+// averages per pixel, laid out as rgba floats row by row so a denoiser can take them as they are, and the image shown
+struct Accumulation {
+  extent: vk::Extent2D,
+  color: Buffer,
+  albedo: Buffer,
+  normal: Buffer,
+  output: Image
+}
+
+// traces the scene in a compute shader, bouncing rays around a two level hierarchy built on the cpu: one per mesh,
+// built once when it is uploaded, and one over the instances built every frame. gpus with ray queries trace through
+// acceleration structures built the same way instead. samples accumulate while nothing moves and the result is copied
+// into the window
+pub struct PathTracer {
+  pub settings: PathTraceSettings,
+  set_layout: vk::DescriptorSetLayout,
+  pipeline_layout: vk::PipelineLayout,
+  pipeline: vk::Pipeline,
+  // only made along with the acceleration structures
+  query_pipeline: Option<vk::Pipeline>,
+  acceleration: Option<AccelerationStructures>,
+  descriptor_pool: vk::DescriptorPool,
+  descriptor_sets: Vec<vk::DescriptorSet>,
+  geometry: Option<Geometry>,
+  frames: Vec<Option<TraceFrame>>,
+  accumulation: Option<Accumulation>,
+  // the hierarchy over the instances for the next frame
+  scene_nodes: Vec<BvhNode>,
+  instances: Vec<TraceInstance>,
+  // the mesh and transform of every instance, for the acceleration structures
+  placements: Vec<(u64, Mat4)>,
+  traced: Option<Traced>,
+  samples: u32,
+  seed: u32,
+  // whether the frame being recorded adds samples, or only shows what was already there
+  dispatching: bool
+}
+
+impl PathTracer {
+  const WORKGROUP_SIZE: u32 = 8;
+  const BINDING_COUNT: u32 = 9;
+  const OUTPUT_BINDING: u32 = 8;
+  // only in the layout when there are acceleration structures
+  const ACCELERATION_BINDING: u32 = 9;
+  // frame buffers start with room for this many instances and double from there
+  const MIN_INSTANCES: usize = 64;
+
+  pub fn new(
+    instance: &ash::Instance,
+    device: &ash::Device,
+    gpu: &GPU,
+    environment_layout: vk::DescriptorSetLayout,
+    frame_count: usize
+  ) -> Self {
+    let acceleration = AccelerationStructures::new(instance, device, gpu, frame_count);
+    let ray_query = acceleration.is_some();
+
+    let set_layout = PathTracer::create_set_layout(device, ray_query);
+    let pipeline_layout = PathTracer::create_pipeline_layout(device, &[set_layout, environment_layout]);
+    let pipeline = PathTracer::create_pipeline(device, &pipeline_layout, shader::PATH_TRACE_BVH);
+    let query_pipeline = ray_query.then(|| PathTracer::create_pipeline(device, &pipeline_layout, shader::PATH_TRACE_RAY_QUERY));
+    let (descriptor_pool, descriptor_sets) = PathTracer::allocate_sets(device, &set_layout, frame_count as u32, ray_query);
+
+    PathTracer {
+      settings: PathTraceSettings::default(),
+      set_layout,
+      pipeline_layout,
+      pipeline,
+      query_pipeline,
+      acceleration,
+      descriptor_pool,
+      descriptor_sets,
+      geometry: None,
+      frames: (0..frame_count).map(|_| None).collect(),
+      accumulation: None,
+      scene_nodes: Vec::new(),
+      instances: Vec::new(),
+      placements: Vec::new(),
+      traced: None,
+      samples: 0,
+      seed: 0,
+      dispatching: false
+    }
+  }
+
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    self.release(device, allocator);
+
+    unsafe {
+      device.destroy_pipeline(self.pipeline, None);
+
+      if let Some(pipeline) = self.query_pipeline {
+        device.destroy_pipeline(pipeline, None);
+      }
+
+      device.destroy_pipeline_layout(self.pipeline_layout, None);
+      device.destroy_descriptor_pool(self.descriptor_pool, None);
+      device.destroy_descriptor_set_layout(self.set_layout, None);
+    };
+  }
+
+  // frees the buffers and images, which are made again the next time the scene is traced
+  pub fn release(&mut self, device: &ash::Device, allocator: &Allocator) {
+    if self.geometry.is_none() && self.accumulation.is_none() && self.frames.iter().all(Option::is_none) { return; }
+
+    if let Err(error) = unsafe { device.device_wait_idle() } {
+      panic!("failed to wait for device idle with error: {error}")
+    }
+
+    if let Some(geometry) = self.geometry.take() {
+      allocator.destroy_buffer(device, &geometry.nodes);
+      allocator.destroy_buffer(device, &geometry.triangles);
+    }
+
+    if let Some(accumulation) = self.accumulation.take() {
+      PathTracer::destroy_accumulation(device, allocator, &accumulation);
+    }
+
+    for frame in self.frames.iter_mut().filter_map(Option::take) {
+      PathTracer::destroy_frame(device, allocator, &frame);
+    }
+
+    if let Some(acceleration) = &mut self.acceleration {
+      acceleration.clean(device, allocator);
+    }
+
+    self.traced = None;
+  }
+
+  // builds the hierarchy over this frame's meshes and makes sure every buffer fits them and the window. anything made
+  // again is waited for first since frames in flight may still be reading it, which only happens when the meshes
+  // being traced, the window size or the instance count grow past what is there
+  pub fn prepare(&mut self, device: &ash::Device, allocator: &Allocator, frame_index: usize, extent: vk::Extent2D, draws: &[DrawCall]) {
+    let mut rebuilt = false;
+
+    let mut ids: Vec<u64> = draws.iter().map(|draw| draw.mesh.bvh.id).collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let stale = self.geometry.as_ref().is_none_or(|geometry| {
+      geometry.meshes.len() != ids.len() || geometry.meshes.iter().any(|(id, _, _)| ids.binary_search(id).is_err())
+    });
+
+    if stale {
+      PathTracer::wait(device);
+
+      if let Some(geometry) = self.geometry.take() {
+        allocator.destroy_buffer(device, &geometry.nodes);
+        allocator.destroy_buffer(device, &geometry.triangles);
+      }
+
+      self.geometry = Some(PathTracer::upload_geometry(device, allocator, draws));
+      rebuilt = true;
+
+      if let Some(acceleration) = &mut self.acceleration {
+        let mut meshes = Vec::<&MeshBvh>::new();
+
+        for draw in draws {
+          if !meshes.iter().any(|mesh| mesh.id == draw.mesh.bvh.id) { meshes.push(&draw.mesh.bvh); }
+        }
+
+        acceleration.prepare_meshes(device, allocator, &meshes);
+      }
+    }
+
+    let instances = self.build_instances(draws);
+
+    if instances != self.instances {
+      self.samples = 0;
+    }
+
+    self.instances = instances;
+
+    if self.accumulation.as_ref().is_none_or(|accumulation| accumulation.extent != extent) {
+      PathTracer::wait(device);
+
+      if let Some(accumulation) = self.accumulation.take() {
+        PathTracer::destroy_accumulation(device, allocator, &accumulation);
+      }
+
+      self.accumulation = Some(PathTracer::create_accumulation(device, allocator, extent));
+      self.samples = 0;
+      rebuilt = true;
+    }
+
+    let needed = self.instances.len().max(1);
+    let fits = self.frames[frame_index].as_ref().is_some_and(|frame| {
+      frame.instances.buffer.size >= (needed * size_of::<TraceInstance>()) as vk::DeviceSize
+        && frame.nodes.buffer.size >= (self.scene_nodes.len().max(1) * size_of::<BvhNode>()) as vk::DeviceSize
+    });
+
+    if !fits {
+      PathTracer::wait(device);
+
+      if let Some(frame) = self.frames[frame_index].take() {
+        PathTracer::destroy_frame(device, allocator, &frame);
+      }
+
+      self.frames[frame_index] = Some(PathTracer::create_frame(device, allocator, needed.next_power_of_two().max(PathTracer::MIN_INSTANCES)));
+      rebuilt = true;
+    }
+
+    if let Some(acceleration) = &mut self.acceleration {
+      rebuilt |= acceleration.prepare_frame(device, allocator, frame_index, self.instances.len());
+    }
+
+    // everything is idle whenever something was made again, so every set can be written
+    if rebuilt {
+      self.write_sets(device);
+    }
+  }
+
+  // whether the frame can be traced, which needs everything prepare makes for the window's current size
+  pub fn ready(&self, frame_index: usize, extent: vk::Extent2D) -> bool {
+    self.geometry.is_some()
+      && self.frames[frame_index].is_some()
+      && self.accumulation.as_ref().is_some_and(|accumulation| accumulation.extent == extent)
+  }
+
+  // fills the frame's buffers once its fence has been waited on. the samples start over whenever the view, the light
+  // or the settings differ from what they were traced with
+  pub fn update(&mut self, frame_index: usize, view: &RenderView, sun: &Sun, blend: f32) {
+    let Some(frame) = &self.frames[frame_index] else { return; };
+    let Some(accumulation) = &self.accumulation else { return; };
+
+    let traced = Traced {
+      view: view.view,
+      projection: view.projection,
+      light: sun.direction.normalize().extend(sun.intensity),
+      light_color: sun.color.extend(1.0),
+      blend,
+      settings: self.settings
+    };
+
+    if self.traced != Some(traced) {
+      self.traced = Some(traced);
+      self.samples = 0;
+    }
+
+    let samples_per_frame = self.settings.samples_per_frame.max(1);
+    self.dispatching = self.settings.max_samples == 0 || self.samples < self.settings.max_samples;
+
+    let uniforms = TraceUniforms {
+      inverse_view: view.view.inverse(),
+      inverse_projection: view.projection.inverse(),
+      light: traced.light,
+      light_color: traced.light_color,
+      environment: Vec4::new(super::Renderer::IBL_INTENSITY, blend, 0.0, 0.0),
+      frame: [accumulation.extent.width, accumulation.extent.height, self.samples, self.seed],
+      settings: [self.settings.max_bounces, samples_per_frame, self.settings.view as u32, self.instances.len() as u32]
+    };
+
+    PathTracer::copy(&frame.uniforms, as_bytes(slice::from_ref(&uniforms)));
+    PathTracer::copy(&frame.nodes, as_bytes(&self.scene_nodes));
+    PathTracer::copy(&frame.instances, as_bytes(&self.instances));
+
+    if self.dispatching {
+      self.samples += samples_per_frame;
+      self.seed = self.seed.wrapping_add(1);
+
+      if let Some(acceleration) = self.acceleration.as_mut().filter(|_| self.settings.ray_query) {
+        acceleration.write_instances(frame_index, &self.placements);
+      }
+    }
+  }
+
+  // the pipeline tracing the frame along with whether it goes through the acceleration structures
+  fn pipeline(&self) -> (vk::Pipeline, bool) {
+    match self.query_pipeline {
+      Some(pipeline) if self.settings.ray_query => (pipeline, true),
+      _                                         => (self.pipeline, false)
+    }
+  }
+
+  // traces into the output image when there are samples to add, then copies it over the window image, which is left
+  // in COLOR_ATTACHMENT_OPTIMAL for panels to be drawn over it
+  pub fn record(
+    &self,
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    frame_index: usize,
+    environment: vk::DescriptorSet,
+    window: vk::Image
+  ) {
+    let Some(accumulation) = &self.accumulation else { return; };
+    let range = Allocator::color_range(0, 1, 0, 1);
+
+    if self.dispatching {
+      // the last frame's averages are read back and added to
+      let barrier = {
+        vk::MemoryBarrier::default()
+          .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+          .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE)
+      };
+
+      unsafe {
+        device.cmd_pipeline_barrier(
+          command_buffer,
+          vk::PipelineStageFlags::COMPUTE_SHADER,
+          vk::PipelineStageFlags::COMPUTE_SHADER,
+          vk::DependencyFlags::empty(),
+          &[barrier],
+          &[],
+          &[]
+        )
+      };
+
+      // the whole image is written so whatever it held is dropped
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        accumulation.output.image,
+        range,
+        (vk::ImageLayout::UNDEFINED, vk::ImageLayout::GENERAL),
+        (vk::AccessFlags::empty(), vk::AccessFlags::SHADER_WRITE),
+        (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::COMPUTE_SHADER)
+      );
+
+      let groups_x = accumulation.extent.width.div_ceil(PathTracer::WORKGROUP_SIZE);
+      let groups_y = accumulation.extent.height.div_ceil(PathTracer::WORKGROUP_SIZE);
+      let (pipeline, ray_query) = self.pipeline();
+
+      if let Some(acceleration) = self.acceleration.as_ref().filter(|_| ray_query) {
+        acceleration.record(device, command_buffer, frame_index);
+      }
+
+      unsafe {
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline);
+        device.cmd_bind_descriptor_sets(
+          command_buffer,
+          vk::PipelineBindPoint::COMPUTE,
+          self.pipeline_layout,
+          0,
+          &[self.descriptor_sets[frame_index], environment],
+          &[]
+        );
+        device.cmd_dispatch(command_buffer, groups_x, groups_y, 1);
+      };
+
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        accumulation.output.image,
+        range,
+        (vk::ImageLayout::GENERAL, vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+        (vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::TRANSFER_READ),
+        (vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::TRANSFER)
+      );
+    }
+
+    // the window image is only available once the acquire semaphore waited on at color output has been signaled
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      window,
+      range,
+      (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+      (vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE),
+      (vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT, vk::PipelineStageFlags::TRANSFER)
+    );
+
+    let subresource = {
+      vk::ImageSubresourceLayers::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .layer_count(1)
+    };
+
+    let corner = vk::Offset3D { x: accumulation.extent.width as i32, y: accumulation.extent.height as i32, z: 1 };
+
+    // a blit rather than a copy so the floats are converted to whatever format the window has
+    let region = {
+      vk::ImageBlit::default()
+        .src_subresource(subresource)
+        .src_offsets([vk::Offset3D::default(), corner])
+        .dst_subresource(subresource)
+        .dst_offsets([vk::Offset3D::default(), corner])
+    };
+
+    unsafe {
+      device.cmd_blit_image(
+        command_buffer,
+        accumulation.output.image,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        window,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &[region],
+        vk::Filter::NEAREST
+      )
+    };
+
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      window,
+      range,
+      (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+      (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE),
+      (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+    );
+  }
+
+  // one instance per draw, with the hierarchy over them. meshes without triangles are left out, as are masked
+  // materials cut away entirely since there are no textures to cut them with
+  fn build_instances(&mut self, draws: &[DrawCall]) -> Vec<TraceInstance> {
+    let Some(geometry) = &self.geometry else { return Vec::new(); };
+
+    let kept: Vec<&DrawCall> = draws.iter().filter(|draw| {
+      let uniforms = &draw.material.uniforms;
+      let cut = uniforms.alpha[1] == 1.0 && uniforms.base_color[3] < uniforms.alpha[0];
+
+      !draw.mesh.bvh.nodes.is_empty() && !cut
+    }).collect();
+
+    let bounds: Vec<_> = kept.iter().map(|draw| draw.mesh.bvh.bounds(&draw.transform)).collect();
+    let bvh = Bvh::build(&bounds);
+
+    self.scene_nodes = bvh.nodes;
+    self.placements = bvh.order.iter().map(|index| (kept[*index as usize].mesh.bvh.id, kept[*index as usize].transform)).collect();
+
+    bvh.order.iter().map(|index| {
+      let draw = kept[*index as usize];
+      let uniforms = &draw.material.uniforms;
+
+      let (root, first_triangle) = geometry.meshes.iter()
+        .find(|(id, _, _)| *id == draw.mesh.bvh.id)
+        .map_or((0, 0), |(_, root, first_triangle)| (*root, *first_triangle));
+
+      TraceInstance {
+        world_to_object: draw.transform.inverse(),
+        base_color: uniforms.base_color,
+        emissive: uniforms.emissive,
+        surface: [uniforms.surface[0], uniforms.surface[1], 0.0, 0.0],
+        mesh: [root, draw.material.blend as u32, first_triangle, 0]
+      }
+    }).collect()
+  }
+
+  // every distinct mesh's nodes and triangles one after another, with the nodes pointing at where things ended up
+  fn upload_geometry(device: &ash::Device, allocator: &Allocator, draws: &[DrawCall]) -> Geometry {
+    let mut meshes = Vec::<(u64, u32, u32)>::new();
+    let mut nodes = Vec::<BvhNode>::new();
+    let mut triangles = Vec::<BvhTriangle>::new();
+
+    for draw in draws {
+      let bvh = &draw.mesh.bvh;
+      if meshes.iter().any(|(id, _, _)| *id == bvh.id) { continue; }
+
+      let (node_offset, triangle_offset) = (nodes.len() as u32, triangles.len() as u32);
+      meshes.push((bvh.id, node_offset, triangle_offset));
+
+      nodes.extend(bvh.nodes.iter().map(|node| match node.count {
+        0 => BvhNode { first: node.first + node_offset, ..*node },
+        _ => BvhNode { first: node.first + triangle_offset, ..*node }
+      }));
+
+      triangles.extend_from_slice(&bvh.triangles);
+    }
+
+    // storage buffers cannot be empty
+    if nodes.is_empty() { nodes.push(BvhNode::default()); }
+    if triangles.is_empty() { triangles.push(BvhTriangle::default()); }
+
+    Geometry {
+      meshes,
+      nodes: allocator.upload_buffer(device, as_bytes(&nodes), vk::BufferUsageFlags::STORAGE_BUFFER),
+      triangles: allocator.upload_buffer(device, as_bytes(&triangles), vk::BufferUsageFlags::STORAGE_BUFFER)
+    }
+  }
+
+  fn create_accumulation(device: &ash::Device, allocator: &Allocator, extent: vk::Extent2D) -> Accumulation {
+    let size = (extent.width * extent.height) as vk::DeviceSize * size_of::<Vec4>() as vk::DeviceSize;
+
+    let buffer = || {
+      allocator.create_buffer(
+        device,
+        size.max(1),
+        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL
+      )
+    };
+
+    let create_info = {
+      vk::ImageCreateInfo::default()
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(Ibl::FORMAT)
+        .extent(extent.into())
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+    };
+
+    Accumulation {
+      extent,
+      color: buffer(),
+      albedo: buffer(),
+      normal: buffer(),
+      output: allocator.create_image(device, &create_info, vk::ImageAspectFlags::COLOR, vk::ImageViewType::TYPE_2D)
+    }
+  }
+
+  fn create_frame(device: &ash::Device, allocator: &Allocator, instances: usize) -> TraceFrame {
+    let mapped = |size: usize, usage: vk::BufferUsageFlags| {
+      let buffer = allocator.create_buffer(
+        device,
+        size as vk::DeviceSize,
+        usage,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+      );
+
+      let pointer = allocator.map(device, &buffer);
+      Mapped { buffer, pointer }
+    };
+
+    // a hierarchy never has more than twice as many nodes as it has items
+    TraceFrame {
+      uniforms: mapped(size_of::<TraceUniforms>(), vk::BufferUsageFlags::UNIFORM_BUFFER),
+      nodes: mapped(2 * instances * size_of::<BvhNode>(), vk::BufferUsageFlags::STORAGE_BUFFER),
+      instances: mapped(instances * size_of::<TraceInstance>(), vk::BufferUsageFlags::STORAGE_BUFFER)
+    }
+  }
+
+  fn destroy_accumulation(device: &ash::Device, allocator: &Allocator, accumulation: &Accumulation) {
+    for buffer in [&accumulation.color, &accumulation.albedo, &accumulation.normal] {
+      allocator.destroy_buffer(device, buffer);
+    }
+
+    allocator.destroy_image(device, &accumulation.output);
+  }
+
+  fn destroy_frame(device: &ash::Device, allocator: &Allocator, frame: &TraceFrame) {
+    for mapped in [&frame.uniforms, &frame.nodes, &frame.instances] {
+      unsafe { device.unmap_memory(mapped.buffer.memory) };
+      allocator.destroy_buffer(device, &mapped.buffer);
+    }
+  }
+
+  fn copy(mapped: &Mapped, bytes: &[u8]) {
+    let length = bytes.len().min(mapped.buffer.size as usize);
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), mapped.pointer as *mut u8, length) };
+  }
+
+  fn wait(device: &ash::Device) {
+    if let Err(error) = unsafe { device.device_wait_idle() } {
+      panic!("failed to wait for device idle with error: {error}")
+    }
+  }
+
+  // frames without their buffers yet are skipped, they are written once they have them
+  fn write_sets(&self, device: &ash::Device) {
+    let (Some(geometry), Some(accumulation)) = (&self.geometry, &self.accumulation) else { return; };
+
+    for (frame_index, (set, frame)) in self.descriptor_sets.iter().zip(&self.frames).enumerate() {
+      let Some(frame) = frame else { continue; };
+
+      let buffers = [
+        &frame.uniforms.buffer,
+        &geometry.nodes,
+        &geometry.triangles,
+        &frame.nodes.buffer,
+        &frame.instances.buffer,
+        &accumulation.color,
+        &accumulation.albedo,
+        &accumulation.normal
+      ];
+
+      let buffer_infos = buffers.map(|buffer| [vk::DescriptorBufferInfo::default().buffer(buffer.buffer).range(vk::WHOLE_SIZE)]);
+      let image_info = [vk::DescriptorImageInfo::default().image_view(accumulation.output.view).image_layout(vk::ImageLayout::GENERAL)];
+
+      let mut writes: Vec<vk::WriteDescriptorSet> = buffer_infos.iter().enumerate().map(|(binding, buffer_info)| {
+        let descriptor_type = match binding {
+          0 => vk::DescriptorType::UNIFORM_BUFFER,
+          _ => vk::DescriptorType::STORAGE_BUFFER
+        };
+
+        vk::WriteDescriptorSet::default()
+          .dst_set(*set)
+          .dst_binding(binding as u32)
+          .descriptor_type(descriptor_type)
+          .buffer_info(buffer_info)
+      }).collect();
+
+      writes.push(
+        vk::WriteDescriptorSet::default()
+          .dst_set(*set)
+          .dst_binding(PathTracer::OUTPUT_BINDING)
+          .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+          .image_info(&image_info)
+      );
+
+      let structures = self.acceleration.as_ref().and_then(|acceleration| acceleration.top_level(frame_index)).map(|top_level| [top_level]);
+      let mut acceleration_info = structures.as_ref().map(|structures| {
+        vk::WriteDescriptorSetAccelerationStructureKHR::default().acceleration_structures(structures)
+      });
+
+      if let Some(acceleration_info) = &mut acceleration_info {
+        writes.push(
+          vk::WriteDescriptorSet::default()
+            .dst_set(*set)
+            .dst_binding(PathTracer::ACCELERATION_BINDING)
+            .descriptor_type(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)
+            .descriptor_count(1)
+            .push_next(acceleration_info)
+        );
+      }
+
+      unsafe { device.update_descriptor_sets(&writes, &[]) };
+    }
+  }
+
+  // binding 0 holds the uniforms, 1 to 7 the storage buffers, 8 the output image and 9 the top level acceleration
+  // structure when there is one
+  fn create_set_layout(device: &ash::Device, ray_query: bool) -> vk::DescriptorSetLayout {
+    let count = PathTracer::BINDING_COUNT + ray_query as u32;
+
+    let bindings: Vec<vk::DescriptorSetLayoutBinding> = (0..count).map(|binding| {
+      let descriptor_type = match binding {
+        0                                => vk::DescriptorType::UNIFORM_BUFFER,
+        PathTracer::OUTPUT_BINDING       => vk::DescriptorType::STORAGE_IMAGE,
+        PathTracer::ACCELERATION_BINDING => vk::DescriptorType::ACCELERATION_STRUCTURE_KHR,
+        _                                => vk::DescriptorType::STORAGE_BUFFER
+      };
+
+      vk::DescriptorSetLayoutBinding::default()
+        .binding(binding)
+        .descriptor_type(descriptor_type)
+        .descriptor_count(1)
+        .stage_flags(vk::ShaderStageFlags::COMPUTE)
+    }).collect();
+
+    let create_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+    match unsafe { device.create_descriptor_set_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create path trace descriptor set layout with error: {error}")
+    }
+  }
+
+  fn allocate_sets(
+    device: &ash::Device,
+    layout: &vk::DescriptorSetLayout,
+    count: u32,
+    ray_query: bool
+  ) -> (vk::DescriptorPool, Vec<vk::DescriptorSet>) {
+    let mut pool_sizes = vec![
+      vk::DescriptorPoolSize::default().ty(vk::DescriptorType::UNIFORM_BUFFER).descriptor_count(count),
+      vk::DescriptorPoolSize::default().ty(vk::DescriptorType::STORAGE_BUFFER).descriptor_count(count * (PathTracer::BINDING_COUNT - 2)),
+      vk::DescriptorPoolSize::default().ty(vk::DescriptorType::STORAGE_IMAGE).descriptor_count(count)
+    ];
+
+    if ray_query {
+      pool_sizes.push(vk::DescriptorPoolSize::default().ty(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR).descriptor_count(count));
+    }
+
+    let create_info = {
+      vk::DescriptorPoolCreateInfo::default()
+        .max_sets(count)
+        .pool_sizes(&pool_sizes)
+    };
+
+    let pool = match unsafe { device.create_descriptor_pool(&create_info, None) } {
+      Ok(pool)   => pool,
+      Err(error) => panic!("failed to create path trace descriptor pool with error: {error}")
+    };
+
+    let layouts = vec![*layout; count as usize];
+    let allocate_info = {
+      vk::DescriptorSetAllocateInfo::default()
+        .descriptor_pool(pool)
+        .set_layouts(&layouts)
+    };
+
+    match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+      Ok(sets)   => (pool, sets),
+      Err(error) => panic!("failed to allocate path trace descriptor sets with error: {error}")
+    }
+  }
+
+  // set 0 is the tracer's own and set 1 the environment the meshes are lit with
+  fn create_pipeline_layout(device: &ash::Device, set_layouts: &[vk::DescriptorSetLayout]) -> vk::PipelineLayout {
+    let create_info = vk::PipelineLayoutCreateInfo::default().set_layouts(set_layouts);
+
+    match unsafe { device.create_pipeline_layout(&create_info, None) } {
+      Ok(layout) => layout,
+      Err(error) => panic!("failed to create path trace pipeline layout with error: {error}")
+    }
+  }
+
+  // the software and ray query tracers share the layout, the software one leaves the acceleration structure unused
+  fn create_pipeline(device: &ash::Device, layout: &vk::PipelineLayout, spirv: &[u8]) -> vk::Pipeline {
+    let module = shader::create_shader_module(device, spirv);
+
+    let stage = {
+      vk::PipelineShaderStageCreateInfo::default()
+        .stage(vk::ShaderStageFlags::COMPUTE)
+        .module(module)
+        .name(c"compute_main")
+    };
+
+    let create_info = vk::ComputePipelineCreateInfo::default().stage(stage).layout(*layout);
+
+    let pipeline = match unsafe { device.create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None) } {
+      Ok(pipelines)   => pipelines[0],
+      Err((_, error)) => panic!("failed to create path trace pipeline with error: {error}")
+    };
+
+    unsafe { device.destroy_shader_module(module, None) };
+
+    pipeline
+  }
+}
@@ -0,0 +1,128 @@
+use crate::gvp_engine::{
+  allocator::{Allocator, Buffer, Image},
+  surface::SurfaceUpload
+};
+
+use ash::vk;
+
+use std::ffi::c_void;
+
+// the packed pixels of a surface and the panel target they are copied into
+pub struct SurfaceDraw<'a> {
+  pub target: &'a Image,
+  pub upload: &'a SurfaceUpload
+}
+
+// a staging buffer of one frame in flight, mapped while it lives
+struct Staging {
+  buffer: Buffer,
+  pointer: *mut c_void,
+  size: usize
+}
+
+// copies the changed regions of external surfaces into their panel targets, after the canvases so a surface covers
+// whatever was drawn into its panel. staging buffers only grow, to the most a frame uploaded so far
+#[derive(Default)]
+pub struct SurfaceUploader {
+  frames: Vec<Option<Staging>>
+}
+
+impl SurfaceUploader {
+  // the smallest staging buffer made, so small uploads do not each grow it a little
+  const MIN_STAGING: usize = 4 * 1024 * 1024;
+
+  pub fn new(frame_count: usize) -> Self {
+    SurfaceUploader { frames: (0..frame_count).map(|_| None).collect() }
+  }
+
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    for staging in self.frames.iter_mut().filter_map(Option::take) {
+      SurfaceUploader::destroy(device, allocator, &staging);
+    }
+  }
+
+  // makes room in the frame's staging buffer for all of its uploads. the gpu may still be reading the old buffer, so
+  // it is waited on before the buffer is replaced, which only happens while uploads are still growing
+  pub fn prepare(&mut self, device: &ash::Device, allocator: &Allocator, frame_index: usize, draws: &[SurfaceDraw]) {
+    let needed: usize = draws.iter().map(|draw| draw.upload.pixels.len()).sum();
+    if needed == 0 || self.frames[frame_index].as_ref().is_some_and(|staging| staging.size >= needed) { return; }
+
+    if let Some(staging) = self.frames[frame_index].take() {
+      if let Err(error) = unsafe { device.device_wait_idle() } {
+        panic!("failed to wait for device idle with error: {error}")
+      }
+
+      SurfaceUploader::destroy(device, allocator, &staging);
+    }
+
+    let size = needed.next_power_of_two().max(SurfaceUploader::MIN_STAGING);
+    let buffer = allocator.create_buffer(
+      device,
+      size as vk::DeviceSize,
+      vk::BufferUsageFlags::TRANSFER_SRC,
+      vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+    );
+
+    let pointer = allocator.map(device, &buffer);
+    self.frames[frame_index] = Some(Staging { buffer, pointer, size });
+  }
+
+  // targets are taken and left in SHADER_READ_ONLY_OPTIMAL
+  pub fn record(&self, device: &ash::Device, command_buffer: vk::CommandBuffer, frame_index: usize, draws: &[SurfaceDraw]) {
+    let Some(staging) = &self.frames[frame_index] else { return; };
+    let range = Allocator::color_range(0, 1, 0, 1);
+    let mut offset = 0;
+
+    for draw in draws {
+      let pixels = &draw.upload.pixels;
+      if pixels.is_empty() || offset + pixels.len() > staging.size { continue; }
+
+      unsafe { std::ptr::copy_nonoverlapping(pixels.as_ptr(), (staging.pointer as *mut u8).add(offset), pixels.len()) };
+
+      let regions: Vec<vk::BufferImageCopy> = draw.upload.regions.iter().map(|region| {
+        vk::BufferImageCopy::default()
+          .buffer_offset((offset + region.offset) as vk::DeviceSize)
+          .image_subresource(vk::ImageSubresourceLayers::default().aspect_mask(vk::ImageAspectFlags::COLOR).layer_count(1))
+          .image_offset(vk::Offset3D { x: region.rect.x as i32, y: region.rect.y as i32, z: 0 })
+          .image_extent(vk::Extent3D { width: region.rect.width, height: region.rect.height, depth: 1 })
+      }).collect();
+
+      offset += pixels.len();
+
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        draw.target.image,
+        range,
+        (vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+        (vk::AccessFlags::SHADER_READ, vk::AccessFlags::TRANSFER_WRITE),
+        (vk::PipelineStageFlags::FRAGMENT_SHADER, vk::PipelineStageFlags::TRANSFER)
+      );
+
+      unsafe {
+        device.cmd_copy_buffer_to_image(
+          command_buffer,
+          staging.buffer.buffer,
+          draw.target.image,
+          vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+          &regions
+        )
+      };
+
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        draw.target.image,
+        range,
+        (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+        (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ),
+        (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::FRAGMENT_SHADER)
+      );
+    }
+  }
+
+  fn destroy(device: &ash::Device, allocator: &Allocator, staging: &Staging) {
+    unsafe { device.unmap_memory(staging.buffer.memory) };
+    allocator.destroy_buffer(device, &staging.buffer);
+  }
+}
@@ -0,0 +1,452 @@
+mod components;
+mod file;
+
+pub use components::*;
+pub use file::*;
+
+use crate::gvp_engine::{material::Material, transform::Transform};
+
+use glam::Mat4;
+
+// handles stay valid until their entity is despawned. the generation keeps a reused slot from answering to an old
+// handle
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Entity {
+  index: u32,
+  generation: u32
+}
+
+struct EntityData {
+  generation: u32,
+  alive: bool,
+  name: Option<String>,
+  parent: Option<Entity>,
+  children: Vec<Entity>,
+  transform: Transform,
+  world: Mat4,
+  dirty: bool
+}
+
+// one slot per entity index along with the revision the component was last written at
+pub struct Components<T> {
+  entries: Vec<Option<(T, u64)>>
+}
+
+impl<T> Default for Components<T> {
+  fn default() -> Self {
+    Components { entries: Vec::new() }
+  }
+}
+
+impl<T> Components<T> {
+  fn insert(&mut self, index: usize, value: T, revision: u64) {
+    if index >= self.entries.len() {
+      self.entries.resize_with(index + 1, || None);
+    }
+
+    self.entries[index] = Some((value, revision));
+  }
+
+  fn get(&self, index: usize) -> Option<&T> {
+    self.entries.get(index)?.as_ref().map(|(value, _)| value)
+  }
+
+  fn get_mut(&mut self, index: usize, revision: u64) -> Option<&mut T> {
+    let (value, changed) = self.entries.get_mut(index)?.as_mut()?;
+    *changed = revision;
+
+    Some(value)
+  }
+
+  fn remove(&mut self, index: usize) -> Option<T> {
+    self.entries.get_mut(index)?.take().map(|(value, _)| value)
+  }
+
+  fn iter(&self) -> impl Iterator<Item = (usize, &T, u64)> {
+    self.entries.iter().enumerate().filter_map(|(index, entry)| {
+      entry.as_ref().map(|(value, revision)| (index, value, *revision))
+    })
+  }
+}
+
+pub trait Component: Sized + 'static {
+  fn components(scene: &Scene) -> &Components<Self>;
+  fn components_mut(scene: &mut Scene) -> &mut Components<Self>;
+}
+
+macro_rules! component {
+  ($type:ty, $field:ident) => {
+    impl Component for $type {
+      fn components(scene: &Scene) -> &Components<Self> { &scene.$field }
+      fn components_mut(scene: &mut Scene) -> &mut Components<Self> { &mut scene.$field }
+    }
+  };
+}
+
+component!(MeshSource, meshes);
+component!(Material, materials);
+component!(Light, lights);
+component!(CameraComponent, cameras);
+component!(AudioSource, audio_sources);
+component!(RigidBody, bodies);
+component!(Collider, colliders);
+
+#[derive(Default)]
+pub struct Scene {
+  entities: Vec<EntityData>,
+  free: Vec<u32>,
+  roots: Vec<Entity>,
+  // bumped on every change so consumers can ask for everything that happened since they last looked
+  revision: u64,
+  meshes: Components<MeshSource>,
+  materials: Components<Material>,
+  lights: Components<Light>,
+  cameras: Components<CameraComponent>,
+  audio_sources: Components<AudioSource>,
+  bodies: Components<RigidBody>,
+  colliders: Components<Collider>
+}
+
+impl Scene {
+  pub fn new() -> Self {
+    Scene::default()
+  }
+
+  pub fn revision(&self) -> u64 {
+    self.revision
+  }
+
+  pub fn spawn(&mut self, transform: Transform) -> Entity {
+    self.next_revision();
+
+    let data = EntityData {
+      generation: 0,
+      alive: true,
+      name: None,
+      parent: None,
+      children: Vec::new(),
+      transform,
+      world: transform.matrix(),
+      dirty: true
+    };
+
+    let entity = match self.free.pop() {
+      Some(index) => {
+        let generation = self.entities[index as usize].generation + 1;
+        self.entities[index as usize] = EntityData { generation, ..data };
+
+        Entity { index, generation }
+      },
+      None        => {
+        self.entities.push(data);
+        Entity { index: self.entities.len() as u32 - 1, generation: 0 }
+      }
+    };
+
+    self.roots.push(entity);
+    entity
+  }
+
+  pub fn spawn_child(&mut self, parent: Entity, transform: Transform) -> Entity {
+    let entity = self.spawn(transform);
+    self.set_parent(entity, Some(parent));
+
+    entity
+  }
+
+  // removes the entity along with all of its descendants and their components
+  pub fn despawn(&mut self, entity: Entity) {
+    if !self.is_alive(entity) { return; }
+
+    self.set_parent(entity, None);
+    self.roots.retain(|root| *root != entity);
+
+    let mut stack = vec![entity];
+    while let Some(entity) = stack.pop() {
+      let index = entity.index as usize;
+
+      stack.append(&mut self.entities[index].children);
+      self.entities[index].alive = false;
+      self.entities[index].name = None;
+
+      self.meshes.remove(index);
+      self.materials.remove(index);
+      self.lights.remove(index);
+      self.cameras.remove(index);
+      self.audio_sources.remove(index);
+      self.bodies.remove(index);
+      self.colliders.remove(index);
+
+      self.free.push(entity.index);
+    }
+
+    self.next_revision();
+  }
+
+  pub fn is_alive(&self, entity: Entity) -> bool {
+    match self.entities.get(entity.index as usize) {
+      Some(data) => data.alive && data.generation == entity.generation,
+      None       => false
+    }
+  }
+
+  pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+    self.entities.iter().enumerate()
+      .filter(|(_, data)| data.alive)
+      .map(|(index, data)| Entity { index: index as u32, generation: data.generation })
+  }
+
+  pub fn name(&self, entity: Entity) -> Option<&str> {
+    self.data(entity).name.as_deref()
+  }
+
+  pub fn set_name(&mut self, entity: Entity, name: &str) {
+    self.data_mut(entity).name = Some(String::from(name));
+  }
+
+  pub fn find(&self, name: &str) -> Option<Entity> {
+    self.entities().find(|entity| self.name(*entity) == Some(name))
+  }
+
+  pub fn parent(&self, entity: Entity) -> Option<Entity> {
+    self.data(entity).parent
+  }
+
+  pub fn children(&self, entity: Entity) -> &[Entity] {
+    &self.data(entity).children
+  }
+
+  pub fn roots(&self) -> &[Entity] {
+    &self.roots
+  }
+
+  pub fn set_parent(&mut self, entity: Entity, parent: Option<Entity>) {
+    if let Some(parent) = parent {
+      let mut ancestor = Some(parent);
+      while let Some(current) = ancestor {
+        if current == entity {
+          panic!("failed to set parent with error: entity would become its own ancestor")
+        }
+
+        ancestor = self.data(current).parent;
+      }
+    }
+
+    match self.data(entity).parent {
+      Some(old_parent) => self.data_mut(old_parent).children.retain(|child| *child != entity),
+      None             => self.roots.retain(|root| *root != entity)
+    }
+
+    match parent {
+      Some(parent) => self.data_mut(parent).children.push(entity),
+      None         => self.roots.push(entity)
+    }
+
+    let data = self.data_mut(entity);
+    data.parent = parent;
+    data.dirty = true;
+
+    self.next_revision();
+  }
+
+  // local transform relative to the parent
+  pub fn transform(&self, entity: Entity) -> &Transform {
+    &self.data(entity).transform
+  }
+
+  pub fn set_transform(&mut self, entity: Entity, transform: Transform) {
+    let data = self.data_mut(entity);
+    data.transform = transform;
+    data.dirty = true;
+  }
+
+  // world matrix as of the last call to update_transforms
+  pub fn world_matrix(&self, entity: Entity) -> Mat4 {
+    self.data(entity).world
+  }
+
+  // recomputes the world matrix of every dirty entity and everything below it
+  pub fn update_transforms(&mut self) {
+    let mut stack: Vec<(Entity, Mat4, bool)> = self.roots.iter().map(|root| (*root, Mat4::IDENTITY, false)).collect();
+
+    while let Some((entity, parent_world, parent_changed)) = stack.pop() {
+      let index = entity.index as usize;
+      let changed = parent_changed || self.entities[index].dirty;
+
+      if changed {
+        let data = &mut self.entities[index];
+
+        data.world = parent_world * data.transform.matrix();
+        data.dirty = false;
+      }
+
+      let data = &self.entities[index];
+      stack.extend(data.children.iter().map(|child| (*child, data.world, changed)));
+    }
+  }
+
+  pub fn insert<T: Component>(&mut self, entity: Entity, component: T) {
+    if !self.is_alive(entity) {
+      panic!("failed to insert component with error: entity {entity:?} is not alive")
+    }
+
+    let revision = self.next_revision();
+    T::components_mut(self).insert(entity.index as usize, component, revision);
+  }
+
+  pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+    if !self.is_alive(entity) { return None; }
+
+    T::components(self).get(entity.index as usize)
+  }
+
+  // handing out a mutable reference counts as a change
+  pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+    if !self.is_alive(entity) { return None; }
+
+    let revision = self.next_revision();
+    T::components_mut(self).get_mut(entity.index as usize, revision)
+  }
+
+  pub fn query<T: Component>(&self) -> impl Iterator<Item = (Entity, &T)> + '_ {
+    T::components(self).iter().map(|(index, component, _)| (self.entity(index), component))
+  }
+
+  pub fn changed_since<T: Component>(&self, revision: u64) -> impl Iterator<Item = (Entity, &T)> + '_ {
+    T::components(self).iter()
+      .filter(move |(_, _, changed)| *changed > revision)
+      .map(|(index, component, _)| (self.entity(index), component))
+  }
+
+  pub fn active_camera(&self) -> Option<(Entity, &CameraComponent)> {
+    self.query::<CameraComponent>().find(|(_, camera)| camera.active)
+  }
+
+  fn entity(&self, index: usize) -> Entity {
+    Entity { index: index as u32, generation: self.entities[index].generation }
+  }
+
+  fn next_revision(&mut self) -> u64 {
+    self.revision += 1;
+    self.revision
+  }
+
+  fn data(&self, entity: Entity) -> &EntityData {
+    match self.is_alive(entity) {
+      true  => &self.entities[entity.index as usize],
+      false => panic!("failed to access entity with error: entity {entity:?} is not alive")
+    }
+  }
+
+  fn data_mut(&mut self, entity: Entity) -> &mut EntityData {
+    match self.is_alive(entity) {
+      true  => &mut self.entities[entity.index as usize],
+      false => panic!("failed to access entity with error: entity {entity:?} is not alive")
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use glam::Vec3;
+
+  #[test]
+  fn reuses_despawned_slots_under_a_new_generation() {
+    let mut scene = Scene::new();
+    let first = scene.spawn(Transform::IDENTITY);
+    scene.despawn(first);
+
+    let second = scene.spawn(Transform::IDENTITY);
+    assert_eq!(second.index, first.index);
+    assert_eq!(second.generation, first.generation + 1);
+    assert!(!scene.is_alive(first));
+    assert!(scene.is_alive(second));
+    assert_eq!(scene.entities().collect::<Vec<_>>(), vec![second]);
+  }
+
+  #[test]
+  fn despawns_a_whole_subtree() {
+    let mut scene = Scene::new();
+    let root = scene.spawn(Transform::IDENTITY);
+    let parent = scene.spawn_child(root, Transform::IDENTITY);
+    let child = scene.spawn_child(parent, Transform::IDENTITY);
+    scene.insert(child, Material::color([1.0; 4], 0.0, 1.0));
+    scene.set_name(child, "child");
+
+    scene.despawn(parent);
+
+    assert!(scene.is_alive(root));
+    assert!(!scene.is_alive(parent));
+    assert!(!scene.is_alive(child));
+    assert!(scene.children(root).is_empty());
+    assert_eq!(scene.find("child"), None);
+    assert_eq!(scene.query::<Material>().count(), 0);
+
+    // a despawned slot comes back without the components it had
+    let reused = scene.spawn(Transform::IDENTITY);
+    assert!(scene.get::<Material>(reused).is_none());
+  }
+
+  #[test]
+  fn reparenting_moves_the_entity_and_its_world_matrix() {
+    let mut scene = Scene::new();
+    let left = scene.spawn(Transform::from_translation(Vec3::X));
+    let right = scene.spawn(Transform::from_translation(Vec3::NEG_X));
+    let child = scene.spawn_child(left, Transform::from_translation(Vec3::Y));
+    scene.update_transforms();
+    assert_eq!(scene.world_matrix(child).w_axis.truncate(), Vec3::new(1.0, 1.0, 0.0));
+
+    scene.set_parent(child, Some(right));
+    scene.update_transforms();
+    assert_eq!(scene.parent(child), Some(right));
+    assert!(scene.children(left).is_empty());
+    assert_eq!(scene.children(right), &[child]);
+    assert_eq!(scene.world_matrix(child).w_axis.truncate(), Vec3::new(-1.0, 1.0, 0.0));
+
+    scene.set_parent(child, None);
+    scene.update_transforms();
+    assert_eq!(scene.roots(), &[left, right, child]);
+    assert_eq!(scene.world_matrix(child).w_axis.truncate(), Vec3::Y);
+
+    // moving a parent carries its children along
+    scene.set_parent(child, Some(left));
+    scene.set_transform(left, Transform::from_translation(Vec3::Z));
+    scene.update_transforms();
+    assert_eq!(scene.world_matrix(child).w_axis.truncate(), Vec3::new(0.0, 1.0, 1.0));
+  }
+
+  #[test]
+  #[should_panic(expected = "its own ancestor")]
+  fn refuses_to_parent_an_entity_under_its_descendant() {
+    let mut scene = Scene::new();
+    let parent = scene.spawn(Transform::IDENTITY);
+    let child = scene.spawn_child(parent, Transform::IDENTITY);
+
+    scene.set_parent(parent, Some(child));
+  }
+
+  #[test]
+  fn reports_components_changed_since_a_revision() {
+    let mut scene = Scene::new();
+    let first = scene.spawn(Transform::IDENTITY);
+    let second = scene.spawn(Transform::IDENTITY);
+    scene.insert(first, Material::color([1.0; 4], 0.0, 1.0));
+    scene.insert(second, Material::color([1.0; 4], 0.0, 1.0));
+
+    let synced = scene.revision();
+    assert_eq!(scene.changed_since::<Material>(synced).count(), 0);
+
+    scene.get_mut::<Material>(second);
+    let changed: Vec<Entity> = scene.changed_since::<Material>(synced).map(|(entity, _)| entity).collect();
+    assert_eq!(changed, vec![second]);
+
+    // reading or touching other components leaves it alone
+    let synced = scene.revision();
+    scene.get::<Material>(first);
+    scene.insert(first, Light { kind: LightKind::Point { range: 1.0 }, color: Vec3::ONE, intensity: 1.0 });
+    assert_eq!(scene.changed_since::<Material>(synced).count(), 0);
+    assert_eq!(scene.changed_since::<Light>(synced).count(), 1);
+  }
+}
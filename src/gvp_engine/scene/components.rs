@@ -0,0 +1,150 @@
+use crate::gvp_engine::primitives::PrimitiveKind;
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use std::path::PathBuf;
+
+// where the geometry of an entity comes from. the engine turns these into uploaded meshes when they change
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MeshSource {
+  Primitive(PrimitiveKind),
+  Model { model: usize, mesh: usize, primitive: usize }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LightKind {
+  // shines along the forward (-z) axis of its entity
+  Directional,
+  Point { range: f32 },
+  Spot { range: f32, inner_angle: f32, outer_angle: f32 }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Light {
+  pub kind: LightKind,
+  pub color: Vec3,
+  pub intensity: f32
+}
+
+// looks down the forward (-z) axis of its entity. only the active camera is rendered from
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraComponent {
+  pub fov_y: f32,
+  pub near: f32,
+  pub far: f32,
+  pub active: bool
+}
+
+impl Default for CameraComponent {
+  fn default() -> Self {
+    CameraComponent {
+      fov_y: 70f32.to_radians(),
+      near: 0.05,
+      far: 500.0,
+      active: true
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AudioSource {
+  pub clip: Option<PathBuf>,
+  pub volume: f32,
+  pub looping: bool,
+  // non spatial sources play straight into the listener like headphones would
+  pub spatial: bool,
+  // holds the clip where it is. clips that do not loop pause back at the start once they end
+  #[serde(default)]
+  pub paused: bool
+}
+
+impl Default for AudioSource {
+  fn default() -> Self {
+    AudioSource {
+      clip: None,
+      volume: 1.0,
+      looping: false,
+      spatial: true,
+      paused: false
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum BodyKind {
+  Static,
+  #[default]
+  Dynamic,
+  Kinematic
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RigidBody {
+  pub kind: BodyKind,
+  pub mass: f32,
+  pub linear_velocity: Vec3,
+  pub angular_velocity: Vec3
+}
+
+impl Default for RigidBody {
+  fn default() -> Self {
+    RigidBody {
+      kind: BodyKind::Dynamic,
+      mass: 1.0,
+      linear_velocity: Vec3::ZERO,
+      angular_velocity: Vec3::ZERO
+    }
+  }
+}
+
+// in the entity's own units, scaled along with it. spheres and capsules stay round, taking the largest scale across
+// them
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ColliderShape {
+  Sphere { radius: f32 },
+  Box { half_extents: Vec3 },
+  // along the entity's y axis, half_height being the distance from the center to either cap's center
+  Capsule { radius: f32, half_height: f32 }
+}
+
+// what a rigid body collides with. an entity with a collider and no rigid body is a static obstacle
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Collider {
+  pub shape: ColliderShape,
+  // of the shape's center from the entity's origin
+  pub offset: Vec3,
+  pub friction: f32,
+  pub restitution: f32
+}
+
+impl Collider {
+  pub fn new(shape: ColliderShape) -> Self {
+    Collider {
+      shape,
+      offset: Vec3::ZERO,
+      friction: 0.6,
+      restitution: 0.2
+    }
+  }
+
+  // fitted to the generated mesh. the shapes without a collider of their own get their bounding box
+  pub fn for_primitive(kind: PrimitiveKind) -> Self {
+    match kind {
+      PrimitiveKind::UvSphere { .. } | PrimitiveKind::IcoSphere { .. } => Collider::new(ColliderShape::Sphere { radius: 0.5 }),
+      PrimitiveKind::Capsule { .. }                                    => Collider::new(ColliderShape::Capsule { radius: 0.5, half_height: 0.5 }),
+      // a slab under the plane so things resting on it are not resting on an edge
+      PrimitiveKind::Plane { .. }                                      => Collider {
+        offset: Vec3::new(0.0, -0.05, 0.0),
+        ..Collider::new(ColliderShape::Box { half_extents: Vec3::new(0.5, 0.05, 0.5) })
+      },
+      PrimitiveKind::Torus { tube_radius, .. }                         => {
+        let tube = tube_radius.clamp(0.01, 0.5);
+        Collider::new(ColliderShape::Box { half_extents: Vec3::new(0.5 + tube, tube, 0.5 + tube) })
+      }
+      PrimitiveKind::Cube { .. } | PrimitiveKind::Cylinder { .. } | PrimitiveKind::Cone { .. } => {
+        Collider::new(ColliderShape::Box { half_extents: Vec3::splat(0.5) })
+      }
+    }
+  }
+}
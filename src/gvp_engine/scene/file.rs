@@ -0,0 +1,446 @@
+use crate::gvp_engine::{
+  material::Material,
+  primitives::PrimitiveKind,
+  scene::{AudioSource, CameraComponent, Collider, Entity, Light, MeshSource, RigidBody, Scene},
+  transform::Transform
+};
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt, fs, path::{Path, PathBuf}};
+
+pub const SCENE_VERSION: u64 = 2;
+
+// upgrades a document in place to the version after its own
+type Migration = fn(&mut Value);
+
+// MIGRATIONS[n] upgrades the oldest version still read, SCENE_VERSION - MIGRATIONS.len(), plus n. bump SCENE_VERSION
+// and push a hook here whenever the format changes so older workspaces keep loading
+const MIGRATIONS: [Migration; 1] = [add_panels];
+
+// version 1 did not keep where app panels were placed
+fn add_panels(value: &mut Value) {
+  value["panels"] = Value::Object(Map::new());
+}
+
+// why a scene file was not read
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneError {
+  Read(String),
+  Write(String),
+  Parse(String),
+  MissingVersion,
+  // older than the migrations reach back to, or written by a newer build
+  UnsupportedVersion(u64),
+  Invalid(String),
+  // an entity recorded before its parent
+  ParentOrder(u32)
+}
+
+impl fmt::Display for SceneError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SceneError::Read(error)               => write!(formatter, "could not read file: {error}"),
+      SceneError::Write(error)              => write!(formatter, "could not write file: {error}"),
+      SceneError::Parse(error)              => write!(formatter, "could not parse: {error}"),
+      SceneError::MissingVersion            => write!(formatter, "missing version"),
+      SceneError::UnsupportedVersion(version) => write!(formatter, "version {version} is not supported (newest is {SCENE_VERSION})"),
+      SceneError::Invalid(error)            => write!(formatter, "does not match the scene format: {error}"),
+      SceneError::ParentOrder(id)           => write!(formatter, "entity {id} appears before its parent")
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssetRecord {
+  pub id: Uuid,
+  pub path: PathBuf
+}
+
+// like MeshSource but refers to models by asset id instead of by their index in the running engine
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MeshRecord {
+  Primitive(PrimitiveKind),
+  Model { asset: Uuid, mesh: usize, primitive: usize }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EntityRecord {
+  pub id: u32,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub parent: Option<u32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  pub transform: Transform,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub mesh: Option<MeshRecord>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub material: Option<Material>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub light: Option<Light>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub camera: Option<CameraComponent>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub audio_source: Option<AudioSource>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub rigid_body: Option<RigidBody>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub collider: Option<Collider>
+}
+
+// where an app's panel was left, keyed by the app's name in the file
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PanelRecord {
+  pub transform: Transform,
+  pub size: Vec2
+}
+
+// a model the engine has loaded, as far as restoring the entities drawing it goes
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadedModel {
+  pub index: usize,
+  // how many primitives each of its meshes has
+  pub primitives: Vec<usize>
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+  pub version: u64,
+  pub assets: Vec<AssetRecord>,
+  // the panorama or cubemap the sky and lighting come from, none for the built in one
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub environment: Option<PathBuf>,
+  // parents always come before their children
+  pub entities: Vec<EntityRecord>,
+  pub panels: BTreeMap<String, PanelRecord>
+}
+
+impl SceneFile {
+  // models holds the asset record of every model the engine has loaded, indexed the same way MeshSource indexes them
+  pub fn capture(scene: &Scene, models: &[AssetRecord], environment: Option<&Path>, panels: BTreeMap<String, PanelRecord>) -> Self {
+    let mut ids = HashMap::<Entity, u32>::new();
+    let mut entities = Vec::<EntityRecord>::new();
+    let mut used_assets = Vec::<AssetRecord>::new();
+
+    let mut stack: Vec<Entity> = scene.roots().iter().rev().copied().collect();
+    while let Some(entity) = stack.pop() {
+      let id = entities.len() as u32;
+      ids.insert(entity, id);
+
+      let mesh = scene.get::<MeshSource>(entity).map(|source| match *source {
+        MeshSource::Primitive(kind)                  => MeshRecord::Primitive(kind),
+        MeshSource::Model { model, mesh, primitive } => {
+          let asset = &models[model];
+          if !used_assets.contains(asset) {
+            used_assets.push(asset.clone());
+          }
+
+          MeshRecord::Model { asset: asset.id, mesh, primitive }
+        }
+      });
+
+      entities.push(EntityRecord {
+        id,
+        parent: scene.parent(entity).map(|parent| ids[&parent]),
+        name: scene.name(entity).map(String::from),
+        transform: *scene.transform(entity),
+        mesh,
+        material: scene.get::<Material>(entity).cloned(),
+        light: scene.get::<Light>(entity).copied(),
+        camera: scene.get::<CameraComponent>(entity).copied(),
+        audio_source: scene.get::<AudioSource>(entity).cloned(),
+        rigid_body: scene.get::<RigidBody>(entity).copied(),
+        collider: scene.get::<Collider>(entity).copied()
+      });
+
+      stack.extend(scene.children(entity).iter().rev());
+    }
+
+    SceneFile {
+      version: SCENE_VERSION,
+      assets: used_assets,
+      environment: environment.map(Path::to_path_buf),
+      entities,
+      panels
+    }
+  }
+
+  // spawns every recorded entity into the scene. models maps asset ids to the already loaded models, the entities of
+  // models missing from it or drawing a mesh the model does not have are spawned without their mesh. nothing is spawned
+  // if an entity comes before its parent
+  pub fn restore(&self, scene: &mut Scene, models: &HashMap<Uuid, LoadedModel>) -> Result<Vec<Entity>, SceneError> {
+    self.check_order()?;
+
+    let mut spawned = HashMap::<u32, Entity>::new();
+    let mut roots = Vec::<Entity>::new();
+
+    for record in &self.entities {
+      let entity = match record.parent {
+        Some(parent) => scene.spawn_child(spawned[&parent], record.transform),
+        None         => {
+          let entity = scene.spawn(record.transform);
+          roots.push(entity);
+
+          entity
+        }
+      };
+
+      spawned.insert(record.id, entity);
+
+      if let Some(name) = &record.name {
+        scene.set_name(entity, name);
+      }
+
+      let source = record.mesh.and_then(|mesh| match mesh {
+        MeshRecord::Primitive(kind)                  => Some(MeshSource::Primitive(kind)),
+        MeshRecord::Model { asset, mesh, primitive } => models.get(&asset)
+          .filter(|model| model.primitives.get(mesh).is_some_and(|count| primitive < *count))
+          .map(|model| MeshSource::Model { model: model.index, mesh, primitive })
+      });
+
+      if let Some(source) = source {
+        scene.insert(entity, source);
+      }
+
+      if let Some(material) = &record.material { scene.insert(entity, material.clone()); }
+      if let Some(light) = record.light { scene.insert(entity, light); }
+      if let Some(camera) = record.camera { scene.insert(entity, camera); }
+      if let Some(audio_source) = &record.audio_source { scene.insert(entity, audio_source.clone()); }
+      if let Some(rigid_body) = record.rigid_body { scene.insert(entity, rigid_body); }
+      if let Some(collider) = record.collider { scene.insert(entity, collider); }
+    }
+
+    Ok(roots)
+  }
+
+  pub fn to_json(&self) -> String {
+    match serde_json::to_string_pretty(self) {
+      Ok(json)   => json,
+      Err(error) => panic!("failed to serialize scene with error: {error}")
+    }
+  }
+
+  // upgrades documents written by older versions before reading them
+  pub fn from_json(json: &str) -> Result<Self, SceneError> {
+    let mut value: Value = serde_json::from_str(json).map_err(|error| SceneError::Parse(error.to_string()))?;
+    SceneFile::migrate(&mut value, &MIGRATIONS, SCENE_VERSION)?;
+
+    let file: SceneFile = serde_json::from_value(value).map_err(|error| SceneError::Invalid(error.to_string()))?;
+    file.check_order()?;
+
+    Ok(file)
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), SceneError> {
+    fs::write(path, self.to_json()).map_err(|error| SceneError::Write(error.to_string()))
+  }
+
+  pub fn load(path: &Path) -> Result<Self, SceneError> {
+    let json = fs::read_to_string(path).map_err(|error| SceneError::Read(error.to_string()))?;
+    SceneFile::from_json(&json)
+  }
+
+  // restoring spawns parents first, so a child before its parent would have nothing to attach to
+  fn check_order(&self) -> Result<(), SceneError> {
+    let mut seen = HashSet::<u32>::new();
+    for record in &self.entities {
+      if record.parent.is_some_and(|parent| !seen.contains(&parent)) {
+        return Err(SceneError::ParentOrder(record.id));
+      }
+
+      seen.insert(record.id);
+    }
+
+    Ok(())
+  }
+
+  // runs the migrations from the document's version up to newest, the first of them upgrading the oldest version read
+  fn migrate(value: &mut Value, migrations: &[Migration], newest: u64) -> Result<(), SceneError> {
+    let mut version = value.get("version").and_then(Value::as_u64).ok_or(SceneError::MissingVersion)?;
+    let oldest = newest - migrations.len() as u64;
+
+    if version < oldest || version > newest {
+      return Err(SceneError::UnsupportedVersion(version));
+    }
+
+    while version < newest {
+      migrations[(version - oldest) as usize](value);
+
+      version += 1;
+      value["version"] = Value::from(version);
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::gvp_engine::scene::{BodyKind, ColliderShape, LightKind};
+
+  use glam::{Quat, Vec3};
+  use serde_json::json;
+
+  // an entity with every built in component, and children drawing a model and a primitive
+  fn scene() -> Scene {
+    let mut scene = Scene::new();
+
+    let root = scene.spawn(Transform::new(Vec3::new(1.0, 2.0, 3.0), Quat::from_rotation_y(0.5), Vec3::splat(2.0)));
+    scene.set_name(root, "root");
+    scene.insert(root, MeshSource::Primitive(PrimitiveKind::Torus { major_segments: 24, minor_segments: 12, tube_radius: 0.1 }));
+    scene.insert(root, Material::color([0.2, 0.4, 0.6, 1.0], 0.3, 0.7));
+    scene.insert(root, Light { kind: LightKind::Spot { range: 8.0, inner_angle: 0.3, outer_angle: 0.6 }, color: Vec3::X, intensity: 4.0 });
+    scene.insert(root, CameraComponent { fov_y: 1.2, near: 0.05, far: 50.0, active: true });
+    scene.insert(root, AudioSource { clip: Some(PathBuf::from("sounds/rain.ogg")), volume: 0.5, looping: true, ..AudioSource::default() });
+    scene.insert(root, RigidBody { kind: BodyKind::Kinematic, mass: 3.0, linear_velocity: Vec3::Y, angular_velocity: Vec3::Z });
+    scene.insert(root, Collider { shape: ColliderShape::Capsule { radius: 0.25, half_height: 0.5 }, offset: Vec3::X, friction: 0.2, restitution: 0.8 });
+
+    let child = scene.spawn_child(root, Transform::from_translation(Vec3::NEG_Z));
+    scene.insert(child, MeshSource::Model { model: 0, mesh: 2, primitive: 1 });
+    scene.insert(child, Light { kind: LightKind::Directional, color: Vec3::ONE, intensity: 1.5 });
+
+    let grandchild = scene.spawn_child(child, Transform::IDENTITY);
+    scene.insert(grandchild, Collider::new(ColliderShape::Box { half_extents: Vec3::splat(0.5) }));
+
+    scene.spawn(Transform::IDENTITY);
+    scene.set_name(scene.roots()[1], "empty");
+
+    scene
+  }
+
+  // has the mesh and primitive the scene's child draws
+  fn chair() -> LoadedModel {
+    LoadedModel { index: 0, primitives: vec![1, 1, 2] }
+  }
+
+  #[test]
+  fn round_trips_every_component() {
+    let model = Uuid::new_v4();
+    let assets = [AssetRecord { id: model, path: PathBuf::from("models/chair.glb") }];
+    let environment = Path::new("skies/noon.hdr");
+    let panels = BTreeMap::from([(String::from("Documents"), PanelRecord { transform: Transform::from_translation(Vec3::Y), size: Vec2::new(1.2, 0.8) })]);
+
+    let file = SceneFile::capture(&scene(), &assets, Some(environment), panels.clone());
+    let read = SceneFile::from_json(&file.to_json()).unwrap();
+    assert_eq!(read, file);
+    assert_eq!(read.environment.as_deref(), Some(environment));
+
+    let mut restored = Scene::new();
+    let roots = read.restore(&mut restored, &HashMap::from([(model, chair())])).unwrap();
+    assert_eq!(roots.len(), 2);
+
+    // capturing what was restored gives back the same file, so nothing was lost either way
+    assert_eq!(SceneFile::capture(&restored, &assets, Some(environment), panels), file);
+  }
+
+  #[test]
+  fn restores_entities_of_missing_models_without_their_mesh() {
+    let model = Uuid::new_v4();
+    let assets = [AssetRecord { id: model, path: PathBuf::from("models/chair.glb") }];
+    let file = SceneFile::capture(&scene(), &assets, None, BTreeMap::new());
+
+    let mut restored = Scene::new();
+    let roots = file.restore(&mut restored, &HashMap::new()).unwrap();
+    let child = restored.children(roots[0])[0];
+
+    assert!(restored.get::<MeshSource>(child).is_none());
+    assert!(restored.get::<Light>(child).is_some());
+  }
+
+  #[test]
+  fn restores_entities_drawing_a_mesh_the_model_lacks_without_it() {
+    let model = Uuid::new_v4();
+    let assets = [AssetRecord { id: model, path: PathBuf::from("models/chair.glb") }];
+    let file = SceneFile::capture(&scene(), &assets, None, BTreeMap::new());
+
+    // one mesh too few, then one primitive too few
+    for primitives in [vec![1, 1], vec![1, 1, 1]] {
+      let mut restored = Scene::new();
+      let roots = file.restore(&mut restored, &HashMap::from([(model, LoadedModel { index: 0, primitives })])).unwrap();
+      let child = restored.children(roots[0])[0];
+
+      assert!(restored.get::<MeshSource>(child).is_none());
+      assert!(restored.get::<MeshSource>(roots[0]).is_some());
+    }
+  }
+
+  #[test]
+  fn restores_nothing_when_a_child_comes_before_its_parent() {
+    let mut file = SceneFile::capture(&scene(), &[AssetRecord { id: Uuid::new_v4(), path: PathBuf::new() }], None, BTreeMap::new());
+    file.entities.swap(0, 1);
+
+    let mut restored = Scene::new();
+    assert_eq!(file.restore(&mut restored, &HashMap::new()), Err(SceneError::ParentOrder(1)));
+    assert_eq!(restored.entities().count(), 0);
+  }
+
+  #[test]
+  fn reports_files_it_cannot_write() {
+    let file = SceneFile::capture(&Scene::new(), &[], None, BTreeMap::new());
+    let path = std::env::temp_dir().join(format!("missing_{}", std::process::id())).join("workspace.json");
+
+    assert!(matches!(file.save(&path), Err(SceneError::Write(_))));
+  }
+
+  #[test]
+  fn migrates_older_versions() {
+    // pretend version 0 called entities nodes
+    fn rename_nodes(value: &mut Value) {
+      if let Some(nodes) = value.as_object_mut().and_then(|object| object.remove("nodes")) {
+        value["entities"] = nodes;
+      }
+    }
+
+    let mut value = json!({
+      "version": 0,
+      "assets": [],
+      "nodes": [{ "id": 0, "name": "old", "transform": Transform::IDENTITY }],
+      "panels": {}
+    });
+
+    SceneFile::migrate(&mut value, &[rename_nodes], 1).unwrap();
+    assert_eq!(value["version"], 1);
+
+    let file: SceneFile = serde_json::from_value(value).unwrap();
+    assert_eq!(file.entities[0].name.as_deref(), Some("old"));
+
+    // without the migration version 0 is too old to read
+    let mut value = json!({ "version": 0 });
+    assert_eq!(SceneFile::migrate(&mut value, &[], 1), Err(SceneError::UnsupportedVersion(0)));
+  }
+
+  #[test]
+  fn reads_version_one_without_panel_placements() {
+    let old = json!({
+      "version": 1,
+      "assets": [],
+      "entities": [{ "id": 0, "name": "old", "transform": Transform::IDENTITY }]
+    }).to_string();
+
+    let file = SceneFile::from_json(&old).unwrap();
+    assert_eq!(file.version, SCENE_VERSION);
+    assert!(file.panels.is_empty());
+  }
+
+  #[test]
+  fn rejects_unreadable_files() {
+    let newer = json!({ "version": SCENE_VERSION + 1, "assets": [], "entities": [], "panels": {} }).to_string();
+    let orphan = json!({
+      "version": SCENE_VERSION,
+      "assets": [],
+      "entities": [{ "id": 1, "parent": 0, "transform": Transform::IDENTITY }],
+      "panels": {}
+    }).to_string();
+
+    assert!(matches!(SceneFile::from_json("{ not json"), Err(SceneError::Parse(_))));
+    assert_eq!(SceneFile::from_json("{}"), Err(SceneError::MissingVersion));
+    assert_eq!(SceneFile::from_json(&newer), Err(SceneError::UnsupportedVersion(SCENE_VERSION + 1)));
+    assert!(matches!(SceneFile::from_json(&json!({ "version": SCENE_VERSION }).to_string()), Err(SceneError::Invalid(_))));
+    assert_eq!(SceneFile::from_json(&orphan), Err(SceneError::ParentOrder(1)));
+  }
+}
@@ -0,0 +1,36 @@
+use ash::{util::read_spv, vk};
+
+use std::io::Cursor;
+
+// shaders are compiled from shaders/*.wgsl and shaders/*/*.wgsl into OUT_DIR by build.rs
+macro_rules! spirv {
+  ($name:literal) => {
+    include_bytes!(concat!(env!("OUT_DIR"), "/", $name, ".spv"))
+  };
+}
+
+pub const MESH: &[u8] = spirv!("mesh");
+pub const EQUIRECT_TO_CUBE: &[u8] = spirv!("equirect_to_cube");
+pub const IRRADIANCE: &[u8] = spirv!("irradiance");
+pub const PREFILTER: &[u8] = spirv!("prefilter");
+pub const BRDF_LUT: &[u8] = spirv!("brdf_lut");
+pub const SKYBOX: &[u8] = spirv!("skybox");
+pub const PANEL: &[u8] = spirv!("panel");
+pub const CANVAS: &[u8] = spirv!("canvas");
+pub const GLYPH: &[u8] = spirv!("glyph");
+pub const PATH_TRACE_BVH: &[u8] = spirv!("path_trace_bvh");
+pub const PATH_TRACE_RAY_QUERY: &[u8] = spirv!("path_trace_ray_query");
+
+pub fn create_shader_module(device: &ash::Device, spirv: &[u8]) -> vk::ShaderModule {
+  let code = match read_spv(&mut Cursor::new(spirv)) {
+    Ok(code)   => code,
+    Err(error) => panic!("failed to read spir-v with error: {error}")
+  };
+
+  let create_info = vk::ShaderModuleCreateInfo::default().code(&code);
+
+  match unsafe { device.create_shader_module(&create_info, None) } {
+    Ok(module) => module,
+    Err(error) => panic!("failed to create shader module with error: {error}")
+  }
+}
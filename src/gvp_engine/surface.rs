@@ -0,0 +1,448 @@
+mod app;
+mod pattern;
+
+pub use app::*;
+pub use pattern::*;
+
+use crate::gvp_engine::{
+  compositor::{Compositor, PanelHit, PanelId},
+  gui::GuiInput,
+  input::Input
+};
+
+use glam::Vec2;
+use sdl2::keyboard::Keycode;
+
+use std::{mem, time::Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SurfaceId(usize);
+
+// 8 bits per channel in srgb, which is what panel targets hold. bgra is swizzled while packing the upload
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PixelFormat {
+  #[default]
+  Rgba8,
+  Bgra8
+}
+
+// in pixels from the top left of the surface
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DirtyRect {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32
+}
+
+impl DirtyRect {
+  pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+    DirtyRect { x, y, width, height }
+  }
+
+  pub fn full(width: u32, height: u32) -> Self {
+    DirtyRect::new(0, 0, width, height)
+  }
+
+  pub fn area(&self) -> u64 {
+    self.width as u64 * self.height as u64
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.width == 0 || self.height == 0
+  }
+
+  // sources hand over any rect, so the far edges stop at u32::MAX instead of wrapping
+  fn right(&self) -> u32 {
+    self.x.saturating_add(self.width)
+  }
+
+  fn bottom(&self) -> u32 {
+    self.y.saturating_add(self.height)
+  }
+
+  pub fn union(&self, other: DirtyRect) -> DirtyRect {
+    if self.is_empty() { return other; }
+    if other.is_empty() { return *self; }
+
+    let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+    let right = self.right().max(other.right());
+    let bottom = self.bottom().max(other.bottom());
+
+    DirtyRect::new(x, y, right - x, bottom - y)
+  }
+
+  pub fn overlaps(&self, other: DirtyRect) -> bool {
+    self.x < other.right() && other.x < self.right() && self.y < other.bottom() && other.y < self.bottom()
+  }
+
+  // cut to a surface of this size
+  pub fn clamp(&self, width: u32, height: u32) -> DirtyRect {
+    let (x, y) = (self.x.min(width), self.y.min(height));
+    DirtyRect::new(x, y, self.width.min(width - x), self.height.min(height - y))
+  }
+}
+
+// what changed since the last frame a source handed over
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Damage {
+  #[default]
+  None,
+  Full,
+  Rects(Vec<DirtyRect>)
+}
+
+// a frame of a source's pixels. rows are stride bytes apart and only the damaged parts are read. frames with too few
+// pixels for their size are skipped
+pub struct SurfaceFrame<'a> {
+  pub pixels: &'a [u8],
+  pub width: u32,
+  pub height: u32,
+  pub stride: u32,
+  pub format: PixelFormat,
+  pub damage: Damage
+}
+
+// input over the surface's panel, positions in the surface's pixels
+#[derive(Clone, Debug, PartialEq)]
+pub enum SurfaceEvent {
+  PointerMoved(Vec2),
+  PointerLeft,
+  // the select action, where it went down or came back up
+  Pressed(Vec2),
+  Released(Vec2),
+  // wheel clicks, up and right being positive
+  Scrolled(Vec2),
+  // only while the panel is focused, repeating while held
+  KeyPressed(Keycode),
+  Typed(String),
+  Focused(bool)
+}
+
+// pixels drawn outside the engine, like a web view, shown on a panel. the source is resized to the panel's pixels
+// before its first frame and whenever they change, and only the damage of a frame is uploaded
+pub trait ExternalSurface {
+  fn resize(&mut self, width: u32, height: u32);
+
+  // seconds since the last call. none when nothing changed. a frame of another size than the last resize is skipped
+  fn frame(&mut self, delta: f32) -> Option<SurfaceFrame<'_>>;
+
+  fn input(&mut self, _event: &SurfaceEvent) {}
+}
+
+// a rectangle of a surface's upload, its rows packed tightly from offset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceRegion {
+  pub rect: DirtyRect,
+  pub offset: usize
+}
+
+// the changed pixels of one surface in a frame, ready to be copied into its panel's target
+pub struct SurfaceUpload {
+  pub panel: PanelId,
+  pub pixels: Vec<u8>,
+  pub regions: Vec<SurfaceRegion>
+}
+
+// what a source was last told about the pointer and focus, so only changes are sent
+#[derive(Default)]
+struct Forwarded {
+  pointer: Option<Vec2>,
+  focused: bool
+}
+
+struct Entry {
+  panel: PanelId,
+  source: Box<dyn ExternalSurface>,
+  size: (u32, u32),
+  // damage not uploaded yet, from frames skipped for having the wrong size
+  pending: Damage,
+  forwarded: Forwarded
+}
+
+// the external surfaces attached to panels, pulled for their frames once a frame
+#[derive(Default)]
+pub struct Surfaces {
+  // ids index into this and are not reused once a surface is detached
+  entries: Vec<Option<Entry>>,
+  last_update: Option<Instant>
+}
+
+impl Surfaces {
+  // past this many rectangles a frame's damage is uploaded as their bounds
+  const MAX_RECTS: usize = 16;
+  // uploading the damage separately stops paying off once it covers this much of the bounds
+  const MERGE_COVERAGE: f64 = 0.75;
+
+  // replaces whatever the panel showed. a panel shows one surface, the last one attached
+  pub fn attach(&mut self, panel: PanelId, source: Box<dyn ExternalSurface>) -> SurfaceId {
+    self.detach_panel(panel);
+    self.entries.push(Some(Entry { panel, source, size: (0, 0), pending: Damage::None, forwarded: Forwarded::default() }));
+
+    SurfaceId(self.entries.len() - 1)
+  }
+
+  pub fn detach_panel(&mut self, panel: PanelId) {
+    for slot in &mut self.entries {
+      if slot.as_ref().is_some_and(|entry| entry.panel == panel) {
+        *slot = None;
+      }
+    }
+  }
+
+  // the panel's target was drawn over, so the next frame of its surface goes up whole
+  pub fn invalidate(&mut self, panel: PanelId) {
+    for entry in self.entries.iter_mut().flatten().filter(|entry| entry.panel == panel) {
+      entry.pending = Damage::Full;
+    }
+  }
+
+  // forwards the frame's input to every source, keeps them sized to their panels and packs what they changed
+  pub fn update(&mut self, compositor: &Compositor, input: &Input, hit: Option<PanelHit>, focused: Option<PanelId>) -> Vec<SurfaceUpload> {
+    let now = Instant::now();
+    let delta = self.last_update.map_or(0.0, |last| (now - last).as_secs_f32());
+    self.last_update = Some(now);
+
+    let mut uploads = Vec::new();
+
+    for entry in self.entries.iter_mut().flatten() {
+      // hidden panels are left alone, sources keep their damage until they are asked for a frame again
+      if !compositor.panel(entry.panel).visible { continue; }
+
+      let extent = compositor.panel(entry.panel).extent();
+      let size = (extent.width, extent.height);
+
+      if entry.size != size {
+        entry.size = size;
+        entry.pending = Damage::Full;
+        entry.source.resize(size.0, size.1);
+      }
+
+      let pointer = hit.filter(|hit| hit.panel == entry.panel).map(|hit| hit.position);
+      Surfaces::forward(entry.source.as_mut(), &mut entry.forwarded, input, pointer, focused == Some(entry.panel));
+
+      let Some(frame) = entry.source.frame(delta) else { continue; };
+      let damage = Surfaces::combine(mem::take(&mut entry.pending), frame.damage.clone());
+
+      if (frame.width, frame.height) != size || !Surfaces::complete(&frame) {
+        entry.pending = damage;
+        continue;
+      }
+
+      let rects = Surfaces::rects(&damage, size);
+      if rects.is_empty() { continue; }
+
+      let (pixels, regions) = Surfaces::pack(&frame, &rects);
+      uploads.push(SurfaceUpload { panel: entry.panel, pixels, regions });
+    }
+
+    uploads
+  }
+
+  // turned into surface events the way guis read input
+  fn forward(source: &mut dyn ExternalSurface, forwarded: &mut Forwarded, input: &Input, pointer: Option<Vec2>, focused: bool) {
+    let frame = GuiInput::from_input(input, pointer, focused);
+    let mut events = Vec::new();
+
+    if focused != forwarded.focused {
+      forwarded.focused = focused;
+      events.push(SurfaceEvent::Focused(focused));
+    }
+
+    match (pointer, forwarded.pointer) {
+      (Some(position), last) if last != Some(position) => events.push(SurfaceEvent::PointerMoved(position)),
+      (None, Some(_))                                  => events.push(SurfaceEvent::PointerLeft),
+      _                                                => {}
+    }
+
+    // a release lands where the pointer last was over the panel, so a drag off of it still ends
+    let last = pointer.or(forwarded.pointer);
+
+    if let (true, Some(position)) = (frame.pressed, pointer) {
+      events.push(SurfaceEvent::Pressed(position));
+    }
+
+    if let (true, Some(position)) = (frame.released, last) {
+      events.push(SurfaceEvent::Released(position));
+    }
+
+    if frame.scroll != Vec2::ZERO {
+      events.push(SurfaceEvent::Scrolled(frame.scroll));
+    }
+
+    events.extend(frame.keys.into_iter().map(SurfaceEvent::KeyPressed));
+
+    if !frame.text.is_empty() {
+      events.push(SurfaceEvent::Typed(frame.text));
+    }
+
+    forwarded.pointer = pointer;
+
+    for event in &events {
+      source.input(event);
+    }
+  }
+
+  fn combine(first: Damage, second: Damage) -> Damage {
+    match (first, second) {
+      (Damage::Full, _) | (_, Damage::Full)         => Damage::Full,
+      (Damage::None, damage) | (damage, Damage::None) => damage,
+      (Damage::Rects(mut first), Damage::Rects(second)) => {
+        first.extend(second);
+        Damage::Rects(first)
+      }
+    }
+  }
+
+  // clamped to the surface and merged where overlapping, or where keeping them apart would barely save anything
+  fn rects(damage: &Damage, (width, height): (u32, u32)) -> Vec<DirtyRect> {
+    let mut rects: Vec<DirtyRect> = match damage {
+      Damage::None        => return Vec::new(),
+      Damage::Full        => return vec![DirtyRect::full(width, height)],
+      Damage::Rects(rects) => rects.iter().map(|rect| rect.clamp(width, height)).filter(|rect| !rect.is_empty()).collect()
+    };
+
+    // overlapping rectangles would upload the same pixels twice
+    let mut merged = true;
+    while merged {
+      merged = false;
+
+      'outer: for index in 0..rects.len() {
+        for other in index + 1..rects.len() {
+          if rects[index].overlaps(rects[other]) {
+            let removed = rects.remove(other);
+            rects[index] = rects[index].union(removed);
+            merged = true;
+            break 'outer;
+          }
+        }
+      }
+    }
+
+    let bounds = rects.iter().fold(DirtyRect::default(), |bounds, rect| bounds.union(*rect));
+    let area: u64 = rects.iter().map(DirtyRect::area).sum();
+
+    match rects.len() > Surfaces::MAX_RECTS || area as f64 >= bounds.area() as f64 * Surfaces::MERGE_COVERAGE {
+      true  => vec![bounds].into_iter().filter(|bounds| !bounds.is_empty()).collect(),
+      false => rects
+    }
+  }
+
+  // holds every row it claims to
+  fn complete(frame: &SurfaceFrame) -> bool {
+    let row = frame.width as usize * 4;
+    let needed = (frame.height as usize).saturating_sub(1) * frame.stride as usize + row;
+
+    frame.stride as usize >= row && frame.pixels.len() >= needed
+  }
+
+  // the rects' rows packed one after another, as rgba
+  fn pack(frame: &SurfaceFrame, rects: &[DirtyRect]) -> (Vec<u8>, Vec<SurfaceRegion>) {
+    let mut pixels = Vec::with_capacity(rects.iter().map(|rect| rect.area() as usize * 4).sum());
+    let mut regions = Vec::with_capacity(rects.len());
+
+    for rect in rects {
+      regions.push(SurfaceRegion { rect: *rect, offset: pixels.len() });
+
+      for y in rect.y..rect.y + rect.height {
+        let start = y as usize * frame.stride as usize + rect.x as usize * 4;
+        let row = &frame.pixels[start..start + rect.width as usize * 4];
+
+        match frame.format {
+          PixelFormat::Rgba8 => pixels.extend_from_slice(row),
+          PixelFormat::Bgra8 => pixels.extend(row.chunks_exact(4).flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]]))
+        }
+      }
+    }
+
+    (pixels, regions)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::gvp_engine::camera::Camera;
+
+  use sdl2::mouse::MouseButton;
+
+  const SIZE: (u32, u32) = (200, 120);
+
+  // sized and past its first frame, which is always whole
+  fn pattern() -> TestPattern {
+    let mut pattern = TestPattern::new();
+    pattern.resize(SIZE.0, SIZE.1);
+    pattern.frame(0.0);
+
+    pattern
+  }
+
+  // as rgba
+  fn pixel(frame: &SurfaceFrame, x: u32, y: u32) -> [u8; 4] {
+    let index = (y * frame.stride + x * 4) as usize;
+    let [b, g, r, a] = frame.pixels[index..index + 4] else { unreachable!() };
+
+    [r, g, b, a]
+  }
+
+  #[test]
+  fn joins_rects_reaching_past_the_largest_coordinate() {
+    let far = DirtyRect::new(u32::MAX - 10, 5, 100, 10);
+    let near = DirtyRect::new(0, 0, 20, 20);
+
+    assert_eq!(far.union(near), DirtyRect::new(0, 0, u32::MAX, 20));
+    assert!(far.overlaps(DirtyRect::new(u32::MAX - 1, 0, 1, 6)));
+    assert!(!far.overlaps(near));
+  }
+
+  #[test]
+  fn packs_only_the_damage() {
+    let mut pattern = pattern();
+    let frame = pattern.frame(0.1).unwrap();
+
+    let Damage::Rects(damage) = &frame.damage else { panic!("expected rects, got {:?}", frame.damage) };
+    let rects = Surfaces::rects(&frame.damage, SIZE);
+    let (pixels, regions) = Surfaces::pack(&frame, &rects);
+
+    let inside = |rect: &DirtyRect, outer: &DirtyRect| rect.union(*outer) == *outer;
+    assert!(damage.iter().filter(|rect| !rect.is_empty()).all(|rect| rects.iter().any(|packed| inside(rect, packed))));
+
+    let area: u64 = rects.iter().map(DirtyRect::area).sum();
+    assert!(area < DirtyRect::full(SIZE.0, SIZE.1).area());
+    assert_eq!(pixels.len() as u64, area * 4);
+
+    for region in &regions {
+      let rect = region.rect;
+
+      for (row, y) in (rect.y..rect.y + rect.height).enumerate() {
+        for (column, x) in (rect.x..rect.x + rect.width).enumerate() {
+          let index = region.offset + (row * rect.width as usize + column) * 4;
+          assert_eq!(pixels[index..index + 4], pixel(&frame, x, y));
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn forwards_input_to_the_source() {
+    let mut pattern = pattern();
+    let mut forwarded = Forwarded::default();
+    let mut input = Input::new();
+    let at = Vec2::new(100.0, 60.0);
+
+    let before = pixel(&pattern.frame(0.0).unwrap(), 100, 60);
+
+    input.desktop_mut().press_button(MouseButton::Left);
+    input.resolve(&Camera::default());
+    Surfaces::forward(&mut pattern, &mut forwarded, &input, Some(at), true);
+
+    assert_eq!((forwarded.pointer, forwarded.focused), (Some(at), true));
+
+    // the press painted a dot in the next color where it landed, and focus drew the border
+    let frame = pattern.frame(0.0).unwrap();
+    assert_ne!(pixel(&frame, 100, 60), before);
+    assert_eq!(pixel(&frame, 100, 60), [70, 170, 90, 255]);
+    assert_eq!(pixel(&frame, 100, SIZE.1 - 1), [250, 250, 250, 255]);
+  }
+}
@@ -0,0 +1,42 @@
+use crate::gvp_engine::{
+  app::{AppContext, GVPApp},
+  compositor::Panel,
+  transform::Transform
+};
+
+use super::ExternalSurface;
+
+use glam::{Quat, Vec2, Vec3};
+
+// an app that only shows an external surface in its panel. the surface goes away with the panel when the app closes
+pub struct SurfaceApp {
+  name: String,
+  panel: Panel,
+  source: Option<Box<dyn ExternalSurface>>
+}
+
+impl SurfaceApp {
+  // off to the right of where apps usually open, turned towards the middle
+  pub fn new(name: &str, source: Box<dyn ExternalSurface>) -> Self {
+    let transform = Transform::new(Vec3::new(1.4, 1.5, -1.2), Quat::from_rotation_y(-0.6), Vec3::ONE);
+
+    SurfaceApp { name: name.to_string(), panel: Panel::new(transform, Vec2::new(0.8, 0.6)), source: Some(source) }
+  }
+}
+
+impl GVPApp for SurfaceApp {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn panel(&self) -> Panel {
+    self.panel
+  }
+
+  fn on_open(&mut self, context: &mut AppContext) {
+    if let Some(source) = self.source.take() {
+      let panel = context.panel();
+      context.attach_surface(panel, source);
+    }
+  }
+}
@@ -0,0 +1,214 @@
+use super::{Damage, DirtyRect, ExternalSurface, PixelFormat, SurfaceEvent, SurfaceFrame};
+
+use glam::Vec2;
+use sdl2::keyboard::Keycode;
+
+// a stand-in for a web view: an animated pattern that only redraws what moved, painted on with the pointer. it hands
+// out bgra rows with padding after them the way ultralight's bitmaps come, so the swizzle and stride paths get used
+#[derive(Default)]
+pub struct TestPattern {
+  width: u32,
+  height: u32,
+  stride: u32,
+  // the background with whatever was painted on it, what the moving parts are drawn over
+  base: Vec<u8>,
+  pixels: Vec<u8>,
+  time: f32,
+  // top left of the bouncing square and its velocity in pixels per second
+  square: Vec2,
+  velocity: Vec2,
+  color: usize,
+  painting: bool,
+  pointer: Option<Vec2>,
+  focused: bool,
+  damage: Vec<DirtyRect>,
+  full: bool
+}
+
+impl TestPattern {
+  const COLORS: [[u8; 3]; 4] = [[230, 80, 60], [70, 170, 90], [60, 120, 230], [240, 190, 50]];
+  const BAR_HEIGHT: u32 = 12;
+  const BRUSH: f32 = 10.0;
+  const BORDER: u32 = 6;
+  // rows are padded out to this many bytes
+  const ROW_ALIGNMENT: u32 = 256;
+
+  pub fn new() -> Self {
+    TestPattern { velocity: Vec2::new(240.0, 170.0), ..TestPattern::default() }
+  }
+
+  fn square_size(&self) -> u32 {
+    (self.height / 8).max(8)
+  }
+
+  fn square_rect(&self) -> DirtyRect {
+    let size = self.square_size();
+    DirtyRect::new(self.square.x as u32, self.square.y as u32, size, size).clamp(self.width, self.height)
+  }
+
+  // the bar at the top fills up once a second
+  fn bar_rect(&self) -> DirtyRect {
+    DirtyRect::full(self.width, TestPattern::BAR_HEIGHT.min(self.height))
+  }
+
+  fn border_rects(&self) -> [DirtyRect; 4] {
+    let (width, height, border) = (self.width, self.height, TestPattern::BORDER.min(self.width / 2).min(self.height / 2));
+
+    [
+      DirtyRect::new(0, 0, width, border),
+      DirtyRect::new(0, height - border, width, border),
+      DirtyRect::new(0, 0, border, height),
+      DirtyRect::new(width - border, 0, border, height)
+    ]
+  }
+
+  // diagonal stripes over a gradient, different enough everywhere that a misplaced upload shows
+  fn background(x: u32, y: u32, width: u32, height: u32) -> [u8; 4] {
+    let stripe = ((x + y) / 32).is_multiple_of(2);
+    let u = x as f32 / width.max(1) as f32;
+    let v = y as f32 / height.max(1) as f32;
+    let shade = if stripe { 1.0 } else { 0.85 };
+
+    [(40.0 + 60.0 * u * shade) as u8, (45.0 + 50.0 * v * shade) as u8, (70.0 + 80.0 * shade) as u8, 255]
+  }
+
+  fn set(buffer: &mut [u8], stride: u32, x: u32, y: u32, [r, g, b, a]: [u8; 4]) {
+    let index = (y * stride + x * 4) as usize;
+    buffer[index..index + 4].copy_from_slice(&[b, g, r, a]);
+  }
+
+  fn paint(&mut self, at: Vec2) {
+    let brush = TestPattern::BRUSH;
+    let [r, g, b] = TestPattern::COLORS[(self.color + 1) % TestPattern::COLORS.len()];
+    let min = (at - brush).max(Vec2::ZERO);
+    let max = (at + brush).min(Vec2::new(self.width as f32, self.height as f32));
+
+    for y in min.y as u32..max.y as u32 {
+      for x in min.x as u32..max.x as u32 {
+        if Vec2::new(x as f32 + 0.5, y as f32 + 0.5).distance(at) <= brush {
+          TestPattern::set(&mut self.base, self.stride, x, y, [r, g, b, 255]);
+        }
+      }
+    }
+
+    self.damage.push(DirtyRect::new(min.x as u32, min.y as u32, (max.x - min.x) as u32, (max.y - min.y) as u32));
+  }
+
+  // the base under the rect with the moving parts over it
+  fn compose(&mut self, rect: DirtyRect) {
+    let square = self.square_rect();
+    let bar = self.bar_rect();
+    let filled = (self.time.fract() * self.width as f32) as u32;
+    let [r, g, b] = TestPattern::COLORS[self.color];
+    let borders = self.border_rects();
+
+    for y in rect.y..rect.y + rect.height {
+      let row = (y * self.stride) as usize;
+      let range = row + rect.x as usize * 4..row + (rect.x + rect.width) as usize * 4;
+      self.pixels[range.clone()].copy_from_slice(&self.base[range]);
+
+      for x in rect.x..rect.x + rect.width {
+        let inside = |area: DirtyRect| x >= area.x && x < area.x + area.width && y >= area.y && y < area.y + area.height;
+
+        let color = match () {
+          _ if self.focused && borders.iter().any(|border| inside(*border)) => Some([250, 250, 250, 255]),
+          _ if inside(bar) && x < filled                                    => Some([200, 200, 210, 255]),
+          _ if inside(bar)                                                  => Some([20, 20, 25, 255]),
+          _ if inside(square)                                               => Some([r, g, b, 255]),
+          _                                                                 => None
+        };
+
+        if let Some(color) = color {
+          TestPattern::set(&mut self.pixels, self.stride, x, y, color);
+        }
+      }
+    }
+  }
+}
+
+impl ExternalSurface for TestPattern {
+  fn resize(&mut self, width: u32, height: u32) {
+    self.width = width;
+    self.height = height;
+    self.stride = (width * 4).div_ceil(TestPattern::ROW_ALIGNMENT) * TestPattern::ROW_ALIGNMENT;
+    self.base = vec![0; (self.stride * height) as usize];
+    self.pixels = vec![0; (self.stride * height) as usize];
+
+    for y in 0..height {
+      for x in 0..width {
+        TestPattern::set(&mut self.base, self.stride, x, y, TestPattern::background(x, y, width, height));
+      }
+    }
+
+    let limit = Vec2::new(width.saturating_sub(self.square_size()) as f32, height.saturating_sub(self.square_size()) as f32);
+    self.square = self.square.clamp(Vec2::ZERO, limit);
+    self.full = true;
+  }
+
+  fn frame(&mut self, delta: f32) -> Option<SurfaceFrame<'_>> {
+    if self.width == 0 || self.height == 0 { return None; }
+
+    self.time += delta;
+
+    // the square bounces off the edges, its old and new spots both need drawing
+    let old = self.square_rect();
+    let limit = Vec2::new(self.width.saturating_sub(self.square_size()) as f32, self.height.saturating_sub(self.square_size()) as f32);
+    self.square += self.velocity * delta;
+
+    for axis in 0..2 {
+      if self.square[axis] < 0.0 || self.square[axis] > limit[axis] {
+        self.velocity[axis] = -self.velocity[axis];
+        self.square[axis] = self.square[axis].clamp(0.0, limit[axis]);
+      }
+    }
+
+    self.damage.extend([old, self.square_rect(), self.bar_rect()]);
+
+    let damage = match std::mem::take(&mut self.full) {
+      true  => vec![DirtyRect::full(self.width, self.height)],
+      false => std::mem::take(&mut self.damage)
+    };
+    self.damage.clear();
+
+    for rect in &damage {
+      self.compose(*rect);
+    }
+
+    Some(SurfaceFrame {
+      pixels: &self.pixels,
+      width: self.width,
+      height: self.height,
+      stride: self.stride,
+      format: PixelFormat::Bgra8,
+      damage: Damage::Rects(damage)
+    })
+  }
+
+  fn input(&mut self, event: &SurfaceEvent) {
+    match event {
+      SurfaceEvent::Pressed(position)          => {
+        self.painting = true;
+        self.paint(*position);
+      }
+      SurfaceEvent::Released(_)                => self.painting = false,
+      SurfaceEvent::PointerMoved(position)     => {
+        // strokes are filled in between frames so fast moves stay connected
+        if let (true, Some(last)) = (self.painting, self.pointer) {
+          let steps = (last.distance(*position) / (TestPattern::BRUSH * 0.5)).ceil().max(1.0) as u32;
+          for step in 1..=steps {
+            self.paint(last.lerp(*position, step as f32 / steps as f32));
+          }
+        }
+
+        self.pointer = Some(*position);
+      }
+      SurfaceEvent::PointerLeft                => self.pointer = None,
+      SurfaceEvent::KeyPressed(Keycode::Space) => self.color = (self.color + 1) % TestPattern::COLORS.len(),
+      SurfaceEvent::Focused(focused)           => {
+        self.focused = *focused;
+        self.damage.extend(self.border_rects());
+      }
+      _                                        => {}
+    }
+  }
+}
@@ -0,0 +1,115 @@
+mod font;
+mod shaping;
+mod layout;
+mod sdf;
+mod atlas;
+
+pub use font::*;
+pub use shaping::*;
+pub use layout::*;
+pub use sdf::*;
+pub use atlas::*;
+
+use crate::gvp_engine::gui::{Canvas, Color, FontStyle, TextRun};
+
+use glam::Vec2;
+
+use std::{ops::Range, path::Path};
+
+// one glyph drawn by the canvas renderer. position and size are in pixels of the canvas, uvs in 0..1 of the atlas
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GlyphQuad {
+  pub position: [f32; 2],
+  pub size: [f32; 2],
+  pub uv_min: [f32; 2],
+  pub uv_max: [f32; 2],
+  pub color: [f32; 4],
+  // how far the outline is pushed out in distance field units, the slant, and the baseline y the slant leans from
+  pub shape: [f32; 4]
+}
+
+// glyphs of every text run of a canvas, run i has the glyphs in runs[i]
+#[derive(Clone, Debug, Default)]
+pub struct CanvasGlyphs {
+  pub quads: Vec<GlyphQuad>,
+  pub runs: Vec<Range<u32>>
+}
+
+// the loaded fonts and the atlas their glyphs are drawn from
+pub struct Text {
+  fonts: Fonts,
+  atlas: GlyphAtlas
+}
+
+impl Default for Text {
+  fn default() -> Self {
+    Text::new()
+  }
+}
+
+impl Text {
+  pub fn new() -> Self {
+    Text { fonts: Fonts::default(), atlas: GlyphAtlas::new() }
+  }
+
+  // the first font loaded is the primary one, later ones are fallbacks for characters it lacks
+  pub fn load_font(&mut self, path: &Path) -> FontId {
+    self.fonts.add(Font::load(path))
+  }
+
+  pub fn fonts(&self) -> &Fonts {
+    &self.fonts
+  }
+
+  pub fn atlas(&self) -> &GlyphAtlas {
+    &self.atlas
+  }
+
+  pub fn atlas_mut(&mut self) -> &mut GlyphAtlas {
+    &mut self.atlas
+  }
+
+  pub fn layout(&self, text: &str, style: &TextStyle) -> TextLayout {
+    TextLayout::new(&self.fonts, text, style)
+  }
+
+  // how much bold thickens outlines in distance field units and how far italic slants per pixel of height
+  pub const BOLD_WEIGHT: f32 = 0.08;
+  pub const ITALIC_SLANT: f32 = 0.2;
+
+  // the glyphs of a laid out text with its top left at origin
+  pub fn glyphs(&mut self, layout: &TextLayout, origin: Vec2, size: f32, color: Color, style: FontStyle, quads: &mut Vec<GlyphQuad>) {
+    let scale = size / GlyphAtlas::GLYPH_SIZE;
+    let color = color.linear();
+    let weight = if style.bold { Text::BOLD_WEIGHT } else { 0.0 };
+    let slant = if style.italic { Text::ITALIC_SLANT } else { 0.0 };
+
+    for glyph in &layout.glyphs {
+      let Some(entry) = self.atlas.glyph(&self.fonts, glyph.font, glyph.glyph) else { continue; };
+
+      quads.push(GlyphQuad {
+        position: (origin + glyph.position + entry.bearing * scale).to_array(),
+        size: (entry.size * scale).to_array(),
+        uv_min: (entry.position / GlyphAtlas::SIZE as f32).to_array(),
+        uv_max: ((entry.position + entry.size) / GlyphAtlas::SIZE as f32).to_array(),
+        color,
+        shape: [weight, slant, origin.y + glyph.position.y, 0.0]
+      });
+    }
+  }
+
+  pub fn canvas_glyphs(&mut self, canvas: &Canvas) -> CanvasGlyphs {
+    let mut glyphs = CanvasGlyphs::default();
+
+    for TextRun { position, text, size, color, style, .. } in &canvas.texts {
+      let start = glyphs.quads.len() as u32;
+      let layout = self.layout(text, &TextStyle { size: *size, ..TextStyle::default() });
+
+      self.glyphs(&layout, *position, *size, *color, *style, &mut glyphs.quads);
+      glyphs.runs.push(start..glyphs.quads.len() as u32);
+    }
+
+    glyphs
+  }
+}
@@ -0,0 +1,121 @@
+use super::{distance_field, FontId, Fonts};
+
+use glam::Vec2;
+
+use std::{collections::HashMap, ops::Range};
+
+// where a glyph is in the atlas, in atlas pixels at GlyphAtlas::GLYPH_SIZE
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasGlyph {
+  pub position: Vec2,
+  pub size: Vec2,
+  // from the pen on the baseline to the top left of the glyph's rectangle
+  pub bearing: Vec2
+}
+
+// a row of glyphs as tall as the tallest one in it
+struct Shelf {
+  y: u32,
+  height: u32,
+  x: u32
+}
+
+// distance fields of every glyph drawn so far, packed into shelves of one single channel texture. the fields are made
+// at one size and scale to any font size, staying sharp on panels seen up close. when it fills up it starts over
+pub struct GlyphAtlas {
+  pixels: Vec<u8>,
+  shelves: Vec<Shelf>,
+  // none for glyphs that draw nothing
+  glyphs: HashMap<(FontId, u16), Option<AtlasGlyph>>,
+  // rows written since the renderer last copied them
+  dirty: Option<Range<u32>>
+}
+
+impl GlyphAtlas {
+  pub const SIZE: u32 = 2048;
+  // font size the distance fields are made at and how many pixels they reach past the outline
+  pub const GLYPH_SIZE: f32 = 48.0;
+  pub const SPREAD: f32 = 6.0;
+  const PADDING: u32 = 1;
+
+  pub fn new() -> Self {
+    GlyphAtlas {
+      pixels: vec![0; (GlyphAtlas::SIZE * GlyphAtlas::SIZE) as usize],
+      shelves: Vec::new(),
+      glyphs: HashMap::new(),
+      dirty: None
+    }
+  }
+
+  pub fn pixels(&self) -> &[u8] {
+    &self.pixels
+  }
+
+  // the rows to upload, forgetting them
+  pub fn take_dirty(&mut self) -> Option<Range<u32>> {
+    self.dirty.take()
+  }
+
+  pub fn glyph(&mut self, fonts: &Fonts, font: FontId, glyph: u16) -> Option<AtlasGlyph> {
+    if let Some(entry) = self.glyphs.get(&(font, glyph)) {
+      return *entry;
+    }
+
+    let entry = distance_field(fonts.get(font), glyph, GlyphAtlas::GLYPH_SIZE, GlyphAtlas::SPREAD).and_then(|bitmap| {
+      let position = match self.allocate(bitmap.width, bitmap.height) {
+        Some(position) => position,
+        None           => {
+          self.clear();
+          self.allocate(bitmap.width, bitmap.height)?
+        }
+      };
+
+      for row in 0..bitmap.height {
+        let source = (row * bitmap.width) as usize;
+        let destination = ((position.1 + row) * GlyphAtlas::SIZE + position.0) as usize;
+        self.pixels[destination..destination + bitmap.width as usize].copy_from_slice(&bitmap.pixels[source..source + bitmap.width as usize]);
+      }
+
+      let rows = position.1..position.1 + bitmap.height;
+      self.dirty = Some(match self.dirty.take() {
+        Some(dirty) => dirty.start.min(rows.start)..dirty.end.max(rows.end),
+        None        => rows
+      });
+
+      Some(AtlasGlyph {
+        position: Vec2::new(position.0 as f32, position.1 as f32),
+        size: Vec2::new(bitmap.width as f32, bitmap.height as f32),
+        bearing: bitmap.bearing
+      })
+    });
+
+    self.glyphs.insert((font, glyph), entry);
+    entry
+  }
+
+  // forgets every glyph, they are made again as they are drawn
+  pub fn clear(&mut self) {
+    self.pixels.fill(0);
+    self.shelves.clear();
+    self.glyphs.clear();
+    self.dirty = Some(0..GlyphAtlas::SIZE);
+  }
+
+  // the first shelf with room that is not much taller than the glyph, or a new one under the last
+  fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+    let (width, height) = (width + GlyphAtlas::PADDING, height + GlyphAtlas::PADDING);
+    if width > GlyphAtlas::SIZE { return None; }
+
+    let fits = |shelf: &&mut Shelf| shelf.height >= height && shelf.height <= height + height / 2 && shelf.x + width <= GlyphAtlas::SIZE;
+    if let Some(shelf) = self.shelves.iter_mut().find(fits) {
+      shelf.x += width;
+      return Some((shelf.x - width, shelf.y));
+    }
+
+    let y = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+    if y + height > GlyphAtlas::SIZE { return None; }
+
+    self.shelves.push(Shelf { y, height, x: width });
+    Some((0, y))
+  }
+}
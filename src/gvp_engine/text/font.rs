@@ -0,0 +1,131 @@
+use crate::gvp_engine::gui::{ApproximateText, TextMeasure};
+
+use super::{TextLayout, TextStyle};
+
+use glam::Vec2;
+use rustybuzz::ttf_parser;
+
+use std::{fs, path::Path, sync::Arc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontId(pub usize);
+
+// a ttf or otf file. faces borrow the data so they are parsed again whenever they are needed, which only reads the
+// table directory
+pub struct Font {
+  data: Vec<u8>,
+  index: u32,
+  pub units_per_em: f32,
+  // above and below the baseline in font units, the descender is negative
+  pub ascender: f32,
+  pub descender: f32,
+  pub line_gap: f32
+}
+
+impl Font {
+  pub fn load(path: &Path) -> Self {
+    match fs::read(path) {
+      Ok(data)   => Font::from_bytes(data, 0),
+      Err(error) => panic!("failed to read font {} with error: {error}", path.display())
+    }
+  }
+
+  // index picks the face out of a font collection, 0 for plain font files
+  pub fn from_bytes(data: Vec<u8>, index: u32) -> Self {
+    let (units_per_em, ascender, descender, line_gap) = match ttf_parser::Face::parse(&data, index) {
+      Ok(face)   => (face.units_per_em() as f32, face.ascender() as f32, face.descender() as f32, face.line_gap() as f32),
+      Err(error) => panic!("failed to parse font with error: {error}")
+    };
+
+    Font { data, index, units_per_em, ascender, descender, line_gap }
+  }
+
+  pub fn face(&self) -> ttf_parser::Face<'_> {
+    match ttf_parser::Face::parse(&self.data, self.index) {
+      Ok(face)   => face,
+      Err(error) => panic!("failed to parse font with error: {error}")
+    }
+  }
+
+  pub fn shaper(&self) -> rustybuzz::Face<'_> {
+    rustybuzz::Face::from_face(self.face())
+  }
+
+  // font units to pixels at a font size
+  pub fn scale(&self, size: f32) -> f32 {
+    size / self.units_per_em
+  }
+}
+
+// vertical metrics of a line in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineMetrics {
+  pub ascent: f32,
+  pub descent: f32,
+  pub height: f32
+}
+
+// the fonts text is drawn with. the first one is the primary font and the rest are fallbacks, tried in order for
+// characters the ones before them do not have. cloning shares the loaded fonts
+#[derive(Clone, Default)]
+pub struct Fonts {
+  fonts: Vec<Arc<Font>>
+}
+
+impl Fonts {
+  pub fn add(&mut self, font: Font) -> FontId {
+    self.fonts.push(Arc::new(font));
+    FontId(self.fonts.len() - 1)
+  }
+
+  pub fn get(&self, id: FontId) -> &Font {
+    &self.fonts[id.0]
+  }
+
+  pub fn len(&self) -> usize {
+    self.fonts.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.fonts.is_empty()
+  }
+
+  // lines are spaced by the primary font, or like ApproximateText when there are no fonts
+  pub fn line_metrics(&self, size: f32) -> LineMetrics {
+    match self.fonts.first() {
+      Some(font) => {
+        let scale = font.scale(size);
+        LineMetrics {
+          ascent: font.ascender * scale,
+          descent: -font.descender * scale,
+          height: (font.ascender - font.descender + font.line_gap) * scale
+        }
+      },
+      None => LineMetrics { ascent: size, descent: size * 0.25, height: size * 1.25 }
+    }
+  }
+}
+
+// single lines laid out the way the text renderer draws them
+impl TextMeasure for Fonts {
+  fn measure(&self, text: &str, size: f32) -> Vec2 {
+    if self.is_empty() { return ApproximateText.measure(text, size); }
+
+    TextLayout::new(self, text, &TextStyle { size, ..TextStyle::default() }).size
+  }
+
+  fn caret(&self, text: &str, size: f32, index: usize) -> f32 {
+    if self.is_empty() { return ApproximateText.caret(text, size, index); }
+
+    let byte = text.char_indices().nth(index).map_or(text.len(), |(byte, _)| byte);
+    TextLayout::new(self, text, &TextStyle { size, ..TextStyle::default() }).caret(byte).position.x
+  }
+
+  fn index_at(&self, text: &str, size: f32, x: f32) -> usize {
+    if self.is_empty() { return ApproximateText.index_at(text, size, x); }
+
+    let layout = TextLayout::new(self, text, &TextStyle { size, ..TextStyle::default() });
+    let byte = layout.index_at(Vec2::new(x, layout.lines[0].top + layout.lines[0].height * 0.5));
+    text[..byte].chars().count()
+  }
+}
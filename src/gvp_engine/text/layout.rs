@@ -0,0 +1,410 @@
+use crate::gvp_engine::gui::Rect;
+
+use super::{shape, FontId, Fonts, ShapedRun};
+
+use glam::Vec2;
+use unicode_bidi::BidiInfo;
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+  // font size in pixels
+  pub size: f32,
+  // multiplies the primary font's line height
+  pub line_spacing: f32,
+  // lines wrap at break opportunities past this, or inside a word when it is wider on its own
+  pub max_width: Option<f32>
+}
+
+impl Default for TextStyle {
+  fn default() -> Self {
+    TextStyle { size: 16.0, line_spacing: 1.0, max_width: None }
+  }
+}
+
+// a glyph placed on its line, position is the pen on the baseline
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionedGlyph {
+  pub font: FontId,
+  pub glyph: u16,
+  pub position: Vec2
+}
+
+// where a character ended up on its line. left and right are its edges, the caret before it sits on the right edge
+// for right to left text
+#[derive(Clone, Debug, PartialEq)]
+pub struct CharBox {
+  pub range: Range<usize>,
+  pub left: f32,
+  pub right: f32,
+  pub rtl: bool
+}
+
+impl CharBox {
+  fn leading(&self) -> f32 {
+    if self.rtl { self.right } else { self.left }
+  }
+
+  fn trailing(&self) -> f32 {
+    if self.rtl { self.left } else { self.right }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLine {
+  // bytes of the text on the line, including the line break ending it
+  pub range: Range<usize>,
+  // the line's entries in the layout's glyphs
+  pub glyphs: Range<usize>,
+  pub top: f32,
+  pub baseline: f32,
+  pub height: f32,
+  // without trailing whitespace
+  pub width: f32,
+  // in logical order
+  pub chars: Vec<CharBox>,
+  // the caret after the last character
+  pub end: f32
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Caret {
+  // top of the caret, it reaches down a line height
+  pub position: Vec2,
+  pub height: f32,
+  pub line: usize
+}
+
+// text shaped, broken into lines and placed with its top left at the origin. indices are byte indices into the text
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextLayout {
+  pub glyphs: Vec<PositionedGlyph>,
+  pub lines: Vec<TextLine>,
+  pub size: Vec2
+}
+
+impl TextLayout {
+  pub fn new(fonts: &Fonts, text: &str, style: &TextStyle) -> Self {
+    let metrics = fonts.line_metrics(style.size);
+    let line_height = metrics.height * style.line_spacing;
+
+    let bidi = BidiInfo::new(text, None);
+    let runs = shape(fonts, text, &bidi.levels, style.size);
+
+    // advances summed up in logical order so any range of the text can be measured
+    let mut prefix = vec![0.0f32; text.len() + 1];
+    for glyph in runs.iter().flat_map(|run| &run.glyphs) {
+      prefix[glyph.cluster + 1] += glyph.advance;
+    }
+
+    for index in 0..text.len() {
+      prefix[index + 1] += prefix[index];
+    }
+
+    let trimmed = |range: Range<usize>| range.start + text[range].trim_end().len();
+    let width = |range: Range<usize>| prefix[trimmed(range.clone())] - prefix[range.start];
+
+    let ranges = TextLayout::break_lines(text, style.max_width, &width);
+    let mut layout = TextLayout::default();
+    let mut widest = 0.0f32;
+
+    for (index, range) in ranges.into_iter().enumerate() {
+      let top = index as f32 * line_height;
+      let baseline = top + (line_height - metrics.ascent - metrics.descent) * 0.5 + metrics.ascent;
+
+      let first = layout.glyphs.len();
+      let line = layout.place_line(text, &bidi, &runs, range.clone(), baseline);
+      widest = widest.max(width(range.clone()));
+
+      layout.lines.push(TextLine { glyphs: first..layout.glyphs.len(), top, height: line_height, width: width(range), ..line });
+    }
+
+    let box_width = style.max_width.unwrap_or(widest);
+    layout.size = Vec2::new(box_width.max(widest), layout.lines.len() as f32 * line_height);
+
+    layout
+  }
+
+  // the caret before the character at index, or after the last one
+  pub fn caret(&self, index: usize) -> Caret {
+    let line_index = self.line_of(index);
+    let line = &self.lines[line_index];
+
+    let x = match line.chars.iter().find(|char_box| char_box.range.contains(&index)) {
+      Some(char_box) => char_box.leading(),
+      None           => line.end
+    };
+
+    Caret { position: Vec2::new(x, line.top), height: line.height, line: line_index }
+  }
+
+  // the caret index closest to a point
+  pub fn index_at(&self, point: Vec2) -> usize {
+    let index = self.lines.iter().position(|line| point.y < line.top + line.height).unwrap_or(self.lines.len() - 1);
+    let line = &self.lines[index];
+
+    let mut best = (line.range.end, (line.end - point.x).abs());
+    for char_box in &line.chars {
+      for (index, x) in [(char_box.range.start, char_box.leading()), (char_box.range.end, char_box.trailing())] {
+        let distance = (x - point.x).abs();
+        if distance < best.1 {
+          best = (index, distance);
+        }
+      }
+    }
+
+    // the end of a line is the start of the next one, so every line but the last stops before its final character,
+    // which is the line break or the space it wrapped at
+    match index + 1 < self.lines.len() {
+      true  => best.0.min(line.chars.last().map_or(line.range.start, |char_box| char_box.range.start)),
+      false => best.0
+    }
+  }
+
+  // rectangles covering the selected characters, one or more per line
+  pub fn selection(&self, range: Range<usize>) -> Vec<Rect> {
+    let mut rects = Vec::new();
+
+    for line in &self.lines {
+      if line.range.end <= range.start || line.range.start >= range.end { continue; }
+
+      let mut spans: Vec<(f32, f32)> = line.chars.iter()
+        .filter(|char_box| char_box.range.start >= range.start && char_box.range.end <= range.end)
+        .map(|char_box| (char_box.left, char_box.right))
+        .collect();
+
+      spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+      let mut merged: Vec<(f32, f32)> = Vec::new();
+      for (left, right) in spans {
+        match merged.last_mut() {
+          Some(last) if left <= last.1 + 0.5 => last.1 = last.1.max(right),
+          _                                  => merged.push((left, right))
+        }
+      }
+
+      for (left, right) in merged {
+        rects.push(Rect::new(Vec2::new(left, line.top), Vec2::new(right - left, line.height)));
+      }
+    }
+
+    rects
+  }
+
+  // the line a caret index is on. the index at a line break belongs to the line after it
+  pub fn line_of(&self, index: usize) -> usize {
+    self.lines.iter().rposition(|line| line.range.start <= index).unwrap_or(0)
+  }
+
+  // greedy: every line takes as much as fits and ends at the last break opportunity before that, mandatory breaks
+  // always end it
+  fn break_lines(text: &str, max_width: Option<f32>, width: &dyn Fn(Range<usize>) -> f32) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut candidate: Option<usize> = None;
+
+    for (position, opportunity) in linebreaks(text) {
+      while let Some(max_width) = max_width.filter(|max_width| width(start..position) > *max_width) {
+        let end = match candidate {
+          Some(candidate) if candidate > start => candidate,
+          // a word wider than the line is cut between characters, keeping at least one on each line
+          _ => {
+            let ends = text[start..position].char_indices().skip(1).map(|(index, _)| start + index);
+            let first = ends.clone().next().unwrap_or(position);
+            ends.take_while(|end| width(start..*end) <= max_width).last().unwrap_or(first)
+          }
+        };
+
+        if end >= position { break; }
+
+        lines.push(start..end);
+        start = end;
+        candidate = None;
+      }
+
+      match opportunity {
+        BreakOpportunity::Mandatory => {
+          if position > start || lines.is_empty() {
+            lines.push(start..position);
+          }
+
+          start = position;
+          candidate = None;
+        },
+        BreakOpportunity::Allowed => candidate = Some(position)
+      }
+    }
+
+    // the caret can go on the empty line after a final line break
+    if lines.is_empty() || text.ends_with(['\n', '\r', '\u{2029}']) {
+      lines.push(text.len()..text.len());
+    }
+
+    lines
+  }
+
+  // puts the glyphs of a line in visual order from x 0, going through the bidi runs of the line
+  fn place_line(&mut self, text: &str, bidi: &BidiInfo, runs: &[ShapedRun], range: Range<usize>, baseline: f32) -> TextLine {
+    let mut line = TextLine { range: range.clone(), glyphs: 0..0, top: 0.0, baseline, height: 0.0, width: 0.0, chars: Vec::new(), end: 0.0 };
+    let paragraph = bidi.paragraphs.iter().find(|paragraph| paragraph.range.contains(&range.start));
+
+    let Some(paragraph) = paragraph.filter(|_| !range.is_empty()) else { return line; };
+    let (levels, visual_runs) = bidi.visual_runs(paragraph, range.clone());
+
+    let mut x = 0.0;
+    let mut clusters: Vec<(usize, f32, f32, bool)> = Vec::new();
+
+    for visual_run in visual_runs {
+      let rtl = levels[visual_run.start].is_rtl();
+      let overlapping: Vec<&ShapedRun> = runs.iter()
+        .filter(|run| run.range.start < visual_run.end && run.range.end > visual_run.start)
+        .collect();
+
+      let ordered: Box<dyn Iterator<Item = &&ShapedRun>> = match rtl {
+        true  => Box::new(overlapping.iter().rev()),
+        false => Box::new(overlapping.iter())
+      };
+
+      for run in ordered {
+        for glyph in run.glyphs.iter().filter(|glyph| visual_run.contains(&glyph.cluster)) {
+          if let Some(id) = glyph.glyph {
+            self.glyphs.push(PositionedGlyph { font: glyph.font, glyph: id, position: Vec2::new(x, baseline) + glyph.offset });
+          }
+
+          match clusters.last_mut() {
+            Some(last) if last.0 == glyph.cluster => last.2 += glyph.advance,
+            _                                     => clusters.push((glyph.cluster, x, x + glyph.advance, run.rtl))
+          }
+
+          x += glyph.advance;
+        }
+      }
+    }
+
+    // characters share the room of the cluster they were shaped into, like the letters of a ligature
+    let mut starts: Vec<usize> = clusters.iter().map(|cluster| cluster.0).collect();
+    starts.sort_unstable();
+
+    for (cluster, left, right, rtl) in clusters {
+      let next = starts.iter().find(|start| **start > cluster).copied().unwrap_or(range.end).min(range.end);
+      let characters: Vec<(usize, char)> = text[cluster..next].char_indices().collect();
+      let step = (right - left) / characters.len().max(1) as f32;
+
+      for (index, (offset, character)) in characters.iter().enumerate() {
+        let (left, right) = match rtl {
+          true  => (right - step * (index + 1) as f32, right - step * index as f32),
+          false => (left + step * index as f32, left + step * (index + 1) as f32)
+        };
+
+        let start = cluster + offset;
+        line.chars.push(CharBox { range: start..start + character.len_utf8(), left, right, rtl });
+      }
+    }
+
+    line.chars.sort_by_key(|char_box| char_box.range.start);
+
+    // after the last character in logical order, which is on the left of right to left paragraphs
+    line.end = match line.chars.iter().rfind(|char_box| !text[char_box.range.clone()].chars().all(char::is_control)) {
+      Some(char_box) => char_box.trailing(),
+      None           => if paragraph.level.is_rtl() { x } else { 0.0 }
+    };
+
+    line
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::gvp_engine::text::Font;
+
+  use std::path::Path;
+
+  // monospaced, so every character is one advance wide
+  fn fonts() -> (Fonts, f32) {
+    let mut fonts = Fonts::default();
+    fonts.add(Font::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/fonts/DejaVuSansMono.ttf")));
+
+    let advance = TextLayout::new(&fonts, "a", &TextStyle::default()).lines[0].chars[0].right;
+    (fonts, advance)
+  }
+
+  fn char_box<'a>(layout: &'a TextLayout, index: usize) -> &'a CharBox {
+    layout.lines.iter().flat_map(|line| &line.chars).find(|char_box| char_box.range.start == index).unwrap()
+  }
+
+  #[test]
+  fn breaks_lines_after_the_last_word_that_fits_and_inside_words_wider_than_a_line() {
+    let (fonts, advance) = fonts();
+    let ranges = |text, columns: f32| {
+      let style = TextStyle { max_width: Some(columns * advance), ..TextStyle::default() };
+      let layout = TextLayout::new(&fonts, text, &style);
+      layout.lines.iter().map(|line| line.range.clone()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(ranges("hello world again", 12.5), [0..12, 12..17]);
+    assert_eq!(ranges("abcdefgh", 3.5), [0..3, 3..6, 6..8]);
+    assert_eq!(ranges("ab\ncd\n", 10.0), [0..3, 3..6, 6..6]);
+  }
+
+  #[test]
+  fn reorders_right_to_left_runs_within_a_line() {
+    let (fonts, advance) = fonts();
+    let layout = TextLayout::new(&fonts, "abc אבג def", &TextStyle::default());
+
+    // the hebrew letters are two bytes each and fill columns 4 to 7 from the right
+    for (index, column) in [(0, 0.0), (4, 6.0), (6, 5.0), (8, 4.0), (11, 8.0)] {
+      let char_box = char_box(&layout, index);
+      assert!((char_box.left - column * advance).abs() < 0.01, "{index} {char_box:?}");
+      assert_eq!(char_box.rtl, (4..10).contains(&index));
+    }
+  }
+
+  #[test]
+  fn starts_right_to_left_paragraphs_on_the_right_of_every_line() {
+    let (fonts, advance) = fonts();
+    let text = "אבג דהו זחט";
+    let layout = TextLayout::new(&fonts, text, &TextStyle { max_width: Some(8.5 * advance), ..TextStyle::default() });
+
+    assert_eq!(layout.lines.iter().map(|line| line.range.clone()).collect::<Vec<_>>(), [0..14, 14..20]);
+    assert!(char_box(&layout, 0).left > char_box(&layout, 7).left);
+    assert!(char_box(&layout, 14).left > char_box(&layout, 18).left);
+    assert_eq!(layout.caret(0).line, 0);
+    assert_eq!(layout.caret(14).line, 1);
+  }
+
+  #[test]
+  fn places_carets_before_characters_and_after_the_last_one() {
+    let (fonts, advance) = fonts();
+    let layout = TextLayout::new(&fonts, "ab\ncd", &TextStyle::default());
+    let height = layout.lines[0].height;
+
+    for (index, expected) in [(0, Vec2::ZERO), (2, Vec2::new(2.0 * advance, 0.0)), (3, Vec2::new(0.0, height)),
+      (5, Vec2::new(2.0 * advance, height))] {
+      let caret = layout.caret(index);
+      assert!(caret.position.distance(expected) < 0.01, "{index} {caret:?}");
+      assert_eq!(layout.index_at(caret.position + Vec2::new(0.1, height * 0.5)), index);
+    }
+
+    // before a right to left character the caret is on its right edge
+    let layout = TextLayout::new(&fonts, "ab אב", &TextStyle::default());
+    assert!((layout.caret(3).position.x - 5.0 * advance).abs() < 0.01);
+  }
+
+  #[test]
+  fn covers_selections_with_a_rectangle_per_visual_span() {
+    let (fonts, advance) = fonts();
+    let spans = |layout: &TextLayout, range| layout.selection(range).iter()
+      .map(|rect| ((rect.position.x / advance).round(), (rect.size.x / advance).round(), rect.position.y))
+      .collect::<Vec<_>>();
+
+    let layout = TextLayout::new(&fonts, "ab\ncd", &TextStyle::default());
+    assert_eq!(spans(&layout, 1..4), [(1.0, 1.0, 0.0), (0.0, 1.0, layout.lines[1].top)]);
+
+    // c, the space and the first hebrew letter, which is drawn at the far end of its run
+    let layout = TextLayout::new(&fonts, "abc אבג", &TextStyle::default());
+    assert_eq!(spans(&layout, 2..6), [(2.0, 2.0, 0.0), (6.0, 1.0, 0.0)]);
+  }
+}
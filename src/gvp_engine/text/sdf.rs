@@ -0,0 +1,115 @@
+use super::Font;
+
+use glam::Vec2;
+use rustybuzz::ttf_parser::{GlyphId, OutlineBuilder};
+
+// a glyph as a signed distance field. 0.5 is the outline, values rise inside and fall outside reaching 0 and 1 at
+// spread pixels away from it
+pub struct GlyphBitmap {
+  pub width: u32,
+  pub height: u32,
+  pub pixels: Vec<u8>,
+  // from the pen on the baseline to the top left of the bitmap, y pointing down
+  pub bearing: Vec2
+}
+
+// outlines flattened into line segments, already scaled to pixels with y pointing down
+#[derive(Default)]
+struct Outline {
+  segments: Vec<(Vec2, Vec2)>,
+  start: Vec2,
+  current: Vec2,
+  scale: f32
+}
+
+impl Outline {
+  const CURVE_STEPS: usize = 8;
+
+  fn point(&self, x: f32, y: f32) -> Vec2 {
+    Vec2::new(x, -y) * self.scale
+  }
+
+  fn push(&mut self, to: Vec2) {
+    if to != self.current {
+      self.segments.push((self.current, to));
+    }
+
+    self.current = to;
+  }
+}
+
+impl OutlineBuilder for Outline {
+  fn move_to(&mut self, x: f32, y: f32) {
+    self.start = self.point(x, y);
+    self.current = self.start;
+  }
+
+  fn line_to(&mut self, x: f32, y: f32) {
+    self.push(self.point(x, y));
+  }
+
+  fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+    let (from, control, to) = (self.current, self.point(x1, y1), self.point(x, y));
+
+    for step in 1..=Outline::CURVE_STEPS {
+      let t = step as f32 / Outline::CURVE_STEPS as f32;
+      self.push(from.lerp(control, t).lerp(control.lerp(to, t), t));
+    }
+  }
+
+  fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+    let (from, first, second, to) = (self.current, self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+
+    for step in 1..=Outline::CURVE_STEPS * 2 {
+      let t = step as f32 / (Outline::CURVE_STEPS * 2) as f32;
+      let (a, b, c) = (from.lerp(first, t), first.lerp(second, t), second.lerp(to, t));
+      self.push(a.lerp(b, t).lerp(b.lerp(c, t), t));
+    }
+  }
+
+  fn close(&mut self) {
+    self.push(self.start);
+  }
+}
+
+// none for glyphs without an outline, like spaces
+pub fn distance_field(font: &Font, glyph: u16, size: f32, spread: f32) -> Option<GlyphBitmap> {
+  let mut outline = Outline { scale: font.scale(size), ..Outline::default() };
+  let bounds = font.face().outline_glyph(GlyphId(glyph), &mut outline)?;
+
+  if outline.segments.is_empty() { return None; }
+
+  let min = (outline.point(bounds.x_min as f32, bounds.y_max as f32) - spread).floor();
+  let max = (outline.point(bounds.x_max as f32, bounds.y_min as f32) + spread).ceil();
+  let (width, height) = ((max.x - min.x) as u32, (max.y - min.y) as u32);
+
+  let mut pixels = vec![0u8; (width * height) as usize];
+
+  for y in 0..height {
+    for x in 0..width {
+      let point = min + Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+
+      let mut distance = f32::MAX;
+      let mut winding = 0;
+
+      for (from, to) in &outline.segments {
+        let edge = *to - *from;
+        let t = ((point - *from).dot(edge) / edge.length_squared()).clamp(0.0, 1.0);
+        distance = distance.min(point.distance(*from + edge * t));
+
+        // nonzero winding of a ray going right from the point
+        if (from.y <= point.y) != (to.y <= point.y) {
+          let crossing = from.x + (point.y - from.y) / edge.y * edge.x;
+          if crossing > point.x {
+            winding += if edge.y > 0.0 { 1 } else { -1 };
+          }
+        }
+      }
+
+      let signed = if winding != 0 { distance } else { -distance };
+      pixels[(y * width + x) as usize] = ((0.5 + signed / (spread * 2.0)).clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+  }
+
+  Some(GlyphBitmap { width, height, pixels, bearing: min })
+}
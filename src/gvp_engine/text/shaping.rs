@@ -0,0 +1,97 @@
+use super::{FontId, Fonts};
+
+use glam::Vec2;
+use rustybuzz::{Direction, UnicodeBuffer};
+use unicode_bidi::Level;
+
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlyph {
+  pub font: FontId,
+  // none for control characters like line breaks, which take no room and draw nothing
+  pub glyph: Option<u16>,
+  // byte index of the first character the glyph was made from
+  pub cluster: usize,
+  // in pixels, the offset moves the glyph away from the pen with y pointing down
+  pub advance: f32,
+  pub offset: Vec2
+}
+
+// characters sharing a font and a direction, shaped together. glyphs are in visual order so right to left runs start
+// with the glyph of their last character
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapedRun {
+  pub range: Range<usize>,
+  pub rtl: bool,
+  pub glyphs: Vec<ShapedGlyph>
+}
+
+// splits text into runs by bidi level and by the first font with a glyph for each character, then shapes every run
+// with its font's kerning and ligatures. levels has one entry per byte like unicode_bidi's. whitespace and marks stay in
+// the font of the characters before them so a space does not break a run apart
+pub fn shape(fonts: &Fonts, text: &str, levels: &[Level], size: f32) -> Vec<ShapedRun> {
+  if fonts.is_empty() { return Vec::new(); }
+
+  let faces: Vec<_> = (0..fonts.len()).map(|index| fonts.get(FontId(index)).face()).collect();
+  let mut items: Vec<(Range<usize>, FontId, Level)> = Vec::new();
+
+  for (index, character) in text.char_indices() {
+    let end = index + character.len_utf8();
+    let level = levels[index];
+    let previous = items.last().filter(|(_, _, previous)| *previous == level).map(|(_, font, _)| *font);
+
+    let keep = character.is_whitespace() || character.is_control() || is_mark(character);
+    let font = match (keep, previous) {
+      (true, Some(font)) => font,
+      _                  => {
+        let found = faces.iter().position(|face| face.glyph_index(character).is_some());
+        FontId(found.unwrap_or(0))
+      }
+    };
+
+    match items.last_mut() {
+      Some((range, last_font, last_level)) if *last_font == font && *last_level == level => range.end = end,
+      _                                                                                  => items.push((index..end, font, level))
+    }
+  }
+
+  items.into_iter().map(|(range, font, level)| shape_run(fonts, text, range, font, level.is_rtl(), size)).collect()
+}
+
+fn shape_run(fonts: &Fonts, text: &str, range: Range<usize>, font: FontId, rtl: bool, size: f32) -> ShapedRun {
+  let face = fonts.get(font).shaper();
+  let scale = fonts.get(font).scale(size);
+
+  let mut buffer = UnicodeBuffer::new();
+  for (index, character) in text[range.clone()].char_indices() {
+    buffer.add(character, (range.start + index) as u32);
+  }
+
+  buffer.guess_segment_properties();
+  buffer.set_direction(if rtl { Direction::RightToLeft } else { Direction::LeftToRight });
+
+  let output = rustybuzz::shape(&face, &[], buffer);
+
+  let glyphs = output.glyph_infos().iter().zip(output.glyph_positions()).map(|(info, position)| {
+    let cluster = info.cluster as usize;
+    let control = text[cluster..].chars().next().is_some_and(char::is_control);
+
+    ShapedGlyph {
+      font,
+      glyph: (!control).then_some(info.glyph_id as u16),
+      cluster,
+      advance: if control { 0.0 } else { position.x_advance as f32 * scale },
+      offset: Vec2::new(position.x_offset as f32, -position.y_offset as f32) * scale
+    }
+  }).collect();
+
+  ShapedRun { range, rtl, glyphs }
+}
+
+// combining marks belong with the character before them
+fn is_mark(character: char) -> bool {
+  matches!(character as u32, 0x0300..=0x036F | 0x0483..=0x0489 | 0x0591..=0x05BD | 0x0610..=0x061A | 0x064B..=0x065F |
+    0x0670 | 0x06D6..=0x06ED | 0x0900..=0x0903 | 0x093A..=0x094F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x200C..=0x200D |
+    0x20D0..=0x20FF | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F)
+}
@@ -0,0 +1,512 @@
+mod blocks;
+
+use crate::gvp_engine::{
+  allocator::{Allocator, Image},
+  gpu::GPU
+};
+
+use ash::vk;
+use half::f16;
+use image::DynamicImage;
+use ktx2::SupercompressionScheme;
+
+use std::{fmt, fs, io::Read, path::Path};
+
+// why a texture was not loaded or could not be made into something the gpu samples
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextureError {
+  Read(String),
+  Decode(String),
+  // basis universal needs a transcoder this engine does not have
+  BasisUniversal,
+  Volume,
+  Supercompression(String),
+  // the gpu cannot sample the format and there is no cpu decoder for it
+  Unsupported(vk::Format),
+  Truncated { expected: usize, found: usize }
+}
+
+impl fmt::Display for TextureError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      TextureError::Read(error)                  => write!(formatter, "could not read file: {error}"),
+      TextureError::Decode(error)                => write!(formatter, "could not decode: {error}"),
+      TextureError::BasisUniversal               => write!(formatter, "basis universal textures are not supported"),
+      TextureError::Volume                       => write!(formatter, "3d textures are not supported"),
+      TextureError::Supercompression(error)      => write!(formatter, "could not decompress: {error}"),
+      TextureError::Unsupported(format)          => write!(formatter, "{format:?} is not supported by the gpu and has no cpu decoder"),
+      TextureError::Truncated { expected, found } => write!(formatter, "expected {expected} bytes but found {found}")
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerInfo {
+  pub mag_filter: vk::Filter,
+  pub min_filter: vk::Filter,
+  pub mipmap_mode: vk::SamplerMipmapMode,
+  // u, v, w
+  pub address_mode: [vk::SamplerAddressMode; 3],
+  // uses the highest anisotropy the gpu allows, if it supports anisotropic filtering at all
+  pub anisotropy: bool
+}
+
+impl Default for SamplerInfo {
+  fn default() -> Self {
+    SamplerInfo {
+      mag_filter: vk::Filter::LINEAR,
+      min_filter: vk::Filter::LINEAR,
+      mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+      address_mode: [vk::SamplerAddressMode::REPEAT; 3],
+      anisotropy: true
+    }
+  }
+}
+
+impl SamplerInfo {
+  pub const CLAMP: SamplerInfo = SamplerInfo {
+    mag_filter: vk::Filter::LINEAR,
+    min_filter: vk::Filter::LINEAR,
+    mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+    address_mode: [vk::SamplerAddressMode::CLAMP_TO_EDGE; 3],
+    anisotropy: false
+  };
+}
+
+// pixel data ready to be copied into a vulkan image. loading never touches vulkan so it works headlessly
+#[derive(Clone, Debug)]
+pub struct TextureData {
+  pub width: u32,
+  pub height: u32,
+  pub format: vk::Format,
+  // array layers, faces included. cubemaps have six per cube in +x, -x, +y, -y, +z, -z order
+  pub layers: u32,
+  pub cube: bool,
+  // each level holds every layer back to back. when only the base level is given the rest of the chain is generated
+  // on upload
+  pub levels: Vec<Vec<u8>>
+}
+
+pub struct Texture {
+  pub image: Image,
+  pub sampler: vk::Sampler
+}
+
+impl TextureData {
+  // png and jpeg load as rgba8, radiance hdr as rgba16f and ktx2 keeps whatever format it was stored in. srgb only
+  // applies to 8 bit images since ktx2 names its own color space
+  pub fn load(path: &Path, srgb: bool) -> Result<Self, TextureError> {
+    let is_ktx2 = path.extension().and_then(|extension| extension.to_str())
+      .is_some_and(|extension| extension.eq_ignore_ascii_case("ktx2"));
+
+    if is_ktx2 {
+      let bytes = fs::read(path).map_err(|error| TextureError::Read(error.to_string()))?;
+      return TextureData::ktx2(&bytes);
+    }
+
+    let image = image::open(path).map_err(|error| TextureError::Decode(error.to_string()))?;
+
+    Ok(match image {
+      DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+        let image = image.to_rgba32f();
+        TextureData::rgba32f(image.width(), image.height(), image.as_raw())
+      },
+      _ => {
+        let image = image.to_rgba8();
+        TextureData::rgba8(image.width(), image.height(), image.into_raw(), srgb)
+      }
+    })
+  }
+
+  pub fn rgba8(width: u32, height: u32, pixels: Vec<u8>, srgb: bool) -> Self {
+    let format = match srgb {
+      true  => vk::Format::R8G8B8A8_SRGB,
+      false => vk::Format::R8G8B8A8_UNORM
+    };
+
+    TextureData {
+      width,
+      height,
+      format,
+      layers: 1,
+      cube: false,
+      levels: vec![pixels]
+    }
+  }
+
+  // stored as half floats which every gpu can filter, unlike full 32 bit floats
+  pub fn rgba32f(width: u32, height: u32, pixels: &[f32]) -> Self {
+    let bytes = pixels.iter().flat_map(|&value| f16::from_f32(value).to_le_bytes()).collect();
+
+    TextureData {
+      width,
+      height,
+      format: vk::Format::R16G16B16A16_SFLOAT,
+      layers: 1,
+      cube: false,
+      levels: vec![bytes]
+    }
+  }
+
+  pub fn level_extent(&self, level: u32) -> vk::Extent2D {
+    vk::Extent2D {
+      width: (self.width >> level).max(1),
+      height: (self.height >> level).max(1)
+    }
+  }
+
+  // whether every level holds as many bytes as its extent and layers take up, for formats whose blocks are known
+  fn check_levels(&self) -> Result<(), TextureError> {
+    let Some((block_width, block_height, block_size)) = blocks::footprint(self.format) else { return Ok(()); };
+
+    for (level, pixels) in self.levels.iter().enumerate() {
+      let extent = self.level_extent(level as u32);
+      let blocks = extent.width.div_ceil(block_width) as usize * extent.height.div_ceil(block_height) as usize;
+      let expected = blocks * block_size * self.layers as usize;
+
+      if pixels.len() < expected {
+        return Err(TextureError::Truncated { expected, found: pixels.len() });
+      }
+    }
+
+    Ok(())
+  }
+
+  fn ktx2(bytes: &[u8]) -> Result<Self, TextureError> {
+    let reader = ktx2::Reader::new(bytes).map_err(|error| TextureError::Decode(error.to_string()))?;
+    let header = reader.header();
+
+    // basis universal stores its own block format which would need a transcoder to turn into something the gpu reads
+    let format = vk::Format::from_raw(header.format.ok_or(TextureError::BasisUniversal)?.value() as i32);
+
+    if header.pixel_depth > 1 {
+      return Err(TextureError::Volume);
+    }
+
+    let levels = reader.levels().map(|level| match header.supercompression_scheme {
+      None                                      => Ok(level.data.to_vec()),
+      Some(SupercompressionScheme::ZLIB)        => {
+        miniz_oxide::inflate::decompress_to_vec_zlib(level.data).map_err(|error| TextureError::Supercompression(error.to_string()))
+      },
+      Some(SupercompressionScheme::Zstandard)   => {
+        let mut data = Vec::<u8>::with_capacity(level.uncompressed_byte_length as usize);
+
+        ruzstd::decoding::StreamingDecoder::new(level.data)
+          .map_err(|error| error.to_string())
+          .and_then(|mut decoder| decoder.read_to_end(&mut data).map_err(|error| error.to_string()))
+          .map_err(TextureError::Supercompression)?;
+
+        Ok(data)
+      },
+      Some(scheme)                              => Err(TextureError::Supercompression(format!("{scheme:?} is not supported")))
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(TextureData {
+      width: header.pixel_width,
+      height: header.pixel_height.max(1),
+      format,
+      layers: header.layer_count.max(1) * header.face_count,
+      cube: header.face_count == 6,
+      levels
+    })
+  }
+
+  // decodes block compressed levels to rgba8 on the cpu for gpus that cannot sample the original format
+  fn transcode(&self) -> Result<Self, TextureError> {
+    let mut srgb = false;
+    let mut levels = Vec::<Vec<u8>>::with_capacity(self.levels.len());
+
+    for (level, data) in self.levels.iter().enumerate() {
+      let extent = self.level_extent(level as u32);
+      let mut pixels = Vec::<u8>::new();
+
+      for layer in data.chunks_exact((data.len() / self.layers as usize).max(1)) {
+        let (decoded, decoded_srgb) = blocks::decode(self.format, extent.width, extent.height, layer)?;
+
+        pixels.extend(decoded);
+        srgb = decoded_srgb;
+      }
+
+      levels.push(pixels);
+    }
+
+    let format = match srgb {
+      true  => vk::Format::R8G8B8A8_SRGB,
+      false => vk::Format::R8G8B8A8_UNORM
+    };
+
+    Ok(TextureData {
+      format,
+      levels,
+      ..*self
+    })
+  }
+
+  // a white pixel in every layer, shaped like the texture it stands in for so it binds wherever that one would
+  fn stand_in(&self) -> Self {
+    TextureData {
+      width: 1,
+      height: 1,
+      format: vk::Format::R8G8B8A8_UNORM,
+      levels: vec![vec![255; 4 * self.layers as usize]],
+      ..*self
+    }
+  }
+}
+
+impl Texture {
+  pub fn upload(
+    instance: &ash::Instance,
+    device: &ash::Device,
+    allocator: &Allocator,
+    gpu: &GPU,
+    data: &TextureData,
+    sampler: &SamplerInfo
+  ) -> Self {
+    // formats the gpu cannot sample are decoded on the cpu. those without a decoder are drawn white, and so are levels
+    // too short for their size, which would otherwise be copied from past the end of the data
+    let transcoded: TextureData;
+    let data = match gpu.supports_format(instance, data.format, vk::FormatFeatureFlags::SAMPLED_IMAGE) {
+      true  => match data.check_levels() {
+        Ok(())     => data,
+        Err(error) => {
+          eprintln!("failed to upload texture with error: {error}");
+          transcoded = data.stand_in();
+
+          &transcoded
+        }
+      },
+      false => {
+        transcoded = data.transcode().unwrap_or_else(|error| {
+          eprintln!("failed to transcode texture with error: {error}");
+          data.stand_in()
+        });
+
+        &transcoded
+      }
+    };
+
+    // the chain is only generated when every level can be blitted with linear filtering, otherwise the image keeps
+    // the levels it came with
+    let full_chain = 32 - data.width.max(data.height).leading_zeros();
+    let blit_features = {
+      vk::FormatFeatureFlags::BLIT_SRC | vk::FormatFeatureFlags::BLIT_DST | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+    };
+
+    let generate_mips = data.levels.len() == 1 && full_chain > 1 && gpu.supports_format(instance, data.format, blit_features);
+    let mip_levels = match generate_mips {
+      true  => full_chain,
+      false => data.levels.len() as u32
+    };
+
+    let mut usage = vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST;
+    if generate_mips { usage |= vk::ImageUsageFlags::TRANSFER_SRC; }
+
+    let (flags, view_type) = match (data.cube, data.layers) {
+      (true, 6)  => (vk::ImageCreateFlags::CUBE_COMPATIBLE, vk::ImageViewType::CUBE),
+      (true, _)  => (vk::ImageCreateFlags::CUBE_COMPATIBLE, vk::ImageViewType::CUBE_ARRAY),
+      (false, 1) => (vk::ImageCreateFlags::empty(), vk::ImageViewType::TYPE_2D),
+      (false, _) => (vk::ImageCreateFlags::empty(), vk::ImageViewType::TYPE_2D_ARRAY)
+    };
+
+    let create_info = {
+      vk::ImageCreateInfo::default()
+        .flags(flags)
+        .image_type(vk::ImageType::TYPE_2D)
+        .format(data.format)
+        .extent(vk::Extent3D { width: data.width, height: data.height, depth: 1 })
+        .mip_levels(mip_levels)
+        .array_layers(data.layers)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(usage)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+    };
+
+    let image = allocator.create_image(device, &create_info, vk::ImageAspectFlags::COLOR, view_type);
+
+    let mut regions = Vec::<vk::BufferImageCopy>::new();
+    let mut offset = 0;
+
+    for (level, pixels) in data.levels.iter().enumerate() {
+      let extent = data.level_extent(level as u32);
+
+      regions.push(
+        vk::BufferImageCopy::default()
+          .buffer_offset(offset)
+          .image_subresource(
+            vk::ImageSubresourceLayers::default()
+              .aspect_mask(vk::ImageAspectFlags::COLOR)
+              .mip_level(level as u32)
+              .base_array_layer(0)
+              .layer_count(data.layers)
+          )
+          .image_extent(vk::Extent3D { width: extent.width, height: extent.height, depth: 1 })
+      );
+
+      offset += pixels.len() as vk::DeviceSize;
+    }
+
+    let staging = allocator.create_staging_buffer(device, &data.levels.concat());
+
+    allocator.submit(device, |command_buffer| {
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        image.image,
+        Allocator::color_range(0, mip_levels, 0, data.layers),
+        (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+        (vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE),
+        (vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::TRANSFER)
+      );
+
+      unsafe {
+        device.cmd_copy_buffer_to_image(
+          command_buffer,
+          staging.buffer,
+          image.image,
+          vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+          &regions
+        )
+      };
+
+      if generate_mips {
+        Texture::generate_mips(device, command_buffer, &image);
+      }
+      else {
+        Allocator::transition_image(
+          device,
+          command_buffer,
+          image.image,
+          Allocator::color_range(0, mip_levels, 0, data.layers),
+          (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+          (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ),
+          (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::FRAGMENT_SHADER)
+        );
+      }
+    });
+
+    allocator.destroy_buffer(device, &staging);
+
+    Texture {
+      sampler: Texture::create_sampler(device, gpu, sampler, mip_levels),
+      image
+    }
+  }
+
+  pub fn clean(&self, device: &ash::Device, allocator: &Allocator) {
+    unsafe { device.destroy_sampler(self.sampler, None) };
+    allocator.destroy_image(device, &self.image);
+  }
+
+  pub fn create_sampler(device: &ash::Device, gpu: &GPU, info: &SamplerInfo, mip_levels: u32) -> vk::Sampler {
+    let anisotropy = info.anisotropy && gpu.features.sampler_anisotropy == vk::TRUE;
+
+    let create_info = {
+      vk::SamplerCreateInfo::default()
+        .mag_filter(info.mag_filter)
+        .min_filter(info.min_filter)
+        .mipmap_mode(info.mipmap_mode)
+        .address_mode_u(info.address_mode[0])
+        .address_mode_v(info.address_mode[1])
+        .address_mode_w(info.address_mode[2])
+        .anisotropy_enable(anisotropy)
+        .max_anisotropy(if anisotropy { gpu.properties.limits.max_sampler_anisotropy } else { 1.0 })
+        .min_lod(0.0)
+        .max_lod(mip_levels as f32)
+    };
+
+    match unsafe { device.create_sampler(&create_info, None) } {
+      Ok(sampler) => sampler,
+      Err(error)  => panic!("failed to create sampler with error: {error}")
+    }
+  }
+
+  // every level starts in TRANSFER_DST. each one is read back as the source for the next and ends up shader readable
+  pub fn generate_mips(device: &ash::Device, command_buffer: vk::CommandBuffer, image: &Image) {
+    let extent = |level: u32| vk::Offset3D {
+      x: (image.extent.width >> level).max(1) as i32,
+      y: (image.extent.height >> level).max(1) as i32,
+      z: 1
+    };
+
+    let layers = |level: u32| {
+      vk::ImageSubresourceLayers::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .mip_level(level)
+        .base_array_layer(0)
+        .layer_count(image.array_layers)
+    };
+
+    for level in 1..image.mip_levels {
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        image.image,
+        Allocator::color_range(level - 1, 1, 0, image.array_layers),
+        (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+        (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::TRANSFER_READ),
+        (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::TRANSFER)
+      );
+
+      let blit = {
+        vk::ImageBlit::default()
+          .src_subresource(layers(level - 1))
+          .src_offsets([vk::Offset3D::default(), extent(level - 1)])
+          .dst_subresource(layers(level))
+          .dst_offsets([vk::Offset3D::default(), extent(level)])
+      };
+
+      unsafe {
+        device.cmd_blit_image(
+          command_buffer,
+          image.image,
+          vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+          image.image,
+          vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+          &[blit],
+          vk::Filter::LINEAR
+        )
+      };
+
+      Allocator::transition_image(
+        device,
+        command_buffer,
+        image.image,
+        Allocator::color_range(level - 1, 1, 0, image.array_layers),
+        (vk::ImageLayout::TRANSFER_SRC_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+        (vk::AccessFlags::TRANSFER_READ, vk::AccessFlags::SHADER_READ),
+        (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::FRAGMENT_SHADER)
+      );
+    }
+
+    Allocator::transition_image(
+      device,
+      command_buffer,
+      image.image,
+      Allocator::color_range(image.mip_levels - 1, 1, 0, image.array_layers),
+      (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+      (vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ),
+      (vk::PipelineStageFlags::TRANSFER, vk::PipelineStageFlags::FRAGMENT_SHADER)
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reports_levels_shorter_than_their_extent() {
+    let mut data = TextureData::rgba8(4, 2, vec![0; 32], false);
+    assert_eq!(data.check_levels(), Ok(()));
+
+    data.levels.push(vec![0; 4]);
+    assert_eq!(data.check_levels(), Err(TextureError::Truncated { expected: 8, found: 4 }));
+
+    // block formats round partial blocks up, and every layer counts
+    let cube = TextureData { format: vk::Format::BC1_RGB_UNORM_BLOCK, layers: 6, cube: true, levels: vec![vec![0; 40]], ..data };
+    assert_eq!(cube.check_levels(), Err(TextureError::Truncated { expected: 48, found: 40 }));
+  }
+}
@@ -0,0 +1,475 @@
+use super::TextureError;
+
+use ash::vk;
+
+// cpu decoders used when the gpu cannot sample a block compressed format directly. every decoder writes rgba8
+// pixels. formats without a decoder here (bc6h, bc7, astc) are unsupported
+
+const ETC1_MODIFIERS: [[i32; 2]; 8] = [
+  [2, 8], [5, 17], [9, 29], [13, 42], [18, 60], [24, 80], [33, 106], [47, 183]
+];
+
+const ETC2_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+  [-3, -6, -9, -15, 2, 5, 8, 14], [-3, -7, -10, -13, 2, 6, 9, 12], [-2, -5, -8, -13, 1, 4, 7, 12],
+  [-2, -4, -6, -13, 1, 3, 5, 12], [-3, -6, -8, -12, 2, 5, 7, 11], [-3, -7, -9, -11, 2, 6, 8, 10],
+  [-4, -7, -8, -11, 3, 6, 7, 10], [-3, -5, -8, -11, 2, 4, 7, 10], [-2, -6, -8, -10, 1, 5, 7, 9],
+  [-2, -5, -8, -10, 1, 4, 7, 9], [-2, -4, -8, -10, 1, 3, 7, 9], [-2, -5, -7, -10, 1, 4, 6, 9],
+  [-3, -4, -7, -10, 2, 3, 6, 9], [-1, -2, -3, -10, 0, 1, 2, 9], [-4, -6, -8, -9, 3, 5, 7, 8],
+  [-3, -5, -7, -9, 2, 4, 6, 8]
+];
+
+type Block = [[u8; 4]; 16];
+type Decoder = fn(&[u8]) -> Block;
+
+// returns the decoded pixels and whether they are srgb encoded
+pub fn decode(format: vk::Format, width: u32, height: u32, data: &[u8]) -> Result<(Vec<u8>, bool), TextureError> {
+  let (block_size, srgb, decoder): (usize, bool, Decoder) = match format {
+    vk::Format::BC1_RGB_UNORM_BLOCK       => (8, false, |block| bc1(block, false)),
+    vk::Format::BC1_RGB_SRGB_BLOCK        => (8, true, |block| bc1(block, false)),
+    vk::Format::BC1_RGBA_UNORM_BLOCK      => (8, false, |block| bc1(block, true)),
+    vk::Format::BC1_RGBA_SRGB_BLOCK       => (8, true, |block| bc1(block, true)),
+    vk::Format::BC2_UNORM_BLOCK           => (16, false, bc2),
+    vk::Format::BC2_SRGB_BLOCK            => (16, true, bc2),
+    vk::Format::BC3_UNORM_BLOCK           => (16, false, bc3),
+    vk::Format::BC3_SRGB_BLOCK            => (16, true, bc3),
+    vk::Format::BC4_UNORM_BLOCK           => (8, false, bc4),
+    vk::Format::BC5_UNORM_BLOCK           => (16, false, bc5),
+    vk::Format::ETC2_R8G8B8_UNORM_BLOCK   => (8, false, |block| etc2(read_u64_be(block), false)),
+    vk::Format::ETC2_R8G8B8_SRGB_BLOCK    => (8, true, |block| etc2(read_u64_be(block), false)),
+    vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK => (8, false, |block| etc2(read_u64_be(block), true)),
+    vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK  => (8, true, |block| etc2(read_u64_be(block), true)),
+    vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK => (16, false, etc2_eac),
+    vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK  => (16, true, etc2_eac),
+    _                                     => return Err(TextureError::Unsupported(format))
+  };
+
+  let blocks_wide = width.div_ceil(4) as usize;
+  let blocks_high = height.div_ceil(4) as usize;
+
+  if data.len() < blocks_wide * blocks_high * block_size {
+    return Err(TextureError::Truncated { expected: blocks_wide * blocks_high * block_size, found: data.len() });
+  }
+
+  let mut pixels = vec![0u8; width as usize * height as usize * 4];
+
+  for block_y in 0..blocks_high {
+    for block_x in 0..blocks_wide {
+      let offset = (block_y * blocks_wide + block_x) * block_size;
+      let block = decoder(&data[offset..offset + block_size]);
+
+      // blocks on the right and bottom edges can hang over the end of the image
+      for (index, texel) in block.iter().enumerate() {
+        let x = block_x * 4 + index % 4;
+        let y = block_y * 4 + index / 4;
+        if x >= width as usize || y >= height as usize { continue; }
+
+        let pixel = (y * width as usize + x) * 4;
+        pixels[pixel..pixel + 4].copy_from_slice(texel);
+      }
+    }
+  }
+
+  Ok((pixels, srgb))
+}
+
+// the width and height in texels of one block of the format and its size in bytes, uncompressed formats having blocks
+// of a texel. none for formats whose layout is not known here
+pub fn footprint(format: vk::Format) -> Option<(u32, u32, usize)> {
+  let footprint = match format {
+    vk::Format::R8_UNORM | vk::Format::R8_SRGB                                          => (1, 1, 1),
+    vk::Format::R8G8_UNORM | vk::Format::R8G8_SRGB | vk::Format::R16_SFLOAT             => (1, 1, 2),
+    vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB
+      | vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB
+      | vk::Format::A2B10G10R10_UNORM_PACK32 | vk::Format::B10G11R11_UFLOAT_PACK32
+      | vk::Format::E5B9G9R9_UFLOAT_PACK32 | vk::Format::R16G16_SFLOAT
+      | vk::Format::R32_SFLOAT                                                          => (1, 1, 4),
+    vk::Format::R16G16B16A16_UNORM | vk::Format::R16G16B16A16_SFLOAT
+      | vk::Format::R32G32_SFLOAT                                                       => (1, 1, 8),
+    vk::Format::R32G32B32A32_SFLOAT                                                     => (1, 1, 16),
+    vk::Format::BC1_RGB_UNORM_BLOCK | vk::Format::BC1_RGB_SRGB_BLOCK
+      | vk::Format::BC1_RGBA_UNORM_BLOCK | vk::Format::BC1_RGBA_SRGB_BLOCK
+      | vk::Format::BC4_UNORM_BLOCK | vk::Format::BC4_SNORM_BLOCK
+      | vk::Format::ETC2_R8G8B8_UNORM_BLOCK | vk::Format::ETC2_R8G8B8_SRGB_BLOCK
+      | vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK | vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK
+      | vk::Format::EAC_R11_UNORM_BLOCK | vk::Format::EAC_R11_SNORM_BLOCK               => (4, 4, 8),
+    vk::Format::BC2_UNORM_BLOCK | vk::Format::BC2_SRGB_BLOCK
+      | vk::Format::BC3_UNORM_BLOCK | vk::Format::BC3_SRGB_BLOCK
+      | vk::Format::BC5_UNORM_BLOCK | vk::Format::BC5_SNORM_BLOCK
+      | vk::Format::BC6H_UFLOAT_BLOCK | vk::Format::BC6H_SFLOAT_BLOCK
+      | vk::Format::BC7_UNORM_BLOCK | vk::Format::BC7_SRGB_BLOCK
+      | vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK | vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK
+      | vk::Format::EAC_R11G11_UNORM_BLOCK | vk::Format::EAC_R11G11_SNORM_BLOCK         => (4, 4, 16),
+    vk::Format::ASTC_4X4_UNORM_BLOCK | vk::Format::ASTC_4X4_SRGB_BLOCK                  => (4, 4, 16),
+    vk::Format::ASTC_5X5_UNORM_BLOCK | vk::Format::ASTC_5X5_SRGB_BLOCK                  => (5, 5, 16),
+    vk::Format::ASTC_6X6_UNORM_BLOCK | vk::Format::ASTC_6X6_SRGB_BLOCK                  => (6, 6, 16),
+    vk::Format::ASTC_8X8_UNORM_BLOCK | vk::Format::ASTC_8X8_SRGB_BLOCK                  => (8, 8, 16),
+    _                                                                                   => return None
+  };
+
+  Some(footprint)
+}
+
+fn read_u64_be(bytes: &[u8]) -> u64 {
+  u64::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
+}
+
+fn read_u64_le(bytes: &[u8]) -> u64 {
+  u64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
+}
+
+fn rgb565(color: u16) -> [i32; 3] {
+  let (r, g, b) = ((color >> 11) & 31, (color >> 5) & 63, color & 31);
+  [((r << 3) | (r >> 2)) as i32, ((g << 2) | (g >> 4)) as i32, ((b << 3) | (b >> 2)) as i32]
+}
+
+// the color half shared by bc1, bc2 and bc3. the latter two always use the four color palette
+fn bc_color(block: &[u8], punch_through: bool, alpha: bool) -> Block {
+  let c0 = u16::from_le_bytes([block[0], block[1]]);
+  let c1 = u16::from_le_bytes([block[2], block[3]]);
+  let (a, b) = (rgb565(c0), rgb565(c1));
+
+  let mut palette = [[0u8; 4]; 4];
+  for channel in 0..3 {
+    palette[0][channel] = a[channel] as u8;
+    palette[1][channel] = b[channel] as u8;
+
+    if c0 > c1 || !punch_through {
+      palette[2][channel] = ((2 * a[channel] + b[channel]) / 3) as u8;
+      palette[3][channel] = ((a[channel] + 2 * b[channel]) / 3) as u8;
+    }
+    else {
+      palette[2][channel] = ((a[channel] + b[channel]) / 2) as u8;
+    }
+  }
+
+  for (index, color) in palette.iter_mut().enumerate() {
+    color[3] = match index == 3 && punch_through && c0 <= c1 && alpha {
+      true  => 0,
+      false => 255
+    };
+  }
+
+  let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+  let mut texels = [[0u8; 4]; 16];
+
+  for (index, texel) in texels.iter_mut().enumerate() {
+    *texel = palette[((indices >> (2 * index)) & 3) as usize];
+  }
+
+  texels
+}
+
+// eight interpolated values from two endpoints, used for the bc3 alpha block as well as bc4 and bc5 channels
+fn bc_channel(block: &[u8]) -> [u8; 16] {
+  let (a, b) = (block[0] as u32, block[1] as u32);
+
+  let mut palette = [a, b, 0, 0, 0, 0, 0, 255];
+  match a > b {
+    true  => {
+      for index in 0..6 {
+        palette[index + 2] = ((6 - index as u32) * a + (1 + index as u32) * b) / 7;
+      }
+    },
+    false => {
+      for index in 0..4 {
+        palette[index + 2] = ((4 - index as u32) * a + (1 + index as u32) * b) / 5;
+      }
+    }
+  }
+
+  let indices = read_u64_le(block) >> 16;
+  let mut values = [0u8; 16];
+
+  for (index, value) in values.iter_mut().enumerate() {
+    *value = palette[((indices >> (3 * index)) & 7) as usize] as u8;
+  }
+
+  values
+}
+
+fn bc1(block: &[u8], alpha: bool) -> Block {
+  bc_color(block, true, alpha)
+}
+
+fn bc2(block: &[u8]) -> Block {
+  let mut texels = bc_color(&block[8..], false, false);
+  let alpha = read_u64_le(block);
+
+  for (index, texel) in texels.iter_mut().enumerate() {
+    texel[3] = (((alpha >> (4 * index)) & 15) * 17) as u8;
+  }
+
+  texels
+}
+
+fn bc3(block: &[u8]) -> Block {
+  let mut texels = bc_color(&block[8..], false, false);
+
+  for (texel, alpha) in texels.iter_mut().zip(bc_channel(block)) {
+    texel[3] = alpha;
+  }
+
+  texels
+}
+
+fn bc4(block: &[u8]) -> Block {
+  bc_channel(block).map(|red| [red, 0, 0, 255])
+}
+
+fn bc5(block: &[u8]) -> Block {
+  let (red, green) = (bc_channel(block), bc_channel(&block[8..]));
+
+  let mut texels = [[0u8; 4]; 16];
+  for index in 0..16 {
+    texels[index] = [red[index], green[index], 0, 255];
+  }
+
+  texels
+}
+
+fn bits(block: u64, high: u32, count: u32) -> i32 {
+  ((block >> (high + 1 - count)) & ((1 << count) - 1)) as i32
+}
+
+fn extend(value: i32, count: u32) -> i32 {
+  (value << (8 - count)) | (value >> (2 * count - 8))
+}
+
+fn clamp(value: i32) -> u8 {
+  value.clamp(0, 255) as u8
+}
+
+// etc2 texel indices run down columns. this maps them to the row major order the other decoders produce
+fn etc_index(block: u64, x: usize, y: usize) -> usize {
+  let bit = x * 4 + y;
+  ((((block >> (bit + 16)) & 1) << 1) | ((block >> bit) & 1)) as usize
+}
+
+// punch-through blocks have no individual mode, their bit for it says whether the block is opaque instead. the rest
+// of their texels with index 2 are transparent black, and in differential mode they lose the small modifiers
+fn etc2(block: u64, punch_through: bool) -> Block {
+  let mut texels = [[0u8; 4]; 16];
+  let opaque = !punch_through || bits(block, 33, 1) == 1;
+
+  if !punch_through && bits(block, 33, 1) == 0 {
+    let first = [bits(block, 63, 4), bits(block, 55, 4), bits(block, 47, 4)].map(|value| extend(value, 4));
+    let second = [bits(block, 59, 4), bits(block, 51, 4), bits(block, 43, 4)].map(|value| extend(value, 4));
+
+    etc1(block, first, second, true, &mut texels);
+    return texels;
+  }
+
+  let base = [bits(block, 63, 5), bits(block, 55, 5), bits(block, 47, 5)];
+  let delta = [bits(block, 58, 3), bits(block, 50, 3), bits(block, 42, 3)].map(|value| (value << 29) >> 29);
+  let sum = [base[0] + delta[0], base[1] + delta[1], base[2] + delta[2]];
+
+  // an overflowing differential color selects one of the modes etc2 added on top of etc1
+  if !(0..32).contains(&sum[0]) {
+    let first = [
+      (bits(block, 60, 2) << 2) | bits(block, 57, 2),
+      bits(block, 55, 4),
+      bits(block, 51, 4)
+    ].map(|value| extend(value, 4));
+
+    let second = [bits(block, 47, 4), bits(block, 43, 4), bits(block, 39, 4)].map(|value| extend(value, 4));
+    let distance = ETC2_DISTANCES[((bits(block, 35, 2) << 1) | bits(block, 32, 1)) as usize];
+
+    let palette = [
+      first,
+      second.map(|value| value + distance),
+      second,
+      second.map(|value| value - distance)
+    ];
+
+    paint(block, &palette, opaque, &mut texels);
+  }
+  else if !(0..32).contains(&sum[1]) {
+    let first_raw = [
+      bits(block, 62, 4),
+      (bits(block, 58, 3) << 1) | bits(block, 52, 1),
+      (bits(block, 51, 1) << 3) | bits(block, 49, 3)
+    ];
+    let second_raw = [bits(block, 46, 4), bits(block, 42, 4), bits(block, 38, 4)];
+
+    let order = ((first_raw[0] << 8) | (first_raw[1] << 4) | first_raw[2]) >= ((second_raw[0] << 8) | (second_raw[1] << 4) | second_raw[2]);
+    let distance = ETC2_DISTANCES[((bits(block, 34, 1) << 2) | (bits(block, 32, 1) << 1) | order as i32) as usize];
+
+    let (first, second) = (first_raw.map(|value| extend(value, 4)), second_raw.map(|value| extend(value, 4)));
+
+    let palette = [
+      first.map(|value| value + distance),
+      first.map(|value| value - distance),
+      second.map(|value| value + distance),
+      second.map(|value| value - distance)
+    ];
+
+    paint(block, &palette, opaque, &mut texels);
+  }
+  else if !(0..32).contains(&sum[2]) {
+    planar(block, &mut texels);
+  }
+  else {
+    etc1(block, base.map(|value| extend(value, 5)), sum.map(|value| extend(value, 5)), opaque, &mut texels);
+  }
+
+  texels
+}
+
+fn etc1(block: u64, first: [i32; 3], second: [i32; 3], opaque: bool, texels: &mut Block) {
+  let tables = [bits(block, 39, 3) as usize, bits(block, 36, 3) as usize];
+  let flip = bits(block, 32, 1) == 1;
+
+  for y in 0..4 {
+    for x in 0..4 {
+      let subblock = match flip {
+        true  => (y >= 2) as usize,
+        false => (x >= 2) as usize
+      };
+
+      let color = if subblock == 0 { first } else { second };
+      let [small, large] = ETC1_MODIFIERS[tables[subblock]];
+
+      let modifier = match (etc_index(block, x, y), opaque) {
+        (0, true)  => small,
+        (0, false) => 0,
+        (1, _)     => large,
+        (2, true)  => -small,
+        (2, false) => {
+          texels[y * 4 + x] = [0; 4];
+          continue;
+        },
+        _          => -large
+      };
+
+      texels[y * 4 + x] = [clamp(color[0] + modifier), clamp(color[1] + modifier), clamp(color[2] + modifier), 255];
+    }
+  }
+}
+
+fn paint(block: u64, palette: &[[i32; 3]; 4], opaque: bool, texels: &mut Block) {
+  for y in 0..4 {
+    for x in 0..4 {
+      let index = etc_index(block, x, y);
+      let color = palette[index];
+
+      texels[y * 4 + x] = match !opaque && index == 2 {
+        true  => [0; 4],
+        false => [clamp(color[0]), clamp(color[1]), clamp(color[2]), 255]
+      };
+    }
+  }
+}
+
+fn planar(block: u64, texels: &mut Block) {
+  let origin = [
+    extend(bits(block, 62, 6), 6),
+    extend((bits(block, 56, 1) << 6) | bits(block, 54, 6), 7),
+    extend((bits(block, 48, 1) << 5) | (bits(block, 44, 2) << 3) | bits(block, 41, 3), 6)
+  ];
+
+  let horizontal = [
+    extend((bits(block, 38, 5) << 1) | bits(block, 32, 1), 6),
+    extend(bits(block, 31, 7), 7),
+    extend(bits(block, 24, 6), 6)
+  ];
+
+  let vertical = [
+    extend(bits(block, 18, 6), 6),
+    extend(bits(block, 12, 7), 7),
+    extend(bits(block, 5, 6), 6)
+  ];
+
+  for y in 0..4 {
+    for x in 0..4 {
+      let channel = |index: usize| {
+        let (o, h, v) = (origin[index], horizontal[index], vertical[index]);
+        clamp((x as i32 * (h - o) + y as i32 * (v - o) + 4 * o + 2) >> 2)
+      };
+
+      texels[y * 4 + x] = [channel(0), channel(1), channel(2), 255];
+    }
+  }
+}
+
+fn etc2_eac(block: &[u8]) -> Block {
+  let alpha = read_u64_be(block);
+  let mut texels = etc2(read_u64_be(&block[8..]), false);
+
+  let base = bits(alpha, 63, 8);
+  let multiplier = bits(alpha, 55, 4);
+  let table = EAC_MODIFIERS[bits(alpha, 51, 4) as usize];
+
+  for y in 0..4 {
+    for x in 0..4 {
+      let index = bits(alpha, 47 - 3 * (x * 4 + y) as u32, 3) as usize;
+      texels[y * 4 + x][3] = clamp(base + table[index] * multiplier);
+    }
+  }
+
+  texels
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // a differential mode block with a 5 bit base color of (16, 8, 4), no deltas and every texel on the same index
+  fn differential(opaque: bool, index: u64) -> [u8; 8] {
+    let color = (16u64 << 59) | (8 << 51) | (4 << 43);
+    let msb = if index & 2 != 0 { 0xffff << 16 } else { 0 };
+    let lsb = if index & 1 != 0 { 0xffff } else { 0 };
+
+    (color | ((opaque as u64) << 33) | msb | lsb).to_be_bytes()
+  }
+
+  fn decode_block(format: vk::Format, block: [u8; 8]) -> Vec<u8> {
+    decode(format, 4, 4, &block).unwrap().0
+  }
+
+  #[test]
+  fn decodes_opaque_punch_through_blocks_like_etc2() {
+    let block = differential(true, 0);
+    let pixels = decode_block(vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK, block);
+
+    // the base color extended to 8 bits, plus the first table's small modifier
+    assert_eq!(&pixels[..4], &[134, 68, 35, 255]);
+    assert_eq!(pixels, decode_block(vk::Format::ETC2_R8G8B8_UNORM_BLOCK, block));
+  }
+
+  #[test]
+  fn punches_through_index_two_of_transparent_blocks() {
+    let transparent = decode_block(vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK, differential(false, 2));
+    assert!(transparent.iter().all(|&channel| channel == 0));
+
+    // and the small modifiers are gone from the others
+    let base = decode_block(vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK, differential(false, 0));
+    assert_eq!(&base[..4], &[132, 66, 33, 255]);
+
+    let large = decode_block(vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK, differential(false, 3));
+    assert_eq!(&large[..4], &[124, 58, 25, 255]);
+  }
+
+  #[test]
+  fn reports_formats_without_a_decoder() {
+    for format in [vk::Format::BC7_UNORM_BLOCK, vk::Format::BC6H_UFLOAT_BLOCK, vk::Format::ASTC_4X4_UNORM_BLOCK] {
+      assert_eq!(decode(format, 4, 4, &[0; 16]), Err(TextureError::Unsupported(format)));
+    }
+  }
+
+  #[test]
+  fn knows_the_footprint_of_uncompressed_and_block_formats() {
+    assert_eq!(footprint(vk::Format::R16G16B16A16_SFLOAT), Some((1, 1, 8)));
+    assert_eq!(footprint(vk::Format::BC1_RGB_SRGB_BLOCK), Some((4, 4, 8)));
+    assert_eq!(footprint(vk::Format::BC7_UNORM_BLOCK), Some((4, 4, 16)));
+    assert_eq!(footprint(vk::Format::ASTC_6X6_SRGB_BLOCK), Some((6, 6, 16)));
+    assert_eq!(footprint(vk::Format::D32_SFLOAT), None);
+  }
+
+  #[test]
+  fn reports_truncated_levels() {
+    let error = decode(vk::Format::BC1_RGB_UNORM_BLOCK, 8, 8, &[0; 8]);
+    assert_eq!(error, Err(TextureError::Truncated { expected: 32, found: 8 }));
+  }
+}
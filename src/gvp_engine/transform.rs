@@ -0,0 +1,56 @@
+use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+  pub translation: Vec3,
+  pub rotation: Quat,
+  pub scale: Vec3
+}
+
+impl Default for Transform {
+  fn default() -> Self {
+    Transform::IDENTITY
+  }
+}
+
+impl Transform {
+  pub const IDENTITY: Transform = Transform {
+    translation: Vec3::ZERO,
+    rotation: Quat::IDENTITY,
+    scale: Vec3::ONE
+  };
+
+  pub fn new(translation: Vec3, rotation: Quat, scale: Vec3) -> Self {
+    Transform {
+      translation,
+      rotation,
+      scale
+    }
+  }
+
+  pub fn from_translation(translation: Vec3) -> Self {
+    Transform {
+      translation,
+      ..Transform::IDENTITY
+    }
+  }
+
+  pub fn from_matrix(matrix: &Mat4) -> Self {
+    let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+
+    Transform {
+      translation,
+      rotation,
+      scale
+    }
+  }
+
+  pub fn matrix(&self) -> Mat4 {
+    Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+  }
+
+  pub fn forward(&self) -> Vec3 {
+    self.rotation * Vec3::NEG_Z
+  }
+}
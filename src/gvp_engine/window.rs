@@ -1,11 +1,19 @@
-use sdl2::{event::Event, keyboard::Keycode, video::Window as SDLWindow, Sdl};
+use crate::gvp_engine::{
+  audio::{Audio, Playback, SpeakerQueue, Speakers},
+  input::DesktopInput
+};
+
+use sdl2::{audio::{AudioDevice, AudioSpecDesired}, event::Event, keyboard::Keycode, video::Window as SDLWindow, Sdl};
 use ash::vk::{self, Handle};
+use glam::Vec2;
 
 use std::vec::Vec;
 
 pub struct Window {
   context: Sdl,
-  window: SDLWindow
+  window: SDLWindow,
+  // plays whatever the mixer queues for it once opened
+  audio: Option<AudioDevice<Playback>>
 }
 
 impl Window {
@@ -32,10 +40,27 @@ impl Window {
 
     Window {
       context,
-      window
+      window,
+      audio: None
     }
   }
 
+  // opens the default sound card, keeping the device and handing back the end the mixer writes to. none when there is
+  // no sound hardware to open
+  pub fn speakers(&mut self) -> Option<Speakers> {
+    let audio = self.context.audio().ok()?;
+    let desired = AudioSpecDesired { freq: Some(Audio::SAMPLE_RATE as i32), channels: Some(2), samples: Some(512) };
+    let queue = SpeakerQueue::default();
+
+    let device = audio.open_playback(None, &desired, |spec| queue.playback(spec.channels)).ok()?;
+    let speakers = queue.speakers(device.spec().freq as u32);
+
+    device.resume();
+    self.audio = Some(device);
+
+    Some(speakers)
+  }
+
   pub fn extensions(&self) -> Vec<*const i8> {
     let str_extensions = match self.window.vulkan_instance_extensions() {
       Ok(ext)     => ext,
@@ -58,7 +83,11 @@ impl Window {
     }
   }
 
-  pub fn poll_events(&self) -> bool {
+  // feeds keyboard and mouse events into the desktop input. returns true when the application should quit
+  pub fn poll_events(&self, input: &mut DesktopInput) -> bool {
+    let (width, height) = self.window.size();
+    input.window_size = Vec2::new(width as f32, height as f32);
+
     let mut event_pump = match self.context.event_pump() {
       Ok(event_pump)  => event_pump,
       Err(error)      => panic!("failed to get event pump with error: {error}")
@@ -68,6 +97,13 @@ impl Window {
       match event {
         Event::Quit { .. }                                    => return true,
         Event::KeyDown { keycode: Some(Keycode::Escape), .. } => return true,
+        Event::KeyDown { keycode: Some(key), .. }             => input.press_key(key),
+        Event::KeyUp { keycode: Some(key), .. }               => input.release_key(key),
+        Event::MouseButtonDown { mouse_btn, .. }              => input.press_button(mouse_btn),
+        Event::MouseButtonUp { mouse_btn, .. }                => input.release_button(mouse_btn),
+        Event::MouseWheel { precise_x, precise_y, .. }        => input.scroll(Vec2::new(precise_x, precise_y)),
+        Event::TextInput { text, .. }                         => input.type_text(&text),
+        Event::MouseMotion { x, y, .. }                       => input.move_cursor(Vec2::new(x as f32, y as f32)),
         _ => ()
       }
     }
@@ -0,0 +1,395 @@
+mod actions;
+mod swapchain;
+
+use actions::XrActions;
+use swapchain::XrSwapchain;
+
+use crate::gvp_engine::{
+  allocator::Allocator,
+  camera::Camera,
+  gpu::GPU,
+  input::Input,
+  renderer::{Renderer, RenderTarget, RenderView}
+};
+
+use ash::vk::{self, Handle};
+use glam::{Mat4, Quat, Vec3};
+use openxr as xr;
+
+use std::mem;
+
+struct XrSession {
+  session: xr::Session<xr::Vulkan>,
+  frame_waiter: xr::FrameWaiter,
+  frame_stream: xr::FrameStream<xr::Vulkan>,
+  space: xr::Space,
+  // a single array swapchain when the eyes are drawn with multiview, otherwise one per eye
+  swapchains: Vec<XrSwapchain>,
+  // between the runtime reporting READY and STOPPING, the only time frames may be submitted
+  running: bool,
+  // created on the first input sync after a frame has started
+  actions: Option<XrActions>,
+  // predicted display time of the latest frame, which input poses are located at
+  display_time: Option<xr::Time>
+}
+
+// a frame the runtime asked for. views is empty when nothing should be drawn this time
+pub struct XrFrame {
+  state: xr::FrameState,
+  views: Vec<xr::View>
+}
+
+// the headset side of the engine. the vulkan instance and device have to be created through the runtime
+// (XR_KHR_vulkan_enable2) so it can add whatever it needs to them, after which a session is started on the device
+pub struct Xr {
+  instance: xr::Instance,
+  system: xr::SystemId,
+  blend_mode: xr::EnvironmentBlendMode,
+  // whether XR_EXT_hand_tracking is enabled and the system can track hands
+  hand_tracking: bool,
+  session: Option<XrSession>,
+  event_buffer: xr::EventDataBuffer
+}
+
+impl Xr {
+  const VIEW_TYPE: xr::ViewConfigurationType = xr::ViewConfigurationType::PRIMARY_STEREO;
+
+  // returns None when there is no runtime or no headset, in which case the engine only renders to the window
+  pub fn new() -> Option<Self> {
+    let entry = unsafe { xr::Entry::load(&()) }.ok()?;
+    let available = entry.enumerate_extensions().ok()?;
+
+    if !available.khr_vulkan_enable2 { return None; }
+
+    let mut extensions = xr::ExtensionSet::default();
+    extensions.khr_vulkan_enable2 = true;
+    extensions.ext_hand_tracking = available.ext_hand_tracking;
+
+    let application_info = xr::ApplicationInfo {
+      application_name: "Groot Vision Pro",
+      application_version: 0,
+      engine_name: "GVP Engine",
+      engine_version: 0,
+      api_version: xr::Version::new(1, 0, 0)
+    };
+
+    let instance = entry.create_instance(&application_info, &extensions, &[], &()).ok()?;
+    let system = instance.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY).ok()?;
+    let hand_tracking = available.ext_hand_tracking && instance.supports_hand_tracking(system).unwrap_or(false);
+
+    // the engine is written against vulkan 1.3
+    let requirements = instance.graphics_requirements::<xr::Vulkan>(system).ok()?;
+    let minimum = requirements.min_api_version_supported;
+    if (minimum.major(), minimum.minor()) > (1, 3) { return None; }
+
+    let blend_modes = instance.enumerate_environment_blend_modes(system, Xr::VIEW_TYPE).ok()?;
+    let blend_mode = match blend_modes.contains(&xr::EnvironmentBlendMode::OPAQUE) {
+      true  => xr::EnvironmentBlendMode::OPAQUE,
+      false => *blend_modes.first()?
+    };
+
+    Some(Xr {
+      instance,
+      system,
+      blend_mode,
+      hand_tracking,
+      session: None,
+      event_buffer: xr::EventDataBuffer::new()
+    })
+  }
+
+  pub fn create_vulkan_instance(&self, entry: &ash::Entry, create_info: &vk::InstanceCreateInfo) -> ash::Instance {
+    let handle = match unsafe {
+      self.instance.create_vulkan_instance(
+        self.system,
+        Xr::get_instance_proc_addr(entry),
+        create_info as *const _ as *const _
+      )
+    } {
+      Ok(Ok(handle))  => handle,
+      Ok(Err(result)) => panic!("failed to create vulkan instance through xr with error: {}", vk::Result::from_raw(result)),
+      Err(error)      => panic!("failed to create vulkan instance through xr with error: {error}")
+    };
+
+    unsafe { ash::Instance::load(entry.static_fn(), vk::Instance::from_raw(handle as u64)) }
+  }
+
+  // the gpu the headset is connected to
+  pub fn physical_device(&self, instance: &ash::Instance) -> vk::PhysicalDevice {
+    match unsafe { self.instance.vulkan_graphics_device(self.system, instance.handle().as_raw() as _) } {
+      Ok(device) => vk::PhysicalDevice::from_raw(device as u64),
+      Err(error) => panic!("failed to get xr physical device with error: {error}")
+    }
+  }
+
+  pub fn create_vulkan_device(
+    &self,
+    entry: &ash::Entry,
+    instance: &ash::Instance,
+    gpu: &GPU,
+    create_info: &vk::DeviceCreateInfo
+  ) -> ash::Device {
+    let handle = match unsafe {
+      self.instance.create_vulkan_device(
+        self.system,
+        Xr::get_instance_proc_addr(entry),
+        gpu.device.as_raw() as _,
+        create_info as *const _ as *const _
+      )
+    } {
+      Ok(Ok(handle))  => handle,
+      Ok(Err(result)) => panic!("failed to create vulkan device through xr with error: {}", vk::Result::from_raw(result)),
+      Err(error)      => panic!("failed to create vulkan device through xr with error: {error}")
+    };
+
+    unsafe { ash::Device::load(instance.fp_v1_0(), vk::Device::from_raw(handle as u64)) }
+  }
+
+  // format is what the window is drawn in. it is used for the eyes too when the runtime allows it so both can share
+  // pipelines
+  pub fn start_session(
+    &mut self,
+    instance: &ash::Instance,
+    device: &ash::Device,
+    allocator: &Allocator,
+    gpu: &GPU,
+    format: vk::Format
+  ) {
+    let create_info = xr::vulkan::SessionCreateInfo {
+      instance: instance.handle().as_raw() as _,
+      physical_device: gpu.device.as_raw() as _,
+      device: device.handle().as_raw() as _,
+      queue_family_index: gpu.main_queue().index as u32,
+      queue_index: 0
+    };
+
+    let (session, frame_waiter, frame_stream) = match unsafe {
+      self.instance.create_session::<xr::Vulkan>(self.system, &create_info)
+    } {
+      Ok(session) => session,
+      Err(error)  => panic!("failed to create xr session with error: {error}")
+    };
+
+    // local space starts at the head, which lines up with the desktop camera sitting at eye height
+    let space = match session.create_reference_space(xr::ReferenceSpaceType::LOCAL, xr::Posef::IDENTITY) {
+      Ok(space)  => space,
+      Err(error) => panic!("failed to create xr reference space with error: {error}")
+    };
+
+    let formats = match session.enumerate_swapchain_formats() {
+      Ok(formats) => formats,
+      Err(error)  => panic!("failed to get xr swapchain formats with error: {error}")
+    };
+
+    let format = [format, vk::Format::R8G8B8A8_SRGB, vk::Format::B8G8R8A8_SRGB].into_iter()
+      .find(|format| formats.contains(&(format.as_raw() as u32)))
+      .unwrap_or(vk::Format::from_raw(formats[0] as i32));
+
+    let views = match self.instance.enumerate_view_configuration_views(self.system, Xr::VIEW_TYPE) {
+      Ok(views)  => views,
+      Err(error) => panic!("failed to get xr view configuration with error: {error}")
+    };
+
+    let extents: Vec<vk::Extent2D> = views.iter().map(|view| vk::Extent2D {
+      width: view.recommended_image_rect_width,
+      height: view.recommended_image_rect_height
+    }).collect();
+
+    // multiview draws every layer at the same size, so eyes with different recommended sizes get separate images
+    let multiview = extents.len() <= Renderer::MAX_LAYERS && extents.windows(2).all(|pair| pair[0] == pair[1]);
+
+    let swapchains = match multiview {
+      true  => vec![XrSwapchain::new(&session, device, allocator, format, extents[0], extents.len() as u32)],
+      false => extents.iter().map(|extent| XrSwapchain::new(&session, device, allocator, format, *extent, 1)).collect()
+    };
+
+    self.session = Some(XrSession {
+      session,
+      frame_waiter,
+      frame_stream,
+      space,
+      swapchains,
+      running: false,
+      actions: None,
+      display_time: None
+    });
+  }
+
+  pub fn clean(&mut self, device: &ash::Device, allocator: &Allocator) {
+    if let Some(session) = self.session.take() {
+      for swapchain in &session.swapchains {
+        swapchain.clean(device, allocator);
+      }
+    }
+  }
+
+  // follows the session through its lifecycle. returns true when the runtime wants the application to exit
+  pub fn poll_events(&mut self) -> bool {
+    let Some(session) = &mut self.session else { return false; };
+
+    loop {
+      let event = match self.instance.poll_event(&mut self.event_buffer) {
+        Ok(Some(event)) => event,
+        Ok(None)        => return false,
+        Err(error)      => panic!("failed to poll xr events with error: {error}")
+      };
+
+      match event {
+        xr::Event::SessionStateChanged(change) => match change.state() {
+          xr::SessionState::READY => {
+            if let Err(error) = session.session.begin(Xr::VIEW_TYPE) {
+              panic!("failed to begin xr session with error: {error}")
+            }
+
+            session.running = true;
+          },
+          xr::SessionState::STOPPING => {
+            if let Err(error) = session.session.end() {
+              panic!("failed to end xr session with error: {error}")
+            }
+
+            session.running = false;
+          },
+          xr::SessionState::EXITING | xr::SessionState::LOSS_PENDING => return true,
+          _ => ()
+        },
+        xr::Event::InstanceLossPending(_) => return true,
+        _ => ()
+      }
+    }
+  }
+
+  // reads controllers and tracked hands into the input, placed relative to the camera like the eyes are. nothing is
+  // read until the first frame has started
+  pub fn sync_input(&mut self, input: &mut Input, camera: &Camera) {
+    let Some(session) = self.session.as_mut().filter(|session| session.running) else { return; };
+    let Some(time) = session.display_time else { return; };
+
+    let actions = session.actions.get_or_insert_with(|| {
+      XrActions::new(&self.instance, &session.session, input, self.hand_tracking)
+    });
+
+    actions.sync(&session.session, &session.space, time, camera.transform.matrix(), input);
+  }
+
+  // waits for the runtime to schedule the next frame and acquires an image for each eye when it should be drawn
+  pub fn begin_frame(&mut self) -> Option<XrFrame> {
+    let session = self.session.as_mut().filter(|session| session.running)?;
+
+    let state = match session.frame_waiter.wait() {
+      Ok(state)  => state,
+      Err(error) => panic!("failed to wait for xr frame with error: {error}")
+    };
+
+    session.display_time = Some(state.predicted_display_time);
+
+    if let Err(error) = session.frame_stream.begin() {
+      panic!("failed to begin xr frame with error: {error}")
+    }
+
+    if !state.should_render {
+      return Some(XrFrame { state, views: Vec::new() });
+    }
+
+    let views = match session.session.locate_views(Xr::VIEW_TYPE, state.predicted_display_time, &session.space) {
+      Ok((_, views)) => views,
+      Err(error)     => panic!("failed to locate xr views with error: {error}")
+    };
+
+    for swapchain in &mut session.swapchains {
+      swapchain.acquire();
+    }
+
+    Some(XrFrame { state, views })
+  }
+
+  // the eyes as seen from the camera, which acts as the origin of the tracking space. left eye first
+  pub fn targets(&self, frame: &XrFrame, camera: &Camera) -> Vec<RenderTarget<'_>> {
+    let Some(session) = &self.session else { return Vec::new(); };
+    let origin = camera.transform.matrix();
+
+    let mut eyes = frame.views.iter().map(|view| {
+      let eye = origin * Xr::pose_matrix(&view.pose);
+
+      RenderView {
+        view: eye.inverse(),
+        projection: camera.fov_projection(view.fov.angle_left, view.fov.angle_right, view.fov.angle_up, view.fov.angle_down),
+        position: eye.w_axis.truncate()
+      }
+    });
+
+    session.swapchains.iter().filter_map(|swapchain| {
+      let views: Vec<RenderView> = eyes.by_ref().take(swapchain.layers as usize).collect();
+      let index = swapchain.acquired?;
+
+      Some(RenderTarget {
+        image: swapchain.images[index],
+        image_view: swapchain.views[index],
+        depth: &swapchain.depth,
+        format: swapchain.format,
+        extent: swapchain.extent,
+        views,
+        // the runtime expects images back in the layout they were drawn in
+        final_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+      })
+    }).collect()
+  }
+
+  // releases the eye images and submits them as a single projection layer. called after the frame was submitted
+  pub fn end_frame(&mut self, frame: XrFrame) {
+    let Some(session) = &mut self.session else { return; };
+
+    for swapchain in &mut session.swapchains {
+      swapchain.release();
+    }
+
+    // every eye is a layer of some swapchain, in the same order the views were located in
+    let layers = session.swapchains.iter().flat_map(|swapchain| (0..swapchain.layers).map(move |layer| (swapchain, layer)));
+
+    let views: Vec<xr::CompositionLayerProjectionView<xr::Vulkan>> = {
+      layers.zip(&frame.views).map(|((swapchain, layer), view)| {
+        let rect = xr::Rect2Di {
+          offset: xr::Offset2Di { x: 0, y: 0 },
+          extent: xr::Extent2Di { width: swapchain.extent.width as i32, height: swapchain.extent.height as i32 }
+        };
+
+        let sub_image = {
+          xr::SwapchainSubImage::new()
+            .swapchain(&swapchain.swapchain)
+            .image_array_index(layer)
+            .image_rect(rect)
+        };
+
+        xr::CompositionLayerProjectionView::new()
+          .pose(view.pose)
+          .fov(view.fov)
+          .sub_image(sub_image)
+      }).collect()
+    };
+
+    let layer = xr::CompositionLayerProjection::new().space(&session.space).views(&views);
+
+    let result = match views.is_empty() {
+      true  => session.frame_stream.end(frame.state.predicted_display_time, self.blend_mode, &[]),
+      false => session.frame_stream.end(frame.state.predicted_display_time, self.blend_mode, &[&layer])
+    };
+
+    if let Err(error) = result {
+      panic!("failed to end xr frame with error: {error}")
+    }
+  }
+
+  // ash and openxr describe the same function with different handle types
+  fn get_instance_proc_addr(entry: &ash::Entry) -> xr::sys::platform::VkGetInstanceProcAddr {
+    unsafe {
+      mem::transmute::<vk::PFN_vkGetInstanceProcAddr, xr::sys::platform::VkGetInstanceProcAddr>(entry.static_fn().get_instance_proc_addr)
+    }
+  }
+
+  pub fn pose_matrix(pose: &xr::Posef) -> Mat4 {
+    let orientation = Quat::from_xyzw(pose.orientation.x, pose.orientation.y, pose.orientation.z, pose.orientation.w);
+    let position = Vec3::new(pose.position.x, pose.position.y, pose.position.z);
+
+    Mat4::from_rotation_translation(orientation, position)
+  }
+}
@@ -0,0 +1,203 @@
+use crate::gvp_engine::{input::{ActionKind, ActionValue, Binding, Hand, HandJoint, HandPose, Input}, xr::Xr};
+
+use glam::{Mat4, Vec2};
+use openxr as xr;
+
+use std::collections::HashMap;
+
+enum XrAction {
+  Button(xr::Action<bool>),
+  Axis2d(xr::Action<xr::Vector2f>),
+  // poses are read by locating a space that follows the action
+  Pose(xr::Action<xr::Posef>, xr::Space)
+}
+
+// the runtime side of the engine's input actions. built from the actions that exist when the first frame starts since
+// a session only accepts action sets once
+pub struct XrActions {
+  sets: Vec<xr::ActionSet>,
+  // same order as the actions in Input
+  actions: Vec<XrAction>,
+  hand_trackers: Option<[xr::HandTracker; 2]>,
+  // follows the headset
+  view_space: xr::Space
+}
+
+impl XrActions {
+  pub fn new(
+    instance: &xr::Instance,
+    session: &xr::Session<xr::Vulkan>,
+    input: &Input,
+    hand_tracking: bool
+  ) -> Self {
+    let sets: Vec<xr::ActionSet> = input.sets().map(|(_, set)| {
+      match instance.create_action_set(&set.name, &set.localized_name, 0) {
+        Ok(action_set) => action_set,
+        Err(error)     => panic!("failed to create xr action set {} with error: {error}", set.name)
+      }
+    }).collect();
+
+    let mut suggested = HashMap::<String, Vec<(usize, xr::Path)>>::new();
+
+    let actions = input.actions().enumerate().map(|(index, (_, action))| {
+      let set = &sets[action.set.index()];
+      let (name, localized_name) = (action.name.as_str(), action.localized_name.as_str());
+
+      let created = match action.kind {
+        ActionKind::Button => set.create_action(name, localized_name, &[]).map(XrAction::Button),
+        ActionKind::Axis2d => set.create_action(name, localized_name, &[]).map(XrAction::Axis2d),
+        ActionKind::Pose   => set.create_action::<xr::Posef>(name, localized_name, &[]).and_then(|pose| {
+          pose.create_space(session, xr::Path::NULL, xr::Posef::IDENTITY).map(|space| XrAction::Pose(pose, space))
+        })
+      };
+
+      let created = match created {
+        Ok(created) => created,
+        Err(error)  => panic!("failed to create xr action {name} with error: {error}")
+      };
+
+      for binding in &action.bindings {
+        let Binding::Xr { profile, path } = binding else { continue; };
+
+        let path = match instance.string_to_path(path) {
+          Ok(path)   => path,
+          Err(error) => panic!("failed to convert xr binding path {path} with error: {error}")
+        };
+
+        suggested.entry(profile.clone()).or_default().push((index, path));
+      }
+
+      created
+    }).collect::<Vec<XrAction>>();
+
+    for (profile, bindings) in &suggested {
+      let bindings: Vec<xr::Binding> = bindings.iter().map(|(index, path)| match &actions[*index] {
+        XrAction::Button(action)  => xr::Binding::new(action, *path),
+        XrAction::Axis2d(action)  => xr::Binding::new(action, *path),
+        XrAction::Pose(action, _) => xr::Binding::new(action, *path)
+      }).collect();
+
+      let profile_path = match instance.string_to_path(profile) {
+        Ok(path)   => path,
+        Err(error) => panic!("failed to convert xr interaction profile {profile} with error: {error}")
+      };
+
+      if let Err(error) = instance.suggest_interaction_profile_bindings(profile_path, &bindings) {
+        panic!("failed to suggest xr bindings for {profile} with error: {error}")
+      }
+    }
+
+    if let Err(error) = session.attach_action_sets(&sets.iter().collect::<Vec<_>>()) {
+      panic!("failed to attach xr action sets with error: {error}")
+    }
+
+    let hand_trackers = match hand_tracking {
+      true  => Some(Hand::BOTH.map(|hand| XrActions::create_hand_tracker(session, hand))),
+      false => None
+    };
+
+    let view_space = match session.create_reference_space(xr::ReferenceSpaceType::VIEW, xr::Posef::IDENTITY) {
+      Ok(space)  => space,
+      Err(error) => panic!("failed to create xr view space with error: {error}")
+    };
+
+    XrActions {
+      sets,
+      actions,
+      hand_trackers,
+      view_space
+    }
+  }
+
+  // reads every action, tracked hand and the headset into the input. origin places the tracking space in the world
+  pub fn sync(
+    &self,
+    session: &xr::Session<xr::Vulkan>,
+    space: &xr::Space,
+    time: xr::Time,
+    origin: Mat4,
+    input: &mut Input
+  ) {
+    // sets made after the actions were attached are not known to the runtime
+    let active: Vec<xr::ActiveActionSet> = input.sets()
+      .filter(|(_, set)| set.active)
+      .filter_map(|(set, _)| self.sets.get(set.index()))
+      .map(xr::ActiveActionSet::new)
+      .collect();
+
+    if let Err(error) = session.sync_actions(&active) {
+      panic!("failed to sync xr actions with error: {error}")
+    }
+
+    let handles: Vec<_> = input.actions().map(|(action, _)| action).collect();
+
+    for (action, xr_action) in handles.into_iter().zip(&self.actions) {
+      let value = match xr_action {
+        XrAction::Button(action) => XrActions::state(action, session).map(ActionValue::Button),
+        XrAction::Axis2d(action) => XrActions::state(action, session).map(|value| ActionValue::Axis2d(Vec2::new(value.x, value.y))),
+        XrAction::Pose(action, action_space) => {
+          let active = match action.is_active(session, xr::Path::NULL) {
+            Ok(active) => active,
+            Err(error) => panic!("failed to get xr pose action state with error: {error}")
+          };
+
+          active.then(|| ActionValue::Pose(XrActions::locate(action_space, space, time).map(|pose| origin * pose)))
+        }
+      };
+
+      input.set_xr_value(action, value);
+    }
+
+    input.set_xr_head(XrActions::locate(&self.view_space, space, time).map(|head| origin * head));
+
+    let Some(trackers) = &self.hand_trackers else { return; };
+
+    for (hand, tracker) in Hand::BOTH.iter().zip(trackers) {
+      let locations = match space.locate_hand_joints(tracker, time) {
+        Ok(locations) => locations,
+        Err(error)    => panic!("failed to locate xr hand joints with error: {error}")
+      };
+
+      let pose = locations.map(|locations| HandPose {
+        joints: locations.map(|location| {
+          let joint = origin * Xr::pose_matrix(&location.pose);
+          let (_, orientation, position) = joint.to_scale_rotation_translation();
+
+          HandJoint { position, orientation, radius: location.radius }
+        })
+      });
+
+      input.set_hand(*hand, pose);
+    }
+  }
+
+  fn state<T: xr::ActionInput>(action: &xr::Action<T>, session: &xr::Session<xr::Vulkan>) -> Option<T> {
+    match action.state(session, xr::Path::NULL) {
+      Ok(state)  => state.is_active.then_some(state.current_state),
+      Err(error) => panic!("failed to get xr action state with error: {error}")
+    }
+  }
+
+  // None unless both the position and orientation are known
+  fn locate(located: &xr::Space, space: &xr::Space, time: xr::Time) -> Option<Mat4> {
+    let location = match located.locate(space, time) {
+      Ok(location) => location,
+      Err(error)   => panic!("failed to locate xr space with error: {error}")
+    };
+
+    let valid = xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+    location.location_flags.contains(valid).then(|| Xr::pose_matrix(&location.pose))
+  }
+
+  fn create_hand_tracker(session: &xr::Session<xr::Vulkan>, hand: Hand) -> xr::HandTracker {
+    let hand = match hand {
+      Hand::Left  => xr::Hand::LEFT,
+      Hand::Right => xr::Hand::RIGHT
+    };
+
+    match session.create_hand_tracker(hand) {
+      Ok(tracker) => tracker,
+      Err(error)  => panic!("failed to create xr hand tracker with error: {error}")
+    }
+  }
+}
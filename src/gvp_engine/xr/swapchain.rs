@@ -0,0 +1,118 @@
+use crate::gvp_engine::{allocator::{Allocator, Image}, renderer::Renderer};
+
+use ash::vk::{self, Handle};
+use openxr as xr;
+
+// a runtime owned swapchain for one eye, or an array swapchain with a layer per eye for multiview, plus the views and
+// depth buffer the renderer needs to draw into it
+pub struct XrSwapchain {
+  pub swapchain: xr::Swapchain<xr::Vulkan>,
+  pub images: Vec<vk::Image>,
+  pub views: Vec<vk::ImageView>,
+  pub depth: Image,
+  pub format: vk::Format,
+  pub extent: vk::Extent2D,
+  pub layers: u32,
+  // index of the image held for the frame being drawn
+  pub acquired: Option<usize>
+}
+
+impl XrSwapchain {
+  pub fn new(
+    session: &xr::Session<xr::Vulkan>,
+    device: &ash::Device,
+    allocator: &Allocator,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    layers: u32
+  ) -> Self {
+    let create_info = xr::SwapchainCreateInfo::<xr::Vulkan> {
+      create_flags: xr::SwapchainCreateFlags::EMPTY,
+      usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT | xr::SwapchainUsageFlags::SAMPLED,
+      format: format.as_raw() as u32,
+      sample_count: 1,
+      width: extent.width,
+      height: extent.height,
+      face_count: 1,
+      array_size: layers,
+      mip_count: 1
+    };
+
+    let swapchain = match session.create_swapchain(&create_info) {
+      Ok(swapchain) => swapchain,
+      Err(error)    => panic!("failed to create xr swapchain with error: {error}")
+    };
+
+    let images: Vec<vk::Image> = match swapchain.enumerate_images() {
+      Ok(images) => images.into_iter().map(vk::Image::from_raw).collect(),
+      Err(error) => panic!("failed to get xr swapchain images with error: {error}")
+    };
+
+    let view_type = match layers {
+      1 => vk::ImageViewType::TYPE_2D,
+      _ => vk::ImageViewType::TYPE_2D_ARRAY
+    };
+
+    let views = images.iter().map(|image| {
+      let create_info = {
+        vk::ImageViewCreateInfo::default()
+          .image(*image)
+          .view_type(view_type)
+          .format(format)
+          .subresource_range(Allocator::color_range(0, 1, 0, layers))
+      };
+
+      match unsafe { device.create_image_view(&create_info, None) } {
+        Ok(view)   => view,
+        Err(error) => panic!("failed to create xr swapchain image view with error: {error}")
+      }
+    }).collect();
+
+    XrSwapchain {
+      swapchain,
+      images,
+      views,
+      depth: Renderer::create_depth_image(device, allocator, &extent, layers),
+      format,
+      extent,
+      layers,
+      acquired: None
+    }
+  }
+
+  // the swapchain and its images belong to the runtime, only the views and depth buffer are ours to destroy
+  pub fn clean(&self, device: &ash::Device, allocator: &Allocator) {
+    unsafe {
+      for view in &self.views {
+        device.destroy_image_view(*view, None);
+      }
+    };
+
+    allocator.destroy_image(device, &self.depth);
+  }
+
+  // blocks until the runtime is done reading the next image
+  pub fn acquire(&mut self) -> usize {
+    let index = match self.swapchain.acquire_image() {
+      Ok(index)  => index as usize,
+      Err(error) => panic!("failed to acquire xr swapchain image with error: {error}")
+    };
+
+    if let Err(error) = self.swapchain.wait_image(xr::Duration::INFINITE) {
+      panic!("failed to wait for xr swapchain image with error: {error}")
+    }
+
+    self.acquired = Some(index);
+
+    index
+  }
+
+  // hands the image back once the commands drawing it have been submitted
+  pub fn release(&mut self) {
+    if self.acquired.take().is_none() { return; }
+
+    if let Err(error) = self.swapchain.release_image() {
+      panic!("failed to release xr swapchain image with error: {error}")
+    }
+  }
+}
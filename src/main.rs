@@ -1,11 +1,215 @@
+mod apps;
 mod gvp_engine;
 
-use gvp_engine::engine::GVPEngine;
+use apps::{DocumentsApp, LocalProvider, MusicApp};
+use gvp_engine::{
+  audio::{Audio, AudioEvent, AudioMode, WavOutput},
+  engine::GVPEngine,
+  gesture::{GestureSource, GestureThresholds},
+  material::Material,
+  plugin::PluginEvent,
+  primitives::PrimitiveKind,
+  renderer::RenderMode,
+  scene::{BodyKind, RigidBody},
+  surface::{SurfaceApp, TestPattern},
+  transform::Transform
+};
+
+use glam::{Quat, Vec3};
+
+use std::{env, fs, path::{Path, PathBuf}, time::Duration};
+
+const WORKSPACE: &str = "workspace.json";
+const APP_STATES: &str = "apps.json";
+const PLUGINS: &str = "plugins";
+const MUSIC: &str = "music";
+const FONTS: &str = "fonts";
+const AUDIO_CAPTURE_FILE: &str = "capture.wav";
+const GESTURE_RECORDING_FILE: &str = "gestures.json";
+// shows the test pattern surface in a panel of its own
+const SURFACE_TEST: &str = "--surface-test";
+// path traces the window instead of rasterizing it
+const PATH_TRACE: &str = "--path-trace";
+// plays every sound straight into both ears instead of from where it is
+const HEADPHONES: &str = "--headphones";
+// records what would be heard to a wav file instead of playing it
+const AUDIO_CAPTURE: &str = "--audio-capture";
+// starts with the performance overlay shown, F3 toggles it either way
+const PERFORMANCE: &str = "--performance";
+// keeps every panel fully opaque wherever the user looks
+const NO_GAZE_FADE: &str = "--no-gaze-fade";
+// records the pointer's pose stream for the session, to be checked and added to the gesture fixtures
+const RECORD_GESTURES: &str = "--record-gestures";
+const FLAGS: [&str; 7] = [SURFACE_TEST, PATH_TRACE, HEADPHONES, AUDIO_CAPTURE, PERFORMANCE, NO_GAZE_FADE, RECORD_GESTURES];
 
 fn main() {
-  let engine = GVPEngine::init();
+  let mut engine = GVPEngine::init();
+
+  // the workspace from the last session is restored if there is one, otherwise a fresh scene is built. one that cannot
+  // be read, like a corrupt file or one from a newer build, gets the fresh scene too
+  let workspace = Path::new(WORKSPACE);
+
+  let restored = workspace.exists() && match engine.load_scene(workspace) {
+    Ok(())     => true,
+    Err(error) => {
+      eprintln!("failed to load workspace {WORKSPACE} with error: {error}");
+      false
+    }
+  };
+
+  if !restored {
+    engine.spawn_body(
+      PrimitiveKind::Plane { subdivisions: 8 },
+      Transform::new(Vec3::ZERO, Quat::IDENTITY, Vec3::splat(20.0)),
+      Material::color([0.35, 0.5, 0.3, 1.0], 0.0, 0.9),
+      RigidBody { kind: BodyKind::Static, ..RigidBody::default() }
+    );
+
+    // dropped in from a little above the ground, grab them with the right mouse button or the squeeze of a controller
+    engine.spawn_body(
+      PrimitiveKind::CUBE,
+      Transform::from_translation(Vec3::new(-1.5, 1.5, -1.0)),
+      Material::color([0.8, 0.3, 0.2, 1.0], 0.0, 0.5),
+      RigidBody::default()
+    );
+
+    engine.spawn_body(
+      PrimitiveKind::SPHERE,
+      Transform::from_translation(Vec3::new(0.0, 2.0, -1.5)),
+      Material::color([0.9, 0.9, 0.9, 1.0], 1.0, 0.2),
+      RigidBody::default()
+    );
+
+    engine.spawn_body(
+      PrimitiveKind::TORUS,
+      Transform::new(Vec3::new(1.5, 1.5, -1.0), Quat::from_rotation_x(1.0), Vec3::ONE),
+      Material::color([0.2, 0.4, 0.8, 1.0], 0.0, 0.3),
+      RigidBody::default()
+    );
+
+    engine.spawn_body(
+      PrimitiveKind::CAPSULE,
+      Transform::new(Vec3::new(0.8, 2.5, -2.0), Quat::from_rotation_z(0.4), Vec3::new(0.5, 0.5, 0.5)),
+      Material::color([0.9, 0.75, 0.2, 1.0], 0.0, 0.4),
+      RigidBody::default()
+    );
+
+    engine.spawn_body(
+      PrimitiveKind::ICO_SPHERE,
+      Transform::new(Vec3::new(-0.8, 2.5, -2.0), Quat::IDENTITY, Vec3::splat(0.6)),
+      Material::color([0.6, 0.3, 0.8, 1.0], 0.0, 0.6),
+      RigidBody::default()
+    );
+  }
+
+  // apps start from scratch if their states cannot be read
+  let app_states = Path::new(APP_STATES);
+
+  if app_states.exists() {
+    if let Err(error) = engine.load_app_states(app_states) {
+      eprintln!("failed to load app states {APP_STATES} with error: {error}");
+    }
+  }
+
+  // in name order, so the primary font can be picked by naming it first
+  let mut fonts: Vec<PathBuf> = fs::read_dir(FONTS).into_iter().flatten().flatten().map(|entry| entry.path()).collect();
+  fonts.retain(|path| matches!(path.extension().and_then(|extension| extension.to_str()), Some("ttf" | "otf")));
+  fonts.sort();
+
+  for path in fonts {
+    engine.text_mut().load_font(&path);
+  }
+
+  engine.launch_app(Box::new(DocumentsApp::default()));
+  engine.launch_app(Box::new(MusicApp::new(Box::new(LocalProvider::new(Path::new(MUSIC))))));
+  engine.set_plugin_directory(Path::new(PLUGINS));
+
+  if env::args().any(|arg| arg == SURFACE_TEST) {
+    engine.launch_app(Box::new(SurfaceApp::new("Surface Test", Box::new(TestPattern::new()))));
+  }
+
+  if env::args().any(|arg| arg == PATH_TRACE) {
+    engine.set_render_mode(RenderMode::PathTraced);
+  }
+
+  if env::args().any(|arg| arg == HEADPHONES) {
+    engine.audio_mut().settings.mode = AudioMode::Headphones;
+  }
+
+  if env::args().any(|arg| arg == AUDIO_CAPTURE) {
+    match WavOutput::create(Path::new(AUDIO_CAPTURE_FILE), Audio::SAMPLE_RATE) {
+      Ok(output) => engine.audio_mut().set_output(Box::new(output)),
+      Err(error) => eprintln!("failed to create {AUDIO_CAPTURE_FILE} with error: {error}")
+    }
+  }
+
+  if env::args().any(|arg| arg == PERFORMANCE) {
+    engine.set_performance_overlay(true);
+  }
+
+  if env::args().any(|arg| arg == NO_GAZE_FADE) {
+    engine.gaze_fade_mut().enabled = false;
+  }
+
+  if env::args().any(|arg| arg == RECORD_GESTURES) {
+    engine.gesture_recognizer_mut().start_recording(GestureSource::Pointer);
+  }
+
+  // paths passed on the command line are loaded as models, except hdr panoramas and ktx2 cubemaps which replace the
+  // environment and sounds which are played from speakers lined up in front of the user
+  let mut speakers = 0;
+
+  for path in env::args().skip(1).filter(|arg| !FLAGS.contains(&arg.as_str())) {
+    let path = Path::new(&path);
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+      Some("hdr" | "ktx2")         => if let Err(error) = engine.set_environment(path, Duration::ZERO) {
+        eprintln!("failed to load environment {} with error: {error}", path.display());
+      },
+      Some("wav" | "ogg" | "flac") => {
+        let position = Vec3::new(speakers as f32 * 1.5, 1.0, -2.5);
+        engine.spawn_speaker(path, Transform::new(position, Quat::IDENTITY, Vec3::new(0.3, 0.5, 0.3)));
+        speakers += 1;
+      },
+      _                            => if let Err(error) = engine.load_model(path) {
+        eprintln!("failed to load model {} with error: {error}", path.display());
+      }
+    }
+  }
 
   'main_loop: loop {
     if engine.poll_events() { break 'main_loop; };
+
+    // plugins dropped into the directory open right away
+    for event in engine.poll_plugins() {
+      match event {
+        PluginEvent::Loaded(plugin)        => { engine.launch_plugin(plugin); }
+        PluginEvent::Rejected(path, error) => eprintln!("failed to load plugin {} with error: {error}", path.display()),
+        _                                  => {}
+      }
+    }
+
+    engine.update_physics();
+
+    for event in engine.update_audio() {
+      if let AudioEvent::Failed { path, error, .. } = event {
+        eprintln!("failed to play {} with error: {error}", path.display());
+      }
+    }
+
+    engine.update_apps();
+    engine.render();
+  }
+
+  engine.save_app_states(app_states);
+
+  // expects whatever the default thresholds find in it, which is worth checking before it becomes a fixture
+  if let Some(mut recording) = engine.gesture_recognizer_mut().stop_recording() {
+    recording.expected = recording.replay(GestureThresholds::default());
+    recording.save(Path::new(GESTURE_RECORDING_FILE));
+  }
+
+  if let Err(error) = engine.save_scene(workspace) {
+    eprintln!("failed to save workspace {WORKSPACE} with error: {error}");
   }
 }